@@ -1,5 +1,6 @@
 use criterion::{black_box, criterion_group, criterion_main, Criterion};
-use rust_audio_processor::{AudioProcessor, AudioConfig, StereoDelayConfig, DistortionConfig};
+use rust_audio_processor::{AudioProcessor, AudioProcessorTrait, AudioConfig, StereoDelay};
+use rust_audio_processor::config::{StereoDelayConfig, DistortionConfig};
 
 fn benchmark_audio_processing(c: &mut Criterion) {
     let mut group = c.benchmark_group("Audio Processing");
@@ -16,6 +17,7 @@ fn benchmark_audio_processing(c: &mut Criterion) {
             ping_pong: true,
             stereo_width: 0.5,
             cross_feedback: 0.2,
+            ..Default::default()
         },
         distortion: DistortionConfig {
             enabled: true,
@@ -23,6 +25,7 @@ fn benchmark_audio_processing(c: &mut Criterion) {
             drive: 0.3,
             mix: 0.7,
             feedback_intensity: 0.5,
+            ..Default::default()
         },
         ..Default::default()
     };
@@ -66,7 +69,7 @@ fn benchmark_parameter_setting(c: &mut Criterion) {
     let mut group = c.benchmark_group("Parameter Setting");
     
     let config = AudioConfig::default();
-    let processor = AudioProcessor::with_config(config).unwrap();
+    let mut processor = AudioProcessor::with_config(config).unwrap();
     
     group.bench_function("set_feedback", |b| {
         b.iter(|| {
@@ -94,7 +97,7 @@ fn benchmark_stereo_delay_creation(c: &mut Criterion) {
     
     group.bench_function("create_stereo_delay", |b| {
         b.iter(|| {
-            use rust_audio_processor::{StereoDelay, DistortionType};
+            use rust_audio_processor::DistortionType;
             black_box(StereoDelay::new(
                 44100,
                 0.3,
@@ -115,10 +118,36 @@ fn benchmark_stereo_delay_creation(c: &mut Criterion) {
     group.finish();
 }
 
+fn benchmark_mono_vs_stereo_delay(c: &mut Criterion) {
+    let mut group = c.benchmark_group("Mono vs Stereo Delay");
+    
+    // 100ms of guitar-level sine, fed to both inputs
+    let test_audio: Vec<f32> = (0..4410)
+        .map(|i| 0.3 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin())
+        .collect();
+    
+    for (name, mono_mode) in [("stereo_100ms", false), ("mono_100ms", true)] {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.mono_mode = mono_mode;
+        let mut delay = StereoDelay::from_config(&config);
+        
+        group.bench_function(name, |b| {
+            b.iter(|| {
+                for &sample in &test_audio {
+                    black_box(delay.process_sample(black_box(sample), black_box(sample)));
+                }
+            });
+        });
+    }
+    
+    group.finish();
+}
+
 criterion_group!(
     benches,
     benchmark_audio_processing,
     benchmark_parameter_setting,
-    benchmark_stereo_delay_creation
+    benchmark_stereo_delay_creation,
+    benchmark_mono_vs_stereo_delay
 );
 criterion_main!(benches);
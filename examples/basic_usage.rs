@@ -1,4 +1,4 @@
-use rust_audio_processor::{AudioProcessor, AudioConfig, config::{StereoDelayConfig, DistortionConfig}};
+use rust_audio_processor::{AudioProcessor, AudioProcessorTrait, AudioConfig, config::{StereoDelayConfig, DistortionConfig}};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🎸 Rust Audio Processor - Basic Usage Example");
@@ -16,6 +16,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             ping_pong: true,
             stereo_width: 0.7,
             cross_feedback: 0.3,
+            ..Default::default()
         },
         distortion: DistortionConfig {
             enabled: true,
@@ -23,12 +24,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             drive: 0.4,
             mix: 0.6,
             feedback_intensity: 0.8,
+            ..Default::default()
         },
         ..Default::default()
     };
     
     // Create audio processor with custom configuration
-    let processor = AudioProcessor::with_config(config)?;
+    let mut processor = AudioProcessor::with_config(config)?;
     
     println!("✅ Audio processor created successfully!");
     println!("📊 Configuration:");
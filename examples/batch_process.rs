@@ -0,0 +1,32 @@
+use rust_audio_processor::{batch, AudioConfig};
+use std::env;
+use std::path::PathBuf;
+
+fn main() -> Result<(), Box<dyn std::error::Error>> {
+    println!("🎸 Batch Processing - Apply the stereo delay to a folder of stems");
+    println!("=================================================================\n");
+    
+    let args: Vec<String> = env::args().collect();
+    let input_dir = PathBuf::from(args.get(1).map(String::as_str).unwrap_or("stems"));
+    let output_dir = args.get(2).map(PathBuf::from).unwrap_or_else(|| input_dir.join("processed"));
+    
+    // Use the same settings for every stem
+    let mut config = AudioConfig::load_or_default("pi_config.json");
+    config.stereo_delay.set_bpm(120.0);
+    config.stereo_delay.wet_mix = 0.4;
+    
+    println!("📁 Input:  {}", input_dir.display());
+    println!("📁 Output: {}\n", output_dir.display());
+    
+    let summary = batch::process_directory(&input_dir, &output_dir, &config)?;
+    
+    for path in &summary.succeeded {
+        println!("  ✅ {}", path.display());
+    }
+    for (path, reason) in &summary.failed {
+        println!("  ❌ {}: {}", path.display(), reason);
+    }
+    
+    println!("\n📊 {}", summary.get_info());
+    Ok(())
+}
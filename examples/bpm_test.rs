@@ -1,4 +1,4 @@
-use rust_audio_processor::{AudioProcessor, AudioConfig};
+use rust_audio_processor::{AudioProcessor, AudioProcessorTrait, AudioConfig};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🎸 BPM Test - Demonstrating BPM-based delay timing");
@@ -1,4 +1,4 @@
-use rust_audio_processor::{AudioProcessor, AudioConfig, config::{StereoDelayConfig, DistortionConfig}};
+use rust_audio_processor::{AudioProcessor, AudioProcessorTrait, AudioConfig, config::{StereoDelayConfig, DistortionConfig}};
 
 fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("🎸 Rust Audio Processor - Distortion Demo");
@@ -18,6 +18,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             ping_pong: true,
             stereo_width: 0.5,
             cross_feedback: 0.2,
+            ..Default::default()
         },
         distortion: DistortionConfig {
             enabled: true,
@@ -25,11 +26,13 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
             drive: 0.5,
             mix: 0.7,
             feedback_intensity: 0.3,
+            ..Default::default()
         },
+        ..Default::default()
     };
     
     // Create audio processor
-    let processor = AudioProcessor::with_config(config)?;
+    let mut processor = AudioProcessor::with_config(config)?;
     
     println!("📋 Initial Configuration:");
     println!("  Sample rate: {} Hz", processor.get_config().sample_rate);
@@ -5,7 +5,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
     println!("===============================\n");
     
     let host = cpal::default_host();
-    println!("🎵 Using host: {}", host.name());
+    println!("🎵 Using host: {}", host.id().name());
     
     // List all input devices
     println!("\n📋 Input Devices:");
@@ -1,22 +1,118 @@
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
 use std::sync::{Arc, Mutex};
 use std::thread;
 use std::time::Duration;
 use parking_lot::RwLock;
-use crate::delay::BaseDelay;
+use crate::backend::AudioBackend;
 use crate::config::AudioConfig;
 use crate::delay::StereoDelay;
-use crate::distortion::DistortionType;
 use crate::error::AudioProcessorError;
+use crate::idle::{IdleDetector, IdleTransition};
+use crate::latency::BufferFrames;
+use crate::health::Heartbeat;
+use crate::logging::RateLimitedLog;
+use crate::processor_core::ProcessorCore;
+use crate::test_signal::TestToneParams;
+use crate::realtime::apply_audio_thread_hints;
+use crate::devices::{
+    check_output_channel_offset, frame_to_stereo, list_alsa_pcm_devices, negotiate_channels, negotiate_period_and_buffer, negotiate_sample_format,
+    stereo_to_frame, ChannelLayout, DeviceList, SampleFormat, TpdfDither,
+};
+use crate::AudioProcessorTrait;
+use log::{error, info, warn};
 #[cfg(target_os = "linux")]
 use alsa::{pcm::{PCM, Format, HwParams}, Direction, ValueOr};
 
+/// PCM opened when no input or output device is configured
+#[cfg(target_os = "linux")]
+const DEFAULT_ALSA_DEVICE: &str = "hw:CARD=USB,DEV=0";
+
+/// Settings the devices were actually opened with, 0 until the stream has started
+#[cfg(target_os = "linux")]
+#[derive(Default)]
+struct NegotiatedFormat {
+    input_channels: AtomicUsize,
+    output_channels: AtomicUsize,
+    period_size: AtomicUsize,
+    buffer_size: AtomicUsize,
+    input_format: RwLock<Option<SampleFormat>>,
+    output_format: RwLock<Option<SampleFormat>>,
+    latency: BufferFrames,
+}
+
+/// Interleaved I/O in a device's negotiated sample format, converted to and from f32
+#[cfg(target_os = "linux")]
+enum PcmIo<'a> {
+    /// S32 and S24, which ALSA stores in 32-bit containers
+    I32 { io: alsa::pcm::IO<'a, i32>, buffer: Vec<i32>, format: SampleFormat },
+    I16 { io: alsa::pcm::IO<'a, i16>, buffer: Vec<i16> },
+}
+
+#[cfg(target_os = "linux")]
+impl<'a> PcmIo<'a> {
+    /// I/O for a PCM configured with `format`, buffering `samples` interleaved samples
+    fn new(pcm: &'a PCM, format: SampleFormat, samples: usize) -> Result<Self, AudioProcessorError> {
+        let unavailable = |_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable);
+        Ok(match format {
+            SampleFormat::S32 => PcmIo::I32 { io: pcm.io_i32().map_err(unavailable)?, buffer: vec![0; samples], format },
+            SampleFormat::S24 => {
+                // SAFETY: the PCM was configured as S24, whose samples are 32-bit containers
+                let io = unsafe { pcm.io_unchecked::<i32>() };
+                PcmIo::I32 { io, buffer: vec![0; samples], format }
+            }
+            SampleFormat::S16 => PcmIo::I16 { io: pcm.io_i16().map_err(unavailable)?, buffer: vec![0; samples] },
+        })
+    }
+    
+    /// Read one period into `samples` as -1.0..1.0
+    fn read(&mut self, samples: &mut [f32]) -> alsa::Result<usize> {
+        match self {
+            PcmIo::I32 { io, buffer, format } => {
+                let frames = io.readi(buffer)?;
+                for (sample, value) in samples.iter_mut().zip(buffer.iter()) {
+                    *sample = format.to_f32(*value);
+                }
+                Ok(frames)
+            }
+            PcmIo::I16 { io, buffer } => {
+                let frames = io.readi(buffer)?;
+                for (sample, value) in samples.iter_mut().zip(buffer.iter()) {
+                    *sample = SampleFormat::S16.to_f32(*value as i32);
+                }
+                Ok(frames)
+            }
+        }
+    }
+    
+    /// Write up to one period of -1.0..1.0 samples, dithered if a dither is given
+    fn write(&mut self, samples: &[f32], mut dither: Option<&mut TpdfDither>) -> alsa::Result<usize> {
+        let mut convert = |format: SampleFormat, sample: f32| match dither.as_deref_mut() {
+            Some(dither) => dither.quantize(format, sample),
+            None => format.from_f32(sample),
+        };
+        match self {
+            PcmIo::I32 { io, buffer, format } => {
+                for (value, sample) in buffer.iter_mut().zip(samples) {
+                    *value = convert(*format, *sample);
+                }
+                io.writei(&buffer[..samples.len().min(buffer.len())])
+            }
+            PcmIo::I16 { io, buffer } => {
+                for (value, sample) in buffer.iter_mut().zip(samples) {
+                    *value = convert(SampleFormat::S16, *sample) as i16;
+                }
+                io.writei(&buffer[..samples.len().min(buffer.len())])
+            }
+        }
+    }
+}
+
 #[cfg(target_os = "linux")]
 /// ALSA-based audio processor for direct hardware access
 pub struct AlsaAudioProcessor {
-    config: AudioConfig,
-    stereo_delay: Arc<Mutex<StereoDelay>>,
-    is_running: Arc<RwLock<bool>>,
-    audio_thread: Option<thread::JoinHandle<()>>,
+    core: ProcessorCore,
+    negotiated: Arc<NegotiatedFormat>,
+    idle: Arc<AtomicBool>,
 }
 
 #[cfg(target_os = "linux")]
@@ -29,324 +125,432 @@ impl AlsaAudioProcessor {
     
     /// Create a new ALSA audio processor with custom configuration
     pub fn with_config(config: AudioConfig) -> Result<Self, AudioProcessorError> {
-        // Validate configuration
-        config.validate()?;
-        
-        // Create stereo delay effect
-        let distortion_type = DistortionType::from(config.distortion.distortion_type.as_str());
-        let stereo_delay = StereoDelay::new(
-            config.sample_rate,
-            config.stereo_delay.left_delay,
-            config.stereo_delay.right_delay,
-            config.stereo_delay.feedback,
-            config.stereo_delay.wet_mix,
-            config.stereo_delay.ping_pong,
-            config.stereo_delay.stereo_width,
-            config.stereo_delay.cross_feedback,
-            config.distortion.enabled,
-            distortion_type,
-            config.distortion.drive,
-            config.distortion.mix,
-        );
-        
         Ok(Self {
-            config,
-            stereo_delay: Arc::new(Mutex::new(stereo_delay)),
-            is_running: Arc::new(RwLock::new(false)),
-            audio_thread: None,
+            core: ProcessorCore::new(AudioBackend::Alsa, config)?,
+            negotiated: Arc::new(NegotiatedFormat::default()),
+            idle: Arc::new(AtomicBool::new(false)),
         })
     }
     
-    /// Start ALSA audio processing
-    pub fn start_audio(&mut self) -> Result<(), AudioProcessorError> {
-        if *self.is_running.read() {
-            return Err(AudioProcessorError::Processing("Audio already running".to_string()));
-        }
-        
-        let config = self.config.clone();
-        let stereo_delay = Arc::clone(&self.stereo_delay);
-        let is_running = Arc::clone(&self.is_running);
-        
-        let thread_handle = thread::spawn(move || {
-            if let Err(e) = Self::run_alsa_audio_stream(config, stereo_delay, is_running) {
-                eprintln!("ALSA audio stream error: {}", e);
-            }
-        });
-        
-        self.audio_thread = Some(thread_handle);
-        *self.is_running.write() = true;
-        
-        Ok(())
-    }
-    
-    /// Stop ALSA audio processing
-    pub fn stop_audio(&mut self) -> Result<(), AudioProcessorError> {
-        if !*self.is_running.read() {
-            return Err(AudioProcessorError::Processing("Audio not running".to_string()));
-        }
-        
-        *self.is_running.write() = false;
-        
-        if let Some(thread_handle) = self.audio_thread.take() {
-            thread_handle.join().map_err(|_| {
-                AudioProcessorError::Threading("Failed to join audio thread".to_string())
+    /// Check that the configured input and output PCMs can be opened
+    ///
+    /// The devices are opened without blocking and closed again straight away.
+    pub fn probe_devices(config: &AudioConfig) -> Result<(), AudioProcessorError> {
+        let input_device = config.input_device.as_deref().unwrap_or(DEFAULT_ALSA_DEVICE);
+        let output_device = config.output_device.as_deref().unwrap_or(DEFAULT_ALSA_DEVICE);
+        
+        for (device, direction) in [(input_device, Direction::Capture), (output_device, Direction::Playback)] {
+            PCM::new(device, direction, true).map_err(|e| {
+                AudioProcessorError::Processing(format!("cannot open ALSA device {}: {}", device, e))
             })?;
         }
         
-        // Reset delay buffers to clear any lingering feedback
-        self.reset_delay()?;
-        
         Ok(())
     }
     
-    /// Get overall system status
-    pub fn get_status(&self) -> Result<std::collections::HashMap<String, String>, AudioProcessorError> {
-        let mut status = std::collections::HashMap::new();
-        
-        // Stereo delay parameters (in seconds, not milliseconds)
-        status.insert("left_delay".to_string(), format!("{:.3}", self.config.stereo_delay.left_delay));
-        status.insert("right_delay".to_string(), format!("{:.3}", self.config.stereo_delay.right_delay));
-        status.insert("feedback".to_string(), format!("{:.3}", self.config.stereo_delay.feedback));
-        status.insert("wet_mix".to_string(), format!("{:.3}", self.config.stereo_delay.wet_mix));
-        status.insert("ping_pong".to_string(), self.config.stereo_delay.ping_pong.to_string());
-        status.insert("stereo_width".to_string(), format!("{:.3}", self.config.stereo_delay.stereo_width));
-        status.insert("cross_feedback".to_string(), format!("{:.3}", self.config.stereo_delay.cross_feedback));
-        
-        // Distortion parameters
-        status.insert("distortion_enabled".to_string(), self.config.distortion.enabled.to_string());
-        status.insert("distortion_type".to_string(), self.config.distortion.distortion_type.clone());
-        status.insert("distortion_drive".to_string(), format!("{:.3}", self.config.distortion.drive));
-        status.insert("distortion_mix".to_string(), format!("{:.3}", self.config.distortion.mix));
-        status.insert("distortion_feedback_intensity".to_string(), format!("{:.3}", self.config.distortion.feedback_intensity));
-        
-        // System parameters
-        status.insert("sample_rate".to_string(), self.config.sample_rate.to_string());
-        status.insert("buffer_size".to_string(), self.config.buffer_size.to_string());
-        status.insert("is_running".to_string(), self.is_running.read().to_string());
-        
-        // Add BPM information if available
-        if let Some(bpm) = self.config.stereo_delay.bpm {
-            status.insert("bpm".to_string(), format!("{:.0}", bpm));
-        }
-        
-        Ok(status)
-    }
-    
     /// Run the ALSA audio stream with direct hardware access
     fn run_alsa_audio_stream(
         config: AudioConfig,
         stereo_delay: Arc<Mutex<StereoDelay>>,
         is_running: Arc<RwLock<bool>>,
+        heartbeat: Arc<Heartbeat>,
+        negotiated: Arc<NegotiatedFormat>,
+        idle: Arc<AtomicBool>,
     ) -> Result<(), AudioProcessorError> {
-        println!("🎵 Initializing ALSA audio streams with direct hardware access...");
+        info!("🎵 Initializing ALSA audio streams with direct hardware access...");
         
         // Open input PCM device
-        let input_device = config.input_device.as_deref().unwrap_or("hw:CARD=USB,DEV=0");
-        println!("🎤 Opening input device: {}", input_device);
+        let input_device = config.input_device.as_deref().unwrap_or(DEFAULT_ALSA_DEVICE);
+        info!("🎤 Opening input device: {}", input_device);
         
         let input_pcm = PCM::new(input_device, Direction::Capture, false)
             .map_err(|e| {
-                println!("❌ Failed to open input device {}: {}", input_device, e);
+                error!("❌ Failed to open input device {}: {}", input_device, e);
                 AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable)
             })?;
         
-        println!("✅ Successfully opened input device: {}", input_device);
+        info!("✅ Successfully opened input device: {}", input_device);
         
         // Open output PCM device
-        let output_device = config.output_device.as_deref().unwrap_or("hw:CARD=USB,DEV=0");
-        println!("🔊 Opening output device: {}", output_device);
+        let output_device = config.output_device.as_deref().unwrap_or(DEFAULT_ALSA_DEVICE);
+        info!("🔊 Opening output device: {}", output_device);
         
         let output_pcm = PCM::new(output_device, Direction::Playback, false)
             .map_err(|e| {
-                println!("❌ Failed to open output device {}: {}", output_device, e);
+                error!("❌ Failed to open output device {}: {}", output_device, e);
                 AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable)
             })?;
         
-        println!("✅ Successfully opened output device: {}", output_device);
+        info!("✅ Successfully opened output device: {}", output_device);
         
-        // Configure input PCM using the correct ALSA API
-        let input_hwp = HwParams::any(&input_pcm).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        input_hwp.set_channels(2).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        input_hwp.set_rate(config.sample_rate, ValueOr::Nearest).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        input_hwp.set_format(Format::s32()).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        input_hwp.set_access(alsa::pcm::Access::RWInterleaved).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        input_pcm.hw_params(&input_hwp).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        input_pcm.prepare().map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
+        // Configure input PCM
+        let (input_channels, input_format, period_size, device_buffer_size) = Self::configure_pcm(&input_pcm, &config)?;
         
-        println!("🎤 Input configured: {} Hz, 2 channels, S32", config.sample_rate);
+        info!(
+            "🎤 Input configured: {} Hz, {} channels, {}, period {} frames, buffer {} frames",
+            config.sample_rate, input_channels, input_format, period_size, device_buffer_size
+        );
         
-        // Configure output PCM using the correct ALSA API
-        let output_hwp = HwParams::any(&output_pcm).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        output_hwp.set_channels(2).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        output_hwp.set_rate(config.sample_rate, ValueOr::Nearest).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        output_hwp.set_format(Format::s32()).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        output_hwp.set_access(alsa::pcm::Access::RWInterleaved).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        output_pcm.hw_params(&output_hwp).map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        output_pcm.prepare().map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
+        // Configure output PCM
+        let (output_channels, output_format, output_period_size, output_buffer_size) = Self::configure_pcm(&output_pcm, &config)?;
         
-        println!("🔊 Output configured: {} Hz, 2 channels, S32", config.sample_rate);
+        info!(
+            "🔊 Output configured: {} Hz, {} channels, {}, period {} frames, buffer {} frames",
+            config.sample_rate, output_channels, output_format, output_period_size, output_buffer_size
+        );
         
-        // Audio processing loop
-        let buffer_size = config.buffer_size;
-        let mut input_buffer = vec![0i32; buffer_size * 2]; // Stereo
-        let mut output_buffer = vec![0i32; buffer_size * 2]; // Stereo
+        let input_channels = input_channels as usize;
+        let output_channels = output_channels as usize;
+        let output_channel_offset = config.output_channel_offset;
+        check_output_channel_offset(output_channel_offset, output_channels)?;
+        negotiated.input_channels.store(input_channels, Ordering::Relaxed);
+        negotiated.output_channels.store(output_channels, Ordering::Relaxed);
+        negotiated.period_size.store(period_size, Ordering::Relaxed);
+        negotiated.buffer_size.store(device_buffer_size, Ordering::Relaxed);
+        *negotiated.input_format.write() = Some(input_format);
+        *negotiated.output_format.write() = Some(output_format);
+        
+        // A read waits for a full input period, then the block is played after
+        // everything already queued in the output device buffer
+        negotiated.latency.input.store(period_size, Ordering::Relaxed);
+        negotiated.latency.processing.store(0, Ordering::Relaxed);
+        negotiated.latency.output.store(output_buffer_size, Ordering::Relaxed);
+        negotiated.latency.sample_rate.store(config.sample_rate, Ordering::Relaxed);
+        
+        // Audio processing loop, one input period at a time
+        let buffer_size = if period_size > 0 { period_size } else { config.buffer_size };
+        let mut input_buffer = vec![0.0f32; buffer_size * input_channels];
+        let mut output_buffer = vec![0.0f32; buffer_size * output_channels];
+        let mut output_frame = vec![0.0f32; output_channels];
+        
+        // Planar devices are reordered to and from the interleaved frames processed below
+        let planar = config.channel_layout == ChannelLayout::Planar;
+        let mut planar_input = Vec::with_capacity(if planar { input_buffer.len() } else { 0 });
+        let mut planar_output = vec![0.0f32; if planar { output_buffer.len() } else { 0 }];
+        if planar {
+            info!("🔀 Reordering planar device buffers");
+        }
+        
+        info!("🎵 Starting ALSA audio processing loop...");
         
-        println!("🎵 Starting ALSA audio processing loop...");
+        // This thread runs the loop, so the scheduling hints apply to it directly
+        apply_audio_thread_hints(&config);
         
-        // Get I/O interfaces
-        let input_io = input_pcm.io_i32().map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        let output_io = output_pcm.io_i32().map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
+        // Get I/O interfaces, converting from and to the negotiated formats
+        let mut input_io = PcmIo::new(&input_pcm, input_format, input_buffer.len())?;
+        let mut output_io = PcmIo::new(&output_pcm, output_format, output_buffer.len())?;
+        let mut dither = config.dither.unwrap_or(output_format.dither_by_default()).then(TpdfDither::new);
+        if dither.is_some() {
+            info!("🎚️  Dithering {} output", output_format);
+        }
         
+        // Read/write errors repeat every iteration while a device is failing, so throttle them
+        let mut io_error_log = RateLimitedLog::default();
         let mut frames_processed = 0;
+        
+        // While idle only the input is read, to notice when signal returns
+        let mut idle_detector = config.auto_idle_enabled
+            .then(|| IdleDetector::new(config.auto_idle_threshold, config.auto_idle_timeout_s));
+        let block_duration = Duration::from_secs_f32(buffer_size as f32 / config.sample_rate as f32);
+        
         while *is_running.read() {
             // Read input using the correct ALSA API
-            match input_io.readi(&mut input_buffer) {
-                Ok(_) => {
+            match input_io.read(&mut input_buffer) {
+                Ok(0) => heartbeat.beat(),
+                Ok(frames_read) => {
+                    heartbeat.beat();
+                    
+                    // A short read only fills part of the period, so only that part is processed
+                    let frames_read = frames_read.min(buffer_size);
+                    let mut input = &input_buffer[..frames_read * input_channels];
+                    if planar {
+                        ChannelLayout::Planar.copy_to_interleaved(input, input_channels, &mut planar_input);
+                        input = &planar_input[..];
+                    }
+                    let output = &mut output_buffer[..frames_read * output_channels];
+                    
+                    if let Some(detector) = idle_detector.as_mut() {
+                        let sum: f32 = input.iter().map(|s| s.powi(2)).sum();
+                        let rms = (sum / input.len().max(1) as f32).sqrt();
+                        match detector.update(rms, block_duration) {
+                            IdleTransition::Sleep => {
+                                info!("💤 Input silent for {}s - stopping output", config.auto_idle_timeout_s);
+                                if let Err(e) = output_pcm.drop() {
+                                    io_error_log.error(&format!("Output stop error: {}", e));
+                                }
+                                idle.store(true, Ordering::Relaxed);
+                            }
+                            IdleTransition::Wake => {
+                                info!("🎸 Signal detected - restarting output");
+                                if let Err(e) = output_pcm.prepare() {
+                                    io_error_log.error(&format!("Output restart error: {}", e));
+                                }
+                                if let Ok(mut delay) = stereo_delay.lock() {
+                                    delay.begin_fade_in();
+                                }
+                                idle.store(false, Ordering::Relaxed);
+                            }
+                            IdleTransition::Stay => {}
+                        }
+                        if detector.is_idle() {
+                            continue;
+                        }
+                    }
+                    
                     // Process audio through stereo delay
                     if let Ok(mut delay) = stereo_delay.lock() {
-                        for i in (0..input_buffer.len()).step_by(2) {
-                            let left_input = input_buffer[i] as f32 / i32::MAX as f32;
-                            let right_input = if i + 1 < input_buffer.len() { 
-                                input_buffer[i + 1] as f32 / i32::MAX as f32 
-                            } else { 
-                                left_input 
-                            };
-                            
+                        let frames = input.chunks(input_channels).zip(output.chunks_mut(output_channels));
+                        for (input_frame, output_samples) in frames {
+                            let (left_input, right_input) = frame_to_stereo(input_frame);
                             let (left_output, right_output) = delay.process_sample(left_input, right_input);
                             
-                            // Convert back to S32
-                            output_buffer[i] = (left_output * i32::MAX as f32) as i32;
-                            if i + 1 < output_buffer.len() {
-                                output_buffer[i + 1] = (right_output * i32::MAX as f32) as i32;
-                            }
+                            // Spread over the output channels, from the configured pair on
+                            stereo_to_frame(left_output, right_output, output_channel_offset, &mut output_frame);
+                            output_samples.copy_from_slice(&output_frame);
+                        }
+                        
+                        // A tails stop ends once the repeats have rung out
+                        if delay.tails_finished() {
+                            info!("🔇 Delay tails finished - stopping audio");
+                            *is_running.write() = false;
                         }
                     }
                     
                     // Write output using the correct ALSA API
-                    if let Err(e) = output_io.writei(&output_buffer) {
-                        eprintln!("Output write error: {}", e);
+                    let output: &[f32] = if planar {
+                        let planar_output = &mut planar_output[..output.len()];
+                        ChannelLayout::Planar.copy_from_interleaved(output, output_channels, planar_output);
+                        planar_output
+                    } else {
+                        output
+                    };
+                    if let Err(e) = output_io.write(output, dither.as_mut()) {
+                        io_error_log.error(&format!("Output write error: {}", e));
                     }
                     
                     frames_processed += 1;
                 }
                 Err(e) => {
-                    eprintln!("Input read error: {}", e);
+                    io_error_log.error(&format!("Input read error: {}", e));
                     thread::sleep(Duration::from_millis(10));
                 }
             }
         }
         
-        println!("🎵 ALSA audio processing stopped - processed {} frames", frames_processed);
+        info!("🎵 ALSA audio processing stopped - processed {} frames", frames_processed);
         Ok(())
     }
     
-    /// Set stereo delay effect parameter
-    pub fn set_stereo_delay_parameter(&mut self, param: &str, value: f32) -> Result<(), AudioProcessorError> {
-        let mut delay = self.stereo_delay.lock().map_err(|_| {
-            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
-        })?;
-        
-        match param {
-            "left_delay" => delay.set_left_delay(value),
-            "right_delay" => delay.set_right_delay(value),
-            "bpm" => {
-                // Set BPM and calculate delay times
-                let mut config = self.config.clone();
-                config.stereo_delay.set_bpm(value);
-                delay.set_left_delay(config.stereo_delay.left_delay);
-                delay.set_right_delay(config.stereo_delay.right_delay);
-                // Update the stored config
-                self.config.stereo_delay.bpm = config.stereo_delay.bpm;
-                self.config.stereo_delay.left_delay = config.stereo_delay.left_delay;
-                self.config.stereo_delay.right_delay = config.stereo_delay.right_delay;
-            },
-            "feedback" => delay.set_feedback(value),
-            "wet_mix" => delay.set_wet_mix(value),
-            "ping_pong" => delay.set_stereo_parameters(Some(value > 0.5), None, None),
-            "stereo_width" => delay.set_stereo_parameters(None, Some(value), None),
-            "cross_feedback" => delay.set_stereo_parameters(None, None, Some(value)),
-            // Distortion parameters
-            "distortion_enabled" => delay.set_cross_feedback_distortion(Some(value > 0.5), None, None, None, None),
-            "distortion_drive" => delay.set_cross_feedback_distortion(None, None, Some(value), None, None),
-            "distortion_mix" => delay.set_cross_feedback_distortion(None, None, None, Some(value), None),
-            "distortion_feedback_intensity" => delay.set_cross_feedback_distortion(None, None, None, None, Some(value)),
-            _ => {
-                return Err(AudioProcessorError::InvalidParameter {
-                    param: param.to_string(),
-                    value,
-                    min: 0.0,
-                    max: 1.0,
-                });
+    /// Set up an opened PCM for interleaved stereo processing and prepare it
+    ///
+    /// Returns the channel count, sample format, period size and device buffer
+    /// size in frames the device settled on.
+    fn configure_pcm(pcm: &PCM, config: &AudioConfig) -> Result<(u32, SampleFormat, usize, usize), AudioProcessorError> {
+        let unavailable = |_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable);
+        let hwp = HwParams::any(pcm).map_err(unavailable)?;
+        let channels = Self::pick_channels(&hwp, config.alsa_channels);
+        hwp.set_channels(channels).map_err(unavailable)?;
+        hwp.set_rate(config.sample_rate, ValueOr::Nearest).map_err(unavailable)?;
+        let format = Self::pick_format(&hwp, config.alsa_format)?;
+        hwp.set_access(alsa::pcm::Access::RWInterleaved).map_err(unavailable)?;
+        Self::set_period_and_buffer(&hwp, config)?;
+        pcm.hw_params(&hwp).map_err(unavailable)?;
+        pcm.prepare().map_err(unavailable)?;
+        let (period_size, buffer_size) = Self::current_period_and_buffer(pcm)?;
+        Ok((channels, format, period_size, buffer_size))
+    }
+    
+    /// Pick the channel count to open a device with from its supported range
+    ///
+    /// Devices that do not report a range are asked for the configured count as is.
+    fn pick_channels(hwp: &HwParams, requested: u32) -> u32 {
+        match (hwp.get_channels_min(), hwp.get_channels_max()) {
+            (Ok(min), Ok(max)) => {
+                let channels = negotiate_channels(requested, min, max);
+                if channels != requested {
+                    info!("🔧 Device supports {}-{} channels, using {} instead of {}", min, max, channels, requested);
+                }
+                channels
             }
+            _ => requested,
+        }
+    }
+    
+    /// Set the configured sample format, or the first of S32, S24 and S16 the device supports
+    fn pick_format(hwp: &HwParams, preferred: SampleFormat) -> Result<SampleFormat, AudioProcessorError> {
+        let format = negotiate_sample_format(preferred, |format| hwp.set_format(Self::alsa_format(format)).is_ok())
+            .ok_or_else(|| AudioProcessorError::Processing("device supports none of the S32, S24 and S16 sample formats".to_string()))?;
+        if format != preferred {
+            info!("🔧 Device does not support {}, using {} instead", preferred, format);
+        }
+        Ok(format)
+    }
+    
+    /// ALSA format for a sample format, in native byte order
+    fn alsa_format(format: SampleFormat) -> Format {
+        match format {
+            SampleFormat::S32 => Format::s32(),
+            SampleFormat::S24 => Format::s24(),
+            SampleFormat::S16 => Format::s16(),
+        }
+    }
+    
+    /// Ask for a period of `buffer_size` frames and a device buffer of `alsa_periods` periods
+    ///
+    /// Both are fitted into the ranges the device reports; ALSA may still round them.
+    fn set_period_and_buffer(hwp: &HwParams, config: &AudioConfig) -> Result<(), AudioProcessorError> {
+        let frames = |value: alsa::Result<alsa::pcm::Frames>, default: u32| {
+            value.map(|frames| u32::try_from(frames.max(0)).unwrap_or(u32::MAX)).unwrap_or(default)
+        };
+        let period_range = (frames(hwp.get_period_size_min(), 0), frames(hwp.get_period_size_max(), u32::MAX));
+        let buffer_range = (frames(hwp.get_buffer_size_min(), 0), frames(hwp.get_buffer_size_max(), u32::MAX));
+        let (period_size, buffer_size) =
+            negotiate_period_and_buffer(config.buffer_size, config.alsa_periods, period_range, buffer_range);
+        if period_size as usize != config.buffer_size {
+            info!(
+                "🔧 Device supports periods of {}-{} frames, using {} instead of {}",
+                period_range.0, period_range.1, period_size, config.buffer_size
+            );
         }
         
+        hwp.set_period_size_near(period_size as alsa::pcm::Frames, ValueOr::Nearest)
+            .map_err(|e| AudioProcessorError::Processing(format!("cannot set ALSA period size: {}", e)))?;
+        hwp.set_buffer_size_near(buffer_size as alsa::pcm::Frames)
+            .map_err(|e| AudioProcessorError::Processing(format!("cannot set ALSA buffer size: {}", e)))?;
         Ok(())
     }
     
-    /// Set distortion type (string parameter)
-    pub fn set_distortion_type(&self, distortion_type: &str) -> Result<(), AudioProcessorError> {
-        let mut delay = self.stereo_delay.lock().map_err(|_| {
-            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
-        })?;
-        
-        let dist_type = DistortionType::from(distortion_type);
-        delay.set_cross_feedback_distortion(None, Some(dist_type), None, None, None);
+    /// Read back the period and buffer size in frames a PCM was configured with
+    fn current_period_and_buffer(pcm: &PCM) -> Result<(usize, usize), AudioProcessorError> {
+        let hwp = pcm.hw_params_current()
+            .map_err(|e| AudioProcessorError::Processing(format!("cannot read ALSA hardware parameters: {}", e)))?;
+        let period_size = hwp.get_period_size().unwrap_or(0).max(0) as usize;
+        let buffer_size = hwp.get_buffer_size().unwrap_or(0).max(0) as usize;
+        Ok((period_size, buffer_size))
+    }
+    
+    /// Write processed stereo audio to the output device and wait for it to play out
+    fn play_test_tone(&self, left: &[f32], right: &[f32]) -> Result<(), AudioProcessorError> {
+        let unavailable = |_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable);
+        let output_device = self.core.config.output_device.as_deref().unwrap_or(DEFAULT_ALSA_DEVICE);
+        let output_pcm = PCM::new(output_device, Direction::Playback, false).map_err(unavailable)?;
+        
+        let (channels, format, period_size, _) = Self::configure_pcm(&output_pcm, &self.core.config)?;
+        let channels = channels as usize;
+        check_output_channel_offset(self.core.config.output_channel_offset, channels)?;
+        let period_size = if period_size > 0 { period_size } else { self.core.config.buffer_size.max(1) };
+        let mut io = PcmIo::new(&output_pcm, format, period_size * channels)?;
+        let mut dither = self.core.config.dither.unwrap_or(format.dither_by_default()).then(TpdfDither::new);
+        let mut output = vec![0.0f32; period_size * channels];
+        let mut planar_output = vec![0.0f32; output.len()];
+        
+        info!("🔊 Playing test tone on {}", output_device);
+        for (left, right) in left.chunks(period_size).zip(right.chunks(period_size)) {
+            let output = &mut output[..left.len() * channels];
+            for ((&left, &right), frame) in left.iter().zip(right).zip(output.chunks_mut(channels)) {
+                stereo_to_frame(left, right, self.core.config.output_channel_offset, frame);
+            }
+            let output: &[f32] = if self.core.config.channel_layout == ChannelLayout::Planar {
+                let planar_output = &mut planar_output[..output.len()];
+                ChannelLayout::Planar.copy_from_interleaved(output, channels, planar_output);
+                planar_output
+            } else {
+                output
+            };
+            io.write(output, dither.as_mut())
+                .map_err(|e| AudioProcessorError::Processing(format!("test tone write failed: {}", e)))?;
+        }
         
+        // Block until everything written has been played
+        output_pcm.drain().map_err(|e| AudioProcessorError::Processing(format!("test tone drain failed: {}", e)))?;
         Ok(())
     }
+}
+
+#[cfg(target_os = "linux")]
+impl AudioProcessorTrait for AlsaAudioProcessor {
+    fn core(&self) -> &ProcessorCore {
+        &self.core
+    }
     
-    /// Reset the delay buffers to clear any lingering feedback
-    pub fn reset_delay(&self) -> Result<(), AudioProcessorError> {
-        let mut delay = self.stereo_delay.lock().map_err(|_| {
-            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
-        })?;
-        
-        delay.reset();
+    fn core_mut(&mut self) -> &mut ProcessorCore {
+        &mut self.core
+    }
+    
+    /// Start ALSA audio processing
+    fn start_audio(&mut self) -> Result<(), AudioProcessorError> {
+        self.core.prepare_start()?;
+        
+        let config = self.core.config.clone();
+        let stereo_delay = Arc::clone(&self.core.stereo_delay);
+        let is_running = Arc::clone(&self.core.is_running);
+        let heartbeat = Arc::clone(&self.core.heartbeat);
+        self.negotiated.latency.clear();
+        let negotiated = Arc::clone(&self.negotiated);
+        let idle = Arc::clone(&self.idle);
+        idle.store(false, Ordering::Relaxed);
+        
+        self.core.start(move || {
+            Self::run_alsa_audio_stream(config, stereo_delay, is_running, heartbeat, negotiated, idle)
+        });
         
         Ok(())
     }
     
-    /// Test ALSA audio processing
-    pub fn test_audio(&self) -> Result<(), AudioProcessorError> {
-        println!("🧪 Testing ALSA audio processing...");
-        
-        // Create a simple test delay
-        let test_delay = StereoDelay::new(
-            self.config.sample_rate,
-            0.1, // 100ms delay
-            0.2, // 200ms delay
-            0.3, // 30% feedback
-            0.5, // 50% wet mix
-            true, // ping pong
-            0.5, // stereo width
-            0.2, // cross feedback
-            false, // no distortion for test
-            DistortionType::SoftClip,
-            0.0,
-            0.0,
-        );
-        
-        // Generate test audio (sine wave)
-        let sample_rate = self.config.sample_rate as f32;
-        let frequency = 440.0; // A4 note
-        let duration = 1.0; // 1 second
-        let num_samples = (sample_rate * duration) as usize;
-        
-        let mut input_audio = Vec::with_capacity(num_samples);
-        for i in 0..num_samples {
-            let t = i as f32 / sample_rate;
-            let sample = (2.0 * std::f32::consts::PI * frequency * t).sin();
-            input_audio.push(sample);
+    /// Process a custom test tone through a copy of the current settings and play it
+    ///
+    /// The tone is only processed when the output device cannot be opened,
+    /// e.g. because the audio loop already holds it.
+    fn test_audio_with(&self, params: TestToneParams) -> Result<(), AudioProcessorError> {
+        info!("🧪 Testing ALSA audio processing...");
+        
+        let (left, right) = params.render(&self.core.config)?;
+        if params.play {
+            if let Err(e) = self.play_test_tone(&left, &right) {
+                warn!("⚠️  Test tone not played: {}", e);
+            }
         }
         
-        // Process through delay
-        let mut delay = test_delay;
-        for sample in &input_audio {
-            let (_left, _right) = delay.process_sample(*sample, *sample);
-            // Just process, don't need to store output for test
+        info!("✅ ALSA audio test completed - processed {} samples", left.len());
+        Ok(())
+    }
+    
+    /// Get overall system status
+    fn get_status(&self) -> Result<std::collections::HashMap<String, String>, AudioProcessorError> {
+        let mut status = self.core.status(Some(&self.negotiated.latency))?;
+        
+        // Both PCMs are opened at the configured rate, so nothing is resampled
+        status.insert("resampler".to_string(), "off".to_string());
+        status.insert("idle".to_string(), self.idle.load(Ordering::Relaxed).to_string());
+        let negotiated = [
+            ("input_channels", &self.negotiated.input_channels),
+            ("output_channels", &self.negotiated.output_channels),
+            ("negotiated_period_size", &self.negotiated.period_size),
+            ("negotiated_buffer_size", &self.negotiated.buffer_size),
+        ];
+        for (key, value) in negotiated {
+            let value = value.load(Ordering::Relaxed);
+            if value > 0 {
+                status.insert(key.to_string(), value.to_string());
+            }
+        }
+        let formats = [("input_format", &self.negotiated.input_format), ("output_format", &self.negotiated.output_format)];
+        for (key, format) in formats {
+            if let Some(format) = *format.read() {
+                status.insert(key.to_string(), format.to_string());
+            }
         }
         
-        println!("✅ ALSA audio test completed - processed {} samples", num_samples);
-        Ok(())
+        Ok(status)
+    }
+    
+    /// Get the smoothed output meter readings
+    fn get_meters(&self) -> Result<std::collections::HashMap<String, f32>, AudioProcessorError> {
+        self.core.meters(Some(&self.negotiated.latency))
+    }
+    
+    /// List the ALSA PCM devices that can be opened for capture and playback
+    fn list_devices(&self) -> DeviceList {
+        list_alsa_pcm_devices()
     }
 }
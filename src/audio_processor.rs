@@ -1,14 +1,142 @@
+use std::path::Path;
 use std::sync::{Arc, Mutex};
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::thread;
 use std::time::Duration;
 use cpal::traits::{DeviceTrait, HostTrait, StreamTrait};
 use parking_lot::RwLock;
-use crate::delay::BaseDelay;
 
+use crate::backend::AudioBackend;
 use crate::config::AudioConfig;
 use crate::delay::StereoDelay;
-use crate::distortion::DistortionType;
 use crate::error::AudioProcessorError;
+use crate::file_input::{run_input_loop, InputLoop};
+use crate::resample::SampleRateConversion;
+use crate::idle::{IdleDetector, IdleTransition};
+use crate::latency::BufferFrames;
+use crate::health::{CallbackFailure, Heartbeat};
+use crate::logging::RateLimitedLog;
+use crate::processor_core::ProcessorCore;
+use crate::test_signal::TestToneParams;
+use crate::realtime::apply_audio_thread_hints;
+use crate::ring_buffer::{AudioRing, XrunCounters, XrunReport, AUDIO_RING_FRAMES};
+use crate::devices::{buffer_size_hint, check_output_channel_offset, list_cpal_devices, stereo_to_frame, ChannelLayout, DeviceList};
+use crate::AudioProcessorTrait;
+use log::{error, info, warn};
+
+/// Extra time a test tone's stream is kept open so the device buffer plays out
+const TEST_TONE_MARGIN: Duration = Duration::from_millis(200);
+
+/// Build a stream with the requested buffer size, retrying with the device default if it is rejected
+///
+/// `build` is called again for the retry, so it must create a fresh data callback each time.
+fn build_stream_with_fallback<F>(
+    direction: &str,
+    stream_config: &mut cpal::StreamConfig,
+    mut build: F,
+) -> Result<cpal::Stream, AudioProcessorError>
+where
+    F: FnMut(&cpal::StreamConfig) -> Result<cpal::Stream, cpal::BuildStreamError>,
+{
+    match build(stream_config) {
+        Ok(stream) => Ok(stream),
+        Err(e) if stream_config.buffer_size != cpal::BufferSize::Default => {
+            warn!(
+                "⚠️  {} device rejected buffer size {:?} ({}), using the device default",
+                direction, stream_config.buffer_size, e
+            );
+            stream_config.buffer_size = cpal::BufferSize::Default;
+            build(stream_config).map_err(AudioProcessorError::AudioDevice)
+        }
+        Err(e) => Err(AudioProcessorError::AudioDevice(e)),
+    }
+}
+
+/// Feed an input block to the idle detector, returning whether the block should be skipped
+///
+/// Keeps the shared idle flag in sync and fades the delay back in on wake-up.
+fn update_idle(
+    detector: &mut Option<IdleDetector>,
+    rms: f32,
+    duration: Duration,
+    idle: &AtomicBool,
+    stereo_delay: &Mutex<StereoDelay>,
+) -> bool {
+    let detector = match detector.as_mut() {
+        Some(detector) => detector,
+        None => return false,
+    };
+    
+    match detector.update(rms, duration) {
+        IdleTransition::Sleep => idle.store(true, Ordering::Relaxed),
+        IdleTransition::Wake => {
+            if let Ok(mut delay) = stereo_delay.lock() {
+                delay.begin_fade_in();
+            }
+            idle.store(false, Ordering::Relaxed);
+        }
+        IdleTransition::Stay => {}
+    }
+    detector.is_idle()
+}
+
+/// Fail once a stream callback has panicked or the delay lock has been poisoned
+///
+/// The loop owning the streams calls this so a failure inside a callback stops
+/// audio with the error instead of leaving the callbacks skipping every block.
+fn check_stream_failure(failure: &CallbackFailure, stereo_delay: &Mutex<StereoDelay>) -> Result<(), AudioProcessorError> {
+    failure.check().map_err(AudioProcessorError::Processing)?;
+    if stereo_delay.is_poisoned() {
+        return Err(AudioProcessorError::Threading("stereo delay lock poisoned by a panic".to_string()));
+    }
+    Ok(())
+}
+
+/// Body of an output callback: plays the processed frames queued in the ring
+struct OutputWriter {
+    audio_ring: Arc<AudioRing>,
+    buffer_frames: Arc<BufferFrames>,
+    channels: usize,
+    channel_layout: ChannelLayout,
+    output_channel_offset: usize,
+    interleaved: Vec<f32>,
+}
+
+impl OutputWriter {
+    fn new(audio_ring: Arc<AudioRing>, buffer_frames: Arc<BufferFrames>, channels: usize, config: &AudioConfig) -> Self {
+        Self {
+            audio_ring,
+            buffer_frames,
+            channels: channels.max(1),
+            channel_layout: config.channel_layout,
+            output_channel_offset: config.output_channel_offset,
+            interleaved: Vec::new(),
+        }
+    }
+    
+    /// Fill one output buffer of `channels` channels
+    fn write(&mut self, data: &mut [f32]) {
+        let channels = self.channels;
+        self.buffer_frames.output.store(data.len() / channels, Ordering::Relaxed);
+        
+        // One queued stereo frame per output frame, silence if the ring runs dry
+        self.buffer_frames.processing.store(self.audio_ring.len(), Ordering::Relaxed);
+        let output_channel_offset = self.output_channel_offset;
+        let mut frames = data.chunks_mut(channels);
+        self.audio_ring.read_frames(frames.len(), |left, right| {
+            if let Some(frame) = frames.next() {
+                stereo_to_frame(left, right, output_channel_offset, frame);
+            }
+        });
+        
+        // Planar devices take the interleaved samples channel by channel
+        if self.channel_layout == ChannelLayout::Planar {
+            self.interleaved.clear();
+            self.interleaved.extend_from_slice(data);
+            self.channel_layout.copy_from_interleaved(&self.interleaved, channels, data);
+        }
+    }
+}
 
 /// Helper function to find a device by name
 fn find_device_by_name(devices: Vec<cpal::Device>, target_name: &str) -> Option<cpal::Device> {
@@ -19,10 +147,13 @@ fn find_device_by_name(devices: Vec<cpal::Device>, target_name: &str) -> Option<
 
 /// Unified audio processor for guitar stereo delay effects system
 pub struct AudioProcessor {
-    config: AudioConfig,
-    stereo_delay: Arc<Mutex<StereoDelay>>,
-    is_running: Arc<RwLock<bool>>,
-    audio_thread: Option<thread::JoinHandle<()>>,
+    core: ProcessorCore,
+    // Callback and queue sizes in frames, i.e. the buffer sizes the devices actually use
+    buffer_frames: Arc<BufferFrames>,
+    // Underruns and overruns of the ring between the input and output callbacks
+    xruns: Arc<XrunCounters>,
+    // Set while the output is stopped because the input is silent
+    idle: Arc<AtomicBool>,
 }
 
 impl AudioProcessor {
@@ -34,109 +165,21 @@ impl AudioProcessor {
     
     /// Create a new audio processor with custom configuration
     pub fn with_config(config: AudioConfig) -> Result<Self, AudioProcessorError> {
-        // Validate configuration
-        config.validate()?;
-        
-        // Create stereo delay effect
-        let distortion_type = DistortionType::from(config.distortion.distortion_type.as_str());
-        let stereo_delay = StereoDelay::new(
-            config.sample_rate,
-            config.stereo_delay.left_delay,
-            config.stereo_delay.right_delay,
-            config.stereo_delay.feedback,
-            config.stereo_delay.wet_mix,
-            config.stereo_delay.ping_pong,
-            config.stereo_delay.stereo_width,
-            config.stereo_delay.cross_feedback,
-            config.distortion.enabled,
-            distortion_type,
-            config.distortion.drive,
-            config.distortion.mix,
-        );
-        
         Ok(Self {
-            config,
-            stereo_delay: Arc::new(Mutex::new(stereo_delay)),
-            is_running: Arc::new(RwLock::new(false)),
-            audio_thread: None,
+            core: ProcessorCore::new(AudioBackend::Cpal, config)?,
+            buffer_frames: Arc::new(BufferFrames::default()),
+            xruns: Arc::new(XrunCounters::default()),
+            idle: Arc::new(AtomicBool::new(false)),
         })
     }
     
-    /// Set stereo delay effect parameter
-    pub fn set_stereo_delay_parameter(&mut self, param: &str, value: f32) -> Result<(), AudioProcessorError> {
-        let mut delay = self.stereo_delay.lock().map_err(|_| {
-            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
-        })?;
-        
-        match param {
-            "left_delay" => delay.set_left_delay(value),
-            "right_delay" => delay.set_right_delay(value),
-            "bpm" => {
-                // Set BPM and calculate delay times
-                let mut config = self.config.clone();
-                config.stereo_delay.set_bpm(value);
-                delay.set_left_delay(config.stereo_delay.left_delay);
-                delay.set_right_delay(config.stereo_delay.right_delay);
-                // Update the stored config
-                self.config.stereo_delay.bpm = config.stereo_delay.bpm;
-                self.config.stereo_delay.left_delay = config.stereo_delay.left_delay;
-                self.config.stereo_delay.right_delay = config.stereo_delay.right_delay;
-            },
-            "feedback" => delay.set_feedback(value),
-            "wet_mix" => delay.set_wet_mix(value),
-            "ping_pong" => delay.set_stereo_parameters(Some(value > 0.5), None, None),
-            "stereo_width" => delay.set_stereo_parameters(None, Some(value), None),
-            "cross_feedback" => delay.set_stereo_parameters(None, None, Some(value)),
-            // Distortion parameters
-            "distortion_enabled" => delay.set_cross_feedback_distortion(Some(value > 0.5), None, None, None, None),
-            "distortion_drive" => delay.set_cross_feedback_distortion(None, None, Some(value), None, None),
-            "distortion_mix" => delay.set_cross_feedback_distortion(None, None, None, Some(value), None),
-            "distortion_feedback_intensity" => delay.set_cross_feedback_distortion(None, None, None, None, Some(value)),
-            _ => {
-                return Err(AudioProcessorError::InvalidParameter {
-                    param: param.to_string(),
-                    value,
-                    min: 0.0,
-                    max: 1.0,
-                });
-            }
-        }
-        
-        Ok(())
-    }
-    
-    /// Set distortion type (string parameter)
-    pub fn set_distortion_type(&self, distortion_type: &str) -> Result<(), AudioProcessorError> {
-        let mut delay = self.stereo_delay.lock().map_err(|_| {
-            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
-        })?;
-        
-        let dist_type = DistortionType::from(distortion_type);
-        delay.set_cross_feedback_distortion(None, Some(dist_type), None, None, None);
-        
-        Ok(())
-    }
-    
-    /// Reset the delay buffers to clear any lingering feedback
-    pub fn reset_delay(&self) -> Result<(), AudioProcessorError> {
-        let mut delay = self.stereo_delay.lock().map_err(|_| {
-            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
-        })?;
-        
-        delay.reset();
-        
-        Ok(())
-    }
-    
     /// Process audio through stereo delay effect
     pub fn process_audio(&self, input_audio: &[f32]) -> Result<Vec<f32>, AudioProcessorError> {
         if input_audio.is_empty() {
             return Ok(input_audio.to_vec());
         }
         
-        let mut delay = self.stereo_delay.lock().map_err(|_| {
-            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
-        })?;
+        let mut delay = self.core.delay()?;
         
         // Process through stereo delay effect
         let (left_output, right_output) = delay.process_mono_to_stereo(input_audio);
@@ -151,24 +194,19 @@ impl AudioProcessor {
         Ok(output_audio)
     }
     
-    /// Start audio processing
-    pub fn start_audio(&mut self) -> Result<(), AudioProcessorError> {
-        if *self.is_running.read() {
-            return Err(AudioProcessorError::Processing("Audio already running".to_string()));
+    /// Check that cpal can find an input and an output device
+    ///
+    /// Configured devices that are missing fall back to the host defaults when the
+    /// stream starts, so only the defaults are required here. With an input file
+    /// only the output device is needed.
+    pub fn probe_devices(config: &AudioConfig) -> Result<(), AudioProcessorError> {
+        let host = cpal::default_host();
+        if config.input_file.is_none() && host.default_input_device().is_none() {
+            return Err(AudioProcessorError::Processing(format!("no input device on the {} host", host.id().name())));
+        }
+        if host.default_output_device().is_none() {
+            return Err(AudioProcessorError::Processing(format!("no output device on the {} host", host.id().name())));
         }
-        
-        let config = self.config.clone();
-        let stereo_delay = Arc::clone(&self.stereo_delay);
-        let is_running = Arc::clone(&self.is_running);
-        
-        let thread_handle = thread::spawn(move || {
-            if let Err(e) = Self::run_audio_stream(config, stereo_delay, is_running) {
-                eprintln!("Audio stream error: {}", e);
-            }
-        });
-        
-        self.audio_thread = Some(thread_handle);
-        *self.is_running.write() = true;
         
         Ok(())
     }
@@ -178,43 +216,42 @@ impl AudioProcessor {
         config: AudioConfig,
         stereo_delay: Arc<Mutex<StereoDelay>>,
         is_running: Arc<RwLock<bool>>,
+        heartbeat: Arc<Heartbeat>,
+        buffer_frames: Arc<BufferFrames>,
+        xruns: Arc<XrunCounters>,
+        idle: Arc<AtomicBool>,
     ) -> Result<(), AudioProcessorError> {
         // List available hosts to see what's available
-        println!("🎵 Available audio hosts:");
+        info!("🎵 Available audio hosts:");
         for host_id in cpal::available_hosts() {
-            println!("  - {:?}", host_id);
+            info!("  - {:?}", host_id);
         }
         
         // Try to use ALSA host directly
         let available_hosts = cpal::available_hosts();
         let host = if let Some(alsa_host_id) = available_hosts.iter().find(|id| format!("{:?}", id).to_lowercase().contains("alsa")) {
             if let Ok(alsa_host) = cpal::host_from_id(*alsa_host_id) {
-                println!("🎵 Using ALSA host for direct hardware access");
+                info!("🎵 Using ALSA host for direct hardware access");
                 alsa_host
             } else {
-                println!("⚠️  Could not use ALSA host, falling back to default");
+                warn!("⚠️  Could not use ALSA host, falling back to default");
                 cpal::default_host()
             }
         } else {
-            println!("⚠️  No ALSA host found, using default");
+            warn!("⚠️  No ALSA host found, using default");
             cpal::default_host()
         };
         
-        println!("🎵 Using ALSA host for direct hardware access...");
-        
-        // List available devices for debugging
-
-        
-
+        info!("🎵 Using ALSA host for direct hardware access...");
         
         // Also try to list all devices with more detail
-        println!("🔍 Detailed device enumeration:");
+        info!("🔍 Detailed device enumeration:");
         if let Ok(devices) = host.output_devices() {
             for device in devices {
                 if let Ok(name) = device.name() {
-                    println!("  Device: '{}'", name);
+                    info!("  Device: '{}'", name);
                     if let Ok(config) = device.default_output_config() {
-                        println!("    Config: {:?}", config);
+                        info!("    Config: {:?}", config);
                     }
                 }
             }
@@ -224,16 +261,16 @@ impl AudioProcessor {
         let input_device = if let Ok(devices) = host.input_devices() {
             // Collect all devices first to avoid enumeration issues
             let device_list: Vec<_> = devices.collect();
-            println!("🔍 Found {} input devices to check", device_list.len());
+            info!("🔍 Found {} input devices to check", device_list.len());
             
             // First try to use configured input device
             if let Some(ref configured_device) = config.input_device {
-                println!("🎯 Looking for configured input device: '{}'", configured_device);
+                info!("🎯 Looking for configured input device: '{}'", configured_device);
                 if let Some(device) = find_device_by_name(device_list.clone(), configured_device) {
-                    println!("✅ Found configured input device: '{}'", configured_device);
+                    info!("✅ Found configured input device: '{}'", configured_device);
                     Some(device)
                 } else {
-                    println!("⚠️  Configured input device '{}' not found, falling back to USB detection", configured_device);
+                    warn!("⚠️  Configured input device '{}' not found, falling back to USB detection", configured_device);
                     None
                 }
             } else {
@@ -243,7 +280,7 @@ impl AudioProcessor {
                 device_list.into_iter().find(|device| {
                     device.name().map(|name| {
                         let name_lower = name.to_lowercase();
-                        println!("🔍 Checking input device: '{}'", name);
+                        info!("🔍 Checking input device: '{}'", name);
                         name_lower.contains("usb") || 
                         name_lower.contains("scarlett") ||
                         name_lower.contains("focusrite") ||
@@ -253,27 +290,27 @@ impl AudioProcessor {
                     }).unwrap_or(false)
                 })
             }).or_else(|| {
-                println!("⚠️  No USB audio input device found, trying default...");
+                warn!("⚠️  No USB audio input device found, trying default...");
                 host.default_input_device()
             })
         } else {
-            println!("⚠️  Could not enumerate input devices, using default...");
+            warn!("⚠️  Could not enumerate input devices, using default...");
             host.default_input_device()
         }.ok_or_else(|| {
-            println!("❌ No input device available");
+            error!("❌ No input device available");
             AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable)
         })?;
-            
+        
         // Try to find output device from detailed enumeration since main list is broken
         let output_device = {
-            println!("🔍 Searching for USB output device in detailed enumeration...");
+            info!("🔍 Searching for USB output device in detailed enumeration...");
             let mut usb_device = None;
             
             // Try to enumerate all devices and look for USB devices
             if let Ok(devices) = host.output_devices() {
                 for device in devices {
                     if let Ok(name) = device.name() {
-                        println!("🔍 Checking device: '{}'", name);
+                        info!("🔍 Checking device: '{}'", name);
                         let name_lower = name.to_lowercase();
                         if name_lower.contains("usb") || 
                            name_lower.contains("scarlett") ||
@@ -282,7 +319,7 @@ impl AudioProcessor {
                            name_lower.contains("card=usb") ||
                            name_lower.contains("hw:card=usb") ||
                            name_lower.contains("plughw:card=usb") {
-                            println!("✅ Found USB output device: '{}'", name);
+                            info!("✅ Found USB output device: '{}'", name);
                             usb_device = Some(device);
                             break;
                         }
@@ -294,370 +331,531 @@ impl AudioProcessor {
             if let Some(device) = usb_device {
                 device
             } else {
-                println!("⚠️  No USB output device found in enumeration, trying default...");
+                warn!("⚠️  No USB output device found in enumeration, trying default...");
                 host.default_output_device().ok_or_else(|| {
-                    println!("❌ No default output device available");
+                    error!("❌ No default output device available");
                     AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable)
                 })?
             }
         };
         
-        println!("🎤 Using input device: {}", input_device.name().unwrap_or_else(|_| "Unknown".to_string()));
-        println!("🔊 Using output device: {}", output_device.name().unwrap_or_else(|_| "Unknown".to_string()));
+        info!("🎤 Using input device: {}", input_device.name().unwrap_or_else(|_| "Unknown".to_string()));
+        info!("🔊 Using output device: {}", output_device.name().unwrap_or_else(|_| "Unknown".to_string()));
         
         // Get supported configs and ensure format compatibility
         let input_config = input_device.default_input_config()
             .map_err(|e| {
-                println!("❌ Failed to get input config: {:?}", e);
+                error!("❌ Failed to get input config: {:?}", e);
                 AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable)
             })?;
         let output_config = output_device.default_output_config()
             .map_err(|e| {
-                println!("❌ Failed to get output config: {:?}", e);
+                error!("❌ Failed to get output config: {:?}", e);
                 AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable)
             })?;
         
-        println!("🎤 Input config: {:?}", input_config);
-        println!("🔊 Output config: {:?}", output_config);
-        
-        // Create a simple buffer for audio data with size limit
-        let audio_buffer = Arc::new(Mutex::new(Vec::<f32>::with_capacity(4096)));
-        let audio_buffer_clone = Arc::clone(&audio_buffer);
-        
-        // Create input stream with format conversion if needed
-        let input_stream = if input_config.sample_format() == cpal::SampleFormat::I32 {
-            println!("🔄 Converting I32 input to F32 for processing...");
-            // Handle I32 input format
-            input_device.build_input_stream(
-                &input_config.into(),
-                move |data: &[i32], _: &cpal::InputCallbackInfo| {
-                    // Convert I32 to F32 and process
-                    if let Ok(mut delay) = stereo_delay.lock() {
-                        if let Ok(mut buffer) = audio_buffer_clone.lock() {
+        info!("🎤 Input config: {:?}", input_config);
+        info!("🔊 Output config: {:?}", output_config);
+        
+        // Ask for the configured buffer size, clamped to what each device supports
+        let input_format = input_config.sample_format();
+        let mut input_stream_config = input_config.config();
+        input_stream_config.buffer_size = buffer_size_hint(config.buffer_size, input_config.buffer_size());
+        let mut output_stream_config = output_config.config();
+        output_stream_config.buffer_size = buffer_size_hint(config.buffer_size, output_config.buffer_size());
+        check_output_channel_offset(config.output_channel_offset, output_stream_config.channels as usize)?;
+        
+        // Convert the processed stream to the output rate when the devices disagree
+        let input_rate = input_config.sample_rate().0;
+        let output_rate = output_config.sample_rate().0;
+        buffer_frames.sample_rate.store(output_rate, Ordering::Relaxed);
+        if input_rate != output_rate {
+            info!("🔄 Resampling processed audio from {} Hz to {} Hz ({} quality)", input_rate, output_rate, config.resample_quality);
+            buffer_frames.resampled_from.store(input_rate, Ordering::Relaxed);
+        }
+        
+        // Lock-free ring carrying the processed frames to the output callback
+        let audio_ring = Arc::new(AudioRing::new(AUDIO_RING_FRAMES, xruns));
+        let tails_delay = Arc::clone(&stereo_delay);
+        
+        // Panics in the callbacks are caught there and reported by the keep-alive loop
+        let callback_failure = Arc::new(CallbackFailure::new());
+        
+        if input_format == cpal::SampleFormat::I32 {
+            info!("🔄 Converting I32 input to F32 for processing...");
+        } else {
+            info!("✅ Using F32 input format directly...");
+        }
+        
+        // Create input stream with format conversion if needed. The callback is built
+        // inside the closure so the stream can be rebuilt if the buffer size is rejected.
+        let input_stream = build_stream_with_fallback("input", &mut input_stream_config, |stream_config| {
+            let stereo_delay = Arc::clone(&stereo_delay);
+            let audio_ring = Arc::clone(&audio_ring);
+            let callback_failure = Arc::clone(&callback_failure);
+            let heartbeat = Arc::clone(&heartbeat);
+            let buffer_frames = Arc::clone(&buffer_frames);
+            let idle = Arc::clone(&idle);
+            let mut idle_detector = config.auto_idle_enabled
+                .then(|| IdleDetector::new(config.auto_idle_threshold, config.auto_idle_timeout_s));
+            let channels = (stream_config.channels as usize).max(1);
+            let mut resampler = if input_rate != output_rate {
+                Some(SampleRateConversion::with_quality(input_rate, output_rate, 2, config.resample_quality))
+            } else {
+                None
+            };
+            let mut processed = Vec::<f32>::with_capacity(4096);
+            let mut resampled = Vec::<f32>::with_capacity(4096);
+            
+            // Planar input is reordered into interleaved frames before processing
+            let channel_layout = config.channel_layout;
+            let mut interleaved_i32 = Vec::<i32>::new();
+            let mut interleaved_f32 = Vec::<f32>::new();
+            
+            // cpal owns the callback thread, so the scheduling hints are applied from its first callback
+            let mut thread_hints = Some(config.clone());
+            
+            // Stream errors can repeat every callback, so throttle them
+            let mut input_error_log = RateLimitedLog::default();
+            let error_callback = move |err: cpal::StreamError| {
+                input_error_log.error(&format!("Audio input error: {}", err));
+            };
+            
+            if input_format == cpal::SampleFormat::I32 {
+                // Handle I32 input format
+                input_device.build_input_stream(
+                    stream_config,
+                    move |data: &[i32], _: &cpal::InputCallbackInfo| callback_failure.run(|| {
+                        heartbeat.beat();
+                        buffer_frames.input.store(data.len() / channels, Ordering::Relaxed);
+                        if let Some(config) = thread_hints.take() {
+                            apply_audio_thread_hints(&config);
+                        }
+                        let data = if channel_layout == ChannelLayout::Planar {
+                            channel_layout.copy_to_interleaved(data, channels, &mut interleaved_i32);
+                            &interleaved_i32[..]
+                        } else {
+                            data
+                        };
+                        
+                        // While idle only the input level is watched
+                        if idle_detector.is_some() {
+                            let sum: f32 = data.iter().map(|&s| (s as f32 / i32::MAX as f32).powi(2)).sum();
+                            let rms = (sum / data.len().max(1) as f32).sqrt();
+                            let duration = Duration::from_secs_f32((data.len() / channels) as f32 / input_rate as f32);
+                            if update_idle(&mut idle_detector, rms, duration, &idle, &stereo_delay) {
+                                return;
+                            }
+                        }
+                        
+                        // Convert I32 to F32 and process
+                        if let Ok(mut delay) = stereo_delay.lock() {
                             // Process stereo input (assuming interleaved LRLR...)
                             for i in (0..data.len()).step_by(2) {
                                 let left_input = if i < data.len() { data[i] as f32 / i32::MAX as f32 } else { 0.0 };
                                 let right_input = if i + 1 < data.len() { data[i + 1] as f32 / i32::MAX as f32 } else { left_input };
                                 
                                 let (left_output, right_output) = delay.process_sample(left_input, right_input);
-                                
-                                // Keep stereo separation and limit buffer size
-                                if buffer.len() < 4096 {
-                                    buffer.push(left_output);
-                                    buffer.push(right_output);
-                                }
+                                processed.push(left_output);
+                                processed.push(right_output);
                             }
+                            
+                            // Frames that do not fit in the ring are dropped and counted
+                            if let Some(resampler) = resampler.as_mut() {
+                                resampler.process(&processed, &mut resampled);
+                                audio_ring.push_interleaved(&resampled);
+                                resampled.clear();
+                            } else {
+                                audio_ring.push_interleaved(&processed);
+                            }
+                            processed.clear();
                         }
-                    }
-                },
-                move |err| {
-                    eprintln!("Audio input error: {}", err);
-                },
-                None,
-            ).map_err(AudioProcessorError::AudioDevice)?
-        } else {
-            println!("✅ Using F32 input format directly...");
-            // Handle F32 input format
-            input_device.build_input_stream(
-                &input_config.into(),
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    // Process input data and send to buffer
-                    if let Ok(mut delay) = stereo_delay.lock() {
-                        if let Ok(mut buffer) = audio_buffer_clone.lock() {
+                    }),
+                    error_callback,
+                    None,
+                )
+            } else {
+                // Handle F32 input format
+                input_device.build_input_stream(
+                    stream_config,
+                    move |data: &[f32], _: &cpal::InputCallbackInfo| callback_failure.run(|| {
+                        heartbeat.beat();
+                        buffer_frames.input.store(data.len() / channels, Ordering::Relaxed);
+                        if let Some(config) = thread_hints.take() {
+                            apply_audio_thread_hints(&config);
+                        }
+                        let data = if channel_layout == ChannelLayout::Planar {
+                            channel_layout.copy_to_interleaved(data, channels, &mut interleaved_f32);
+                            &interleaved_f32[..]
+                        } else {
+                            data
+                        };
+                        
+                        // While idle only the input level is watched
+                        if idle_detector.is_some() {
+                            let rms = (data.iter().map(|s| s * s).sum::<f32>() / data.len().max(1) as f32).sqrt();
+                            let duration = Duration::from_secs_f32((data.len() / channels) as f32 / input_rate as f32);
+                            if update_idle(&mut idle_detector, rms, duration, &idle, &stereo_delay) {
+                                return;
+                            }
+                        }
+                        
+                        // Process input data and send to buffer
+                        if let Ok(mut delay) = stereo_delay.lock() {
                             // Process stereo input (assuming interleaved LRLR...)
                             for i in (0..data.len()).step_by(2) {
                                 let left_input = if i < data.len() { data[i] } else { 0.0 };
                                 let right_input = if i + 1 < data.len() { data[i + 1] } else { left_input };
                                 
                                 let (left_output, right_output) = delay.process_sample(left_input, right_input);
-                                
-                                // Keep stereo separation and limit buffer size
-                                if buffer.len() < 4096 {
-                                    buffer.push(left_output);
-                                    buffer.push(right_output);
-                                }
+                                processed.push(left_output);
+                                processed.push(right_output);
+                            }
+                            
+                            // Frames that do not fit in the ring are dropped and counted
+                            if let Some(resampler) = resampler.as_mut() {
+                                resampler.process(&processed, &mut resampled);
+                                audio_ring.push_interleaved(&resampled);
+                                resampled.clear();
+                            } else {
+                                audio_ring.push_interleaved(&processed);
                             }
+                            processed.clear();
                         }
-                    }
-                },
-                move |err| {
-                    eprintln!("Audio input error: {}", err);
+                    }),
+                    error_callback,
+                    None,
+                )
+            }
+        })?;
+        
+        // Create output stream
+        let output_stream = build_stream_with_fallback("output", &mut output_stream_config, |stream_config| {
+            let callback_failure = Arc::clone(&callback_failure);
+            let mut output_error_log = RateLimitedLog::default();
+            let mut writer = OutputWriter::new(Arc::clone(&audio_ring), Arc::clone(&buffer_frames), stream_config.channels as usize, &config);
+            
+            output_device.build_output_stream(
+                stream_config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| callback_failure.run(|| writer.write(data)),
+                move |err: cpal::StreamError| {
+                    output_error_log.error(&format!("Audio output error: {}", err));
                 },
                 None,
-            ).map_err(AudioProcessorError::AudioDevice)?
-        };
+            )
+        })?;
         
-        // Create output stream
-        let output_stream = output_device.build_output_stream(
-            &output_config.into(),
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                // Fill output buffer with processed audio from buffer
-                if let Ok(mut buffer) = audio_buffer.lock() {
-                    for sample in data.iter_mut() {
-                        if let Some(processed_sample) = buffer.pop() {
-                            *sample = processed_sample;
-                        } else {
-                            *sample = 0.0; // Silence if no data available
-                        }
-                    }
-                }
-            },
-            move |err| {
-                eprintln!("Audio output error: {}", err);
-            },
-            None,
-        ).map_err(AudioProcessorError::AudioDevice)?;
+        info!("🎚️  Buffer size: input {:?}, output {:?}", input_stream_config.buffer_size, output_stream_config.buffer_size);
         
         // Start both streams
         input_stream.play().map_err(AudioProcessorError::AudioStream)?;
         output_stream.play().map_err(AudioProcessorError::AudioStream)?;
         
-        println!("🎵 Audio streams started - input and output are now active!");
+        info!("🎵 Audio streams started - input and output are now active!");
         
         // Keep the streams alive while running
+        let mut output_paused = false;
         while *is_running.read() {
             thread::sleep(Duration::from_millis(100));
+            check_stream_failure(&callback_failure, &tails_delay)?;
+            
+            // Stop the output while the input is silent, the input callback keeps watching the level
+            let is_idle = idle.load(Ordering::Relaxed);
+            if is_idle != output_paused {
+                if is_idle {
+                    info!("💤 Input silent for {}s - stopping output", config.auto_idle_timeout_s);
+                    if let Err(e) = output_stream.pause() {
+                        warn!("⚠️  Could not pause output stream: {}", e);
+                    }
+                } else {
+                    info!("🎸 Signal detected - restarting output");
+                    // The output callback is paused, so nothing else is reading the ring
+                    audio_ring.clear();
+                    output_stream.play().map_err(AudioProcessorError::AudioStream)?;
+                }
+                output_paused = is_idle;
+            }
+            
+            // A tails stop ends once the repeats have rung out
+            if tails_delay.lock().map(|delay| delay.tails_finished()).unwrap_or(false) {
+                info!("🔇 Delay tails finished - stopping audio");
+                *is_running.write() = false;
+            }
         }
         
         Ok(())
     }
     
-    /// Run the audio stream with the same device for input and output
-    fn run_audio_stream_with_device(
-        _config: AudioConfig,
+    /// Run the output stream on input looped from a file instead of an input device
+    ///
+    /// This thread stands in for the input callback: it processes one buffer of the
+    /// file at a time at the pace of the processing rate and queues the result for
+    /// the output stream, which plays it exactly as it plays live input.
+    fn run_file_stream(
+        config: AudioConfig,
+        input: InputLoop,
         stereo_delay: Arc<Mutex<StereoDelay>>,
         is_running: Arc<RwLock<bool>>,
-        input_device: cpal::Device,
-        output_device: cpal::Device,
+        heartbeat: Arc<Heartbeat>,
+        buffer_frames: Arc<BufferFrames>,
+        xruns: Arc<XrunCounters>,
     ) -> Result<(), AudioProcessorError> {
-        println!("🎵 Initializing audio streams with same device...");
-        
-        // List available devices for debugging
-        println!("📋 Available input devices:");
-        if let Ok(devices) = cpal::default_host().input_devices() {
-            for device in devices {
-                if let Ok(name) = device.name() {
-                    println!("  - {}", name);
-                }
-            }
-        }
+        let host = cpal::default_host();
+        let output_device = match &config.output_device {
+            Some(name) => host.output_devices().ok().and_then(|devices| find_device_by_name(devices.collect(), name)),
+            None => None,
+        }.or_else(|| host.default_output_device()).ok_or_else(|| {
+            error!("❌ No output device available");
+            AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable)
+        })?;
+        info!("🔊 Using output device: {}", output_device.name().unwrap_or_else(|_| "Unknown".to_string()));
         
-        println!("📋 Available output devices:");
-        if let Ok(devices) = cpal::default_host().output_devices() {
-            for device in devices {
-                if let Ok(name) = device.name() {
-                    println!("  - {}", name);
-                }
-            }
-        }
+        let output_config = output_device.default_output_config()
+            .map_err(|e| {
+                error!("❌ Failed to get output config: {:?}", e);
+                AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable)
+            })?;
+        let mut output_stream_config = output_config.config();
+        output_stream_config.buffer_size = buffer_size_hint(config.buffer_size, output_config.buffer_size());
+        check_output_channel_offset(config.output_channel_offset, output_stream_config.channels as usize)?;
         
-        println!("🎤 Using input device: {}", input_device.name().unwrap_or_else(|_| "Unknown".to_string()));
-        println!("🔊 Using output device: {}", output_device.name().unwrap_or_else(|_| "Unknown".to_string()));
+        let output_rate = output_config.sample_rate().0;
+        buffer_frames.sample_rate.store(output_rate, Ordering::Relaxed);
         
-        // Get supported configs and ensure format compatibility
-        let input_config = input_device.default_input_config()
-            .map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        let output_config = output_device.default_output_config()
-            .map_err(|_e| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
-        
-        println!("🎤 Input config: {:?}", input_config);
-        println!("🔊 Output config: {:?}", output_config);
-        
-        // Create a simple buffer for audio data with size limit
-        let audio_buffer = Arc::new(Mutex::new(Vec::<f32>::with_capacity(4096)));
-        let audio_buffer_clone = Arc::clone(&audio_buffer);
-        
-        // Create input stream with format conversion if needed
-        let input_stream = if input_config.sample_format() == cpal::SampleFormat::I32 {
-            println!("🔄 Converting I32 input to F32 for processing...");
-            // Handle I32 input format
-            input_device.build_input_stream(
-                &input_config.into(),
-                move |data: &[i32], _: &cpal::InputCallbackInfo| {
-                    // Convert I32 to F32 and process
-                    if let Ok(mut delay) = stereo_delay.lock() {
-                        if let Ok(mut buffer) = audio_buffer_clone.lock() {
-                            // Process stereo input (assuming interleaved LRLR...)
-                            for i in (0..data.len()).step_by(2) {
-                                let left_input = if i < data.len() { data[i] as f32 / i32::MAX as f32 } else { 0.0 };
-                                let right_input = if i + 1 < data.len() { data[i + 1] as f32 / i32::MAX as f32 } else { left_input };
-                                
-                                let (left_output, right_output) = delay.process_sample(left_input, right_input);
-                                
-                                // Keep stereo separation and limit buffer size
-                                if buffer.len() < 4096 {
-                                    buffer.push(left_output);
-                                    buffer.push(right_output);
-                                }
-                            }
-                        }
-                    }
-                },
-                move |err| {
-                    eprintln!("Audio input error: {}", err);
+        let audio_ring = Arc::new(AudioRing::new(AUDIO_RING_FRAMES, xruns));
+        let callback_failure = Arc::new(CallbackFailure::new());
+        let output_stream = build_stream_with_fallback("output", &mut output_stream_config, |stream_config| {
+            let callback_failure = Arc::clone(&callback_failure);
+            let mut output_error_log = RateLimitedLog::default();
+            let mut writer = OutputWriter::new(Arc::clone(&audio_ring), Arc::clone(&buffer_frames), stream_config.channels as usize, &config);
+            
+            output_device.build_output_stream(
+                stream_config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| callback_failure.run(|| writer.write(data)),
+                move |err: cpal::StreamError| {
+                    output_error_log.error(&format!("Audio output error: {}", err));
                 },
                 None,
-            ).map_err(AudioProcessorError::AudioDevice)?
+            )
+        })?;
+        output_stream.play().map_err(AudioProcessorError::AudioStream)?;
+        info!("🎵 File input started - the output stream is now active!");
+        
+        Self::feed_file_stream(&config, input, output_rate, &stereo_delay, &is_running, &heartbeat, &buffer_frames, &audio_ring, &callback_failure)
+    }
+    
+    /// Process the looped file one buffer at a time and queue it for the output stream
+    ///
+    /// The file is processed at the configured rate and converted to `output_rate`
+    /// before it is queued. Runs until stopped or until an output callback fails.
+    #[allow(clippy::too_many_arguments)]
+    fn feed_file_stream(
+        config: &AudioConfig,
+        input: InputLoop,
+        output_rate: u32,
+        stereo_delay: &Mutex<StereoDelay>,
+        is_running: &RwLock<bool>,
+        heartbeat: &Heartbeat,
+        buffer_frames: &BufferFrames,
+        audio_ring: &AudioRing,
+        callback_failure: &CallbackFailure,
+    ) -> Result<(), AudioProcessorError> {
+        let mut resampler = if config.sample_rate != output_rate {
+            info!("🔄 Resampling processed audio from {} Hz to {} Hz ({} quality)", config.sample_rate, output_rate, config.resample_quality);
+            buffer_frames.resampled_from.store(config.sample_rate, Ordering::Relaxed);
+            Some(SampleRateConversion::with_quality(config.sample_rate, output_rate, 2, config.resample_quality))
         } else {
-            println!("✅ Using F32 input format directly...");
-            // Handle F32 input format
-            input_device.build_input_stream(
-                &input_config.into(),
-                move |data: &[f32], _: &cpal::InputCallbackInfo| {
-                    // Process input data and send to buffer
-                    if let Ok(mut delay) = stereo_delay.lock() {
-                        if let Ok(mut buffer) = audio_buffer_clone.lock() {
-                            // Process stereo input (assuming interleaved LRLR...)
-                            for i in (0..data.len()).step_by(2) {
-                                let left_input = if i < data.len() { data[i] } else { 0.0 };
-                                let right_input = if i + 1 < data.len() { data[i + 1] } else { left_input };
-                                
-                                let (left_output, right_output) = delay.process_sample(left_input, right_input);
-                                
-                                // Keep stereo separation and limit buffer size
-                                if buffer.len() < 4096 {
-                                    buffer.push(left_output);
-                                    buffer.push(right_output);
-                                }
-                            }
-                        }
-                    }
-                },
-                move |err| {
-                    eprintln!("Audio input error: {}", err);
-                },
-                None,
-            ).map_err(AudioProcessorError::AudioDevice)?
+            None
         };
+        let mut resampled = Vec::<f32>::with_capacity(config.buffer_size.max(1) * 2);
+        buffer_frames.input.store(config.buffer_size.max(1), Ordering::Relaxed);
+        apply_audio_thread_hints(config);
         
-        // Create output stream
-        let output_stream = output_device.build_output_stream(
-            &output_config.into(),
-            move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
-                // Fill output buffer with processed audio from buffer
-                if let Ok(mut buffer) = audio_buffer.lock() {
-                    for sample in data.iter_mut() {
-                        if let Some(processed_sample) = buffer.pop() {
-                            *sample = processed_sample;
-                        } else {
-                            *sample = 0.0; // Silence if no data available
-                        }
-                    }
-                }
-            },
-            move |err| {
-                eprintln!("Audio output error: {}", err);
-            },
-            None,
-        ).map_err(AudioProcessorError::AudioDevice)?;
-        
-        // Start both streams
-        input_stream.play().map_err(AudioProcessorError::AudioStream)?;
-        output_stream.play().map_err(AudioProcessorError::AudioStream)?;
-        
-        println!("🎵 Audio streams started - input and output are now active!");
+        run_input_loop(config, Some(input), stereo_delay, is_running, heartbeat, |processed| {
+            if let Some(resampler) = resampler.as_mut() {
+                resampler.process(processed, &mut resampled);
+                audio_ring.push_interleaved(&resampled);
+                resampled.clear();
+            } else {
+                audio_ring.push_interleaved(processed);
+            }
+            check_stream_failure(callback_failure, stereo_delay)
+        })?;
+        Ok(())
+    }
+    
+    /// Play processed stereo audio on the output device, returning once it has played
+    fn play_test_tone(&self, left: &[f32], right: &[f32]) -> Result<(), AudioProcessorError> {
+        let host = cpal::default_host();
+        let output_device = match &self.core.config.output_device {
+            Some(name) => host.output_devices().ok().and_then(|devices| find_device_by_name(devices.collect(), name)),
+            None => None,
+        }.or_else(|| host.default_output_device())
+            .ok_or(AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
+        let output_config = output_device.default_output_config()
+            .map_err(|_| AudioProcessorError::AudioDevice(cpal::BuildStreamError::DeviceNotAvailable))?;
+        let mut stream_config = output_config.config();
+        let channels = (stream_config.channels as usize).max(1);
+        check_output_channel_offset(self.core.config.output_channel_offset, channels)?;
         
-        // Keep the streams alive while running
-        while *is_running.read() {
-            thread::sleep(Duration::from_millis(100));
+        // The tone is queued whole, converted to the device rate if needed
+        let mut interleaved: Vec<f32> = left.iter().zip(right).flat_map(|(&l, &r)| [l, r]).collect();
+        let output_rate = output_config.sample_rate().0;
+        if output_rate != self.core.config.sample_rate {
+            let mut resampler = SampleRateConversion::with_quality(self.core.config.sample_rate, output_rate, 2, self.core.config.resample_quality);
+            let mut resampled = Vec::with_capacity(interleaved.len() * output_rate as usize / self.core.config.sample_rate as usize + 2);
+            resampler.process(&interleaved, &mut resampled);
+            interleaved = resampled;
         }
+        let frames = interleaved.len() / 2;
+        let tone = Arc::new(AudioRing::new(frames, Arc::new(XrunCounters::default())));
+        tone.push_interleaved(&interleaved);
+        
+        let stream = build_stream_with_fallback("output", &mut stream_config, |stream_config| {
+            let tone = Arc::clone(&tone);
+            let channel_layout = self.core.config.channel_layout;
+            let output_channel_offset = self.core.config.output_channel_offset;
+            let mut planar = Vec::<f32>::new();
+            output_device.build_output_stream(
+                stream_config,
+                move |data: &mut [f32], _: &cpal::OutputCallbackInfo| {
+                    let mut frames = data.chunks_mut(channels);
+                    tone.read_frames(frames.len(), |left, right| {
+                        if let Some(frame) = frames.next() {
+                            stereo_to_frame(left, right, output_channel_offset, frame);
+                        }
+                    });
+                    if channel_layout == ChannelLayout::Planar {
+                        planar.clear();
+                        planar.extend_from_slice(data);
+                        channel_layout.copy_from_interleaved(&planar, channels, data);
+                    }
+                },
+                |err: cpal::StreamError| warn!("⚠️  Test tone output error: {}", err),
+                None,
+            )
+        })?;
+        stream.play().map_err(AudioProcessorError::AudioStream)?;
+        info!("🔊 Playing test tone on {}", output_device.name().unwrap_or_else(|_| "Unknown".to_string()));
+        thread::sleep(Duration::from_secs_f64(frames as f64 / output_rate as f64) + TEST_TONE_MARGIN);
         
         Ok(())
     }
     
-    /// Stop audio processing
-    pub fn stop_audio(&mut self) -> Result<(), AudioProcessorError> {
-        if !*self.is_running.read() {
-            return Err(AudioProcessorError::Processing("Audio not running".to_string()));
-        }
+    /// Update the configuration
+    pub fn update_config(&mut self, new_config: AudioConfig) -> Result<(), AudioProcessorError> {
+        new_config.validate()?;
+        self.core.config = new_config;
+        Ok(())
+    }
+}
+
+impl AudioProcessorTrait for AudioProcessor {
+    fn core(&self) -> &ProcessorCore {
+        &self.core
+    }
+    
+    fn core_mut(&mut self) -> &mut ProcessorCore {
+        &mut self.core
+    }
+    
+    /// Start audio processing
+    fn start_audio(&mut self) -> Result<(), AudioProcessorError> {
+        self.core.prepare_start()?;
         
-        *self.is_running.write() = false;
+        // The input file is read on every start, so a missing file is reported here
+        let config = self.core.config.clone();
+        let input_file = match &config.input_file {
+            Some(path) => Some(InputLoop::open(Path::new(path), config.sample_rate, config.resample_quality)?),
+            None => None,
+        };
         
-        if let Some(thread) = self.audio_thread.take() {
-            thread.join().map_err(|_| {
-                AudioProcessorError::Threading("Failed to join audio thread".to_string())
-            })?;
-        }
+        let stereo_delay = Arc::clone(&self.core.stereo_delay);
+        let is_running = Arc::clone(&self.core.is_running);
+        let heartbeat = Arc::clone(&self.core.heartbeat);
+        let buffer_frames = Arc::clone(&self.buffer_frames);
+        buffer_frames.clear();
+        let xruns = Arc::clone(&self.xruns);
+        let idle = Arc::clone(&self.idle);
+        idle.store(false, Ordering::Relaxed);
         
-        // Reset delay buffers to clear any lingering feedback
-        self.reset_delay()?;
+        self.core.start(move || match input_file {
+            Some(input) => Self::run_file_stream(config, input, stereo_delay, is_running, heartbeat, buffer_frames, xruns),
+            None => Self::run_audio_stream(config, stereo_delay, is_running, heartbeat, buffer_frames, xruns, idle),
+        });
         
         Ok(())
     }
     
-    /// Test audio system with a simple tone
-    pub fn test_audio(&self) -> Result<(), AudioProcessorError> {
-        // Generate test tone (440Hz A note)
-        let duration = 1.0; // 1 second
-        let sample_count = (duration * self.config.sample_rate as f32) as usize;
-        let mut test_tone = Vec::with_capacity(sample_count);
-        
-        for i in 0..sample_count {
-            let t = i as f32 / self.config.sample_rate as f32;
-            let sample = 0.3 * (2.0 * std::f32::consts::PI * 440.0 * t).sin();
-            test_tone.push(sample);
-        }
-        
-        // Process through stereo delay effect
-        let processed_tone = self.process_audio(&test_tone)?;
+    /// Process a test tone through the delay and play it on the output device
+    ///
+    /// Without an output device, or when it cannot be opened, the tone is only
+    /// processed, so the test still checks the effect.
+    fn test_audio_with(&self, params: TestToneParams) -> Result<(), AudioProcessorError> {
+        let (left, right) = params.render(&self.core.config)?;
+        info!(
+            "🧪 Test tone: {:.0} Hz {} for {:.2}s at {:.2} - processed {} frames",
+            params.frequency, params.waveform, params.duration, params.amplitude, left.len()
+        );
         
-        // For now, just print that the test completed
-        // In a real implementation, you would play the audio
-        println!("Audio test completed - processed {} samples", processed_tone.len());
+        if params.play {
+            if let Err(e) = self.play_test_tone(&left, &right) {
+                warn!("⚠️  Test tone not played: {}", e);
+            }
+        }
         
         Ok(())
     }
     
     /// Get overall system status
-    pub fn get_status(&self) -> Result<std::collections::HashMap<String, String>, AudioProcessorError> {
-        let mut status = std::collections::HashMap::new();
-        
-        // Stereo delay parameters (in seconds, not milliseconds)
-        status.insert("left_delay".to_string(), format!("{:.3}", self.config.stereo_delay.left_delay));
-        status.insert("right_delay".to_string(), format!("{:.3}", self.config.stereo_delay.right_delay));
-        status.insert("feedback".to_string(), format!("{:.3}", self.config.stereo_delay.feedback));
-        status.insert("wet_mix".to_string(), format!("{:.3}", self.config.stereo_delay.wet_mix));
-        status.insert("ping_pong".to_string(), self.config.stereo_delay.ping_pong.to_string());
-        status.insert("stereo_width".to_string(), format!("{:.3}", self.config.stereo_delay.stereo_width));
-        status.insert("cross_feedback".to_string(), format!("{:.3}", self.config.stereo_delay.cross_feedback));
-        
-        // Distortion parameters
-        status.insert("distortion_enabled".to_string(), self.config.distortion.enabled.to_string());
-        status.insert("distortion_type".to_string(), self.config.distortion.distortion_type.clone());
-        status.insert("distortion_drive".to_string(), format!("{:.3}", self.config.distortion.drive));
-        status.insert("distortion_mix".to_string(), format!("{:.3}", self.config.distortion.mix));
-        status.insert("distortion_feedback_intensity".to_string(), format!("{:.3}", self.config.distortion.feedback_intensity));
-        
-        // System parameters
-        status.insert("sample_rate".to_string(), self.config.sample_rate.to_string());
-        status.insert("buffer_size".to_string(), self.config.buffer_size.to_string());
-        status.insert("is_running".to_string(), self.is_running.read().to_string());
-        
-        // Add BPM information if available
-        if let Some(bpm) = self.config.stereo_delay.bpm {
-            status.insert("bpm".to_string(), format!("{:.0}", bpm));
+    fn get_status(&self) -> Result<std::collections::HashMap<String, String>, AudioProcessorError> {
+        let mut status = self.core.status(Some(&self.buffer_frames))?;
+        
+        let callback_frames = self.buffer_frames.input.load(Ordering::Relaxed);
+        if callback_frames > 0 {
+            status.insert("negotiated_buffer_size".to_string(), callback_frames.to_string());
         }
+        let xruns = self.xruns.report();
+        status.insert("buffer_underruns".to_string(), xruns.underruns.to_string());
+        status.insert("buffer_overruns".to_string(), xruns.overruns.to_string());
+        let resampled_from = self.buffer_frames.resampled_from.load(Ordering::Relaxed);
+        let resampler = if resampled_from > 0 {
+            format!("{} {} -> {} Hz", self.core.config.resample_quality.resampler_name(), resampled_from, self.buffer_frames.sample_rate.load(Ordering::Relaxed))
+        } else {
+            "off".to_string()
+        };
+        status.insert("resampler".to_string(), resampler);
+        status.insert("idle".to_string(), self.idle.load(Ordering::Relaxed).to_string());
         
         Ok(status)
     }
     
-    /// Get the current configuration
-    pub fn get_config(&self) -> &AudioConfig {
-        &self.config
+    /// Get the smoothed output meter readings
+    fn get_meters(&self) -> Result<std::collections::HashMap<String, f32>, AudioProcessorError> {
+        self.core.meters(Some(&self.buffer_frames))
     }
     
-    /// Update the configuration
-    pub fn update_config(&mut self, new_config: AudioConfig) -> Result<(), AudioProcessorError> {
-        new_config.validate()?;
-        self.config = new_config;
-        Ok(())
+    /// List the input and output devices of the default cpal host
+    fn list_devices(&self) -> DeviceList {
+        list_cpal_devices()
+    }
+    
+    /// Get how often the ring between the callbacks ran dry or overflowed since the last reset
+    fn get_buffer_xruns(&self) -> XrunReport {
+        self.xruns.report()
+    }
+    
+    /// Start counting underruns and overruns afresh
+    fn reset_buffer_xruns(&self) {
+        self.xruns.reset();
     }
 }
 
-impl Drop for AudioProcessor {
-    fn drop(&mut self) {
-        // Ensure audio is stopped when the processor is dropped
-        let _ = self.stop_audio();
+#[cfg(test)]
+impl AudioProcessor {
+    /// Mark the stream as running without opening devices, to test the stop paths
+    pub(crate) fn mark_running(&self) {
+        *self.core.is_running.write() = true;
     }
 }
 
@@ -677,19 +875,233 @@ mod tests {
         let input = vec![0.1, 0.2, 0.3, 0.4, 0.5];
         let output = processor.process_audio(&input).unwrap();
         assert_eq!(output.len(), input.len());
+        assert!(processor.process_audio(&[]).unwrap().is_empty());
     }
     
     #[test]
     fn test_parameter_setting() {
-        let processor = AudioProcessor::new().unwrap();
+        let mut processor = AudioProcessor::new().unwrap();
         let result = processor.set_stereo_delay_parameter("feedback", 0.5);
         assert!(result.is_ok());
     }
     
     #[test]
     fn test_invalid_parameter() {
-        let processor = AudioProcessor::new().unwrap();
+        let mut processor = AudioProcessor::new().unwrap();
         let result = processor.set_stereo_delay_parameter("invalid_param", 0.5);
         assert!(result.is_err());
     }
+    
+    #[test]
+    fn test_macro_moves_mapped_parameters_to_their_endpoints() {
+        use crate::macro_control::{MacroCurve, MacroMapping};
+        
+        let mut config = AudioConfig::default();
+        config.macro_control.mappings = vec![
+            MacroMapping::new("feedback", 0.1, 0.7, MacroCurve::Exponential),
+            MacroMapping::new("wet_mix", 0.2, 0.9, MacroCurve::Linear),
+            MacroMapping::new("stereo_width", 0.0, 1.0, MacroCurve::Logarithmic),
+        ];
+        let mappings = config.macro_control.mappings.clone();
+        let mut processor = AudioProcessor::with_config(config).unwrap();
+        
+        for position in [0.0, 1.0] {
+            processor.set_stereo_delay_parameter("macro", position).unwrap();
+            let status = processor.get_status().unwrap();
+            for mapping in &mappings {
+                let expected = if position == 0.0 { mapping.min } else { mapping.max };
+                let actual: f32 = status[&mapping.parameter].parse().unwrap();
+                assert!(
+                    (actual - expected).abs() < 1e-3,
+                    "{} at macro {}: {} != {}", mapping.parameter, position, actual, expected
+                );
+            }
+        }
+        
+        assert!(processor.set_stereo_delay_parameter("macro", 1.5).is_err());
+    }
+    
+    #[test]
+    fn test_state_round_trips_through_export_and_import() {
+        let mut processor = AudioProcessor::new().unwrap();
+        for (param, value) in [
+            ("feedback", 0.65),
+            ("wet_mix", 0.4),
+            ("mix_law", 1.0),
+            ("ping_pong", 0.0),
+            ("feedback_tilt", -0.5),
+            ("distortion_drive", 0.8),
+            ("distortion_placement", 2.0),
+            ("comp_enabled", 1.0),
+            ("comp_ratio", 8.0),
+            ("smoothing_feedback", 75.0),
+            ("tails", 1.0),
+            ("bpm", 90.0),
+        ] {
+            processor.set_stereo_delay_parameter(param, value).unwrap();
+        }
+        let expected = processor.get_status().unwrap();
+        let state = processor.export_state().unwrap();
+        
+        // Reset everything to the defaults
+        let defaults = serde_json::to_string(&AudioConfig::default()).unwrap();
+        processor.import_state(&defaults).unwrap();
+        assert_ne!(processor.get_status().unwrap(), expected);
+        
+        processor.import_state(&state).unwrap();
+        assert_eq!(processor.get_status().unwrap(), expected);
+        
+        assert!(processor.import_state("{ not json").is_err());
+        assert_eq!(processor.get_status().unwrap(), expected);
+    }
+    
+    #[test]
+    fn test_panicking_audio_thread_clears_running_flag() {
+        let processor = AudioProcessor::new().unwrap();
+        *processor.core.is_running.write() = true;
+        
+        // Panic while holding the delay lock, like an indexing bug in the callback would
+        let stereo_delay = Arc::clone(&processor.core.stereo_delay);
+        let handle = processor.core.spawn_audio_thread(move || {
+            let _delay = stereo_delay.lock().unwrap();
+            #[allow(clippy::useless_vec)]
+            let buffer = vec![0.0_f32; 4];
+            let index = buffer.len();
+            let _sample = buffer[index];
+            Ok(())
+        });
+        handle.join().unwrap();
+        
+        assert!(!*processor.core.is_running.read());
+        let status = processor.get_status().unwrap();
+        assert_eq!(status["is_running"], "false");
+        assert!(status["last_error"].contains("panicked"), "{}", status["last_error"]);
+        assert!(!processor.get_health().is_healthy());
+    }
+    
+    #[test]
+    fn test_panicking_stream_callback_stops_audio_with_the_error() {
+        let mut processor = AudioProcessor::new().unwrap();
+        *processor.core.is_running.write() = true;
+        
+        // The callback panics on its own thread while holding the delay lock,
+        // the audio thread's keep-alive loop has to notice and stop
+        let stereo_delay = Arc::clone(&processor.core.stereo_delay);
+        let is_running = Arc::clone(&processor.core.is_running);
+        let handle = processor.core.spawn_audio_thread(move || {
+            let callback_failure = Arc::new(CallbackFailure::new());
+            let callback = {
+                let callback_failure = Arc::clone(&callback_failure);
+                let stereo_delay = Arc::clone(&stereo_delay);
+                move || callback_failure.run(|| {
+                    let mut delay = stereo_delay.lock().unwrap();
+                    let _ = delay.process_sample(0.0, 0.0);
+                    #[allow(clippy::useless_vec)]
+                    let buffer = vec![0.0_f32; 4];
+                    let index = buffer.len();
+                    let _sample = buffer[index];
+                })
+            };
+            thread::spawn(callback).join().unwrap();
+            
+            while *is_running.read() {
+                check_stream_failure(&callback_failure, &stereo_delay)?;
+                thread::sleep(Duration::from_millis(1));
+            }
+            Ok(())
+        });
+        handle.join().unwrap();
+        
+        assert!(!*processor.core.is_running.read());
+        let status = processor.get_status().unwrap();
+        assert!(status["last_error"].contains("stream callback panicked"), "{}", status["last_error"]);
+        assert!(status["last_error"].contains("index out of bounds"), "{}", status["last_error"]);
+        
+        // The poisoned lock is cleared, so parameters work again
+        processor.set_stereo_delay_parameter("wet_mix", 0.4).unwrap();
+    }
+    
+    #[test]
+    fn test_file_stream_plays_the_processed_file_through_the_ring() {
+        let config = AudioConfig {
+            buffer_size: 256,
+            output_channel_offset: 1,
+            ..Default::default()
+        };
+        let processor = AudioProcessor::with_config(config.clone()).unwrap();
+        let tone: Vec<f32> = (0..1000).map(|i| 0.5 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 48000.0).sin()).collect();
+        let input = || InputLoop::new(tone.clone(), tone.iter().map(|s| -s).collect()).unwrap();
+        *processor.core.is_running.write() = true;
+        
+        // The feeding side of run_file_stream, at the output device's rate
+        let audio_ring = Arc::new(AudioRing::new(AUDIO_RING_FRAMES, Arc::clone(&processor.xruns)));
+        let callback_failure = CallbackFailure::new();
+        thread::scope(|scope| {
+            let feeder = scope.spawn(|| AudioProcessor::feed_file_stream(
+                &config,
+                input(),
+                config.sample_rate,
+                &processor.core.stereo_delay,
+                &processor.core.is_running,
+                &processor.core.heartbeat,
+                &processor.buffer_frames,
+                &audio_ring,
+                &callback_failure,
+            ));
+            thread::sleep(Duration::from_millis(30));
+            *processor.core.is_running.write() = false;
+            feeder.join().unwrap().unwrap();
+        });
+        assert!(processor.core.heartbeat.status(crate::health::HEARTBEAT_TIMEOUT).is_healthy());
+        assert_eq!(processor.buffer_frames.input.load(Ordering::Relaxed), 256);
+        
+        // The output callback plays the queued frames in order on the offset channels
+        let frames = audio_ring.len();
+        assert!(frames >= 256 && frames.is_multiple_of(256), "{} frames queued", frames);
+        let mut writer = OutputWriter::new(Arc::clone(&audio_ring), Arc::clone(&processor.buffer_frames), 3, &config);
+        let mut data = vec![1.0f32; frames * 3];
+        writer.write(&mut data);
+        assert_eq!(processor.buffer_frames.output.load(Ordering::Relaxed), frames);
+        assert!(audio_ring.is_empty());
+        
+        let mut reference = StereoDelay::from_config(&config);
+        let mut expected_input = input();
+        for frame in data.chunks_exact(3) {
+            let (left_input, right_input) = expected_input.next_frame();
+            let (left, right) = reference.process_sample(left_input, right_input);
+            assert_eq!(frame, [0.0, left, right]);
+        }
+        assert!(data.iter().any(|&s| s != 0.0));
+        
+        // A failed output callback stops the feeding with its error
+        *processor.core.is_running.write() = true;
+        callback_failure.run(|| panic!("output callback failed"));
+        let result = AudioProcessor::feed_file_stream(
+            &config,
+            input(),
+            config.sample_rate,
+            &processor.core.stereo_delay,
+            &processor.core.is_running,
+            &processor.core.heartbeat,
+            &processor.buffer_frames,
+            &audio_ring,
+            &callback_failure,
+        );
+        assert!(matches!(result, Err(AudioProcessorError::Processing(message)) if message.contains("output callback failed")));
+    }
+    
+    #[test]
+    fn test_poisoned_delay_lock_is_a_stream_failure() {
+        let processor = AudioProcessor::new().unwrap();
+        let stereo_delay = Arc::clone(&processor.core.stereo_delay);
+        let failure = CallbackFailure::new();
+        assert!(check_stream_failure(&failure, &stereo_delay).is_ok());
+        
+        let poisoner = Arc::clone(&stereo_delay);
+        let _ = thread::spawn(move || {
+            let _delay = poisoner.lock().unwrap();
+            panic!("poison the delay");
+        }).join();
+        assert!(matches!(check_stream_failure(&failure, &stereo_delay), Err(AudioProcessorError::Threading(_))));
+    }
 }
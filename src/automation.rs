@@ -0,0 +1,271 @@
+use serde::{Deserialize, Serialize};
+use std::time::{Duration, Instant};
+
+use crate::error::AudioProcessorError;
+
+/// How often recorded automation is checked for events that are due
+pub const AUTOMATION_TICK: Duration = Duration::from_millis(10);
+
+/// One recorded parameter change
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutomationEvent {
+    /// Beats since recording started
+    pub time: f64,
+    
+    /// Parameter name as accepted by `set_stereo_delay_parameter`
+    pub parameter: String,
+    pub value: f32,
+}
+
+/// What the automation is doing
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum AutomationState {
+    #[default]
+    Stopped,
+    Recording,
+    Playing,
+}
+
+impl std::fmt::Display for AutomationState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AutomationState::Stopped => "stopped",
+            AutomationState::Recording => "recording",
+            AutomationState::Playing => "playing",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Automation command from the CLI or the web interface
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AutomationCommand {
+    /// Start a new recording, replacing the current one
+    Record,
+    /// Loop the recording from its start
+    Play,
+    /// Stop recording or playing
+    Stop,
+    /// Stop and forget the recording
+    Clear,
+}
+
+impl AutomationCommand {
+    /// Look up a command by name, `None` for unknown names
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_lowercase().as_str() {
+            "record" => Some(AutomationCommand::Record),
+            "play" => Some(AutomationCommand::Play),
+            "stop" => Some(AutomationCommand::Stop),
+            "clear" => Some(AutomationCommand::Clear),
+            _ => None,
+        }
+    }
+}
+
+/// Recorded automation as served by `GET /api/automation`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct AutomationStatus {
+    pub state: AutomationState,
+    
+    /// Length of the recording in beats
+    pub length: f64,
+    pub events: Vec<AutomationEvent>,
+}
+
+/// Recorder and player of parameter changes on a clock that follows the tempo
+///
+/// Event times are kept in beats, so a recording played back at another tempo
+/// stays in time with it. Playback loops over the recorded length, like a looper.
+/// Every call takes the current time, so the clock can be driven from tests.
+pub struct Automation {
+    events: Vec<AutomationEvent>,
+    state: AutomationState,
+    length: f64,
+    
+    // Clock position in beats, advanced at the tempo in force since the last update
+    bpm: f32,
+    position: f64,
+    last_update: Instant,
+    next_event: usize,
+}
+
+impl Automation {
+    /// Create an empty automation at a tempo
+    pub fn new(bpm: f32) -> Self {
+        Self {
+            events: Vec::new(),
+            state: AutomationState::Stopped,
+            length: 0.0,
+            bpm,
+            position: 0.0,
+            last_update: Instant::now(),
+            next_event: 0,
+        }
+    }
+    
+    /// Run a command from the CLI or the web interface
+    pub fn command(&mut self, command: AutomationCommand, now: Instant) -> Result<(), AudioProcessorError> {
+        match command {
+            AutomationCommand::Record => self.start_recording(now),
+            AutomationCommand::Play => self.start_playback(now)?,
+            AutomationCommand::Stop => self.stop(now),
+            AutomationCommand::Clear => self.clear(now),
+        }
+        Ok(())
+    }
+    
+    /// Start a new recording, replacing the current one
+    pub fn start_recording(&mut self, now: Instant) {
+        self.events.clear();
+        self.length = 0.0;
+        self.restart_clock(now);
+        self.state = AutomationState::Recording;
+    }
+    
+    /// Play the recording from its start, looping over its length
+    pub fn start_playback(&mut self, now: Instant) -> Result<(), AudioProcessorError> {
+        self.stop(now);
+        if self.events.is_empty() || self.length <= 0.0 {
+            return Err(AudioProcessorError::Configuration("No automation has been recorded".to_string()));
+        }
+        self.restart_clock(now);
+        self.state = AutomationState::Playing;
+        Ok(())
+    }
+    
+    /// Stop recording or playing; a recording ends here
+    pub fn stop(&mut self, now: Instant) {
+        if self.state == AutomationState::Recording {
+            self.advance(now);
+            self.length = self.position;
+        }
+        self.state = AutomationState::Stopped;
+    }
+    
+    /// Stop and forget the recording
+    pub fn clear(&mut self, now: Instant) {
+        self.stop(now);
+        self.events.clear();
+        self.length = 0.0;
+    }
+    
+    /// Change the tempo of the clock from now on
+    pub fn set_bpm(&mut self, bpm: f32, now: Instant) {
+        self.advance(now);
+        self.bpm = bpm;
+    }
+    
+    /// Record a parameter change, if recording
+    pub fn record(&mut self, parameter: &str, value: f32, now: Instant) {
+        if self.state != AutomationState::Recording {
+            return;
+        }
+        self.advance(now);
+        self.events.push(AutomationEvent {
+            time: self.position,
+            parameter: parameter.to_string(),
+            value,
+        });
+    }
+    
+    /// Take the events that have come due since the last call, if playing
+    pub fn due_events(&mut self, now: Instant) -> Vec<AutomationEvent> {
+        if self.state != AutomationState::Playing {
+            return Vec::new();
+        }
+        self.advance(now);
+        
+        let mut due = self.take_events_until(self.position);
+        if self.position >= self.length {
+            // Finish the pass and carry on from the same point of the next one
+            due.extend(self.take_events_until(self.length));
+            self.position = if self.length > 0.0 { self.position % self.length } else { 0.0 };
+            self.next_event = 0;
+            due.extend(self.take_events_until(self.position));
+        }
+        due
+    }
+    
+    /// Get the current state
+    pub fn state(&self) -> AutomationState {
+        self.state
+    }
+    
+    /// Get the recording and what the automation is doing
+    pub fn status(&self) -> AutomationStatus {
+        AutomationStatus {
+            state: self.state,
+            length: self.length,
+            events: self.events.clone(),
+        }
+    }
+    
+    fn restart_clock(&mut self, now: Instant) {
+        self.position = 0.0;
+        self.last_update = now;
+        self.next_event = 0;
+    }
+    
+    fn advance(&mut self, now: Instant) {
+        let elapsed = now.saturating_duration_since(self.last_update).as_secs_f64();
+        self.position += elapsed * self.bpm as f64 / 60.0;
+        self.last_update = now;
+    }
+    
+    fn take_events_until(&mut self, time: f64) -> Vec<AutomationEvent> {
+        let start = self.next_event;
+        while self.next_event < self.events.len() && self.events[self.next_event].time <= time {
+            self.next_event += 1;
+        }
+        self.events[start..self.next_event].to_vec()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn parameters(events: &[AutomationEvent]) -> Vec<(&str, f32)> {
+        events.iter().map(|event| (event.parameter.as_str(), event.value)).collect()
+    }
+    
+    #[test]
+    fn test_playback_reproduces_recorded_changes_in_time() {
+        let seconds = |s: f64| Duration::from_secs_f64(s);
+        let start = Instant::now();
+        let mut automation = Automation::new(120.0);
+        
+        // Two beats a second at 120 BPM
+        automation.command(AutomationCommand::Record, start).unwrap();
+        automation.record("feedback", 0.5, start + seconds(0.5));
+        automation.record("wet_mix", 0.8, start + seconds(1.0));
+        automation.record("feedback", 0.2, start + seconds(1.5));
+        automation.command(AutomationCommand::Stop, start + seconds(2.0)).unwrap();
+        let status = automation.status();
+        assert_eq!(status.length, 4.0);
+        assert_eq!(status.events.iter().map(|event| event.time).collect::<Vec<_>>(), vec![1.0, 2.0, 3.0]);
+        
+        // Nothing is recorded while stopped
+        automation.record("feedback", 0.9, start + seconds(2.5));
+        assert_eq!(automation.status().events.len(), 3);
+        
+        let play = start + seconds(10.0);
+        automation.command(AutomationCommand::Play, play).unwrap();
+        assert!(automation.due_events(play + seconds(0.49)).is_empty());
+        assert_eq!(parameters(&automation.due_events(play + seconds(0.5))), vec![("feedback", 0.5)]);
+        assert!(automation.due_events(play + seconds(0.9)).is_empty());
+        assert_eq!(parameters(&automation.due_events(play + seconds(1.6))), vec![("wet_mix", 0.8), ("feedback", 0.2)]);
+        
+        // The recording loops, and follows a tempo change
+        assert_eq!(parameters(&automation.due_events(play + seconds(2.5))), vec![("feedback", 0.5)]);
+        automation.set_bpm(240.0, play + seconds(2.5));
+        assert!(automation.due_events(play + seconds(2.74)).is_empty());
+        assert_eq!(parameters(&automation.due_events(play + seconds(2.75))), vec![("wet_mix", 0.8)]);
+        
+        automation.command(AutomationCommand::Clear, play + seconds(3.0)).unwrap();
+        assert_eq!(automation.state(), AutomationState::Stopped);
+        assert!(automation.command(AutomationCommand::Play, play + seconds(3.0)).is_err());
+    }
+}
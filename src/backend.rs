@@ -0,0 +1,140 @@
+use std::fmt;
+use log::{info, warn};
+use crate::audio_processor::AudioProcessor;
+use crate::config::AudioConfig;
+use crate::error::AudioProcessorError;
+use crate::null_processor::NullProcessor;
+use crate::AudioProcessorTrait;
+#[cfg(target_os = "linux")]
+use crate::alsa_processor::AlsaAudioProcessor;
+
+/// Audio backend used to talk to the sound card
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioBackend {
+    /// Direct ALSA hardware access (Linux only)
+    Alsa,
+    /// Cross-platform access through cpal
+    Cpal,
+    /// No device at all, the effect runs on synthetic input
+    Null,
+}
+
+impl AudioBackend {
+    /// Get the name used in logs and status
+    pub fn name(&self) -> &'static str {
+        match self {
+            AudioBackend::Alsa => "alsa",
+            AudioBackend::Cpal => "cpal",
+            AudioBackend::Null => "null",
+        }
+    }
+    
+    /// Backends built into this binary, in the order they are tried
+    ///
+    /// The null backend is not part of the order; it is only the last resort.
+    pub fn fallback_order() -> Vec<AudioBackend> {
+        if cfg!(target_os = "linux") {
+            vec![AudioBackend::Alsa, AudioBackend::Cpal]
+        } else {
+            vec![AudioBackend::Cpal]
+        }
+    }
+}
+
+impl fmt::Display for AudioBackend {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.name())
+    }
+}
+
+/// Pick the first backend in `order` that can open its devices
+///
+/// `probe` returns why a backend cannot be used. Every attempt is logged.
+pub fn select_backend<F>(order: &[AudioBackend], mut probe: F) -> Option<AudioBackend>
+where
+    F: FnMut(AudioBackend) -> Result<(), String>,
+{
+    for &backend in order {
+        info!("🎵 Trying {} audio backend...", backend);
+        match probe(backend) {
+            Ok(()) => {
+                info!("✅ Using {} audio backend", backend);
+                return Some(backend);
+            }
+            Err(reason) => warn!("⚠️  {} audio backend unavailable: {}", backend, reason),
+        }
+    }
+    None
+}
+
+/// Check that a backend can open the configured devices
+fn probe_backend(backend: AudioBackend, config: &AudioConfig) -> Result<(), String> {
+    match backend {
+        #[cfg(target_os = "linux")]
+        AudioBackend::Alsa => AlsaAudioProcessor::probe_devices(config).map_err(|e| e.to_string()),
+        #[cfg(not(target_os = "linux"))]
+        AudioBackend::Alsa => Err("ALSA is only available on Linux".to_string()),
+        AudioBackend::Cpal => AudioProcessor::probe_devices(config).map_err(|e| e.to_string()),
+        AudioBackend::Null => Ok(()),
+    }
+}
+
+/// Create a processor on the first backend whose devices can be opened
+///
+/// On Linux ALSA is tried first and cpal is the fallback. When no backend can
+/// open a device the null backend is used, so the effect still runs without
+/// sound. An input file is only played by cpal, whose output stream is fed from
+/// a queue, so ALSA is skipped then.
+pub fn open_processor(config: AudioConfig) -> Result<Box<dyn AudioProcessorTrait + Send>, AudioProcessorError> {
+    let mut order = AudioBackend::fallback_order();
+    if config.input_file.is_some() {
+        order.retain(|&backend| backend != AudioBackend::Alsa);
+    }
+    let backend = select_backend(&order, |backend| probe_backend(backend, &config))
+        .unwrap_or_else(|| {
+            warn!("⚠️  No audio backend could open the audio devices - running without sound");
+            AudioBackend::Null
+        });
+    
+    match backend {
+        #[cfg(target_os = "linux")]
+        AudioBackend::Alsa => Ok(Box::new(AlsaAudioProcessor::with_config(config)?)),
+        #[cfg(not(target_os = "linux"))]
+        AudioBackend::Alsa => unreachable!("ALSA is not in the fallback order"),
+        AudioBackend::Cpal => Ok(Box::new(AudioProcessor::with_config(config)?)),
+        AudioBackend::Null => open_null_processor(config),
+    }
+}
+
+/// Create a processor that runs the effect without opening any device
+pub fn open_null_processor(config: AudioConfig) -> Result<Box<dyn AudioProcessorTrait + Send>, AudioProcessorError> {
+    info!("🎵 Using null audio backend - no audio device is opened");
+    Ok(Box::new(NullProcessor::with_config(config)?))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_select_backend_falls_back_in_order() {
+        let order = [AudioBackend::Alsa, AudioBackend::Cpal];
+        let select = |alsa_ok: bool, cpal_ok: bool| {
+            let mut tried = Vec::new();
+            let selected = select_backend(&order, |backend| {
+                tried.push(backend);
+                let ok = match backend {
+                    AudioBackend::Alsa => alsa_ok,
+                    AudioBackend::Cpal => cpal_ok,
+                    AudioBackend::Null => unreachable!("the null backend is never probed"),
+                };
+                if ok { Ok(()) } else { Err("device busy".to_string()) }
+            });
+            (selected, tried)
+        };
+        
+        assert_eq!(select(true, true), (Some(AudioBackend::Alsa), vec![AudioBackend::Alsa]));
+        assert_eq!(select(false, true), (Some(AudioBackend::Cpal), vec![AudioBackend::Alsa, AudioBackend::Cpal]));
+        assert_eq!(select(false, false), (None, vec![AudioBackend::Alsa, AudioBackend::Cpal]));
+    }
+}
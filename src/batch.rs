@@ -0,0 +1,174 @@
+use std::fs;
+use std::path::{Path, PathBuf};
+use crate::config::AudioConfig;
+use crate::delay::StereoDelay;
+use crate::error::AudioProcessorError;
+
+/// Outcome of processing a directory of WAV files
+#[derive(Debug, Default)]
+pub struct BatchSummary {
+    /// Files that were processed and written
+    pub succeeded: Vec<PathBuf>,
+    /// Files that failed, with the reason
+    pub failed: Vec<(PathBuf, String)>,
+}
+
+impl BatchSummary {
+    /// Check whether every file was processed
+    pub fn is_success(&self) -> bool {
+        self.failed.is_empty()
+    }
+    
+    /// Get a one-line description of the batch result
+    pub fn get_info(&self) -> String {
+        format!(
+            "{} of {} files processed, {} failed",
+            self.succeeded.len(),
+            self.succeeded.len() + self.failed.len(),
+            self.failed.len()
+        )
+    }
+}
+
+/// Read a mono or stereo WAV file as interleaved -1.0..1.0 samples
+pub fn read_wav(path: &Path) -> Result<(hound::WavSpec, Vec<f32>), AudioProcessorError> {
+    let wav_error = |e: hound::Error| AudioProcessorError::Processing(format!("{}: {}", path.display(), e));
+    
+    let mut reader = hound::WavReader::open(path).map_err(wav_error)?;
+    let spec = reader.spec();
+    let channels = spec.channels as usize;
+    if channels == 0 || channels > 2 {
+        return Err(AudioProcessorError::Processing(format!(
+            "{}: unsupported channel count {}", path.display(), channels
+        )));
+    }
+    
+    let samples = match spec.sample_format {
+        hound::SampleFormat::Float => reader.samples::<f32>().collect::<Result<_, _>>().map_err(wav_error)?,
+        hound::SampleFormat::Int => {
+            let scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+            reader
+                .samples::<i32>()
+                .map(|sample| sample.map(|s| s as f32 / scale))
+                .collect::<Result<_, _>>()
+                .map_err(wav_error)?
+        }
+    };
+    Ok((spec, samples))
+}
+
+/// Process a single WAV file through the stereo delay and write the result
+///
+/// The delay runs at the file's own sample rate. Mono files are fed to both
+/// channels; the output is always stereo in the input's sample format.
+pub fn process_wav_file(input_path: &Path, output_path: &Path, config: &AudioConfig) -> Result<(), AudioProcessorError> {
+    let wav_error = |e: hound::Error| AudioProcessorError::Processing(format!("{}: {}", input_path.display(), e));
+    let (spec, samples) = read_wav(input_path)?;
+    let channels = spec.channels as usize;
+    
+    let mut file_config = config.clone();
+    file_config.sample_rate = spec.sample_rate;
+    let mut delay = StereoDelay::from_config(&file_config);
+    
+    let output_spec = hound::WavSpec { channels: 2, ..spec };
+    let mut writer = hound::WavWriter::create(output_path, output_spec).map_err(wav_error)?;
+    let int_scale = (1i64 << (spec.bits_per_sample - 1)) as f32;
+    
+    for frame in samples.chunks(channels) {
+        let left_input = frame[0];
+        let right_input = if channels == 2 { frame[1] } else { left_input };
+        let (left_output, right_output) = delay.process_sample(left_input, right_input);
+        
+        for output in [left_output, right_output] {
+            match spec.sample_format {
+                hound::SampleFormat::Float => writer.write_sample(output).map_err(wav_error)?,
+                hound::SampleFormat::Int => {
+                    let value = (output.clamp(-1.0, 1.0) * (int_scale - 1.0)).round() as i32;
+                    writer.write_sample(value).map_err(wav_error)?
+                }
+            }
+        }
+    }
+    
+    writer.finalize().map_err(wav_error)?;
+    Ok(())
+}
+
+/// Process every `.wav` file in `input_dir` into `output_dir`, keeping the file names
+///
+/// Files are processed one after another with a fresh delay each. A failing file is
+/// recorded in the summary and does not stop the rest of the batch.
+pub fn process_directory(input_dir: &Path, output_dir: &Path, config: &AudioConfig) -> Result<BatchSummary, AudioProcessorError> {
+    config.validate()?;
+    fs::create_dir_all(output_dir)?;
+    
+    let mut input_files: Vec<PathBuf> = fs::read_dir(input_dir)?
+        .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+        .filter(|path| {
+            path.is_file() && path.extension().map(|ext| ext.eq_ignore_ascii_case("wav")).unwrap_or(false)
+        })
+        .collect();
+    input_files.sort();
+    
+    let mut summary = BatchSummary::default();
+    for input_path in input_files {
+        let file_name = match input_path.file_name() {
+            Some(name) => name.to_owned(),
+            None => continue,
+        };
+        let output_path = output_dir.join(file_name);
+        
+        match process_wav_file(&input_path, &output_path, config) {
+            Ok(()) => summary.succeeded.push(input_path),
+            Err(e) => summary.failed.push((input_path, e.to_string())),
+        }
+    }
+    
+    Ok(summary)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn write_test_wav(path: &Path, channels: u16, frames: usize) {
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(path, spec).unwrap();
+        for i in 0..frames {
+            let sample = (0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin() * i16::MAX as f32) as i16;
+            for _ in 0..channels {
+                writer.write_sample(sample).unwrap();
+            }
+        }
+        writer.finalize().unwrap();
+    }
+    
+    #[test]
+    fn test_process_directory_writes_every_wav() {
+        let root = std::env::temp_dir().join(format!("batch_test_{}", std::process::id()));
+        let input_dir = root.join("stems");
+        let output_dir = root.join("processed");
+        fs::create_dir_all(&input_dir).unwrap();
+        
+        write_test_wav(&input_dir.join("guitar.wav"), 1, 4410);
+        write_test_wav(&input_dir.join("keys.wav"), 2, 4410);
+        fs::write(input_dir.join("notes.txt"), "not audio").unwrap();
+        
+        let summary = process_directory(&input_dir, &output_dir, &AudioConfig::default()).unwrap();
+        assert!(summary.is_success(), "{:?}", summary.failed);
+        assert_eq!(summary.succeeded.len(), 2);
+        
+        for name in ["guitar.wav", "keys.wav"] {
+            let reader = hound::WavReader::open(output_dir.join(name)).unwrap();
+            assert_eq!(reader.spec().channels, 2);
+            assert_eq!(reader.duration(), 4410);
+        }
+        
+        fs::remove_dir_all(&root).unwrap();
+    }
+}
@@ -0,0 +1,495 @@
+use crate::distortion::{CrossFeedbackDistortion, DistortionType};
+use crate::filters::TiltEq;
+use crate::AudioProcessorError;
+
+/// Identifiers of the stages an effect chain can be built from
+pub const STAGE_IDS: [&str; 3] = ["eq", "distortion", "delay"];
+
+/// Stages every effect chain must contain
+pub const REQUIRED_STAGES: [&str; 1] = ["delay"];
+
+/// Effect chain used when the configuration does not set one
+pub fn default_effect_chain() -> Vec<String> {
+    vec!["delay".to_string()]
+}
+
+/// A stereo effect that can run as one stage of the effect chain
+///
+/// Effects keep their typed setters; `set_param` is the uniform way to reach them
+/// by name, with booleans as 0.0/1.0 and enums as their `to_parameter` value.
+/// In an `EffectChain` the "mix" parameter of every stage is the chain's, see
+/// `EffectChain::set_param`.
+pub trait AudioStage: Send {
+    /// Identifier of the stage, as used in the `effect_chain` configuration
+    fn name(&self) -> &'static str;
+    
+    /// Process one stereo sample
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32);
+    
+    /// Clear the internal state, keeping the parameters
+    fn reset(&mut self);
+    
+    /// Set a parameter by name
+    fn set_param(&mut self, param: &str, value: f32) -> Result<(), AudioProcessorError>;
+    
+    /// Process a block of left and right samples in place
+    ///
+    /// An empty block is left as it is; blocks of different lengths are an error
+    /// and are not processed at all.
+    fn process_block(&mut self, left: &mut [f32], right: &mut [f32]) -> Result<(), AudioProcessorError> {
+        check_stereo_lengths(left.len(), right.len())?;
+        for (left, right) in left.iter_mut().zip(right.iter_mut()) {
+            (*left, *right) = self.process(*left, *right);
+        }
+        Ok(())
+    }
+}
+
+/// Check that the left and right buffers of a stereo block have the same length
+pub fn check_stereo_lengths(left: usize, right: usize) -> Result<(), AudioProcessorError> {
+    if left != right {
+        return Err(AudioProcessorError::Processing(format!(
+            "left and right buffers differ in length ({} and {} samples)",
+            left, right
+        )));
+    }
+    Ok(())
+}
+
+/// Error returned by `AudioStage::set_param` for a parameter the stage does not have
+pub fn unknown_parameter(param: &str, value: f32) -> AudioProcessorError {
+    AudioProcessorError::InvalidParameter {
+        param: param.to_string(),
+        value,
+        min: 0.0,
+        max: 1.0,
+    }
+}
+
+/// Tone EQ (tilt) on both channels
+pub struct EqStage {
+    left: TiltEq,
+    right: TiltEq,
+}
+
+impl EqStage {
+    /// Create a new tone EQ stage
+    pub fn new(tilt: f32, sample_rate: u32) -> Self {
+        Self {
+            left: TiltEq::new(tilt, sample_rate),
+            right: TiltEq::new(tilt, sample_rate),
+        }
+    }
+    
+    /// Set the tilt (-1.0 darker to 1.0 brighter, 0.0 is flat)
+    pub fn set_tilt(&mut self, tilt: f32) {
+        self.left.set_amount(tilt);
+        self.right.set_amount(tilt);
+    }
+    
+    /// Get the tilt
+    pub fn get_tilt(&self) -> f32 {
+        self.left.get_amount()
+    }
+    
+    /// Process one stereo sample
+    pub fn process_stereo(&mut self, left: f32, right: f32) -> (f32, f32) {
+        (self.left.process(left), self.right.process(right))
+    }
+    
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.left.reset();
+        self.right.reset();
+    }
+}
+
+impl AudioStage for EqStage {
+    fn name(&self) -> &'static str {
+        "eq"
+    }
+    
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        self.process_stereo(left, right)
+    }
+    
+    fn reset(&mut self) {
+        EqStage::reset(self);
+    }
+    
+    fn set_param(&mut self, param: &str, value: f32) -> Result<(), AudioProcessorError> {
+        match param {
+            "tilt" => self.set_tilt(value),
+            _ => return Err(unknown_parameter(param, value)),
+        }
+        Ok(())
+    }
+}
+
+/// Create the stage for an identifier from `STAGE_IDS`
+///
+/// The delay stage is the `StereoDelay` running the chain, so it has no separate
+/// stage and `None` is returned for it as for unknown identifiers. Stages come
+/// fully wet, the chain blends them with their input.
+pub fn stage_from_id(id: &str, sample_rate: u32) -> Option<Box<dyn AudioStage>> {
+    match id {
+        "eq" => Some(Box::new(EqStage::new(0.0, sample_rate))),
+        "distortion" => Some(Box::new(CrossFeedbackDistortion::new(
+            true,
+            DistortionType::SoftClip,
+            0.3,
+            1.0,
+            sample_rate,
+        ))),
+        _ => None,
+    }
+}
+
+/// Check that a chain only names known stages, each once, and has the required ones
+pub fn validate_effect_chain(ids: &[String]) -> Result<(), AudioProcessorError> {
+    for (i, id) in ids.iter().enumerate() {
+        if !STAGE_IDS.contains(&id.as_str()) {
+            return Err(AudioProcessorError::Configuration(format!(
+                "Unknown effect chain stage '{}' (known stages: {})",
+                id,
+                STAGE_IDS.join(", ")
+            )));
+        }
+        if ids[..i].contains(id) {
+            return Err(AudioProcessorError::Configuration(format!(
+                "Effect chain stage '{}' is listed more than once",
+                id
+            )));
+        }
+    }
+    
+    for required in REQUIRED_STAGES {
+        if !ids.iter().any(|id| id == required) {
+            return Err(AudioProcessorError::Configuration(format!(
+                "Effect chain is missing the required '{}' stage",
+                required
+            )));
+        }
+    }
+    
+    Ok(())
+}
+
+/// Ordered stages the signal runs through around the delay
+///
+/// The delay itself is not a stage of the chain: the `StereoDelay` owning it runs
+/// the stages before the delay, then its own processing, then the stages after it.
+#[derive(Default)]
+pub struct EffectChain {
+    before_delay: Vec<Box<dyn AudioStage>>,
+    after_delay: Vec<Box<dyn AudioStage>>,
+    // Stages kept in the chain but left out of the processing
+    bypassed: Vec<&'static str>,
+    // Wet/dry mix of the stages not fully wet
+    mixes: Vec<(&'static str, f32)>,
+}
+
+impl EffectChain {
+    /// Create a chain from the stages before and after the delay, in order
+    pub fn new(before_delay: Vec<Box<dyn AudioStage>>, after_delay: Vec<Box<dyn AudioStage>>) -> Self {
+        Self { before_delay, after_delay, bypassed: Vec::new(), mixes: Vec::new() }
+    }
+    
+    /// Build a chain from stage identifiers, see `validate_effect_chain`
+    pub fn from_ids(ids: &[String], sample_rate: u32) -> Result<Self, AudioProcessorError> {
+        validate_effect_chain(ids)?;
+        let delay_index = ids.iter().position(|id| id == "delay").unwrap_or(ids.len());
+        let stages = |ids: &[String]| ids.iter().filter_map(|id| stage_from_id(id, sample_rate)).collect();
+        Ok(Self::new(stages(&ids[..delay_index]), stages(&ids[delay_index..])))
+    }
+    
+    /// Get the stage identifiers in processing order, including the delay
+    pub fn ids(&self) -> Vec<&'static str> {
+        let before = self.before_delay.iter().map(|stage| stage.name());
+        let after = self.after_delay.iter().map(|stage| stage.name());
+        before.chain(std::iter::once("delay")).chain(after).collect()
+    }
+    
+    /// Check whether a stage is part of the chain
+    pub fn contains(&self, name: &str) -> bool {
+        self.stage(name).is_some()
+    }
+    
+    /// Get a stage of the chain by name
+    pub fn stage(&self, name: &str) -> Option<&dyn AudioStage> {
+        self.before_delay
+            .iter()
+            .chain(self.after_delay.iter())
+            .find(|stage| stage.name() == name)
+            .map(|stage| stage.as_ref())
+    }
+    
+    /// Get a stage of the chain by name
+    pub fn stage_mut(&mut self, name: &str) -> Option<&mut Box<dyn AudioStage>> {
+        self.before_delay
+            .iter_mut()
+            .chain(self.after_delay.iter_mut())
+            .find(|stage| stage.name() == name)
+    }
+    
+    /// Check whether a stage runs before the delay
+    pub fn is_before_delay(&self, name: &str) -> bool {
+        self.before_delay.iter().any(|stage| stage.name() == name)
+    }
+    
+    /// Leave a stage out of the processing, or put it back, keeping its place in the chain
+    pub fn set_bypass(&mut self, name: &'static str, bypass: bool) {
+        self.bypassed.retain(|bypassed| *bypassed != name);
+        if bypass {
+            self.bypassed.push(name);
+        }
+    }
+    
+    /// Set the wet/dry mix of a stage (0.0 leaves it out to 1.0 fully processed)
+    ///
+    /// The mix blends the stage output with its own input only, so the rest of
+    /// the chain and the delay's dry/wet mix are not affected.
+    pub fn set_mix(&mut self, name: &'static str, mix: f32) {
+        self.mixes.retain(|(stage, _)| *stage != name);
+        let mix = mix.clamp(0.0, 1.0);
+        if mix < 1.0 {
+            self.mixes.push((name, mix));
+        }
+    }
+    
+    /// Get the wet/dry mix of a stage
+    pub fn get_mix(&self, name: &str) -> f32 {
+        self.mixes.iter().find(|(stage, _)| *stage == name).map_or(1.0, |(_, mix)| *mix)
+    }
+    
+    /// Set a parameter of a stage by name
+    ///
+    /// "mix" is handled by the chain for every stage, anything else goes to the
+    /// stage's own `set_param`.
+    pub fn set_param(&mut self, name: &str, param: &str, value: f32) -> Result<(), AudioProcessorError> {
+        let stage = self.stage_mut(name).ok_or_else(|| {
+            AudioProcessorError::Configuration(format!("Effect chain has no '{}' stage", name))
+        })?;
+        if param == "mix" {
+            let name = stage.name();
+            self.set_mix(name, value);
+            return Ok(());
+        }
+        stage.set_param(param, value)
+    }
+    
+    /// Run a stereo sample through the stages before the delay
+    pub fn process_before_delay(&mut self, left: f32, right: f32) -> (f32, f32) {
+        Self::process_stages(&mut self.before_delay, &self.bypassed, &self.mixes, left, right)
+    }
+    
+    /// Run a stereo sample through the stages after the delay
+    pub fn process_after_delay(&mut self, left: f32, right: f32) -> (f32, f32) {
+        Self::process_stages(&mut self.after_delay, &self.bypassed, &self.mixes, left, right)
+    }
+    
+    /// Run a stereo sample through stages, skipping the bypassed ones and blending by their mix
+    fn process_stages(
+        stages: &mut [Box<dyn AudioStage>],
+        bypassed: &[&'static str],
+        mixes: &[(&'static str, f32)],
+        left: f32,
+        right: f32,
+    ) -> (f32, f32) {
+        stages
+            .iter_mut()
+            .filter(|stage| !bypassed.contains(&stage.name()))
+            .fold((left, right), |(left, right), stage| {
+                let (wet_left, wet_right) = stage.process(left, right);
+                match mixes.iter().find(|(name, _)| *name == stage.name()) {
+                    Some(&(_, mix)) => (left + mix * (wet_left - left), right + mix * (wet_right - right)),
+                    None => (wet_left, wet_right),
+                }
+            })
+    }
+    
+    /// Clear the state of every stage
+    pub fn reset(&mut self) {
+        for stage in self.before_delay.iter_mut().chain(self.after_delay.iter_mut()) {
+            stage.reset();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AudioConfig;
+    use crate::delay::StereoDelay;
+    use crate::distortion::DistortionEffect;
+    use crate::dynamics::{Compressor, Limiter};
+    use crate::metronome::Metronome;
+    
+    /// Feed the same signal to a stage and to its direct call and compare the output
+    fn assert_same_output(mut stage: Box<dyn AudioStage>, mut direct: impl FnMut(f32, f32) -> (f32, f32)) {
+        let name = stage.name();
+        for i in 0..4800 {
+            let phase = 2.0 * std::f32::consts::PI * 220.0 * i as f32 / 48000.0;
+            let (left, right) = (0.8 * phase.sin(), 0.5 * phase.cos());
+            assert_eq!(stage.process(left, right), direct(left, right), "{} at sample {}", name, i);
+        }
+    }
+    
+    #[test]
+    fn test_stages_match_their_direct_calls() {
+        let sample_rate = 48000;
+        let distortion = || DistortionEffect::new(DistortionType::Tube, 0.7, 0.8, sample_rate);
+        let mut direct = distortion();
+        assert_same_output(Box::new(distortion()), |left, right| {
+            (direct.process_sample(left), direct.process_sample(right))
+        });
+        
+        let cross_feedback = || CrossFeedbackDistortion::new(true, DistortionType::Fuzz, 0.5, 0.6, sample_rate);
+        let mut direct = cross_feedback();
+        assert_same_output(Box::new(cross_feedback()), |left, right| direct.process_stereo(left, right));
+        
+        let compressor = || Compressor::new(-20.0, 4.0, 5.0, 100.0, 6.0, sample_rate);
+        let mut direct = compressor();
+        assert_same_output(Box::new(compressor()), |left, right| direct.process_stereo(left, right));
+        
+        let mut direct = Limiter::new(0.5, 50.0, sample_rate);
+        assert_same_output(Box::new(Limiter::new(0.5, 50.0, sample_rate)), |left, right| {
+            direct.process_stereo(left, right)
+        });
+        
+        let mut direct = Metronome::new(180.0, 2, 0.5, sample_rate);
+        assert_same_output(Box::new(Metronome::new(180.0, 2, 0.5, sample_rate)), |left, right| {
+            let click = direct.process();
+            (left + click, right + click)
+        });
+        
+        let (mut left_eq, mut right_eq) = (TiltEq::new(0.6, sample_rate), TiltEq::new(0.6, sample_rate));
+        assert_same_output(Box::new(EqStage::new(0.6, sample_rate)), |left, right| {
+            (left_eq.process(left), right_eq.process(right))
+        });
+        
+        let mut config = AudioConfig::default();
+        config.stereo_delay.feedback = 0.6;
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.015;
+        let mut direct = StereoDelay::from_config(&config);
+        assert_same_output(Box::new(StereoDelay::from_config(&config)), |left, right| {
+            direct.process_sample(left, right)
+        });
+    }
+    
+    #[test]
+    fn test_process_block_handles_empty_and_mismatched_buffers() {
+        let mut stage: Box<dyn AudioStage> = Box::new(StereoDelay::from_config(&AudioConfig::default()));
+        stage.process_block(&mut [], &mut []).unwrap();
+        
+        // Mismatched blocks are rejected untouched
+        let (mut left, mut right) = ([0.5; 4], [0.5; 3]);
+        let error = stage.process_block(&mut left, &mut right).unwrap_err();
+        assert!(error.to_string().contains("4 and 3"), "{}", error);
+        assert_eq!((left, right), ([0.5; 4], [0.5; 3]));
+        
+        let (mut left, mut right) = ([0.5; 4], [0.5; 4]);
+        stage.process_block(&mut left, &mut right).unwrap();
+    }
+    
+    #[test]
+    fn test_set_param_reaches_the_typed_setters() {
+        let mut distortion = CrossFeedbackDistortion::new(false, DistortionType::SoftClip, 0.2, 0.5, 48000);
+        distortion.set_param("enabled", 1.0).unwrap();
+        distortion.set_param("type", DistortionType::Tube.to_parameter()).unwrap();
+        distortion.set_param("drive", 0.9).unwrap();
+        assert!(distortion.is_enabled());
+        assert_eq!(distortion.get_distortion_type(), DistortionType::Tube);
+        assert_eq!(distortion.get_drive(), 0.9);
+        assert!(distortion.set_param("rate", 1.0).is_err());
+        
+        let mut delay: Box<dyn AudioStage> = Box::new(StereoDelay::from_config(&AudioConfig::default()));
+        assert_eq!(delay.name(), "delay");
+        delay.set_param("feedback", 0.4).unwrap();
+        assert!(delay.set_param("invalid_param", 0.5).is_err());
+    }
+    
+    #[test]
+    fn test_chain_runs_stages_in_config_order() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let mut config = AudioConfig::default();
+        config.stereo_delay.feedback = 0.5;
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.eq_tilt = 0.8;
+        
+        // The eq ahead of the delay shapes what goes into it, after it what comes out
+        for order in [ids(&["eq", "delay"]), ids(&["delay", "eq"])] {
+            config.effect_chain = order.clone();
+            assert!(config.validate().is_ok());
+            let mut delay = StereoDelay::from_config(&config);
+            assert_eq!(delay.get_effect_chain(), order);
+            
+            config.effect_chain = default_effect_chain();
+            let mut plain = StereoDelay::from_config(&config);
+            let mut eq = EqStage::new(0.8, config.sample_rate);
+            for i in 0..2000 {
+                let input = if i % 100 == 0 { 1.0 } else { 0.0 };
+                let expected = if order[0] == "eq" {
+                    let (left, right) = eq.process(input, input);
+                    plain.process_sample(left, right)
+                } else {
+                    let (left, right) = plain.process_sample(input, input);
+                    eq.process(left, right)
+                };
+                assert_eq!(delay.process_sample(input, input), expected, "{:?} at sample {}", order, i);
+            }
+        }
+    }
+    
+    #[test]
+    fn test_stage_mix_at_zero_leaves_the_rest_of_the_chain_running() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        let mut config = AudioConfig::default();
+        config.distortion.enabled = true;
+        config.distortion.drive = 0.8;
+        config.distortion.mix = 0.7;
+        config.eq_tilt = 0.8;
+        config.eq_mix = 0.0;
+        config.effect_chain = ids(&["eq", "distortion", "delay"]);
+        assert!(config.validate().is_ok());
+        let mut with_eq = StereoDelay::from_config(&config);
+        config.effect_chain = ids(&["distortion", "delay"]);
+        let mut without_eq = StereoDelay::from_config(&config);
+        
+        for i in 0..2000 {
+            let input = 0.8 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 48000.0).sin();
+            assert_eq!(with_eq.process_sample(input, input), without_eq.process_sample(input, input), "sample {}", i);
+        }
+        
+        // Bringing the eq back in changes the sound without touching the delay mix
+        let wet_mix = with_eq.get_parameters()["wet_mix"];
+        with_eq.set_parameter("eq_mix", 1.0).unwrap();
+        let changed = (0..2000).any(|i| {
+            let input = 0.8 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 48000.0).sin();
+            with_eq.process_sample(input, input) != without_eq.process_sample(input, input)
+        });
+        assert!(changed);
+        assert_eq!(with_eq.get_parameters()["wet_mix"], wet_mix);
+        
+        let mut chain = EffectChain::from_ids(&ids(&["eq", "delay"]), 48000).unwrap();
+        chain.set_param("eq", "mix", 0.25).unwrap();
+        assert_eq!(chain.get_mix("eq"), 0.25);
+        assert!(chain.set_param("distortion", "mix", 0.5).is_err());
+    }
+    
+    #[test]
+    fn test_chain_validation() {
+        let ids = |ids: &[&str]| ids.iter().map(|id| id.to_string()).collect::<Vec<_>>();
+        assert!(validate_effect_chain(&default_effect_chain()).is_ok());
+        assert!(validate_effect_chain(&ids(&["eq", "delay", "distortion"])).is_ok());
+        assert!(validate_effect_chain(&ids(&["eq", "distortion"])).is_err());
+        assert!(validate_effect_chain(&ids(&["delay", "reverb"])).is_err());
+        assert!(validate_effect_chain(&ids(&["eq", "delay", "eq"])).is_err());
+        
+        let chain = EffectChain::from_ids(&ids(&["distortion", "delay", "eq"]), 48000).unwrap();
+        assert_eq!(chain.ids(), ["distortion", "delay", "eq"]);
+        assert!(chain.contains("eq") && !chain.contains("comp"));
+    }
+}
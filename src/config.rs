@@ -1,10 +1,44 @@
 use serde::{Deserialize, Serialize};
+use crate::chain::{default_effect_chain, validate_effect_chain};
+use crate::delay::{Division, FeedbackColor, FeedbackStageOrder, InputSource, InterpolationMode, MixLaw, PingPongMode, PingPongStart, TapPanMode, DEFAULT_AUTO_WIDTH_TARGET, DEFAULT_DELAY_SWEEP_AMOUNT, DEFAULT_DI_SIM_RESONANCE, DEFAULT_FADE_MS, DEFAULT_FREEZE_CROSSFADE_MS, DEFAULT_PRESET_CROSSFADE_MS, DEFAULT_TAP_SPREAD, DEFAULT_WET_LIMITER_THRESHOLD_DB, MAX_FEEDBACK_BIT_DEPTH, MAX_LOOP_GAIN, MAX_PRESET_CROSSFADE_MS, MAX_SELF_OSCILLATION_FEEDBACK};
+use crate::devices::{ChannelLayout, SampleFormat};
+use crate::distortion::{DistortionPlacement, DistortionType};
+use crate::double_track::{DEFAULT_DOUBLE_TRACK_DETUNE_CENTS, DEFAULT_DOUBLE_TRACK_SPREAD};
+use crate::dynamics::DEFAULT_AUTO_FREEZE_THRESHOLD_DB;
+use crate::filters::FULL_RANGE_HZ;
+use crate::footswitch::{FootswitchPin, DEFAULT_DEBOUNCE_MS, MAX_DEBOUNCE_MS};
+use crate::idle::{DEFAULT_IDLE_THRESHOLD, DEFAULT_IDLE_TIMEOUT_S, MAX_IDLE_TIMEOUT_S, MIN_IDLE_TIMEOUT_S};
+use crate::macro_control::MacroMapping;
+use crate::meter::MeterMode;
+use crate::resample::ResampleQuality;
+use crate::schema;
+use crate::shimmer::{DEFAULT_SHIMMER_DIFFUSION, DEFAULT_SHIMMER_PITCH_MIX};
+use crate::smoothing::{MAX_SMOOTHING_MS, SMOOTHED_PARAMETERS};
+use std::collections::HashMap;
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+
+/// Most channels the ALSA backend can be asked to open
+pub const MAX_ALSA_CHANNELS: u32 = 32;
+
+/// Most periods the ALSA device buffer can be asked to hold
+pub const MAX_ALSA_PERIODS: u32 = 16;
+
+/// Schema version written by this build, see `AudioConfig::migrate`
+pub const CONFIG_VERSION: u32 = 2;
+
+/// Estimated feedback loop gain at which `validate_stability` warns of runaway repeats
+pub const STABILITY_LOOP_GAIN: f32 = 1.0;
 
 /// Audio configuration settings
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AudioConfig {
+    /// Schema version of the configuration
+    ///
+    /// Files written before versioning have no version field and load as version 1.
+    #[serde(default = "default_config_version")]
+    pub version: u32,
+    
     /// Sample rate in Hz
     pub sample_rate: u32,
     
@@ -17,11 +51,259 @@ pub struct AudioConfig {
     /// Output device name (optional)
     pub output_device: Option<String>,
     
+    /// WAV file looped in place of the input device, for repeatable runs without a guitar
+    #[serde(default)]
+    pub input_file: Option<String>,
+    
+    /// Channels to open the ALSA devices with; the nearest supported count is used
+    #[serde(default = "default_alsa_channels")]
+    pub alsa_channels: u32,
+    
+    /// Periods of `buffer_size` frames the ALSA device buffer holds (2 to 16)
+    ///
+    /// Fewer periods mean lower latency but less headroom against underruns.
+    #[serde(default = "default_alsa_periods")]
+    pub alsa_periods: u32,
+    
+    /// Sample format to open the ALSA devices with; falls back through S32, S24 and S16
+    #[serde(default)]
+    pub alsa_format: SampleFormat,
+    
+    /// TPDF dither before converting to integer output; unset dithers 16-bit output only
+    #[serde(default)]
+    pub dither: Option<bool>,
+    
     /// Stereo delay configuration
     pub stereo_delay: StereoDelayConfig,
     
     /// Distortion configuration
     pub distortion: DistortionConfig,
+    
+    /// Ballistics of the output level meters
+    #[serde(default)]
+    pub meter_mode: MeterMode,
+    
+    /// Macro control mapping one knob to many parameters
+    #[serde(default)]
+    pub macro_control: MacroConfig,
+    
+    /// Output compressor configuration
+    #[serde(default)]
+    pub compressor: CompressorConfig,
+    
+    /// Volume-pedal auto-swell configuration
+    #[serde(default)]
+    pub swell: SwellConfig,
+    
+    /// Metronome click synced to the configured BPM
+    #[serde(default)]
+    pub metronome: MetronomeConfig,
+    
+    /// Octave-up, diffused repeats under a single intensity control
+    #[serde(default)]
+    pub shimmer: ShimmerConfig,
+    
+    /// Remove DC offset from the input before it reaches the effects
+    #[serde(default = "default_dc_block")]
+    pub dc_block: bool,
+    
+    /// Kind of source on the input, setting its gain and DC blocker cutoff ("instrument" or "line")
+    #[serde(default)]
+    pub input_source: InputSource,
+    
+    /// Simulate the treble loading of an amp input on a guitar plugged straight into the interface
+    #[serde(default)]
+    pub di_sim_enabled: bool,
+    
+    /// Resonant bump of the DI simulation at its cutoff (0.0 to 0.8)
+    #[serde(default = "default_di_sim_resonance")]
+    pub di_sim_resonance: f32,
+    
+    /// Flush near-silent filter state to zero so decaying tails never turn into slow denormals
+    #[serde(default = "default_anti_denormal")]
+    pub anti_denormal: bool,
+    
+    /// Tone EQ tilt (-1.0 darker to 1.0 brighter), heard when the chain has an eq stage
+    #[serde(default)]
+    pub eq_tilt: f32,
+    
+    /// Wet/dry mix of the chain's eq stage (0.0 transparent to 1.0 fully applied)
+    #[serde(default = "default_stage_mix")]
+    pub eq_mix: f32,
+    
+    /// Processing order of the effect stages, see `chain::STAGE_IDS`
+    #[serde(default = "default_effect_chain")]
+    pub effect_chain: Vec<String>,
+    
+    /// Tone EQ on the dry signal only
+    #[serde(default)]
+    pub eq_dry: PathEqConfig,
+    
+    /// Tone EQ on the wet signal only
+    #[serde(default)]
+    pub eq_wet: PathEqConfig,
+    
+    /// Time in milliseconds the output fades out before stopping (and in after starting)
+    #[serde(default = "default_fade_out_ms")]
+    pub fade_out_ms: f32,
+    
+    /// Level in dBFS the output can never exceed, a hard safety clamp after every effect (-40.0 to 0.0)
+    #[serde(default)]
+    pub max_output_db: f32,
+    
+    /// Time in milliseconds the delay taps of the old preset fade out when a preset is loaded
+    #[serde(default = "default_preset_crossfade_ms")]
+    pub preset_crossfade_ms: f32,
+    
+    /// Stop the output while the input is silent and restart it when signal returns
+    #[serde(default)]
+    pub auto_idle_enabled: bool,
+    
+    /// Input RMS below which the input counts as silent (0.0 to 1.0)
+    #[serde(default = "default_auto_idle_threshold")]
+    pub auto_idle_threshold: f32,
+    
+    /// Seconds the input has to stay silent before the output stops (1 to 3600)
+    #[serde(default = "default_auto_idle_timeout_s")]
+    pub auto_idle_timeout_s: f32,
+    
+    /// Ask for real-time scheduling of the audio thread (Linux, needs rtprio or CAP_SYS_NICE)
+    #[serde(default)]
+    pub audio_thread_rt: bool,
+    
+    /// CPU core to pin the audio thread to, e.g. one kept free of the web server
+    #[serde(default)]
+    pub audio_thread_core: Option<usize>,
+    
+    /// Sample order of the device buffers, interleaved (LRLR) or planar (LL..RR..)
+    #[serde(default)]
+    pub channel_layout: ChannelLayout,
+    
+    /// First output channel of the processed stereo pair, e.g. 2 for outputs 3-4
+    ///
+    /// The other output channels are silent.
+    #[serde(default)]
+    pub output_channel_offset: usize,
+    
+    /// Interpolation used when the input and output devices run at different rates
+    #[serde(default)]
+    pub resample_quality: ResampleQuality,
+    
+    /// GPIO footswitches on the Pi (needs a build with the `gpio` feature)
+    #[serde(default)]
+    pub footswitch: FootswitchConfig,
+    
+    /// Reject settings that risk runaway feedback together instead of only warning
+    #[serde(default)]
+    pub strict_stability: bool,
+}
+
+fn default_config_version() -> u32 {
+    1
+}
+
+fn default_alsa_channels() -> u32 {
+    2
+}
+
+fn default_alsa_periods() -> u32 {
+    2
+}
+
+fn default_dc_block() -> bool {
+    true
+}
+
+fn default_anti_denormal() -> bool {
+    true
+}
+
+fn default_di_sim_resonance() -> f32 {
+    DEFAULT_DI_SIM_RESONANCE
+}
+
+fn default_stage_mix() -> f32 {
+    1.0
+}
+
+fn default_fade_out_ms() -> f32 {
+    DEFAULT_FADE_MS
+}
+
+fn default_preset_crossfade_ms() -> f32 {
+    DEFAULT_PRESET_CROSSFADE_MS
+}
+
+fn default_feedback_lp_hz() -> f32 {
+    FULL_RANGE_HZ
+}
+
+fn default_dub_lfo_division() -> f32 {
+    0.25
+}
+
+fn default_feedback_bit_depth() -> f32 {
+    MAX_FEEDBACK_BIT_DEPTH
+}
+
+fn default_auto_width_target_correlation() -> f32 {
+    DEFAULT_AUTO_WIDTH_TARGET
+}
+
+fn default_delay_sweep_amount() -> f32 {
+    DEFAULT_DELAY_SWEEP_AMOUNT
+}
+
+fn default_wet_limiter_threshold() -> f32 {
+    DEFAULT_WET_LIMITER_THRESHOLD_DB
+}
+
+fn default_tap_spread() -> f32 {
+    DEFAULT_TAP_SPREAD
+}
+
+fn default_double_track_spread() -> f32 {
+    DEFAULT_DOUBLE_TRACK_SPREAD
+}
+
+fn default_double_track_detune() -> f32 {
+    DEFAULT_DOUBLE_TRACK_DETUNE_CENTS
+}
+
+fn default_note_divisions() -> Vec<Division> {
+    vec![
+        Division::Quarter,
+        Division::Half,
+        Division::Eighth,
+        Division::Sixteenth,
+        Division::Third,
+        Division::Sixth,
+        Division::DottedQuarter,
+        Division::DottedEighth,
+        Division::DottedSixteenth,
+        Division::Twelfth,
+        Division::ThirtySecond,
+    ]
+}
+
+fn default_freeze_crossfade_ms() -> f32 {
+    DEFAULT_FREEZE_CROSSFADE_MS
+}
+
+fn default_auto_freeze_threshold() -> f32 {
+    DEFAULT_AUTO_FREEZE_THRESHOLD_DB
+}
+
+fn default_debounce_ms() -> f32 {
+    DEFAULT_DEBOUNCE_MS
+}
+
+fn default_auto_idle_threshold() -> f32 {
+    DEFAULT_IDLE_THRESHOLD
+}
+
+fn default_auto_idle_timeout_s() -> f32 {
+    DEFAULT_IDLE_TIMEOUT_S
 }
 
 /// Stereo delay effect configuration
@@ -36,20 +318,195 @@ pub struct StereoDelayConfig {
     /// Tempo in beats per minute (BPM) - used to calculate delay times
     pub bpm: Option<f32>,
     
-    /// Feedback amount (0.0 to 0.9)
+    /// Note divisions listed by `get_delay_times_for_bpm`, in order
+    #[serde(default = "default_note_divisions")]
+    pub note_divisions: Vec<Division>,
+    
+    /// Feedback amount (-0.9 to 0.9, or ±1.1 with `allow_self_oscillation`)
+    ///
+    /// Negative feedback inverts every repeat, moving the comb filter notches.
     pub feedback: f32,
     
+    /// Allow feedback above 0.9 for self-oscillating repeats
+    ///
+    /// The delay inserts a peak limiter in the feedback path while this is enabled,
+    /// so repeats settle at full scale instead of growing without bound.
+    #[serde(default)]
+    pub allow_self_oscillation: bool,
+    
     /// Wet signal mix (0.0 to 1.0)
     pub wet_mix: f32,
     
+    /// Crossfade law used to split the dry and wet gains
+    #[serde(default)]
+    pub mix_law: MixLaw,
+    
+    /// How the delay taps are read between whole samples ("none" for stepped lo-fi repeats)
+    #[serde(default)]
+    pub interpolation: InterpolationMode,
+    
     /// Enable ping-pong delay pattern
     pub ping_pong: bool,
     
+    /// How ping-pong crosses the lines: "swap" the taps or "bounce" each echo between sides
+    #[serde(default)]
+    pub ping_pong_mode: PingPongMode,
+    
+    /// Side a bouncing ping-pong puts the first echo on: "left" or "right"
+    #[serde(default)]
+    pub ping_pong_start: PingPongStart,
+    
+    /// How the taps are placed in the stereo field: "manual", "spread" or "alternate"
+    #[serde(default)]
+    pub tap_pan_mode: TapPanMode,
+    
+    /// Width of the automatic tap panning (0.0 centered to 1.0 hard left and right)
+    #[serde(default = "default_tap_spread")]
+    pub tap_spread: f32,
+    
     /// Stereo width enhancement (0.0 to 1.0)
     pub stereo_width: f32,
     
-    /// Cross-feedback between channels (0.0 to 0.5)
+    /// Limit the width so the delay keeps its level when summed to mono
+    #[serde(default)]
+    pub mono_safe: bool,
+    
+    /// Run a single delay line on the mono sum, skipping the stereo stages to save CPU
+    #[serde(default)]
+    pub mono_mode: bool,
+    
+    /// Peak-limit the repeats before they are mixed with the dry signal
+    #[serde(default)]
+    pub wet_limiter_enabled: bool,
+    
+    /// Wet limiter threshold in dBFS (-40 to 0)
+    #[serde(default = "default_wet_limiter_threshold")]
+    pub wet_limiter_threshold: f32,
+    
+    /// Thicken the dry signal with two short, detuned copies panned left and right
+    #[serde(default)]
+    pub double_track_enabled: bool,
+    
+    /// How far the double-tracked copies are panned apart (0.0 centred to 1.0 hard left and right)
+    #[serde(default = "default_double_track_spread")]
+    pub double_track_spread: f32,
+    
+    /// Peak detune of the double-tracked copies in cents (0 to 25)
+    #[serde(default = "default_double_track_detune")]
+    pub double_track_detune: f32,
+    
+    /// Let the width follow the correlation of the repeats, as wide as the target allows
+    #[serde(default)]
+    pub auto_width_enabled: bool,
+    
+    /// Correlation auto-width keeps the widened repeats at (0.0 to 0.9)
+    #[serde(default = "default_auto_width_target_correlation")]
+    pub auto_width_target_correlation: f32,
+    
+    /// Delay the dry path to line up with the shortest delay tap, for parallel routing
+    #[serde(default)]
+    pub latency_compensation: bool,
+    
+    /// Cross-feedback between channels (0.0 to 0.9)
+    ///
+    /// The delay normalizes the combined feedback and cross-feedback so the loop
+    /// always decays, see `StereoDelay::loop_gain`.
     pub cross_feedback: f32,
+    
+    /// Feedback tilt EQ (-1.0 darker repeats to 1.0 brighter repeats, 0.0 is flat)
+    #[serde(default)]
+    pub feedback_tilt: f32,
+    
+    /// Tape-style saturation of the repeats (0.0 linear to 1.0 heavily compressed)
+    #[serde(default)]
+    pub feedback_saturation: f32,
+    
+    /// Voice the feedback path as a classic delay type, overriding its filter,
+    /// saturation and bit depth settings; `custom` keeps them
+    #[serde(default)]
+    pub feedback_color: FeedbackColor,
+    
+    /// Whether the feedback filters run before or after the distortion in the loop
+    #[serde(default)]
+    pub feedback_stage_order: FeedbackStageOrder,
+    
+    /// Bit depth the delay lines store the repeats at (4.0 gritty to 24.0 full resolution)
+    #[serde(default = "default_feedback_bit_depth")]
+    pub feedback_bit_depth: f32,
+    
+    /// High-pass cutoff of the repeats in Hz (0.0 keeps the lows)
+    #[serde(default)]
+    pub feedback_hp_hz: f32,
+    
+    /// Low-pass cutoff of the repeats in Hz (20000 or above keeps the highs)
+    #[serde(default = "default_feedback_lp_hz")]
+    pub feedback_lp_hz: f32,
+    
+    /// Cutoff of the resonant dub filter on the repeats in Hz (20000 or above bypasses it)
+    #[serde(default = "default_feedback_lp_hz")]
+    pub dub_cutoff: f32,
+    
+    /// Resonance of the dub filter (0.0 to 1.0, self-oscillating at 1.0)
+    #[serde(default)]
+    pub dub_resonance: f32,
+    
+    /// Free-running rate of the LFO sweeping the dub filter cutoff in Hz (0.0 is off)
+    #[serde(default)]
+    pub dub_lfo_rate: f32,
+    
+    /// Sweep of the dub filter LFO in octaves either side of the cutoff
+    #[serde(default)]
+    pub dub_lfo_depth: f32,
+    
+    /// Lock the dub filter LFO to the tempo instead of the free rate
+    #[serde(default)]
+    pub dub_lfo_sync: bool,
+    
+    /// Synced dub filter LFO cycle as a fraction of a whole note (0.25 = 1/4 note)
+    #[serde(default = "default_dub_lfo_division")]
+    pub dub_lfo_division: f32,
+    
+    /// Time the delay taps slide to new times on a BPM change in milliseconds (0.0 uses the delay glide)
+    #[serde(default)]
+    pub bpm_glide_ms: f32,
+    
+    /// Sweep both delay times on each note onset for a pitch dive in the echoes
+    #[serde(default)]
+    pub delay_sweep_trigger: bool,
+    
+    /// Sweep as a fraction of the delay time (-0.5 to 0.5, positive dives first)
+    #[serde(default = "default_delay_sweep_amount")]
+    pub delay_sweep_amount: f32,
+    
+    /// Fraction the left delay is shortened and the right delay lengthened by (0.0 to 0.5)
+    #[serde(default)]
+    pub delay_spread: f32,
+    
+    /// Let the repeats ring out to silence when audio is stopped instead of cutting them off
+    #[serde(default)]
+    pub tails: bool,
+    
+    /// Time taken to enter and leave freeze in milliseconds
+    #[serde(default = "default_freeze_crossfade_ms")]
+    pub freeze_crossfade_ms: f32,
+    
+    /// Mute the dry signal while frozen, for a pure drone of the held repeats
+    #[serde(default)]
+    pub freeze_kill_dry: bool,
+    
+    /// Freeze automatically when the input falls silent after a phrase, and release on the next note
+    #[serde(default)]
+    pub auto_freeze_enabled: bool,
+    
+    /// Input level in dBFS (-80 to -20) below which auto-freeze counts the input as silent
+    #[serde(default = "default_auto_freeze_threshold")]
+    pub auto_freeze_threshold: f32,
+    
+    /// Ramp time in milliseconds per smoothed parameter, overriding the defaults
+    ///
+    /// Keys are parameter names from `smoothing::SMOOTHED_PARAMETERS`.
+    #[serde(default)]
+    pub smoothing_ms: HashMap<String, f32>,
 }
 
 /// Distortion effect configuration
@@ -69,46 +526,353 @@ pub struct DistortionConfig {
     
     /// How much distortion affects feedback (0.0 to 1.0)
     pub feedback_intensity: f32,
+    
+    /// Where in the signal chain the distortion is applied
+    #[serde(default)]
+    pub placement: DistortionPlacement,
+    
+    /// Scale the distortion output to keep its RMS level equal to the input level
+    #[serde(default)]
+    pub auto_gain_comp: bool,
+    
+    /// Tone of the distortion (-1.0 darker to 1.0 brighter, 0.0 unchanged)
+    #[serde(default)]
+    pub tone: f32,
+    
+    /// Keep the dry signal clear of the distortion, whatever the placement
+    #[serde(default)]
+    pub dry_bypass: bool,
+}
+
+/// Output compressor configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CompressorConfig {
+    /// Enable the compressor on the output
+    pub enabled: bool,
+    
+    /// Threshold in dBFS (-60.0 to 0.0)
+    pub threshold_db: f32,
+    
+    /// Compression ratio (1.0 to 20.0)
+    pub ratio: f32,
+    
+    /// Attack time in milliseconds (0.1 to 200.0)
+    pub attack_ms: f32,
+    
+    /// Release time in milliseconds (10.0 to 2000.0)
+    pub release_ms: f32,
+    
+    /// Makeup gain in dB (0.0 to 24.0)
+    pub makeup_db: f32,
+}
+
+/// Volume-pedal auto-swell configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SwellConfig {
+    /// Fade each new note in from silence
+    pub enabled: bool,
+    
+    /// Time for a note to swell to full level in milliseconds (10.0 to 5000.0)
+    pub time_ms: f32,
+    
+    /// Onset sensitivity (0.0 only hard picking to 1.0 soft notes as well)
+    pub sensitivity: f32,
+}
+
+/// Metronome configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MetronomeConfig {
+    /// Mix a click into the output on every beat
+    pub enabled: bool,
+    
+    /// Click level (0.0 to 1.0)
+    pub volume: f32,
+    
+    /// Clicks per beat (1 quarters, 2 eighths, 3 triplets, 4 sixteenths)
+    pub subdivision: u32,
+}
+
+/// Shimmer configuration
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct ShimmerConfig {
+    /// Intensity (0.0 off to 1.0), scaling the pitch mix and the diffusion together
+    pub amount: f32,
+    
+    /// Octave-up share of the repeats at full intensity (0.0 to 1.0)
+    pub pitch_mix: f32,
+    
+    /// Diffusion of the repeats at full intensity (0.0 to 1.0)
+    pub diffusion: f32,
+}
+
+/// Tone EQ on the dry or the wet path
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct PathEqConfig {
+    /// Run the path through the EQ
+    pub enabled: bool,
+    
+    /// Tilt (-1.0 darker to 1.0 brighter)
+    pub tilt: f32,
+}
+
+/// GPIO footswitch configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct FootswitchConfig {
+    /// Poll the footswitch pins while running
+    #[serde(default)]
+    pub enabled: bool,
+    
+    /// Time a pin has to hold a new level before the change counts (0 to 200 ms)
+    #[serde(default = "default_debounce_ms")]
+    pub debounce_ms: f32,
+    
+    /// Action of each button, by BCM pin number
+    #[serde(default)]
+    pub pins: Vec<FootswitchPin>,
+    
+    /// State files exported with `export_state`, stepped through by `preset_next`
+    #[serde(default)]
+    pub presets: Vec<PathBuf>,
+}
+
+/// Macro control configuration
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct MacroConfig {
+    /// Current macro position (0.0 to 1.0)
+    #[serde(default)]
+    pub position: f32,
+    
+    /// Parameters driven by the macro, each with its own range and curve
+    pub mappings: Vec<MacroMapping>,
 }
 
 impl Default for AudioConfig {
     fn default() -> Self {
         Self {
+            version: CONFIG_VERSION,
             sample_rate: 44100,
             buffer_size: 4096,
             input_device: None,
             output_device: None,
+            input_file: None,
+            alsa_channels: 2,
+            alsa_periods: 2,
+            alsa_format: SampleFormat::S32,
+            dither: None,
             stereo_delay: StereoDelayConfig::default(),
             distortion: DistortionConfig::default(),
+            meter_mode: MeterMode::Vu,
+            macro_control: MacroConfig::default(),
+            compressor: CompressorConfig::default(),
+            swell: SwellConfig::default(),
+            metronome: MetronomeConfig::default(),
+            shimmer: ShimmerConfig::default(),
+            dc_block: true,
+            input_source: InputSource::Instrument,
+            di_sim_enabled: false,
+            di_sim_resonance: DEFAULT_DI_SIM_RESONANCE,
+            anti_denormal: true,
+            eq_tilt: 0.0,
+            eq_mix: 1.0,
+            effect_chain: default_effect_chain(),
+            eq_dry: PathEqConfig::default(),
+            eq_wet: PathEqConfig::default(),
+            fade_out_ms: DEFAULT_FADE_MS,
+            max_output_db: 0.0,
+            preset_crossfade_ms: DEFAULT_PRESET_CROSSFADE_MS,
+            auto_idle_enabled: false,
+            auto_idle_threshold: DEFAULT_IDLE_THRESHOLD,
+            auto_idle_timeout_s: DEFAULT_IDLE_TIMEOUT_S,
+            audio_thread_rt: false,
+            audio_thread_core: None,
+            channel_layout: ChannelLayout::Interleaved,
+            output_channel_offset: 0,
+            resample_quality: ResampleQuality::Fast,
+            footswitch: FootswitchConfig::default(),
+            strict_stability: false,
         }
     }
 }
 
 impl AudioConfig {
-    /// Load configuration from a JSON file
+    /// Load configuration from a JSON file, migrating it to the current schema
     pub fn from_file<P: AsRef<Path>>(path: P) -> Result<Self, Box<dyn std::error::Error>> {
         let content = fs::read_to_string(path)?;
-        let config: AudioConfig = serde_json::from_str(&content)?;
+        Self::from_json(&content)
+    }
+    
+    /// Parse configuration from JSON, migrating it to the current schema
+    pub fn from_json(content: &str) -> Result<Self, Box<dyn std::error::Error>> {
+        let mut config: AudioConfig = serde_json::from_str(content)?;
+        config.migrate()?;
         Ok(config)
     }
     
+    /// Upgrade a configuration from an older schema version to `CONFIG_VERSION`
+    ///
+    /// Fields added since the file was written are already filled with their defaults
+    /// by serde; this applies the changes in meaning between versions, one version
+    /// at a time. Configurations from a newer build are rejected rather than guessed at.
+    pub fn migrate(&mut self) -> Result<(), crate::AudioProcessorError> {
+        if self.version > CONFIG_VERSION {
+            return Err(crate::AudioProcessorError::Configuration(format!(
+                "Configuration version {} is newer than the supported version {}",
+                self.version, CONFIG_VERSION
+            )));
+        }
+        
+        if self.version < 2 {
+            self.migrate_v1();
+        }
+        self.version = CONFIG_VERSION;
+        
+        Ok(())
+    }
+    
+    /// Version 1 matched distortion type names in any case and played unknown
+    /// names as no distortion; store the canonical name that was actually used
+    fn migrate_v1(&mut self) {
+        let distortion_type = DistortionType::from(self.distortion.distortion_type.as_str());
+        self.distortion.distortion_type = distortion_type.to_string();
+    }
+    
+    /// Ready-made shimmer: long repeats climbing an octave each pass into a diffuse pad
+    ///
+    /// The same settings ship as `presets/shimmer.json`, for `/api/state` and the
+    /// footswitch preset list.
+    pub fn shimmer_preset() -> Self {
+        let mut config = Self::default();
+        config.stereo_delay.left_delay = 0.45;
+        config.stereo_delay.right_delay = 0.6;
+        config.stereo_delay.feedback = 0.6;
+        config.stereo_delay.wet_mix = 0.45;
+        config.stereo_delay.mix_law = MixLaw::EqualPower;
+        config.distortion.enabled = false;
+        config.shimmer = ShimmerConfig { amount: 0.7, ..ShimmerConfig::default() };
+        config
+    }
+    
     /// Load configuration from file or return default if file doesn't exist
     pub fn load_or_default<P: AsRef<Path>>(path: P) -> Self {
         Self::from_file(path).unwrap_or_else(|_| Self::default())
     }
 }
 
+impl Default for CompressorConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            threshold_db: -18.0,
+            ratio: 4.0,
+            attack_ms: 10.0,
+            release_ms: 150.0,
+            makeup_db: 0.0,
+        }
+    }
+}
+
+impl Default for SwellConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            time_ms: 500.0,
+            sensitivity: 0.5,
+        }
+    }
+}
+
+impl Default for ShimmerConfig {
+    fn default() -> Self {
+        Self {
+            amount: 0.0,
+            pitch_mix: DEFAULT_SHIMMER_PITCH_MIX,
+            diffusion: DEFAULT_SHIMMER_DIFFUSION,
+        }
+    }
+}
+
+impl Default for MetronomeConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            volume: 0.5,
+            subdivision: 1,
+        }
+    }
+}
+
+impl Default for FootswitchConfig {
+    fn default() -> Self {
+        Self {
+            enabled: false,
+            debounce_ms: DEFAULT_DEBOUNCE_MS,
+            pins: Vec::new(),
+            presets: Vec::new(),
+        }
+    }
+}
+
+impl Default for MacroConfig {
+    fn default() -> Self {
+        Self {
+            position: 0.0,
+            mappings: MacroMapping::ambient_wash(),
+        }
+    }
+}
+
 impl Default for StereoDelayConfig {
     fn default() -> Self {
         Self {
             left_delay: 0.3,
             right_delay: 0.6,
             bpm: None,
+            note_divisions: default_note_divisions(),
             feedback: 0.3,
+            allow_self_oscillation: false,
             wet_mix: 0.6,
+            mix_law: MixLaw::Linear,
+            interpolation: InterpolationMode::Linear,
             ping_pong: true,
+            ping_pong_mode: PingPongMode::Swap,
+            ping_pong_start: PingPongStart::Left,
+            tap_pan_mode: TapPanMode::Manual,
+            tap_spread: DEFAULT_TAP_SPREAD,
             stereo_width: 0.5,
+            mono_safe: false,
+            mono_mode: false,
+            wet_limiter_enabled: false,
+            wet_limiter_threshold: DEFAULT_WET_LIMITER_THRESHOLD_DB,
+            double_track_enabled: false,
+            double_track_spread: DEFAULT_DOUBLE_TRACK_SPREAD,
+            double_track_detune: DEFAULT_DOUBLE_TRACK_DETUNE_CENTS,
+            auto_width_enabled: false,
+            auto_width_target_correlation: DEFAULT_AUTO_WIDTH_TARGET,
+            latency_compensation: false,
             cross_feedback: 0.2,
+            feedback_tilt: 0.0,
+            feedback_saturation: 0.0,
+            feedback_color: FeedbackColor::Custom,
+            feedback_stage_order: FeedbackStageOrder::FilterFirst,
+            feedback_bit_depth: MAX_FEEDBACK_BIT_DEPTH,
+            feedback_hp_hz: 0.0,
+            feedback_lp_hz: FULL_RANGE_HZ,
+            dub_cutoff: FULL_RANGE_HZ,
+            dub_resonance: 0.0,
+            dub_lfo_rate: 0.0,
+            dub_lfo_depth: 0.0,
+            dub_lfo_sync: false,
+            dub_lfo_division: default_dub_lfo_division(),
+            bpm_glide_ms: 0.0,
+            delay_sweep_trigger: false,
+            delay_sweep_amount: DEFAULT_DELAY_SWEEP_AMOUNT,
+            delay_spread: 0.0,
+            tails: false,
+            freeze_crossfade_ms: DEFAULT_FREEZE_CROSSFADE_MS,
+            freeze_kill_dry: false,
+            auto_freeze_enabled: false,
+            auto_freeze_threshold: DEFAULT_AUTO_FREEZE_THRESHOLD_DB,
+            smoothing_ms: HashMap::new(),
         }
     }
 }
@@ -121,6 +885,10 @@ impl Default for DistortionConfig {
             drive: 0.3,
             mix: 0.7,
             feedback_intensity: 0.5,
+            placement: DistortionPlacement::Feedback,
+            auto_gain_comp: false,
+            tone: 0.0,
+            dry_bypass: false,
         }
     }
 }
@@ -139,6 +907,36 @@ impl AudioConfig {
         }
     }
     
+    /// Copy of this configuration with every effect parameter at its default
+    ///
+    /// Keeps the audio device, buffer, channel layout and routing, resampling, auto-idle, audio thread, anti-denormal, footswitch and stability check settings.
+    pub fn with_default_effects(&self) -> Self {
+        Self {
+            sample_rate: self.sample_rate,
+            buffer_size: self.buffer_size,
+            input_device: self.input_device.clone(),
+            output_device: self.output_device.clone(),
+            input_file: self.input_file.clone(),
+            alsa_channels: self.alsa_channels,
+            alsa_periods: self.alsa_periods,
+            alsa_format: self.alsa_format,
+            dither: self.dither,
+            auto_idle_enabled: self.auto_idle_enabled,
+            auto_idle_threshold: self.auto_idle_threshold,
+            auto_idle_timeout_s: self.auto_idle_timeout_s,
+            audio_thread_rt: self.audio_thread_rt,
+            audio_thread_core: self.audio_thread_core,
+            anti_denormal: self.anti_denormal,
+            max_output_db: self.max_output_db,
+            channel_layout: self.channel_layout,
+            output_channel_offset: self.output_channel_offset,
+            resample_quality: self.resample_quality,
+            footswitch: self.footswitch.clone(),
+            strict_stability: self.strict_stability,
+            ..Default::default()
+        }
+    }
+    
     /// Validate the configuration
     pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
         if self.sample_rate < 8000 || self.sample_rate > 192000 {
@@ -153,11 +951,130 @@ impl AudioConfig {
             ));
         }
         
+        if !(1..=MAX_ALSA_CHANNELS).contains(&self.alsa_channels) {
+            return Err(crate::AudioProcessorError::InvalidParameter {
+                param: "alsa_channels".to_string(),
+                value: self.alsa_channels as f32,
+                min: 1.0,
+                max: MAX_ALSA_CHANNELS as f32,
+            });
+        }
+        
+        // The device channel count is only known once it is opened, where the offset is checked again
+        if self.output_channel_offset + 2 > MAX_ALSA_CHANNELS as usize {
+            return Err(crate::AudioProcessorError::InvalidParameter {
+                param: "output_channel_offset".to_string(),
+                value: self.output_channel_offset as f32,
+                min: 0.0,
+                max: (MAX_ALSA_CHANNELS - 2) as f32,
+            });
+        }
+        
+        if !(2..=MAX_ALSA_PERIODS).contains(&self.alsa_periods) {
+            return Err(crate::AudioProcessorError::InvalidParameter {
+                param: "alsa_periods".to_string(),
+                value: self.alsa_periods as f32,
+                min: 2.0,
+                max: MAX_ALSA_PERIODS as f32,
+            });
+        }
+        
+        schema::check("fade_out_ms", self.fade_out_ms)?;
+        schema::check("max_output_db", self.max_output_db)?;
+        if !(0.0..=MAX_PRESET_CROSSFADE_MS).contains(&self.preset_crossfade_ms) {
+            return Err(crate::AudioProcessorError::InvalidParameter {
+                param: "preset_crossfade_ms".to_string(),
+                value: self.preset_crossfade_ms,
+                min: 0.0,
+                max: MAX_PRESET_CROSSFADE_MS,
+            });
+        }
+        schema::check("eq_tilt", self.eq_tilt)?;
+        schema::check("eq_mix", self.eq_mix)?;
+        schema::check("di_sim_resonance", self.di_sim_resonance)?;
+        
+        let idle_ranges = [
+            ("auto_idle_threshold", self.auto_idle_threshold, 0.0, 1.0),
+            ("auto_idle_timeout_s", self.auto_idle_timeout_s, MIN_IDLE_TIMEOUT_S, MAX_IDLE_TIMEOUT_S),
+        ];
+        for (param, value, min, max) in idle_ranges {
+            if !(min..=max).contains(&value) {
+                return Err(crate::AudioProcessorError::InvalidParameter {
+                    param: param.to_string(),
+                    value,
+                    min,
+                    max,
+                });
+            }
+        }
+        
         self.stereo_delay.validate()?;
         self.distortion.validate()?;
+        self.macro_control.validate()?;
+        self.compressor.validate()?;
+        self.swell.validate()?;
+        self.metronome.validate()?;
+        self.shimmer.validate()?;
+        self.footswitch.validate()?;
+        self.eq_dry.validate("eq_dry")?;
+        self.eq_wet.validate("eq_wet")?;
+        validate_effect_chain(&self.effect_chain)?;
+        
+        if let Some(warning) = self.validate_stability()? {
+            log::warn!("⚠️  {}", warning);
+        }
         
         Ok(())
     }
+    
+    /// Estimated gain of a quiet repeat on one trip around the feedback loop
+    ///
+    /// The delay scales feedback and cross-feedback down to `MAX_LOOP_GAIN`, but a
+    /// distortion in the loop boosts quiet repeats after that, so settings that are
+    /// each in range can still build up until the distortion clips them.
+    pub fn estimated_loop_gain(&self) -> f32 {
+        let delay = &self.stereo_delay;
+        let linear_gain = (delay.feedback.abs() * (1.0 + delay.cross_feedback)).min(MAX_LOOP_GAIN);
+        
+        let distortion = &self.distortion;
+        if !distortion.enabled || distortion.placement != DistortionPlacement::Feedback {
+            return linear_gain;
+        }
+        let shaper_gain = DistortionType::from(distortion.distortion_type.as_str()).small_signal_gain(distortion.drive);
+        let distortion_gain = 1.0 - distortion.mix + distortion.mix * shaper_gain;
+        let loop_distortion_gain = 1.0 - distortion.feedback_intensity + distortion.feedback_intensity * distortion_gain;
+        linear_gain * loop_distortion_gain
+    }
+    
+    /// Check the feedback settings together for runaway repeats
+    ///
+    /// Returns a warning rather than an error when the estimated loop gain reaches
+    /// `STABILITY_LOOP_GAIN`, unless `strict_stability` is set. Self-oscillation
+    /// runs the loop through the feedback limiter, so it is never flagged.
+    pub fn validate_stability(&self) -> Result<Option<String>, crate::AudioProcessorError> {
+        if self.stereo_delay.allow_self_oscillation {
+            return Ok(None);
+        }
+        
+        let loop_gain = self.estimated_loop_gain();
+        if loop_gain < STABILITY_LOOP_GAIN {
+            return Ok(None);
+        }
+        
+        let warning = format!(
+            "Feedback {}, cross-feedback {} and {} distortion at drive {} give a loop gain of {:.2}, \
+             the repeats may run away; lower them or enable allow_self_oscillation to limit the loop",
+            self.stereo_delay.feedback,
+            self.stereo_delay.cross_feedback,
+            self.distortion.distortion_type,
+            self.distortion.drive,
+            loop_gain
+        );
+        if self.strict_stability {
+            return Err(crate::AudioProcessorError::Configuration(warning));
+        }
+        Ok(Some(warning))
+    }
 }
 
 impl StereoDelayConfig {
@@ -195,91 +1112,85 @@ impl StereoDelayConfig {
         self.bpm
     }
     
-    /// Calculate and return delay times for different note divisions at current BPM
+    /// Calculate the delay time in seconds of a note division at a tempo
+    pub fn delay_time_for_division(bpm: f32, division: Division) -> f32 {
+        Self::bpm_to_delay_time(bpm, division.note_division())
+    }
+    
+    /// Calculate and return delay times for the configured note divisions at a BPM
     pub fn get_delay_times_for_bpm(&self, bpm: f32) -> Vec<(String, f32)> {
-        let divisions = [
-            ("1/4 note", 0.25),
-            ("1/2 note", 0.5),
-            ("1/8 note", 0.125),
-            ("1/16 note", 0.0625),
-            ("1/3 note", 1.0 / 3.0),
-            ("1/6 note", 1.0 / 6.0),
-        ];
-        
-        divisions
+        self.note_divisions
             .iter()
-            .map(|(name, division)| {
-                (name.to_string(), Self::bpm_to_delay_time(bpm, *division))
-            })
+            .map(|&division| (division.to_string(), Self::delay_time_for_division(bpm, division)))
             .collect()
     }
     
     /// Validate stereo delay configuration
     pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
-        if !(0.001..=4.0).contains(&self.left_delay) {
-            return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "left_delay".to_string(),
-                value: self.left_delay,
-                min: 0.001,
-                max: 4.0,
-            });
-        }
-        
-        if !(0.001..=4.0).contains(&self.right_delay) {
-            return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "right_delay".to_string(),
-                value: self.right_delay,
-                min: 0.001,
-                max: 4.0,
-            });
+        schema::check("left_delay", self.left_delay)?;
+        schema::check("right_delay", self.right_delay)?;
+        if let Some(bpm) = self.bpm {
+            schema::check("bpm", bpm)?;
         }
         
-        // Validate BPM if present
-        if let Some(bpm) = self.bpm {
-            if !(20.0..=300.0).contains(&bpm) {
+        // Self-oscillation extends the feedback range past unity
+        if self.allow_self_oscillation {
+            if !(-MAX_SELF_OSCILLATION_FEEDBACK..=MAX_SELF_OSCILLATION_FEEDBACK).contains(&self.feedback) {
                 return Err(crate::AudioProcessorError::InvalidParameter {
-                    param: "bpm".to_string(),
-                    value: bpm,
-                    min: 20.0,
-                    max: 300.0,
+                    param: "feedback".to_string(),
+                    value: self.feedback,
+                    min: -MAX_SELF_OSCILLATION_FEEDBACK,
+                    max: MAX_SELF_OSCILLATION_FEEDBACK,
                 });
             }
+        } else {
+            schema::check("feedback", self.feedback)?;
         }
         
-        if !(0.0..=0.9).contains(&self.feedback) {
-            return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "feedback".to_string(),
-                value: self.feedback,
-                min: 0.0,
-                max: 0.9,
-            });
+        schema::check("wet_mix", self.wet_mix)?;
+        schema::check("stereo_width", self.stereo_width)?;
+        schema::check("auto_width_target_correlation", self.auto_width_target_correlation)?;
+        schema::check("cross_feedback", self.cross_feedback)?;
+        schema::check("feedback_tilt", self.feedback_tilt)?;
+        schema::check("feedback_saturation", self.feedback_saturation)?;
+        schema::check("feedback_bit_depth", self.feedback_bit_depth)?;
+        schema::check("bpm_glide_ms", self.bpm_glide_ms)?;
+        schema::check("delay_sweep_amount", self.delay_sweep_amount)?;
+        schema::check("delay_spread", self.delay_spread)?;
+        schema::check("wet_limiter_threshold", self.wet_limiter_threshold)?;
+        schema::check("tap_spread", self.tap_spread)?;
+        schema::check("double_track_spread", self.double_track_spread)?;
+        schema::check("double_track_detune", self.double_track_detune)?;
+        schema::check("feedback_hp_hz", self.feedback_hp_hz)?;
+        schema::check("feedback_lp_hz", self.feedback_lp_hz)?;
+        if self.feedback_hp_hz >= self.feedback_lp_hz {
+            return Err(crate::AudioProcessorError::Configuration(format!(
+                "Feedback high-pass ({} Hz) must be below the low-pass ({} Hz)",
+                self.feedback_hp_hz, self.feedback_lp_hz
+            )));
         }
+        schema::check("dub_cutoff", self.dub_cutoff)?;
+        schema::check("dub_resonance", self.dub_resonance)?;
+        schema::check("dub_lfo_rate", self.dub_lfo_rate)?;
+        schema::check("dub_lfo_depth", self.dub_lfo_depth)?;
+        schema::check("dub_lfo_division", self.dub_lfo_division)?;
+        schema::check("freeze_crossfade_ms", self.freeze_crossfade_ms)?;
+        schema::check("auto_freeze_threshold", self.auto_freeze_threshold)?;
         
-        if !(0.0..=1.0).contains(&self.wet_mix) {
-            return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "wet_mix".to_string(),
-                value: self.wet_mix,
-                min: 0.0,
-                max: 1.0,
-            });
-        }
-        
-        if !(0.0..=1.0).contains(&self.stereo_width) {
-            return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "stereo_width".to_string(),
-                value: self.stereo_width,
-                min: 0.0,
-                max: 1.0,
-            });
-        }
-        
-        if !(0.0..=0.5).contains(&self.cross_feedback) {
-            return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "cross_feedback".to_string(),
-                value: self.cross_feedback,
-                min: 0.0,
-                max: 0.5,
-            });
+        for (param, smoothing_ms) in &self.smoothing_ms {
+            if !SMOOTHED_PARAMETERS.contains(&param.as_str()) {
+                return Err(crate::AudioProcessorError::Configuration(
+                    format!("Parameter '{}' does not support smoothing", param)
+                ));
+            }
+            if !(0.0..=MAX_SMOOTHING_MS).contains(smoothing_ms) {
+                return Err(crate::AudioProcessorError::InvalidParameter {
+                    param: format!("smoothing_ms.{}", param),
+                    value: *smoothing_ms,
+                    min: 0.0,
+                    max: MAX_SMOOTHING_MS,
+                });
+            }
         }
         
         Ok(())
@@ -289,33 +1200,199 @@ impl StereoDelayConfig {
 impl DistortionConfig {
     /// Validate distortion configuration
     pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
-        if !(0.0..=1.0).contains(&self.drive) {
+        schema::check("distortion_drive", self.drive)?;
+        schema::check("distortion_mix", self.mix)?;
+        schema::check("distortion_feedback_intensity", self.feedback_intensity)?;
+        schema::check("distortion_tone", self.tone)?;
+        
+        Ok(())
+    }
+}
+
+impl CompressorConfig {
+    /// Validate compressor configuration
+    pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
+        schema::check("comp_threshold", self.threshold_db)?;
+        schema::check("comp_ratio", self.ratio)?;
+        schema::check("comp_attack", self.attack_ms)?;
+        schema::check("comp_release", self.release_ms)?;
+        schema::check("comp_makeup", self.makeup_db)?;
+        
+        Ok(())
+    }
+}
+
+impl SwellConfig {
+    /// Validate auto-swell configuration
+    pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
+        schema::check("swell_time_ms", self.time_ms)?;
+        schema::check("swell_sensitivity", self.sensitivity)?;
+        
+        Ok(())
+    }
+}
+
+impl FootswitchConfig {
+    /// Validate footswitch configuration
+    pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
+        if !(0.0..=MAX_DEBOUNCE_MS).contains(&self.debounce_ms) {
             return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "drive".to_string(),
-                value: self.drive,
+                param: "footswitch_debounce_ms".to_string(),
+                value: self.debounce_ms,
                 min: 0.0,
-                max: 1.0,
+                max: MAX_DEBOUNCE_MS,
             });
         }
         
-        if !(0.0..=1.0).contains(&self.mix) {
-            return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "mix".to_string(),
-                value: self.mix,
-                min: 0.0,
-                max: 1.0,
-            });
+        for (i, mapping) in self.pins.iter().enumerate() {
+            if self.pins[..i].iter().any(|other| other.pin == mapping.pin) {
+                return Err(crate::AudioProcessorError::Configuration(
+                    format!("GPIO pin {} has more than one footswitch action", mapping.pin)
+                ));
+            }
         }
         
-        if !(0.0..=1.0).contains(&self.feedback_intensity) {
-            return Err(crate::AudioProcessorError::InvalidParameter {
-                param: "feedback_intensity".to_string(),
-                value: self.feedback_intensity,
-                min: 0.0,
-                max: 1.0,
-            });
+        Ok(())
+    }
+}
+
+impl MetronomeConfig {
+    /// Validate metronome configuration
+    pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
+        schema::check("metronome_volume", self.volume)?;
+        schema::check("metronome_subdivision", self.subdivision as f32)?;
+        
+        Ok(())
+    }
+}
+
+impl ShimmerConfig {
+    /// Validate shimmer configuration
+    pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
+        schema::check("shimmer_amount", self.amount)?;
+        schema::check("shimmer_pitch_mix", self.pitch_mix)?;
+        schema::check("shimmer_diffusion", self.diffusion)?;
+        
+        Ok(())
+    }
+}
+
+impl PathEqConfig {
+    /// Validate path EQ configuration, `path` is the parameter prefix (`eq_dry` or `eq_wet`)
+    pub fn validate(&self, path: &str) -> Result<(), crate::AudioProcessorError> {
+        schema::check(&format!("{}_tilt", path), self.tilt)
+    }
+}
+
+impl MacroConfig {
+    /// Validate macro configuration
+    pub fn validate(&self) -> Result<(), crate::AudioProcessorError> {
+        schema::check("macro", self.position)?;
+        
+        for mapping in &self.mappings {
+            if mapping.parameter.is_empty() || mapping.parameter == "macro" {
+                return Err(crate::AudioProcessorError::Configuration(format!(
+                    "Invalid macro mapping parameter '{}'", mapping.parameter
+                )));
+            }
+            
+            if !mapping.min.is_finite() || !mapping.max.is_finite() {
+                return Err(crate::AudioProcessorError::Configuration(format!(
+                    "Macro mapping for '{}' needs finite min and max values", mapping.parameter
+                )));
+            }
         }
         
         Ok(())
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_migrates_version_1_config() {
+        // A configuration saved before versioning, with none of the newer fields
+        let v1 = r#"{
+            "sample_rate": 48000,
+            "buffer_size": 1024,
+            "input_device": null,
+            "output_device": null,
+            "stereo_delay": {
+                "left_delay": 0.3,
+                "right_delay": 0.6,
+                "feedback": 0.3,
+                "wet_mix": 0.6,
+                "ping_pong": true,
+                "stereo_width": 0.5,
+                "cross_feedback": 0.2
+            },
+            "distortion": {
+                "enabled": true,
+                "distortion_type": "Tube",
+                "drive": 0.3,
+                "mix": 0.7,
+                "feedback_intensity": 0.5
+            }
+        }"#;
+        
+        let config = AudioConfig::from_json(v1).unwrap();
+        assert_eq!(config.version, CONFIG_VERSION);
+        assert_eq!(config.sample_rate, 48000);
+        assert_eq!(config.stereo_delay.bpm, None);
+        assert_eq!(config.distortion.distortion_type, "tube");
+        assert_eq!(config.alsa_channels, 2);
+        assert!(config.dc_block);
+        assert!(!config.metronome.enabled);
+        assert!(config.validate().is_ok());
+        
+        let mut newer = AudioConfig { version: CONFIG_VERSION + 1, ..Default::default() };
+        assert!(newer.migrate().is_err());
+    }
+    
+    #[test]
+    fn test_unstable_feedback_combination_warns() {
+        assert_eq!(AudioConfig::default().validate_stability().unwrap(), None);
+        
+        // Every value is in range, but the driven clipper boosts the repeats past unity
+        let mut config = AudioConfig::default();
+        config.stereo_delay.feedback = 0.9;
+        config.stereo_delay.cross_feedback = 0.5;
+        config.distortion.distortion_type = "hard_clip".to_string();
+        config.distortion.drive = 0.9;
+        assert!(config.estimated_loop_gain() > STABILITY_LOOP_GAIN);
+        let warning = config.validate_stability().unwrap().expect("unstable settings should warn");
+        assert!(warning.contains("allow_self_oscillation"), "{}", warning);
+        assert!(config.validate().is_ok());
+        
+        config.strict_stability = true;
+        assert!(config.validate().is_err());
+        
+        // The feedback limiter bounds a self-oscillating loop
+        config.stereo_delay.allow_self_oscillation = true;
+        assert_eq!(config.validate_stability().unwrap(), None);
+    }
+    
+    #[test]
+    fn test_delay_time_for_division() {
+        // One beat lasts 0.5s at 120 BPM
+        let dotted_quarter = StereoDelayConfig::delay_time_for_division(120.0, Division::DottedQuarter);
+        let quarter = StereoDelayConfig::delay_time_for_division(120.0, Division::Quarter);
+        assert!((dotted_quarter - 0.375 * 0.5).abs() < 1e-6);
+        assert!((dotted_quarter - 1.5 * quarter).abs() < 1e-6);
+        
+        let thirty_second = StereoDelayConfig::delay_time_for_division(120.0, Division::ThirtySecond);
+        assert!((thirty_second - 0.015625).abs() < 1e-6);
+        assert!((thirty_second - StereoDelayConfig::delay_time_for_division(120.0, Division::Sixteenth) / 2.0).abs() < 1e-6);
+        
+        // The table follows the configured list
+        let mut config = StereoDelayConfig::default();
+        let times = config.get_delay_times_for_bpm(120.0);
+        assert_eq!(times[0], ("1/4 note".to_string(), 0.125));
+        assert!(times.iter().any(|(name, _)| name == "1/32 note"));
+        config.note_divisions = vec![Division::DottedEighth, Division::Twelfth];
+        let names: Vec<_> = config.get_delay_times_for_bpm(120.0).into_iter().map(|(name, _)| name).collect();
+        assert_eq!(names, vec!["dotted 1/8 note", "1/12 note"]);
+    }
+}
@@ -1,4 +1,678 @@
-use crate::distortion::{DistortionType, CrossFeedbackDistortion};
+use std::sync::Arc;
+use serde::{Deserialize, Serialize};
+use crate::chain::{unknown_parameter, EffectChain, EqStage};
+use crate::config::AudioConfig;
+use crate::distortion::{DistortionPlacement, DistortionType, CrossFeedbackDistortion};
+use crate::double_track::{DoubleTracker, MAX_DOUBLE_TRACK_DETUNE_CENTS};
+use crate::dynamics::{soft_saturate, time_constant_coeff, AutoFreeze, Compressor, Limiter, OnsetDetector, Swell, DEFAULT_AUTO_FREEZE_THRESHOLD_DB, MAX_AUTO_FREEZE_THRESHOLD_DB, MIN_AUTO_FREEZE_THRESHOLD_DB};
+use crate::filters::{sanitize_sample, set_anti_denormal, BandLimiter, DcBlocker, StateVariableFilter, TiltEq, DC_BLOCK_CUTOFF_HZ, FULL_RANGE_HZ};
+use crate::meter::{linear_to_db, CorrelationMeter, MeterMode, SessionStats, StereoMeter};
+use crate::metronome::{Metronome, DEFAULT_METRONOME_BPM};
+use crate::recorder::RecordingTap;
+use crate::schema::ParameterKind;
+use crate::shimmer::Shimmer;
+use crate::smoothing::{default_smoothing_ms, SmoothedValue, MAX_SMOOTHING_MS, SMOOTHED_PARAMETERS};
+#[cfg(feature = "spectral")]
+use crate::spectral::SpectralFreeze;
+
+/// Maximum feedback amount in the default (safe) configuration
+///
+/// Feedback is signed: negative amounts invert each repeat, and the same limit
+/// applies to their magnitude.
+pub const MAX_FEEDBACK: f32 = 0.9;
+
+/// Maximum feedback amount when self-oscillation is allowed
+pub const MAX_SELF_OSCILLATION_FEEDBACK: f32 = 1.1;
+
+/// Maximum cross-feedback between channels
+pub const MAX_CROSS_FEEDBACK: f32 = 0.9;
+
+/// Largest gain a repeat may have on one trip around the feedback loop
+pub const MAX_LOOP_GAIN: f32 = 0.95;
+
+/// Output level below which a ringing-out tail is considered silent (-80dBFS)
+pub const TAILS_SILENCE_THRESHOLD: f32 = 1e-4;
+
+/// Longest time a tail is allowed to ring out before the stop is forced, in seconds
+pub const MAX_TAILS_SECONDS: f32 = 30.0;
+
+/// Default output fade time on start and stop in milliseconds
+pub const DEFAULT_FADE_MS: f32 = 20.0;
+
+/// Longest output fade time accepted in milliseconds
+pub const MAX_FADE_MS: f32 = 1000.0;
+
+/// Default time taken to enter and leave freeze in milliseconds
+pub const DEFAULT_FREEZE_CROSSFADE_MS: f32 = 50.0;
+
+/// Longest freeze crossfade accepted in milliseconds
+pub const MAX_FREEZE_CROSSFADE_MS: f32 = 2000.0;
+
+/// Lowest and highest output ceiling in dBFS
+pub const MIN_OUTPUT_CEILING_DB: f32 = -40.0;
+pub const MAX_OUTPUT_CEILING_DB: f32 = 0.0;
+
+/// Lowest and highest wet limiter threshold in dBFS
+pub const MIN_WET_LIMITER_THRESHOLD_DB: f32 = -40.0;
+pub const MAX_WET_LIMITER_THRESHOLD_DB: f32 = 0.0;
+
+/// Default wet limiter threshold in dBFS
+pub const DEFAULT_WET_LIMITER_THRESHOLD_DB: f32 = -6.0;
+
+/// Default width of the automatic tap panning (1.0 = hard left to hard right)
+pub const DEFAULT_TAP_SPREAD: f32 = 1.0;
+
+/// Release of the wet limiter in milliseconds, slow enough not to pump on each repeat
+const WET_LIMITER_RELEASE_MS: f32 = 150.0;
+
+/// Cutoff of the DI simulation low-pass in Hz, where a pickup loaded by an amp input rolls off
+pub const DI_SIM_CUTOFF_HZ: f32 = 4000.0;
+
+/// Default resonance of the DI simulation, a bump of about 3 dB at the cutoff
+pub const DEFAULT_DI_SIM_RESONANCE: f32 = 0.6;
+
+/// Highest resonance of the DI simulation, a bump of about 8 dB
+pub const MAX_DI_SIM_RESONANCE: f32 = 0.8;
+
+/// Input gain for line-level sources in dB, bringing them down to guitar level
+pub const LINE_INPUT_GAIN_DB: f32 = -12.0;
+
+/// Cutoff of the DC blocker for line-level sources in Hz, low enough for bass and synths
+pub const LINE_DC_BLOCK_CUTOFF_HZ: f32 = 5.0;
+
+/// Time in milliseconds a triggered delay sweep takes to glide out, and again to glide back
+pub const DELAY_SWEEP_MS: f32 = 150.0;
+
+/// Largest delay sweep, as a fraction of the delay time either way
+pub const MAX_DELAY_SWEEP_AMOUNT: f32 = 0.5;
+
+/// Default delay sweep, lengthening the delay by a quarter for a pitch dive
+pub const DEFAULT_DELAY_SWEEP_AMOUNT: f32 = 0.25;
+
+/// Largest delay spread, as a fraction of each delay time
+pub const MAX_DELAY_SPREAD: f32 = 0.5;
+
+/// Onset sensitivity of the delay sweep trigger
+const DELAY_SWEEP_SENSITIVITY: f32 = 0.5;
+
+/// Default crossfade from the delay taps of the old preset to the new ones in milliseconds
+pub const DEFAULT_PRESET_CROSSFADE_MS: f32 = 100.0;
+
+/// Longest preset crossfade accepted in milliseconds
+pub const MAX_PRESET_CROSSFADE_MS: f32 = 2000.0;
+
+/// Fastest free-running rate of the dub filter LFO in Hz
+pub const MAX_DUB_LFO_RATE: f32 = 10.0;
+
+/// Widest sweep of the dub filter LFO in octaves either side of the cutoff
+pub const MAX_DUB_LFO_DEPTH: f32 = 4.0;
+
+/// Grittiest bit depth the delay lines can store the repeats at
+pub const MIN_FEEDBACK_BIT_DEPTH: f32 = 4.0;
+
+/// Feedback bit depth at which the delay lines store full-resolution samples
+pub const MAX_FEEDBACK_BIT_DEPTH: f32 = 24.0;
+
+/// Largest side to mid level the width boost may reach with `mono_safe` on
+///
+/// At this ratio 80% of the stereo energy survives a mono sum.
+pub const MONO_SAFE_MAX_SIDE_RATIO: f32 = 0.5;
+
+/// Time constant of the mid and side level envelopes used by `mono_safe`
+const MONO_SAFE_ENVELOPE_MS: f32 = 50.0;
+
+/// Correlation auto-width keeps the widened repeats at by default
+pub const DEFAULT_AUTO_WIDTH_TARGET: f32 = 0.3;
+
+/// Highest correlation auto-width can be asked to keep
+pub const MAX_AUTO_WIDTH_TARGET: f32 = 0.9;
+
+/// Rate at which auto-width opens the width, in width per second
+///
+/// It narrows four times as fast, so a drop in correlation is caught quickly.
+const AUTO_WIDTH_RATE: f32 = 0.5;
+
+/// External processor inserted into the feedback loop of a `StereoDelay`
+///
+/// Called once per sample with the left and right repeats on their way back into
+/// the delay lines. It runs on the audio thread, so it must not allocate or block.
+pub type FeedbackInsert = Box<dyn FnMut(f32, f32) -> (f32, f32) + Send>;
+
+/// One optional stage of the effect chain and whether it is active
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+pub struct EffectStage {
+    /// Stage name, also the prefix of its `{name}_enabled` status entry
+    pub name: &'static str,
+    pub enabled: bool,
+}
+
+/// Crossfade law used to derive the dry and wet gains from the wet mix
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MixLaw {
+    /// Linear split (dry = 1 - wet), dips by 6dB at the 50/50 point
+    #[default]
+    Linear,
+    /// Equal-power crossfade (dry² + wet² = 1), keeps a centered mix at full loudness
+    EqualPower,
+}
+
+impl MixLaw {
+    /// Map a numeric parameter value (0 = linear, 1 = equal power) to a mix law
+    pub fn from_parameter(value: f32) -> Self {
+        if value > 0.5 {
+            MixLaw::EqualPower
+        } else {
+            MixLaw::Linear
+        }
+    }
+    
+    /// Calculate the (dry, wet) gains for a wet mix (0.0 to 1.0)
+    pub fn gains(&self, wet_mix: f32) -> (f32, f32) {
+        let wet_mix = wet_mix.clamp(0.0, 1.0);
+        match self {
+            MixLaw::Linear => (1.0 - wet_mix, wet_mix),
+            MixLaw::EqualPower => {
+                let angle = wet_mix * std::f32::consts::FRAC_PI_2;
+                (angle.cos(), angle.sin())
+            }
+        }
+    }
+}
+
+impl From<&str> for MixLaw {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "equal_power" => MixLaw::EqualPower,
+            _ => MixLaw::Linear,
+        }
+    }
+}
+
+impl std::fmt::Display for MixLaw {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MixLaw::Linear => "linear",
+            MixLaw::EqualPower => "equal_power",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How the delay taps are read between whole samples
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InterpolationMode {
+    /// Linear interpolation between neighbouring samples, smooth delay time changes
+    #[default]
+    Linear,
+    /// Read whole samples only, so moving the delay time steps the pitch (lo-fi)
+    None,
+}
+
+impl InterpolationMode {
+    /// Map a numeric parameter value (0 = linear, 1 = none) to an interpolation mode
+    pub fn from_parameter(value: f32) -> Self {
+        if value > 0.5 {
+            InterpolationMode::None
+        } else {
+            InterpolationMode::Linear
+        }
+    }
+}
+
+impl From<&str> for InterpolationMode {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "none" | "stepped" => InterpolationMode::None,
+            _ => InterpolationMode::Linear,
+        }
+    }
+}
+
+impl std::fmt::Display for InterpolationMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InterpolationMode::Linear => "linear",
+            InterpolationMode::None => "none",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How the two delay lines are cross-coupled when ping-pong is on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PingPongMode {
+    /// Swap the left and right taps, keeping each side's input on its own line
+    #[default]
+    Swap,
+    /// The input enters the left line and each line feeds the other, so an echo
+    /// bounces left, right, left at a decreasing level
+    Bounce,
+}
+
+impl PingPongMode {
+    /// Map a numeric parameter value (0 = swap, 1 = bounce) to a ping-pong mode
+    pub fn from_parameter(value: f32) -> Self {
+        if value > 0.5 {
+            PingPongMode::Bounce
+        } else {
+            PingPongMode::Swap
+        }
+    }
+}
+
+impl From<&str> for PingPongMode {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "bounce" => PingPongMode::Bounce,
+            _ => PingPongMode::Swap,
+        }
+    }
+}
+
+impl std::fmt::Display for PingPongMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PingPongMode::Swap => "swap",
+            PingPongMode::Bounce => "bounce",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Side a bouncing ping-pong puts the first echo on
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum PingPongStart {
+    #[default]
+    Left,
+    Right,
+}
+
+impl PingPongStart {
+    /// Map a numeric parameter value (0 = left, 1 = right) to a start side
+    pub fn from_parameter(value: f32) -> Self {
+        if value > 0.5 {
+            PingPongStart::Right
+        } else {
+            PingPongStart::Left
+        }
+    }
+}
+
+impl From<&str> for PingPongStart {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "right" => PingPongStart::Right,
+            _ => PingPongStart::Left,
+        }
+    }
+}
+
+impl std::fmt::Display for PingPongStart {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            PingPongStart::Left => "left",
+            PingPongStart::Right => "right",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// How the delay taps are placed in the stereo field
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TapPanMode {
+    /// Each tap stays on its own channel
+    #[default]
+    Manual,
+    /// Taps are spread evenly from the left (first tap) to the right (last tap)
+    Spread,
+    /// Taps alternate between left and right, starting on the left
+    Alternate,
+}
+
+impl TapPanMode {
+    /// Map a numeric parameter value (0 = manual, 1 = spread, 2 = alternate) to a mode
+    pub fn from_parameter(value: f32) -> Self {
+        match value.round() as i32 {
+            1 => TapPanMode::Spread,
+            2 => TapPanMode::Alternate,
+            _ => TapPanMode::Manual,
+        }
+    }
+    
+    /// Get the numeric parameter value of this mode (inverse of `from_parameter`)
+    pub fn to_parameter(&self) -> f32 {
+        match self {
+            TapPanMode::Manual => 0.0,
+            TapPanMode::Spread => 1.0,
+            TapPanMode::Alternate => 2.0,
+        }
+    }
+    
+    /// Pan positions (-1.0 left to 1.0 right) of `taps` taps scaled by `spread`
+    ///
+    /// Returns `None` in manual mode, where the taps keep their own pans.
+    pub fn pan_positions(&self, taps: usize, spread: f32) -> Option<Vec<f32>> {
+        let spread = spread.clamp(0.0, 1.0);
+        match self {
+            TapPanMode::Manual => None,
+            TapPanMode::Spread => Some((0..taps).map(|tap| {
+                if taps > 1 {
+                    spread * (2.0 * tap as f32 / (taps - 1) as f32 - 1.0)
+                } else {
+                    0.0
+                }
+            }).collect()),
+            TapPanMode::Alternate => Some((0..taps).map(|tap| {
+                if tap % 2 == 0 { -spread } else { spread }
+            }).collect()),
+        }
+    }
+}
+
+impl From<&str> for TapPanMode {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "spread" => TapPanMode::Spread,
+            "alternate" => TapPanMode::Alternate,
+            _ => TapPanMode::Manual,
+        }
+    }
+}
+
+impl std::fmt::Display for TapPanMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            TapPanMode::Manual => "manual",
+            TapPanMode::Spread => "spread",
+            TapPanMode::Alternate => "alternate",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Order of the filters and the distortion in the feedback path
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackStageOrder {
+    /// Filter the repeats, then distort them: the distortion's new harmonics
+    /// reach the delay line unfiltered, so the repeats stay gritty and bright
+    #[default]
+    FilterFirst,
+    /// Distort the repeats, then filter them: the filters smooth the harmonics
+    /// away on every pass, so the repeats darken into a warm, rounded drive
+    DistortionFirst,
+}
+
+impl FeedbackStageOrder {
+    /// Map a numeric parameter value (0 = filter first, 1 = distortion first) to an order
+    pub fn from_parameter(value: f32) -> Self {
+        if value > 0.5 {
+            FeedbackStageOrder::DistortionFirst
+        } else {
+            FeedbackStageOrder::FilterFirst
+        }
+    }
+}
+
+impl From<&str> for FeedbackStageOrder {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "distortion_first" => FeedbackStageOrder::DistortionFirst,
+            _ => FeedbackStageOrder::FilterFirst,
+        }
+    }
+}
+
+impl std::fmt::Display for FeedbackStageOrder {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FeedbackStageOrder::FilterFirst => "filter_first",
+            FeedbackStageOrder::DistortionFirst => "distortion_first",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Kind of source plugged into the input, setting its gain staging and DC blocking
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InputSource {
+    /// A guitar or bass straight into a high-impedance input, at unity gain
+    #[default]
+    Instrument,
+    /// A line-level source such as a keyboard, mixer or pedalboard output,
+    /// padded down to guitar level so the drive and thresholds respond the same
+    Line,
+}
+
+impl InputSource {
+    /// Map a numeric parameter value (0 = instrument, 1 = line) to a source
+    pub fn from_parameter(value: f32) -> Self {
+        if value > 0.5 {
+            InputSource::Line
+        } else {
+            InputSource::Instrument
+        }
+    }
+    
+    /// Linear gain applied to the input before any effect
+    pub fn gain(&self) -> f32 {
+        match self {
+            InputSource::Instrument => 1.0,
+            InputSource::Line => 10f32.powf(LINE_INPUT_GAIN_DB / 20.0),
+        }
+    }
+    
+    /// Cutoff of the input DC blocker in Hz
+    pub fn dc_block_cutoff_hz(&self) -> f32 {
+        match self {
+            InputSource::Instrument => DC_BLOCK_CUTOFF_HZ,
+            InputSource::Line => LINE_DC_BLOCK_CUTOFF_HZ,
+        }
+    }
+}
+
+impl From<&str> for InputSource {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "line" => InputSource::Line,
+            _ => InputSource::Instrument,
+        }
+    }
+}
+
+impl std::fmt::Display for InputSource {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            InputSource::Instrument => "instrument",
+            InputSource::Line => "line",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Voicing of the feedback path, setting its filters, saturation and bit depth together
+///
+/// `Custom` leaves the feedback path as set parameter by parameter; changing any
+/// of those parameters after picking a color turns the color back to `Custom`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FeedbackColor {
+    #[default]
+    Custom,
+    /// Clean, full-range repeats that do not change from pass to pass
+    Digital,
+    /// Darkening, gently saturated repeats that lose their lows and highs
+    Tape,
+    /// Dark, grainy repeats of a bucket-brigade analog delay
+    Bbd,
+    /// A resonant low-pass that closes in on each repeat, with saturation
+    Dub,
+}
+
+/// Feedback path settings a `FeedbackColor` stands for
+struct FeedbackVoicing {
+    tilt: f32,
+    saturation: f32,
+    bit_depth: f32,
+    hp_hz: f32,
+    lp_hz: f32,
+    dub_cutoff: f32,
+    dub_resonance: f32,
+}
+
+impl FeedbackColor {
+    /// Map a numeric parameter value (0 = custom, 1 = digital, 2 = tape, 3 = bbd, 4 = dub) to a color
+    pub fn from_parameter(value: f32) -> Self {
+        match value.round() as i32 {
+            1 => FeedbackColor::Digital,
+            2 => FeedbackColor::Tape,
+            3 => FeedbackColor::Bbd,
+            4 => FeedbackColor::Dub,
+            _ => FeedbackColor::Custom,
+        }
+    }
+    
+    /// Get the numeric parameter value of this color (inverse of `from_parameter`)
+    pub fn to_parameter(&self) -> f32 {
+        match self {
+            FeedbackColor::Custom => 0.0,
+            FeedbackColor::Digital => 1.0,
+            FeedbackColor::Tape => 2.0,
+            FeedbackColor::Bbd => 3.0,
+            FeedbackColor::Dub => 4.0,
+        }
+    }
+    
+    /// Settings of the feedback path for this color, none for `Custom`
+    fn voicing(&self) -> Option<FeedbackVoicing> {
+        let clean = FeedbackVoicing {
+            tilt: 0.0,
+            saturation: 0.0,
+            bit_depth: MAX_FEEDBACK_BIT_DEPTH,
+            hp_hz: 0.0,
+            lp_hz: FULL_RANGE_HZ,
+            dub_cutoff: FULL_RANGE_HZ,
+            dub_resonance: 0.0,
+        };
+        match self {
+            FeedbackColor::Custom => None,
+            FeedbackColor::Digital => Some(clean),
+            FeedbackColor::Tape => Some(FeedbackVoicing {
+                tilt: -0.3,
+                saturation: 0.3,
+                hp_hz: 60.0,
+                lp_hz: 6000.0,
+                ..clean
+            }),
+            FeedbackColor::Bbd => Some(FeedbackVoicing {
+                tilt: -0.2,
+                saturation: 0.15,
+                bit_depth: 12.0,
+                hp_hz: 100.0,
+                lp_hz: 3500.0,
+                ..clean
+            }),
+            FeedbackColor::Dub => Some(FeedbackVoicing {
+                saturation: 0.4,
+                hp_hz: 150.0,
+                dub_cutoff: 1500.0,
+                dub_resonance: 0.5,
+                ..clean
+            }),
+        }
+    }
+}
+
+impl From<&str> for FeedbackColor {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "digital" => FeedbackColor::Digital,
+            "tape" => FeedbackColor::Tape,
+            "bbd" => FeedbackColor::Bbd,
+            "dub" => FeedbackColor::Dub,
+            _ => FeedbackColor::Custom,
+        }
+    }
+}
+
+impl std::fmt::Display for FeedbackColor {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            FeedbackColor::Custom => "custom",
+            FeedbackColor::Digital => "digital",
+            FeedbackColor::Tape => "tape",
+            FeedbackColor::Bbd => "bbd",
+            FeedbackColor::Dub => "dub",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Note division of a tempo-synced delay time
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Division {
+    Half,
+    DottedHalf,
+    Third,
+    Quarter,
+    DottedQuarter,
+    Sixth,
+    Eighth,
+    DottedEighth,
+    Twelfth,
+    Sixteenth,
+    DottedSixteenth,
+    ThirtySecond,
+}
+
+impl Division {
+    /// Note division as taken by `StereoDelayConfig::bpm_to_delay_time`
+    ///
+    /// A dotted note lasts half as long again as the plain one.
+    pub fn note_division(&self) -> f32 {
+        match self {
+            Division::Half => 0.5,
+            Division::DottedHalf => 0.75,
+            Division::Third => 1.0 / 3.0,
+            Division::Quarter => 0.25,
+            Division::DottedQuarter => 0.375,
+            Division::Sixth => 1.0 / 6.0,
+            Division::Eighth => 0.125,
+            Division::DottedEighth => 0.1875,
+            Division::Twelfth => 1.0 / 12.0,
+            Division::Sixteenth => 0.0625,
+            Division::DottedSixteenth => 0.09375,
+            Division::ThirtySecond => 0.03125,
+        }
+    }
+}
+
+impl std::fmt::Display for Division {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Division::Half => "1/2 note",
+            Division::DottedHalf => "dotted 1/2 note",
+            Division::Third => "1/3 note",
+            Division::Quarter => "1/4 note",
+            Division::DottedQuarter => "dotted 1/4 note",
+            Division::Sixth => "1/6 note",
+            Division::Eighth => "1/8 note",
+            Division::DottedEighth => "dotted 1/8 note",
+            Division::Twelfth => "1/12 note",
+            Division::Sixteenth => "1/16 note",
+            Division::DottedSixteenth => "dotted 1/16 note",
+            Division::ThirtySecond => "1/32 note",
+        };
+        write!(f, "{}", name)
+    }
+}
 
 /// Base delay effect trait
 pub trait BaseDelay {
@@ -17,13 +691,25 @@ pub trait BaseDelay {
     /// Set the delay time in seconds
     fn set_delay_time(&mut self, delay_time: f32);
     
-    /// Set the feedback amount (0.0 to 0.9)
+    /// Set the feedback amount (-0.9 to 0.9, negative inverts each repeat)
     fn set_feedback(&mut self, feedback: f32);
     
     /// Set the wet signal mix (0.0 to 1.0)
     fn set_wet_mix(&mut self, wet_mix: f32);
 }
 
+/// LFO rate in Hz for one cycle per note division at a tempo
+///
+/// `division` is a fraction of a whole note, so 0.25 is one cycle per quarter note
+/// and 0.125 one per eighth note.
+pub fn tempo_synced_rate(bpm: f32, division: f32) -> f32 {
+    if bpm <= 0.0 || division <= 0.0 {
+        return 0.0;
+    }
+    let beats_per_cycle = division * 4.0;
+    bpm / 60.0 / beats_per_cycle
+}
+
 /// Simple delay line implementation
 pub struct SimpleDelay {
     sample_rate: u32,
@@ -45,6 +731,11 @@ pub struct SimpleDelay {
     modulation_rate: f32,
     modulation_depth: f32,
     modulation_phase: f32,
+    
+    // Tempo sync for the modulation LFO
+    mod_sync: bool,
+    mod_division: f32,
+    bpm: f32,
 }
 
 impl SimpleDelay {
@@ -61,7 +752,7 @@ impl SimpleDelay {
         Self {
             sample_rate,
             max_delay_time,
-            feedback: feedback.clamp(0.0, 0.9),
+            feedback: feedback.clamp(-0.9, 0.9),
             wet_mix: wet_mix.clamp(0.0, 1.0),
             dry_mix: 1.0 - wet_mix.clamp(0.0, 1.0),
             buffer_size,
@@ -72,6 +763,9 @@ impl SimpleDelay {
             modulation_rate: 0.0,
             modulation_depth: 0.0,
             modulation_phase: 0.0,
+            mod_sync: false,
+            mod_division: 0.25,
+            bpm: 120.0,
         }
     }
     
@@ -81,9 +775,33 @@ impl SimpleDelay {
         self.modulation_depth = depth.max(0.0);
     }
     
+    /// Lock the modulation rate to the tempo instead of the free rate in Hz
+    pub fn set_mod_sync(&mut self, mod_sync: bool) {
+        self.mod_sync = mod_sync;
+    }
+    
+    /// Set the synced LFO cycle length as a fraction of a whole note (0.25 = 1/4 note)
+    pub fn set_mod_division(&mut self, mod_division: f32) {
+        self.mod_division = mod_division.max(1.0 / 64.0);
+    }
+    
+    /// Set the tempo used when the modulation is synced
+    pub fn set_bpm(&mut self, bpm: f32) {
+        self.bpm = bpm.max(0.0);
+    }
+    
+    /// Get the LFO rate in Hz, derived from the tempo when synced
+    pub fn get_modulation_rate(&self) -> f32 {
+        if self.mod_sync && self.bpm > 0.0 {
+            tempo_synced_rate(self.bpm, self.mod_division)
+        } else {
+            self.modulation_rate
+        }
+    }
+    
     /// Get the current delay time with modulation applied
     fn get_modulated_delay(&self) -> usize {
-        if self.modulation_rate > 0.0 && self.modulation_depth > 0.0 {
+        if self.get_modulation_rate() > 0.0 && self.modulation_depth > 0.0 {
             let mod_offset = self.modulation_depth * (2.0 * std::f32::consts::PI * self.modulation_phase).sin();
             let modulated_delay = self.delay_samples as f32 + mod_offset;
             modulated_delay.clamp(1.0, (self.buffer_size - 1) as f32) as usize
@@ -94,8 +812,9 @@ impl SimpleDelay {
     
     /// Update the modulation phase
     fn update_modulation_phase(&mut self) {
-        if self.modulation_rate > 0.0 {
-            self.modulation_phase += self.modulation_rate / self.sample_rate as f32;
+        let rate = self.get_modulation_rate();
+        if rate > 0.0 {
+            self.modulation_phase += rate / self.sample_rate as f32;
             if self.modulation_phase >= 1.0 {
                 self.modulation_phase -= 1.0;
             }
@@ -160,7 +879,7 @@ impl BaseDelay for SimpleDelay {
     }
     
     fn set_feedback(&mut self, feedback: f32) {
-        self.feedback = feedback.clamp(0.0, 0.9);
+        self.feedback = feedback.clamp(-0.9, 0.9);
     }
     
     fn set_wet_mix(&mut self, wet_mix: f32) {
@@ -176,11 +895,20 @@ pub struct StereoDelay {
     feedback: f32,
     wet_mix: f32,
     dry_mix: f32,
+    wet_gain: f32,
+    mix_law: MixLaw,
+    interpolation: InterpolationMode,
     
     // Stereo-specific parameters
     left_delay: f32,
     right_delay: f32,
     ping_pong: bool,
+    ping_pong_mode: PingPongMode,
+    ping_pong_start: PingPongStart,
+    tap_pan_mode: TapPanMode,
+    tap_spread: f32,
+    /// Pan positions of the left and right taps, `None` when they keep their channels
+    tap_pans: Option<(f32, f32)>,
     stereo_width: f32,
     cross_feedback: f32,
     
@@ -192,15 +920,202 @@ pub struct StereoDelay {
     left_write_index: usize,
     right_write_index: usize,
     
+    // Dry path delay line aligning the dry signal with the earliest repeat
+    latency_compensation: bool,
+    left_dry_line: Vec<f32>,
+    right_dry_line: Vec<f32>,
+    dry_write_index: usize,
+    
     // Stereo enhancement
     mid_side_enabled: bool,
     
-    // Cross-feedback distortion
+    // Mono compatibility: caps the side boost from the mid and side power envelopes
+    mono_safe: bool,
+    mono_safe_coeff: f32,
+    
+    // Run a single delay line on the mono sum, skipping everything stereo
+    mono_mode: bool,
+    
+    // Peak limiter on the wet signal alone, so runaway repeats are tamed without touching the dry attack
+    wet_limiter: Limiter,
+    wet_limiter_enabled: bool,
+    wet_limiter_threshold_db: f32,
+    
+    // Double-tracking: two short detuned copies of the dry signal panned apart
+    double_tracker: DoubleTracker,
+    double_track_enabled: bool,
+    
+    // Shimmer: octave-up, diffused repeats in the feedback path
+    shimmer: Shimmer,
+    
+    // Auto-width: the width follows the correlation of the widened repeats
+    auto_width_enabled: bool,
+    auto_width_target: f32,
+    auto_width_meter: CorrelationMeter,
+    mid_power: f32,
+    side_power: f32,
+    
+    // Gain staging and DC blocker cutoff for the kind of source on the input
+    input_source: InputSource,
+    input_gain: f32,
+    
+    // Amp input loading of a direct guitar signal, before the effect chain
+    di_sim_enabled: bool,
+    left_di_sim: StateVariableFilter,
+    right_di_sim: StateVariableFilter,
+    
+    // DC blocking on the input, so offsets are not built up by the feedback
+    dc_block: bool,
+    left_dc_blocker: DcBlocker,
+    right_dc_blocker: DcBlocker,
+    
+    // Cross-feedback distortion, applied at the stage chosen by the placement
     cross_feedback_distortion: CrossFeedbackDistortion,
+    distortion_placement: DistortionPlacement,
+    
+    // Keep the dry path clear of every distortion stage, whatever the placement
+    distortion_dry_bypass: bool,
+    
+    // Stages around the delay in configured order; a distortion stage in it
+    // overrides the placement and mirrors the distortion settings
+    chain: EffectChain,
+    distortion_in_chain: bool,
+    
+    // Tone EQ tilt and mix, only heard when the chain has an eq stage
+    eq_tilt: f32,
+    eq_mix: f32,
+    
+    // Tone EQs on the dry and the wet path, each bypassed on its own
+    dry_eq: EqStage,
+    dry_eq_enabled: bool,
+    wet_eq: EqStage,
+    wet_eq_enabled: bool,
+    
+    // Self-oscillation support: feedback above 1.0 is kept bounded by the limiter
+    allow_self_oscillation: bool,
+    feedback_limiter: Limiter,
+    
+    // Tilt EQ applied to the repeats on each pass through the feedback path
+    left_feedback_tilt: TiltEq,
+    right_feedback_tilt: TiltEq,
+    
+    // High-pass and low-pass band limiting of the repeats, tighter on every pass
+    left_feedback_filter: BandLimiter,
+    right_feedback_filter: BandLimiter,
+    
+    // Resonant dub filter in the feedback path, bypassed at FULL_RANGE_HZ, with its
+    // cutoff swept by a free-running or tempo-synced LFO
+    left_dub_filter: StateVariableFilter,
+    right_dub_filter: StateVariableFilter,
+    dub_cutoff: f32,
+    dub_lfo_rate: f32,
+    dub_lfo_depth: f32,
+    dub_lfo_sync: bool,
+    dub_lfo_division: f32,
+    dub_lfo_phase: f32,
+    
+    // Tape-style soft saturation of the repeats, always in the loop (0.0 is linear)
+    feedback_saturation: f32,
+    
+    // Voicing last picked for the feedback path, Custom once a part of it is changed
+    feedback_color: FeedbackColor,
+    
+    // Whether the feedback filters run before or after the distortion and saturation
+    feedback_stage_order: FeedbackStageOrder,
+    
+    // Resolution of the samples written to the delay lines, full at MAX_FEEDBACK_BIT_DEPTH
+    feedback_bit_depth: f32,
+    
+    // Optional user processor run on the repeats before they are written back
+    feedback_insert: Option<FeedbackInsert>,
+    
+    // Scale applied to the recirculated signal to keep the loop stable
+    loop_gain_scale: f32,
+    
+    // Optional compressor evening out the dynamics of the mixed output
+    compressor_enabled: bool,
+    output_compressor: Compressor,
+    
+    // Optional volume-pedal swell on each detected note onset
+    swell_enabled: bool,
+    swell: Swell,
+    
+    // Time the taps slide to new delay times on a tempo change, 0.0 uses the delay glide
+    bpm_glide_ms: f32,
+    
+    // Fraction the left tap is shortened and the right tap lengthened by
+    delay_spread: f32,
+    
+    // Optional sweep of both delay times on each detected note onset, as a multiplier
+    delay_sweep_trigger: bool,
+    delay_sweep_amount: f32,
+    delay_sweep_onset: OnsetDetector,
+    delay_sweep: SmoothedValue,
+    
+    // Optional metronome click mixed into the output, on the same sample clock
+    metronome_enabled: bool,
+    metronome: Metronome,
+    
+    // Master gain ramped up on start and down before stop to avoid clicks
+    fade_gain: SmoothedValue,
+    
+    // Freeze: the lines recirculate unchanged and take no new input, crossfaded
+    // in and out so the loop does not click
+    freeze: SmoothedValue,
+    freeze_kill_dry: bool,
+    
+    // Auto-freeze: freeze engages when the input falls silent after a phrase and
+    // releases when playing resumes; `auto_frozen` marks a freeze it engaged itself
+    auto_freeze: AutoFreeze,
+    auto_freeze_enabled: bool,
+    auto_frozen: bool,
+    
+    // Spectral freeze: a resynthesized pad of one moment replaces the repeats
+    #[cfg(feature = "spectral")]
+    spectral_freeze: SpectralFreeze,
+    
+    // Soundcheck: only the input is heard and the chain is not run at all
+    monitor_dry_only: bool,
+    
+    // Output level metering, updated every sample
+    output_meter: StereoMeter,
+    correlation_meter: CorrelationMeter,
+    
+    // Peak and clip statistics since the last reset, read without locking the delay
+    session_stats: Arc<SessionStats>,
+    
+    // Output copy for a WAV recording in progress
+    recording_tap: Option<RecordingTap>,
+    
+    // Hard safety ceiling on the final output, in dBFS and as a linear level
+    max_output_db: f32,
+    output_ceiling: f32,
+    
+    // Tails: input is no longer fed while the repeats ring out to silence
+    tailing: bool,
+    tail_samples: usize,
+    tail_quiet_samples: usize,
+    
+    // Smoothed parameters, ramped per sample towards the values set above
+    left_delay_samples: SmoothedValue,
+    right_delay_samples: SmoothedValue,
+    smoothed_feedback: SmoothedValue,
+    // The wet mix is smoothed and the gains derived from it, so the mix law holds while it ramps
+    smoothed_wet_mix: SmoothedValue,
+    
+    // Largest feedback gain that keeps the loop gain below MAX_LOOP_GAIN
+    max_stable_feedback: f32,
+    
+    // Preset load: the taps of the old preset fade out over the crossfade
+    preset_crossfade_samples: usize,
+    outgoing_delay_samples: (f32, f32),
+    outgoing_crossfade_samples: usize,
+    outgoing_remaining: usize,
 }
 
 impl StereoDelay {
     /// Create a new stereo delay effect
+    #[allow(clippy::too_many_arguments)]
     pub fn new(
         sample_rate: u32,
         left_delay: f32,
@@ -215,27 +1130,63 @@ impl StereoDelay {
         distortion_drive: f32,
         distortion_mix: f32,
     ) -> Self {
-        let left_buffer_size = (left_delay * sample_rate as f32) as usize;
-        let right_buffer_size = (right_delay * sample_rate as f32) as usize;
+        // Buffers hold the longest delay so the read taps can move without reallocating
+        let max_delay_time = 4.0;
+        let left_delay = left_delay.clamp(0.001, max_delay_time);
+        let right_delay = right_delay.clamp(0.001, max_delay_time);
+        let left_buffer_size = (max_delay_time * sample_rate as f32) as usize + 2;
+        let right_buffer_size = left_buffer_size;
+        let smoothing = |param: &str| default_smoothing_ms(param).unwrap_or(0.0);
         
-        Self {
+        let mut stereo_delay = Self {
             sample_rate,
-            max_delay_time: 4.0,
-            feedback: feedback.clamp(0.0, 0.9),
+            max_delay_time,
+            feedback: feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK),
             wet_mix: wet_mix.clamp(0.0, 1.0),
-            dry_mix: 1.0 - wet_mix.clamp(0.0, 1.0),
+            // Both gains are set by `update_mix_gains` below
+            dry_mix: 0.0,
+            wet_gain: 0.0,
+            mix_law: MixLaw::Linear,
+            interpolation: InterpolationMode::Linear,
             left_delay,
             right_delay,
             ping_pong,
+            ping_pong_mode: PingPongMode::Swap,
+            ping_pong_start: PingPongStart::Left,
+            tap_pan_mode: TapPanMode::Manual,
+            tap_spread: DEFAULT_TAP_SPREAD,
+            tap_pans: None,
             stereo_width: stereo_width.clamp(0.0, 1.0),
-            cross_feedback: cross_feedback.clamp(0.0, 0.5),
+            cross_feedback: cross_feedback.clamp(0.0, MAX_CROSS_FEEDBACK),
             _left_buffer_size: left_buffer_size,
             _right_buffer_size: right_buffer_size,
             left_buffer: vec![0.0; left_buffer_size],
             right_buffer: vec![0.0; right_buffer_size],
             left_write_index: 0,
             right_write_index: 0,
+            latency_compensation: false,
+            left_dry_line: Vec::new(),
+            right_dry_line: Vec::new(),
+            dry_write_index: 0,
             mid_side_enabled: stereo_width > 0.0,
+            mono_safe: false,
+            mono_safe_coeff: time_constant_coeff(MONO_SAFE_ENVELOPE_MS, sample_rate),
+            mono_mode: false,
+            wet_limiter: Limiter::new(
+                10f32.powf(DEFAULT_WET_LIMITER_THRESHOLD_DB / 20.0),
+                WET_LIMITER_RELEASE_MS,
+                sample_rate,
+            ),
+            wet_limiter_enabled: false,
+            wet_limiter_threshold_db: DEFAULT_WET_LIMITER_THRESHOLD_DB,
+            double_tracker: DoubleTracker::new(sample_rate),
+            double_track_enabled: false,
+            shimmer: Shimmer::new(sample_rate),
+            auto_width_enabled: false,
+            auto_width_target: DEFAULT_AUTO_WIDTH_TARGET,
+            auto_width_meter: CorrelationMeter::new(sample_rate),
+            mid_power: 0.0,
+            side_power: 0.0,
             cross_feedback_distortion: CrossFeedbackDistortion::new(
                 cross_feedback_distortion,
                 distortion_type,
@@ -243,236 +1194,4850 @@ impl StereoDelay {
                 distortion_mix,
                 sample_rate,
             ),
-        }
+            distortion_placement: DistortionPlacement::Feedback,
+            distortion_dry_bypass: false,
+            chain: EffectChain::default(),
+            distortion_in_chain: false,
+            eq_tilt: 0.0,
+            eq_mix: 1.0,
+            dry_eq: EqStage::new(0.0, sample_rate),
+            dry_eq_enabled: false,
+            wet_eq: EqStage::new(0.0, sample_rate),
+            wet_eq_enabled: false,
+            input_source: InputSource::Instrument,
+            input_gain: 1.0,
+            di_sim_enabled: false,
+            left_di_sim: StateVariableFilter::new(DI_SIM_CUTOFF_HZ, DEFAULT_DI_SIM_RESONANCE, sample_rate),
+            right_di_sim: StateVariableFilter::new(DI_SIM_CUTOFF_HZ, DEFAULT_DI_SIM_RESONANCE, sample_rate),
+            dc_block: true,
+            left_dc_blocker: DcBlocker::new(DC_BLOCK_CUTOFF_HZ, sample_rate),
+            right_dc_blocker: DcBlocker::new(DC_BLOCK_CUTOFF_HZ, sample_rate),
+            allow_self_oscillation: false,
+            feedback_limiter: Limiter::new(1.0, 50.0, sample_rate),
+            left_feedback_tilt: TiltEq::new(0.0, sample_rate),
+            right_feedback_tilt: TiltEq::new(0.0, sample_rate),
+            left_feedback_filter: BandLimiter::new(sample_rate),
+            right_feedback_filter: BandLimiter::new(sample_rate),
+            left_dub_filter: StateVariableFilter::new(FULL_RANGE_HZ, 0.0, sample_rate),
+            right_dub_filter: StateVariableFilter::new(FULL_RANGE_HZ, 0.0, sample_rate),
+            dub_cutoff: FULL_RANGE_HZ,
+            dub_lfo_rate: 0.0,
+            dub_lfo_depth: 0.0,
+            dub_lfo_sync: false,
+            dub_lfo_division: 0.25,
+            dub_lfo_phase: 0.0,
+            feedback_saturation: 0.0,
+            feedback_color: FeedbackColor::Custom,
+            feedback_stage_order: FeedbackStageOrder::FilterFirst,
+            feedback_bit_depth: MAX_FEEDBACK_BIT_DEPTH,
+            feedback_insert: None,
+            loop_gain_scale: 1.0,
+            compressor_enabled: false,
+            output_compressor: Compressor::new(-18.0, 4.0, 10.0, 150.0, 0.0, sample_rate),
+            swell_enabled: false,
+            swell: Swell::new(500.0, 0.5, sample_rate),
+            bpm_glide_ms: 0.0,
+            delay_sweep_trigger: false,
+            delay_sweep_amount: DEFAULT_DELAY_SWEEP_AMOUNT,
+            delay_spread: 0.0,
+            delay_sweep_onset: OnsetDetector::new(DELAY_SWEEP_SENSITIVITY, sample_rate),
+            delay_sweep: SmoothedValue::new(1.0, DELAY_SWEEP_MS, sample_rate),
+            metronome_enabled: false,
+            metronome: Metronome::new(DEFAULT_METRONOME_BPM, 1, 0.5, sample_rate),
+            fade_gain: SmoothedValue::new(1.0, DEFAULT_FADE_MS, sample_rate),
+            freeze: SmoothedValue::new(0.0, DEFAULT_FREEZE_CROSSFADE_MS, sample_rate),
+            freeze_kill_dry: false,
+            auto_freeze: AutoFreeze::new(DEFAULT_AUTO_FREEZE_THRESHOLD_DB, sample_rate),
+            auto_freeze_enabled: false,
+            auto_frozen: false,
+            #[cfg(feature = "spectral")]
+            spectral_freeze: SpectralFreeze::new(DEFAULT_FREEZE_CROSSFADE_MS, sample_rate),
+            monitor_dry_only: false,
+            output_meter: StereoMeter::new(MeterMode::Vu, sample_rate),
+            correlation_meter: CorrelationMeter::new(sample_rate),
+            session_stats: Arc::new(SessionStats::new()),
+            recording_tap: None,
+            max_output_db: MAX_OUTPUT_CEILING_DB,
+            output_ceiling: 1.0,
+            tailing: false,
+            tail_samples: 0,
+            tail_quiet_samples: 0,
+            left_delay_samples: SmoothedValue::new(left_delay * sample_rate as f32, smoothing("left_delay"), sample_rate),
+            right_delay_samples: SmoothedValue::new(right_delay * sample_rate as f32, smoothing("right_delay"), sample_rate),
+            smoothed_feedback: SmoothedValue::new(feedback.clamp(-MAX_FEEDBACK, MAX_FEEDBACK), smoothing("feedback"), sample_rate),
+            smoothed_wet_mix: SmoothedValue::new(0.0, smoothing("wet_mix"), sample_rate),
+            max_stable_feedback: MAX_FEEDBACK,
+            preset_crossfade_samples: (DEFAULT_PRESET_CROSSFADE_MS / 1000.0 * sample_rate as f32) as usize,
+            outgoing_delay_samples: (0.0, 0.0),
+            outgoing_crossfade_samples: 0,
+            outgoing_remaining: 0,
+        };
+        stereo_delay.update_mix_gains();
+        stereo_delay.smoothed_wet_mix.snap();
+        stereo_delay.update_loop_gain_scale();
+        stereo_delay
     }
     
-    /// Set the left channel delay time
-    pub fn set_left_delay(&mut self, delay_time: f32) {
-        self.left_delay = delay_time.clamp(0.001, self.max_delay_time);
-        let new_buffer_size = (self.left_delay * self.sample_rate as f32) as usize;
+    /// Create a stereo delay effect from the delay and distortion settings of a configuration
+    pub fn from_config(config: &AudioConfig) -> Self {
+        let distortion_type = DistortionType::from(config.distortion.distortion_type.as_str());
+        let mut stereo_delay = Self::new(
+            config.sample_rate,
+            config.stereo_delay.left_delay,
+            config.stereo_delay.right_delay,
+            config.stereo_delay.feedback,
+            config.stereo_delay.wet_mix,
+            config.stereo_delay.ping_pong,
+            config.stereo_delay.stereo_width,
+            config.stereo_delay.cross_feedback,
+            config.distortion.enabled,
+            distortion_type,
+            config.distortion.drive,
+            config.distortion.mix,
+        );
+        stereo_delay.apply_config(config);
         
-        if new_buffer_size != self.left_buffer.len() {
-            self.left_buffer = vec![0.0; new_buffer_size];
-            self.left_write_index = 0;
-        }
+        // Start at the configured values rather than ramping towards them
+        stereo_delay.snap_smoothed_parameters();
+        stereo_delay
     }
     
-    /// Set the right channel delay time
-    pub fn set_right_delay(&mut self, delay_time: f32) {
-        self.right_delay = delay_time.clamp(0.001, self.max_delay_time);
-        let new_buffer_size = (self.right_delay * self.sample_rate as f32) as usize;
+    /// Apply the effect settings of a configuration
+    ///
+    /// Unlike `from_config` the delay lines keep their contents and smoothed
+    /// parameters ramp to their new values, so this is safe while audio is running.
+    /// The sample rate of the delay is not changed.
+    pub fn apply_config(&mut self, config: &AudioConfig) {
+        let delay = &config.stereo_delay;
+        let distortion = &config.distortion;
+        let compressor = &config.compressor;
+        let swell = &config.swell;
+        let metronome = &config.metronome;
+        let shimmer = &config.shimmer;
         
-        if new_buffer_size != self.right_buffer.len() {
-            self.right_buffer = vec![0.0; new_buffer_size];
-            self.right_write_index = 0;
+        self.set_left_delay(delay.left_delay);
+        self.set_right_delay(delay.right_delay);
+        self.set_stereo_parameters(Some(delay.ping_pong), Some(delay.stereo_width), Some(delay.cross_feedback));
+        self.set_ping_pong_mode(delay.ping_pong_mode);
+        self.set_ping_pong_start(delay.ping_pong_start);
+        self.set_tap_pan(Some(delay.tap_pan_mode), Some(delay.tap_spread));
+        self.set_mono_safe(delay.mono_safe);
+        self.set_mono_mode(delay.mono_mode);
+        self.set_wet_limiter(Some(delay.wet_limiter_enabled), Some(delay.wet_limiter_threshold));
+        self.set_double_track(Some(delay.double_track_enabled), Some(delay.double_track_spread), Some(delay.double_track_detune));
+        self.set_auto_width(Some(delay.auto_width_enabled), Some(delay.auto_width_target_correlation));
+        self.set_latency_compensation(delay.latency_compensation);
+        self.set_cross_feedback_distortion(
+            Some(distortion.enabled),
+            Some(DistortionType::from(distortion.distortion_type.as_str())),
+            Some(distortion.drive),
+            Some(distortion.mix),
+            Some(distortion.feedback_intensity),
+        );
+        self.set_distortion_placement(distortion.placement);
+        self.set_distortion_dry_bypass(distortion.dry_bypass);
+        self.set_distortion_auto_gain_comp(distortion.auto_gain_comp);
+        self.set_distortion_tone(distortion.tone);
+        self.set_mix_law(delay.mix_law);
+        self.set_interpolation(delay.interpolation);
+        self.set_wet_mix(delay.wet_mix);
+        self.set_allow_self_oscillation(delay.allow_self_oscillation);
+        self.set_feedback(delay.feedback);
+        self.set_feedback_tilt(delay.feedback_tilt);
+        self.set_feedback_saturation(delay.feedback_saturation);
+        self.set_feedback_bit_depth(delay.feedback_bit_depth);
+        self.set_bpm_glide(delay.bpm_glide_ms);
+        self.set_delay_sweep(Some(delay.delay_sweep_trigger), Some(delay.delay_sweep_amount));
+        self.set_delay_spread(delay.delay_spread);
+        // A band that fails validation leaves the current one in place
+        let _ = self.set_feedback_filter(delay.feedback_hp_hz, delay.feedback_lp_hz);
+        self.set_dub_filter(Some(delay.dub_cutoff), Some(delay.dub_resonance));
+        self.set_dub_lfo(Some(delay.dub_lfo_rate), Some(delay.dub_lfo_depth), Some(delay.dub_lfo_sync), Some(delay.dub_lfo_division));
+        self.set_feedback_color(delay.feedback_color);
+        self.set_feedback_stage_order(delay.feedback_stage_order);
+        self.set_meter_mode(config.meter_mode);
+        self.set_input_source(config.input_source);
+        self.set_di_sim(Some(config.di_sim_enabled), Some(config.di_sim_resonance));
+        self.set_dc_block(config.dc_block);
+        self.set_max_output_db(config.max_output_db);
+        set_anti_denormal(config.anti_denormal);
+        self.set_eq_tilt(config.eq_tilt);
+        self.set_eq_mix(config.eq_mix);
+        self.set_dry_eq(Some(config.eq_dry.enabled), Some(config.eq_dry.tilt));
+        self.set_wet_eq(Some(config.eq_wet.enabled), Some(config.eq_wet.tilt));
+        // A chain that fails validation leaves the current one in place
+        let _ = self.set_effect_chain(&config.effect_chain);
+        self.set_fade_time(config.fade_out_ms);
+        self.set_preset_crossfade(config.preset_crossfade_ms);
+        self.set_freeze_crossfade(delay.freeze_crossfade_ms);
+        self.set_freeze_kill_dry(delay.freeze_kill_dry);
+        self.set_auto_freeze(Some(delay.auto_freeze_enabled), Some(delay.auto_freeze_threshold));
+        self.set_compressor(
+            Some(compressor.enabled),
+            Some(compressor.threshold_db),
+            Some(compressor.ratio),
+            Some(compressor.attack_ms),
+            Some(compressor.release_ms),
+            Some(compressor.makeup_db),
+        );
+        self.set_swell(Some(swell.enabled), Some(swell.time_ms), Some(swell.sensitivity));
+        self.set_metronome_bpm(delay.bpm.unwrap_or(DEFAULT_METRONOME_BPM));
+        self.set_metronome(Some(metronome.enabled), Some(metronome.volume), Some(metronome.subdivision));
+        self.set_shimmer(Some(shimmer.amount), Some(shimmer.pitch_mix), Some(shimmer.diffusion));
+        for param in SMOOTHED_PARAMETERS {
+            let smoothing_ms = delay.smoothing_ms.get(*param).copied().or_else(|| default_smoothing_ms(param));
+            if let Some(smoothing_ms) = smoothing_ms {
+                self.set_smoothing_time(param, smoothing_ms);
+            }
         }
     }
     
+    /// Write the current effect settings into a configuration
+    ///
+    /// The counterpart of `apply_config`: fields the delay does not own (sample rate,
+    /// devices, BPM, tails, macro) are left as they are.
+    pub fn write_config(&self, config: &mut AudioConfig) {
+        let distortion = &self.cross_feedback_distortion;
+        
+        config.stereo_delay.left_delay = self.left_delay;
+        config.stereo_delay.right_delay = self.right_delay;
+        config.stereo_delay.feedback = self.feedback;
+        config.stereo_delay.allow_self_oscillation = self.allow_self_oscillation;
+        config.stereo_delay.wet_mix = self.wet_mix;
+        config.stereo_delay.mix_law = self.mix_law;
+        config.stereo_delay.interpolation = self.interpolation;
+        config.stereo_delay.ping_pong = self.ping_pong;
+        config.stereo_delay.ping_pong_mode = self.ping_pong_mode;
+        config.stereo_delay.ping_pong_start = self.ping_pong_start;
+        config.stereo_delay.tap_pan_mode = self.tap_pan_mode;
+        config.stereo_delay.tap_spread = self.tap_spread;
+        config.stereo_delay.stereo_width = self.stereo_width;
+        config.stereo_delay.mono_safe = self.mono_safe;
+        config.stereo_delay.mono_mode = self.mono_mode;
+        config.stereo_delay.wet_limiter_enabled = self.wet_limiter_enabled;
+        config.stereo_delay.wet_limiter_threshold = self.wet_limiter_threshold_db;
+        config.stereo_delay.double_track_enabled = self.double_track_enabled;
+        config.stereo_delay.double_track_spread = self.double_tracker.get_spread();
+        config.stereo_delay.double_track_detune = self.double_tracker.get_detune();
+        config.stereo_delay.auto_width_enabled = self.auto_width_enabled;
+        config.stereo_delay.auto_width_target_correlation = self.auto_width_target;
+        config.stereo_delay.latency_compensation = self.latency_compensation;
+        config.stereo_delay.cross_feedback = self.cross_feedback;
+        config.stereo_delay.feedback_tilt = self.get_feedback_tilt();
+        config.stereo_delay.feedback_saturation = self.feedback_saturation;
+        config.stereo_delay.feedback_color = self.feedback_color;
+        config.stereo_delay.feedback_stage_order = self.feedback_stage_order;
+        config.stereo_delay.feedback_bit_depth = self.feedback_bit_depth;
+        config.stereo_delay.bpm_glide_ms = self.bpm_glide_ms;
+        config.stereo_delay.delay_sweep_trigger = self.delay_sweep_trigger;
+        config.stereo_delay.delay_sweep_amount = self.delay_sweep_amount;
+        config.stereo_delay.delay_spread = self.delay_spread;
+        config.stereo_delay.feedback_hp_hz = self.left_feedback_filter.get_high_pass_hz();
+        config.stereo_delay.feedback_lp_hz = self.left_feedback_filter.get_low_pass_hz();
+        config.stereo_delay.dub_cutoff = self.dub_cutoff;
+        config.stereo_delay.dub_resonance = self.left_dub_filter.get_resonance();
+        config.stereo_delay.dub_lfo_rate = self.dub_lfo_rate;
+        config.stereo_delay.dub_lfo_depth = self.dub_lfo_depth;
+        config.stereo_delay.dub_lfo_sync = self.dub_lfo_sync;
+        config.stereo_delay.dub_lfo_division = self.dub_lfo_division;
+        config.stereo_delay.freeze_crossfade_ms = self.get_freeze_crossfade();
+        config.stereo_delay.freeze_kill_dry = self.freeze_kill_dry;
+        config.stereo_delay.auto_freeze_enabled = self.auto_freeze_enabled;
+        config.stereo_delay.auto_freeze_threshold = self.auto_freeze.get_threshold_db();
+        config.stereo_delay.smoothing_ms = SMOOTHED_PARAMETERS
+            .iter()
+            .filter_map(|param| self.get_smoothing_time(param).map(|ms| (param.to_string(), ms)))
+            .collect();
+        
+        config.distortion.enabled = distortion.is_enabled();
+        config.distortion.distortion_type = distortion.get_distortion_type().to_string();
+        config.distortion.drive = distortion.get_drive();
+        config.distortion.mix = distortion.get_mix();
+        config.distortion.feedback_intensity = distortion.get_feedback_intensity();
+        config.distortion.placement = self.distortion_placement;
+        config.distortion.dry_bypass = self.distortion_dry_bypass;
+        config.distortion.auto_gain_comp = distortion.is_auto_gain_comp();
+        config.distortion.tone = distortion.get_tone();
+        
+        config.compressor.enabled = self.compressor_enabled;
+        config.compressor.threshold_db = self.output_compressor.get_threshold_db();
+        config.compressor.ratio = self.output_compressor.get_ratio();
+        config.compressor.attack_ms = self.output_compressor.get_attack_ms();
+        config.compressor.release_ms = self.output_compressor.get_release_ms();
+        config.compressor.makeup_db = self.output_compressor.get_makeup_db();
+        
+        config.swell.enabled = self.swell_enabled;
+        config.swell.time_ms = self.swell.get_time_ms();
+        config.swell.sensitivity = self.swell.get_sensitivity();
+        
+        config.metronome.enabled = self.metronome_enabled;
+        config.metronome.volume = self.metronome.get_volume();
+        config.metronome.subdivision = self.metronome.get_subdivision();
+        
+        config.shimmer.amount = self.shimmer.get_amount();
+        config.shimmer.pitch_mix = self.shimmer.get_pitch_mix();
+        config.shimmer.diffusion = self.shimmer.get_diffusion();
+        
+        config.meter_mode = self.get_meter_mode();
+        config.input_source = self.input_source;
+        config.di_sim_enabled = self.di_sim_enabled;
+        config.di_sim_resonance = self.get_di_sim_resonance();
+        config.dc_block = self.dc_block;
+        config.eq_tilt = self.get_eq_tilt();
+        config.eq_mix = self.get_eq_mix();
+        config.eq_dry.enabled = self.dry_eq_enabled;
+        config.eq_dry.tilt = self.dry_eq.get_tilt();
+        config.eq_wet.enabled = self.wet_eq_enabled;
+        config.eq_wet.tilt = self.wet_eq.get_tilt();
+        config.effect_chain = self.get_effect_chain().into_iter().map(String::from).collect();
+        config.fade_out_ms = self.get_fade_time();
+        config.max_output_db = self.max_output_db;
+        config.preset_crossfade_ms = self.get_preset_crossfade();
+    }
+    
+    /// Set the ramp time used when a smoothed parameter changes
+    ///
+    /// Returns false if `param` is not one of `smoothing::SMOOTHED_PARAMETERS`.
+    pub fn set_smoothing_time(&mut self, param: &str, smoothing_ms: f32) -> bool {
+        let sample_rate = self.sample_rate;
+        match param {
+            "left_delay" => self.left_delay_samples.set_ramp_time(smoothing_ms, sample_rate),
+            "right_delay" => self.right_delay_samples.set_ramp_time(smoothing_ms, sample_rate),
+            "feedback" => self.smoothed_feedback.set_ramp_time(smoothing_ms, sample_rate),
+            "wet_mix" => self.smoothed_wet_mix.set_ramp_time(smoothing_ms, sample_rate),
+            _ => return false,
+        }
+        true
+    }
+    
+    /// Get the ramp time of a smoothed parameter in milliseconds
+    pub fn get_smoothing_time(&self, param: &str) -> Option<f32> {
+        let ramp_samples = match param {
+            "left_delay" => self.left_delay_samples.ramp_samples(),
+            "right_delay" => self.right_delay_samples.ramp_samples(),
+            "feedback" => self.smoothed_feedback.ramp_samples(),
+            "wet_mix" => self.smoothed_wet_mix.ramp_samples(),
+            _ => return None,
+        };
+        Some(ramp_samples as f32 * 1000.0 / self.sample_rate as f32)
+    }
+    
+    /// Get the value a smoothed parameter currently has on its way to the target
+    pub fn get_smoothed_value(&self, param: &str) -> Option<f32> {
+        match param {
+            "left_delay" => Some(self.left_delay_samples.current() / self.sample_rate as f32),
+            "right_delay" => Some(self.right_delay_samples.current() / self.sample_rate as f32),
+            "feedback" => Some(self.smoothed_feedback.current()),
+            "wet_mix" => Some(self.smoothed_wet_mix.current()),
+            _ => None,
+        }
+    }
+    
+    /// Load a preset, crossfading from the current delay taps to the new ones
+    ///
+    /// Like `apply_config`, but changed delay times do not glide. The taps jump to
+    /// the new times at once while the taps of the old times fade out over the
+    /// preset crossfade. Both read the same delay lines, so the repeats already
+    /// playing carry on (trails) without a click or a pitch sweep. With a zero
+    /// crossfade this is the same as `apply_config`.
+    pub fn load_preset(&mut self, config: &AudioConfig) {
+        let outgoing = (self.left_delay_samples.current(), self.right_delay_samples.current());
+        let crossfade_samples = self.preset_crossfade_samples;
+        self.apply_config(config);
+        if crossfade_samples == 0 {
+            return;
+        }
+        
+        self.left_delay_samples.snap();
+        self.right_delay_samples.snap();
+        if outgoing != (self.left_delay_samples.current(), self.right_delay_samples.current()) {
+            self.outgoing_delay_samples = outgoing;
+            self.outgoing_crossfade_samples = crossfade_samples;
+            self.outgoing_remaining = crossfade_samples;
+        }
+    }
+    
+    /// Set the time the taps of the old preset take to fade out on a preset load in milliseconds
+    pub fn set_preset_crossfade(&mut self, crossfade_ms: f32) {
+        let crossfade_ms = crossfade_ms.clamp(0.0, MAX_PRESET_CROSSFADE_MS);
+        self.preset_crossfade_samples = (crossfade_ms / 1000.0 * self.sample_rate as f32) as usize;
+    }
+    
+    /// Get the preset crossfade time in milliseconds
+    pub fn get_preset_crossfade(&self) -> f32 {
+        self.preset_crossfade_samples as f32 * 1000.0 / self.sample_rate as f32
+    }
+    
+    /// Jump every smoothed parameter straight to its target
+    fn snap_smoothed_parameters(&mut self) {
+        self.left_delay_samples.snap();
+        self.right_delay_samples.snap();
+        self.smoothed_feedback.snap();
+        self.smoothed_wet_mix.snap();
+    }
+    
+    /// Set the crossfade law used for the dry/wet mix
+    pub fn set_mix_law(&mut self, mix_law: MixLaw) {
+        self.mix_law = mix_law;
+        self.update_mix_gains();
+    }
+    
+    /// Derive the dry and wet gains from the wet mix and the mix law
+    ///
+    /// The only place the gains are computed, used by the constructor,
+    /// `set_wet_mix` and `set_mix_law`, so they always agree with the wet mix.
+    fn update_mix_gains(&mut self) {
+        let (dry_mix, wet_gain) = self.mix_law.gains(self.wet_mix);
+        self.dry_mix = dry_mix;
+        self.wet_gain = wet_gain;
+        self.smoothed_wet_mix.set_target(self.wet_mix);
+    }
+    
+    /// Get the crossfade law used for the dry/wet mix
+    pub fn get_mix_law(&self) -> MixLaw {
+        self.mix_law
+    }
+    
+    /// Set how the delay taps are read between whole samples
+    pub fn set_interpolation(&mut self, interpolation: InterpolationMode) {
+        self.interpolation = interpolation;
+    }
+    
+    /// Get how the delay taps are read between whole samples
+    pub fn get_interpolation(&self) -> InterpolationMode {
+        self.interpolation
+    }
+    
+    /// Set how the two delay lines are cross-coupled when ping-pong is on
+    pub fn set_ping_pong_mode(&mut self, mode: PingPongMode) {
+        self.ping_pong_mode = mode;
+    }
+    
+    /// Get how the two delay lines are cross-coupled when ping-pong is on
+    pub fn get_ping_pong_mode(&self) -> PingPongMode {
+        self.ping_pong_mode
+    }
+    
+    /// Set the side a bouncing ping-pong puts the first echo on
+    pub fn set_ping_pong_start(&mut self, start: PingPongStart) {
+        self.ping_pong_start = start;
+    }
+    
+    /// Get the side a bouncing ping-pong puts the first echo on
+    pub fn get_ping_pong_start(&self) -> PingPongStart {
+        self.ping_pong_start
+    }
+    
+    /// Set how the left and right taps are panned and how wide the automatic panning is
+    ///
+    /// In `Spread` and `Alternate` modes the left tap is the first tap and the
+    /// right tap the last, so the two are placed at `-spread` and `spread`.
+    pub fn set_tap_pan(&mut self, mode: Option<TapPanMode>, spread: Option<f32>) {
+        if let Some(mode) = mode {
+            self.tap_pan_mode = mode;
+        }
+        if let Some(spread) = spread {
+            self.tap_spread = spread.clamp(0.0, 1.0);
+        }
+        self.tap_pans = self.tap_pan_mode.pan_positions(2, self.tap_spread).map(|pans| (pans[0], pans[1]));
+    }
+    
+    /// Get how the taps are panned
+    pub fn get_tap_pan_mode(&self) -> TapPanMode {
+        self.tap_pan_mode
+    }
+    
+    /// Get the width of the automatic tap panning
+    pub fn get_tap_spread(&self) -> f32 {
+        self.tap_spread
+    }
+    
+    /// Set the time the delay taps take to glide to a new delay time in milliseconds
+    ///
+    /// The tap moves gradually, bending the pitch of the repeats like a tape delay.
+    /// This is the smoothing time of both `left_delay` and `right_delay`.
+    pub fn set_delay_glide(&mut self, glide_ms: f32) {
+        self.set_smoothing_time("left_delay", glide_ms);
+        self.set_smoothing_time("right_delay", glide_ms);
+    }
+    
+    /// Get the delay glide time in milliseconds
+    pub fn get_delay_glide(&self) -> f32 {
+        self.get_smoothing_time("left_delay").unwrap_or(0.0)
+    }
+    
+    /// Set the time the delay taps take to slide to the times of a new tempo in milliseconds
+    ///
+    /// Used by `set_tempo_delays`, so a BPM change can slide slowly like a tape
+    /// machine changing speed while ordinary delay changes keep the delay glide.
+    /// 0.0 leaves tempo changes to the delay glide.
+    pub fn set_bpm_glide(&mut self, glide_ms: f32) {
+        self.bpm_glide_ms = glide_ms.clamp(0.0, MAX_SMOOTHING_MS);
+    }
+    
+    /// Get the BPM glide time in milliseconds
+    pub fn get_bpm_glide(&self) -> f32 {
+        self.bpm_glide_ms
+    }
+    
+    /// Move both delay taps to the delay times of a new tempo
+    ///
+    /// The taps glide over the BPM glide time when one is set, otherwise they move
+    /// like any other delay time change.
+    pub fn set_tempo_delays(&mut self, left_delay: f32, right_delay: f32) {
+        if self.bpm_glide_ms <= 0.0 {
+            self.set_left_delay(left_delay);
+            self.set_right_delay(right_delay);
+            return;
+        }
+        self.left_delay = left_delay.clamp(0.001, self.max_delay_time);
+        self.right_delay = right_delay.clamp(0.001, self.max_delay_time);
+        let sample_rate = self.sample_rate;
+        let (left_samples, right_samples) = self.spread_delay_samples();
+        self.left_delay_samples.set_target_over(left_samples, self.bpm_glide_ms, sample_rate);
+        self.right_delay_samples.set_target_over(right_samples, self.bpm_glide_ms, sample_rate);
+    }
+    
+    /// Spread the left and right delay times apart by a fraction of each (0.0 to 0.5)
+    ///
+    /// The left tap is shortened and the right tap lengthened by `spread`, so equal
+    /// delay times open up symmetrically around their center for a wide stereo
+    /// image from one control. The taps glide to the spread times like any other
+    /// delay time change; the configured delay times themselves are kept.
+    pub fn set_delay_spread(&mut self, spread: f32) {
+        self.delay_spread = spread.clamp(0.0, MAX_DELAY_SPREAD);
+        let (left_samples, right_samples) = self.spread_delay_samples();
+        self.left_delay_samples.set_target(left_samples);
+        self.right_delay_samples.set_target(right_samples);
+    }
+    
+    /// Get the delay spread as a fraction of each delay time
+    pub fn get_delay_spread(&self) -> f32 {
+        self.delay_spread
+    }
+    
+    /// Tap times of both channels in samples, with the delay spread applied
+    fn spread_delay_samples(&self) -> (f32, f32) {
+        let sample_rate = self.sample_rate as f32;
+        let longest = self.max_delay_time * sample_rate;
+        (
+            (self.left_delay * (1.0 - self.delay_spread) * sample_rate).min(longest),
+            (self.right_delay * (1.0 + self.delay_spread) * sample_rate).min(longest),
+        )
+    }
+    
+    /// Enable the onset-triggered delay sweep or set its amount (-0.5 to 0.5)
+    ///
+    /// Each detected note onset glides both delay times out by `amount` of their
+    /// length over `DELAY_SWEEP_MS` and back again, using the same ramps as
+    /// `set_delay_glide`. The moving taps bend the pitch of the echoes: a positive
+    /// amount lengthens the delay for a dive and then a rise back, a negative one
+    /// the other way round.
+    pub fn set_delay_sweep(&mut self, trigger: Option<bool>, amount: Option<f32>) {
+        if let Some(trigger) = trigger {
+            if trigger != self.delay_sweep_trigger {
+                self.delay_sweep_onset.reset();
+                self.delay_sweep.set_target(1.0);
+            }
+            self.delay_sweep_trigger = trigger;
+        }
+        if let Some(amount) = amount {
+            self.delay_sweep_amount = amount.clamp(-MAX_DELAY_SWEEP_AMOUNT, MAX_DELAY_SWEEP_AMOUNT);
+        }
+    }
+    
+    /// Check whether note onsets trigger a delay sweep
+    pub fn is_delay_sweep_trigger(&self) -> bool {
+        self.delay_sweep_trigger
+    }
+    
+    /// Get the delay sweep amount as a fraction of the delay time
+    pub fn get_delay_sweep_amount(&self) -> f32 {
+        self.delay_sweep_amount
+    }
+    
+    /// Start a sweep on a note onset, and glide back once the sweep is out
+    fn update_delay_sweep(&mut self, level: f32) {
+        if self.delay_sweep_onset.process(level) {
+            self.delay_sweep.set_target(1.0 + self.delay_sweep_amount);
+        } else if !self.delay_sweep.is_smoothing() && self.delay_sweep.target() != 1.0 {
+            self.delay_sweep.set_target(1.0);
+        }
+    }
+    
+    /// Allow feedback above 0.9 for self-oscillating (infinite) repeats
+    ///
+    /// When enabled the feedback ceiling rises to ±1.1 and a peak limiter is inserted
+    /// in the feedback path so the oscillation settles at full scale instead of
+    /// growing without bound. Disabling it re-applies the ±0.9 ceiling.
+    pub fn set_allow_self_oscillation(&mut self, allow: bool) {
+        self.allow_self_oscillation = allow;
+        self.feedback_limiter.reset();
+        self.feedback = self.feedback.clamp(-self.max_feedback(), self.max_feedback());
+        self.smoothed_feedback.set_target(self.feedback);
+        self.update_loop_gain_scale();
+    }
+    
+    /// Get the highest feedback magnitude currently accepted
+    pub fn max_feedback(&self) -> f32 {
+        if self.allow_self_oscillation {
+            MAX_SELF_OSCILLATION_FEEDBACK
+        } else {
+            MAX_FEEDBACK
+        }
+    }
+    
+    /// Set the feedback tilt (-1.0 darker repeats to 1.0 brighter repeats, 0.0 is flat)
+    pub fn set_feedback_tilt(&mut self, amount: f32) {
+        self.left_feedback_tilt.set_amount(amount);
+        self.right_feedback_tilt.set_amount(amount);
+        self.feedback_color = FeedbackColor::Custom;
+        self.update_loop_gain_scale();
+    }
+    
+    /// Get the feedback tilt amount
+    pub fn get_feedback_tilt(&self) -> f32 {
+        self.left_feedback_tilt.get_amount()
+    }
+    
+    /// Set the feedback saturation amount (0.0 linear to 1.0 heavily compressed repeats)
+    ///
+    /// Unlike the cross-feedback distortion this is always in the loop. It never
+    /// raises the level, so it cannot destabilize the feedback.
+    pub fn set_feedback_saturation(&mut self, amount: f32) {
+        self.feedback_saturation = amount.clamp(0.0, 1.0);
+        self.feedback_color = FeedbackColor::Custom;
+    }
+    
+    /// Get the feedback saturation amount
+    pub fn get_feedback_saturation(&self) -> f32 {
+        self.feedback_saturation
+    }
+    
+    /// Set the bit depth the delay lines store the signal at (4.0 gritty to 24.0 full resolution)
+    ///
+    /// Every pass through the loop is quantized again, so the repeats degrade
+    /// progressively, unlike the bit crush distortion which only colours the mix.
+    /// Fractional depths are allowed for a smooth sweep.
+    pub fn set_feedback_bit_depth(&mut self, bits: f32) {
+        self.feedback_bit_depth = bits.clamp(MIN_FEEDBACK_BIT_DEPTH, MAX_FEEDBACK_BIT_DEPTH);
+        self.feedback_color = FeedbackColor::Custom;
+    }
+    
+    /// Get the bit depth the delay lines store the signal at
+    pub fn get_feedback_bit_depth(&self) -> f32 {
+        self.feedback_bit_depth
+    }
+    
+    /// Set whether the feedback filters run before or after the distortion
+    ///
+    /// The filters are the feedback tilt, the band limiting and the dub filter;
+    /// with `DistortionFirst` they follow the feedback saturation, the insert and
+    /// a distortion placed in the feedback path instead of preceding them.
+    pub fn set_feedback_stage_order(&mut self, order: FeedbackStageOrder) {
+        self.feedback_stage_order = order;
+    }
+    
+    /// Get the order of the filters and the distortion in the feedback path
+    pub fn get_feedback_stage_order(&self) -> FeedbackStageOrder {
+        self.feedback_stage_order
+    }
+    
+    /// Run the repeats through the feedback tilt, band limiting and dub filter
+    fn filter_repeats(&mut self, left: f32, right: f32) -> (f32, f32) {
+        // Shape the repeats with the feedback tilt EQ so each pass gets brighter or darker
+        let left = self.left_feedback_tilt.process(left);
+        let right = self.right_feedback_tilt.process(right);
+        
+        // Narrow the band of the repeats a little more on every pass
+        let left = self.left_feedback_filter.process(left);
+        let right = self.right_feedback_filter.process(right);
+        
+        // Sweep the resonant dub filter over the repeats
+        if self.dub_cutoff < FULL_RANGE_HZ {
+            self.sweep_dub_filter();
+            (self.left_dub_filter.process(left), self.right_dub_filter.process(right))
+        } else {
+            (left, right)
+        }
+    }
+    
+    /// Run the repeats of the mono line through the left channel's feedback filters
+    fn filter_mono_repeat(&mut self, sample: f32) -> f32 {
+        let sample = self.left_feedback_tilt.process(sample);
+        let sample = self.left_feedback_filter.process(sample);
+        if self.dub_cutoff < FULL_RANGE_HZ {
+            self.sweep_dub_filter();
+            self.left_dub_filter.process(sample)
+        } else {
+            sample
+        }
+    }
+    
+    /// Quantize a sample to the feedback bit depth before it is stored
+    ///
+    /// Truncating toward zero never raises the level, so quiet repeats still
+    /// decay to silence instead of sticking on a quantization step.
+    fn reduce_bit_depth(&self, sample: f32) -> f32 {
+        if self.feedback_bit_depth >= MAX_FEEDBACK_BIT_DEPTH {
+            return sample;
+        }
+        let steps = 2f32.powf(self.feedback_bit_depth - 1.0);
+        (sample * steps).trunc() / steps
+    }
+    
+    /// Band-limit the repeats between a high-pass and a low-pass cutoff in Hz
+    ///
+    /// The filters sit in the feedback path, so every repeat loses a little more
+    /// of its lows and highs, like a tape echo. A 0 Hz high-pass and a low-pass at
+    /// `FULL_RANGE_HZ` or above (Nyquist, say) keep the full range. Neither filter
+    /// adds gain, so the loop stays stable.
+    pub fn set_feedback_filter(&mut self, hp_hz: f32, lp_hz: f32) -> Result<(), crate::AudioProcessorError> {
+        let lp_hz = lp_hz.min(FULL_RANGE_HZ);
+        if !(hp_hz >= 0.0 && hp_hz < lp_hz) {
+            return Err(crate::AudioProcessorError::Configuration(format!(
+                "Feedback high-pass ({} Hz) must be below the low-pass ({} Hz)",
+                hp_hz, lp_hz
+            )));
+        }
+        self.left_feedback_filter.set_band(hp_hz, lp_hz, self.sample_rate);
+        self.right_feedback_filter.set_band(hp_hz, lp_hz, self.sample_rate);
+        self.feedback_color = FeedbackColor::Custom;
+        Ok(())
+    }
+    
+    /// Get the feedback high-pass and low-pass cutoffs in Hz
+    pub fn get_feedback_filter(&self) -> (f32, f32) {
+        (self.left_feedback_filter.get_high_pass_hz(), self.left_feedback_filter.get_low_pass_hz())
+    }
+    
+    /// Set the cutoff in Hz and the resonance (0.0 to 1.0) of the dub filter
+    ///
+    /// A resonant low-pass in the feedback path, after the band limiting, so every
+    /// repeat is filtered again and the resonant peak grows with each pass. A cutoff
+    /// at `FULL_RANGE_HZ` or above bypasses it. The resonant peak counts towards the
+    /// loop gain, so with self-oscillation off high resonance lowers the feedback
+    /// ceiling, down to silence at 1.0; allow self-oscillation to let it squeal.
+    pub fn set_dub_filter(&mut self, cutoff: Option<f32>, resonance: Option<f32>) {
+        if let Some(cutoff) = cutoff {
+            let cutoff = cutoff.clamp(20.0, FULL_RANGE_HZ);
+            if cutoff < FULL_RANGE_HZ && self.dub_cutoff >= FULL_RANGE_HZ {
+                self.left_dub_filter.reset();
+                self.right_dub_filter.reset();
+            }
+            self.dub_cutoff = cutoff;
+            self.left_dub_filter.set_cutoff(cutoff);
+            self.right_dub_filter.set_cutoff(cutoff);
+        }
+        if let Some(resonance) = resonance {
+            self.left_dub_filter.set_resonance(resonance);
+            self.right_dub_filter.set_resonance(resonance);
+        }
+        self.feedback_color = FeedbackColor::Custom;
+        self.update_loop_gain_scale();
+    }
+    
+    /// Voice the feedback path as a classic delay type
+    ///
+    /// Sets the feedback tilt, saturation, bit depth, band limiting and dub filter
+    /// in one go. `Custom` keeps the current settings.
+    pub fn set_feedback_color(&mut self, color: FeedbackColor) {
+        if let Some(voicing) = color.voicing() {
+            self.set_feedback_tilt(voicing.tilt);
+            self.set_feedback_saturation(voicing.saturation);
+            self.set_feedback_bit_depth(voicing.bit_depth);
+            // Every voicing has its high-pass below its low-pass
+            let _ = self.set_feedback_filter(voicing.hp_hz, voicing.lp_hz);
+            self.set_dub_filter(Some(voicing.dub_cutoff), Some(voicing.dub_resonance));
+        }
+        self.feedback_color = color;
+    }
+    
+    /// Get the voicing of the feedback path, `Custom` once a part of it has been changed
+    pub fn get_feedback_color(&self) -> FeedbackColor {
+        self.feedback_color
+    }
+    
+    /// Get the dub filter cutoff in Hz, before the LFO sweep
+    pub fn get_dub_cutoff(&self) -> f32 {
+        self.dub_cutoff
+    }
+    
+    /// Get the dub filter resonance
+    pub fn get_dub_resonance(&self) -> f32 {
+        self.left_dub_filter.get_resonance()
+    }
+    
+    /// Set the LFO sweeping the dub filter cutoff
+    ///
+    /// `depth` is in octaves either side of the cutoff. When synced, the rate
+    /// follows the metronome tempo with one cycle per `division` of a whole note
+    /// (0.25 = 1/4 note) instead of the free rate in Hz.
+    pub fn set_dub_lfo(&mut self, rate: Option<f32>, depth: Option<f32>, sync: Option<bool>, division: Option<f32>) {
+        if let Some(rate) = rate {
+            self.dub_lfo_rate = rate.clamp(0.0, MAX_DUB_LFO_RATE);
+        }
+        if let Some(depth) = depth {
+            self.dub_lfo_depth = depth.clamp(0.0, MAX_DUB_LFO_DEPTH);
+        }
+        if let Some(sync) = sync {
+            self.dub_lfo_sync = sync;
+        }
+        if let Some(division) = division {
+            self.dub_lfo_division = division.clamp(1.0 / 64.0, 4.0);
+        }
+    }
+    
+    /// Get the dub filter LFO rate in Hz, derived from the tempo when synced
+    pub fn get_dub_lfo_rate(&self) -> f32 {
+        if self.dub_lfo_sync {
+            tempo_synced_rate(self.metronome.get_bpm(), self.dub_lfo_division)
+        } else {
+            self.dub_lfo_rate
+        }
+    }
+    
+    /// Advance the dub filter LFO and retune the filters to the swept cutoff
+    fn sweep_dub_filter(&mut self) {
+        let rate = self.get_dub_lfo_rate();
+        if rate <= 0.0 || self.dub_lfo_depth <= 0.0 {
+            return;
+        }
+        self.dub_lfo_phase += rate / self.sample_rate as f32;
+        if self.dub_lfo_phase >= 1.0 {
+            self.dub_lfo_phase -= 1.0;
+        }
+        let octaves = self.dub_lfo_depth * (2.0 * std::f32::consts::PI * self.dub_lfo_phase).sin();
+        let cutoff = (self.dub_cutoff * octaves.exp2()).clamp(20.0, FULL_RANGE_HZ);
+        self.left_dub_filter.set_cutoff(cutoff);
+        self.right_dub_filter.set_cutoff(cutoff);
+    }
+    
+    /// Insert an external processor into the feedback loop (send/return)
+    ///
+    /// The insert sees the repeats after the feedback gain and saturation, before
+    /// the input is added and they are written back. The loop gain normalization
+    /// does not know about it, so an insert that adds gain can make the loop run
+    /// away. Returns the previous insert, so it can be dropped off the audio thread.
+    pub fn set_feedback_insert(&mut self, insert: FeedbackInsert) -> Option<FeedbackInsert> {
+        self.feedback_insert.replace(insert)
+    }
+    
+    /// Remove the feedback insert and hand it back to the caller
+    pub fn take_feedback_insert(&mut self) -> Option<FeedbackInsert> {
+        self.feedback_insert.take()
+    }
+    
+    /// Check whether a feedback insert is installed
+    pub fn has_feedback_insert(&self) -> bool {
+        self.feedback_insert.is_some()
+    }
+    
+    /// Calculate the gain of one trip around the feedback loop before normalization
+    ///
+    /// Per sample the recirculated pair passes through the ping-pong swap `P`, the
+    /// mid/side width stage `W`, the feedback gain `f`, the tilt EQ and dub filter
+    /// (combined peak gain `t`) and the cross-feedback mix `C`:
+    ///
+    /// ```text
+    /// P = [0 1; 1 0]   W = [(2+w)/2  -w/2; -w/2  (2+w)/2]   C = [1 c; c 1]
+    /// ```
+    ///
+    /// All three are symmetric circulant matrices, so they share the eigenvectors
+    /// `[1, 1]` (mid) and `[1, -1]` (side), and so does their product. The mid
+    /// eigenvalue is `f·t·(1+c)`. The side eigenvalue is `-f·t·(1-c)·(1+w)`. With no
+    /// ping-pong the swap drops out and only the sign changes. A normal matrix
+    /// has spectral norm equal to its spectral radius. The loop is therefore
+    /// contractive, and every repeat decays whatever the two delay lengths, as long
+    /// as `|f|·t·max(1+c, (1-c)(1+w)) < 1`. Negative feedback only flips the sign of
+    /// both eigenvalues, so it is bounded exactly like positive feedback.
+    pub fn loop_gain(&self) -> f32 {
+        self.feedback.abs() * self.loop_gain_per_feedback()
+    }
+    
+    /// Loop gain contributed by everything in the loop except the feedback amount
+    ///
+    /// Auto-width may open the width fully at any time, so it counts as full width.
+    fn loop_gain_per_feedback(&self) -> f32 {
+        let tilt_gain = self.left_feedback_tilt.peak_gain();
+        let dub_gain = if self.dub_cutoff < FULL_RANGE_HZ { self.left_dub_filter.peak_gain() } else { 1.0 };
+        
+        // A mono line has no cross-feedback or width to add to the loop
+        if self.mono_mode {
+            return tilt_gain * dub_gain;
+        }
+        
+        let width = if self.auto_width_enabled {
+            1.0
+        } else if self.mid_side_enabled {
+            self.stereo_width
+        } else {
+            0.0
+        };
+        let mid_gain = 1.0 + self.cross_feedback;
+        let side_gain = (1.0 - self.cross_feedback) * (1.0 + width);
+        tilt_gain * dub_gain * mid_gain.max(side_gain)
+    }
+    
+    /// Recalculate the normalization that keeps the loop gain below `MAX_LOOP_GAIN`
+    ///
+    /// Self-oscillation deliberately runs the loop above unity (the feedback limiter
+    /// bounds it instead), so no normalization is applied in that mode. The ceiling
+    /// is applied to the smoothed feedback, so a ramp can never overshoot it.
+    fn update_loop_gain_scale(&mut self) {
+        self.max_stable_feedback = if self.allow_self_oscillation {
+            f32::INFINITY
+        } else {
+            MAX_LOOP_GAIN / self.loop_gain_per_feedback()
+        };
+        self.loop_gain_scale = if self.feedback.abs() > self.max_stable_feedback {
+            self.max_stable_feedback / self.feedback.abs()
+        } else {
+            1.0
+        };
+    }
+    
+    /// Enable the DI simulation or set its resonance (0.0 to 0.8)
+    ///
+    /// Plugged straight into an interface, a pickup loses the treble roll-off and
+    /// resonant bump it gets from the input capacitance of an amp, and sounds harsh.
+    /// The simulation puts that back with a gentle resonant low-pass at
+    /// `DI_SIM_CUTOFF_HZ` on the input, before the effect chain.
+    pub fn set_di_sim(&mut self, enabled: Option<bool>, resonance: Option<f32>) {
+        if let Some(enabled) = enabled {
+            if enabled != self.di_sim_enabled {
+                self.left_di_sim.reset();
+                self.right_di_sim.reset();
+            }
+            self.di_sim_enabled = enabled;
+        }
+        if let Some(resonance) = resonance {
+            let resonance = resonance.clamp(0.0, MAX_DI_SIM_RESONANCE);
+            self.left_di_sim.set_resonance(resonance);
+            self.right_di_sim.set_resonance(resonance);
+        }
+    }
+    
+    /// Set the kind of source on the input
+    ///
+    /// Sets the gain applied to the input ahead of every effect and the cutoff of
+    /// the DC blocker, see `InputSource`.
+    pub fn set_input_source(&mut self, source: InputSource) {
+        if source != self.input_source {
+            let cutoff_hz = source.dc_block_cutoff_hz();
+            self.left_dc_blocker = DcBlocker::new(cutoff_hz, self.sample_rate);
+            self.right_dc_blocker = DcBlocker::new(cutoff_hz, self.sample_rate);
+        }
+        self.input_source = source;
+        self.input_gain = source.gain();
+    }
+    
+    /// Get the kind of source on the input
+    pub fn get_input_source(&self) -> InputSource {
+        self.input_source
+    }
+    
+    /// Get the gain the input is staged with before any effect
+    pub fn get_input_gain(&self) -> f32 {
+        self.input_gain
+    }
+    
+    /// Check whether the DI simulation is enabled
+    pub fn is_di_sim(&self) -> bool {
+        self.di_sim_enabled
+    }
+    
+    /// Get the resonance of the DI simulation
+    pub fn get_di_sim_resonance(&self) -> f32 {
+        self.left_di_sim.get_resonance()
+    }
+    
+    /// Enable or disable the DC-blocking filter on the input
+    pub fn set_dc_block(&mut self, enabled: bool) {
+        if enabled != self.dc_block {
+            self.left_dc_blocker.reset();
+            self.right_dc_blocker.reset();
+        }
+        self.dc_block = enabled;
+    }
+    
+    /// Check whether the input DC-blocking filter is enabled
+    pub fn is_dc_block(&self) -> bool {
+        self.dc_block
+    }
+    
+    /// Set the ballistics of the output meters
+    pub fn set_meter_mode(&mut self, mode: MeterMode) {
+        self.output_meter.set_mode(mode);
+    }
+    
+    /// Get the ballistics of the output meters
+    pub fn get_meter_mode(&self) -> MeterMode {
+        self.output_meter.get_mode()
+    }
+    
+    /// Set output compressor parameters
+    pub fn set_compressor(
+        &mut self,
+        enabled: Option<bool>,
+        threshold_db: Option<f32>,
+        ratio: Option<f32>,
+        attack_ms: Option<f32>,
+        release_ms: Option<f32>,
+        makeup_db: Option<f32>,
+    ) {
+        if let Some(enabled) = enabled {
+            if enabled != self.compressor_enabled {
+                self.output_compressor.reset();
+            }
+            self.compressor_enabled = enabled;
+        }
+        if let Some(threshold_db) = threshold_db {
+            self.output_compressor.set_threshold_db(threshold_db);
+        }
+        if let Some(ratio) = ratio {
+            self.output_compressor.set_ratio(ratio);
+        }
+        if let Some(attack_ms) = attack_ms {
+            self.output_compressor.set_attack_ms(attack_ms);
+        }
+        if let Some(release_ms) = release_ms {
+            self.output_compressor.set_release_ms(release_ms);
+        }
+        if let Some(makeup_db) = makeup_db {
+            self.output_compressor.set_makeup_db(makeup_db);
+        }
+    }
+    
+    /// Set metronome parameters
+    ///
+    /// Enabling the metronome restarts it on a beat, so the first click plays at once.
+    pub fn set_metronome(&mut self, enabled: Option<bool>, volume: Option<f32>, subdivision: Option<u32>) {
+        if let Some(enabled) = enabled {
+            if enabled && !self.metronome_enabled {
+                self.metronome.reset();
+            }
+            self.metronome_enabled = enabled;
+        }
+        if let Some(volume) = volume {
+            self.metronome.set_volume(volume);
+        }
+        if let Some(subdivision) = subdivision {
+            self.metronome.set_subdivision(subdivision);
+        }
+    }
+    
+    /// Set the tempo the metronome clicks at
+    ///
+    /// The delay itself only knows delay times; callers pass the configured BPM here
+    /// whenever it changes.
+    pub fn set_metronome_bpm(&mut self, bpm: f32) {
+        self.metronome.set_bpm(bpm);
+    }
+    
+    /// Set auto-swell parameters
+    pub fn set_swell(&mut self, enabled: Option<bool>, time_ms: Option<f32>, sensitivity: Option<f32>) {
+        if let Some(enabled) = enabled {
+            if enabled != self.swell_enabled {
+                self.swell.reset();
+            }
+            self.swell_enabled = enabled;
+        }
+        if let Some(time_ms) = time_ms {
+            self.swell.set_time_ms(time_ms);
+        }
+        if let Some(sensitivity) = sensitivity {
+            self.swell.set_sensitivity(sensitivity);
+        }
+    }
+    
+    /// Get the smoothed output meter readings
+    pub fn get_meters(&self) -> std::collections::HashMap<String, f32> {
+        let (left, right) = self.output_meter.levels();
+        let mut meters = std::collections::HashMap::new();
+        meters.insert("meter_left".to_string(), left);
+        meters.insert("meter_right".to_string(), right);
+        meters.insert("meter_left_db".to_string(), linear_to_db(left));
+        meters.insert("meter_right_db".to_string(), linear_to_db(right));
+        meters.insert("comp_gain_reduction_db".to_string(), self.output_compressor.get_gain_reduction_db());
+        meters.insert("correlation".to_string(), self.correlation_meter.correlation());
+        meters
+    }
+    
+    /// Get the session statistics, shared so they can be read and reset while audio runs
+    pub fn session_stats(&self) -> Arc<SessionStats> {
+        Arc::clone(&self.session_stats)
+    }
+    
+    /// Copy every output frame to a recording, or stop copying with `None`
+    pub fn set_recording_tap(&mut self, tap: Option<RecordingTap>) {
+        self.recording_tap = tap;
+    }
+    
+    /// Stop feeding new input and let the existing repeats ring out
+    ///
+    /// The output carries only the decaying echoes from here on. Once it has stayed
+    /// below `TAILS_SILENCE_THRESHOLD` for longer than the longest delay line (so
+    /// every stored repeat has been heard), `tails_finished` reports true.
+    pub fn begin_tails(&mut self) {
+        self.tailing = true;
+        self.tail_samples = 0;
+        self.tail_quiet_samples = 0;
+    }
+    
+    /// Resume feeding input after a tails stop
+    pub fn end_tails(&mut self) {
+        self.tailing = false;
+        self.tail_samples = 0;
+        self.tail_quiet_samples = 0;
+    }
+    
+    /// Clear every internal state, for when a patch runs away or sounds broken
+    ///
+    /// Goes beyond `reset`, which clears the delay lines and filters: the
+    /// distortion state and the output meters are cleared as well, and smoothed
+    /// parameters jump to their targets, so the next sample starts from silence.
+    /// Parameters are kept; see the processors' `panic` to restore the defaults.
+    pub fn panic(&mut self) {
+        BaseDelay::reset(self);
+        self.cross_feedback_distortion.reset();
+        self.output_meter.reset();
+        self.correlation_meter.reset();
+        self.snap_smoothed_parameters();
+    }
+    
+    /// Check whether the delay is ringing out after a tails stop
+    pub fn is_tailing(&self) -> bool {
+        self.tailing
+    }
+    
+    /// Check whether a ringing-out tail has decayed to silence
+    ///
+    /// Self-oscillating repeats never decay, so tails are cut off after `MAX_TAILS_SECONDS`.
+    pub fn tails_finished(&self) -> bool {
+        if !self.tailing {
+            return false;
+        }
+        let longest_delay = self.left_delay_samples.target().max(self.right_delay_samples.target()) as usize;
+        let max_tail_samples = (MAX_TAILS_SECONDS * self.sample_rate as f32) as usize;
+        self.tail_quiet_samples > longest_delay || self.tail_samples >= max_tail_samples
+    }
+    
+    /// Set the time the output takes to fade in on start and out before stop
+    pub fn set_fade_time(&mut self, fade_ms: f32) {
+        self.fade_gain.set_ramp_time(fade_ms.clamp(0.0, MAX_FADE_MS), self.sample_rate);
+    }
+    
+    /// Get the output fade time in milliseconds
+    pub fn get_fade_time(&self) -> f32 {
+        self.fade_gain.ramp_samples() as f32 * 1000.0 / self.sample_rate as f32
+    }
+    
+    /// Set the level in dBFS the output can never exceed (-40.0 to 0.0)
+    ///
+    /// A hearing safety clamp, not a musical limiter: every sample above the
+    /// ceiling is cut off hard after all other processing, so runaway feedback or
+    /// distortion can never be louder than this in headphones.
+    pub fn set_max_output_db(&mut self, max_output_db: f32) {
+        self.max_output_db = max_output_db.clamp(MIN_OUTPUT_CEILING_DB, MAX_OUTPUT_CEILING_DB);
+        self.output_ceiling = 10f32.powf(self.max_output_db / 20.0);
+    }
+    
+    /// Get the output ceiling in dBFS
+    pub fn get_max_output_db(&self) -> f32 {
+        self.max_output_db
+    }
+    
+    /// Start from silence and ramp the output up over the fade time
+    pub fn begin_fade_in(&mut self) {
+        self.fade_gain.set_target(0.0);
+        self.fade_gain.snap();
+        self.fade_gain.set_target(1.0);
+    }
+    
+    /// Ramp the output down to silence over the fade time
+    pub fn begin_fade_out(&mut self) {
+        self.fade_gain.set_target(0.0);
+    }
+    
+    /// Check whether a fade out has reached silence
+    pub fn is_faded_out(&self) -> bool {
+        self.fade_gain.current() == 0.0
+    }
+    
+    /// Hold the current repeats indefinitely, or release them
+    ///
+    /// While frozen each delay line recirculates at unity gain, past the feedback,
+    /// tilt and saturation stages, and no new input is written, so whatever is in
+    /// the lines plays on as a drone. Entering and leaving crossfade over the
+    /// freeze crossfade time.
+    pub fn set_freeze(&mut self, frozen: bool) {
+        self.freeze.set_target(if frozen { 1.0 } else { 0.0 });
+        self.auto_frozen = false;
+    }
+    
+    /// Check whether the delay is frozen or crossfading into freeze
+    pub fn is_frozen(&self) -> bool {
+        self.freeze.target() > 0.0
+    }
+    
+    /// Set the time taken to enter and leave freeze in milliseconds
+    pub fn set_freeze_crossfade(&mut self, crossfade_ms: f32) {
+        let crossfade_ms = crossfade_ms.clamp(0.0, MAX_FREEZE_CROSSFADE_MS);
+        self.freeze.set_ramp_time(crossfade_ms, self.sample_rate);
+        #[cfg(feature = "spectral")]
+        self.spectral_freeze.set_crossfade(crossfade_ms, self.sample_rate);
+    }
+    
+    /// Get the freeze crossfade time in milliseconds
+    pub fn get_freeze_crossfade(&self) -> f32 {
+        self.freeze.ramp_samples() as f32 * 1000.0 / self.sample_rate as f32
+    }
+    
+    /// Hold the spectrum of the current sound as an endless pad, or release it
+    ///
+    /// Captures the input plus the repeats and resynthesizes their magnitude
+    /// spectrum with an FFT loop; the pad replaces the repeats at the output,
+    /// crossfaded over the freeze crossfade time. The delay lines keep running
+    /// underneath. Needs the `spectral` feature, without which enabling fails.
+    pub fn set_spectral_freeze(&mut self, frozen: bool) -> Result<(), crate::AudioProcessorError> {
+        Self::check_spectral_freeze(frozen)?;
+        #[cfg(feature = "spectral")]
+        self.spectral_freeze.set_frozen(frozen);
+        Ok(())
+    }
+    
+    /// Check that the spectral freeze can be engaged in this build
+    fn check_spectral_freeze(frozen: bool) -> Result<(), crate::AudioProcessorError> {
+        if frozen && !cfg!(feature = "spectral") {
+            return Err(crate::AudioProcessorError::Configuration(
+                "Spectral freeze needs the 'spectral' feature".to_string(),
+            ));
+        }
+        Ok(())
+    }
+    
+    /// Check whether the spectral freeze is holding a pad
+    pub fn is_spectral_frozen(&self) -> bool {
+        #[cfg(feature = "spectral")]
+        {
+            self.spectral_freeze.is_frozen()
+        }
+        #[cfg(not(feature = "spectral"))]
+        {
+            false
+        }
+    }
+    
+    /// Get how far the output has crossfaded to the spectral freeze pad
+    fn spectral_freeze_mix(&self) -> f32 {
+        #[cfg(feature = "spectral")]
+        {
+            self.spectral_freeze.mix()
+        }
+        #[cfg(not(feature = "spectral"))]
+        {
+            0.0
+        }
+    }
+    
+    /// Mute the dry signal while frozen, leaving only the frozen repeats
+    pub fn set_freeze_kill_dry(&mut self, kill_dry: bool) {
+        self.freeze_kill_dry = kill_dry;
+    }
+    
+    /// Check whether the dry signal is muted while frozen
+    pub fn is_freeze_kill_dry(&self) -> bool {
+        self.freeze_kill_dry
+    }
+    
+    /// Enable auto-freeze or set its threshold in dBFS (-80 to -20)
+    ///
+    /// After a phrase, once the input stays below the threshold for
+    /// `AUTO_FREEZE_HOLD_MS`, freeze engages and holds the last repeats as a pad;
+    /// the next note above the threshold releases it. A freeze set by hand is left
+    /// alone, and disabling auto-freeze releases one it engaged.
+    pub fn set_auto_freeze(&mut self, enabled: Option<bool>, threshold_db: Option<f32>) {
+        if let Some(enabled) = enabled {
+            if enabled != self.auto_freeze_enabled {
+                self.auto_freeze.reset();
+                if self.auto_frozen {
+                    self.set_freeze(false);
+                }
+            }
+            self.auto_freeze_enabled = enabled;
+        }
+        if let Some(threshold_db) = threshold_db {
+            self.auto_freeze.set_threshold_db(threshold_db);
+        }
+    }
+    
+    /// Check whether auto-freeze is enabled
+    pub fn is_auto_freeze_enabled(&self) -> bool {
+        self.auto_freeze_enabled
+    }
+    
+    /// Get the auto-freeze threshold in dBFS
+    pub fn get_auto_freeze_threshold(&self) -> f32 {
+        self.auto_freeze.get_threshold_db()
+    }
+    
+    /// Engage freeze on a sustaining phrase and release it when playing resumes
+    fn update_auto_freeze(&mut self, level: f32) {
+        match self.auto_freeze.process(level) {
+            Some(true) if !self.is_frozen() => {
+                self.set_freeze(true);
+                self.auto_frozen = true;
+            },
+            Some(false) if self.auto_frozen => self.set_freeze(false),
+            _ => {},
+        }
+    }
+    
+    /// Hear only the input, for setting levels
+    ///
+    /// The effect chain is skipped entirely while monitoring: nothing is written
+    /// to the delay lines, so turning it off resumes with the repeats as they were.
+    pub fn set_monitor_dry_only(&mut self, enabled: bool) {
+        self.monitor_dry_only = enabled;
+    }
+    
+    /// Check whether only the input is heard
+    pub fn is_monitor_dry_only(&self) -> bool {
+        self.monitor_dry_only
+    }
+    
+    /// Set the left channel delay time
+    pub fn set_left_delay(&mut self, delay_time: f32) {
+        self.left_delay = delay_time.clamp(0.001, self.max_delay_time);
+        self.left_delay_samples.set_target(self.spread_delay_samples().0);
+    }
+    
+    /// Set the right channel delay time
+    pub fn set_right_delay(&mut self, delay_time: f32) {
+        self.right_delay = delay_time.clamp(0.001, self.max_delay_time);
+        self.right_delay_samples.set_target(self.spread_delay_samples().1);
+    }
+    
+    /// Get the left and right delay times in seconds
+    pub fn get_delay_times(&self) -> (f32, f32) {
+        (self.left_delay, self.right_delay)
+    }
+    
     /// Set stereo-specific parameters
     pub fn set_stereo_parameters(&mut self, ping_pong: Option<bool>, stereo_width: Option<f32>, cross_feedback: Option<f32>) {
         if let Some(ping_pong) = ping_pong {
             self.ping_pong = ping_pong;
         }
-        if let Some(stereo_width) = stereo_width {
-            self.stereo_width = stereo_width.clamp(0.0, 1.0);
-            self.mid_side_enabled = self.stereo_width > 0.0;
+        if let Some(stereo_width) = stereo_width {
+            self.stereo_width = stereo_width.clamp(0.0, 1.0);
+            self.mid_side_enabled = self.stereo_width > 0.0;
+        }
+        if let Some(cross_feedback) = cross_feedback {
+            self.cross_feedback = cross_feedback.clamp(0.0, MAX_CROSS_FEEDBACK);
+        }
+        self.update_loop_gain_scale();
+    }
+    
+    /// Delay the dry path to line up with the wet path's minimum latency
+    ///
+    /// For parallel routing, where the output is recombined with a dry track that
+    /// is itself delayed by `dry_latency_samples`. The dry line is allocated the
+    /// first time compensation is enabled.
+    pub fn set_latency_compensation(&mut self, enabled: bool) {
+        if enabled && self.left_dry_line.is_empty() {
+            self.left_dry_line = vec![0.0; self.left_buffer.len()];
+            self.right_dry_line = vec![0.0; self.right_buffer.len()];
+        }
+        if enabled != self.latency_compensation {
+            self.left_dry_line.fill(0.0);
+            self.right_dry_line.fill(0.0);
+        }
+        self.latency_compensation = enabled;
+    }
+    
+    /// Check whether the dry path is delayed to match the wet path
+    pub fn is_latency_compensation(&self) -> bool {
+        self.latency_compensation
+    }
+    
+    /// Samples the dry path is currently delayed by, 0 without compensation
+    ///
+    /// The wet path can answer the input no sooner than its shortest delay tap, so
+    /// that is the latency the dry path is aligned to.
+    pub fn dry_latency_samples(&self) -> usize {
+        if !self.latency_compensation {
+            return 0;
+        }
+        let shortest = self.left_delay_samples.target().min(self.right_delay_samples.target());
+        (shortest.round() as usize).min(self.left_dry_line.len().saturating_sub(1))
+    }
+    
+    /// Bytes allocated for the delay and dry compensation lines
+    ///
+    /// Counts capacity rather than length, so it is what the allocator handed out.
+    /// The dry lines only count once latency compensation has allocated them.
+    pub fn memory_usage_bytes(&self) -> usize {
+        let samples = self.left_buffer.capacity()
+            + self.right_buffer.capacity()
+            + self.left_dry_line.capacity()
+            + self.right_dry_line.capacity();
+        let bytes = samples * std::mem::size_of::<f32>();
+        #[cfg(feature = "spectral")]
+        let bytes = bytes + self.spectral_freeze.memory_usage_bytes();
+        bytes
+    }
+    
+    /// Pass the dry signal through the compensation delay line
+    fn compensate_dry(&mut self, left_dry: f32, right_dry: f32) -> (f32, f32) {
+        if !self.latency_compensation {
+            return (left_dry, right_dry);
+        }
+        let len = self.left_dry_line.len();
+        self.left_dry_line[self.dry_write_index] = left_dry;
+        self.right_dry_line[self.dry_write_index] = right_dry;
+        let read_index = (self.dry_write_index + len - self.dry_latency_samples()) % len;
+        self.dry_write_index = (self.dry_write_index + 1) % len;
+        (self.left_dry_line[read_index], self.right_dry_line[read_index])
+    }
+    
+    /// Keep the stereo width mono compatible
+    ///
+    /// The width boosts the side signal, which cancels when the output is summed to
+    /// mono. With this on the boost is limited so the side level stays below
+    /// `MONO_SAFE_MAX_SIDE_RATIO` of the mid level. Side content already in the
+    /// signal is never narrowed.
+    pub fn set_mono_safe(&mut self, enabled: bool) {
+        if enabled != self.mono_safe {
+            self.mid_power = 0.0;
+            self.side_power = 0.0;
+        }
+        self.mono_safe = enabled;
+    }
+    
+    /// Check whether the mono-compatibility guard on the stereo width is enabled
+    pub fn is_mono_safe(&self) -> bool {
+        self.mono_safe
+    }
+    
+    /// Run the delay as a single line on the mono sum of the input, for lower CPU
+    ///
+    /// Only the left line is read and written, at the left delay time, and the
+    /// cross-feedback, ping-pong and width stages are skipped; both outputs carry
+    /// the same signal. Leaving mono mode copies the left line into the right one,
+    /// so the repeats carry on in both channels.
+    pub fn set_mono_mode(&mut self, enabled: bool) {
+        if self.mono_mode && !enabled {
+            self.right_buffer.copy_from_slice(&self.left_buffer);
+            self.right_write_index = self.left_write_index;
+        }
+        self.mono_mode = enabled;
+        self.update_loop_gain_scale();
+    }
+    
+    /// Check whether the delay runs as a single mono line
+    pub fn is_mono_mode(&self) -> bool {
+        self.mono_mode
+    }
+    
+    /// Enable the wet limiter or set its threshold in dBFS
+    ///
+    /// The limiter sits on the repeats just before they are mixed with the dry
+    /// signal, so feedback build-up is held at the threshold while the dry attack
+    /// passes untouched. The threshold applies to the repeats before the wet mix.
+    pub fn set_wet_limiter(&mut self, enabled: Option<bool>, threshold_db: Option<f32>) {
+        if let Some(enabled) = enabled {
+            if enabled && !self.wet_limiter_enabled {
+                self.wet_limiter.reset();
+            }
+            self.wet_limiter_enabled = enabled;
+        }
+        if let Some(threshold_db) = threshold_db {
+            self.wet_limiter_threshold_db = threshold_db.clamp(MIN_WET_LIMITER_THRESHOLD_DB, MAX_WET_LIMITER_THRESHOLD_DB);
+            self.wet_limiter.set_threshold(10f32.powf(self.wet_limiter_threshold_db / 20.0));
+        }
+    }
+    
+    /// Check whether the wet limiter is enabled
+    pub fn is_wet_limiter_enabled(&self) -> bool {
+        self.wet_limiter_enabled
+    }
+    
+    /// Get the wet limiter threshold in dBFS
+    pub fn get_wet_limiter_threshold(&self) -> f32 {
+        self.wet_limiter_threshold_db
+    }
+    
+    /// Enable double-tracking or set its pan spread (0.0 to 1.0) and detune in cents (0 to 25)
+    ///
+    /// Adds two copies of the dry signal at around 25ms and 33ms, each slowly
+    /// detuned, one panned left and one right. The copies follow the dry mix,
+    /// so the doubled part stays at the level of the guitar it thickens.
+    pub fn set_double_track(&mut self, enabled: Option<bool>, spread: Option<f32>, detune_cents: Option<f32>) {
+        if let Some(enabled) = enabled {
+            if enabled && !self.double_track_enabled {
+                self.double_tracker.reset();
+            }
+            self.double_track_enabled = enabled;
+        }
+        if let Some(spread) = spread {
+            self.double_tracker.set_spread(spread);
+        }
+        if let Some(detune_cents) = detune_cents {
+            self.double_tracker.set_detune(detune_cents);
+        }
+    }
+    
+    /// Check whether double-tracking is enabled
+    pub fn is_double_track_enabled(&self) -> bool {
+        self.double_track_enabled
+    }
+    
+    /// Set the shimmer amount (0.0 off to 1.0), and the pitch mix and diffusion it reaches (0.0 to 1.0)
+    ///
+    /// The amount is the single intensity control: it scales the octave-up share of
+    /// the repeats and their diffusion together, so each pass around the feedback
+    /// loop climbs an octave and blurs into a pad.
+    pub fn set_shimmer(&mut self, amount: Option<f32>, pitch_mix: Option<f32>, diffusion: Option<f32>) {
+        if let Some(amount) = amount {
+            self.shimmer.set_amount(amount);
+        }
+        if let Some(pitch_mix) = pitch_mix {
+            self.shimmer.set_pitch_mix(pitch_mix);
+        }
+        if let Some(diffusion) = diffusion {
+            self.shimmer.set_diffusion(diffusion);
+        }
+    }
+    
+    /// Get the shimmer amount
+    pub fn get_shimmer_amount(&self) -> f32 {
+        self.shimmer.get_amount()
+    }
+    
+    /// Enable auto-width or set the correlation it keeps (0.0 to 0.9)
+    ///
+    /// Auto-width takes over the stereo width: it opens the width as far as it
+    /// goes while the correlation of the widened repeats stays above the target,
+    /// and narrows it again when the correlation drops below. The width it settles
+    /// at is kept when it is switched off.
+    pub fn set_auto_width(&mut self, enabled: Option<bool>, target_correlation: Option<f32>) {
+        if let Some(enabled) = enabled {
+            if enabled != self.auto_width_enabled {
+                self.auto_width_meter.reset();
+            }
+            self.auto_width_enabled = enabled;
+            self.mid_side_enabled = self.stereo_width > 0.0;
+            self.update_loop_gain_scale();
+        }
+        if let Some(target) = target_correlation {
+            self.auto_width_target = target.clamp(0.0, MAX_AUTO_WIDTH_TARGET);
+        }
+    }
+    
+    /// Check whether auto-width is adjusting the stereo width
+    pub fn is_auto_width(&self) -> bool {
+        self.auto_width_enabled
+    }
+    
+    /// Get the correlation auto-width keeps the repeats at
+    pub fn get_auto_width_target(&self) -> f32 {
+        self.auto_width_target
+    }
+    
+    /// Move the width one sample towards the target correlation
+    ///
+    /// Silence leaves the width where it is, as there is no correlation to follow.
+    fn update_auto_width(&mut self, left: f32, right: f32) {
+        self.auto_width_meter.process(left, right);
+        if self.auto_width_meter.is_silent() {
+            return;
+        }
+        let step = AUTO_WIDTH_RATE / self.sample_rate as f32;
+        let width = if self.auto_width_meter.correlation() > self.auto_width_target {
+            self.stereo_width + step
+        } else {
+            self.stereo_width - 4.0 * step
+        };
+        self.stereo_width = width.clamp(0.0, 1.0);
+        self.mid_side_enabled = self.stereo_width > 0.0;
+    }
+    
+    /// Set cross-feedback distortion parameters
+    pub fn set_cross_feedback_distortion(&mut self, enabled: Option<bool>, distortion_type: Option<DistortionType>, drive: Option<f32>, mix: Option<f32>, feedback_intensity: Option<f32>) {
+        if let Some(enabled) = enabled {
+            self.cross_feedback_distortion.set_enabled(enabled);
+        }
+        if let Some(distortion_type) = distortion_type {
+            self.cross_feedback_distortion.set_distortion_type(distortion_type);
+        }
+        if let Some(drive) = drive {
+            self.cross_feedback_distortion.set_drive(drive);
+        }
+        if let Some(mix) = mix {
+            self.cross_feedback_distortion.set_mix(mix);
+        }
+        if let Some(feedback_intensity) = feedback_intensity {
+            self.cross_feedback_distortion.set_feedback_intensity(feedback_intensity);
+        }
+        self.sync_chain_distortion();
+    }
+    
+    /// Set where in the signal chain the distortion is applied
+    pub fn set_distortion_placement(&mut self, placement: DistortionPlacement) {
+        self.distortion_placement = placement;
+    }
+    
+    /// Get where in the signal chain the distortion is applied
+    pub fn get_distortion_placement(&self) -> DistortionPlacement {
+        self.distortion_placement
+    }
+    
+    /// Keep the dry signal clear of the distortion, whatever its placement
+    ///
+    /// With `Input` placement the dry path takes the input from before the
+    /// distortion. A distortion stage in the effect chain is taken out of the
+    /// chain and run on the wet path instead, before the delay if the stage is
+    /// before it and on the wet output if it is after.
+    pub fn set_distortion_dry_bypass(&mut self, enabled: bool) {
+        self.distortion_dry_bypass = enabled;
+        self.chain.set_bypass("distortion", enabled);
+    }
+    
+    /// Check whether the dry signal is kept clear of the distortion
+    pub fn is_distortion_dry_bypass(&self) -> bool {
+        self.distortion_dry_bypass
+    }
+    
+    /// Check whether the delay applies the distortion itself at this placement
+    ///
+    /// A distortion stage in the effect chain takes over from the placement, unless
+    /// dry bypass has moved it onto the wet path at its side of the delay.
+    fn distortion_at(&self, placement: DistortionPlacement) -> bool {
+        if !self.distortion_in_chain {
+            return self.distortion_placement == placement;
+        }
+        let chain_placement = if self.chain.is_before_delay("distortion") {
+            DistortionPlacement::Input
+        } else {
+            DistortionPlacement::WetOutput
+        };
+        self.distortion_dry_bypass && placement == chain_placement
+    }
+    
+    /// Set the order of the effect chain from stage identifiers
+    ///
+    /// See `chain::STAGE_IDS`. The chain must contain the delay stage and may list
+    /// each stage only once; on error the current chain is kept.
+    pub fn set_effect_chain(&mut self, ids: &[String]) -> Result<(), crate::AudioProcessorError> {
+        self.chain = EffectChain::from_ids(ids, self.sample_rate)?;
+        self.distortion_in_chain = self.chain.contains("distortion");
+        self.chain.set_bypass("distortion", self.distortion_dry_bypass);
+        self.set_chain_param("eq", "tilt", self.eq_tilt);
+        self.set_chain_param("eq", "mix", self.eq_mix);
+        self.set_chain_param("distortion", "auto_gain_comp", self.cross_feedback_distortion.is_auto_gain_comp() as u8 as f32);
+        self.sync_chain_distortion();
+        Ok(())
+    }
+    
+    /// Get the stage identifiers of the effect chain in processing order
+    pub fn get_effect_chain(&self) -> Vec<&'static str> {
+        self.chain.ids()
+    }
+    
+    /// Set the tone EQ tilt (-1.0 darker to 1.0 brighter, 0.0 is flat)
+    ///
+    /// Only heard when the effect chain contains the eq stage.
+    pub fn set_eq_tilt(&mut self, amount: f32) {
+        self.eq_tilt = amount.clamp(-1.0, 1.0);
+        self.set_chain_param("eq", "tilt", self.eq_tilt);
+    }
+    
+    /// Get the tone EQ tilt
+    pub fn get_eq_tilt(&self) -> f32 {
+        self.eq_tilt
+    }
+    
+    /// Set the wet/dry mix of the tone EQ (0.0 transparent to 1.0 fully applied)
+    ///
+    /// Only blends the eq stage with its own input; the rest of the chain and the
+    /// delay mix are unaffected.
+    pub fn set_eq_mix(&mut self, mix: f32) {
+        self.eq_mix = mix.clamp(0.0, 1.0);
+        self.set_chain_param("eq", "mix", self.eq_mix);
+    }
+    
+    /// Get the wet/dry mix of the tone EQ
+    pub fn get_eq_mix(&self) -> f32 {
+        self.eq_mix
+    }
+    
+    /// Set dry path EQ parameters
+    ///
+    /// The dry EQ shapes only the dry signal, so the core tone can be kept apart
+    /// from the EQ on the repeats.
+    pub fn set_dry_eq(&mut self, enabled: Option<bool>, tilt: Option<f32>) {
+        if let Some(enabled) = enabled {
+            if enabled != self.dry_eq_enabled {
+                self.dry_eq.reset();
+            }
+            self.dry_eq_enabled = enabled;
+        }
+        if let Some(tilt) = tilt {
+            self.dry_eq.set_tilt(tilt);
+        }
+    }
+    
+    /// Set wet path EQ parameters
+    ///
+    /// The wet EQ shapes the repeats on their way to the output, not what is fed back.
+    pub fn set_wet_eq(&mut self, enabled: Option<bool>, tilt: Option<f32>) {
+        if let Some(enabled) = enabled {
+            if enabled != self.wet_eq_enabled {
+                self.wet_eq.reset();
+            }
+            self.wet_eq_enabled = enabled;
+        }
+        if let Some(tilt) = tilt {
+            self.wet_eq.set_tilt(tilt);
+        }
+    }
+    
+    /// Set a parameter of a chain stage if the chain has that stage
+    fn set_chain_param(&mut self, stage: &str, param: &str, value: f32) {
+        // The delay's own setters have already clamped the value
+        let _ = self.chain.set_param(stage, param, value);
+    }
+    
+    /// Copy the distortion settings to the chain's distortion stage
+    fn sync_chain_distortion(&mut self) {
+        let distortion = &self.cross_feedback_distortion;
+        let params = [
+            ("enabled", distortion.is_enabled() as u8 as f32),
+            ("type", distortion.get_distortion_type().to_parameter()),
+            ("drive", distortion.get_drive()),
+            ("mix", distortion.get_mix()),
+            ("feedback_intensity", distortion.get_feedback_intensity()),
+            ("tone", distortion.get_tone()),
+        ];
+        for (param, value) in params {
+            self.set_chain_param("distortion", param, value);
+        }
+    }
+    
+    /// Enable or disable auto gain compensation of the distortion
+    pub fn set_distortion_auto_gain_comp(&mut self, enabled: bool) {
+        self.cross_feedback_distortion.set_auto_gain_comp(enabled);
+        self.set_chain_param("distortion", "auto_gain_comp", enabled as u8 as f32);
+    }
+    
+    /// Set the distortion tone (-1.0 darker to 1.0 brighter, 0.0 unchanged)
+    pub fn set_distortion_tone(&mut self, tone: f32) {
+        self.cross_feedback_distortion.set_tone(tone);
+        self.set_chain_param("distortion", "tone", self.cross_feedback_distortion.get_tone());
+    }
+    
+    /// Read delayed signals from both channels at the current (smoothed) delay times
+    fn read_stereo_delays(&mut self) -> (f32, f32) {
+        let left_delay_samples = self.left_delay_samples.next_value();
+        let right_delay_samples = self.right_delay_samples.next_value();
+        
+        // A triggered sweep stretches both taps, kept within the lines
+        let sweep = self.delay_sweep.next_value();
+        let longest = (self.left_buffer.len() - 2) as f32;
+        let (left_delay_samples, right_delay_samples) = if sweep != 1.0 {
+            ((left_delay_samples * sweep).clamp(1.0, longest), (right_delay_samples * sweep).clamp(1.0, longest))
+        } else {
+            (left_delay_samples, right_delay_samples)
+        };
+        
+        // Stepped mode snaps the taps to whole samples
+        let (left_delay_samples, right_delay_samples) = match self.interpolation {
+            InterpolationMode::Linear => (left_delay_samples, right_delay_samples),
+            InterpolationMode::None => (left_delay_samples.round(), right_delay_samples.round()),
+        };
+        
+        let left_delayed = read_interpolated(&self.left_buffer, self.left_write_index, left_delay_samples);
+        let right_delayed = read_interpolated(&self.right_buffer, self.right_write_index, right_delay_samples);
+        if self.outgoing_remaining == 0 {
+            return (left_delayed, right_delayed);
+        }
+        
+        // Fade out the taps of the preset loaded before
+        let (left_outgoing_samples, right_outgoing_samples) = match self.interpolation {
+            InterpolationMode::Linear => self.outgoing_delay_samples,
+            InterpolationMode::None => (self.outgoing_delay_samples.0.round(), self.outgoing_delay_samples.1.round()),
+        };
+        let left_outgoing = read_interpolated(&self.left_buffer, self.left_write_index, left_outgoing_samples);
+        let right_outgoing = read_interpolated(&self.right_buffer, self.right_write_index, right_outgoing_samples);
+        let outgoing_gain = self.outgoing_remaining as f32 / self.outgoing_crossfade_samples as f32;
+        self.outgoing_remaining -= 1;
+        
+        (
+            left_delayed + outgoing_gain * (left_outgoing - left_delayed),
+            right_delayed + outgoing_gain * (right_outgoing - right_delayed),
+        )
+    }
+    
+    /// Read the left line alone at its current (smoothed) delay time, for mono mode
+    fn read_mono_delay(&mut self) -> f32 {
+        let sweep = self.delay_sweep.next_value();
+        let longest = (self.left_buffer.len() - 2) as f32;
+        let delay_samples = self.left_delay_samples.next_value();
+        let delay_samples = if sweep != 1.0 { (delay_samples * sweep).clamp(1.0, longest) } else { delay_samples };
+        let round = |samples: f32| match self.interpolation {
+            InterpolationMode::Linear => samples,
+            InterpolationMode::None => samples.round(),
+        };
+        
+        let delayed = read_interpolated(&self.left_buffer, self.left_write_index, round(delay_samples));
+        if self.outgoing_remaining == 0 {
+            return delayed;
+        }
+        
+        // Fade out the tap of the preset loaded before
+        let outgoing = read_interpolated(&self.left_buffer, self.left_write_index, round(self.outgoing_delay_samples.0));
+        let outgoing_gain = self.outgoing_remaining as f32 / self.outgoing_crossfade_samples as f32;
+        self.outgoing_remaining -= 1;
+        delayed + outgoing_gain * (outgoing - delayed)
+    }
+    
+    /// Apply ping-pong delay pattern
+    ///
+    /// A bouncing ping-pong crosses over in the feedback path instead, see `process_delay_sample`.
+    fn apply_ping_pong(&self, left_delayed: f32, right_delayed: f32) -> (f32, f32) {
+        if self.ping_pong && self.ping_pong_mode == PingPongMode::Swap {
+            (right_delayed, left_delayed)
+        } else {
+            (left_delayed, right_delayed)
+        }
+    }
+    
+    /// Pan the left and right taps to their automatic positions
+    fn apply_tap_pan(&self, left_tap: f32, right_tap: f32) -> (f32, f32) {
+        match self.tap_pans {
+            Some((left_pan, right_pan)) => (
+                left_tap * (1.0 - left_pan) * 0.5 + right_tap * (1.0 - right_pan) * 0.5,
+                left_tap * (1.0 + left_pan) * 0.5 + right_tap * (1.0 + right_pan) * 0.5,
+            ),
+            None => (left_tap, right_tap),
+        }
+    }
+    
+    /// Apply stereo width enhancement using mid-side processing
+    fn apply_stereo_enhancement(&mut self, left_sample: f32, right_sample: f32) -> (f32, f32) {
+        if !self.mid_side_enabled && !self.auto_width_enabled {
+            return (left_sample, right_sample);
+        }
+        
+        // Convert to mid-side
+        let mid = (left_sample + right_sample) * 0.5;
+        let side = (left_sample - right_sample) * 0.5;
+        
+        // Limit the side boost so the mono sum keeps most of the energy
+        let mut side_gain = 1.0 + self.stereo_width;
+        if self.mono_safe {
+            let coeff = self.mono_safe_coeff;
+            self.mid_power = coeff * self.mid_power + (1.0 - coeff) * mid * mid;
+            self.side_power = coeff * self.side_power + (1.0 - coeff) * side * side;
+            if self.side_power > 0.0 {
+                let max_gain = MONO_SAFE_MAX_SIDE_RATIO * (self.mid_power / self.side_power).sqrt();
+                side_gain = side_gain.min(max_gain.max(1.0));
+            }
+        }
+        
+        // Enhance side signal
+        let enhanced_side = side * side_gain;
+        
+        // Convert back to left-right
+        let enhanced_left = mid + enhanced_side;
+        let enhanced_right = mid - enhanced_side;
+        
+        if self.auto_width_enabled {
+            self.update_auto_width(enhanced_left, enhanced_right);
+        }
+        
+        (enhanced_left, enhanced_right)
+    }
+    
+    /// Write to both stereo buffers with cross-feedback and distortion
+    ///
+    /// The held samples are the repeats kept by freeze; they are written back
+    /// as they are, after the cross-feedback, distortion and limiter.
+    fn write_stereo_buffers(&mut self, left_sample: f32, right_sample: f32, left_held: f32, right_held: f32) {
+        // Calculate cross-feedback
+        let left_feedback = left_sample + self.cross_feedback * right_sample;
+        let right_feedback = right_sample + self.cross_feedback * left_sample;
+        
+        // Apply distortion to cross-feedback signals
+        let (left_feedback, right_feedback) = if self.distortion_at(DistortionPlacement::Feedback) {
+            self.cross_feedback_distortion.process_cross_feedback(left_feedback, right_feedback)
+        } else {
+            (left_feedback, right_feedback)
+        };
+        
+        // Filter the distorted repeats when the distortion comes first
+        let (left_feedback, right_feedback) = if self.feedback_stage_order == FeedbackStageOrder::DistortionFirst {
+            self.filter_repeats(left_feedback, right_feedback)
+        } else {
+            (left_feedback, right_feedback)
+        };
+        
+        // Keep self-oscillating feedback bounded
+        let (left_feedback, right_feedback) = if self.allow_self_oscillation {
+            self.feedback_limiter.process_stereo(left_feedback, right_feedback)
+        } else {
+            (left_feedback, right_feedback)
+        };
+        
+        // Write to buffers, never letting a NaN or denormal into the loop
+        let (left_written, right_written) = (sanitize_sample(left_feedback + left_held), sanitize_sample(right_feedback + right_held));
+        self.left_buffer[self.left_write_index] = left_written;
+        self.right_buffer[self.right_write_index] = right_written;
+        self.session_stats.record_feedback(left_written, right_written);
+        
+        // Update write indices
+        self.left_write_index = (self.left_write_index + 1) % self.left_buffer.len();
+        self.right_write_index = (self.right_write_index + 1) % self.right_buffer.len();
+    }
+    
+    /// Process stereo audio samples through the configured effect chain
+    pub fn process_sample(&mut self, left_input: f32, right_input: f32) -> (f32, f32) {
+        // Bring the source to the level the effects are voiced for
+        let (left_input, right_input) = (left_input * self.input_gain, right_input * self.input_gain);
+        
+        // Load the input like an amp would, before any effect hears it
+        let (left_input, right_input) = if self.di_sim_enabled {
+            (self.left_di_sim.process(left_input), self.right_di_sim.process(right_input))
+        } else {
+            (left_input, right_input)
+        };
+        let (left, right) = if self.monitor_dry_only {
+            self.process_monitor_sample(left_input, right_input)
+        } else {
+            let (left, right) = self.chain.process_before_delay(left_input, right_input);
+            let (left, right) = if self.mono_mode {
+                self.process_mono_delay_sample(left, right)
+            } else {
+                self.process_delay_sample(left, right)
+            };
+            self.chain.process_after_delay(left, right)
+        };
+        
+        // Nothing leaves above the safety ceiling, whatever the stages did
+        let ceiling = self.output_ceiling;
+        let (left, right) = (sanitize_sample(left).clamp(-ceiling, ceiling), sanitize_sample(right).clamp(-ceiling, ceiling));
+        
+        // Meters, stats and the recording all see exactly what is sent to the output
+        self.output_meter.process(left, right);
+        self.correlation_meter.process(left, right);
+        self.session_stats.record_output(left, right);
+        if let Some(tap) = &self.recording_tap {
+            tap.push(left, right);
+        }
+        (left, right)
+    }
+    
+    /// Pass the cleaned-up input straight to the output, still metered and faded
+    fn process_monitor_sample(&mut self, left_input: f32, right_input: f32) -> (f32, f32) {
+        let (left, right) = (sanitize_sample(left_input), sanitize_sample(right_input));
+        let (left, right) = if self.dc_block {
+            (self.left_dc_blocker.process(left), self.right_dc_blocker.process(right))
+        } else {
+            (left, right)
+        };
+        
+        let fade_gain = self.fade_gain.next_value();
+        (left * fade_gain, right * fade_gain)
+    }
+    
+    /// Process one stereo sample through the delay stage of the chain
+    fn process_delay_sample(&mut self, left_input: f32, right_input: f32) -> (f32, f32) {
+        // While ringing out only the repeats are heard; a bad input sample is dropped
+        let (left_input, right_input) = if self.tailing {
+            (0.0, 0.0)
+        } else {
+            (sanitize_sample(left_input), sanitize_sample(right_input))
+        };
+        
+        // Remove any DC offset before it is mixed and fed back
+        let (left_input, right_input) = if self.dc_block {
+            (self.left_dc_blocker.process(left_input), self.right_dc_blocker.process(right_input))
+        } else {
+            (left_input, right_input)
+        };
+        
+        if self.delay_sweep_trigger {
+            self.update_delay_sweep(left_input.abs().max(right_input.abs()));
+        }
+        // Ringing out is not a pause in the playing
+        if self.auto_freeze_enabled && !self.tailing {
+            self.update_auto_freeze(left_input.abs().max(right_input.abs()));
+        }
+        
+        // Distort the input before it reaches the delay, keeping the clean input for a bypassed dry path
+        let (left_clean, right_clean) = (left_input, right_input);
+        let (left_input, right_input) = if self.distortion_at(DistortionPlacement::Input) {
+            self.cross_feedback_distortion.process_stereo(left_input, right_input)
+        } else {
+            (left_input, right_input)
+        };
+        
+        // Read delayed signals
+        let (left_tap, right_tap) = self.read_stereo_delays();
+        
+        // Apply ping-pong if enabled
+        let (left_delayed, right_delayed) = self.apply_ping_pong(left_tap, right_tap);
+        
+        // Apply stereo enhancement
+        let (left_delayed, right_delayed) = self.apply_stereo_enhancement(left_delayed, right_delayed);
+        
+        // Place the taps in the stereo field; the feedback keeps them on their own lines
+        let (left_wet, right_wet) = self.apply_tap_pan(left_delayed, right_delayed);
+        
+        // Distort only the wet signal heard at the output, not what is fed back
+        let (left_wet, right_wet) = if self.distortion_at(DistortionPlacement::WetOutput) {
+            self.cross_feedback_distortion.process_stereo(left_wet, right_wet)
+        } else {
+            (left_wet, right_wet)
+        };
+        
+        // Each path has its own EQ, so the repeats can be shaped without the core tone
+        let (left_wet, right_wet) = if self.wet_eq_enabled {
+            self.wet_eq.process_stereo(left_wet, right_wet)
+        } else {
+            (left_wet, right_wet)
+        };
+        
+        // A spectral freeze replaces the repeats with the pad of the moment it was engaged
+        #[cfg(feature = "spectral")]
+        let (left_wet, right_wet) = self.spectral_freeze.process(
+            (left_input + left_wet, right_input + right_wet),
+            (left_wet, right_wet),
+        );
+        
+        // Hold runaway repeats down before they meet the dry signal
+        let (left_wet, right_wet) = if self.wet_limiter_enabled {
+            self.wet_limiter.process_stereo(left_wet, right_wet)
+        } else {
+            (left_wet, right_wet)
+        };
+        
+        // Calculate outputs (dry + wet)
+        let (left_dry, right_dry) = if self.distortion_dry_bypass {
+            self.compensate_dry(left_clean, right_clean)
+        } else {
+            self.compensate_dry(left_input, right_input)
+        };
+        let (left_dry, right_dry) = if self.dry_eq_enabled {
+            self.dry_eq.process_stereo(left_dry, right_dry)
+        } else {
+            (left_dry, right_dry)
+        };
+        let (left_dry, right_dry) = if self.double_track_enabled {
+            self.double_tracker.process(left_dry, right_dry)
+        } else {
+            (left_dry, right_dry)
+        };
+        let freeze = self.freeze.next_value();
+        let (dry_mix, wet_gain) = self.mix_law.gains(self.smoothed_wet_mix.next_value());
+        let dry_mix = if self.freeze_kill_dry {
+            dry_mix * (1.0 - freeze.max(self.spectral_freeze_mix()))
+        } else {
+            dry_mix
+        };
+        let left_output = dry_mix * left_dry + wet_gain * left_wet;
+        let right_output = dry_mix * right_dry + wet_gain * right_wet;
+        
+        // Even out the dynamics of the mixed output
+        let (left_output, right_output) = if self.compressor_enabled {
+            self.output_compressor.process_stereo(left_output, right_output)
+        } else {
+            (left_output, right_output)
+        };
+        
+        // Swell each new note in from silence, like a volume pedal
+        let (left_output, right_output) = if self.swell_enabled {
+            let swell_gain = self.swell.process(left_input.abs().max(right_input.abs()));
+            (left_output * swell_gain, right_output * swell_gain)
+        } else {
+            (left_output, right_output)
+        };
+        
+        // Mix in the metronome click after the dynamics so it keeps a steady level
+        let (left_output, right_output) = if self.metronome_enabled {
+            let click = self.metronome.process();
+            (left_output + click, right_output + click)
+        } else {
+            (left_output, right_output)
+        };
+        
+        // Filter the repeats here unless the distortion is to come first
+        let (left_recirculated, right_recirculated) = if self.feedback_stage_order == FeedbackStageOrder::FilterFirst {
+            self.filter_repeats(left_delayed, right_delayed)
+        } else {
+            (left_delayed, right_delayed)
+        };
+        
+        // Write to buffers with feedback, normalized so the loop always decays
+        let feedback_gain = self.smoothed_feedback.next_value().clamp(-self.max_stable_feedback, self.max_stable_feedback);
+        let left_returned = soft_saturate(feedback_gain * left_recirculated, self.feedback_saturation);
+        let right_returned = soft_saturate(feedback_gain * right_recirculated, self.feedback_saturation);
+        
+        // Send the repeats through the external insert, if there is one
+        let (left_returned, right_returned) = match self.feedback_insert.as_mut() {
+            Some(insert) => insert(left_returned, right_returned),
+            None => (left_returned, right_returned),
+        };
+        
+        // Shift the repeats up an octave and smear them, a little more on every pass
+        let (left_returned, right_returned) = if self.shimmer.is_active() {
+            self.shimmer.process(left_returned, right_returned)
+        } else {
+            (left_returned, right_returned)
+        };
+        let (left_feedback_sample, right_feedback_sample) = if self.ping_pong && self.ping_pong_mode == PingPongMode::Bounce {
+            // The input enters the start side's line and every pass crosses to the other line
+            let mono_input = (left_input + right_input) * 0.5;
+            match self.ping_pong_start {
+                PingPongStart::Left => (mono_input + right_returned, left_returned),
+                PingPongStart::Right => (right_returned, mono_input + left_returned),
+            }
+        } else {
+            (left_input + left_returned, right_input + right_returned)
+        };
+        
+        // Store at the feedback bit depth, so each pass is quantized again
+        let left_feedback_sample = self.reduce_bit_depth(left_feedback_sample);
+        let right_feedback_sample = self.reduce_bit_depth(right_feedback_sample);
+        
+        // Freeze crossfades from the normal loop to holding what the lines already contain
+        let live = 1.0 - freeze;
+        self.write_stereo_buffers(
+            sanitize_sample(left_feedback_sample * live),
+            sanitize_sample(right_feedback_sample * live),
+            left_tap * freeze,
+            right_tap * freeze,
+        );
+        
+        // Fade the output in after start and out before stop
+        let fade_gain = self.fade_gain.next_value();
+        let left_output = sanitize_sample(left_output * fade_gain);
+        let right_output = sanitize_sample(right_output * fade_gain);
+        
+        if self.tailing {
+            self.tail_samples += 1;
+            if left_output.abs().max(right_output.abs()) < TAILS_SILENCE_THRESHOLD {
+                self.tail_quiet_samples += 1;
+            } else {
+                self.tail_quiet_samples = 0;
+            }
+        }
+        
+        (left_output, right_output)
+    }
+    
+    /// Process one sample through the delay stage as a single line on the mono sum
+    ///
+    /// Follows `process_delay_sample` stage for stage on the left channel's
+    /// filters and line, without the cross-feedback, ping-pong and width stages.
+    /// The optional stages that only come in stereo are run on the mono signal in
+    /// both channels.
+    fn process_mono_delay_sample(&mut self, left_input: f32, right_input: f32) -> (f32, f32) {
+        let input = if self.tailing { 0.0 } else { sanitize_sample((left_input + right_input) * 0.5) };
+        let input = if self.dc_block { self.left_dc_blocker.process(input) } else { input };
+        
+        if self.delay_sweep_trigger {
+            self.update_delay_sweep(input.abs());
+        }
+        if self.auto_freeze_enabled && !self.tailing {
+            self.update_auto_freeze(input.abs());
+        }
+        
+        let clean = input;
+        let input = if self.distortion_at(DistortionPlacement::Input) {
+            self.cross_feedback_distortion.process_mono(input)
+        } else {
+            input
+        };
+        
+        let tap = self.read_mono_delay();
+        let wet = if self.distortion_at(DistortionPlacement::WetOutput) {
+            self.cross_feedback_distortion.process_mono(tap)
+        } else {
+            tap
+        };
+        let wet = if self.wet_eq_enabled { self.wet_eq.process_stereo(wet, wet).0 } else { wet };
+        #[cfg(feature = "spectral")]
+        let wet = self.spectral_freeze.process((input + wet, input + wet), (wet, wet)).0;
+        let wet = if self.wet_limiter_enabled { self.wet_limiter.process_stereo(wet, wet).0 } else { wet };
+        
+        let dry = if self.distortion_dry_bypass { clean } else { input };
+        let dry = self.compensate_dry(dry, dry).0;
+        let dry = if self.dry_eq_enabled { self.dry_eq.process_stereo(dry, dry).0 } else { dry };
+        // The copies are panned apart, so a mono output takes both sides
+        let dry = if self.double_track_enabled {
+            let (left, right) = self.double_tracker.process(dry, dry);
+            (left + right) * 0.5
+        } else {
+            dry
+        };
+        let freeze = self.freeze.next_value();
+        let (dry_mix, wet_gain) = self.mix_law.gains(self.smoothed_wet_mix.next_value());
+        let dry_mix = if self.freeze_kill_dry {
+            dry_mix * (1.0 - freeze.max(self.spectral_freeze_mix()))
+        } else {
+            dry_mix
+        };
+        let output = dry_mix * dry + wet_gain * wet;
+        
+        let output = if self.compressor_enabled { self.output_compressor.process_stereo(output, output).0 } else { output };
+        let output = if self.swell_enabled { output * self.swell.process(input.abs()) } else { output };
+        let output = if self.metronome_enabled { output + self.metronome.process() } else { output };
+        
+        // The feedback path, with the same stage order as the stereo lines
+        let recirculated = if self.feedback_stage_order == FeedbackStageOrder::FilterFirst {
+            self.filter_mono_repeat(tap)
+        } else {
+            tap
+        };
+        let feedback_gain = self.smoothed_feedback.next_value().clamp(-self.max_stable_feedback, self.max_stable_feedback);
+        let returned = soft_saturate(feedback_gain * recirculated, self.feedback_saturation);
+        let returned = match self.feedback_insert.as_mut() {
+            Some(insert) => insert(returned, returned).0,
+            None => returned,
+        };
+        let returned = if self.shimmer.is_active() { self.shimmer.process_mono(returned) } else { returned };
+        let feedback_sample = sanitize_sample(self.reduce_bit_depth(input + returned) * (1.0 - freeze));
+        let feedback_sample = if self.distortion_at(DistortionPlacement::Feedback) {
+            self.cross_feedback_distortion.process_mono_feedback(feedback_sample)
+        } else {
+            feedback_sample
+        };
+        let feedback_sample = if self.feedback_stage_order == FeedbackStageOrder::DistortionFirst {
+            self.filter_mono_repeat(feedback_sample)
+        } else {
+            feedback_sample
+        };
+        let feedback_sample = if self.allow_self_oscillation {
+            self.feedback_limiter.process_stereo(feedback_sample, feedback_sample).0
+        } else {
+            feedback_sample
+        };
+        let written = sanitize_sample(feedback_sample + tap * freeze);
+        self.left_buffer[self.left_write_index] = written;
+        self.session_stats.record_feedback(written, written);
+        self.left_write_index = (self.left_write_index + 1) % self.left_buffer.len();
+        self.right_write_index = self.left_write_index;
+        
+        let output = sanitize_sample(output * self.fade_gain.next_value());
+        
+        if self.tailing {
+            self.tail_samples += 1;
+            if output.abs() < TAILS_SILENCE_THRESHOLD {
+                self.tail_quiet_samples += 1;
+            } else {
+                self.tail_quiet_samples = 0;
+            }
+        }
+        
+        (output, output)
+    }
+    
+    /// Process mono input to stereo output with stereo delay effect
+    pub fn process_mono_to_stereo(&mut self, input_buffer: &[f32]) -> (Vec<f32>, Vec<f32>) {
+        let mut left_output = vec![0.0; input_buffer.len()];
+        let mut right_output = vec![0.0; input_buffer.len()];
+        
+        for (i, &input_sample) in input_buffer.iter().enumerate() {
+            let (left_sample, right_sample) = self.process_sample(input_sample, input_sample);
+            left_output[i] = left_sample;
+            right_output[i] = right_sample;
+        }
+        
+        (left_output, right_output)
+    }
+    
+    /// Get stereo-specific information
+    pub fn get_stereo_info(&self) -> String {
+        let base_info = format!(
+            "Left: {:.0}ms, Right: {:.0}ms, Ping-pong: {}, Width: {:.0}%",
+            self.left_delay * 1000.0,
+            self.right_delay * 1000.0,
+            if self.ping_pong { "On" } else { "Off" },
+            self.stereo_width * 100.0
+        );
+        
+        let distortion_info = self.cross_feedback_distortion.get_info();
+        format!("{} | {}", base_info, distortion_info)
+    }
+    
+    /// Set a parameter by name, as accepted by `set_stereo_delay_parameter`
+    ///
+    /// Covers every parameter the delay handles itself; the processors add the ones
+    /// that also change their configuration, such as `bpm` and `macro`. Booleans are
+    /// on above 0.5 and enums take their numeric parameter value.
+    pub fn set_parameter(&mut self, param: &str, value: f32) -> Result<(), crate::AudioProcessorError> {
+        let out_of_range = |min: f32, max: f32| crate::AudioProcessorError::InvalidParameter {
+            param: param.to_string(),
+            value,
+            min,
+            max,
+        };
+        
+        match param {
+            "left_delay" => self.set_left_delay(value),
+            "right_delay" => self.set_right_delay(value),
+            "feedback" => self.set_feedback(value),
+            "allow_self_oscillation" => self.set_allow_self_oscillation(value > 0.5),
+            "wet_mix" => self.set_wet_mix(value),
+            "mix_law" => self.set_mix_law(MixLaw::from_parameter(value)),
+            "interpolation" => self.set_interpolation(InterpolationMode::from_parameter(value)),
+            "ping_pong_mode" => self.set_ping_pong_mode(PingPongMode::from_parameter(value)),
+            "ping_pong_start" => self.set_ping_pong_start(PingPongStart::from_parameter(value)),
+            "tap_pan_mode" => self.set_tap_pan(Some(TapPanMode::from_parameter(value)), None),
+            "tap_spread" => {
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(out_of_range(0.0, 1.0));
+                }
+                self.set_tap_pan(None, Some(value));
+            },
+            "delay_glide" => {
+                if !(0.0..=MAX_SMOOTHING_MS).contains(&value) {
+                    return Err(out_of_range(0.0, MAX_SMOOTHING_MS));
+                }
+                self.set_delay_glide(value);
+            },
+            "ping_pong" => self.set_stereo_parameters(Some(value > 0.5), None, None),
+            "stereo_width" => self.set_stereo_parameters(None, Some(value), None),
+            "mono_safe" => self.set_mono_safe(value > 0.5),
+            "mono_mode" => self.set_mono_mode(value > 0.5),
+            "wet_limiter_enabled" => self.set_wet_limiter(Some(value > 0.5), None),
+            "wet_limiter_threshold" => {
+                if !(MIN_WET_LIMITER_THRESHOLD_DB..=MAX_WET_LIMITER_THRESHOLD_DB).contains(&value) {
+                    return Err(out_of_range(MIN_WET_LIMITER_THRESHOLD_DB, MAX_WET_LIMITER_THRESHOLD_DB));
+                }
+                self.set_wet_limiter(None, Some(value));
+            },
+            "double_track_enabled" => self.set_double_track(Some(value > 0.5), None, None),
+            "double_track_spread" => {
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(out_of_range(0.0, 1.0));
+                }
+                self.set_double_track(None, Some(value), None);
+            },
+            "double_track_detune" => {
+                if !(0.0..=MAX_DOUBLE_TRACK_DETUNE_CENTS).contains(&value) {
+                    return Err(out_of_range(0.0, MAX_DOUBLE_TRACK_DETUNE_CENTS));
+                }
+                self.set_double_track(None, None, Some(value));
+            },
+            "shimmer_amount" | "shimmer_pitch_mix" | "shimmer_diffusion" => {
+                if !(0.0..=1.0).contains(&value) {
+                    return Err(out_of_range(0.0, 1.0));
+                }
+                match param {
+                    "shimmer_amount" => self.set_shimmer(Some(value), None, None),
+                    "shimmer_pitch_mix" => self.set_shimmer(None, Some(value), None),
+                    _ => self.set_shimmer(None, None, Some(value)),
+                }
+            },
+            "auto_width_enabled" => self.set_auto_width(Some(value > 0.5), None),
+            "auto_width_target_correlation" => self.set_auto_width(None, Some(value)),
+            "latency_compensation" => self.set_latency_compensation(value > 0.5),
+            "cross_feedback" => self.set_stereo_parameters(None, None, Some(value)),
+            "feedback_tilt" => self.set_feedback_tilt(value),
+            "feedback_saturation" => self.set_feedback_saturation(value),
+            "feedback_color" => self.set_feedback_color(FeedbackColor::from_parameter(value)),
+            "feedback_stage_order" => self.set_feedback_stage_order(FeedbackStageOrder::from_parameter(value)),
+            "feedback_bit_depth" => self.set_feedback_bit_depth(value),
+            "bpm_glide_ms" => {
+                if !(0.0..=MAX_SMOOTHING_MS).contains(&value) {
+                    return Err(out_of_range(0.0, MAX_SMOOTHING_MS));
+                }
+                self.set_bpm_glide(value);
+            },
+            "delay_sweep_trigger" => self.set_delay_sweep(Some(value > 0.5), None),
+            "delay_sweep_amount" => self.set_delay_sweep(None, Some(value)),
+            "delay_spread" => self.set_delay_spread(value),
+            "feedback_hp_hz" => self.set_feedback_filter(value, self.get_feedback_filter().1)?,
+            "feedback_lp_hz" => self.set_feedback_filter(self.get_feedback_filter().0, value)?,
+            "dub_cutoff" => self.set_dub_filter(Some(value), None),
+            "dub_resonance" => self.set_dub_filter(None, Some(value)),
+            "dub_lfo_rate" => self.set_dub_lfo(Some(value), None, None, None),
+            "dub_lfo_depth" => self.set_dub_lfo(None, Some(value), None, None),
+            "dub_lfo_sync" => self.set_dub_lfo(None, None, Some(value > 0.5), None),
+            "dub_lfo_division" => self.set_dub_lfo(None, None, None, Some(value)),
+            "input_source" => self.set_input_source(InputSource::from_parameter(value)),
+            "di_sim_enabled" => self.set_di_sim(Some(value > 0.5), None),
+            "di_sim_resonance" => self.set_di_sim(None, Some(value)),
+            "dc_block" => self.set_dc_block(value > 0.5),
+            "eq_tilt" => self.set_eq_tilt(value),
+            "eq_mix" => self.set_eq_mix(value),
+            "eq_dry_enabled" => self.set_dry_eq(Some(value > 0.5), None),
+            "eq_dry_tilt" => self.set_dry_eq(None, Some(value)),
+            "eq_wet_enabled" => self.set_wet_eq(Some(value > 0.5), None),
+            "eq_wet_tilt" => self.set_wet_eq(None, Some(value)),
+            "fade_out_ms" => {
+                if !(0.0..=MAX_FADE_MS).contains(&value) {
+                    return Err(out_of_range(0.0, MAX_FADE_MS));
+                }
+                self.set_fade_time(value);
+            },
+            "max_output_db" => {
+                if !(MIN_OUTPUT_CEILING_DB..=MAX_OUTPUT_CEILING_DB).contains(&value) {
+                    return Err(out_of_range(MIN_OUTPUT_CEILING_DB, MAX_OUTPUT_CEILING_DB));
+                }
+                self.set_max_output_db(value);
+            },
+            "freeze" => self.set_freeze(value > 0.5),
+            "freeze_crossfade_ms" => {
+                if !(0.0..=MAX_FREEZE_CROSSFADE_MS).contains(&value) {
+                    return Err(out_of_range(0.0, MAX_FREEZE_CROSSFADE_MS));
+                }
+                self.set_freeze_crossfade(value);
+            },
+            "freeze_kill_dry" => self.set_freeze_kill_dry(value > 0.5),
+            "auto_freeze_enabled" => self.set_auto_freeze(Some(value > 0.5), None),
+            "auto_freeze_threshold" => {
+                if !(MIN_AUTO_FREEZE_THRESHOLD_DB..=MAX_AUTO_FREEZE_THRESHOLD_DB).contains(&value) {
+                    return Err(out_of_range(MIN_AUTO_FREEZE_THRESHOLD_DB, MAX_AUTO_FREEZE_THRESHOLD_DB));
+                }
+                self.set_auto_freeze(None, Some(value));
+            },
+            "spectral_freeze" => self.set_spectral_freeze(value > 0.5)?,
+            "monitor_dry_only" => self.set_monitor_dry_only(value > 0.5),
+            "meter_mode" => self.set_meter_mode(MeterMode::from_parameter(value)),
+            // Output compressor parameters
+            "comp_enabled" => self.set_compressor(Some(value > 0.5), None, None, None, None, None),
+            "comp_threshold" => self.set_compressor(None, Some(value), None, None, None, None),
+            "comp_ratio" => self.set_compressor(None, None, Some(value), None, None, None),
+            "comp_attack" => self.set_compressor(None, None, None, Some(value), None, None),
+            "comp_release" => self.set_compressor(None, None, None, None, Some(value), None),
+            "comp_makeup" => self.set_compressor(None, None, None, None, None, Some(value)),
+            // Auto-swell parameters
+            "swell_enabled" => self.set_swell(Some(value > 0.5), None, None),
+            "swell_time_ms" => self.set_swell(None, Some(value), None),
+            "swell_sensitivity" => self.set_swell(None, None, Some(value)),
+            // Metronome parameters
+            "metronome_enabled" => self.set_metronome(Some(value > 0.5), None, None),
+            "metronome_volume" => self.set_metronome(None, Some(value), None),
+            "metronome_subdivision" => self.set_metronome(None, None, Some(value.round().max(1.0) as u32)),
+            // Distortion parameters
+            "distortion_enabled" => self.set_cross_feedback_distortion(Some(value > 0.5), None, None, None, None),
+            "distortion_type" => self.set_cross_feedback_distortion(None, Some(DistortionType::from_parameter(value)), None, None, None),
+            "distortion_drive" => self.set_cross_feedback_distortion(None, None, Some(value), None, None),
+            "distortion_mix" => self.set_cross_feedback_distortion(None, None, None, Some(value), None),
+            "distortion_feedback_intensity" => self.set_cross_feedback_distortion(None, None, None, None, Some(value)),
+            "distortion_placement" => self.set_distortion_placement(DistortionPlacement::from_parameter(value)),
+            "distortion_auto_gain_comp" => self.set_distortion_auto_gain_comp(value > 0.5),
+            "distortion_dry_bypass" => self.set_distortion_dry_bypass(value > 0.5),
+            "distortion_tone" => self.set_distortion_tone(value),
+            // Per-parameter ramp times, e.g. smoothing_left_delay=300
+            _ if param.starts_with("smoothing_") => {
+                let smoothed_param = param.trim_start_matches("smoothing_");
+                if !(0.0..=MAX_SMOOTHING_MS).contains(&value) || !self.set_smoothing_time(smoothed_param, value) {
+                    return Err(out_of_range(0.0, MAX_SMOOTHING_MS));
+                }
+            },
+            _ => return Err(unknown_parameter(param, value)),
+        }
+        
+        Ok(())
+    }
+    
+    /// Check a batch of parameters without applying any of them
+    ///
+    /// Every value must lie in its range in `schema::PARAMETERS`, with the feedback
+    /// range and the feedback band taken as they will be once the whole batch is
+    /// applied. This covers everything `set_parameter` can reject, so a batch that
+    /// passes is applied in full. The processors' own parameters, such as `bpm`,
+    /// are not the delay's and are rejected; the processors check them themselves.
+    pub fn check_parameters(&self, params: &std::collections::HashMap<String, f32>) -> Result<(), crate::AudioProcessorError> {
+        let self_oscillation = params.get("allow_self_oscillation").map_or(self.allow_self_oscillation, |value| *value > 0.5);
+        for (param, &value) in params {
+            if let Some(smoothed_param) = param.strip_prefix("smoothing_") {
+                if !SMOOTHED_PARAMETERS.contains(&smoothed_param) || !(0.0..=MAX_SMOOTHING_MS).contains(&value) {
+                    return Err(crate::AudioProcessorError::InvalidParameter {
+                        param: param.clone(),
+                        value,
+                        min: 0.0,
+                        max: MAX_SMOOTHING_MS,
+                    });
+                }
+            } else if param == "feedback" && self_oscillation {
+                if !(-MAX_SELF_OSCILLATION_FEEDBACK..=MAX_SELF_OSCILLATION_FEEDBACK).contains(&value) {
+                    return Err(crate::AudioProcessorError::InvalidParameter {
+                        param: param.clone(),
+                        value,
+                        min: -MAX_SELF_OSCILLATION_FEEDBACK,
+                        max: MAX_SELF_OSCILLATION_FEEDBACK,
+                    });
+                }
+            } else if crate::schema::PROCESSOR_PARAMETERS.contains(&param.as_str()) {
+                return Err(unknown_parameter(param, value));
+            } else {
+                crate::schema::check(param, value)?;
+            }
+        }
+        if let Some(&frozen) = params.get("spectral_freeze") {
+            Self::check_spectral_freeze(frozen > 0.5)?;
+        }
+        
+        let (hp_hz, lp_hz) = self.get_feedback_filter();
+        let hp_hz = params.get("feedback_hp_hz").copied().unwrap_or(hp_hz);
+        let lp_hz = params.get("feedback_lp_hz").copied().unwrap_or(lp_hz).min(FULL_RANGE_HZ);
+        if hp_hz >= lp_hz {
+            return Err(crate::AudioProcessorError::Configuration(format!(
+                "Feedback high-pass ({} Hz) must be below the low-pass ({} Hz)",
+                hp_hz, lp_hz
+            )));
+        }
+        Ok(())
+    }
+    
+    /// Set a batch of parameters, all or none
+    ///
+    /// The batch is checked with `check_parameters` first and nothing is changed if
+    /// any of it is invalid. Parameters are applied in `schema::PARAMETERS` order,
+    /// so a mode such as `allow_self_oscillation` is in place before the values it
+    /// affects, and the feedback band is set in one go.
+    pub fn set_parameters(&mut self, params: &std::collections::HashMap<String, f32>) -> Result<(), crate::AudioProcessorError> {
+        self.check_parameters(params)?;
+        
+        let band = ["feedback_hp_hz", "feedback_lp_hz"];
+        if band.iter().any(|param| params.contains_key(*param)) {
+            let (hp_hz, lp_hz) = self.get_feedback_filter();
+            let hp_hz = params.get("feedback_hp_hz").copied().unwrap_or(hp_hz);
+            let lp_hz = params.get("feedback_lp_hz").copied().unwrap_or(lp_hz);
+            self.set_feedback_filter(hp_hz, lp_hz)?;
+        }
+        
+        let position = |param: &str| {
+            let index = crate::schema::PARAMETERS.iter().position(|spec| spec.name == param);
+            index.unwrap_or(crate::schema::PARAMETERS.len())
+        };
+        let mut ordered: Vec<(&String, f32)> = params
+            .iter()
+            .filter(|(param, _)| !band.contains(&param.as_str()))
+            .map(|(param, value)| (param, *value))
+            .collect();
+        ordered.sort_by_key(|(param, _)| position(param));
+        for (param, value) in ordered {
+            self.set_parameter(param, value)?;
+        }
+        Ok(())
+    }
+    
+    /// Get current parameter values including stereo-specific and distortion ones
+    ///
+    /// This is the single source of truth for parameter state. Keys match the names
+    /// accepted by `set_parameter`; booleans are 0.0 or 1.0 and enums
+    /// use their numeric parameter value (see `get_named_parameters` for names).
+    pub fn get_parameters(&self) -> std::collections::HashMap<String, f32> {
+        let as_value = |flag: bool| if flag { 1.0 } else { 0.0 };
+        let distortion = &self.cross_feedback_distortion;
+        
+        let mut params = std::collections::HashMap::new();
+        params.insert("feedback".to_string(), self.feedback);
+        params.insert("allow_self_oscillation".to_string(), as_value(self.allow_self_oscillation));
+        params.insert("wet_mix".to_string(), self.wet_mix);
+        params.insert("mix_law".to_string(), as_value(self.mix_law == MixLaw::EqualPower));
+        params.insert("interpolation".to_string(), as_value(self.interpolation == InterpolationMode::None));
+        params.insert("ping_pong_mode".to_string(), as_value(self.ping_pong_mode == PingPongMode::Bounce));
+        params.insert("ping_pong_start".to_string(), as_value(self.ping_pong_start == PingPongStart::Right));
+        params.insert("tap_pan_mode".to_string(), self.tap_pan_mode.to_parameter());
+        params.insert("tap_spread".to_string(), self.tap_spread);
+        params.insert("delay_glide".to_string(), self.get_delay_glide());
+        params.insert("left_delay".to_string(), self.left_delay);
+        params.insert("right_delay".to_string(), self.right_delay);
+        params.insert("ping_pong".to_string(), as_value(self.ping_pong));
+        params.insert("stereo_width".to_string(), self.stereo_width);
+        params.insert("mono_safe".to_string(), as_value(self.mono_safe));
+        params.insert("mono_mode".to_string(), as_value(self.mono_mode));
+        params.insert("wet_limiter_enabled".to_string(), as_value(self.wet_limiter_enabled));
+        params.insert("wet_limiter_threshold".to_string(), self.wet_limiter_threshold_db);
+        params.insert("double_track_enabled".to_string(), as_value(self.double_track_enabled));
+        params.insert("double_track_spread".to_string(), self.double_tracker.get_spread());
+        params.insert("double_track_detune".to_string(), self.double_tracker.get_detune());
+        params.insert("auto_width_enabled".to_string(), as_value(self.auto_width_enabled));
+        params.insert("auto_width_target_correlation".to_string(), self.auto_width_target);
+        params.insert("latency_compensation".to_string(), as_value(self.latency_compensation));
+        params.insert("cross_feedback".to_string(), self.cross_feedback);
+        params.insert("feedback_tilt".to_string(), self.get_feedback_tilt());
+        params.insert("feedback_saturation".to_string(), self.get_feedback_saturation());
+        params.insert("feedback_color".to_string(), self.feedback_color.to_parameter());
+        params.insert("feedback_stage_order".to_string(), as_value(self.feedback_stage_order == FeedbackStageOrder::DistortionFirst));
+        params.insert("feedback_bit_depth".to_string(), self.get_feedback_bit_depth());
+        params.insert("shimmer_amount".to_string(), self.shimmer.get_amount());
+        params.insert("shimmer_pitch_mix".to_string(), self.shimmer.get_pitch_mix());
+        params.insert("shimmer_diffusion".to_string(), self.shimmer.get_diffusion());
+        params.insert("bpm_glide_ms".to_string(), self.bpm_glide_ms);
+        params.insert("delay_sweep_trigger".to_string(), as_value(self.delay_sweep_trigger));
+        params.insert("delay_sweep_amount".to_string(), self.delay_sweep_amount);
+        params.insert("delay_spread".to_string(), self.delay_spread);
+        let (feedback_hp_hz, feedback_lp_hz) = self.get_feedback_filter();
+        params.insert("feedback_hp_hz".to_string(), feedback_hp_hz);
+        params.insert("feedback_lp_hz".to_string(), feedback_lp_hz);
+        params.insert("dub_cutoff".to_string(), self.dub_cutoff);
+        params.insert("dub_resonance".to_string(), self.get_dub_resonance());
+        params.insert("dub_lfo_rate".to_string(), self.dub_lfo_rate);
+        params.insert("dub_lfo_depth".to_string(), self.dub_lfo_depth);
+        params.insert("dub_lfo_sync".to_string(), as_value(self.dub_lfo_sync));
+        params.insert("dub_lfo_division".to_string(), self.dub_lfo_division);
+        params.insert("input_source".to_string(), as_value(self.input_source == InputSource::Line));
+        params.insert("di_sim_enabled".to_string(), as_value(self.di_sim_enabled));
+        params.insert("di_sim_resonance".to_string(), self.get_di_sim_resonance());
+        params.insert("dc_block".to_string(), as_value(self.dc_block));
+        params.insert("eq_tilt".to_string(), self.get_eq_tilt());
+        params.insert("eq_mix".to_string(), self.get_eq_mix());
+        params.insert("eq_dry_enabled".to_string(), as_value(self.dry_eq_enabled));
+        params.insert("eq_dry_tilt".to_string(), self.dry_eq.get_tilt());
+        params.insert("eq_wet_enabled".to_string(), as_value(self.wet_eq_enabled));
+        params.insert("eq_wet_tilt".to_string(), self.wet_eq.get_tilt());
+        params.insert("fade_out_ms".to_string(), self.get_fade_time());
+        params.insert("max_output_db".to_string(), self.max_output_db);
+        params.insert("freeze".to_string(), as_value(self.is_frozen()));
+        params.insert("freeze_crossfade_ms".to_string(), self.get_freeze_crossfade());
+        params.insert("freeze_kill_dry".to_string(), as_value(self.freeze_kill_dry));
+        params.insert("auto_freeze_enabled".to_string(), as_value(self.auto_freeze_enabled));
+        params.insert("auto_freeze_threshold".to_string(), self.auto_freeze.get_threshold_db());
+        params.insert("spectral_freeze".to_string(), as_value(self.is_spectral_frozen()));
+        params.insert("monitor_dry_only".to_string(), as_value(self.monitor_dry_only));
+        params.insert("distortion_enabled".to_string(), as_value(distortion.is_enabled()));
+        params.insert("distortion_type".to_string(), distortion.get_distortion_type().to_parameter());
+        params.insert("distortion_drive".to_string(), distortion.get_drive());
+        params.insert("distortion_mix".to_string(), distortion.get_mix());
+        params.insert("distortion_feedback_intensity".to_string(), distortion.get_feedback_intensity());
+        params.insert("distortion_auto_gain_comp".to_string(), as_value(distortion.is_auto_gain_comp()));
+        params.insert("distortion_dry_bypass".to_string(), as_value(self.distortion_dry_bypass));
+        params.insert("distortion_tone".to_string(), distortion.get_tone());
+        params.insert("comp_enabled".to_string(), as_value(self.compressor_enabled));
+        params.insert("comp_threshold".to_string(), self.output_compressor.get_threshold_db());
+        params.insert("comp_ratio".to_string(), self.output_compressor.get_ratio());
+        params.insert("comp_attack".to_string(), self.output_compressor.get_attack_ms());
+        params.insert("comp_release".to_string(), self.output_compressor.get_release_ms());
+        params.insert("comp_makeup".to_string(), self.output_compressor.get_makeup_db());
+        params.insert("swell_enabled".to_string(), as_value(self.swell_enabled));
+        params.insert("swell_time_ms".to_string(), self.swell.get_time_ms());
+        params.insert("swell_sensitivity".to_string(), self.swell.get_sensitivity());
+        params.insert("metronome_enabled".to_string(), as_value(self.metronome_enabled));
+        params.insert("metronome_volume".to_string(), self.metronome.get_volume());
+        params.insert("metronome_subdivision".to_string(), self.metronome.get_subdivision() as f32);
+        params
+    }
+    
+    /// Get the names of the current values of enum parameters
+    pub fn get_named_parameters(&self) -> std::collections::HashMap<String, String> {
+        let mut params = std::collections::HashMap::new();
+        params.insert("mix_law".to_string(), self.mix_law.to_string());
+        params.insert("interpolation".to_string(), self.interpolation.to_string());
+        params.insert("ping_pong_mode".to_string(), self.ping_pong_mode.to_string());
+        params.insert("ping_pong_start".to_string(), self.ping_pong_start.to_string());
+        params.insert("tap_pan_mode".to_string(), self.tap_pan_mode.to_string());
+        params.insert("feedback_color".to_string(), self.feedback_color.to_string());
+        params.insert("feedback_stage_order".to_string(), self.feedback_stage_order.to_string());
+        params.insert("input_source".to_string(), self.input_source.to_string());
+        params.insert("meter_mode".to_string(), self.get_meter_mode().to_string());
+        params.insert("distortion_type".to_string(), self.cross_feedback_distortion.get_distortion_type().to_string());
+        params.insert("distortion_placement".to_string(), self.distortion_placement.to_string());
+        params
+    }
+    
+    /// Get the optional stages in the order the signal passes through them
+    ///
+    /// Follows the configured effect chain, with the stages inside the delay in
+    /// place of its delay stage. Unless the chain has its own distortion stage, the
+    /// distortion is listed where its placement puts it: before the delay, on the
+    /// wet output or in the feedback loop.
+    pub fn effect_chain(&self) -> Vec<EffectStage> {
+        let distortion = EffectStage { name: "distortion", enabled: self.cross_feedback_distortion.is_enabled() };
+        let mut chain = vec![EffectStage { name: "di_sim", enabled: self.di_sim_enabled }];
+        for id in self.chain.ids() {
+            match id {
+                "eq" => chain.push(EffectStage { name: "eq", enabled: self.eq_tilt != 0.0 && self.eq_mix > 0.0 }),
+                "distortion" => chain.push(distortion),
+                _ => chain.extend(self.delay_stages()),
+            }
+        }
+        chain.push(EffectStage { name: "output_ceiling", enabled: self.max_output_db < MAX_OUTPUT_CEILING_DB });
+        chain
+    }
+    
+    /// Optional stages inside the delay stage, in signal order
+    fn delay_stages(&self) -> Vec<EffectStage> {
+        let distortion = EffectStage { name: "distortion", enabled: self.cross_feedback_distortion.is_enabled() };
+        let stage = |name, enabled| EffectStage { name, enabled };
+        
+        let mut chain = vec![stage("dc_block", self.dc_block)];
+        if self.distortion_at(DistortionPlacement::Input) {
+            chain.push(distortion);
+        }
+        chain.push(stage("delay_sweep", self.delay_sweep_trigger));
+        let stereo = !self.mono_mode;
+        chain.push(stage("ping_pong", stereo && self.ping_pong));
+        chain.push(stage("stereo_width", stereo && self.mid_side_enabled));
+        chain.push(stage("mono_safe", stereo && self.mid_side_enabled && self.mono_safe));
+        chain.push(stage("auto_width", stereo && self.auto_width_enabled));
+        chain.push(stage("tap_pan", stereo && self.tap_pans.is_some()));
+        if self.distortion_at(DistortionPlacement::WetOutput) {
+            chain.push(distortion);
+        }
+        chain.push(stage("latency_compensation", self.latency_compensation));
+        chain.push(stage("eq_wet", self.wet_eq_enabled));
+        chain.push(stage("wet_limiter", self.wet_limiter_enabled));
+        chain.push(stage("eq_dry", self.dry_eq_enabled));
+        chain.push(stage("double_track", self.double_track_enabled));
+        chain.push(stage("comp", self.compressor_enabled));
+        chain.push(stage("swell", self.swell_enabled));
+        chain.push(stage("metronome", self.metronome_enabled));
+        let filters = [
+            stage("feedback_tilt", self.get_feedback_tilt() != 0.0),
+            stage("dub_filter", self.dub_cutoff < FULL_RANGE_HZ),
+        ];
+        if self.feedback_stage_order == FeedbackStageOrder::FilterFirst {
+            chain.extend(filters);
+        }
+        chain.push(stage("feedback_saturation", self.feedback_saturation > 0.0));
+        chain.push(stage("feedback_bit_depth", self.feedback_bit_depth < MAX_FEEDBACK_BIT_DEPTH));
+        chain.push(stage("feedback_insert", self.has_feedback_insert()));
+        chain.push(stage("shimmer", self.shimmer.is_active()));
+        if self.distortion_at(DistortionPlacement::Feedback) {
+            chain.push(distortion);
+        }
+        if self.feedback_stage_order == FeedbackStageOrder::DistortionFirst {
+            chain.extend(filters);
+        }
+        chain.push(stage("feedback_limiter", self.allow_self_oscillation));
+        chain
+    }
+    
+    /// Get the current parameters and meter readings formatted for status reports
+    ///
+    /// Built from `get_parameters`, with the schema's toggles as "true"/"false" and enums by name.
+    pub fn get_status(&self) -> std::collections::HashMap<String, String> {
+        let mut status = std::collections::HashMap::new();
+        for (param, value) in self.get_parameters() {
+            let formatted = match crate::schema::parameter(&param).map(|spec| spec.kind) {
+                Some(ParameterKind::Toggle) => (value > 0.5).to_string(),
+                _ => format!("{:.3}", value),
+            };
+            status.insert(param, formatted);
+        }
+        status.extend(self.get_named_parameters());
+        
+        // Delay times also in milliseconds, as shown by the CLI and examples
+        status.insert("left_delay_ms".to_string(), format!("{:.1}", self.left_delay * 1000.0));
+        status.insert("right_delay_ms".to_string(), format!("{:.1}", self.right_delay * 1000.0));
+        status.insert("dry_latency_samples".to_string(), self.dry_latency_samples().to_string());
+        status.insert("memory_usage_bytes".to_string(), self.memory_usage_bytes().to_string());
+        // A parameter of the same name keeps its value, e.g. `auto_width_enabled`
+        // stays switched on while mono mode bypasses the stage
+        for stage in self.effect_chain() {
+            status.entry(format!("{}_enabled", stage.name)).or_insert_with(|| stage.enabled.to_string());
+        }
+        for (meter, level) in self.get_meters() {
+            status.insert(meter, format!("{:.3}", level));
+        }
+        status
+    }
+    
+    /// Get a human-readable description of current settings
+    pub fn get_info(&self) -> String {
+        format!(
+            "{}: L={:.0}ms, R={:.0}ms, Feedback={:.0}%, Wet={:.0}%",
+            self.get_effect_name(),
+            self.left_delay * 1000.0,
+            self.right_delay * 1000.0,
+            self.feedback * 100.0,
+            self.wet_mix * 100.0
+        )
+    }
+}
+
+/// Read a circular buffer `delay_samples` behind the write index, interpolating linearly
+pub(crate) fn read_interpolated(buffer: &[f32], write_index: usize, delay_samples: f32) -> f32 {
+    let len = buffer.len();
+    let read_position = (write_index + len) as f32 - delay_samples;
+    let index = read_position.floor();
+    let frac = read_position - index;
+    let index = index as usize % len;
+    let next = (index + 1) % len;
+    buffer[index] + frac * (buffer[next] - buffer[index])
+}
+
+impl BaseDelay for StereoDelay {
+    fn get_effect_name(&self) -> &str {
+        "Stereo Delay"
+    }
+    
+    fn process_sample(&mut self, input_sample: f32) -> (f32, f32) {
+        self.process_sample(input_sample, input_sample)
+    }
+    
+    fn process_buffer(&mut self, input_buffer: &[f32]) -> Vec<(f32, f32)> {
+        let mut output = Vec::with_capacity(input_buffer.len());
+        
+        for &input_sample in input_buffer {
+            output.push(self.process_sample(input_sample, input_sample));
+        }
+        
+        output
+    }
+    
+    fn reset(&mut self) {
+        self.left_buffer.fill(0.0);
+        self.right_buffer.fill(0.0);
+        self.left_write_index = 0;
+        self.right_write_index = 0;
+        self.feedback_limiter.reset();
+        self.wet_limiter.reset();
+        self.double_tracker.reset();
+        self.shimmer.reset();
+        self.output_compressor.reset();
+        self.swell.reset();
+        self.delay_sweep_onset.reset();
+        self.delay_sweep.set_target(1.0);
+        self.delay_sweep.snap();
+        self.metronome.reset();
+        self.left_feedback_tilt.reset();
+        self.right_feedback_tilt.reset();
+        self.left_feedback_filter.reset();
+        self.right_feedback_filter.reset();
+        self.left_dub_filter.reset();
+        self.right_dub_filter.reset();
+        self.dub_lfo_phase = 0.0;
+        self.outgoing_remaining = 0;
+        self.left_di_sim.reset();
+        self.right_di_sim.reset();
+        self.left_dc_blocker.reset();
+        self.right_dc_blocker.reset();
+        self.chain.reset();
+        self.dry_eq.reset();
+        self.wet_eq.reset();
+        self.mid_power = 0.0;
+        self.side_power = 0.0;
+        self.auto_width_meter.reset();
+        self.left_dry_line.fill(0.0);
+        self.right_dry_line.fill(0.0);
+        self.fade_gain.set_target(1.0);
+        self.fade_gain.snap();
+        self.freeze.set_target(0.0);
+        self.freeze.snap();
+        self.auto_freeze.reset();
+        self.auto_frozen = false;
+        #[cfg(feature = "spectral")]
+        self.spectral_freeze.reset();
+        self.end_tails();
+    }
+    
+    fn set_delay_time(&mut self, delay_time: f32) {
+        self.set_left_delay(delay_time);
+        self.set_right_delay(delay_time);
+    }
+    
+    fn set_feedback(&mut self, feedback: f32) {
+        self.feedback = feedback.clamp(-self.max_feedback(), self.max_feedback());
+        self.smoothed_feedback.set_target(self.feedback);
+        self.update_loop_gain_scale();
+    }
+    
+    fn set_wet_mix(&mut self, wet_mix: f32) {
+        self.wet_mix = wet_mix.clamp(0.0, 1.0);
+        self.update_mix_gains();
+    }
+}
+
+impl crate::chain::AudioStage for StereoDelay {
+    fn name(&self) -> &'static str {
+        "delay"
+    }
+    
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        self.process_sample(left, right)
+    }
+    
+    fn reset(&mut self) {
+        BaseDelay::reset(self);
+    }
+    
+    fn set_param(&mut self, param: &str, value: f32) -> Result<(), crate::AudioProcessorError> {
+        self.set_parameter(param, value)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::{PathEqConfig, ShimmerConfig};
+    use crate::dynamics::AUTO_FREEZE_HOLD_MS;
+    
+    #[test]
+    fn test_empty_buffers_give_empty_output() {
+        let mut simple = SimpleDelay::new(44100, 1.0, 0.3, 0.5);
+        assert!(BaseDelay::process_buffer(&mut simple, &[]).is_empty());
+        
+        let mut stereo = StereoDelay::from_config(&AudioConfig::default());
+        assert!(BaseDelay::process_buffer(&mut stereo, &[]).is_empty());
+        assert_eq!(stereo.process_mono_to_stereo(&[]), (Vec::new(), Vec::new()));
+        
+        // An empty buffer leaves the state alone, so the next sample is the same
+        let mut fresh = StereoDelay::from_config(&AudioConfig::default());
+        assert_eq!(stereo.process_sample(0.5, 0.5), fresh.process_sample(0.5, 0.5));
+    }
+    
+    #[test]
+    fn test_synced_modulation_cycles_once_per_quarter_note() {
+        let sample_rate = 44100;
+        let mut delay = SimpleDelay::new(sample_rate, 1.0, 0.3, 0.5);
+        delay.set_modulation(3.0, 20.0);
+        delay.set_bpm(120.0);
+        delay.set_mod_sync(true);
+        delay.set_mod_division(0.25);
+        assert!((delay.get_modulation_rate() - 2.0).abs() < 1e-6);
+        
+        // Half a cycle in, then the full cycle lands back at the start after 0.5s
+        for _ in 0..sample_rate / 4 {
+            delay.process_sample(0.0);
+        }
+        assert!((delay.modulation_phase - 0.5).abs() < 0.001, "phase {}", delay.modulation_phase);
+        for _ in 0..sample_rate / 4 {
+            delay.process_sample(0.0);
+        }
+        let distance = delay.modulation_phase.min(1.0 - delay.modulation_phase);
+        assert!(distance < 0.001, "phase {}", delay.modulation_phase);
+        
+        // Unsynced falls back to the free rate
+        delay.set_mod_sync(false);
+        assert_eq!(delay.get_modulation_rate(), 3.0);
+    }
+    
+    #[test]
+    fn test_equal_power_mix_law_keeps_constant_power() {
+        for step in 0..=20 {
+            let wet_mix = step as f32 / 20.0;
+            let (dry, wet) = MixLaw::EqualPower.gains(wet_mix);
+            assert!((dry * dry + wet * wet - 1.0).abs() < 1e-5, "power drifted at wet_mix={}", wet_mix);
+        }
+    }
+    
+    #[test]
+    fn test_self_oscillation_sustains_without_exploding() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.05;
+        config.stereo_delay.right_delay = 0.07;
+        config.stereo_delay.feedback = 1.0;
+        config.stereo_delay.allow_self_oscillation = true;
+        config.distortion.enabled = false;
+        assert!(config.validate().is_ok());
+        
+        let mut delay = StereoDelay::from_config(&config);
+        assert_eq!(delay.get_parameters()["feedback"], 1.0);
+        
+        delay.process_sample(1.0, 1.0);
+        let mut late_peak = 0.0f32;
+        for i in 0..(config.sample_rate * 10) {
+            let (left, right) = delay.process_sample(0.0, 0.0);
+            assert!(left.is_finite() && right.is_finite());
+            assert!(left.abs() <= 2.0 && right.abs() <= 2.0);
+            if i > config.sample_rate * 9 {
+                late_peak = late_peak.max(left.abs()).max(right.abs());
+            }
+        }
+        
+        // Repeats are still ringing after 10 seconds
+        assert!(late_peak > 0.1);
+    }
+    
+    #[test]
+    fn test_feedback_clamped_without_self_oscillation() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.feedback = 1.0;
+        assert!(config.validate().is_err());
+        
+        let mut delay = StereoDelay::from_config(&AudioConfig::default());
+        delay.set_feedback(1.0);
+        assert_eq!(delay.get_parameters()["feedback"], MAX_FEEDBACK);
+        delay.set_feedback(-1.0);
+        assert_eq!(delay.get_parameters()["feedback"], -MAX_FEEDBACK);
+    }
+    
+    #[test]
+    fn test_parameter_batch_that_passes_the_check_is_applied_in_full() {
+        let mut delay = StereoDelay::from_config(&AudioConfig::default());
+        let batch = |params: &[(&str, f32)]| params.iter().map(|(param, value)| (param.to_string(), *value)).collect();
+        
+        // Every parameter the check lets through is one the delay handles
+        let current = delay.get_parameters();
+        for spec in crate::schema::PARAMETERS {
+            if !crate::schema::PROCESSOR_PARAMETERS.contains(&spec.name) {
+                let value = current.get(spec.name).copied().unwrap_or(spec.min);
+                assert!(delay.set_parameter(spec.name, value).is_ok(), "{} is not handled", spec.name);
+            }
+        }
+        
+        // Keys that only fail once applied are caught before anything changes
+        let before = delay.get_parameters();
+        assert!(delay.set_parameters(&batch(&[("wet_mix", 0.95), ("bpm", 100.0)])).is_err());
+        if !cfg!(feature = "spectral") {
+            assert!(delay.set_parameters(&batch(&[("wet_mix", 0.95), ("spectral_freeze", 1.0)])).is_err());
+        }
+        assert_eq!(delay.get_parameters(), before);
+    }
+    
+    #[cfg(feature = "spectral")]
+    #[test]
+    fn test_spectral_freeze_sustains_the_tone_after_the_input_stops() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.25;
+        config.stereo_delay.right_delay = 0.25;
+        config.stereo_delay.feedback = 0.0;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let sample_rate = config.sample_rate as f32;
+        let mut delay = StereoDelay::from_config(&config);
+        
+        // Amplitude of a frequency in a block of output
+        let level = |samples: &[f32], hz: f32| {
+            let (re, im) = samples.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, sample)| {
+                let phase = 2.0 * std::f32::consts::PI * hz * n as f32 / sample_rate;
+                (re + sample * phase.cos(), im - sample * phase.sin())
+            });
+            2.0 * (re * re + im * im).sqrt() / samples.len() as f32
+        };
+        
+        let tone = |n: usize| 0.5 * (2.0 * std::f32::consts::PI * 440.0 * n as f32 / sample_rate).sin();
+        for n in 0..sample_rate as usize {
+            delay.process_sample(tone(n), tone(n));
+        }
+        delay.set_parameter("spectral_freeze", 1.0).unwrap();
+        assert_eq!(delay.get_status()["spectral_freeze"], "true");
+        
+        // Long after the input and its one repeat are gone, the pad still holds the tone
+        let output: Vec<f32> = (0..3 * sample_rate as usize).map(|_| delay.process_sample(0.0, 0.0).0).collect();
+        let second = &output[sample_rate as usize..2 * sample_rate as usize];
+        let third = &output[2 * sample_rate as usize..];
+        assert!(level(third, 440.0) > 0.25, "440 Hz at {}", level(third, 440.0));
+        assert!(level(third, 660.0) < 0.05 * level(third, 440.0));
+        assert!(level(third, 1000.0) < 0.05 * level(third, 440.0));
+        assert!((level(third, 440.0) / level(second, 440.0) - 1.0).abs() < 0.05);
+        
+        // Releasing fades back to the (now silent) repeats
+        delay.set_spectral_freeze(false).unwrap();
+        let released: Vec<f32> = (0..sample_rate as usize / 2).map(|_| delay.process_sample(0.0, 0.0).0).collect();
+        assert!(released[released.len() - 100..].iter().all(|sample| sample.abs() < 1e-6));
+    }
+    
+    #[test]
+    fn test_negative_feedback_alternates_the_sign_of_each_repeat() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.05;
+        config.stereo_delay.right_delay = 0.05;
+        config.stereo_delay.feedback = -0.5;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        assert!(config.validate().is_ok());
+        let delay_samples = (0.05 * config.sample_rate as f32).round() as usize;
+        
+        let mut delay = StereoDelay::from_config(&config);
+        assert_eq!(delay.loop_gain(), 0.5);
+        let output: Vec<f32> = (0..5 * delay_samples + 1)
+            .map(|i| delay.process_sample(if i == 0 { 1.0 } else { 0.0 }, 0.0).0)
+            .collect();
+        
+        // Each repeat is the previous one inverted and halved
+        let repeats: Vec<f32> = (1..=5).map(|n| output[n * delay_samples]).collect();
+        assert!(repeats[0] > 0.0);
+        for pair in repeats.windows(2) {
+            assert!((pair[1] + 0.5 * pair[0]).abs() < 1e-6, "{:?}", repeats);
+        }
+        
+        // Negative feedback is bounded like positive feedback
+        config.stereo_delay.feedback = -1.0;
+        assert!(config.validate().is_err());
+    }
+    
+    #[test]
+    fn test_feedback_bit_depth_degrades_each_repeat() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.05;
+        config.stereo_delay.right_delay = 0.05;
+        config.stereo_delay.feedback = 0.6;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let delay_samples = (0.05 * config.sample_rate as f32).round() as usize;
+        let repeats = |bits: f32| -> Vec<f32> {
+            let mut config = config.clone();
+            config.stereo_delay.feedback_bit_depth = bits;
+            assert!(config.validate().is_ok());
+            let mut delay = StereoDelay::from_config(&config);
+            let output: Vec<f32> = (0..6 * delay_samples + 1)
+                .map(|i| delay.process_sample(if i == 0 { 1.0 } else { 0.0 }, 0.0).0)
+                .collect();
+            (1..=6).map(|n| output[n * delay_samples]).collect()
+        };
+        let quantization_error = |repeats: &[f32], clean: &[f32]| -> Vec<f32> {
+            repeats.iter().zip(clean).map(|(repeat, clean)| (clean - repeat) / clean).collect()
+        };
+        
+        let clean = repeats(MAX_FEEDBACK_BIT_DEPTH);
+        assert!((clean[1] - 0.6 * clean[0]).abs() < 1e-6);
+        
+        // Every pass is stored again, so the error of each repeat adds to the last one
+        let gritty = quantization_error(&repeats(6.0), &clean);
+        for pair in gritty.windows(2) {
+            assert!(pair[1] >= pair[0], "{:?}", gritty);
+        }
+        assert!(gritty[5] > gritty[0] + 0.05, "{:?}", gritty);
+        
+        // Fewer bits, more noise
+        let fine = quantization_error(&repeats(12.0), &clean);
+        assert!(fine[5] > 0.0 && fine[5] < 0.01, "{:?}", fine);
+        assert!(gritty[5] > 10.0 * fine[5]);
+    }
+    
+    #[test]
+    fn test_preset_load_crossfades_to_the_new_delay_time() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.3;
+        config.stereo_delay.right_delay = 0.3;
+        config.stereo_delay.feedback = 0.3;
+        config.stereo_delay.wet_mix = 0.5;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        // No glide, so without the crossfade the tap jumps
+        config.stereo_delay.smoothing_ms.insert("left_delay".to_string(), 0.0);
+        config.stereo_delay.smoothing_ms.insert("right_delay".to_string(), 0.0);
+        let mut preset = config.clone();
+        // Half a cycle of the tone away from the old tap, the worst place to jump to
+        preset.stereo_delay.left_delay = 0.1012;
+        preset.stereo_delay.right_delay = 0.1012;
+        
+        let sample_rate = config.sample_rate as f32;
+        let tone = |i: usize| 0.5 * (2.0 * std::f32::consts::PI * 250.0 * i as f32 / sample_rate).sin();
+        let largest_step = |samples: &[f32]| samples.windows(2).fold(0.0f32, |step, pair| step.max((pair[1] - pair[0]).abs()));
+        let switch = |crossfade_ms: f32| -> (f32, f32) {
+            let mut config = config.clone();
+            config.preset_crossfade_ms = crossfade_ms;
+            let mut delay = StereoDelay::from_config(&config);
+            let settled = sample_rate as usize;
+            let before: Vec<f32> = (0..settled).map(|i| delay.process_sample(tone(i), tone(i)).0).collect();
+            delay.load_preset(&preset);
+            let after: Vec<f32> = (settled..2 * settled).map(|i| delay.process_sample(tone(i), tone(i)).0).collect();
+            let steady = largest_step(&before[settled / 2..]).max(largest_step(&after[settled / 2..]));
+            (largest_step(&[&before[settled - 1..], &after[..settled / 2]].concat()), steady)
+        };
+        
+        // Over the crossfade the output moves no faster than the tone itself
+        let (step, steady) = switch(50.0);
+        assert!(step < 1.2 * steady, "step {} with the tone moving {} per sample", step, steady);
+        
+        // A hard switch of the tap is a jump several times larger
+        let (step, steady) = switch(0.0);
+        assert!(step > 3.0 * steady, "step {} with the tone moving {} per sample", step, steady);
+    }
+    
+    /// Ratio of energy above 4kHz to energy below 200Hz
+    fn brightness(samples: &[f32]) -> f32 {
+        let mut high_split = crate::filters::OnePoleLowPass::new(4000.0, 44100);
+        let mut low_split = crate::filters::OnePoleLowPass::new(200.0, 44100);
+        let mut high = 0.0;
+        let mut low = 0.0;
+        for &sample in samples {
+            high += (sample - high_split.process(sample)).powi(2);
+            low += low_split.process(sample).powi(2);
+        }
+        high / low.max(1e-12)
+    }
+    
+    fn repeats_with_tilt(tilt: f32) -> Vec<f32> {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.stereo_delay.feedback = 0.8;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.stereo_delay.feedback_tilt = tilt;
+        config.distortion.enabled = false;
+        let mut delay = StereoDelay::from_config(&config);
+        
+        let mut output = Vec::new();
+        delay.process_sample(1.0, 1.0);
+        for _ in 0..(config.sample_rate / 10) {
+            output.push(delay.process_sample(0.0, 0.0).0);
+        }
+        output
+    }
+    
+    #[test]
+    fn test_positive_feedback_tilt_brightens_repeats() {
+        // Skip the first repeat, which has not yet passed through the feedback path
+        let skip = 600;
+        let neutral = repeats_with_tilt(0.0);
+        let bright = repeats_with_tilt(1.0);
+        let dark = repeats_with_tilt(-1.0);
+        
+        assert!(brightness(&bright[skip..]) > brightness(&neutral[skip..]));
+        assert!(brightness(&dark[skip..]) < brightness(&neutral[skip..]));
+    }
+    
+    #[test]
+    fn test_high_feedback_and_cross_feedback_decay() {
+        for &feedback in &[0.5, 0.7, 0.9] {
+            for &cross_feedback in &[0.3, 0.6, 0.9] {
+                for &stereo_width in &[0.0, 0.5, 1.0] {
+                    let mut config = AudioConfig::default();
+                    config.stereo_delay.left_delay = 0.05;
+                    config.stereo_delay.right_delay = 0.07;
+                    config.stereo_delay.feedback = feedback;
+                    config.stereo_delay.cross_feedback = cross_feedback;
+                    config.stereo_delay.stereo_width = stereo_width;
+                    config.distortion.enabled = false;
+                    assert!(config.validate().is_ok());
+                    
+                    let mut delay = StereoDelay::from_config(&config);
+                    assert!(delay.loop_gain() * delay.loop_gain_scale <= MAX_LOOP_GAIN + 1e-6);
+                    
+                    // Energy of the impulse response in consecutive half-second blocks
+                    delay.process_sample(1.0, 0.0);
+                    let block = (config.sample_rate / 2) as usize;
+                    let energies: Vec<f32> = (0..6)
+                        .map(|_| {
+                            (0..block)
+                                .map(|_| {
+                                    let (left, right) = delay.process_sample(0.0, 0.0);
+                                    left * left + right * right
+                                })
+                                .sum()
+                        })
+                        .collect();
+                    
+                    for pair in energies.windows(2) {
+                        assert!(
+                            pair[1] < pair[0],
+                            "energy grew with feedback={} cross_feedback={} width={}: {:?}",
+                            feedback, cross_feedback, stereo_width, energies
+                        );
+                    }
+                }
+            }
+        }
+    }
+    
+    #[test]
+    fn test_linear_mix_law_is_default() {
+        assert_eq!(MixLaw::default(), MixLaw::Linear);
+        assert_eq!(MixLaw::Linear.gains(0.25), (0.75, 0.25));
+    }
+    
+    #[test]
+    fn test_mix_gains_follow_the_wet_mix() {
+        let check = |delay: &StereoDelay| {
+            let (dry, wet) = delay.mix_law.gains(delay.wet_mix);
+            assert!((0.0..=1.0).contains(&delay.wet_mix));
+            assert_eq!((delay.dry_mix, delay.wet_gain), (dry, wet), "wet_mix={}", delay.wet_mix);
+            assert_eq!(delay.smoothed_wet_mix.target(), delay.wet_mix);
+        };
+        
+        // Out of range mixes are clamped before the gains are derived, and a new delay starts on them
+        for wet_mix in [-0.5, 0.0, 0.3, 1.0, 1.5] {
+            let delay = StereoDelay::new(48000, 0.3, 0.3, 0.3, wet_mix, false, 0.0, 0.0, false, DistortionType::SoftClip, 1.0, 0.0);
+            check(&delay);
+            assert!((delay.dry_mix + delay.wet_gain - 1.0).abs() < 1e-6);
+            assert_eq!(delay.get_smoothed_value("wet_mix"), Some(delay.wet_mix));
+        }
+        
+        let mut delay = StereoDelay::new(48000, 0.3, 0.3, 0.3, 0.5, false, 0.0, 0.0, false, DistortionType::SoftClip, 1.0, 0.0);
+        for mix_law in [MixLaw::Linear, MixLaw::EqualPower] {
+            delay.set_mix_law(mix_law);
+            check(&delay);
+            for step in -2..=12 {
+                delay.set_wet_mix(step as f32 / 10.0);
+                check(&delay);
+                let sum = match mix_law {
+                    MixLaw::Linear => delay.dry_mix + delay.wet_gain,
+                    MixLaw::EqualPower => delay.dry_mix.powi(2) + delay.wet_gain.powi(2),
+                };
+                assert!((sum - 1.0).abs() < 1e-5, "{} mix drifted at wet_mix={}", mix_law, delay.wet_mix);
+            }
+        }
+        
+        // The smoothed value is the mix itself, not the equal-power gain it sets
+        delay.set_wet_mix(0.5);
+        delay.snap_smoothed_parameters();
+        assert_eq!(delay.get_smoothed_value("wet_mix"), Some(0.5));
+        delay.set_wet_mix(1.0);
+        for _ in 0..100 {
+            delay.process_sample(0.0, 0.0);
+        }
+        let ramping = delay.get_smoothed_value("wet_mix").unwrap();
+        assert!(ramping > 0.5 && ramping < 1.0, "wet_mix ramping at {}", ramping);
+    }
+    
+    #[test]
+    fn test_bounce_ping_pong_alternates_sides_and_decays() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.stereo_delay.feedback = 0.5;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.ping_pong = true;
+        config.stereo_delay.ping_pong_mode = PingPongMode::Bounce;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let mut delay = StereoDelay::from_config(&config);
+        
+        let delay_samples = (0.01 * config.sample_rate as f32).round() as usize;
+        let output: Vec<(f32, f32)> = (0..6 * delay_samples + 1)
+            .map(|i| delay.process_sample(if i == 0 { 1.0 } else { 0.0 }, 0.0))
+            .collect();
+        
+        // Each echo is heard on one side only, starting on the left
+        let mut previous = f32::INFINITY;
+        for echo in 1..=6 {
+            let (left, right) = output[echo * delay_samples];
+            let (heard, silent) = if echo % 2 == 1 { (left, right) } else { (right, left) };
+            assert!(heard.abs() > 1e-3, "echo {} missing: {:?}", echo, (left, right));
+            assert!(silent.abs() < 1e-6, "echo {} leaked to the other side: {:?}", echo, (left, right));
+            assert!(heard.abs() < previous, "echo {} did not decay", echo);
+            previous = heard.abs();
+        }
+        
+        // The default swap mode keeps the previous behaviour
+        assert_eq!(PingPongMode::default(), PingPongMode::Swap);
+        assert_eq!(delay.get_status()["ping_pong_mode"], "bounce");
+    }
+    
+    #[test]
+    fn test_ping_pong_start_puts_the_first_echo_on_the_start_side() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.stereo_delay.feedback = 0.5;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.ping_pong = true;
+        config.stereo_delay.ping_pong_mode = PingPongMode::Bounce;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let delay_samples = (0.01 * config.sample_rate as f32).round() as usize;
+        
+        for start in [PingPongStart::Left, PingPongStart::Right] {
+            config.stereo_delay.ping_pong_start = start;
+            let mut delay = StereoDelay::from_config(&config);
+            
+            // A mono impulse, the same on both inputs
+            let output: Vec<(f32, f32)> = (0..2 * delay_samples + 1)
+                .map(|i| {
+                    let impulse = if i == 0 { 1.0 } else { 0.0 };
+                    delay.process_sample(impulse, impulse)
+                })
+                .collect();
+            let (first_left, first_right) = output[delay_samples];
+            let (second_left, second_right) = output[2 * delay_samples];
+            let (first_heard, first_silent, second_heard, second_silent) = match start {
+                PingPongStart::Left => (first_left, first_right, second_right, second_left),
+                PingPongStart::Right => (first_right, first_left, second_left, second_right),
+            };
+            assert!(first_heard.abs() > 1e-3, "{} start: first echo missing: {:?}", start, output[delay_samples]);
+            assert!(first_silent.abs() < 1e-6, "{} start: first echo on the wrong side: {:?}", start, output[delay_samples]);
+            assert!(second_heard.abs() > 1e-3 && second_silent.abs() < 1e-6, "{} start: second echo did not cross", start);
+        }
+        
+        let mut delay = StereoDelay::from_config(&config);
+        delay.set_parameter("ping_pong_start", 0.0).unwrap();
+        assert_eq!(delay.get_ping_pong_start(), PingPongStart::Left);
+        assert_eq!(delay.get_named_parameters()["ping_pong_start"], "left");
+        let mut saved = AudioConfig::default();
+        delay.write_config(&mut saved);
+        assert_eq!(saved.stereo_delay.ping_pong_start, PingPongStart::Left);
+    }
+    
+    #[test]
+    fn test_tap_spread_pans_the_taps_across_the_stereo_field() {
+        // Four taps spread first left to last right, evenly spaced across -1..1
+        let pans = TapPanMode::Spread.pan_positions(4, 1.0).unwrap();
+        let expected = [-1.0, -1.0 / 3.0, 1.0 / 3.0, 1.0];
+        for (pan, expected) in pans.iter().zip(expected) {
+            assert!((pan - expected).abs() < 1e-6, "pans {:?}", pans);
+        }
+        for pair in pans.windows(3) {
+            assert!(((pair[1] - pair[0]) - (pair[2] - pair[1])).abs() < 1e-6, "pans {:?} are not evenly spaced", pans);
+        }
+        assert_eq!(TapPanMode::Alternate.pan_positions(4, 0.5), Some(vec![-0.5, 0.5, -0.5, 0.5]));
+        assert_eq!(TapPanMode::Spread.pan_positions(1, 1.0), Some(vec![0.0]));
+        assert_eq!(TapPanMode::Manual.pan_positions(4, 1.0), None);
+        
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.stereo_delay.feedback = 0.0;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let delay_samples = (0.01 * config.sample_rate as f32).round() as usize;
+        
+        // The echo of an impulse on the left input, which only reaches the left tap
+        let echo = |mode: TapPanMode, spread: f32| {
+            let mut config = config.clone();
+            config.stereo_delay.tap_pan_mode = mode;
+            config.stereo_delay.tap_spread = spread;
+            let mut delay = StereoDelay::from_config(&config);
+            (0..=delay_samples)
+                .map(|i| delay.process_sample(if i == 0 { 1.0 } else { 0.0 }, 0.0))
+                .last()
+                .unwrap()
+        };
+        let (manual_left, manual_right) = echo(TapPanMode::Manual, 1.0);
+        assert!(manual_left > 0.1 && manual_right.abs() < 1e-6, "manual echo {:?}", (manual_left, manual_right));
+        
+        // Full spread keeps the left tap hard left, none centers it, half is in between
+        let (left, right) = echo(TapPanMode::Spread, 1.0);
+        assert!((left - manual_left).abs() < 1e-6 && right.abs() < 1e-6, "full spread echo {:?}", (left, right));
+        let (left, right) = echo(TapPanMode::Spread, 0.0);
+        assert!((left - right).abs() < 1e-6 && (left - 0.5 * manual_left).abs() < 1e-6, "centered echo {:?}", (left, right));
+        let (left, right) = echo(TapPanMode::Alternate, 0.5);
+        assert!((left - 0.75 * manual_left).abs() < 1e-6 && (right - 0.25 * manual_left).abs() < 1e-6, "half spread echo {:?}", (left, right));
+        
+        let mut delay = StereoDelay::from_config(&config);
+        delay.set_parameter("tap_pan_mode", 1.0).unwrap();
+        delay.set_parameter("tap_spread", 0.25).unwrap();
+        assert!(delay.set_parameter("tap_spread", 1.5).is_err());
+        assert_eq!(delay.get_tap_pan_mode(), TapPanMode::Spread);
+        assert_eq!(delay.get_named_parameters()["tap_pan_mode"], "spread");
+        assert_eq!(delay.get_parameters()["tap_spread"], 0.25);
+        let mut saved = AudioConfig::default();
+        delay.write_config(&mut saved);
+        assert_eq!(saved.stereo_delay.tap_pan_mode, TapPanMode::Spread);
+        assert_eq!(saved.stereo_delay.tap_spread, 0.25);
+    }
+    
+    #[test]
+    fn test_freeze_kill_dry_keeps_live_input_out() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.freeze_crossfade_ms = 10.0;
+        config.stereo_delay.freeze_kill_dry = true;
+        config.distortion.enabled = false;
+        let sample_rate = config.sample_rate as f32;
+        let crossfade = (0.01 * sample_rate) as usize;
+        let tone = |i: usize, hz: f32| 0.5 * (2.0 * std::f32::consts::PI * hz * i as f32 / sample_rate).sin();
+        
+        // Two delays fill up alike, then one hears silence and the other keeps playing
+        let mut quiet = StereoDelay::from_config(&config);
+        let mut played = StereoDelay::from_config(&config);
+        for i in 0..(0.7 * sample_rate) as usize {
+            let input = tone(i, 220.0);
+            quiet.process_sample(input, input);
+            played.process_sample(input, input);
+        }
+        quiet.set_parameter("freeze", 1.0).unwrap();
+        played.set_parameter("freeze", 1.0).unwrap();
+        assert_eq!(played.get_parameters()["freeze"], 1.0);
+        for i in 0..crossfade {
+            let input = tone(i, 220.0);
+            quiet.process_sample(input, input);
+            played.process_sample(input, input);
+        }
+        
+        // Fully frozen, the live input is neither heard nor written into the lines
+        let frozen: Vec<(f32, f32)> = (0..sample_rate as usize)
+            .map(|i| {
+                let input = tone(i, 1000.0);
+                let heard = played.process_sample(input, input);
+                let reference = quiet.process_sample(0.0, 0.0);
+                assert!((heard.0 - reference.0).abs() < 1e-6 && (heard.1 - reference.1).abs() < 1e-6, "live input at {}", i);
+                heard
+            })
+            .collect();
+        
+        // The held repeats play on as a drone rather than decaying
+        let level = |samples: &[(f32, f32)]| samples.iter().map(|(left, _)| left.abs()).fold(0.0, f32::max);
+        let window = (0.6 * sample_rate) as usize;
+        assert!(level(&frozen[..window]) > 0.05);
+        assert!(level(&frozen[frozen.len() - window..]) > 0.9 * level(&frozen[..window]));
+        
+        // Released, the live input is heard again
+        played.set_parameter("freeze", 0.0).unwrap();
+        quiet.set_parameter("freeze", 0.0).unwrap();
+        let difference = (0..2 * crossfade)
+            .map(|i| {
+                let input = tone(i, 1000.0);
+                (played.process_sample(input, input).0 - quiet.process_sample(0.0, 0.0).0).abs()
+            })
+            .fold(0.0, f32::max);
+        assert!(difference > 0.1, "live input still muted: {}", difference);
+    }
+    
+    #[test]
+    fn test_freeze_without_kill_dry_passes_the_dry_signal() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.freeze_crossfade_ms = 0.0;
+        let mut delay = StereoDelay::from_config(&config);
+        delay.set_freeze(true);
+        assert!(delay.is_frozen());
+        
+        // The lines are empty, so only the dry signal can be heard
+        let (left, right) = delay.process_sample(0.5, 0.5);
+        assert!(left.abs() > 0.1 && right.abs() > 0.1);
+        
+        delay.set_freeze_kill_dry(true);
+        assert_eq!(delay.process_sample(0.5, 0.5), (0.0, 0.0));
+        
+        let mut written = AudioConfig::default();
+        delay.write_config(&mut written);
+        assert_eq!(written.stereo_delay.freeze_crossfade_ms, 0.0);
+        assert!(written.stereo_delay.freeze_kill_dry);
+    }
+    
+    #[test]
+    fn test_auto_freeze_engages_after_a_phrase_falls_silent() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.1;
+        config.stereo_delay.right_delay = 0.1;
+        config.stereo_delay.feedback = 0.7;
+        config.stereo_delay.freeze_crossfade_ms = 10.0;
+        config.distortion.enabled = false;
+        config.stereo_delay.auto_freeze_enabled = true;
+        config.stereo_delay.auto_freeze_threshold = -50.0;
+        let sample_rate = config.sample_rate as f32;
+        let ms = |samples: usize| samples as f32 * 1000.0 / sample_rate;
+        let phrase: Vec<f32> = (0..config.sample_rate as usize / 2)
+            .map(|i| 0.3 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / sample_rate).sin())
+            .collect();
+        let silence = config.sample_rate as usize * 2;
+        
+        // A phrase then silence, returning when freeze engaged and the level at the end
+        let play = |delay: &mut StereoDelay| {
+            for &sample in &phrase {
+                delay.process_sample(sample, sample);
+            }
+            let mut engaged_at = None;
+            let mut tail = 0.0f32;
+            for i in 0..silence {
+                let (left, _) = delay.process_sample(0.0, 0.0);
+                if engaged_at.is_none() && delay.is_frozen() {
+                    engaged_at = Some(i);
+                }
+                if i >= silence - config.sample_rate as usize / 10 {
+                    tail = tail.max(left.abs());
+                }
+            }
+            (engaged_at, tail)
+        };
+        
+        // Silence before anything is played leaves the delay alone
+        let mut delay = StereoDelay::from_config(&config);
+        for _ in 0..silence {
+            delay.process_sample(0.0, 0.0);
+        }
+        assert!(!delay.is_frozen());
+        
+        // Freeze waits for the envelope to fall and then the hold time
+        let (engaged_at, frozen_tail) = play(&mut delay);
+        let engaged_ms = ms(engaged_at.expect("auto-freeze never engaged"));
+        assert!((AUTO_FREEZE_HOLD_MS..AUTO_FREEZE_HOLD_MS + 200.0).contains(&engaged_ms), "engaged after {}ms", engaged_ms);
+        
+        // The last phrase sustains, where the plain repeats die away
+        config.stereo_delay.auto_freeze_enabled = false;
+        let (never, plain_tail) = play(&mut StereoDelay::from_config(&config));
+        assert!(never.is_none());
+        assert!(frozen_tail > 0.01 && frozen_tail > 10.0 * plain_tail, "frozen {} against {}", frozen_tail, plain_tail);
+        
+        // Playing again releases it
+        delay.process_sample(0.3, 0.3);
+        assert!(!delay.is_frozen());
+        
+        // A freeze set by hand is not released by playing
+        delay.set_freeze(true);
+        delay.process_sample(0.3, 0.3);
+        assert!(delay.is_frozen());
+        
+        assert!(delay.set_parameter("auto_freeze_threshold", -10.0).is_err());
+        delay.set_parameter("auto_freeze_threshold", -60.0).unwrap();
+        let mut saved = AudioConfig::default();
+        delay.write_config(&mut saved);
+        assert!(saved.stereo_delay.auto_freeze_enabled);
+        assert_eq!(saved.stereo_delay.auto_freeze_threshold, -60.0);
+    }
+    
+    #[test]
+    fn test_status_reports_delay_times_in_ms() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.3;
+        config.stereo_delay.right_delay = 0.45;
+        let status = StereoDelay::from_config(&config).get_status();
+        
+        assert_eq!(status["left_delay_ms"].parse::<f32>().unwrap(), 300.0);
+        assert_eq!(status["right_delay_ms"].parse::<f32>().unwrap(), 450.0);
+        assert_eq!(status["left_delay"], "0.300");
+    }
+    
+    #[test]
+    fn test_feedback_filter_band_limits_each_repeat() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.05;
+        config.stereo_delay.right_delay = 0.05;
+        config.stereo_delay.feedback = 0.8;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let sample_rate = config.sample_rate as f32;
+        let delay_samples = (0.05 * sample_rate).round() as usize;
+        let impulse_response = |delay: &mut StereoDelay| -> Vec<f32> {
+            (0..5 * delay_samples).map(|i| delay.process_sample(if i == 0 { 1.0 } else { 0.0 }, 0.0).0).collect()
+        };
+        
+        // Level of one repeat at a frequency
+        let magnitude = |repeat: &[f32], hz: f32| {
+            let (re, im) = repeat.iter().enumerate().fold((0.0, 0.0), |(re, im), (n, sample)| {
+                let phase = 2.0 * std::f32::consts::PI * hz * n as f32 / sample_rate;
+                (re + sample * phase.cos(), im - sample * phase.sin())
+            });
+            (re * re + im * im).sqrt()
+        };
+        
+        let mut band_limited = StereoDelay::from_config(&config);
+        band_limited.set_feedback_filter(300.0, 3000.0).unwrap();
+        let output = impulse_response(&mut band_limited);
+        let repeats: Vec<&[f32]> = (1..5).map(|n| &output[n * delay_samples..(n + 1) * delay_samples]).collect();
+        
+        // Relative to the mids, every repeat has less low and less high content than the last
+        for pair in repeats.windows(2) {
+            let relative = |repeat: &[f32], hz: f32| magnitude(repeat, hz) / magnitude(repeat, 1000.0);
+            assert!(relative(pair[1], 50.0) < 0.5 * relative(pair[0], 50.0));
+            assert!(relative(pair[1], 15000.0) < 0.5 * relative(pair[0], 15000.0));
+        }
+        
+        // A flat band leaves the repeats exactly as before
+        let mut flat = StereoDelay::from_config(&config);
+        flat.set_feedback_filter(0.0, sample_rate / 2.0).unwrap();
+        assert_eq!(impulse_response(&mut flat), impulse_response(&mut StereoDelay::from_config(&config)));
+        
+        // The high-pass must stay below the low-pass
+        assert!(band_limited.set_feedback_filter(3000.0, 300.0).is_err());
+        assert!(band_limited.set_parameter("feedback_hp_hz", 5000.0).is_err());
+        assert_eq!(band_limited.get_feedback_filter(), (300.0, 3000.0));
+        config.stereo_delay.feedback_hp_hz = 5000.0;
+        config.stereo_delay.feedback_lp_hz = 3000.0;
+        assert!(config.validate().is_err());
+        
+        let mut written = AudioConfig::default();
+        band_limited.write_config(&mut written);
+        assert_eq!((written.stereo_delay.feedback_hp_hz, written.stereo_delay.feedback_lp_hz), (300.0, 3000.0));
+    }
+    
+    #[test]
+    fn test_dub_filter_resonance_is_part_of_the_loop_gain() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.feedback = 0.8;
+        let mut delay = StereoDelay::from_config(&config);
+        let flat_loop_gain = delay.loop_gain();
+        
+        // A resonant peak lowers the feedback ceiling, whatever the cutoff
+        delay.set_parameter("dub_cutoff", 800.0).unwrap();
+        delay.set_parameter("dub_resonance", 0.8).unwrap();
+        assert!(delay.loop_gain() > flat_loop_gain);
+        let ceiling = delay.max_stable_feedback;
+        assert!(ceiling < 0.8 && ceiling > 0.0);
+        delay.set_parameter("dub_cutoff", 3000.0).unwrap();
+        assert_eq!(delay.max_stable_feedback, ceiling);
+        
+        // The swept repeats stay bounded
+        delay.set_dub_lfo(Some(5.0), Some(2.0), None, None);
+        let peak = (0..48000).fold(0.0f32, |peak, i| {
+            let (left, right) = delay.process_sample(if i % 4800 == 0 { 1.0 } else { 0.0 }, 0.0);
+            peak.max(left.abs()).max(right.abs())
+        });
+        assert!(peak.is_finite() && peak < 4.0, "peak {}", peak);
+        
+        // Synced, the sweep follows the tempo
+        delay.set_metronome_bpm(120.0);
+        delay.set_parameter("dub_lfo_sync", 1.0).unwrap();
+        delay.set_parameter("dub_lfo_division", 0.25).unwrap();
+        assert_eq!(delay.get_dub_lfo_rate(), 2.0);
+        assert_eq!(delay.get_status()["dub_lfo_sync"], "true");
+        
+        let mut written = AudioConfig::default();
+        delay.write_config(&mut written);
+        assert_eq!((written.stereo_delay.dub_cutoff, written.stereo_delay.dub_resonance), (3000.0, 0.8));
+        assert!(written.validate().is_ok());
+    }
+    
+    #[test]
+    fn test_delay_glide_moves_the_tap_gradually() {
+        let config = AudioConfig::default();
+        let mut delay = StereoDelay::from_config(&config);
+        delay.set_interpolation(InterpolationMode::None);
+        delay.set_delay_glide(100.0);
+        assert_eq!(delay.get_delay_glide(), 100.0);
+        
+        let start = delay.get_smoothed_value("left_delay").unwrap();
+        delay.set_left_delay(start + 0.1);
+        
+        // The tap moves a little every 10ms instead of jumping
+        let samples_per_step = config.sample_rate as usize / 100;
+        let mut previous = start;
+        for step in 1..=10 {
+            for _ in 0..samples_per_step {
+                delay.process_sample(0.0, 0.0);
+            }
+            let position = delay.get_smoothed_value("left_delay").unwrap();
+            assert!(position > previous, "tap stalled after {} ms", step * 10);
+            assert!((position - (start + 0.01 * step as f32)).abs() < 0.001, "tap at {} after {} ms", position, step * 10);
+            previous = position;
+        }
+        assert!((previous - (start + 0.1)).abs() < 1e-4);
+        assert_eq!(delay.get_status()["interpolation"], "none");
+    }
+    
+    #[test]
+    fn test_bpm_glide_slides_the_taps_to_the_new_tempo() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.set_bpm(120.0);
+        config.stereo_delay.bpm_glide_ms = 500.0;
+        let mut delay = StereoDelay::from_config(&config);
+        delay.set_delay_glide(0.0);
+        let start = delay.get_smoothed_value("left_delay").unwrap();
+        assert!((start - 0.125).abs() < 1e-6);
+        
+        // Halving the tempo doubles the delay times, but the taps slide there
+        config.stereo_delay.set_bpm(60.0);
+        delay.set_tempo_delays(config.stereo_delay.left_delay, config.stereo_delay.right_delay);
+        let samples_per_step = config.sample_rate as usize / 20;
+        let mut previous = start;
+        for step in 1..=10 {
+            for _ in 0..samples_per_step {
+                delay.process_sample(0.0, 0.0);
+            }
+            let position = delay.get_smoothed_value("left_delay").unwrap();
+            assert!(position > previous, "tap stalled after {} ms", step * 50);
+            assert!((position - (start + 0.0125 * step as f32)).abs() < 0.001, "tap at {} after {} ms", position, step * 50);
+            previous = position;
         }
-        if let Some(cross_feedback) = cross_feedback {
-            self.cross_feedback = cross_feedback.clamp(0.0, 0.5);
+        assert!((previous - 0.25).abs() < 1e-4);
+        assert!((delay.get_smoothed_value("right_delay").unwrap() - 0.5).abs() < 1e-4);
+        
+        // Ordinary delay changes still use the delay glide
+        assert_eq!(delay.get_delay_glide(), 0.0);
+        delay.set_left_delay(0.3);
+        delay.process_sample(0.0, 0.0);
+        assert_eq!(delay.get_smoothed_value("left_delay").unwrap(), 0.3);
+    }
+    
+    #[test]
+    fn test_onset_sweeps_the_delay_tap_out_and_back() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.delay_sweep_trigger = true;
+        config.stereo_delay.delay_sweep_amount = 0.25;
+        let mut delay = StereoDelay::from_config(&config);
+        let base = delay.left_delay_samples.current();
+        let sweep_samples = (DELAY_SWEEP_MS * 0.001 * config.sample_rate as f32) as usize;
+        
+        // Effective tap position in samples after each processed sample
+        let tap = |delay: &mut StereoDelay, input: f32| {
+            delay.process_sample(input, input);
+            delay.left_delay_samples.current() * delay.delay_sweep.current()
+        };
+        
+        // Silence leaves the tap alone
+        for _ in 0..4800 {
+            assert_eq!(tap(&mut delay, 0.0), base);
         }
+        
+        // A pick attack sweeps the tap out to a quarter longer, then back
+        let mut previous = base;
+        for i in 0..sweep_samples {
+            let position = tap(&mut delay, if i < 480 { 0.8 } else { 0.0 });
+            assert!(position > previous, "tap not lengthening at sample {}", i);
+            previous = position;
+        }
+        assert!((previous - base * 1.25).abs() < 1.0, "tap at {} instead of {}", previous, base * 1.25);
+        for i in 0..sweep_samples {
+            let position = tap(&mut delay, 0.0);
+            assert!(position < previous, "tap not returning at sample {}", i);
+            previous = position;
+        }
+        assert!((previous - base).abs() < 1e-3);
     }
     
-    /// Set cross-feedback distortion parameters
-    pub fn set_cross_feedback_distortion(&mut self, enabled: Option<bool>, distortion_type: Option<DistortionType>, drive: Option<f32>, mix: Option<f32>, feedback_intensity: Option<f32>) {
-        if let Some(enabled) = enabled {
-            self.cross_feedback_distortion.set_enabled(enabled);
+    #[test]
+    fn test_delay_spread_offsets_the_taps_symmetrically() {
+        let mut config = AudioConfig { dc_block: false, ..Default::default() };
+        config.stereo_delay.left_delay = 0.2;
+        config.stereo_delay.right_delay = 0.2;
+        config.stereo_delay.delay_spread = 0.1;
+        config.stereo_delay.feedback = 0.0;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        let mut delay = StereoDelay::from_config(&config);
+        
+        // First echo of an impulse on each channel
+        let outputs: Vec<(f32, f32)> = (0..config.sample_rate as usize / 2)
+            .map(|i| delay.process_sample(if i == 0 { 1.0 } else { 0.0 }, if i == 0 { 1.0 } else { 0.0 }))
+            .collect();
+        let echo = |channel: fn(&(f32, f32)) -> f32| outputs.iter().position(|frame| channel(frame).abs() > 0.5).unwrap();
+        let (left_echo, right_echo) = (echo(|frame| frame.0), echo(|frame| frame.1));
+        
+        let center = 0.2 * config.sample_rate as f32;
+        assert_eq!(left_echo, (center * 0.9).round() as usize);
+        assert_eq!(right_echo, (center * 1.1).round() as usize);
+        assert_eq!(left_echo + right_echo, 2 * center as usize);
+        
+        // The configured times are kept, and no spread puts the taps back together
+        assert_eq!(delay.get_parameters()["left_delay"], 0.2);
+        delay.set_parameter("delay_spread", 0.0).unwrap();
+        delay.snap_smoothed_parameters();
+        assert_eq!(delay.left_delay_samples.current(), delay.right_delay_samples.current());
+    }
+    
+    #[test]
+    fn test_output_never_exceeds_the_ceiling() {
+        let mut config = AudioConfig { max_output_db: -12.0, ..Default::default() };
+        config.stereo_delay.allow_self_oscillation = true;
+        config.stereo_delay.feedback = 1.1;
+        config.distortion.drive = 1.0;
+        let mut delay = StereoDelay::from_config(&config);
+        let ceiling = 10f32.powf(-12.0 / 20.0);
+        
+        // A signal far over full scale, fed back until the repeats run away
+        let mut loudest: f32 = 0.0;
+        for i in 0..config.sample_rate as usize * 2 {
+            let s = 4.0 * (2.0 * std::f32::consts::PI * 110.0 * i as f32 / config.sample_rate as f32).sin();
+            let (left, right) = delay.process_sample(s, -s);
+            loudest = loudest.max(left.abs()).max(right.abs());
         }
-        if let Some(distortion_type) = distortion_type {
-            self.cross_feedback_distortion.set_distortion_type(distortion_type);
+        assert!(loudest <= ceiling, "output reached {} dBFS", linear_to_db(loudest));
+        assert!(loudest > ceiling * 0.99, "the overload should be clamped at the ceiling, not below it");
+        
+        // The dry monitor path is clamped too
+        delay.set_parameter("monitor_dry_only", 1.0).unwrap();
+        let (left, _) = delay.process_sample(4.0, 4.0);
+        assert!(left <= ceiling);
+        assert!(delay.set_parameter("max_output_db", 6.0).is_err());
+    }
+    
+    #[test]
+    fn test_fade_out_reaches_silence_over_fade_time() {
+        let mut config = AudioConfig { fade_out_ms: 50.0, ..Default::default() };
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let mut delay = StereoDelay::from_config(&config);
+        let fade_samples = config.sample_rate as usize * 50 / 1000;
+        let (level, _) = delay.process_sample(1.0, 1.0);
+        
+        delay.begin_fade_out();
+        let mut previous = f32::MAX;
+        for i in 1..fade_samples {
+            let (left, _) = delay.process_sample(1.0, 1.0);
+            assert!(left < previous, "fade not falling at sample {}", i);
+            previous = left;
+            assert!(!delay.is_faded_out(), "silent after {} of {} samples", i, fade_samples);
         }
-        if let Some(drive) = drive {
-            self.cross_feedback_distortion.set_drive(drive);
+        assert_eq!(delay.process_sample(1.0, 1.0), (0.0, 0.0));
+        assert!(delay.is_faded_out());
+        
+        // Fading back in ramps up from silence over the same time
+        delay.begin_fade_in();
+        let (first, _) = delay.process_sample(1.0, 1.0);
+        assert!(first > 0.0 && first < 0.01, "fade in starts at {}", first);
+        for _ in 0..fade_samples {
+            delay.process_sample(1.0, 1.0);
         }
-        if let Some(mix) = mix {
-            self.cross_feedback_distortion.set_mix(mix);
+        assert_eq!(delay.process_sample(1.0, 1.0).0, level);
+    }
+    
+    #[test]
+    fn test_swell_ramps_up_after_each_onset() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.wet_mix = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        config.swell.enabled = true;
+        config.swell.time_ms = 200.0;
+        let mut delay = StereoDelay::from_config(&config);
+        let swell_samples = config.sample_rate as usize / 5;
+        
+        for _ in 0..1000 {
+            delay.process_sample(0.0, 0.0);
         }
-        if let Some(feedback_intensity) = feedback_intensity {
-            self.cross_feedback_distortion.set_feedback_intensity(feedback_intensity);
+        
+        // A sharp note onset starts silent and rises steadily over the swell time
+        let mut previous = -1.0;
+        for i in 0..swell_samples {
+            let (left, _) = delay.process_sample(0.5, 0.5);
+            assert!(left > previous, "swell not rising at sample {}", i);
+            previous = left;
+            if i == 0 {
+                assert_eq!(left, 0.0);
+            }
+            if i == swell_samples / 2 {
+                assert!((left - 0.25).abs() < 0.01, "halfway level {}", left);
+            }
         }
+        assert!((delay.process_sample(0.5, 0.5).0 - 0.5).abs() < 1e-3);
+        
+        // A new note after a pause swells in again
+        for _ in 0..config.sample_rate {
+            delay.process_sample(0.0, 0.0);
+        }
+        let (left, _) = delay.process_sample(0.5, 0.5);
+        assert!(left < 0.01, "retriggered note starts at {}", left);
     }
     
-    /// Read delayed signals from both channels
-    fn read_stereo_delays(&self) -> (f32, f32) {
-        let left_read_idx = (self.left_write_index + self.left_buffer.len() - (self.left_delay * self.sample_rate as f32) as usize) % self.left_buffer.len();
-        let left_delayed = self.left_buffer[left_read_idx];
+    #[test]
+    fn test_dc_block_removes_input_offset() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.wet_mix = 0.0;
+        config.distortion.enabled = false;
+        let sample_rate = config.sample_rate as usize;
+        let input = |i: usize| 0.5 + 0.3 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate as f32).sin();
         
-        let right_read_idx = (self.right_write_index + self.right_buffer.len() - (self.right_delay * self.sample_rate as f32) as usize) % self.right_buffer.len();
-        let right_delayed = self.right_buffer[right_read_idx];
+        // Mean and RMS of the AC part over the second half of a second of audio
+        let measure = |delay: &mut StereoDelay| {
+            let outputs: Vec<f32> = (0..sample_rate).map(|i| delay.process_sample(input(i), input(i)).0).collect();
+            let settled = &outputs[sample_rate / 2..];
+            let mean = settled.iter().sum::<f32>() / settled.len() as f32;
+            let rms = (settled.iter().map(|s| (s - mean) * (s - mean)).sum::<f32>() / settled.len() as f32).sqrt();
+            (mean, rms)
+        };
         
-        (left_delayed, right_delayed)
+        let (mean, rms) = measure(&mut StereoDelay::from_config(&config));
+        assert!(mean.abs() < 0.01, "offset left in output: {}", mean);
+        assert!((rms - 0.3 / 2.0f32.sqrt()).abs() < 0.01, "audio content changed: {}", rms);
+        
+        config.dc_block = false;
+        let (mean, _) = measure(&mut StereoDelay::from_config(&config));
+        assert!((mean - 0.5).abs() < 0.01, "offset should pass with dc_block off: {}", mean);
     }
     
-    /// Apply ping-pong delay pattern
-    fn apply_ping_pong(&self, left_delayed: f32, right_delayed: f32) -> (f32, f32) {
-        if self.ping_pong {
-            (right_delayed, left_delayed)
-        } else {
-            (left_delayed, right_delayed)
+    #[test]
+    fn test_input_source_stages_the_input_gain() {
+        let mut config = AudioConfig { dc_block: false, ..Default::default() };
+        config.stereo_delay.wet_mix = 0.0;
+        config.distortion.enabled = false;
+        
+        let mut instrument = StereoDelay::from_config(&config);
+        let (dry_instrument, _) = instrument.process_sample(0.5, 0.5);
+        assert!((dry_instrument - 0.5).abs() < 1e-6, "instrument input should pass at unity: {}", dry_instrument);
+        
+        // The same raw sample from a line source comes in padded down
+        config.input_source = InputSource::Line;
+        let mut line = StereoDelay::from_config(&config);
+        let (dry_line, _) = line.process_sample(0.5, 0.5);
+        assert!((dry_line - 0.5 * line.get_input_gain()).abs() < 1e-6);
+        assert!((linear_to_db(dry_line / dry_instrument) - LINE_INPUT_GAIN_DB).abs() < 0.01);
+        
+        // Switchable as a parameter, and kept in the saved configuration
+        instrument.set_parameter("input_source", 1.0).unwrap();
+        assert_eq!(instrument.get_input_source(), InputSource::Line);
+        assert_eq!(instrument.get_named_parameters()["input_source"], "line");
+        let mut saved = AudioConfig::default();
+        instrument.write_config(&mut saved);
+        assert_eq!(saved.input_source, InputSource::Line);
+    }
+    
+    #[test]
+    fn test_tails_ring_out_after_input_stops() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.05;
+        config.stereo_delay.right_delay = 0.05;
+        config.stereo_delay.feedback = 0.5;
+        config.distortion.enabled = false;
+        let mut delay = StereoDelay::from_config(&config);
+        
+        for _ in 0..2205 {
+            delay.process_sample(1.0, 1.0);
+        }
+        delay.begin_tails();
+        
+        // Input keeps arriving but only the decaying repeats are heard
+        let block = 2205;
+        let mut block_peaks = Vec::new();
+        let mut samples = 0;
+        while !delay.tails_finished() {
+            let mut peak = 0.0f32;
+            for _ in 0..block {
+                let (left, right) = delay.process_sample(1.0, 1.0);
+                peak = peak.max(left.abs()).max(right.abs());
+            }
+            block_peaks.push(peak);
+            samples += block;
+            assert!(samples < config.sample_rate as usize * 10, "tail never finished");
         }
+        
+        assert!(block_peaks[0] > 0.1, "no echoes after stop: {:?}", block_peaks);
+        assert!(block_peaks[4] < block_peaks[0]);
+        assert!(*block_peaks.last().unwrap() < TAILS_SILENCE_THRESHOLD);
+        
+        delay.end_tails();
+        assert!(delay.process_sample(1.0, 1.0).0 > 0.0);
     }
     
-    /// Apply stereo width enhancement using mid-side processing
-    fn apply_stereo_enhancement(&self, left_sample: f32, right_sample: f32) -> (f32, f32) {
-        if !self.mid_side_enabled {
-            return (left_sample, right_sample);
+    #[test]
+    fn test_parameters_converge_at_their_own_smoothing_rates() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.smoothing_ms.insert("feedback".to_string(), 10.0);
+        config.stereo_delay.smoothing_ms.insert("wet_mix".to_string(), 100.0);
+        assert!(config.validate().is_ok());
+        let mut delay = StereoDelay::from_config(&config);
+        assert_eq!(delay.get_smoothing_time("left_delay"), default_smoothing_ms("left_delay"));
+        
+        delay.set_feedback(0.8);
+        delay.set_wet_mix(1.0);
+        
+        // 20ms in, the fast feedback ramp is done while the slow wet ramp is not
+        for _ in 0..882 {
+            delay.process_sample(0.0, 0.0);
         }
+        assert_eq!(delay.get_smoothed_value("feedback"), Some(0.8));
+        let wet = delay.get_smoothed_value("wet_mix").unwrap();
+        assert!(wet > 0.6 && wet < 0.75, "wet mix ramped too fast: {}", wet);
         
-        // Convert to mid-side
-        let mid = (left_sample + right_sample) * 0.5;
-        let side = (left_sample - right_sample) * 0.5;
+        for _ in 0..4410 {
+            delay.process_sample(0.0, 0.0);
+        }
+        assert_eq!(delay.get_smoothed_value("wet_mix"), Some(1.0));
+    }
+    
+    #[test]
+    fn test_input_placement_distorts_before_the_delay() {
+        let mut config = AudioConfig::default();
+        config.distortion.placement = DistortionPlacement::Input;
         
-        // Enhance side signal
-        let enhanced_side = side * (1.0 + self.stereo_width);
+        // The first output sample is pure dry signal, already through the distortion
+        let first_output = |level: f32| {
+            let mut delay = StereoDelay::from_config(&config);
+            delay.process_sample(level, level).0
+        };
+        let full = first_output(1.0);
+        let half = first_output(0.5);
+        assert!((full - 2.0 * half).abs() > 0.01, "input was not distorted: {} vs {}", full, half);
         
-        // Convert back to left-right
-        let enhanced_left = mid + enhanced_side;
-        let enhanced_right = mid - enhanced_side;
+        // With the default feedback placement the dry signal stays clean
+        config.distortion.placement = DistortionPlacement::Feedback;
+        let mut delay = StereoDelay::from_config(&config);
+        let (dry, _) = MixLaw::Linear.gains(config.stereo_delay.wet_mix);
+        assert!((delay.process_sample(1.0, 1.0).0 - dry).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_monitor_dry_only_outputs_the_input_alone() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.dc_block = false;
+        let delay_samples = (0.01 * config.sample_rate as f32).round() as usize;
+        let mut delay = StereoDelay::from_config(&config);
+        delay.set_parameter("monitor_dry_only", 1.0).unwrap();
         
-        (enhanced_left, enhanced_right)
+        // The input comes straight through with no repeats at the delay time
+        let input = |i: usize| if i.is_multiple_of(100) { 0.5 } else { 0.1 * (i as f32 * 0.05).sin() };
+        for i in 0..3 * delay_samples {
+            assert_eq!(delay.process_sample(input(i), -input(i)), (input(i), -input(i)), "sample {}", i);
+        }
+        
+        // Nothing reached the delay lines, so leaving the mode gives silence for silence
+        delay.set_parameter("monitor_dry_only", 0.0).unwrap();
+        for _ in 0..3 * delay_samples {
+            assert_eq!(delay.process_sample(0.0, 0.0), (0.0, 0.0));
+        }
     }
     
-    /// Write to both stereo buffers with cross-feedback and distortion
-    fn write_stereo_buffers(&mut self, left_sample: f32, right_sample: f32) {
-        // Calculate cross-feedback
-        let left_feedback = left_sample + self.cross_feedback * right_sample;
-        let right_feedback = right_sample + self.cross_feedback * left_sample;
+    #[test]
+    fn test_memory_usage_counts_the_allocated_lines() {
+        let config = AudioConfig::with_sample_rate(48000);
+        let mut delay = StereoDelay::from_config(&config);
+        let f32_bytes = std::mem::size_of::<f32>();
         
-        // Apply distortion to cross-feedback signals
-        let (left_feedback, right_feedback) = self.cross_feedback_distortion.process_cross_feedback(left_feedback, right_feedback);
+        // The spectral freeze frames are allocated up front when built in
+        #[cfg(feature = "spectral")]
+        let spectral_bytes = delay.spectral_freeze.memory_usage_bytes();
+        #[cfg(not(feature = "spectral"))]
+        let spectral_bytes = 0;
         
-        // Write to buffers
-        self.left_buffer[self.left_write_index] = left_feedback;
-        self.right_buffer[self.right_write_index] = right_feedback;
+        // Two lines of the 4 second maximum plus the interpolation guard
+        let line_capacity = delay.left_buffer.capacity() + delay.right_buffer.capacity();
+        assert_eq!(delay.memory_usage_bytes(), line_capacity * f32_bytes + spectral_bytes);
+        assert!(delay.memory_usage_bytes() >= 2 * 192_002 * f32_bytes);
+        assert_eq!(delay.get_status()["memory_usage_bytes"], delay.memory_usage_bytes().to_string());
         
-        // Update write indices
-        self.left_write_index = (self.left_write_index + 1) % self.left_buffer.len();
-        self.right_write_index = (self.right_write_index + 1) % self.right_buffer.len();
+        // Latency compensation adds the dry lines
+        delay.set_latency_compensation(true);
+        let dry_capacity = delay.left_dry_line.capacity() + delay.right_dry_line.capacity();
+        assert!(dry_capacity > 0);
+        assert_eq!(delay.memory_usage_bytes(), (line_capacity + dry_capacity) * f32_bytes + spectral_bytes);
     }
     
-    /// Process stereo audio samples through the stereo delay effect
-    pub fn process_sample(&mut self, left_input: f32, right_input: f32) -> (f32, f32) {
-        // Read delayed signals
-        let (left_delayed, right_delayed) = self.read_stereo_delays();
+    #[test]
+    fn test_dry_bypass_keeps_the_dry_path_clean() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.dc_block = false;
+        config.distortion.placement = DistortionPlacement::Input;
+        config.distortion.dry_bypass = true;
+        let delay_samples = (0.01 * config.sample_rate as f32).round() as usize;
+        let (dry, _) = MixLaw::Linear.gains(config.stereo_delay.wet_mix);
         
-        // Apply ping-pong if enabled
-        let (left_delayed, right_delayed) = self.apply_ping_pong(left_delayed, right_delayed);
+        // Dry output at the first sample and the first repeat for an impulse at a level
+        let response = |config: &AudioConfig, level: f32| {
+            let mut delay = StereoDelay::from_config(config);
+            let output: Vec<f32> = (0..=delay_samples)
+                .map(|i| delay.process_sample(if i == 0 { level } else { 0.0 }, if i == 0 { level } else { 0.0 }).0)
+                .collect();
+            (output[0], output[delay_samples])
+        };
         
-        // Apply stereo enhancement
-        let (left_delayed, right_delayed) = self.apply_stereo_enhancement(left_delayed, right_delayed);
+        for chain in [vec!["delay"], vec!["distortion", "delay"]] {
+            config.effect_chain = chain.iter().map(|id| id.to_string()).collect();
+            let (full_dry, full_wet) = response(&config, 1.0);
+            let (half_dry, half_wet) = response(&config, 0.5);
+            
+            // The dry component is the clean input, the repeats are distorted
+            assert!((full_dry - dry).abs() < 1e-6 && (half_dry - 0.5 * dry).abs() < 1e-6, "{:?}: dry was distorted", chain);
+            assert!((full_wet - 2.0 * half_wet).abs() > 0.01, "{:?}: wet was not distorted", chain);
+        }
         
-        // Calculate outputs (dry + wet)
-        let left_output = self.dry_mix * left_input + self.wet_mix * left_delayed;
-        let right_output = self.dry_mix * right_input + self.wet_mix * right_delayed;
+        // Without the bypass the dry path goes through the distortion too
+        config.distortion.dry_bypass = false;
+        let (full_dry, _) = response(&config, 1.0);
+        assert!((full_dry - dry).abs() > 0.01);
+    }
+    
+    #[test]
+    fn test_get_parameters_covers_delay_and_distortion() {
+        let mut delay = StereoDelay::from_config(&AudioConfig::default());
+        delay.set_stereo_parameters(Some(false), Some(0.25), Some(0.4));
+        delay.set_cross_feedback_distortion(Some(true), Some(DistortionType::Tube), Some(0.6), Some(0.5), Some(0.8));
+        delay.set_feedback(0.45);
         
-        // Write to buffers with feedback
-        let left_feedback_sample = left_input + self.feedback * left_delayed;
-        let right_feedback_sample = right_input + self.feedback * right_delayed;
+        let params = delay.get_parameters();
+        for key in [
+            "feedback", "wet_mix", "left_delay", "right_delay", "ping_pong", "stereo_width",
+            "cross_feedback", "distortion_enabled", "distortion_type", "distortion_drive",
+            "distortion_mix", "distortion_feedback_intensity",
+        ] {
+            assert!(params.contains_key(key), "missing {}", key);
+        }
+        assert_eq!(params["ping_pong"], 0.0);
+        assert_eq!(params["stereo_width"], 0.25);
+        assert_eq!(params["cross_feedback"], 0.4);
+        assert_eq!(params["feedback"], 0.45);
+        assert_eq!(params["distortion_enabled"], 1.0);
+        assert_eq!(DistortionType::from_parameter(params["distortion_type"]), DistortionType::Tube);
+        assert_eq!(params["distortion_drive"], 0.6);
+        assert_eq!(params["distortion_mix"], 0.5);
+        assert_eq!(params["distortion_feedback_intensity"], 0.8);
         
-        self.write_stereo_buffers(left_feedback_sample, right_feedback_sample);
+        delay.set_parameter("freeze", 1.0).unwrap();
+        let status = delay.get_status();
+        assert_eq!(status["ping_pong"], "false");
+        assert_eq!(status["distortion_type"], "tube");
+        assert_eq!(status["freeze"], "true");
         
-        (left_output, right_output)
+        // Every toggle in the schema reads as a boolean
+        for spec in crate::schema::PARAMETERS.iter().filter(|spec| spec.kind == ParameterKind::Toggle) {
+            if let Some(formatted) = status.get(spec.name) {
+                assert!(formatted == "true" || formatted == "false", "{} = {}", spec.name, formatted);
+            }
+        }
     }
     
-    /// Process mono input to stereo output with stereo delay effect
-    pub fn process_mono_to_stereo(&mut self, input_buffer: &[f32]) -> (Vec<f32>, Vec<f32>) {
-        let mut left_output = vec![0.0; input_buffer.len()];
-        let mut right_output = vec![0.0; input_buffer.len()];
+    #[test]
+    fn test_effect_chain_reports_enabled_stages() {
+        let mut config = AudioConfig::default();
+        config.distortion.placement = DistortionPlacement::Input;
+        let mut delay = StereoDelay::from_config(&config);
+        let enabled = |delay: &StereoDelay, name: &str| {
+            let stage = delay.effect_chain().into_iter().find(|stage| stage.name == name).unwrap();
+            assert_eq!(delay.get_status()[&format!("{}_enabled", name)], stage.enabled.to_string());
+            stage.enabled
+        };
         
-        for (i, &input_sample) in input_buffer.iter().enumerate() {
-            let (left_sample, right_sample) = self.process_sample(input_sample, input_sample);
-            left_output[i] = left_sample;
-            right_output[i] = right_sample;
+        assert!(!enabled(&delay, "comp"));
+        delay.set_compressor(Some(true), None, None, None, None, None);
+        assert!(enabled(&delay, "comp"));
+        
+        assert!(enabled(&delay, "dc_block"));
+        delay.set_dc_block(false);
+        assert!(!enabled(&delay, "dc_block"));
+        
+        delay.set_feedback_tilt(-0.5);
+        assert!(enabled(&delay, "feedback_tilt"));
+        
+        // The distortion moves along the chain with its placement
+        let position = |delay: &StereoDelay| delay.effect_chain().iter().position(|stage| stage.name == "distortion");
+        assert_eq!(position(&delay), Some(2));
+        delay.set_distortion_placement(DistortionPlacement::Feedback);
+        assert!(position(&delay).unwrap() > 5);
+        delay.set_cross_feedback_distortion(Some(false), None, None, None, None);
+        assert!(!enabled(&delay, "distortion"));
+    }
+    
+    #[test]
+    fn test_status_keeps_parameters_named_like_chain_stages() {
+        let mut delay = StereoDelay::from_config(&AudioConfig::default());
+        delay.set_parameter("auto_width_enabled", 1.0).unwrap();
+        delay.set_parameter("mono_mode", 1.0).unwrap();
+        
+        // Mono mode bypasses the stage without switching the parameter off
+        let stage = delay.effect_chain().into_iter().find(|stage| stage.name == "auto_width").unwrap();
+        assert!(!stage.enabled);
+        assert_eq!(delay.get_parameters()["auto_width_enabled"], 1.0);
+        assert_eq!(delay.get_status()["auto_width_enabled"], "true");
+    }
+    
+    #[test]
+    fn test_latency_compensation_delays_dry_by_reported_latency() {
+        // First sample index at which a channel is not silent
+        fn first_sound(samples: &[f32]) -> Option<usize> {
+            samples.iter().position(|sample| sample.abs() > 1e-6)
         }
         
-        (left_output, right_output)
+        let mut config = AudioConfig::default();
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.stereo_delay.feedback = 0.0;
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.02;
+        config.stereo_delay.latency_compensation = true;
+        let mut delay = StereoDelay::from_config(&config);
+        
+        let expected = (0.01 * config.sample_rate as f32).round() as usize;
+        assert_eq!(delay.dry_latency_samples(), expected);
+        assert_eq!(delay.get_status()["dry_latency_samples"], expected.to_string());
+        
+        // The right tap is twice as long, so the dry impulse is heard alone first
+        let impulse_response = |delay: &mut StereoDelay| -> Vec<f32> {
+            (0..expected * 3)
+                .map(|i| delay.process_sample(0.0, if i == 0 { 1.0 } else { 0.0 }).1)
+                .collect()
+        };
+        assert_eq!(first_sound(&impulse_response(&mut delay)), Some(delay.dry_latency_samples()));
+        
+        // Without compensation the dry path has no latency at all
+        delay.set_latency_compensation(false);
+        delay.reset();
+        assert_eq!(delay.dry_latency_samples(), 0);
+        assert_eq!(first_sound(&impulse_response(&mut delay)), Some(0));
     }
     
-    /// Get stereo-specific information
-    pub fn get_stereo_info(&self) -> String {
-        let base_info = format!(
-            "Left: {:.0}ms, Right: {:.0}ms, Ping-pong: {}, Width: {:.0}%",
-            self.left_delay * 1000.0,
-            self.right_delay * 1000.0,
-            if self.ping_pong { "On" } else { "Off" },
-            self.stereo_width * 100.0
-        );
+    #[test]
+    fn test_path_eqs_only_shape_their_own_path() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.stereo_delay.feedback = 0.0;
+        config.stereo_delay.ping_pong = false;
+        config.distortion.enabled = false;
+        // The DC blocker's long tail would carry the dry impulse into the repeat
+        config.dc_block = false;
         
-        let distortion_info = self.cross_feedback_distortion.get_info();
-        format!("{} | {}", base_info, distortion_info)
+        // The repeat of an impulse arrives one delay time after the dry impulse
+        let delay_samples = (0.01 * config.sample_rate as f32).round() as usize;
+        let impulse_response = |config: &AudioConfig| {
+            let mut delay = StereoDelay::from_config(config);
+            (0..2 * delay_samples)
+                .map(|i| {
+                    let input = if i == 0 { 1.0 } else { 0.0 };
+                    delay.process_sample(input, input).0
+                })
+                .collect::<Vec<_>>()
+        };
+        let differs = |a: &[f32], b: &[f32]| a.iter().zip(b).any(|(a, b)| (a - b).abs() > 1e-3);
+        let same = |a: &[f32], b: &[f32]| a.iter().zip(b).all(|(a, b)| (a - b).abs() < 1e-6);
+        
+        let plain = impulse_response(&config);
+        let (plain_dry, plain_wet) = plain.split_at(delay_samples);
+        
+        config.eq_dry = PathEqConfig { enabled: true, tilt: 1.0 };
+        let dry_eq = impulse_response(&config);
+        let (dry, wet) = dry_eq.split_at(delay_samples);
+        assert!(differs(dry, plain_dry));
+        assert!(same(wet, plain_wet));
+        
+        config.eq_dry = PathEqConfig::default();
+        config.eq_wet = PathEqConfig { enabled: true, tilt: -1.0 };
+        let wet_eq = impulse_response(&config);
+        let (dry, wet) = wet_eq.split_at(delay_samples);
+        assert!(same(dry, plain_dry));
+        assert!(differs(wet, plain_wet));
+        
+        // Bypassed, a tilt setting has no effect
+        config.eq_wet.enabled = false;
+        assert!(same(&impulse_response(&config), &plain));
     }
     
-    /// Get current parameter values including stereo-specific ones
-    pub fn get_parameters(&self) -> std::collections::HashMap<String, f32> {
-        let mut params = std::collections::HashMap::new();
-        params.insert("feedback".to_string(), self.feedback);
-        params.insert("wet_mix".to_string(), self.wet_mix);
-        params.insert("left_delay".to_string(), self.left_delay);
-        params.insert("right_delay".to_string(), self.right_delay);
-        params.insert("stereo_width".to_string(), self.stereo_width);
-        params.insert("cross_feedback".to_string(), self.cross_feedback);
-        params
+    #[test]
+    fn test_panic_clears_all_state() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.015;
+        config.stereo_delay.feedback = 0.8;
+        config.distortion.enabled = true;
+        config.distortion.distortion_type = "tube".to_string();
+        config.distortion.auto_gain_comp = true;
+        config.compressor.enabled = true;
+        let mut delay = StereoDelay::from_config(&config);
+        let mut clean = StereoDelay::from_config(&config);
+        
+        for i in 0..4800 {
+            let phase = 2.0 * std::f32::consts::PI * 330.0 * i as f32 / 48000.0;
+            delay.process_sample(phase.sin(), phase.cos());
+        }
+        delay.set_feedback(0.3);
+        clean.set_feedback(0.3);
+        clean.snap_smoothed_parameters();
+        delay.panic();
+        
+        assert!(delay.left_buffer.iter().chain(delay.right_buffer.iter()).all(|&sample| sample == 0.0));
+        assert!(delay.get_meters().iter().all(|(_, &value)| value == 0.0 || value == -120.0));
+        assert_eq!(delay.smoothed_feedback.current(), 0.3);
+        
+        // An impulse now gets exactly the response of a freshly built delay
+        for i in 0..2000 {
+            let input = if i == 0 { 1.0 } else { 0.0 };
+            assert_eq!(delay.process_sample(input, input), clean.process_sample(input, input), "sample {}", i);
+        }
     }
     
-    /// Get a human-readable description of current settings
-    pub fn get_info(&self) -> String {
-        format!(
-            "{}: L={:.0}ms, R={:.0}ms, Feedback={:.0}%, Wet={:.0}%",
-            self.get_effect_name(),
-            self.left_delay * 1000.0,
-            self.right_delay * 1000.0,
-            self.feedback * 100.0,
-            self.wet_mix * 100.0
-        )
+    #[test]
+    fn test_nan_input_does_not_poison_the_delay() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.02;
+        config.stereo_delay.feedback = 0.8;
+        let mut delay = StereoDelay::from_config(&config);
+        
+        for i in 0..1024 {
+            delay.process_sample((i as f32 * 0.05).sin() * 0.5, 0.0);
+        }
+        delay.process_sample(f32::NAN, f32::INFINITY);
+        delay.process_sample(f32::NEG_INFINITY, 1e-40);
+        
+        // Several trips around the loop later everything is still finite and audible
+        let mut peak = 0.0f32;
+        for i in 0..config.sample_rate as usize / 10 {
+            let (left, right) = delay.process_sample((i as f32 * 0.05).sin() * 0.5, 0.0);
+            assert!(left.is_finite() && right.is_finite(), "non-finite output at sample {}", i);
+            peak = peak.max(left.abs());
+        }
+        assert!(peak > 0.1, "delay went silent after the bad samples: {}", peak);
+        
+        let meters = delay.get_meters();
+        assert!(meters.values().all(|level| level.is_finite()));
     }
-}
-
-impl BaseDelay for StereoDelay {
-    fn get_effect_name(&self) -> &str {
-        "Stereo Delay"
+    
+    #[test]
+    fn test_feedback_insert_processes_the_repeats() {
+        // Peak of the first three echoes of an impulse
+        fn echo_peaks(delay: &mut StereoDelay, period: usize) -> [f32; 3] {
+            let mut peaks = [0.0f32; 3];
+            for i in 0..period * 3 + period / 2 {
+                let (left, _) = delay.process_sample(if i == 0 { 1.0 } else { 0.0 }, 0.0);
+                let echo = (i + period / 2) / period;
+                if (1..=3).contains(&echo) {
+                    peaks[echo - 1] = peaks[echo - 1].max(left.abs());
+                }
+            }
+            peaks
+        }
+        
+        let mut config = AudioConfig::default();
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.stereo_delay.left_delay = 0.1;
+        config.stereo_delay.right_delay = 0.1;
+        config.stereo_delay.feedback = 0.5;
+        config.stereo_delay.wet_mix = 1.0;
+        let period = config.sample_rate as usize / 10;
+        
+        let mut delay = StereoDelay::from_config(&config);
+        let plain = echo_peaks(&mut delay, period);
+        
+        let mut delay = StereoDelay::from_config(&config);
+        assert!(delay.set_feedback_insert(Box::new(|left, right| (left * 0.5, right * 0.5))).is_none());
+        assert!(delay.has_feedback_insert());
+        let inserted = echo_peaks(&mut delay, period);
+        
+        // The first echo never passes the loop, each later one loses twice as much per pass
+        assert!((plain[0] - inserted[0]).abs() < 1e-6);
+        for n in 1..3 {
+            let plain_ratio = plain[n] / plain[n - 1];
+            let inserted_ratio = inserted[n] / inserted[n - 1];
+            assert!((plain_ratio - 0.5).abs() < 1e-3, "echo {} decays by {}", n, plain_ratio);
+            assert!((inserted_ratio - 0.25).abs() < 1e-3, "echo {} decays by {} with the insert", n, inserted_ratio);
+        }
+        
+        assert!(delay.take_feedback_insert().is_some());
+        assert!(!delay.has_feedback_insert());
     }
     
-    fn process_sample(&mut self, input_sample: f32) -> (f32, f32) {
-        self.process_sample(input_sample, input_sample)
+    #[test]
+    fn test_wet_limiter_leaves_the_dry_attack_intact() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.01;
+        config.stereo_delay.right_delay = 0.01;
+        config.stereo_delay.feedback = 0.9;
+        config.stereo_delay.wet_mix = 0.5;
+        config.stereo_delay.mix_law = MixLaw::Linear;
+        config.stereo_delay.ping_pong = false;
+        config.stereo_delay.stereo_width = 0.0;
+        config.stereo_delay.cross_feedback = 0.0;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let (dry_gain, wet_gain) = MixLaw::Linear.gains(0.5);
+        
+        // A tone on the comb's peaks builds the repeats up, then a loud attack arrives
+        let sample_rate = config.sample_rate as f32;
+        let attack_at = config.sample_rate as usize / 2;
+        let input: Vec<f32> = (0..attack_at + 100)
+            .map(|i| {
+                let tone = 0.2 * (2.0 * std::f32::consts::PI * 200.0 * i as f32 / sample_rate).sin();
+                if i == attack_at { tone + 0.8 } else { tone }
+            })
+            .collect();
+        let wet_peak = |delay: &mut StereoDelay| {
+            let mut peak = 0.0f32;
+            let mut attack = 0.0;
+            for (i, &sample) in input.iter().enumerate() {
+                let (left, _) = delay.process_sample(sample, sample);
+                peak = peak.max((left - dry_gain * sample).abs() / wet_gain);
+                if i == attack_at {
+                    attack = left;
+                }
+            }
+            (peak, attack)
+        };
+        
+        let threshold = 10f32.powf(-12.0 / 20.0);
+        let (runaway, _) = wet_peak(&mut StereoDelay::from_config(&config));
+        assert!(runaway > 2.0 * threshold, "repeats only reached {}", runaway);
+        
+        config.stereo_delay.wet_limiter_enabled = true;
+        config.stereo_delay.wet_limiter_threshold = -12.0;
+        let mut delay = StereoDelay::from_config(&config);
+        let (limited, attack) = wet_peak(&mut delay);
+        assert!(limited <= threshold + 1e-4, "repeats reached {} above the threshold {}", limited, threshold);
+        
+        // The attack itself is not limited: only the bounded repeats can take from it
+        let dry_attack = dry_gain * input[attack_at];
+        assert!(attack >= dry_attack - wet_gain * threshold - 1e-4, "attack {} squashed below {}", attack, dry_attack);
+        
+        assert!(delay.effect_chain().iter().any(|stage| stage.name == "wet_limiter" && stage.enabled));
+        assert!(delay.set_parameter("wet_limiter_threshold", 3.0).is_err());
+        delay.set_parameter("wet_limiter_threshold", -3.0).unwrap();
+        let mut saved = AudioConfig::default();
+        delay.write_config(&mut saved);
+        assert!(saved.stereo_delay.wet_limiter_enabled);
+        assert_eq!(saved.stereo_delay.wet_limiter_threshold, -3.0);
     }
     
-    fn process_buffer(&mut self, input_buffer: &[f32]) -> Vec<(f32, f32)> {
-        let mut output = Vec::with_capacity(input_buffer.len());
+    #[test]
+    fn test_double_track_adds_detuned_copies_panned_left_and_right() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.wet_mix = 0.0;
+        config.stereo_delay.mix_law = MixLaw::Linear;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        config.stereo_delay.double_track_enabled = true;
+        config.stereo_delay.double_track_spread = 1.0;
+        config.stereo_delay.double_track_detune = 8.0;
+        let sample_rate = config.sample_rate as f32;
+        let window = (0.05 * sample_rate) as usize;
+        let second_at = (0.4 * sample_rate) as usize;
         
-        for &input_sample in input_buffer {
-            output.push(self.process_sample(input_sample, input_sample));
+        // Two impulses far enough apart for the modulation to have moved on
+        let responses = |config: &AudioConfig| {
+            let mut delay = StereoDelay::from_config(config);
+            let mut left = Vec::new();
+            let mut right = Vec::new();
+            for i in 0..second_at + window {
+                let input = if i == 0 || i == second_at { 1.0 } else { 0.0 };
+                let (l, r) = delay.process_sample(input, input);
+                left.push(l);
+                right.push(r);
+            }
+            (left, right)
+        };
+        // Level and arrival time in ms of the copies within one side, past the dry impulse
+        let copy = |side: &[f32], start: usize| {
+            let copies = &side[start + 1..start + window];
+            let level: f32 = copies.iter().map(|x| x.abs()).sum();
+            let centre: f32 = copies.iter().enumerate().map(|(i, x)| (i + 1) as f32 * x.abs()).sum::<f32>() / level.max(1e-9);
+            (level, centre * 1000.0 / sample_rate)
+        };
+        
+        let (left, right) = responses(&config);
+        assert!((left[0] - 1.0).abs() < 1e-6 && (right[0] - 1.0).abs() < 1e-6, "dry passes unchanged");
+        let (left_level, left_ms) = copy(&left, 0);
+        let (right_level, right_ms) = copy(&right, 0);
+        assert!((left_level - 0.5).abs() < 0.01 && (right_level - 0.5).abs() < 0.01, "copies at {} and {}", left_level, right_level);
+        assert!((20.0..=40.0).contains(&left_ms) && (20.0..=40.0).contains(&right_ms));
+        assert!((left_ms - right_ms).abs() > 5.0, "one copy on each side, at {}ms and {}ms", left_ms, right_ms);
+        
+        // The copies arrive at a different time for the second impulse, so they are pitch modulated
+        let (_, left_later_ms) = copy(&left, second_at);
+        let (_, right_later_ms) = copy(&right, second_at);
+        assert!((left_later_ms - left_ms).abs() > 0.5, "left copy stayed at {}ms", left_ms);
+        assert!((right_later_ms - right_ms).abs() > 0.5, "right copy stayed at {}ms", right_ms);
+        
+        // Without detune they stay put, and with no spread both copies land on both sides
+        config.stereo_delay.double_track_detune = 0.0;
+        config.stereo_delay.double_track_spread = 0.0;
+        let (left, right) = responses(&config);
+        assert!((copy(&left, second_at).1 - copy(&left, 0).1).abs() < 0.01);
+        assert_eq!(left, right);
+        
+        let mut delay = StereoDelay::from_config(&config);
+        assert!(delay.effect_chain().iter().any(|stage| stage.name == "double_track" && stage.enabled));
+        assert!(delay.set_parameter("double_track_detune", 30.0).is_err());
+        assert!(delay.set_parameter("double_track_spread", 1.5).is_err());
+        delay.set_parameter("double_track_detune", 12.0).unwrap();
+        let mut saved = AudioConfig::default();
+        delay.write_config(&mut saved);
+        assert!(saved.stereo_delay.double_track_enabled);
+        assert_eq!(saved.stereo_delay.double_track_detune, 12.0);
+        assert_eq!(saved.stereo_delay.double_track_spread, 0.0);
+    }
+    
+    #[test]
+    fn test_shimmer_amount_raises_octave_up_content_and_diffusion() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.left_delay = 0.2;
+        config.stereo_delay.right_delay = 0.2;
+        config.stereo_delay.feedback = 0.6;
+        config.stereo_delay.wet_mix = 1.0;
+        config.stereo_delay.mix_law = MixLaw::Linear;
+        config.stereo_delay.ping_pong = false;
+        config.distortion.enabled = false;
+        config.dc_block = false;
+        let sample_rate = config.sample_rate as f32;
+        let delay_samples = (0.2 * sample_rate) as usize;
+        
+        // Left output for the given input, long enough for a few passes of the loop
+        let run = |amount: f32, input: &dyn Fn(usize) -> f32| {
+            let mut config = config.clone();
+            config.shimmer.amount = amount;
+            let mut delay = StereoDelay::from_config(&config);
+            (0..5 * delay_samples).map(|i| delay.process_sample(input(i), input(i)).0).collect::<Vec<f32>>()
+        };
+        // Magnitude of one frequency in a signal (a single DFT bin)
+        let level_at = |signal: &[f32], hz: f32| {
+            let (re, im) = signal.iter().enumerate().fold((0.0f64, 0.0f64), |(re, im), (i, &x)| {
+                let angle = 2.0 * std::f64::consts::PI * hz as f64 * i as f64 / sample_rate as f64;
+                (re + x as f64 * angle.cos(), im - x as f64 * angle.sin())
+            });
+            (re * re + im * im).sqrt()
+        };
+        // Energy-weighted spread in ms of the second repeat, the first one that went round the loop
+        let spread_ms = |output: &[f32]| {
+            let repeat = &output[2 * delay_samples - 10..3 * delay_samples - 10];
+            let energy: f32 = repeat.iter().map(|x| x * x).sum();
+            let centre: f32 = repeat.iter().enumerate().map(|(i, x)| i as f32 * x * x).sum::<f32>() / energy;
+            let variance: f32 = repeat.iter().enumerate().map(|(i, x)| (i as f32 - centre).powi(2) * x * x).sum::<f32>() / energy;
+            variance.sqrt() * 1000.0 / sample_rate
+        };
+        
+        // A 440Hz burst as long as the delay, so the repeats do not overlap
+        let burst = |i: usize| if i < delay_samples { 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate).sin() } else { 0.0 };
+        let impulse = |i: usize| if i == 0 { 1.0 } else { 0.0 };
+        let mut last = (0.0, 0.0);
+        for amount in [0.0, 0.5, 1.0] {
+            let repeats = run(amount, &burst);
+            let octave_up = level_at(&repeats[2 * delay_samples..], 880.0) / level_at(&repeats[2 * delay_samples..], 440.0);
+            let spread = spread_ms(&run(amount, &impulse));
+            if amount == 0.0 {
+                assert!(octave_up < 0.01, "octave-up content {} without shimmer", octave_up);
+                assert!(spread < 0.1, "repeat spread over {}ms without shimmer", spread);
+            } else {
+                assert!(octave_up > last.0 * 1.2, "octave-up content {} at amount {}, {} below", octave_up, amount, last.0);
+                assert!(spread > last.1 + 1.0, "repeat spread {}ms at amount {}, {}ms below", spread, amount, last.1);
+            }
+            last = (octave_up, spread);
         }
         
-        output
+        let mut delay = StereoDelay::from_config(&config);
+        assert!(!delay.effect_chain().iter().any(|stage| stage.name == "shimmer" && stage.enabled));
+        assert!(delay.set_parameter("shimmer_amount", 1.5).is_err());
+        delay.set_parameter("shimmer_amount", 0.4).unwrap();
+        delay.set_parameter("shimmer_diffusion", 0.2).unwrap();
+        assert!(delay.effect_chain().iter().any(|stage| stage.name == "shimmer" && stage.enabled));
+        let mut saved = AudioConfig::default();
+        delay.write_config(&mut saved);
+        assert_eq!(saved.shimmer, ShimmerConfig { amount: 0.4, diffusion: 0.2, ..ShimmerConfig::default() });
+        
+        // The shipped preset is the built-in one
+        let preset = AudioConfig::from_file(concat!(env!("CARGO_MANIFEST_DIR"), "/presets/shimmer.json")).unwrap();
+        assert!(preset.validate().is_ok());
+        assert_eq!(preset.shimmer, AudioConfig::shimmer_preset().shimmer);
+        assert!(preset.shimmer.amount > 0.0);
     }
     
-    fn reset(&mut self) {
-        self.left_buffer.fill(0.0);
-        self.right_buffer.fill(0.0);
-        self.left_write_index = 0;
-        self.right_write_index = 0;
+    #[test]
+    fn test_mono_mode_outputs_identical_channels() {
+        let mut config = AudioConfig::default();
+        config.stereo_delay.mono_mode = true;
+        config.stereo_delay.feedback = 0.6;
+        config.stereo_delay.stereo_width = 1.0;
+        config.stereo_delay.ping_pong = true;
+        let mut delay = StereoDelay::from_config(&config);
+        assert_eq!(delay.get_status()["mono_mode"], "true");
+        assert!(!delay.effect_chain().iter().any(|stage| stage.name == "ping_pong" && stage.enabled));
+        
+        // A wide, uncorrelated input still comes out the same on both sides
+        let mut heard = 0.0f32;
+        for i in 0..config.sample_rate as usize {
+            let t = i as f32 / config.sample_rate as f32;
+            let left = 0.5 * (2.0 * std::f32::consts::PI * 220.0 * t).sin();
+            let right = if i < 2000 { 0.5 * (2.0 * std::f32::consts::PI * 330.0 * t).cos() } else { 0.0 };
+            let (out_left, out_right) = delay.process_sample(left, right);
+            assert_eq!(out_left, out_right, "channels differ at sample {}", i);
+            heard = heard.max(out_left.abs());
+        }
+        assert!(heard > 0.1);
+        
+        // Back in stereo the repeats carry on from the mono line
+        delay.set_parameter("mono_mode", 0.0).unwrap();
+        assert!(delay.right_buffer.iter().any(|&sample| sample != 0.0));
+        assert_eq!(delay.left_buffer, delay.right_buffer);
     }
     
-    fn set_delay_time(&mut self, delay_time: f32) {
-        self.set_left_delay(delay_time);
-        self.set_right_delay(delay_time);
+    #[test]
+    fn test_mono_safe_keeps_mono_sum_energy_at_full_width() {
+        // Fraction of the stereo energy left after summing the enhanced signal to mono
+        fn mono_retention(mono_safe: bool) -> f32 {
+            let mut delay = StereoDelay::new(
+                48000, 0.3, 0.3, 0.3, 0.5, false, 1.0, 0.0, false, DistortionType::SoftClip, 0.0, 0.0,
+            );
+            delay.set_mono_safe(mono_safe);
+            let mut mono_energy = 0.0;
+            let mut stereo_energy = 0.0;
+            for i in 0..48000 {
+                // Correlated source with the side at half the mid level
+                let s = (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 48000.0).sin();
+                let (left, right) = delay.apply_stereo_enhancement(1.5 * s, 0.5 * s);
+                if i >= 4800 {
+                    mono_energy += ((left + right) * 0.5).powi(2);
+                    stereo_energy += (left * left + right * right) * 0.5;
+                }
+            }
+            mono_energy / stereo_energy
+        }
+        
+        let unguarded = mono_retention(false);
+        let guarded = mono_retention(true);
+        assert!(unguarded < 0.55, "full width should push half the energy into the side: {}", unguarded);
+        assert!(guarded > 0.75, "mono_safe should keep most of the energy in mono: {}", guarded);
     }
     
-    fn set_feedback(&mut self, feedback: f32) {
-        self.feedback = feedback.clamp(0.0, 0.9);
+    #[test]
+    fn test_feedback_stage_order_changes_the_tone_of_the_repeats() {
+        // Share of the repeats' energy in their sample-to-sample differences, a
+        // rough measure of how much treble they carry
+        fn brightness_of_repeats(order: FeedbackStageOrder) -> f32 {
+            let mut config = AudioConfig::default();
+            config.stereo_delay.left_delay = 0.005;
+            config.stereo_delay.right_delay = 0.005;
+            config.stereo_delay.feedback = 0.7;
+            config.stereo_delay.wet_mix = 1.0;
+            config.stereo_delay.ping_pong = false;
+            config.stereo_delay.stereo_width = 0.0;
+            config.stereo_delay.cross_feedback = 0.0;
+            config.stereo_delay.feedback_lp_hz = 1500.0;
+            config.stereo_delay.feedback_stage_order = order;
+            config.distortion.enabled = true;
+            config.distortion.distortion_type = "hard_clip".to_string();
+            config.distortion.drive = 0.9;
+            config.distortion.mix = 1.0;
+            config.distortion.placement = DistortionPlacement::Feedback;
+            let mut delay = StereoDelay::from_config(&config);
+            
+            let (mut energy, mut difference, mut previous) = (0.0, 0.0, 0.0);
+            for i in 0..9600 {
+                let (left, _) = delay.process_sample(if i == 0 { 1.0 } else { 0.0 }, 0.0);
+                // Skip the first repeat, which has not been round the loop yet
+                if i >= 480 {
+                    energy += left * left;
+                    difference += (left - previous) * (left - previous);
+                }
+                previous = left;
+            }
+            difference / energy
+        }
+        
+        let filter_first = brightness_of_repeats(FeedbackStageOrder::FilterFirst);
+        let distortion_first = brightness_of_repeats(FeedbackStageOrder::DistortionFirst);
+        assert!(
+            filter_first > 1.5 * distortion_first,
+            "distortion after the filter should leave brighter repeats: {} vs {}",
+            filter_first, distortion_first
+        );
     }
     
-    fn set_wet_mix(&mut self, wet_mix: f32) {
-        self.wet_mix = wet_mix.clamp(0.0, 1.0);
-        self.dry_mix = 1.0 - self.wet_mix;
+    #[test]
+    fn test_feedback_color_voices_the_feedback_path() {
+        let mut delay = StereoDelay::from_config(&AudioConfig::default());
+        delay.set_feedback_color(FeedbackColor::Tape);
+        assert!(delay.get_feedback_filter().1 < FULL_RANGE_HZ, "tape should damp the repeats");
+        assert!(delay.get_feedback_tilt() < 0.0);
+        assert!(delay.get_feedback_saturation() > 0.0);
+        assert_eq!(delay.get_feedback_color(), FeedbackColor::Tape);
+        
+        delay.set_feedback_color(FeedbackColor::Digital);
+        assert_eq!(delay.get_feedback_filter(), (0.0, FULL_RANGE_HZ));
+        assert_eq!(delay.get_feedback_tilt(), 0.0);
+        assert_eq!(delay.get_feedback_saturation(), 0.0);
+        assert_eq!(delay.get_feedback_bit_depth(), MAX_FEEDBACK_BIT_DEPTH);
+        assert_eq!(delay.dub_cutoff, FULL_RANGE_HZ);
+        
+        // Tweaking a part of the voicing leaves it custom, and that is what gets saved
+        delay.set_parameter("feedback_color", FeedbackColor::Bbd.to_parameter()).unwrap();
+        delay.set_parameter("feedback_saturation", 0.5).unwrap();
+        let mut config = AudioConfig::default();
+        delay.write_config(&mut config);
+        assert_eq!(config.stereo_delay.feedback_color, FeedbackColor::Custom);
+        assert_eq!(config.stereo_delay.feedback_bit_depth, 12.0);
+        assert_eq!(config.stereo_delay.feedback_saturation, 0.5);
+    }
+    
+    #[test]
+    fn test_auto_width_widens_until_the_correlation_target() {
+        // Correlation of the enhanced signal over the last second of three
+        fn widened_correlation(delay: &mut StereoDelay) -> f32 {
+            let mut meter = CorrelationMeter::new(48000);
+            for i in 0..3 * 48000 {
+                // Mostly mid, with unrelated side content that full width would push past zero
+                let t = i as f32 / 48000.0;
+                let mid = (2.0 * std::f32::consts::PI * 220.0 * t).sin();
+                let side = 0.55 * (2.0 * std::f32::consts::PI * 331.0 * t).sin();
+                let (left, right) = delay.apply_stereo_enhancement(mid + side, mid - side);
+                meter.process(left, right);
+            }
+            meter.correlation()
+        }
+        
+        let new_delay = |stereo_width| {
+            StereoDelay::new(48000, 0.3, 0.3, 0.3, 0.5, false, stereo_width, 0.0, false, DistortionType::SoftClip, 0.0, 0.0)
+        };
+        let full_width = widened_correlation(&mut new_delay(1.0));
+        assert!(full_width < 0.0, "full width should cancel in mono: {}", full_width);
+        
+        let mut delay = new_delay(0.0);
+        delay.set_auto_width(Some(true), Some(0.2));
+        let correlation = widened_correlation(&mut delay);
+        let width = delay.get_parameters()["stereo_width"];
+        assert!(width > 0.2 && width < 0.9, "auto-width settled at {}", width);
+        assert!(correlation > 0.1, "auto-width let the correlation fall to {}", correlation);
+    }
+    
+    #[test]
+    fn test_di_sim_rolls_off_the_treble_with_a_resonant_bump() {
+        // Steady-state gain of the input stage for a sine at the given frequency
+        fn input_gain(di_sim: bool, frequency: f32) -> f32 {
+            let mut delay = StereoDelay::new(
+                48000, 0.3, 0.3, 0.0, 0.0, false, 1.0, 0.0, false, DistortionType::SoftClip, 0.0, 0.0,
+            );
+            delay.set_dc_block(false);
+            delay.set_di_sim(Some(di_sim), Some(DEFAULT_DI_SIM_RESONANCE));
+            // Half scale, so the resonant bump stays under the output ceiling
+            let mut peak: f32 = 0.0;
+            for i in 0..9600 {
+                let s = 0.5 * (2.0 * std::f32::consts::PI * frequency * i as f32 / 48000.0).sin();
+                let (left, _) = delay.process_sample(s, s);
+                if i >= 4800 {
+                    peak = peak.max(left.abs());
+                }
+            }
+            peak * 2.0
+        }
+        
+        let low = input_gain(true, 200.0);
+        let bump = input_gain(true, DI_SIM_CUTOFF_HZ);
+        let treble = input_gain(true, 12000.0);
+        assert!((low - 1.0).abs() < 0.05, "the low end should pass untouched: {}", low);
+        assert!(bump > 1.2, "there should be a resonant bump at the cutoff: {}", bump);
+        assert!(treble < 0.5, "the treble should be rolled off: {}", treble);
+        assert!(input_gain(false, 12000.0) > 0.95, "a disabled simulation should leave the input alone");
+    }
+    
+    #[test]
+    fn test_recording_meters_and_stats_take_the_output_after_the_chain_and_ceiling() {
+        let mut config = AudioConfig::default();
+        config.distortion.enabled = true;
+        config.distortion.drive = 1.0;
+        config.max_output_db = -24.0;
+        let process = |delay: &mut StereoDelay| -> Vec<(f32, f32)> {
+            (0..4800)
+                .map(|i| {
+                    let s = 0.9 * (2.0 * std::f32::consts::PI * 220.0 * i as f32 / 48000.0).sin();
+                    delay.process_sample(s, s)
+                })
+                .collect()
+        };
+        
+        // A distortion stage after the delay, so the recording has to come after the chain
+        config.effect_chain = vec!["delay".to_string(), "distortion".to_string()];
+        let mut delay = StereoDelay::from_config(&config);
+        assert_eq!(delay.get_effect_chain(), vec!["delay", "distortion"]);
+        let path = std::env::temp_dir().join(format!("delay_recording_{}.wav", std::process::id()));
+        let recording = crate::recorder::Recording::start(&path, 48000).unwrap();
+        delay.set_recording_tap(Some(recording.tap()));
+        let output = process(&mut delay);
+        delay.set_recording_tap(None);
+        recording.finish().unwrap();
+        
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+        std::fs::remove_file(&path).unwrap();
+        let recorded: Vec<(f32, f32)> = samples.chunks_exact(2).map(|frame| (frame[0], frame[1])).collect();
+        assert_eq!(recorded, output);
+        
+        config.effect_chain = vec!["delay".to_string()];
+        assert_ne!(process(&mut StereoDelay::from_config(&config)), recorded, "the post-delay stage was not recorded");
+        
+        // The output reached the ceiling, so its clamped level is what was recorded
+        let ceiling = 10f32.powf(-24.0 / 20.0);
+        assert!(recorded.iter().any(|&(left, _)| (left.abs() - ceiling).abs() < 1e-6));
+        assert!(recorded.iter().all(|&(left, right)| left.abs() <= ceiling && right.abs() <= ceiling));
+        
+        // The stats and meters follow the same output, not the delay stage's
+        let peak = recorded.iter().fold(0.0f32, |peak, &(left, right)| peak.max(left.abs()).max(right.abs()));
+        assert_eq!(delay.session_stats().report().peak_level, peak);
+        let meters = delay.get_meters();
+        assert!(meters["meter_left"] > 0.0 && meters["meter_left"] <= ceiling, "{}", meters["meter_left"]);
     }
 }
@@ -0,0 +1,666 @@
+use cpal::traits::{DeviceTrait, HostTrait};
+use serde::{Deserialize, Serialize};
+use crate::error::AudioProcessorError;
+
+/// Sample rates reported for a device when its supported range includes them
+pub const STANDARD_SAMPLE_RATES: [u32; 8] = [22050, 32000, 44100, 48000, 88200, 96000, 176400, 192000];
+
+/// An audio device that can be selected as input or output
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DeviceInfo {
+    /// Name to put in `input_device` / `output_device` of the configuration
+    pub name: String,
+    
+    /// Human readable description, if the backend provides one
+    pub description: Option<String>,
+    
+    /// Standard sample rates the device supports, in Hz
+    pub sample_rates: Vec<u32>,
+    
+    /// Supported sample formats, e.g. "i16" or "f32"
+    pub formats: Vec<String>,
+}
+
+impl DeviceInfo {
+    /// Create a device entry with no description
+    pub fn new(name: &str, sample_rates: Vec<u32>, formats: Vec<String>) -> Self {
+        Self {
+            name: name.to_string(),
+            description: None,
+            sample_rates,
+            formats,
+        }
+    }
+}
+
+/// Available input and output devices
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct DeviceList {
+    pub inputs: Vec<DeviceInfo>,
+    pub outputs: Vec<DeviceInfo>,
+}
+
+/// Format a device list together with the currently configured devices for the web API
+pub fn devices_json(
+    devices: &DeviceList,
+    input_device: Option<&str>,
+    output_device: Option<&str>,
+) -> serde_json::Value {
+    serde_json::json!({
+        "inputs": devices.inputs,
+        "outputs": devices.outputs,
+        "selected": {
+            "input_device": input_device,
+            "output_device": output_device
+        }
+    })
+}
+
+/// Standard rates that fall inside any of the given (min, max) ranges
+fn rates_in_ranges(ranges: &[(u32, u32)]) -> Vec<u32> {
+    STANDARD_SAMPLE_RATES
+        .iter()
+        .copied()
+        .filter(|rate| ranges.iter().any(|(min, max)| (*min..=*max).contains(rate)))
+        .collect()
+}
+
+/// Clamp a requested buffer size in frames to a device's supported range
+pub fn clamp_buffer_size(requested: usize, min: u32, max: u32) -> u32 {
+    let requested = requested.min(u32::MAX as usize) as u32;
+    requested.clamp(min, max.max(min))
+}
+
+/// Period and device buffer size in frames to open an ALSA device with
+///
+/// The period is the requested size and the buffer holds `periods` of them (at least
+/// two), each clamped to the device's (min, max) range. If the buffer cannot hold
+/// two periods the period is shrunk to fit.
+pub fn negotiate_period_and_buffer(
+    requested_period: usize,
+    periods: u32,
+    period_range: (u32, u32),
+    buffer_range: (u32, u32),
+) -> (u32, u32) {
+    let period = clamp_buffer_size(requested_period, period_range.0, period_range.1);
+    let buffer = clamp_buffer_size(
+        (period as usize).saturating_mul(periods.max(2) as usize),
+        buffer_range.0,
+        buffer_range.1,
+    );
+    if buffer < period.saturating_mul(2) {
+        (clamp_buffer_size(buffer as usize / 2, period_range.0, period_range.1), buffer)
+    } else {
+        (period, buffer)
+    }
+}
+
+/// Buffer size to request from a cpal device for the configured size
+///
+/// Devices that do not report a range get the configured size as is; if they
+/// reject it the stream is rebuilt with the device default.
+pub fn buffer_size_hint(requested: usize, supported: &cpal::SupportedBufferSize) -> cpal::BufferSize {
+    match supported {
+        cpal::SupportedBufferSize::Range { min, max } => cpal::BufferSize::Fixed(clamp_buffer_size(requested, *min, *max)),
+        cpal::SupportedBufferSize::Unknown => cpal::BufferSize::Fixed(requested.min(u32::MAX as usize) as u32),
+    }
+}
+
+/// Channel count to open a device with, given the range it supports
+///
+/// The requested count is used when the device allows it, otherwise the nearest
+/// supported count. A device never gets fewer than one channel.
+pub fn negotiate_channels(requested: u32, min: u32, max: u32) -> u32 {
+    let min = min.max(1);
+    requested.clamp(min, max.max(min))
+}
+
+/// Integer sample format of an ALSA device
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum SampleFormat {
+    /// 32-bit samples
+    #[default]
+    S32,
+    /// 24-bit samples in the low bits of a 32-bit container
+    S24,
+    /// 16-bit samples
+    S16,
+}
+
+impl SampleFormat {
+    /// Formats tried in order when the configured one is not supported
+    pub const FALLBACK_ORDER: [SampleFormat; 3] = [SampleFormat::S32, SampleFormat::S24, SampleFormat::S16];
+    
+    /// Integer value of a full-scale (1.0) sample
+    pub fn scale(&self) -> f32 {
+        match self {
+            SampleFormat::S32 => i32::MAX as f32,
+            SampleFormat::S24 => ((1 << 23) - 1) as f32,
+            SampleFormat::S16 => i16::MAX as f32,
+        }
+    }
+    
+    /// Convert an integer sample to -1.0..1.0
+    pub fn to_f32(&self, sample: i32) -> f32 {
+        sample as f32 / self.scale()
+    }
+    
+    /// Convert a -1.0..1.0 sample to an integer, clipping anything beyond full scale
+    pub fn from_f32(&self, value: f32) -> i32 {
+        (value.clamp(-1.0, 1.0) * self.scale()) as i32
+    }
+    
+    /// Whether output in this format is dithered when the configuration does not say
+    ///
+    /// 16-bit truncation is audible on quiet tails; 24 and 32 bits are below the noise floor.
+    pub fn dither_by_default(&self) -> bool {
+        *self == SampleFormat::S16
+    }
+}
+
+/// Triangular (TPDF) dither for converting f32 samples to integers
+///
+/// Adds the sum of two uniform random values of ±0.5 LSB before rounding, which
+/// turns the quantization error into steady noise that does not follow the signal,
+/// instead of distortion that does.
+pub struct TpdfDither {
+    state: u32,
+}
+
+impl TpdfDither {
+    /// Create a dither with a fixed seed
+    pub fn new() -> Self {
+        Self { state: 0x2545f491 }
+    }
+    
+    /// Convert a -1.0..1.0 sample to an integer with dither, clipping anything beyond full scale
+    pub fn quantize(&mut self, format: SampleFormat, value: f32) -> i32 {
+        // f64 keeps the fraction of an LSB even at 32 bits
+        let scale = format.scale() as f64;
+        let noise = (self.uniform() + self.uniform()) as f64;
+        (value.clamp(-1.0, 1.0) as f64 * scale + noise).round().clamp(-scale, scale) as i32
+    }
+    
+    /// Uniform random value in -0.5..0.5 (xorshift32)
+    fn uniform(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 17;
+        self.state ^= self.state << 5;
+        self.state as f32 / u32::MAX as f32 - 0.5
+    }
+}
+
+impl Default for TpdfDither {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl std::fmt::Display for SampleFormat {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            SampleFormat::S32 => "S32",
+            SampleFormat::S24 => "S24",
+            SampleFormat::S16 => "S16",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// First format a device accepts: the configured one, then S32, S24 and S16
+pub fn negotiate_sample_format(preferred: SampleFormat, mut supported: impl FnMut(SampleFormat) -> bool) -> Option<SampleFormat> {
+    std::iter::once(preferred)
+        .chain(SampleFormat::FALLBACK_ORDER.into_iter().filter(|format| *format != preferred))
+        .find(|format| supported(*format))
+}
+
+/// Order of the samples in a multi-channel device buffer
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ChannelLayout {
+    /// One frame after another, each with a sample per channel (LRLR...)
+    #[default]
+    Interleaved,
+    /// One channel after another, each with every frame of the buffer (LL...RR...)
+    Planar,
+}
+
+impl ChannelLayout {
+    /// Position of a sample in a buffer of `frames` frames
+    fn index(self, frame: usize, channel: usize, frames: usize, channels: usize) -> usize {
+        match self {
+            ChannelLayout::Interleaved => frame * channels + channel,
+            ChannelLayout::Planar => channel * frames + frame,
+        }
+    }
+    
+    /// Split a buffer in this layout into one stream per channel
+    ///
+    /// Samples past the last whole frame are ignored.
+    pub fn deinterleave<T: Copy>(self, buffer: &[T], channels: usize) -> Vec<Vec<T>> {
+        let channels = channels.max(1);
+        let frames = buffer.len() / channels;
+        (0..channels)
+            .map(|channel| (0..frames).map(|frame| buffer[self.index(frame, channel, frames, channels)]).collect())
+            .collect()
+    }
+    
+    /// Join per-channel streams into a buffer in this layout
+    ///
+    /// Every stream is cut to the length of the shortest one.
+    pub fn interleave<T: Copy + Default>(self, streams: &[Vec<T>]) -> Vec<T> {
+        let channels = streams.len();
+        let frames = streams.iter().map(Vec::len).min().unwrap_or(0);
+        let mut buffer = vec![T::default(); frames * channels];
+        for (channel, stream) in streams.iter().enumerate() {
+            for (frame, &sample) in stream.iter().take(frames).enumerate() {
+                buffer[self.index(frame, channel, frames, channels)] = sample;
+            }
+        }
+        buffer
+    }
+    
+    /// Copy a buffer in this layout into `output` as interleaved frames
+    ///
+    /// `output` is reused between calls, so the audio thread does not allocate.
+    pub fn copy_to_interleaved<T: Copy>(self, buffer: &[T], channels: usize, output: &mut Vec<T>) {
+        let channels = channels.max(1);
+        let frames = buffer.len() / channels;
+        output.clear();
+        for frame in 0..frames {
+            for channel in 0..channels {
+                output.push(buffer[self.index(frame, channel, frames, channels)]);
+            }
+        }
+    }
+    
+    /// Copy interleaved frames into a buffer in this layout
+    pub fn copy_from_interleaved<T: Copy>(self, interleaved: &[T], channels: usize, buffer: &mut [T]) {
+        let channels = channels.max(1);
+        let frames = (buffer.len() / channels).min(interleaved.len() / channels);
+        let buffer_frames = buffer.len() / channels;
+        for frame in 0..frames {
+            for channel in 0..channels {
+                buffer[self.index(frame, channel, buffer_frames, channels)] = interleaved[frame * channels + channel];
+            }
+        }
+    }
+}
+
+/// Left and right samples of one interleaved frame
+///
+/// A mono frame feeds both sides; channels beyond the second are ignored. An
+/// empty frame reads as silence.
+pub fn frame_to_stereo<T: Copy + Default>(frame: &[T]) -> (T, T) {
+    match frame {
+        [mono] => (*mono, *mono),
+        [left, right, ..] => (*left, *right),
+        [] => (T::default(), T::default()),
+    }
+}
+
+/// Write a stereo pair into one interleaved frame, starting at channel `offset`
+///
+/// A mono frame gets the average of both sides; every other channel is silent.
+/// An offset that leaves no room for the pair writes silence.
+pub fn stereo_to_frame(left: f32, right: f32, offset: usize, frame: &mut [f32]) {
+    if let [mono] = frame {
+        *mono = 0.5 * (left + right);
+        return;
+    }
+    frame.fill(0.0);
+    if let Some([first, second]) = frame.get_mut(offset..offset + 2) {
+        *first = left;
+        *second = right;
+    }
+}
+
+/// Spread interleaved stereo samples over a buffer of `channels` channel frames
+///
+/// The pair goes to channels `offset` and `offset + 1` of each frame, see
+/// `stereo_to_frame`. Frames past the end of `stereo` are silent.
+pub fn interleave_stereo(stereo: &[f32], channels: usize, offset: usize, output: &mut [f32]) {
+    let mut pairs = stereo.chunks_exact(2);
+    for frame in output.chunks_mut(channels.max(1)) {
+        let (left, right) = pairs.next().map(|pair| (pair[0], pair[1])).unwrap_or((0.0, 0.0));
+        stereo_to_frame(left, right, offset, frame);
+    }
+}
+
+/// Check that a device with `channels` output channels has room for the pair at `offset`
+pub fn check_output_channel_offset(offset: usize, channels: usize) -> Result<(), AudioProcessorError> {
+    if offset == 0 || offset + 2 <= channels {
+        return Ok(());
+    }
+    Err(AudioProcessorError::InvalidParameter {
+        param: "output_channel_offset".to_string(),
+        value: offset as f32,
+        min: 0.0,
+        max: channels.saturating_sub(2) as f32,
+    })
+}
+
+/// Collect the rates and formats of a set of cpal stream configurations
+fn describe_cpal_configs<I>(name: String, configs: I) -> DeviceInfo
+where
+    I: Iterator<Item = cpal::SupportedStreamConfigRange>,
+{
+    let mut ranges = Vec::new();
+    let mut formats: Vec<String> = Vec::new();
+    for config in configs {
+        ranges.push((config.min_sample_rate().0, config.max_sample_rate().0));
+        let format = format!("{:?}", config.sample_format()).to_lowercase();
+        if !formats.contains(&format) {
+            formats.push(format);
+        }
+    }
+    
+    DeviceInfo::new(&name, rates_in_ranges(&ranges), formats)
+}
+
+/// Enumerate the input and output devices of the default cpal host
+pub fn list_cpal_devices() -> DeviceList {
+    let host = cpal::default_host();
+    let mut devices = DeviceList::default();
+    
+    if let Ok(inputs) = host.input_devices() {
+        for device in inputs {
+            if let Ok(name) = device.name() {
+                let configs = device.supported_input_configs().map(|c| c.collect::<Vec<_>>()).unwrap_or_default();
+                devices.inputs.push(describe_cpal_configs(name, configs.into_iter()));
+            }
+        }
+    }
+    
+    if let Ok(outputs) = host.output_devices() {
+        for device in outputs {
+            if let Ok(name) = device.name() {
+                let configs = device.supported_output_configs().map(|c| c.collect::<Vec<_>>()).unwrap_or_default();
+                devices.outputs.push(describe_cpal_configs(name, configs.into_iter()));
+            }
+        }
+    }
+    
+    devices
+}
+
+/// Enumerate ALSA PCM device names from the device hints
+///
+/// Each PCM is briefly opened to probe its rates and formats. Devices that are busy
+/// or cannot be opened are still listed, just without rates and formats.
+#[cfg(target_os = "linux")]
+pub fn list_alsa_pcm_devices() -> DeviceList {
+    use alsa::device_name::HintIter;
+    use alsa::Direction;
+    
+    let mut devices = DeviceList::default();
+    let hints = match HintIter::new_str(None, "pcm") {
+        Ok(hints) => hints,
+        Err(_) => return devices,
+    };
+    
+    for hint in hints {
+        let name = match hint.name {
+            Some(name) if name != "null" => name,
+            _ => continue,
+        };
+        
+        // A hint without a direction supports both capture and playback
+        if hint.direction != Some(Direction::Playback) {
+            let mut info = probe_alsa_pcm(&name, Direction::Capture);
+            info.description = hint.desc.clone();
+            devices.inputs.push(info);
+        }
+        if hint.direction != Some(Direction::Capture) {
+            let mut info = probe_alsa_pcm(&name, Direction::Playback);
+            info.description = hint.desc.clone();
+            devices.outputs.push(info);
+        }
+    }
+    
+    devices
+}
+
+/// Open an ALSA PCM without blocking and read its supported rates and formats
+#[cfg(target_os = "linux")]
+fn probe_alsa_pcm(name: &str, direction: alsa::Direction) -> DeviceInfo {
+    use alsa::pcm::{Format, HwParams, PCM};
+    
+    let mut info = DeviceInfo::new(name, Vec::new(), Vec::new());
+    let pcm = match PCM::new(name, direction, true) {
+        Ok(pcm) => pcm,
+        Err(_) => return info,
+    };
+    
+    if let Ok(hwp) = HwParams::any(&pcm) {
+        info.sample_rates = STANDARD_SAMPLE_RATES
+            .iter()
+            .copied()
+            .filter(|rate| hwp.test_rate(*rate).is_ok())
+            .collect();
+        for (format, label) in [(Format::S16LE, "i16"), (Format::S32LE, "i32"), (Format::FloatLE, "f32")] {
+            if hwp.test_format(format).is_ok() {
+                info.formats.push(label.to_string());
+            }
+        }
+    }
+    
+    info
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_devices_json_shape() {
+        let devices = DeviceList {
+            inputs: vec![DeviceInfo::new("hw:CARD=USB,DEV=0", vec![44100, 48000], vec!["i16".to_string()])],
+            outputs: vec![
+                DeviceInfo::new("hw:CARD=USB,DEV=0", vec![48000], vec!["i16".to_string(), "i32".to_string()]),
+                DeviceInfo {
+                    description: Some("Default ALSA Output".to_string()),
+                    ..DeviceInfo::new("default", Vec::new(), Vec::new())
+                },
+            ],
+        };
+        
+        let json = devices_json(&devices, Some("hw:CARD=USB,DEV=0"), None);
+        assert_eq!(json, serde_json::json!({
+            "inputs": [
+                {
+                    "name": "hw:CARD=USB,DEV=0",
+                    "description": null,
+                    "sample_rates": [44100, 48000],
+                    "formats": ["i16"]
+                }
+            ],
+            "outputs": [
+                {
+                    "name": "hw:CARD=USB,DEV=0",
+                    "description": null,
+                    "sample_rates": [48000],
+                    "formats": ["i16", "i32"]
+                },
+                {
+                    "name": "default",
+                    "description": "Default ALSA Output",
+                    "sample_rates": [],
+                    "formats": []
+                }
+            ],
+            "selected": {
+                "input_device": "hw:CARD=USB,DEV=0",
+                "output_device": null
+            }
+        }));
+    }
+    
+    #[test]
+    fn test_clamp_buffer_size() {
+        assert_eq!(clamp_buffer_size(512, 64, 4096), 512);
+        assert_eq!(clamp_buffer_size(32, 64, 4096), 64);
+        assert_eq!(clamp_buffer_size(16384, 64, 4096), 4096);
+        assert_eq!(clamp_buffer_size(usize::MAX, 64, 4096), 4096);
+        // A device reporting an inverted range gets its minimum
+        assert_eq!(clamp_buffer_size(512, 1024, 256), 1024);
+    }
+    
+    #[test]
+    fn test_negotiate_period_and_buffer() {
+        // Device allows everything asked for
+        assert_eq!(negotiate_period_and_buffer(256, 2, (32, 8192), (64, 16384)), (256, 512));
+        assert_eq!(negotiate_period_and_buffer(256, 4, (32, 8192), (64, 16384)), (256, 1024));
+        // Period below the device minimum, fewer than two periods requested
+        assert_eq!(negotiate_period_and_buffer(16, 1, (64, 8192), (128, 16384)), (64, 128));
+        // Buffer too small for the periods asked for
+        assert_eq!(negotiate_period_and_buffer(1024, 4, (32, 8192), (64, 2048)), (1024, 2048));
+        // Buffer too small for even two periods, so the period shrinks
+        assert_eq!(negotiate_period_and_buffer(4096, 2, (32, 8192), (64, 4096)), (2048, 4096));
+    }
+    
+    #[test]
+    fn test_channel_adaptation() {
+        // Stereo interface, mono-only interface, and an 8 channel interface that needs at least 4
+        assert_eq!(negotiate_channels(2, 1, 2), 2);
+        assert_eq!(negotiate_channels(2, 1, 1), 1);
+        assert_eq!(negotiate_channels(2, 4, 8), 4);
+        assert_eq!(negotiate_channels(6, 4, 8), 6);
+        assert_eq!(negotiate_channels(2, 0, 0), 1);
+        
+        assert_eq!(frame_to_stereo(&[0.5]), (0.5, 0.5));
+        assert_eq!(frame_to_stereo(&[0.1, 0.2]), (0.1, 0.2));
+        assert_eq!(frame_to_stereo(&[1, 2, 3, 4]), (1, 2));
+        assert_eq!(frame_to_stereo::<f32>(&[]), (0.0, 0.0));
+        
+        let mut mono = [0.0];
+        stereo_to_frame(0.2, 0.6, 0, &mut mono);
+        assert!((mono[0] - 0.4).abs() < 1e-6);
+        let mut quad = [1.0; 4];
+        stereo_to_frame(0.2, 0.6, 0, &mut quad);
+        assert_eq!(quad, [0.2, 0.6, 0.0, 0.0]);
+    }
+    
+    #[test]
+    fn test_interleave_stereo_at_an_output_offset() {
+        // Two stereo frames routed to outputs 3-4 (channels 2-3) of a 4 channel interface
+        let stereo = [0.1, 0.2, 0.3, 0.4];
+        let mut output = [1.0; 8];
+        interleave_stereo(&stereo, 4, 2, &mut output);
+        assert_eq!(output, [0.0, 0.0, 0.1, 0.2, 0.0, 0.0, 0.3, 0.4]);
+        
+        // Missing samples are silent rather than left over from the last block
+        let mut output = [1.0; 8];
+        interleave_stereo(&stereo[..2], 4, 0, &mut output);
+        assert_eq!(output, [0.1, 0.2, 0.0, 0.0, 0.0, 0.0, 0.0, 0.0]);
+        
+        assert!(check_output_channel_offset(2, 4).is_ok());
+        assert!(check_output_channel_offset(0, 1).is_ok());
+        assert!(check_output_channel_offset(3, 4).is_err());
+        assert!(check_output_channel_offset(2, 2).is_err());
+    }
+    
+    #[test]
+    fn test_channel_layouts_round_trip() {
+        // Three frames of a 4 channel buffer, sample value = 10 * channel + frame
+        let streams: Vec<Vec<i32>> = (0..4).map(|channel| (0..3).map(|frame| 10 * channel + frame).collect()).collect();
+        let interleaved = vec![0, 10, 20, 30, 1, 11, 21, 31, 2, 12, 22, 32];
+        let planar = vec![0, 1, 2, 10, 11, 12, 20, 21, 22, 30, 31, 32];
+        
+        assert_eq!(ChannelLayout::Interleaved.deinterleave(&interleaved, 4), streams);
+        assert_eq!(ChannelLayout::Planar.deinterleave(&planar, 4), streams);
+        assert_eq!(ChannelLayout::Interleaved.interleave(&streams), interleaved);
+        assert_eq!(ChannelLayout::Planar.interleave(&streams), planar);
+        
+        // Device buffers convert to and from the interleaved frames the effects process
+        let mut frames = Vec::new();
+        ChannelLayout::Planar.copy_to_interleaved(&planar, 4, &mut frames);
+        assert_eq!(frames, interleaved);
+        let mut device = vec![0; 12];
+        ChannelLayout::Planar.copy_from_interleaved(&interleaved, 4, &mut device);
+        assert_eq!(device, planar);
+        ChannelLayout::Interleaved.copy_from_interleaved(&interleaved, 4, &mut device);
+        assert_eq!(device, interleaved);
+        
+        // A trailing partial frame is dropped
+        assert_eq!(ChannelLayout::Planar.deinterleave(&[1, 2, 3], 2), vec![vec![1], vec![2]]);
+    }
+    
+    #[test]
+    fn test_sample_format_scaling() {
+        assert_eq!(SampleFormat::S32.scale(), i32::MAX as f32);
+        assert_eq!(SampleFormat::S24.scale(), 8_388_607.0);
+        assert_eq!(SampleFormat::S16.scale(), 32_767.0);
+        
+        // Full scale maps to each format's largest sample and back
+        assert_eq!(SampleFormat::S24.from_f32(1.0), 8_388_607);
+        assert_eq!(SampleFormat::S16.from_f32(-1.0), -32_767);
+        assert_eq!(SampleFormat::S16.from_f32(0.5), 16_383);
+        assert_eq!(SampleFormat::S24.to_f32(8_388_607), 1.0);
+        assert!((SampleFormat::S16.to_f32(16_384) - 0.5).abs() < 1e-4);
+        assert!((SampleFormat::S32.to_f32(SampleFormat::S32.from_f32(0.25)) - 0.25).abs() < 1e-6);
+        
+        // Overs are clipped instead of wrapping into the sign bit
+        assert_eq!(SampleFormat::S24.from_f32(1.5), 8_388_607);
+        assert_eq!(SampleFormat::S16.from_f32(-2.0), -32_767);
+    }
+    
+    #[test]
+    fn test_dither_decorrelates_quantization_error() {
+        // A sine of 2.5 LSB at 16 bits, like the end of a decaying tail
+        let format = SampleFormat::S16;
+        let signal: Vec<f32> = (0..48000)
+            .map(|i| 2.5 / format.scale() * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin())
+            .collect();
+        
+        // Correlation of the error (in LSB) with the signal, and the mean error on each half wave
+        let error_stats = |quantize: &mut dyn FnMut(f32) -> i32| {
+            let (mut cross, mut signal_power, mut error_power) = (0.0f64, 0.0f64, 0.0f64);
+            let (mut positive, mut negative) = ((0.0f64, 0), (0.0f64, 0));
+            for &sample in &signal {
+                let x = (sample * format.scale()) as f64;
+                let error = quantize(sample) as f64 - x;
+                cross += x * error;
+                signal_power += x * x;
+                error_power += error * error;
+                let half = if x > 0.0 { &mut positive } else { &mut negative };
+                half.0 += error;
+                half.1 += 1;
+            }
+            let correlation = cross / (signal_power * error_power).sqrt();
+            (correlation, positive.0 / positive.1 as f64, negative.0 / negative.1 as f64)
+        };
+        
+        // Truncation pulls every sample towards zero, so the error follows the signal
+        let (correlation, positive, negative) = error_stats(&mut |sample| format.from_f32(sample));
+        assert!(correlation < -0.3, "truncation correlation {}", correlation);
+        assert!(positive < -0.3 && negative > 0.3, "truncation bias {} / {}", positive, negative);
+        
+        // Dithered, the error is the same noise whatever the signal does
+        let mut dither = TpdfDither::new();
+        let (correlation, positive, negative) = error_stats(&mut |sample| dither.quantize(format, sample));
+        assert!(correlation.abs() < 0.02, "dithered correlation {}", correlation);
+        assert!(positive.abs() < 0.02 && negative.abs() < 0.02, "dithered bias {} / {}", positive, negative);
+        
+        assert_eq!(TpdfDither::new().quantize(format, 1.5), 32_767);
+        assert!(format.dither_by_default() && !SampleFormat::S32.dither_by_default());
+    }
+    
+    #[test]
+    fn test_sample_format_fallback() {
+        let only = |formats: &'static [SampleFormat]| move |format| formats.contains(&format);
+        
+        assert_eq!(negotiate_sample_format(SampleFormat::S32, only(&[SampleFormat::S32, SampleFormat::S16])), Some(SampleFormat::S32));
+        assert_eq!(negotiate_sample_format(SampleFormat::S16, only(&[SampleFormat::S32, SampleFormat::S16])), Some(SampleFormat::S16));
+        assert_eq!(negotiate_sample_format(SampleFormat::S32, only(&[SampleFormat::S24, SampleFormat::S16])), Some(SampleFormat::S24));
+        assert_eq!(negotiate_sample_format(SampleFormat::S24, only(&[SampleFormat::S16])), Some(SampleFormat::S16));
+        assert_eq!(negotiate_sample_format(SampleFormat::S32, only(&[])), None);
+    }
+    
+    #[test]
+    fn test_rates_in_ranges() {
+        assert_eq!(rates_in_ranges(&[(44100, 48000)]), vec![44100, 48000]);
+        assert_eq!(rates_in_ranges(&[(8000, 16000)]), Vec::<u32>::new());
+    }
+}
@@ -0,0 +1,270 @@
+use serde::{Deserialize, Serialize};
+
+use crate::config::AudioConfig;
+use crate::delay::StereoDelay;
+use crate::devices::DeviceList;
+
+/// Amplitude of the test impulse, low enough to stay clear of the limiter
+const IMPULSE_LEVEL: f32 = 0.5;
+
+/// Output level below which a sample counts as silence
+const SILENCE_THRESHOLD: f32 = 1e-6;
+
+/// Largest difference between a measured and configured tap time, in milliseconds
+const TAP_TOLERANCE_MS: f32 = 1.0;
+
+/// Result of one diagnostic check
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticCheck {
+    pub name: String,
+    pub passed: bool,
+    
+    /// What was found, or why the check failed
+    pub detail: String,
+}
+
+impl DiagnosticCheck {
+    fn new(name: &str, passed: bool, detail: String) -> Self {
+        Self {
+            name: name.to_string(),
+            passed,
+            detail,
+        }
+    }
+}
+
+/// First repeat of the impulse on one channel
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct TapMeasurement {
+    /// "left" or "right"
+    pub channel: String,
+    
+    /// Delay time from the configuration
+    pub expected_ms: f32,
+    
+    /// Time of the loudest output around the expected tap
+    pub measured_ms: f32,
+    
+    /// Level of the second repeat relative to the first, 0.0 with no feedback
+    pub decay: f32,
+}
+
+/// Everything the diagnostic found
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct DiagnosticReport {
+    /// True if every check passed
+    pub passed: bool,
+    pub checks: Vec<DiagnosticCheck>,
+    pub taps: Vec<TapMeasurement>,
+    
+    /// Time until the dry impulse reached the output, if it did
+    pub dry_latency_ms: Option<f32>,
+}
+
+impl DiagnosticReport {
+    /// Checks that did not pass
+    pub fn failures(&self) -> impl Iterator<Item = &DiagnosticCheck> {
+        self.checks.iter().filter(|check| !check.passed)
+    }
+}
+
+/// Check the devices and configuration and measure an impulse through the current settings
+///
+/// The impulse runs through a fresh delay built from `config`, so running the
+/// diagnostic does not disturb audio that is playing.
+pub fn diagnose(config: &AudioConfig, devices: &DeviceList) -> DiagnosticReport {
+    let mut checks = vec![
+        device_check("input_device", devices.inputs.iter().map(|d| d.name.as_str()), config.input_device.as_deref()),
+        device_check("output_device", devices.outputs.iter().map(|d| d.name.as_str()), config.output_device.as_deref()),
+        match config.validate() {
+            Ok(()) => DiagnosticCheck::new("config", true, "configuration is valid".to_string()),
+            Err(e) => DiagnosticCheck::new("config", false, e.to_string()),
+        },
+        match config.validate_stability() {
+            Ok(None) => DiagnosticCheck::new(
+                "stability",
+                true,
+                format!("estimated feedback loop gain {:.2}", config.estimated_loop_gain()),
+            ),
+            Ok(Some(warning)) => DiagnosticCheck::new("stability", false, warning),
+            Err(e) => DiagnosticCheck::new("stability", false, e.to_string()),
+        },
+    ];
+    
+    let sample_rate = config.sample_rate as f32;
+    let to_samples = |seconds: f32| (seconds.max(0.0) * sample_rate).round() as usize;
+    let to_ms = |samples: usize| samples as f32 * 1000.0 / sample_rate;
+    
+    // Long enough to hear the second repeat of the longer channel
+    let expected = [to_samples(config.stereo_delay.left_delay), to_samples(config.stereo_delay.right_delay)];
+    let dry_latency = StereoDelay::from_config(config).dry_latency_samples();
+    let length = dry_latency + 5 * expected[0].max(expected[1]) / 2 + 1;
+    let output = impulse_response(config, length);
+    
+    let finite = output.0.iter().chain(&output.1).all(|sample| sample.is_finite());
+    checks.push(DiagnosticCheck::new(
+        "output_finite",
+        finite,
+        if finite { "no NaN or infinite samples".to_string() } else { "output contains NaN or infinite samples".to_string() },
+    ));
+    if !finite {
+        // Every measurement below would be meaningless
+        return report(checks, Vec::new(), None);
+    }
+    
+    // The dry impulse should arrive exactly after the latency the delay reports
+    let (dry_gain, _) = config.stereo_delay.mix_law.gains(config.stereo_delay.wet_mix);
+    let first_sound = output.0.iter().position(|sample| sample.abs() > SILENCE_THRESHOLD);
+    let dry_latency_ms = if dry_gain > 0.0 { first_sound.map(to_ms) } else { None };
+    checks.push(if dry_gain <= 0.0 {
+        DiagnosticCheck::new("latency", true, "dry path is muted".to_string())
+    } else if first_sound == Some(dry_latency) {
+        DiagnosticCheck::new("latency", true, format!("dry signal after {:.2}ms", to_ms(dry_latency)))
+    } else {
+        DiagnosticCheck::new(
+            "latency",
+            false,
+            format!(
+                "dry signal expected after {:.2}ms, measured {}",
+                to_ms(dry_latency),
+                first_sound.map_or("silence".to_string(), |sample| format!("{:.2}ms", to_ms(sample)))
+            ),
+        )
+    });
+    
+    // Ping-pong, width and cross-feedback move repeats to the other channel, so
+    // the taps are timed with each channel's repeats kept on that channel
+    let mut isolated = config.clone();
+    isolated.stereo_delay.ping_pong = false;
+    isolated.stereo_delay.stereo_width = 0.0;
+    isolated.stereo_delay.cross_feedback = 0.0;
+    let isolated = impulse_response(&isolated, length);
+    
+    let mut taps = Vec::new();
+    for (channel, samples, expected) in [("left", &isolated.0, expected[0]), ("right", &isolated.1, expected[1])] {
+        // Search half a delay time either side of each repeat, past the dry impulse
+        let first = loudest(samples, (expected / 2).max(dry_latency + 1), 3 * expected / 2);
+        let second = loudest(samples, 3 * expected / 2 + 1, 5 * expected / 2);
+        let (Some((tap, tap_level)), Some((_, repeat_level))) = (first, second) else {
+            checks.push(DiagnosticCheck::new(&format!("{}_tap", channel), false, "delay time too short to measure".to_string()));
+            continue;
+        };
+        
+        let measurement = TapMeasurement {
+            channel: channel.to_string(),
+            expected_ms: to_ms(expected),
+            measured_ms: to_ms(tap),
+            decay: if tap_level > SILENCE_THRESHOLD { repeat_level / tap_level } else { 0.0 },
+        };
+        
+        let on_time = (measurement.measured_ms - measurement.expected_ms).abs() <= TAP_TOLERANCE_MS;
+        checks.push(DiagnosticCheck::new(
+            &format!("{}_tap", channel),
+            tap_level > SILENCE_THRESHOLD && on_time,
+            if tap_level <= SILENCE_THRESHOLD {
+                "no repeat heard".to_string()
+            } else {
+                format!("repeat after {:.2}ms, expected {:.2}ms", measurement.measured_ms, measurement.expected_ms)
+            },
+        ));
+        
+        // Repeats that grow are only expected when self-oscillation is allowed
+        let decaying = measurement.decay < 1.0 || config.stereo_delay.allow_self_oscillation;
+        checks.push(DiagnosticCheck::new(
+            &format!("{}_decay", channel),
+            decaying,
+            format!("second repeat at {:.2}x the first", measurement.decay),
+        ));
+        taps.push(measurement);
+    }
+    
+    report(checks, taps, dry_latency_ms)
+}
+
+/// Left and right output for an impulse into both channels of a fresh delay
+fn impulse_response(config: &AudioConfig, length: usize) -> (Vec<f32>, Vec<f32>) {
+    let mut delay = StereoDelay::from_config(config);
+    (0..length)
+        .map(|i| {
+            let input = if i == 0 { IMPULSE_LEVEL } else { 0.0 };
+            delay.process_sample(input, input)
+        })
+        .unzip()
+}
+
+fn report(checks: Vec<DiagnosticCheck>, taps: Vec<TapMeasurement>, dry_latency_ms: Option<f32>) -> DiagnosticReport {
+    DiagnosticReport {
+        passed: checks.iter().all(|check| check.passed),
+        checks,
+        taps,
+        dry_latency_ms,
+    }
+}
+
+/// Check that a device is available: the configured one, or any when none is configured
+fn device_check<'a>(name: &str, mut available: impl Iterator<Item = &'a str>, configured: Option<&str>) -> DiagnosticCheck {
+    match configured {
+        Some(device) if available.any(|name| name == device) => {
+            DiagnosticCheck::new(name, true, format!("'{}' is available", device))
+        }
+        Some(device) => DiagnosticCheck::new(name, false, format!("'{}' was not found", device)),
+        None => match available.next() {
+            Some(device) => DiagnosticCheck::new(name, true, format!("using default, '{}' is available", device)),
+            None => DiagnosticCheck::new(name, false, "no devices found".to_string()),
+        },
+    }
+}
+
+/// Index and level of the loudest sample in `start..=end`, if the range is not empty
+fn loudest(samples: &[f32], start: usize, end: usize) -> Option<(usize, f32)> {
+    let end = end.min(samples.len().saturating_sub(1));
+    if start > end {
+        return None;
+    }
+    samples[start..=end]
+        .iter()
+        .enumerate()
+        .map(|(i, sample)| (start + i, sample.abs()))
+        .fold(None, |loudest, (i, level)| match loudest {
+            Some((_, max)) if max >= level => loudest,
+            _ => Some((i, level)),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::devices::DeviceInfo;
+    
+    fn devices() -> DeviceList {
+        DeviceList {
+            inputs: vec![DeviceInfo::new("hw:1,0", vec![44100, 48000], vec!["i16".to_string()])],
+            outputs: vec![DeviceInfo::new("hw:1,0", vec![44100, 48000], vec!["i16".to_string()])],
+        }
+    }
+    
+    #[test]
+    fn test_known_good_config_passes_every_check() {
+        let config = AudioConfig::default();
+        let report = diagnose(&config, &devices());
+        
+        assert!(report.passed, "failed checks: {:?}", report.failures().collect::<Vec<_>>());
+        assert_eq!(report.dry_latency_ms, Some(0.0));
+        assert_eq!(report.taps.len(), 2);
+        for tap in &report.taps {
+            assert!((tap.measured_ms - tap.expected_ms).abs() <= TAP_TOLERANCE_MS, "{:?}", tap);
+            assert!(tap.decay > 0.0 && tap.decay < 1.0, "{:?}", tap);
+        }
+    }
+    
+    #[test]
+    fn test_problems_are_flagged() {
+        let mut config = AudioConfig { input_device: Some("hw:9,0".to_string()), ..Default::default() };
+        config.stereo_delay.feedback = 5.0;
+        let report = diagnose(&config, &DeviceList { inputs: devices().inputs, outputs: Vec::new() });
+        
+        assert!(!report.passed);
+        let failed: Vec<_> = report.failures().map(|check| check.name.as_str()).collect();
+        assert_eq!(failed, vec!["input_device", "output_device", "config"]);
+    }
+}
@@ -1,3 +1,20 @@
+use serde::{Deserialize, Serialize};
+use crate::chain::{unknown_parameter, AudioStage};
+use crate::dynamics::time_constant_coeff;
+use crate::filters::TiltEq;
+
+/// Averaging time of the level detectors used for auto gain compensation in milliseconds
+pub const AUTO_GAIN_WINDOW_MS: f32 = 100.0;
+
+/// Largest boost or cut auto gain compensation applies, in dB
+pub const MAX_AUTO_GAIN_DB: f32 = 24.0;
+
+/// Share of the tone applied as pre-emphasis before the nonlinearity
+///
+/// The rest of the tone shaping happens on the distorted signal, so a brighter tone
+/// both distorts more of the highs and lets more of the resulting harmonics through.
+const TONE_PRE_EMPHASIS: f32 = 0.5;
+
 
 
 /// Types of distortion available
@@ -12,23 +29,82 @@ pub enum DistortionType {
     None,
 }
 
-impl From<&str> for DistortionType {
-    fn from(s: &str) -> Self {
-        match s.to_lowercase().as_str() {
-            "soft_clip" => DistortionType::SoftClip,
-            "hard_clip" => DistortionType::HardClip,
-            "tube" => DistortionType::Tube,
-            "fuzz" => DistortionType::Fuzz,
-            "bit_crush" => DistortionType::BitCrush,
-            "waveshaper" => DistortionType::Waveshaper,
+impl DistortionType {
+    /// Map a numeric parameter value (0 = soft clip ... 5 = waveshaper, 6 = none) to a type
+    pub fn from_parameter(value: f32) -> Self {
+        match value.round() as i32 {
+            0 => DistortionType::SoftClip,
+            1 => DistortionType::HardClip,
+            2 => DistortionType::Tube,
+            3 => DistortionType::Fuzz,
+            4 => DistortionType::BitCrush,
+            5 => DistortionType::Waveshaper,
             _ => DistortionType::None,
         }
     }
+    
+    /// Get the numeric parameter value of this type (inverse of `from_parameter`)
+    pub fn to_parameter(&self) -> f32 {
+        match self {
+            DistortionType::SoftClip => 0.0,
+            DistortionType::HardClip => 1.0,
+            DistortionType::Tube => 2.0,
+            DistortionType::Fuzz => 3.0,
+            DistortionType::BitCrush => 4.0,
+            DistortionType::Waveshaper => 5.0,
+            DistortionType::None => 6.0,
+        }
+    }
 }
 
-impl ToString for DistortionType {
-    fn to_string(&self) -> String {
+impl DistortionType {
+    /// Every distortion type, in parameter order
+    pub const ALL: [DistortionType; 7] = [
+        DistortionType::SoftClip,
+        DistortionType::HardClip,
+        DistortionType::Tube,
+        DistortionType::Fuzz,
+        DistortionType::BitCrush,
+        DistortionType::Waveshaper,
+        DistortionType::None,
+    ];
+    
+    /// Look up a type by name (case-insensitive), `None` for unknown names
+    pub fn from_name(name: &str) -> Option<Self> {
+        let name = name.to_lowercase();
+        Self::ALL.into_iter().find(|distortion_type| distortion_type.to_string() == name)
+    }
+    
+    /// Gain of the fully wet shaper on quiet signals at a drive (0.0 to 1.0)
+    ///
+    /// Loud signals are clipped back down, but a repeat fading through the feedback
+    /// loop gets this boost on every trip, so it is the gain that decides whether
+    /// the loop decays.
+    pub fn small_signal_gain(&self, drive: f32) -> f32 {
+        let drive = drive.clamp(0.0, 1.0);
+        let pre_gain = 1.0 + drive * 5.0;
         match self {
+            DistortionType::SoftClip => pre_gain / (1.0 + drive * 10.0),
+            DistortionType::HardClip | DistortionType::BitCrush => pre_gain,
+            // Negative half-cycles are divided down less
+            DistortionType::Tube => 1.0 / 0.7,
+            DistortionType::Fuzz => pre_gain * (1.0 + drive * 20.0),
+            DistortionType::Waveshaper => pre_gain * (1.0 + drive * 3.0),
+            DistortionType::None => 1.0,
+        }
+    }
+}
+
+impl From<&str> for DistortionType {
+    /// Unknown names map to `DistortionType::None`, use `from_name` to reject them
+    fn from(s: &str) -> Self {
+        DistortionType::from_name(s).unwrap_or(DistortionType::None)
+    }
+}
+
+impl std::fmt::Display for DistortionType {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
             DistortionType::SoftClip => "soft_clip",
             DistortionType::HardClip => "hard_clip",
             DistortionType::Tube => "tube",
@@ -36,7 +112,53 @@ impl ToString for DistortionType {
             DistortionType::BitCrush => "bit_crush",
             DistortionType::Waveshaper => "waveshaper",
             DistortionType::None => "none",
-        }.to_string()
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Where in the signal chain the distortion is applied
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum DistortionPlacement {
+    /// Before the delay, like a drive pedal in front of a delay pedal (dry and repeats distorted)
+    Input,
+    /// In the cross-feedback path, so each repeat gets progressively dirtier
+    #[default]
+    Feedback,
+    /// On the wet output only, leaving the dry signal and the feedback path clean
+    WetOutput,
+}
+
+impl DistortionPlacement {
+    /// Map a numeric parameter value (0 = input, 1 = feedback, 2 = wet output) to a placement
+    pub fn from_parameter(value: f32) -> Self {
+        match value.round() as i32 {
+            0 => DistortionPlacement::Input,
+            2 => DistortionPlacement::WetOutput,
+            _ => DistortionPlacement::Feedback,
+        }
+    }
+}
+
+impl From<&str> for DistortionPlacement {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "input" => DistortionPlacement::Input,
+            "wet_output" => DistortionPlacement::WetOutput,
+            _ => DistortionPlacement::Feedback,
+        }
+    }
+}
+
+impl std::fmt::Display for DistortionPlacement {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            DistortionPlacement::Input => "input",
+            DistortionPlacement::Feedback => "feedback",
+            DistortionPlacement::WetOutput => "wet_output",
+        };
+        write!(f, "{}", name)
     }
 }
 
@@ -51,6 +173,17 @@ pub struct DistortionEffect {
     bit_depth: u8,
     sample_rate_reduction: f32,
     last_sample: f32,
+    
+    // Tone control: tilt before and after the nonlinearity
+    tone: f32,
+    pre_emphasis: TiltEq,
+    tone_filter: TiltEq,
+    
+    // Auto gain compensation: running mean square of the input and output
+    auto_gain_comp: bool,
+    auto_gain_coeff: f32,
+    input_power: f32,
+    output_power: f32,
 }
 
 impl DistortionEffect {
@@ -69,6 +202,13 @@ impl DistortionEffect {
             bit_depth: 8,
             sample_rate_reduction: 0.5,
             last_sample: 0.0,
+            tone: 0.0,
+            pre_emphasis: TiltEq::new(0.0, sample_rate),
+            tone_filter: TiltEq::new(0.0, sample_rate),
+            auto_gain_comp: false,
+            auto_gain_coeff: time_constant_coeff(AUTO_GAIN_WINDOW_MS, sample_rate),
+            input_power: 0.0,
+            output_power: 0.0,
         }
     }
     
@@ -87,6 +227,48 @@ impl DistortionEffect {
         self.mix = mix.clamp(0.0, 1.0);
     }
     
+    /// Set the tone (-1.0 darker to 1.0 brighter, 0.0 leaves the character unchanged)
+    pub fn set_tone(&mut self, tone: f32) {
+        self.tone = tone.clamp(-1.0, 1.0);
+        self.pre_emphasis.set_amount(self.tone * TONE_PRE_EMPHASIS);
+        self.tone_filter.set_amount(self.tone);
+    }
+    
+    /// Get the tone
+    pub fn get_tone(&self) -> f32 {
+        self.tone
+    }
+    
+    /// Enable or disable auto gain compensation
+    ///
+    /// When enabled the output is scaled by the ratio of the input and output RMS
+    /// levels, so changing the type or drive keeps the perceived level roughly constant.
+    pub fn set_auto_gain_comp(&mut self, enabled: bool) {
+        self.auto_gain_comp = enabled;
+        self.input_power = 0.0;
+        self.output_power = 0.0;
+    }
+    
+    /// Check whether auto gain compensation is enabled
+    pub fn is_auto_gain_comp(&self) -> bool {
+        self.auto_gain_comp
+    }
+    
+    /// Get the type of distortion
+    pub fn get_distortion_type(&self) -> DistortionType {
+        self.distortion_type
+    }
+    
+    /// Get the drive amount
+    pub fn get_drive(&self) -> f32 {
+        self.drive
+    }
+    
+    /// Get the wet/dry mix
+    pub fn get_mix(&self) -> f32 {
+        self.mix
+    }
+    
     /// Set bit crushing parameters
     pub fn set_bit_crush_parameters(&mut self, bit_depth: u8, sample_rate_reduction: f32) {
         self.bit_depth = bit_depth.clamp(1, 16);
@@ -157,8 +339,8 @@ impl DistortionEffect {
             return sample;
         }
         
-        // Apply drive
-        let driven_sample = sample * (1.0 + self.drive * 5.0);
+        // Apply drive to the pre-emphasized input
+        let driven_sample = self.pre_emphasis.process(sample) * (1.0 + self.drive * 5.0);
         
         // Apply distortion based on type
         let distorted = match self.distortion_type {
@@ -170,9 +352,32 @@ impl DistortionEffect {
             DistortionType::Waveshaper => self.waveshaper(driven_sample),
             DistortionType::None => driven_sample,
         };
+        let distorted = self.tone_filter.process(distorted);
         
         // Apply mix
-        sample * (1.0 - self.mix) + distorted * self.mix
+        let output = sample * (1.0 - self.mix) + distorted * self.mix;
+        
+        if self.auto_gain_comp {
+            self.compensate_gain(sample, output)
+        } else {
+            output
+        }
+    }
+    
+    /// Scale the output so its RMS level tracks the RMS level of the input
+    fn compensate_gain(&mut self, input: f32, output: f32) -> f32 {
+        let coeff = self.auto_gain_coeff;
+        self.input_power = input * input + coeff * (self.input_power - input * input);
+        self.output_power = output * output + coeff * (self.output_power - output * output);
+        
+        // Leave near-silence alone rather than boosting noise
+        if self.output_power < 1e-10 {
+            return output;
+        }
+        
+        let max_gain = 10.0_f32.powf(MAX_AUTO_GAIN_DB / 20.0);
+        let gain = (self.input_power / self.output_power).sqrt().clamp(1.0 / max_gain, max_gain);
+        output * gain
     }
     
     /// Process an entire buffer through the distortion effect
@@ -182,11 +387,20 @@ impl DistortionEffect {
         }
     }
     
+    /// Clear the held bit crusher sample, the tone filters and the level detectors
+    pub fn reset(&mut self) {
+        self.last_sample = 0.0;
+        self.pre_emphasis.reset();
+        self.tone_filter.reset();
+        self.input_power = 0.0;
+        self.output_power = 0.0;
+    }
+    
     /// Get a human-readable description of current settings
     pub fn get_info(&self) -> String {
         format!(
             "Distortion: {}, Drive: {:.0}%, Mix: {:.0}%",
-            self.distortion_type.to_string(),
+            self.distortion_type,
             self.drive * 100.0,
             self.mix * 100.0
         )
@@ -196,7 +410,9 @@ impl DistortionEffect {
 /// Specialized distortion for cross-feedback signals in stereo delay
 pub struct CrossFeedbackDistortion {
     enabled: bool,
-    distortion: DistortionEffect,
+    // One distortion per channel, so filter and bit crusher state stay apart
+    left: DistortionEffect,
+    right: DistortionEffect,
     feedback_intensity: f32,
     _frequency_dependent: bool,
 }
@@ -212,7 +428,8 @@ impl CrossFeedbackDistortion {
     ) -> Self {
         Self {
             enabled,
-            distortion: DistortionEffect::new(distortion_type, drive, mix, sample_rate),
+            left: DistortionEffect::new(distortion_type, drive, mix, sample_rate),
+            right: DistortionEffect::new(distortion_type, drive, mix, sample_rate),
             feedback_intensity: 0.5,
             _frequency_dependent: true,
         }
@@ -225,17 +442,31 @@ impl CrossFeedbackDistortion {
     
     /// Set the type of distortion
     pub fn set_distortion_type(&mut self, distortion_type: DistortionType) {
-        self.distortion.set_distortion_type(distortion_type);
+        self.left.set_distortion_type(distortion_type);
+        self.right.set_distortion_type(distortion_type);
     }
     
     /// Set the drive amount
     pub fn set_drive(&mut self, drive: f32) {
-        self.distortion.set_drive(drive);
+        self.left.set_drive(drive);
+        self.right.set_drive(drive);
     }
     
     /// Set the wet/dry mix
     pub fn set_mix(&mut self, mix: f32) {
-        self.distortion.set_mix(mix);
+        self.left.set_mix(mix);
+        self.right.set_mix(mix);
+    }
+    
+    /// Set the tone (-1.0 darker to 1.0 brighter)
+    pub fn set_tone(&mut self, tone: f32) {
+        self.left.set_tone(tone);
+        self.right.set_tone(tone);
+    }
+    
+    /// Get the tone
+    pub fn get_tone(&self) -> f32 {
+        self.left.get_tone()
     }
     
     /// Set how much the distortion affects feedback (0.0 to 1.0)
@@ -243,6 +474,42 @@ impl CrossFeedbackDistortion {
         self.feedback_intensity = intensity.clamp(0.0, 1.0);
     }
     
+    /// Check whether cross-feedback distortion is enabled
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+    
+    /// Enable or disable auto gain compensation
+    pub fn set_auto_gain_comp(&mut self, enabled: bool) {
+        self.left.set_auto_gain_comp(enabled);
+        self.right.set_auto_gain_comp(enabled);
+    }
+    
+    /// Check whether auto gain compensation is enabled
+    pub fn is_auto_gain_comp(&self) -> bool {
+        self.left.is_auto_gain_comp()
+    }
+    
+    /// Get the type of distortion
+    pub fn get_distortion_type(&self) -> DistortionType {
+        self.left.get_distortion_type()
+    }
+    
+    /// Get the drive amount
+    pub fn get_drive(&self) -> f32 {
+        self.left.get_drive()
+    }
+    
+    /// Get the wet/dry mix
+    pub fn get_mix(&self) -> f32 {
+        self.left.get_mix()
+    }
+    
+    /// Get how much the distortion affects feedback
+    pub fn get_feedback_intensity(&self) -> f32 {
+        self.feedback_intensity
+    }
+    
     /// Process cross-feedback signals with distortion
     pub fn process_cross_feedback(&mut self, left_sample: f32, right_sample: f32) -> (f32, f32) {
         if !self.enabled {
@@ -250,8 +517,8 @@ impl CrossFeedbackDistortion {
         }
         
         // Apply distortion to cross-feedback signals
-        let distorted_left = self.distortion.process_sample(left_sample);
-        let distorted_right = self.distortion.process_sample(right_sample);
+        let distorted_left = self.left.process_sample(left_sample);
+        let distorted_right = self.right.process_sample(right_sample);
         
         // Blend with original based on feedback intensity
         let left_output = left_sample * (1.0 - self.feedback_intensity) 
@@ -262,14 +529,106 @@ impl CrossFeedbackDistortion {
         (left_output, right_output)
     }
     
+    /// Process the repeats of a mono delay line, on the left channel's distortion
+    pub fn process_mono_feedback(&mut self, sample: f32) -> f32 {
+        if !self.enabled {
+            return sample;
+        }
+        
+        let distorted = self.left.process_sample(sample);
+        sample * (1.0 - self.feedback_intensity) + distorted * self.feedback_intensity
+    }
+    
+    /// Process a mono sample with the full distortion, on the left channel's distortion
+    pub fn process_mono(&mut self, sample: f32) -> f32 {
+        if !self.enabled {
+            return sample;
+        }
+        
+        self.left.process_sample(sample)
+    }
+    
+    /// Process a stereo pair with the full distortion, ignoring the feedback intensity
+    ///
+    /// Used when the distortion is placed on the input or the wet output rather
+    /// than in the feedback path.
+    pub fn process_stereo(&mut self, left_sample: f32, right_sample: f32) -> (f32, f32) {
+        if !self.enabled {
+            return (left_sample, right_sample);
+        }
+        
+        (self.left.process_sample(left_sample), self.right.process_sample(right_sample))
+    }
+    
     /// Get a human-readable description of current settings
     pub fn get_info(&self) -> String {
         if !self.enabled {
             "Cross-feedback Distortion: Disabled".to_string()
         } else {
-            format!("Cross-feedback Distortion: {}", self.distortion.get_info())
+            format!("Cross-feedback Distortion: {}", self.left.get_info())
         }
     }
+    
+    /// Clear the distortion state
+    pub fn reset(&mut self) {
+        self.left.reset();
+        self.right.reset();
+    }
+}
+
+/// Both channels run through the one distortion; `CrossFeedbackDistortion` keeps
+/// the tone filter and bit crusher state of each channel apart
+impl AudioStage for DistortionEffect {
+    fn name(&self) -> &'static str {
+        "distortion"
+    }
+    
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        (self.process_sample(left), self.process_sample(right))
+    }
+    
+    fn reset(&mut self) {
+        DistortionEffect::reset(self);
+    }
+    
+    fn set_param(&mut self, param: &str, value: f32) -> Result<(), crate::AudioProcessorError> {
+        match param {
+            "type" => self.set_distortion_type(DistortionType::from_parameter(value)),
+            "drive" => self.set_drive(value),
+            "mix" => self.set_mix(value),
+            "tone" => self.set_tone(value),
+            "auto_gain_comp" => self.set_auto_gain_comp(value > 0.5),
+            _ => return Err(unknown_parameter(param, value)),
+        }
+        Ok(())
+    }
+}
+
+/// Runs the full distortion of `process_stereo`, for use outside the feedback loop
+impl AudioStage for CrossFeedbackDistortion {
+    fn name(&self) -> &'static str {
+        "distortion"
+    }
+    
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        self.process_stereo(left, right)
+    }
+    
+    fn reset(&mut self) {
+        CrossFeedbackDistortion::reset(self);
+    }
+    
+    fn set_param(&mut self, param: &str, value: f32) -> Result<(), crate::AudioProcessorError> {
+        match param {
+            "enabled" => self.set_enabled(value > 0.5),
+            "feedback_intensity" => self.set_feedback_intensity(value),
+            _ => {
+                self.left.set_param(param, value)?;
+                self.right.set_param(param, value)?;
+            },
+        }
+        Ok(())
+    }
 }
 
 // Simple random number generator for bit crushing
@@ -278,7 +637,7 @@ mod fastrand {
     use std::cell::RefCell;
     
     static INIT: Once = Once::new();
-    thread_local!(static RNG: RefCell<u64> = RefCell::new(0));
+    thread_local!(static RNG: RefCell<u64> = const { RefCell::new(0) });
     
     fn init_rng() {
         INIT.call_once(|| {
@@ -302,3 +661,92 @@ mod fastrand {
         })
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn rms_of_tone(distortion: &mut DistortionEffect) -> f32 {
+        let sample_rate = 44100;
+        let mut sum = 0.0;
+        let mut count = 0;
+        for i in 0..sample_rate {
+            let phase = 2.0 * std::f32::consts::PI * 440.0 * i as f32 / sample_rate as f32;
+            let output = distortion.process_sample(0.3 * phase.sin());
+            // Skip the first half second while the level detectors settle
+            if i >= sample_rate / 2 {
+                sum += output * output;
+                count += 1;
+            }
+        }
+        (sum / count as f32).sqrt()
+    }
+    
+    #[test]
+    fn test_process_buffer_accepts_an_empty_buffer() {
+        let mut distortion = DistortionEffect::new(DistortionType::Tube, 0.7, 0.8, 44100);
+        let mut buffer: [f32; 0] = [];
+        distortion.process_buffer(&mut buffer);
+        assert_eq!(distortion.process_sample(0.5), DistortionEffect::new(DistortionType::Tube, 0.7, 0.8, 44100).process_sample(0.5));
+    }
+    
+    #[test]
+    fn test_from_name_rejects_unknown_types() {
+        for distortion_type in DistortionType::ALL {
+            assert_eq!(DistortionType::from_name(&distortion_type.to_string()), Some(distortion_type));
+        }
+        assert_eq!(DistortionType::from_name("Tube"), Some(DistortionType::Tube));
+        assert_eq!(DistortionType::from_name("chorus"), None);
+        assert_eq!(DistortionType::from("chorus"), DistortionType::None);
+    }
+    
+    #[test]
+    fn test_auto_gain_comp_keeps_level_constant() {
+        let dry_rms = rms_of_tone(&mut DistortionEffect::new(DistortionType::None, 0.0, 1.0, 44100));
+        
+        // Heavy soft clipping drops the level a lot without compensation
+        let mut uncompensated = DistortionEffect::new(DistortionType::SoftClip, 0.8, 1.0, 44100);
+        let uncompensated_db = 20.0 * (rms_of_tone(&mut uncompensated) / dry_rms).log10();
+        assert!(uncompensated_db < -6.0, "soft clip only {:.2}dB down", uncompensated_db);
+        
+        for distortion_type in [DistortionType::SoftClip, DistortionType::Tube, DistortionType::Fuzz] {
+            let mut compensated = DistortionEffect::new(distortion_type, 0.8, 1.0, 44100);
+            compensated.set_auto_gain_comp(true);
+            let level_db = 20.0 * (rms_of_tone(&mut compensated) / dry_rms).log10();
+            assert!(level_db.abs() < 1.0, "{:?} is {:.2}dB off with compensation", distortion_type, level_db);
+        }
+    }
+    
+    /// Ratio of the RMS of the first difference to the RMS of a distorted 220Hz tone,
+    /// which grows with the share of high frequencies in the output
+    fn brightness(tone: f32) -> f32 {
+        let mut distortion = DistortionEffect::new(DistortionType::Fuzz, 0.8, 1.0, 44100);
+        distortion.set_tone(tone);
+        let (mut power, mut difference_power, mut previous) = (0.0, 0.0, 0.0);
+        for i in 0..44100 {
+            let phase = 2.0 * std::f32::consts::PI * 220.0 * i as f32 / 44100.0;
+            let output = distortion.process_sample(0.5 * phase.sin());
+            power += output * output;
+            difference_power += (output - previous) * (output - previous);
+            previous = output;
+        }
+        (difference_power / power).sqrt()
+    }
+    
+    #[test]
+    fn test_tone_shapes_the_distorted_highs() {
+        let (dark, neutral, bright) = (brightness(-1.0), brightness(0.0), brightness(1.0));
+        assert!(bright > neutral * 1.2, "bright {} vs neutral {}", bright, neutral);
+        assert!(dark < neutral / 1.2, "dark {} vs neutral {}", dark, neutral);
+        
+        // At 0 the tone control leaves the signal exactly as it was
+        let mut plain = DistortionEffect::new(DistortionType::Tube, 0.6, 0.8, 44100);
+        let mut toned = DistortionEffect::new(DistortionType::Tube, 0.6, 0.8, 44100);
+        toned.set_tone(0.7);
+        toned.set_tone(0.0);
+        for i in 0..1000 {
+            let input = (i as f32 * 0.05).sin();
+            assert_eq!(plain.process_sample(input), toned.process_sample(input));
+        }
+    }
+}
@@ -0,0 +1,133 @@
+use crate::delay::read_interpolated;
+
+/// Highest detune of the double-tracked copies in cents
+pub const MAX_DOUBLE_TRACK_DETUNE_CENTS: f32 = 25.0;
+
+/// Default detune of the double-tracked copies in cents
+pub const DEFAULT_DOUBLE_TRACK_DETUNE_CENTS: f32 = 8.0;
+
+/// Default pan spread of the copies, hard left and right
+pub const DEFAULT_DOUBLE_TRACK_SPREAD: f32 = 1.0;
+
+/// Level of each copy against the dry signal (-6dB)
+const DOUBLE_TRACK_LEVEL: f32 = 0.5;
+
+/// Longest delay a copy can reach in seconds, with the modulation at its deepest
+const MAX_VOICE_DELAY_S: f32 = 0.045;
+
+/// A short, slowly modulated copy of the input
+///
+/// The copies use different delay times and modulation rates, so they never
+/// line up with each other and read as two separate takes.
+struct Voice {
+    /// Centre delay time in seconds
+    delay_s: f32,
+    /// Modulation rate in Hz
+    rate_hz: f32,
+    phase: f32,
+}
+
+impl Voice {
+    /// Delay in samples for the current phase, `depth_s` either side of the centre
+    fn delay_samples(&self, depth_s: f32, sample_rate: f32) -> f32 {
+        (self.delay_s + depth_s * (2.0 * std::f32::consts::PI * self.phase).sin()) * sample_rate
+    }
+    
+    fn advance(&mut self, sample_rate: f32) {
+        self.phase += self.rate_hz / sample_rate;
+        if self.phase >= 1.0 {
+            self.phase -= 1.0;
+        }
+    }
+    
+    /// Modulation depth in seconds giving a peak pitch shift of `cents`
+    ///
+    /// A delay swinging by `depth * sin(2π f t)` shifts the pitch by at most
+    /// `2π f depth` as a frequency ratio, so the depth follows from the rate.
+    fn depth_for_cents(&self, cents: f32) -> f32 {
+        (2f32.powf(cents / 1200.0) - 1.0) / (2.0 * std::f32::consts::PI * self.rate_hz)
+    }
+}
+
+/// Double-tracking: two short, detuned copies of the input panned left and right
+///
+/// Thickens a single guitar into a doubled part. The mono sum of the input is
+/// written into one short line, read by two taps around 25ms and 33ms whose
+/// delay times drift slowly, so each copy is slightly detuned. The copies are
+/// mixed onto the input, the first towards the left and the second towards the
+/// right as far as the spread allows.
+pub struct DoubleTracker {
+    buffer: Vec<f32>,
+    write_index: usize,
+    voices: [Voice; 2],
+    sample_rate: f32,
+    detune_cents: f32,
+    spread: f32,
+}
+
+impl DoubleTracker {
+    /// Create a double tracker with the default detune and spread
+    pub fn new(sample_rate: u32) -> Self {
+        let len = (MAX_VOICE_DELAY_S * sample_rate as f32).ceil() as usize + 2;
+        Self {
+            buffer: vec![0.0; len],
+            write_index: 0,
+            voices: [
+                Voice { delay_s: 0.025, rate_hz: 0.6, phase: 0.0 },
+                Voice { delay_s: 0.033, rate_hz: 0.8, phase: 0.5 },
+            ],
+            sample_rate: sample_rate as f32,
+            detune_cents: DEFAULT_DOUBLE_TRACK_DETUNE_CENTS,
+            spread: DEFAULT_DOUBLE_TRACK_SPREAD,
+        }
+    }
+    
+    /// Set the peak detune of the copies in cents (0 to 25)
+    pub fn set_detune(&mut self, cents: f32) {
+        self.detune_cents = cents.clamp(0.0, MAX_DOUBLE_TRACK_DETUNE_CENTS);
+    }
+    
+    /// Get the peak detune of the copies in cents
+    pub fn get_detune(&self) -> f32 {
+        self.detune_cents
+    }
+    
+    /// Set how far the copies are panned apart (0.0 centred to 1.0 hard left and right)
+    pub fn set_spread(&mut self, spread: f32) {
+        self.spread = spread.clamp(0.0, 1.0);
+    }
+    
+    /// Get how far the copies are panned apart
+    pub fn get_spread(&self) -> f32 {
+        self.spread
+    }
+    
+    /// Add the two copies to a stereo sample
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        self.buffer[self.write_index] = (left + right) * 0.5;
+        self.write_index = (self.write_index + 1) % self.buffer.len();
+        
+        let mut copies = [0.0; 2];
+        for (copy, voice) in copies.iter_mut().zip(self.voices.iter_mut()) {
+            let depth = voice.depth_for_cents(self.detune_cents);
+            *copy = DOUBLE_TRACK_LEVEL * read_interpolated(&self.buffer, self.write_index, voice.delay_samples(depth, self.sample_rate));
+            voice.advance(self.sample_rate);
+        }
+        
+        // Linear pan: the first copy leans left and the second right
+        let near = (1.0 + self.spread) * 0.5;
+        let far = 1.0 - near;
+        (
+            left + near * copies[0] + far * copies[1],
+            right + far * copies[0] + near * copies[1],
+        )
+    }
+    
+    /// Clear the line and restart the modulation
+    pub fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_index = 0;
+        self.voices[0].phase = 0.0;
+        self.voices[1].phase = 0.5;
+    }
+}
@@ -0,0 +1,612 @@
+use crate::chain::{unknown_parameter, AudioStage};
+
+/// Lowest compressor threshold in dBFS
+pub const MIN_COMP_THRESHOLD_DB: f32 = -60.0;
+
+/// Highest compressor ratio
+pub const MAX_COMP_RATIO: f32 = 20.0;
+
+/// Shortest and longest compressor attack times in milliseconds
+pub const MIN_COMP_ATTACK_MS: f32 = 0.1;
+pub const MAX_COMP_ATTACK_MS: f32 = 200.0;
+
+/// Shortest and longest compressor release times in milliseconds
+pub const MIN_COMP_RELEASE_MS: f32 = 10.0;
+pub const MAX_COMP_RELEASE_MS: f32 = 2000.0;
+
+/// Highest compressor makeup gain in dB
+pub const MAX_COMP_MAKEUP_DB: f32 = 24.0;
+
+/// Shortest and longest auto-swell times in milliseconds
+pub const MIN_SWELL_TIME_MS: f32 = 10.0;
+pub const MAX_SWELL_TIME_MS: f32 = 5000.0;
+
+/// Lowest and highest auto-freeze thresholds in dBFS
+pub const MIN_AUTO_FREEZE_THRESHOLD_DB: f32 = -80.0;
+pub const MAX_AUTO_FREEZE_THRESHOLD_DB: f32 = -20.0;
+
+/// Default auto-freeze threshold in dBFS
+pub const DEFAULT_AUTO_FREEZE_THRESHOLD_DB: f32 = -50.0;
+
+/// Time the input has to stay below the auto-freeze threshold before freeze engages
+pub const AUTO_FREEZE_HOLD_MS: f32 = 300.0;
+
+/// Release time of the auto-freeze input envelope in milliseconds
+const AUTO_FREEZE_RELEASE_MS: f32 = 20.0;
+
+/// Level below which the onset detector ignores onsets (-60dBFS)
+const ONSET_GATE: f32 = 0.001;
+
+/// Envelope follower times of the onset detector in milliseconds
+const ONSET_FAST_RELEASE_MS: f32 = 20.0;
+const ONSET_SLOW_ATTACK_MS: f32 = 50.0;
+const ONSET_SLOW_RELEASE_MS: f32 = 200.0;
+
+/// Drive of the feedback saturator at full amount
+pub const MAX_SATURATION_DRIVE: f32 = 4.0;
+
+/// Stereo-linked peak limiter
+///
+/// The attack is instantaneous (the envelope jumps to any new peak), so the output
+/// never exceeds the threshold. The gain recovers exponentially with the release time.
+pub struct Limiter {
+    threshold: f32,
+    release_coeff: f32,
+    envelope: f32,
+}
+
+impl Limiter {
+    /// Create a new limiter
+    ///
+    /// # Arguments
+    /// * `threshold` - Maximum absolute output level (linear)
+    /// * `release_ms` - Time for the gain to recover after a peak
+    /// * `sample_rate` - Sample rate in Hz
+    pub fn new(threshold: f32, release_ms: f32, sample_rate: u32) -> Self {
+        Self {
+            threshold: threshold.max(0.001),
+            release_coeff: time_constant_coeff(release_ms, sample_rate),
+            envelope: 0.0,
+        }
+    }
+    
+    /// Set the maximum absolute output level (linear)
+    pub fn set_threshold(&mut self, threshold: f32) {
+        self.threshold = threshold.max(0.001);
+    }
+    
+    /// Get the maximum absolute output level (linear)
+    pub fn get_threshold(&self) -> f32 {
+        self.threshold
+    }
+    
+    /// Process a stereo sample pair, applying the same gain to both channels
+    pub fn process_stereo(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let peak = left.abs().max(right.abs());
+        if peak > self.envelope {
+            self.envelope = peak;
+        } else {
+            self.envelope = peak + self.release_coeff * (self.envelope - peak);
+        }
+        
+        if self.envelope > self.threshold {
+            let gain = self.threshold / self.envelope;
+            (left * gain, right * gain)
+        } else {
+            (left, right)
+        }
+    }
+    
+    /// Clear the envelope state
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+    }
+}
+
+impl AudioStage for Limiter {
+    fn name(&self) -> &'static str {
+        "limiter"
+    }
+    
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        self.process_stereo(left, right)
+    }
+    
+    fn reset(&mut self) {
+        Limiter::reset(self);
+    }
+    
+    fn set_param(&mut self, param: &str, value: f32) -> Result<(), crate::AudioProcessorError> {
+        match param {
+            "threshold" => self.set_threshold(value),
+            _ => return Err(unknown_parameter(param, value)),
+        }
+        Ok(())
+    }
+}
+
+/// Stereo-linked feed-forward compressor
+///
+/// The peak level of both channels drives a hard-knee gain computer in dB. The
+/// resulting gain reduction is smoothed with separate attack and release times,
+/// so gain changes glide instead of stepping with every peak.
+pub struct Compressor {
+    threshold_db: f32,
+    ratio: f32,
+    attack_ms: f32,
+    release_ms: f32,
+    makeup_db: f32,
+    sample_rate: u32,
+    attack_coeff: f32,
+    release_coeff: f32,
+    gain_reduction_db: f32,
+}
+
+impl Compressor {
+    /// Create a new compressor
+    ///
+    /// # Arguments
+    /// * `threshold_db` - Level above which the signal is compressed
+    /// * `ratio` - Input level change per 1dB of output level change above the threshold
+    /// * `attack_ms` - Time for the gain reduction to follow a rising level
+    /// * `release_ms` - Time for the gain reduction to recover
+    /// * `makeup_db` - Gain applied after compression
+    /// * `sample_rate` - Sample rate in Hz
+    pub fn new(threshold_db: f32, ratio: f32, attack_ms: f32, release_ms: f32, makeup_db: f32, sample_rate: u32) -> Self {
+        let mut compressor = Self {
+            threshold_db: 0.0,
+            ratio: 1.0,
+            attack_ms: 0.0,
+            release_ms: 0.0,
+            makeup_db: 0.0,
+            sample_rate,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            gain_reduction_db: 0.0,
+        };
+        compressor.set_threshold_db(threshold_db);
+        compressor.set_ratio(ratio);
+        compressor.set_attack_ms(attack_ms);
+        compressor.set_release_ms(release_ms);
+        compressor.set_makeup_db(makeup_db);
+        compressor
+    }
+    
+    /// Set the threshold in dBFS
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        self.threshold_db = threshold_db.clamp(MIN_COMP_THRESHOLD_DB, 0.0);
+    }
+    
+    /// Get the threshold in dBFS
+    pub fn get_threshold_db(&self) -> f32 {
+        self.threshold_db
+    }
+    
+    /// Set the compression ratio (1.0 = no compression)
+    pub fn set_ratio(&mut self, ratio: f32) {
+        self.ratio = ratio.clamp(1.0, MAX_COMP_RATIO);
+    }
+    
+    /// Get the compression ratio
+    pub fn get_ratio(&self) -> f32 {
+        self.ratio
+    }
+    
+    /// Set the attack time in milliseconds
+    pub fn set_attack_ms(&mut self, attack_ms: f32) {
+        self.attack_ms = attack_ms.clamp(MIN_COMP_ATTACK_MS, MAX_COMP_ATTACK_MS);
+        self.attack_coeff = time_constant_coeff(self.attack_ms, self.sample_rate);
+    }
+    
+    /// Get the attack time in milliseconds
+    pub fn get_attack_ms(&self) -> f32 {
+        self.attack_ms
+    }
+    
+    /// Set the release time in milliseconds
+    pub fn set_release_ms(&mut self, release_ms: f32) {
+        self.release_ms = release_ms.clamp(MIN_COMP_RELEASE_MS, MAX_COMP_RELEASE_MS);
+        self.release_coeff = time_constant_coeff(self.release_ms, self.sample_rate);
+    }
+    
+    /// Get the release time in milliseconds
+    pub fn get_release_ms(&self) -> f32 {
+        self.release_ms
+    }
+    
+    /// Set the makeup gain in dB
+    pub fn set_makeup_db(&mut self, makeup_db: f32) {
+        self.makeup_db = makeup_db.clamp(0.0, MAX_COMP_MAKEUP_DB);
+    }
+    
+    /// Get the makeup gain in dB
+    pub fn get_makeup_db(&self) -> f32 {
+        self.makeup_db
+    }
+    
+    /// Get the current gain reduction in dB (positive values mean the signal is turned down)
+    pub fn get_gain_reduction_db(&self) -> f32 {
+        self.gain_reduction_db
+    }
+    
+    /// Gain reduction in dB the gain computer asks for at a given input level
+    fn target_gain_reduction_db(&self, level_db: f32) -> f32 {
+        let overshoot_db = level_db - self.threshold_db;
+        if overshoot_db > 0.0 {
+            overshoot_db * (1.0 - 1.0 / self.ratio)
+        } else {
+            0.0
+        }
+    }
+    
+    /// Process a stereo sample pair, applying the same gain to both channels
+    pub fn process_stereo(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let peak = left.abs().max(right.abs()).max(1e-9);
+        let target_db = self.target_gain_reduction_db(20.0 * peak.log10());
+        
+        let coeff = if target_db > self.gain_reduction_db {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.gain_reduction_db = target_db + coeff * (self.gain_reduction_db - target_db);
+        
+        let gain = 10.0_f32.powf((self.makeup_db - self.gain_reduction_db) / 20.0);
+        (left * gain, right * gain)
+    }
+    
+    /// Clear the gain reduction state
+    pub fn reset(&mut self) {
+        self.gain_reduction_db = 0.0;
+    }
+}
+
+impl AudioStage for Compressor {
+    fn name(&self) -> &'static str {
+        "comp"
+    }
+    
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        self.process_stereo(left, right)
+    }
+    
+    fn reset(&mut self) {
+        Compressor::reset(self);
+    }
+    
+    fn set_param(&mut self, param: &str, value: f32) -> Result<(), crate::AudioProcessorError> {
+        match param {
+            "threshold" => self.set_threshold_db(value),
+            "ratio" => self.set_ratio(value),
+            "attack" => self.set_attack_ms(value),
+            "release" => self.set_release_ms(value),
+            "makeup" => self.set_makeup_db(value),
+            _ => return Err(unknown_parameter(param, value)),
+        }
+        Ok(())
+    }
+}
+
+/// Note onset detector
+///
+/// Compares a fast and a slow envelope of the input: an onset is reported when the
+/// fast one jumps above the slow one by the onset ratio. The detector re-arms once
+/// the level has settled, so every new note is reported once.
+pub struct OnsetDetector {
+    sensitivity: f32,
+    fast_release_coeff: f32,
+    slow_attack_coeff: f32,
+    slow_release_coeff: f32,
+    fast_envelope: f32,
+    slow_envelope: f32,
+    armed: bool,
+}
+
+impl OnsetDetector {
+    /// Create a new onset detector
+    ///
+    /// # Arguments
+    /// * `sensitivity` - 0.0 only reacts to hard picking, 1.0 to soft notes as well
+    /// * `sample_rate` - Sample rate in Hz
+    pub fn new(sensitivity: f32, sample_rate: u32) -> Self {
+        let mut detector = Self {
+            sensitivity: 0.0,
+            fast_release_coeff: time_constant_coeff(ONSET_FAST_RELEASE_MS, sample_rate),
+            slow_attack_coeff: time_constant_coeff(ONSET_SLOW_ATTACK_MS, sample_rate),
+            slow_release_coeff: time_constant_coeff(ONSET_SLOW_RELEASE_MS, sample_rate),
+            fast_envelope: 0.0,
+            slow_envelope: 0.0,
+            armed: true,
+        };
+        detector.set_sensitivity(sensitivity);
+        detector
+    }
+    
+    /// Set the onset sensitivity (0.0 to 1.0)
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.sensitivity = sensitivity.clamp(0.0, 1.0);
+    }
+    
+    /// Get the onset sensitivity
+    pub fn get_sensitivity(&self) -> f32 {
+        self.sensitivity
+    }
+    
+    /// Jump of the fast envelope over the slow one that counts as an onset
+    ///
+    /// 8x (18dB) at sensitivity 0.0 down to 1.5x (3.5dB) at 1.0.
+    fn onset_ratio(&self) -> f32 {
+        1.5 + 6.5 * (1.0 - self.sensitivity)
+    }
+    
+    /// Follow the input and return true on the sample a note onset is detected
+    pub fn process(&mut self, input: f32) -> bool {
+        let level = input.abs();
+        
+        self.fast_envelope = if level > self.fast_envelope {
+            level
+        } else {
+            level + self.fast_release_coeff * (self.fast_envelope - level)
+        };
+        let slow_coeff = if level > self.slow_envelope {
+            self.slow_attack_coeff
+        } else {
+            self.slow_release_coeff
+        };
+        self.slow_envelope = level + slow_coeff * (self.slow_envelope - level);
+        
+        let ratio = self.onset_ratio();
+        if self.armed && self.fast_envelope > ONSET_GATE && self.fast_envelope > self.slow_envelope * ratio {
+            self.armed = false;
+            return true;
+        }
+        if !self.armed && self.fast_envelope < self.slow_envelope * (1.0 + ratio) / 2.0 {
+            // Halfway back to the slow envelope the note has settled
+            self.armed = true;
+        }
+        false
+    }
+    
+    /// Clear both envelopes and re-arm
+    pub fn reset(&mut self) {
+        self.fast_envelope = 0.0;
+        self.slow_envelope = 0.0;
+        self.armed = true;
+    }
+}
+
+/// Volume-pedal style auto-swell
+///
+/// On every note onset found by an `OnsetDetector` the gain drops to silence and
+/// rises linearly back to unity over the swell time, hiding the pick attack.
+pub struct Swell {
+    time_ms: f32,
+    sample_rate: u32,
+    detector: OnsetDetector,
+    gain: f32,
+    gain_step: f32,
+}
+
+impl Swell {
+    /// Create a new auto-swell
+    ///
+    /// # Arguments
+    /// * `time_ms` - Time for the gain to rise from silence to unity after an onset
+    /// * `sensitivity` - 0.0 only reacts to hard picking, 1.0 to soft notes as well
+    /// * `sample_rate` - Sample rate in Hz
+    pub fn new(time_ms: f32, sensitivity: f32, sample_rate: u32) -> Self {
+        let mut swell = Self {
+            time_ms: 0.0,
+            sample_rate,
+            detector: OnsetDetector::new(sensitivity, sample_rate),
+            gain: 1.0,
+            gain_step: 0.0,
+        };
+        swell.set_time_ms(time_ms);
+        swell
+    }
+    
+    /// Set the swell time in milliseconds
+    pub fn set_time_ms(&mut self, time_ms: f32) {
+        self.time_ms = time_ms.clamp(MIN_SWELL_TIME_MS, MAX_SWELL_TIME_MS);
+        self.gain_step = 1000.0 / (self.time_ms * self.sample_rate as f32);
+    }
+    
+    /// Get the swell time in milliseconds
+    pub fn get_time_ms(&self) -> f32 {
+        self.time_ms
+    }
+    
+    /// Set the onset sensitivity (0.0 to 1.0)
+    pub fn set_sensitivity(&mut self, sensitivity: f32) {
+        self.detector.set_sensitivity(sensitivity);
+    }
+    
+    /// Get the onset sensitivity
+    pub fn get_sensitivity(&self) -> f32 {
+        self.detector.get_sensitivity()
+    }
+    
+    /// Get the current swell gain (0.0 to 1.0)
+    pub fn get_gain(&self) -> f32 {
+        self.gain
+    }
+    
+    /// Detect onsets in the input and return the gain for this sample
+    pub fn process(&mut self, input: f32) -> f32 {
+        if self.detector.process(input) {
+            self.gain = 0.0;
+        }
+        
+        let gain = self.gain;
+        self.gain = (self.gain + self.gain_step).min(1.0);
+        gain
+    }
+    
+    /// Clear the detector and return to unity gain
+    pub fn reset(&mut self) {
+        self.detector.reset();
+        self.gain = 1.0;
+    }
+}
+
+/// Decides when to freeze on a sustaining phrase and when to let go again
+///
+/// Follows the input with a peak envelope. Once something has been played and the
+/// envelope then stays below the threshold for `AUTO_FREEZE_HOLD_MS`, freeze should
+/// engage; as soon as it rises back to the threshold, playing has resumed and freeze
+/// should release. Silence before the first note never engages it.
+pub struct AutoFreeze {
+    threshold_db: f32,
+    threshold: f32,
+    release_coeff: f32,
+    hold_samples: u32,
+    envelope: f32,
+    quiet_samples: u32,
+    played: bool,
+    engaged: bool,
+}
+
+impl AutoFreeze {
+    /// Create a new auto-freeze detector with a threshold in dBFS
+    pub fn new(threshold_db: f32, sample_rate: u32) -> Self {
+        let mut auto_freeze = Self {
+            threshold_db: 0.0,
+            threshold: 0.0,
+            release_coeff: time_constant_coeff(AUTO_FREEZE_RELEASE_MS, sample_rate),
+            hold_samples: (AUTO_FREEZE_HOLD_MS * 0.001 * sample_rate as f32) as u32,
+            envelope: 0.0,
+            quiet_samples: 0,
+            played: false,
+            engaged: false,
+        };
+        auto_freeze.set_threshold_db(threshold_db);
+        auto_freeze
+    }
+    
+    /// Set the threshold in dBFS
+    pub fn set_threshold_db(&mut self, threshold_db: f32) {
+        self.threshold_db = threshold_db.clamp(MIN_AUTO_FREEZE_THRESHOLD_DB, MAX_AUTO_FREEZE_THRESHOLD_DB);
+        self.threshold = 10f32.powf(self.threshold_db / 20.0);
+    }
+    
+    /// Get the threshold in dBFS
+    pub fn get_threshold_db(&self) -> f32 {
+        self.threshold_db
+    }
+    
+    /// Follow the input level and return `Some(true)` when freeze should engage,
+    /// `Some(false)` when it should release, and `None` otherwise
+    pub fn process(&mut self, level: f32) -> Option<bool> {
+        self.envelope = if level > self.envelope {
+            level
+        } else {
+            level + self.release_coeff * (self.envelope - level)
+        };
+        
+        if self.envelope >= self.threshold {
+            self.played = true;
+            self.quiet_samples = 0;
+            if self.engaged {
+                self.engaged = false;
+                return Some(false);
+            }
+            return None;
+        }
+        
+        if self.played && !self.engaged {
+            self.quiet_samples += 1;
+            if self.quiet_samples >= self.hold_samples {
+                self.engaged = true;
+                return Some(true);
+            }
+        }
+        None
+    }
+    
+    /// Forget the envelope and the last phrase
+    pub fn reset(&mut self) {
+        self.envelope = 0.0;
+        self.quiet_samples = 0;
+        self.played = false;
+        self.engaged = false;
+    }
+}
+
+/// Calculate a one-pole smoothing coefficient for a time constant in milliseconds
+pub fn time_constant_coeff(time_ms: f32, sample_rate: u32) -> f32 {
+    if time_ms <= 0.0 {
+        return 0.0;
+    }
+    (-1.0 / (time_ms * 0.001 * sample_rate as f32)).exp()
+}
+
+/// Tape-style soft saturation of a feedback sample (amount 0.0 to 1.0)
+///
+/// Computes `tanh(k·x) / k`, which has unity slope around zero, so low-level repeats
+/// pass almost unchanged while loud ones are compressed. The output never exceeds
+/// the input in magnitude and an amount of 0.0 is exactly linear.
+pub fn soft_saturate(sample: f32, amount: f32) -> f32 {
+    let drive = amount.clamp(0.0, 1.0) * MAX_SATURATION_DRIVE;
+    if drive <= 0.0 {
+        return sample;
+    }
+    (sample * drive).tanh() / drive
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_compressor_gain_reduction_follows_ratio() {
+        let sample_rate = 44100;
+        let mut compressor = Compressor::new(-20.0, 4.0, 1.0, 100.0, 0.0, sample_rate);
+        
+        // A -8dBFS sine is 12dB over the threshold, so 4:1 should leave it 3dB over
+        let amplitude = 10.0_f32.powf(-8.0 / 20.0);
+        let settled = sample_rate as usize * 9 / 10;
+        let mut output_peak: f32 = 0.0;
+        for i in 0..sample_rate as usize {
+            let phase = 2.0 * std::f32::consts::PI * 1000.0 * i as f32 / sample_rate as f32;
+            let input = amplitude * phase.sin();
+            let (left, _right) = compressor.process_stereo(input, input);
+            // Measure over the last 100ms once the attack has settled
+            if i > settled {
+                output_peak = output_peak.max(left.abs());
+            }
+        }
+        
+        let output_db = 20.0 * output_peak.log10();
+        assert!((output_db - -17.0).abs() < 0.5, "output peak {:.2}dBFS, expected -17dBFS", output_db);
+        assert!((compressor.get_gain_reduction_db() - 9.0).abs() < 0.5);
+    }
+    
+    #[test]
+    fn test_compressor_leaves_quiet_signal_alone() {
+        let mut compressor = Compressor::new(-20.0, 4.0, 1.0, 100.0, 6.0, 44100);
+        let (left, right) = compressor.process_stereo(0.01, -0.01);
+        let makeup = 10.0_f32.powf(6.0 / 20.0);
+        assert!((left - 0.01 * makeup).abs() < 1e-6);
+        assert!((right + 0.01 * makeup).abs() < 1e-6);
+    }
+    
+    #[test]
+    fn test_soft_saturate_compresses_only_loud_samples() {
+        // Off is exactly linear
+        assert_eq!(soft_saturate(0.9, 0.0), 0.9);
+        
+        // Low levels pass nearly linearly
+        for sample in [0.01, -0.02, 0.05] {
+            let saturated = soft_saturate(sample, 1.0);
+            assert!((saturated - sample).abs() < sample.abs() * 0.02, "{} -> {}", sample, saturated);
+        }
+        
+        // High levels are compressed smoothly and keep their sign
+        let loud = soft_saturate(1.0, 1.0);
+        assert!(loud > 0.2 && loud < 0.3, "1.0 -> {}", loud);
+        assert_eq!(soft_saturate(-1.0, 1.0), -loud);
+        assert!(soft_saturate(1.0, 0.5) > loud);
+        assert!(soft_saturate(2.0, 1.0) > loud);
+    }
+}
@@ -0,0 +1,216 @@
+use std::path::Path;
+use std::sync::Mutex;
+use std::thread;
+use std::time::{Duration, Instant};
+use log::info;
+use parking_lot::RwLock;
+use crate::batch::read_wav;
+use crate::config::AudioConfig;
+use crate::delay::StereoDelay;
+use crate::error::AudioProcessorError;
+use crate::health::Heartbeat;
+use crate::resample::{ResampleQuality, SampleRateConversion, SINC_HALF_TAPS};
+
+/// Stereo input played in a loop in place of a device input
+///
+/// Used by the backends to run the live processing path on known input, from a
+/// WAV file or a test signal.
+pub struct InputLoop {
+    left: Vec<f32>,
+    right: Vec<f32>,
+    position: usize,
+}
+
+impl InputLoop {
+    /// Create a loop over the given channels, which must be non-empty and of equal length
+    pub fn new(left: Vec<f32>, right: Vec<f32>) -> Result<Self, AudioProcessorError> {
+        if left.is_empty() || left.len() != right.len() {
+            return Err(AudioProcessorError::Processing(format!(
+                "input loop needs two equal non-empty channels, got {} and {} frames",
+                left.len(),
+                right.len()
+            )));
+        }
+        Ok(Self { left, right, position: 0 })
+    }
+    
+    /// Load a mono or stereo WAV file to loop at the processing sample rate
+    ///
+    /// Mono files are fed to both channels; other channel counts are rejected by
+    /// `read_wav`. A file at another sample rate is converted once when it is
+    /// loaded, not on the audio thread.
+    pub fn open(path: &Path, sample_rate: u32, quality: ResampleQuality) -> Result<Self, AudioProcessorError> {
+        let (spec, samples) = read_wav(path)?;
+        let mut stereo: Vec<f32> = if spec.channels == 1 {
+            samples.iter().flat_map(|&sample| [sample, sample]).collect()
+        } else {
+            samples
+        };
+        
+        if spec.sample_rate != sample_rate && !stereo.is_empty() {
+            info!("🔄 Resampling input file from {} Hz to {} Hz", spec.sample_rate, sample_rate);
+            let frames = stereo.len() / 2;
+            let expected = (frames as u64 * sample_rate as u64).div_ceil(spec.sample_rate as u64) as usize;
+            
+            // The converter holds back the frames it still interpolates towards, so
+            // the loop's first frames follow its last to bring out the whole file
+            let lookahead: Vec<f32> = stereo.iter().copied().cycle().take((SINC_HALF_TAPS + 1) * 2).collect();
+            let mut resampler = SampleRateConversion::with_quality(spec.sample_rate, sample_rate, 2, quality);
+            let mut converted = Vec::with_capacity(expected * 2 + lookahead.len());
+            resampler.process(&stereo, &mut converted);
+            resampler.process(&lookahead, &mut converted);
+            converted.truncate(expected * 2);
+            stereo = converted;
+        }
+        if stereo.is_empty() {
+            return Err(AudioProcessorError::Processing(format!("{}: no audio in file", path.display())));
+        }
+        
+        let left = stereo.iter().step_by(2).copied().collect();
+        let right = stereo.iter().skip(1).step_by(2).copied().collect();
+        info!("📂 Looping {} as input ({} frames)", path.display(), stereo.len() / 2);
+        Self::new(left, right)
+    }
+    
+    /// Number of frames in one pass of the loop
+    pub fn len(&self) -> usize {
+        self.left.len()
+    }
+    
+    /// Whether the loop has no frames
+    pub fn is_empty(&self) -> bool {
+        self.left.is_empty()
+    }
+    
+    /// Get the next frame, wrapping back to the start at the end
+    pub fn next_frame(&mut self) -> (f32, f32) {
+        let frame = (self.left[self.position], self.right[self.position]);
+        self.position = (self.position + 1) % self.left.len();
+        frame
+    }
+}
+
+/// Feed looped input through the delay in real time until stopped
+///
+/// Stands in for a device input callback: one block of `config.buffer_size`
+/// frames is processed per buffer period and handed to `output` as interleaved
+/// stereo, and an error from `output` ends the loop. Silence is fed when there
+/// is no input. Returns the number of blocks processed.
+pub(crate) fn run_input_loop<F>(
+    config: &AudioConfig,
+    mut input: Option<InputLoop>,
+    stereo_delay: &Mutex<StereoDelay>,
+    is_running: &RwLock<bool>,
+    heartbeat: &Heartbeat,
+    mut output: F,
+) -> Result<u64, AudioProcessorError>
+where
+    F: FnMut(&[f32]) -> Result<(), AudioProcessorError>,
+{
+    let block_frames = config.buffer_size.max(1);
+    let block_duration = Duration::from_secs_f64(block_frames as f64 / config.sample_rate as f64);
+    let mut processed = Vec::<f32>::with_capacity(block_frames * 2);
+    let mut blocks_processed = 0u64;
+    let mut next_block = Instant::now();
+    
+    while *is_running.read() {
+        heartbeat.beat();
+        if let Ok(mut delay) = stereo_delay.lock() {
+            for _ in 0..block_frames {
+                let (left_input, right_input) = input.as_mut().map_or((0.0, 0.0), InputLoop::next_frame);
+                let (left_output, right_output) = delay.process_sample(left_input, right_input);
+                processed.push(left_output);
+                processed.push(right_output);
+            }
+            
+            // A tails stop ends once the repeats have rung out
+            if delay.tails_finished() {
+                info!("🔇 Delay tails finished - stopping audio");
+                *is_running.write() = false;
+            }
+        }
+        output(&processed)?;
+        processed.clear();
+        blocks_processed += 1;
+        
+        // Keep to the pace of a real device; after a stall carry on from now
+        next_block += block_duration;
+        let now = Instant::now();
+        if next_block > now {
+            thread::sleep(next_block - now);
+        } else {
+            next_block = now;
+        }
+    }
+    
+    Ok(blocks_processed)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::path::PathBuf;
+    
+    /// Write 16-bit interleaved samples to a temporary WAV file
+    fn write_wav(name: &str, channels: u16, sample_rate: u32, samples: &[i16]) -> PathBuf {
+        let path = std::env::temp_dir().join(format!("input_loop_{}_{}.wav", name, std::process::id()));
+        let spec = hound::WavSpec {
+            channels,
+            sample_rate,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for &sample in samples {
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        path
+    }
+    
+    #[test]
+    fn test_mono_file_loops_on_both_channels() {
+        let path = write_wav("mono", 1, 48000, &[0, 8192, 16384]);
+        
+        let mut input = InputLoop::open(&path, 48000, ResampleQuality::default()).unwrap();
+        assert_eq!(input.len(), 3);
+        let frames: Vec<_> = (0..4).map(|_| input.next_frame()).collect();
+        assert_eq!(frames, vec![(0.0, 0.0), (0.25, 0.25), (0.5, 0.5), (0.0, 0.0)]);
+        
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_files_with_more_than_two_channels_are_rejected() {
+        let path = write_wav("quad", 4, 48000, &[0, 8192, 16384, 24576].repeat(10));
+        assert!(InputLoop::open(&path, 48000, ResampleQuality::default()).is_err());
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_resampled_file_keeps_all_of_its_frames() {
+        let tone: Vec<i16> = (0..44100).map(|i| (8192.0 * (i as f32 * 0.05).sin()) as i16).collect();
+        let path = write_wav("resampled", 1, 44100, &tone);
+        let short = write_wav("single_frame", 2, 44100, &[8192, -8192]);
+        
+        for quality in [ResampleQuality::Fast, ResampleQuality::High] {
+            let input = InputLoop::open(&path, 48000, quality).unwrap();
+            assert_eq!(input.len(), 48000, "{}", quality);
+            
+            // Even a single frame converts to one, where the converters alone hold it back
+            let mut input = InputLoop::open(&short, 48000, quality).unwrap();
+            assert_eq!(input.len(), 2, "{}", quality);
+            assert!(input.next_frame().0 > 0.0, "{}", quality);
+        }
+        
+        std::fs::remove_file(&path).unwrap();
+        std::fs::remove_file(&short).unwrap();
+    }
+    
+    #[test]
+    fn test_loop_channels_must_match() {
+        assert!(InputLoop::new(Vec::new(), Vec::new()).is_err());
+        assert!(InputLoop::new(vec![0.0; 4], vec![0.0; 3]).is_err());
+        assert_eq!(InputLoop::new(vec![0.0; 4], vec![0.0; 4]).unwrap().len(), 4);
+    }
+}
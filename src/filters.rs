@@ -0,0 +1,407 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+
+/// Level below which filter state is flushed to zero while anti-denormal protection is on
+///
+/// About -300 dB, far below anything audible, and far enough above the denormal
+/// range (below 1.2e-38) that the products computed from the state never reach it.
+pub const DENORMAL_FLUSH_LEVEL: f32 = 1e-15;
+
+// Process-wide, as denormal handling is a property of the CPU rather than of one effect
+static ANTI_DENORMAL: AtomicBool = AtomicBool::new(true);
+
+/// Turn flushing of near-silent filter state on or off for every filter
+///
+/// On by default. Decaying tails in a recursive filter otherwise end up as
+/// denormals, which take many times longer to compute on most CPUs, including
+/// the Pi's. With it off only actual denormals are flushed, by `sanitize_sample`.
+pub fn set_anti_denormal(enabled: bool) {
+    ANTI_DENORMAL.store(enabled, Ordering::Relaxed);
+}
+
+/// Whether near-silent filter state is flushed to zero
+pub fn anti_denormal_enabled() -> bool {
+    ANTI_DENORMAL.load(Ordering::Relaxed)
+}
+
+/// Sanitize a new filter state value, flushing it to zero once it is near silence
+#[inline]
+pub fn flush_denormal(sample: f32) -> f32 {
+    if sample.abs() < DENORMAL_FLUSH_LEVEL && anti_denormal_enabled() {
+        0.0
+    } else {
+        sanitize_sample(sample)
+    }
+}
+
+/// Replace NaN, infinite and denormal samples with zero
+///
+/// A NaN or infinity that reaches a feedback path or filter state would otherwise
+/// stay there forever, and denormals from decaying tails are very slow on most CPUs.
+#[inline]
+pub fn sanitize_sample(sample: f32) -> f32 {
+    if sample.is_normal() {
+        sample
+    } else {
+        0.0
+    }
+}
+
+/// One-pole low-pass filter
+pub struct OnePoleLowPass {
+    coeff: f32,
+    state: f32,
+}
+
+impl OnePoleLowPass {
+    /// Create a new one-pole low-pass with the given cutoff frequency
+    pub fn new(cutoff_hz: f32, sample_rate: u32) -> Self {
+        let mut filter = Self { coeff: 0.0, state: 0.0 };
+        filter.set_cutoff(cutoff_hz, sample_rate);
+        filter
+    }
+    
+    /// Set the cutoff frequency in Hz
+    pub fn set_cutoff(&mut self, cutoff_hz: f32, sample_rate: u32) {
+        let nyquist = sample_rate as f32 * 0.5;
+        let cutoff_hz = cutoff_hz.clamp(1.0, nyquist);
+        self.coeff = (-2.0 * std::f32::consts::PI * cutoff_hz / sample_rate as f32).exp();
+    }
+    
+    /// Process a single sample
+    pub fn process(&mut self, sample: f32) -> f32 {
+        self.state = flush_denormal(sample + self.coeff * (self.state - sample));
+        self.state
+    }
+    
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+    }
+}
+
+/// Cutoff of the DC blocker in Hz, well below the lowest guitar note
+pub const DC_BLOCK_CUTOFF_HZ: f32 = 10.0;
+
+/// One-pole DC-blocking high-pass filter
+pub struct DcBlocker {
+    coeff: f32,
+    previous_input: f32,
+    previous_output: f32,
+}
+
+impl DcBlocker {
+    /// Create a new DC blocker with the given cutoff frequency
+    pub fn new(cutoff_hz: f32, sample_rate: u32) -> Self {
+        let cutoff_hz = cutoff_hz.clamp(1.0, sample_rate as f32 * 0.5);
+        Self {
+            coeff: (-2.0 * std::f32::consts::PI * cutoff_hz / sample_rate as f32).exp(),
+            previous_input: 0.0,
+            previous_output: 0.0,
+        }
+    }
+    
+    /// Process a single sample
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let output = flush_denormal(sample - self.previous_input + self.coeff * self.previous_output);
+        self.previous_input = flush_denormal(sample);
+        self.previous_output = output;
+        output
+    }
+    
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.previous_input = 0.0;
+        self.previous_output = 0.0;
+    }
+}
+
+/// Pivot frequency of the tilt EQ in Hz
+pub const TILT_PIVOT_HZ: f32 = 1000.0;
+
+/// Maximum shelf gain of the tilt EQ in dB (at tilt = ±1)
+pub const TILT_MAX_DB: f32 = 6.0;
+
+/// Tilt EQ built from a complementary low/high shelving pair around a pivot frequency
+///
+/// A positive amount boosts the highs and cuts the lows, a negative amount does the
+/// opposite. At 0 the two bands sum back to the input and the filter is flat.
+pub struct TiltEq {
+    amount: f32,
+    low_gain: f32,
+    high_gain: f32,
+    splitter: OnePoleLowPass,
+}
+
+impl TiltEq {
+    /// Create a new tilt EQ
+    pub fn new(amount: f32, sample_rate: u32) -> Self {
+        let mut tilt = Self {
+            amount: 0.0,
+            low_gain: 1.0,
+            high_gain: 1.0,
+            splitter: OnePoleLowPass::new(TILT_PIVOT_HZ, sample_rate),
+        };
+        tilt.set_amount(amount);
+        tilt
+    }
+    
+    /// Set the tilt amount (-1.0 darker to 1.0 brighter)
+    pub fn set_amount(&mut self, amount: f32) {
+        self.amount = amount.clamp(-1.0, 1.0);
+        let shelf_db = self.amount * TILT_MAX_DB;
+        self.high_gain = 10.0f32.powf(shelf_db / 20.0);
+        self.low_gain = 10.0f32.powf(-shelf_db / 20.0);
+    }
+    
+    /// Get the tilt amount
+    pub fn get_amount(&self) -> f32 {
+        self.amount
+    }
+    
+    /// Get the largest gain the filter applies at any frequency
+    pub fn peak_gain(&self) -> f32 {
+        self.low_gain.max(self.high_gain)
+    }
+    
+    /// Process a single sample
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let low = self.splitter.process(sample);
+        if self.amount == 0.0 {
+            return sample;
+        }
+        let high = sample - low;
+        low * self.low_gain + high * self.high_gain
+    }
+    
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.splitter.reset();
+    }
+}
+
+/// Low-pass cutoff in Hz at or above which the band limiter passes the highs untouched
+pub const FULL_RANGE_HZ: f32 = 20000.0;
+
+/// High-pass and low-pass pair limiting a signal to a band
+///
+/// The high-pass is the complement of a one-pole low-pass, so neither filter has
+/// gain above unity at any frequency. A 0 Hz high-pass or a low-pass at
+/// `FULL_RANGE_HZ` and above leaves that side open; with both open the signal
+/// passes unchanged.
+pub struct BandLimiter {
+    high_pass_hz: f32,
+    low_pass_hz: f32,
+    high_pass: OnePoleLowPass,
+    low_pass: OnePoleLowPass,
+}
+
+impl BandLimiter {
+    /// Create a band limiter passing the full range
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            high_pass_hz: 0.0,
+            low_pass_hz: FULL_RANGE_HZ,
+            high_pass: OnePoleLowPass::new(1.0, sample_rate),
+            low_pass: OnePoleLowPass::new(FULL_RANGE_HZ, sample_rate),
+        }
+    }
+    
+    /// Set the high-pass and low-pass cutoffs in Hz
+    pub fn set_band(&mut self, high_pass_hz: f32, low_pass_hz: f32, sample_rate: u32) {
+        self.high_pass_hz = high_pass_hz.max(0.0);
+        self.low_pass_hz = low_pass_hz.max(0.0);
+        self.high_pass.set_cutoff(self.high_pass_hz, sample_rate);
+        self.low_pass.set_cutoff(self.low_pass_hz, sample_rate);
+    }
+    
+    /// Get the high-pass cutoff in Hz
+    pub fn get_high_pass_hz(&self) -> f32 {
+        self.high_pass_hz
+    }
+    
+    /// Get the low-pass cutoff in Hz
+    pub fn get_low_pass_hz(&self) -> f32 {
+        self.low_pass_hz
+    }
+    
+    /// Process a single sample
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let sample = if self.high_pass_hz > 0.0 {
+            sample - self.high_pass.process(sample)
+        } else {
+            sample
+        };
+        if self.low_pass_hz < FULL_RANGE_HZ {
+            self.low_pass.process(sample)
+        } else {
+            sample
+        }
+    }
+    
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.high_pass.reset();
+        self.low_pass.reset();
+    }
+}
+
+/// Resonant low-pass state-variable filter with a sweepable cutoff
+///
+/// Trapezoidal (zero-delay feedback) form, so the cutoff can move every sample
+/// without zipper noise and stays accurate up to near Nyquist. Resonance runs from
+/// 0.0 (no peak) to 1.0, where the damping reaches zero: the filter no longer loses
+/// energy and any excitation rings on as a sine at the cutoff frequency.
+pub struct StateVariableFilter {
+    cutoff_hz: f32,
+    resonance: f32,
+    sample_rate: u32,
+    damping: f32,
+    a1: f32,
+    a2: f32,
+    a3: f32,
+    band_state: f32,
+    low_state: f32,
+}
+
+impl StateVariableFilter {
+    /// Create a new filter with the given cutoff frequency and resonance
+    pub fn new(cutoff_hz: f32, resonance: f32, sample_rate: u32) -> Self {
+        let mut filter = Self {
+            cutoff_hz,
+            resonance: 0.0,
+            sample_rate,
+            damping: 2.0,
+            a1: 0.0,
+            a2: 0.0,
+            a3: 0.0,
+            band_state: 0.0,
+            low_state: 0.0,
+        };
+        filter.set_resonance(resonance);
+        filter
+    }
+    
+    /// Set the cutoff frequency in Hz, kept below Nyquist
+    pub fn set_cutoff(&mut self, cutoff_hz: f32) {
+        let nyquist = self.sample_rate as f32 * 0.5;
+        self.cutoff_hz = cutoff_hz.clamp(1.0, nyquist * 0.95);
+        let g = (std::f32::consts::PI * self.cutoff_hz / self.sample_rate as f32).tan();
+        self.a1 = 1.0 / (1.0 + g * (g + self.damping));
+        self.a2 = g * self.a1;
+        self.a3 = g * self.a2;
+    }
+    
+    /// Get the cutoff frequency in Hz
+    pub fn get_cutoff(&self) -> f32 {
+        self.cutoff_hz
+    }
+    
+    /// Set the resonance (0.0 to 1.0, self-oscillating at 1.0)
+    pub fn set_resonance(&mut self, resonance: f32) {
+        self.resonance = resonance.clamp(0.0, 1.0);
+        self.damping = 2.0 * (1.0 - self.resonance);
+        self.set_cutoff(self.cutoff_hz);
+    }
+    
+    /// Get the resonance
+    pub fn get_resonance(&self) -> f32 {
+        self.resonance
+    }
+    
+    /// Get the largest gain the filter applies at any frequency
+    ///
+    /// Depends on the resonance only, so sweeping the cutoff never changes it.
+    /// Infinite at full resonance.
+    pub fn peak_gain(&self) -> f32 {
+        let k = self.damping;
+        if k * k >= 2.0 {
+            1.0
+        } else if k <= 0.0 {
+            f32::INFINITY
+        } else {
+            1.0 / (k * (1.0 - k * k / 4.0).sqrt())
+        }
+    }
+    
+    /// Process a single sample and return the low-pass output
+    pub fn process(&mut self, sample: f32) -> f32 {
+        let v3 = sample - self.low_state;
+        let band = self.a1 * self.band_state + self.a2 * v3;
+        let low = self.low_state + self.a2 * self.band_state + self.a3 * v3;
+        self.band_state = flush_denormal(2.0 * band - self.band_state);
+        self.low_state = flush_denormal(2.0 * low - self.low_state);
+        low
+    }
+    
+    /// Clear the filter state
+    pub fn reset(&mut self) {
+        self.band_state = 0.0;
+        self.low_state = 0.0;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_full_resonance_rings_at_the_cutoff() {
+        let sample_rate = 48000;
+        let mut filter = StateVariableFilter::new(1000.0, 1.0, sample_rate);
+        
+        // A single impulse, then nothing: whatever comes out is the filter's own tone
+        filter.process(1.0);
+        let settle = sample_rate as usize / 10;
+        for _ in 0..settle {
+            filter.process(0.0);
+        }
+        let ringing: Vec<f32> = (0..sample_rate).map(|_| filter.process(0.0)).collect();
+        
+        let crossings = ringing.windows(2).filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0)).count();
+        let frequency = crossings as f32 / 2.0;
+        assert!((frequency - 1000.0).abs() < 10.0, "rings at {} Hz", frequency);
+        
+        // Still ringing a second later, without building up
+        let peak = |samples: &[f32]| samples.iter().fold(0.0f32, |peak, sample| peak.max(sample.abs()));
+        let early = peak(&ringing[..4800]);
+        let late = peak(&ringing[ringing.len() - 4800..]);
+        assert!(early > 0.01 && (late / early - 1.0).abs() < 0.05, "{} -> {}", early, late);
+        
+        // Without resonance the same impulse dies away
+        let mut damped = StateVariableFilter::new(1000.0, 0.0, sample_rate);
+        damped.process(1.0);
+        for _ in 0..settle {
+            damped.process(0.0);
+        }
+        assert!(damped.process(0.0).abs() < 1e-6);
+        assert_eq!(damped.peak_gain(), 1.0);
+        assert!(filter.peak_gain().is_infinite());
+    }
+    
+    #[test]
+    fn test_decaying_state_never_becomes_denormal() {
+        let sample_rate = 48000;
+        let mut dub = StateVariableFilter::new(800.0, 0.7, sample_rate);
+        let mut damping = OnePoleLowPass::new(3000.0, sample_rate);
+        
+        // A loud note, then ten seconds of silence for the tails to decay through
+        for i in 0..sample_rate as usize / 10 {
+            let sample = (i as f32 * 0.05).sin();
+            dub.process(sample);
+            damping.process(sample);
+        }
+        for _ in 0..10 * sample_rate as usize {
+            dub.process(0.0);
+            damping.process(0.0);
+            
+            // Neither the state nor anything computed from it on the next sample
+            for state in [dub.band_state, dub.low_state, damping.state] {
+                assert!(!state.is_subnormal(), "{:e}", state);
+                assert!(!(state * dub.a3).is_subnormal() && !(state * damping.coeff).is_subnormal(), "{:e}", state);
+            }
+        }
+        
+        // The tails were flushed to silence well before they could become denormal
+        assert_eq!((dub.band_state, dub.low_state, damping.state), (0.0, 0.0, 0.0));
+    }
+}
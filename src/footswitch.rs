@@ -0,0 +1,371 @@
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::fmt;
+use std::path::PathBuf;
+use std::time::Duration;
+
+use crate::config::FootswitchConfig;
+use crate::error::AudioProcessorError;
+use crate::AudioProcessorTrait;
+
+/// Default time a pin has to hold a new level before the change counts
+pub const DEFAULT_DEBOUNCE_MS: f32 = 20.0;
+
+/// Longest debounce time accepted in the configuration
+pub const MAX_DEBOUNCE_MS: f32 = 200.0;
+
+/// How often the pins are read
+pub const FOOTSWITCH_POLL: Duration = Duration::from_millis(1);
+
+/// A gap between taps longer than this starts a new tempo
+pub const TAP_TEMPO_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Taps averaged into the tempo
+pub const TAP_TEMPO_TAPS: usize = 4;
+
+/// Tempo range the taps are clamped to, as accepted by the `bpm` parameter
+const MIN_TAP_BPM: f32 = 20.0;
+const MAX_TAP_BPM: f32 = 300.0;
+
+/// What pressing a footswitch does
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum FootswitchAction {
+    /// Set the tempo from the time between presses
+    TapTempo,
+    /// Toggle the delay freeze
+    Freeze,
+    /// Toggle the effects off, leaving the dry input (see `monitor_dry_only`)
+    Bypass,
+    /// Load the next state file from the configured preset list
+    PresetNext,
+}
+
+impl fmt::Display for FootswitchAction {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            FootswitchAction::TapTempo => "tap tempo",
+            FootswitchAction::Freeze => "freeze",
+            FootswitchAction::Bypass => "bypass",
+            FootswitchAction::PresetNext => "next preset",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// One physical button wired between a GPIO pin and ground
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FootswitchPin {
+    /// BCM pin number
+    pub pin: u8,
+    
+    /// Action taken when the button is pressed
+    pub action: FootswitchAction,
+}
+
+/// Change to apply to the processor after a debounced press
+#[derive(Debug, Clone, PartialEq)]
+pub enum FootswitchCommand {
+    SetBpm(f32),
+    ToggleFreeze,
+    ToggleBypass,
+    LoadPreset(PathBuf),
+}
+
+/// Software debounce of one button
+///
+/// A new level only counts once the pin has held it for the debounce time, so
+/// contact bounce on press and release never produces extra presses.
+#[derive(Debug, Clone)]
+pub struct Debouncer {
+    debounce: Duration,
+    pressed: bool,
+    candidate: bool,
+    candidate_since: Duration,
+}
+
+impl Debouncer {
+    /// Create a debouncer for a released button
+    pub fn new(debounce_ms: f32) -> Self {
+        Self {
+            debounce: Duration::from_secs_f32(debounce_ms.max(0.0) / 1000.0),
+            pressed: false,
+            candidate: false,
+            candidate_since: Duration::ZERO,
+        }
+    }
+    
+    /// Feed a pin reading taken at `now`, returns true once per debounced press
+    pub fn update(&mut self, pressed: bool, now: Duration) -> bool {
+        if pressed != self.candidate {
+            self.candidate = pressed;
+            self.candidate_since = now;
+        }
+        
+        if self.candidate != self.pressed && now.saturating_sub(self.candidate_since) >= self.debounce {
+            self.pressed = self.candidate;
+            return self.pressed;
+        }
+        false
+    }
+    
+    /// Whether the button is currently held down after debouncing
+    pub fn is_pressed(&self) -> bool {
+        self.pressed
+    }
+}
+
+/// Tempo from the average time between the last few taps
+#[derive(Debug, Clone, Default)]
+pub struct TapTempo {
+    taps: VecDeque<Duration>,
+}
+
+impl TapTempo {
+    /// Create a tap tempo with no taps yet
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Register a tap at `now`, returns the tempo once there are two taps in a row
+    pub fn tap(&mut self, now: Duration) -> Option<f32> {
+        if let Some(&last) = self.taps.back() {
+            if now.saturating_sub(last) > TAP_TEMPO_TIMEOUT {
+                self.taps.clear();
+            }
+        }
+        
+        self.taps.push_back(now);
+        while self.taps.len() > TAP_TEMPO_TAPS {
+            self.taps.pop_front();
+        }
+        
+        let first = *self.taps.front()?;
+        let intervals = self.taps.len() - 1;
+        if intervals == 0 {
+            return None;
+        }
+        let beat = now.saturating_sub(first).as_secs_f32() / intervals as f32;
+        if beat <= 0.0 {
+            return None;
+        }
+        Some((60.0 / beat).clamp(MIN_TAP_BPM, MAX_TAP_BPM))
+    }
+}
+
+/// Maps debounced button presses on GPIO pins to processor commands
+///
+/// Independent of the GPIO hardware: the poller feeds it pin readings with
+/// timestamps, which also lets the mapping run against simulated input.
+#[derive(Debug, Clone)]
+pub struct Footswitch {
+    buttons: HashMap<u8, (FootswitchAction, Debouncer)>,
+    tap_tempo: TapTempo,
+    presets: Vec<PathBuf>,
+    next_preset: usize,
+}
+
+impl Footswitch {
+    /// Create the footswitch mapping from the configuration
+    pub fn new(config: &FootswitchConfig) -> Self {
+        let buttons = config
+            .pins
+            .iter()
+            .map(|mapping| (mapping.pin, (mapping.action, Debouncer::new(config.debounce_ms))))
+            .collect();
+        
+        Self {
+            buttons,
+            tap_tempo: TapTempo::new(),
+            presets: config.presets.clone(),
+            next_preset: 0,
+        }
+    }
+    
+    /// Pins with an action, sorted
+    pub fn pins(&self) -> Vec<u8> {
+        let mut pins: Vec<u8> = self.buttons.keys().copied().collect();
+        pins.sort_unstable();
+        pins
+    }
+    
+    /// Feed one pin reading taken at `now`, returns the command of a debounced press
+    pub fn update(&mut self, pin: u8, pressed: bool, now: Duration) -> Option<FootswitchCommand> {
+        let (action, debouncer) = self.buttons.get_mut(&pin)?;
+        if !debouncer.update(pressed, now) {
+            return None;
+        }
+        
+        match action {
+            FootswitchAction::TapTempo => self.tap_tempo.tap(now).map(FootswitchCommand::SetBpm),
+            FootswitchAction::Freeze => Some(FootswitchCommand::ToggleFreeze),
+            FootswitchAction::Bypass => Some(FootswitchCommand::ToggleBypass),
+            FootswitchAction::PresetNext => {
+                if self.presets.is_empty() {
+                    return None;
+                }
+                let preset = self.presets[self.next_preset % self.presets.len()].clone();
+                self.next_preset = (self.next_preset + 1) % self.presets.len();
+                Some(FootswitchCommand::LoadPreset(preset))
+            }
+        }
+    }
+}
+
+/// Read an on/off entry of the processor status, "true" or a number above 0.5
+fn status_flag(processor: &dyn AudioProcessorTrait, key: &str) -> Result<bool, AudioProcessorError> {
+    let status = processor.get_status()?;
+    Ok(status.get(key).is_some_and(|value| {
+        value == "true" || value.parse::<f32>().is_ok_and(|v| v > 0.5)
+    }))
+}
+
+/// Apply a footswitch command to the processor
+pub fn apply_command(processor: &mut dyn AudioProcessorTrait, command: &FootswitchCommand) -> Result<(), AudioProcessorError> {
+    match command {
+        FootswitchCommand::SetBpm(bpm) => processor.set_stereo_delay_parameter("bpm", *bpm),
+        FootswitchCommand::ToggleFreeze => {
+            let frozen = status_flag(processor, "freeze")?;
+            processor.set_stereo_delay_parameter("freeze", if frozen { 0.0 } else { 1.0 })
+        }
+        FootswitchCommand::ToggleBypass => {
+            let bypassed = status_flag(processor, "monitor_dry_only")?;
+            processor.set_stereo_delay_parameter("monitor_dry_only", if bypassed { 0.0 } else { 1.0 })
+        }
+        FootswitchCommand::LoadPreset(path) => {
+            let state = std::fs::read_to_string(path).map_err(|e| {
+                AudioProcessorError::Configuration(format!("Failed to read preset {}: {}", path.display(), e))
+            })?;
+            processor.import_state(&state)
+        }
+    }
+}
+
+/// Poll the configured GPIO pins on a background thread and apply every press
+///
+/// Buttons connect their pin to ground; the internal pull-ups hold released
+/// pins high.
+#[cfg(all(feature = "gpio", target_os = "linux"))]
+pub fn spawn_footswitch(
+    processor: std::sync::Arc<std::sync::Mutex<Box<dyn AudioProcessorTrait + Send>>>,
+    config: &FootswitchConfig,
+) -> Result<(), AudioProcessorError> {
+    use log::{info, warn};
+    use std::time::Instant;
+    
+    let gpio_error = |e: rppal::gpio::Error| AudioProcessorError::Configuration(format!("GPIO: {}", e));
+    
+    let mut footswitch = Footswitch::new(config);
+    let gpio = rppal::gpio::Gpio::new().map_err(gpio_error)?;
+    let mut inputs = Vec::new();
+    for pin in footswitch.pins() {
+        inputs.push((pin, gpio.get(pin).map_err(gpio_error)?.into_input_pullup()));
+    }
+    
+    for mapping in &config.pins {
+        info!("🦶 Footswitch on GPIO {}: {}", mapping.pin, mapping.action);
+    }
+    
+    std::thread::spawn(move || {
+        let start = Instant::now();
+        loop {
+            std::thread::sleep(FOOTSWITCH_POLL);
+            let now = start.elapsed();
+            for (pin, input) in &inputs {
+                let Some(command) = footswitch.update(*pin, input.is_low(), now) else {
+                    continue;
+                };
+                let mut processor_guard = processor.lock().unwrap();
+                if let Err(e) = apply_command(processor_guard.as_mut(), &command) {
+                    warn!("⚠️  Footswitch {:?} failed: {}", command, e);
+                }
+            }
+        }
+    });
+    
+    Ok(())
+}
+
+/// Poll the configured GPIO pins (needs the `gpio` feature on Linux)
+#[cfg(not(all(feature = "gpio", target_os = "linux")))]
+pub fn spawn_footswitch(
+    _processor: std::sync::Arc<std::sync::Mutex<Box<dyn AudioProcessorTrait + Send>>>,
+    _config: &FootswitchConfig,
+) -> Result<(), AudioProcessorError> {
+    Err(AudioProcessorError::Configuration(
+        "footswitch support needs a Linux build with the gpio feature".to_string(),
+    ))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn ms(ms: u64) -> Duration {
+        Duration::from_millis(ms)
+    }
+    
+    /// Feed pin readings as (time in ms, pressed) and collect the commands
+    fn run(footswitch: &mut Footswitch, pin: u8, readings: &[(u64, bool)]) -> Vec<FootswitchCommand> {
+        readings
+            .iter()
+            .filter_map(|&(time, pressed)| footswitch.update(pin, pressed, ms(time)))
+            .collect()
+    }
+    
+    #[test]
+    fn test_bouncing_presses_map_to_single_actions() {
+        let config = FootswitchConfig {
+            enabled: true,
+            debounce_ms: 20.0,
+            pins: vec![
+                FootswitchPin { pin: 17, action: FootswitchAction::Freeze },
+                FootswitchPin { pin: 27, action: FootswitchAction::TapTempo },
+                FootswitchPin { pin: 22, action: FootswitchAction::PresetNext },
+            ],
+            presets: vec![PathBuf::from("a.json"), PathBuf::from("b.json")],
+        };
+        let mut footswitch = Footswitch::new(&config);
+        assert_eq!(footswitch.pins(), vec![17, 22, 27]);
+        
+        // Contact bounce on press and release, then a held press: one toggle
+        let bouncy = [(0, true), (2, false), (4, true), (6, false), (8, true), (30, true), (100, false), (103, true), (105, false), (200, false)];
+        assert_eq!(run(&mut footswitch, 17, &bouncy), vec![FootswitchCommand::ToggleFreeze]);
+        
+        // A glitch shorter than the debounce time is ignored
+        assert!(run(&mut footswitch, 17, &[(300, true), (310, false), (400, false)]).is_empty());
+        
+        // Unmapped pins do nothing
+        assert!(run(&mut footswitch, 4, &[(0, true), (50, true)]).is_empty());
+        
+        // Taps half a second apart give 120 bpm from the second tap on
+        let taps: Vec<(u64, bool)> = (0..4)
+            .flat_map(|tap| [(tap * 500, true), (tap * 500 + 30, true), (tap * 500 + 100, false), (tap * 500 + 130, false)])
+            .collect();
+        let tempos = run(&mut footswitch, 27, &taps);
+        assert_eq!(tempos.len(), 3);
+        for tempo in tempos {
+            match tempo {
+                FootswitchCommand::SetBpm(bpm) => assert!((bpm - 120.0).abs() < 0.1, "{}", bpm),
+                other => panic!("unexpected {:?}", other),
+            }
+        }
+        
+        // After a long pause the tempo starts over
+        assert!(run(&mut footswitch, 27, &[(10_000, true), (10_030, true), (10_100, false), (10_130, false)]).is_empty());
+        
+        // Presets cycle in order
+        let presses: Vec<(u64, bool)> = (0..3)
+            .flat_map(|press| [(press * 200, true), (press * 200 + 30, true), (press * 200 + 100, false), (press * 200 + 130, false)])
+            .collect();
+        let loaded = run(&mut footswitch, 22, &presses);
+        let names: Vec<_> = loaded
+            .iter()
+            .map(|command| match command {
+                FootswitchCommand::LoadPreset(path) => path.to_string_lossy().to_string(),
+                other => panic!("unexpected {:?}", other),
+            })
+            .collect();
+        assert_eq!(names, vec!["a.json", "b.json", "a.json"]);
+    }
+}
@@ -0,0 +1,257 @@
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+/// Longest gap between heartbeats before the audio thread is considered stalled
+pub const HEARTBEAT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Result of a health check
+#[derive(Debug, Clone, PartialEq)]
+pub enum HealthStatus {
+    /// Audio has been processed recently
+    Healthy { heartbeat_age: Duration },
+    /// Audio is not being processed, with the reason why
+    Unhealthy { reason: String },
+}
+
+impl HealthStatus {
+    /// Check whether the status is healthy
+    pub fn is_healthy(&self) -> bool {
+        matches!(self, HealthStatus::Healthy { .. })
+    }
+}
+
+/// Timestamp of the last audio callback, shared lock-free with the audio thread
+///
+/// The audio loop calls `beat` every time it processes a buffer. A running flag
+/// alone cannot tell a working stream from one that silently stopped calling back.
+pub struct Heartbeat {
+    created: Instant,
+    // Milliseconds since `created` plus one, or 0 if no beat has happened yet
+    last_beat_ms: AtomicU64,
+}
+
+impl Heartbeat {
+    /// Create a heartbeat that has not beaten yet
+    pub fn new() -> Self {
+        Self {
+            created: Instant::now(),
+            last_beat_ms: AtomicU64::new(0),
+        }
+    }
+    
+    /// Record that the audio thread processed a buffer just now
+    pub fn beat(&self) {
+        self.beat_at(Instant::now());
+    }
+    
+    /// Same as `beat` but with an explicit timestamp
+    pub fn beat_at(&self, now: Instant) {
+        let elapsed_ms = now.saturating_duration_since(self.created).as_millis() as u64;
+        self.last_beat_ms.store(elapsed_ms + 1, Ordering::Relaxed);
+    }
+    
+    /// Forget the last beat, e.g. when a new audio thread is started
+    pub fn clear(&self) {
+        self.last_beat_ms.store(0, Ordering::Relaxed);
+    }
+    
+    /// Time since the last beat, or `None` if there has not been one
+    pub fn age_at(&self, now: Instant) -> Option<Duration> {
+        match self.last_beat_ms.load(Ordering::Relaxed) {
+            0 => None,
+            beat_ms => {
+                let beat = self.created + Duration::from_millis(beat_ms - 1);
+                Some(now.saturating_duration_since(beat))
+            }
+        }
+    }
+    
+    /// Check the heartbeat against a timeout
+    pub fn status(&self, timeout: Duration) -> HealthStatus {
+        self.status_at(Instant::now(), timeout)
+    }
+    
+    /// Same as `status` but with an explicit timestamp
+    pub fn status_at(&self, now: Instant, timeout: Duration) -> HealthStatus {
+        match self.age_at(now) {
+            None => HealthStatus::Unhealthy {
+                reason: "audio thread has not processed any audio yet".to_string(),
+            },
+            Some(age) if age > timeout => HealthStatus::Unhealthy {
+                reason: format!("audio thread stalled: no audio processed for {}ms", age.as_millis()),
+            },
+            Some(age) => HealthStatus::Healthy { heartbeat_age: age },
+        }
+    }
+}
+
+impl Default for Heartbeat {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Whether audio is being processed, as reported by `get_status`
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AudioState {
+    Stopped,
+    Running,
+    /// Input is no longer fed and the repeats are ringing out before audio stops
+    Stopping,
+}
+
+impl AudioState {
+    /// State of a processor from its running flag and whether the delay is ringing out
+    pub fn new(is_running: bool, tailing: bool) -> Self {
+        match (is_running, tailing) {
+            (false, _) => AudioState::Stopped,
+            (true, false) => AudioState::Running,
+            (true, true) => AudioState::Stopping,
+        }
+    }
+}
+
+impl std::fmt::Display for AudioState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            AudioState::Stopped => "stopped",
+            AudioState::Running => "running",
+            AudioState::Stopping => "stopping",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Run the body of an audio thread, turning both errors and panics into a message
+///
+/// A panic inside the processing closure would otherwise end the thread silently
+/// while the processor still reports itself as running.
+pub fn catch_audio_thread_failure<F, E>(body: F) -> Result<(), String>
+where
+    F: FnOnce() -> Result<(), E>,
+    E: std::fmt::Display,
+{
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)) {
+        Ok(Ok(())) => Ok(()),
+        Ok(Err(e)) => Err(format!("audio stream error: {}", e)),
+        Err(payload) => Err(format!("audio thread panicked: {}", panic_message(payload.as_ref()))),
+    }
+}
+
+/// Text of a panic payload, for the common `&str` and `String` cases
+fn panic_message(payload: &(dyn std::any::Any + Send)) -> String {
+    payload
+        .downcast_ref::<&str>()
+        .map(|s| s.to_string())
+        .or_else(|| payload.downcast_ref::<String>().cloned())
+        .unwrap_or_else(|| "unknown panic".to_string())
+}
+
+/// Panic caught inside a stream data callback, kept for the thread that owns the stream
+///
+/// The backend runs data callbacks on its own threads, so a panic there never
+/// reaches the audio thread. Each callback body runs through `run`, which stops
+/// calling the body after a panic and keeps the message for `check`.
+#[derive(Debug, Default)]
+pub struct CallbackFailure {
+    failed: AtomicBool,
+    message: Mutex<Option<String>>,
+}
+
+impl CallbackFailure {
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Run one callback body, catching a panic instead of unwinding into the backend
+    pub fn run<F: FnOnce()>(&self, body: F) {
+        if self.failed.load(Ordering::Acquire) {
+            return;
+        }
+        if let Err(payload) = std::panic::catch_unwind(std::panic::AssertUnwindSafe(body)) {
+            let message = format!("stream callback panicked: {}", panic_message(payload.as_ref()));
+            if let Ok(mut slot) = self.message.lock() {
+                *slot = Some(message);
+            }
+            self.failed.store(true, Ordering::Release);
+        }
+    }
+    
+    /// Err with the panic message once a callback has panicked
+    pub fn check(&self) -> Result<(), String> {
+        if !self.failed.load(Ordering::Acquire) {
+            return Ok(());
+        }
+        let message = self.message.lock().ok().and_then(|slot| slot.clone());
+        Err(message.unwrap_or_else(|| "stream callback panicked".to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_recent_heartbeat_is_healthy() {
+        let heartbeat = Heartbeat::new();
+        let now = Instant::now();
+        heartbeat.beat_at(now);
+        assert!(heartbeat.status_at(now + Duration::from_millis(50), HEARTBEAT_TIMEOUT).is_healthy());
+    }
+    
+    #[test]
+    fn test_stalled_heartbeat_is_unhealthy() {
+        let heartbeat = Heartbeat::new();
+        let now = Instant::now();
+        assert!(!heartbeat.status_at(now, HEARTBEAT_TIMEOUT).is_healthy());
+        
+        heartbeat.beat_at(now);
+        let status = heartbeat.status_at(now + Duration::from_secs(5), HEARTBEAT_TIMEOUT);
+        match status {
+            HealthStatus::Unhealthy { reason } => assert!(reason.contains("stalled")),
+            HealthStatus::Healthy { .. } => panic!("stalled heartbeat reported healthy"),
+        }
+    }
+    
+    #[test]
+    fn test_audio_thread_panic_becomes_error() {
+        assert_eq!(catch_audio_thread_failure(|| Ok::<(), String>(())), Ok(()));
+        
+        let error = catch_audio_thread_failure(|| Err::<(), _>("device unplugged")).unwrap_err();
+        assert!(error.contains("device unplugged"));
+        
+        let error = catch_audio_thread_failure(|| -> Result<(), String> {
+            #[allow(clippy::useless_vec)]
+            let buffer = vec![0.0_f32; 4];
+            let index = buffer.len() + 3;
+            let _sample = buffer[index];
+            Ok(())
+        })
+        .unwrap_err();
+        assert!(error.starts_with("audio thread panicked"), "{}", error);
+        assert!(error.contains("index out of bounds"), "{}", error);
+    }
+    
+    #[test]
+    fn test_callback_panic_is_kept_and_stops_the_callback() {
+        let failure = CallbackFailure::new();
+        let mut calls = 0;
+        failure.run(|| calls += 1);
+        assert_eq!(failure.check(), Ok(()));
+        
+        failure.run(|| {
+            #[allow(clippy::useless_vec)]
+            let buffer = vec![0.0_f32; 4];
+            let index = buffer.len();
+            let _sample = buffer[index];
+        });
+        let error = failure.check().unwrap_err();
+        assert!(error.starts_with("stream callback panicked"), "{}", error);
+        assert!(error.contains("index out of bounds"), "{}", error);
+        
+        // Later callbacks are skipped rather than running on broken state
+        failure.run(|| calls += 1);
+        assert_eq!(calls, 1);
+    }
+}
@@ -0,0 +1,103 @@
+use std::time::Duration;
+
+/// Default input RMS below which the input counts as silent (-60dBFS)
+pub const DEFAULT_IDLE_THRESHOLD: f32 = 0.001;
+
+/// Default time the input has to stay silent before the processor idles
+pub const DEFAULT_IDLE_TIMEOUT_S: f32 = 300.0;
+
+/// Shortest and longest idle timeouts in seconds
+pub const MIN_IDLE_TIMEOUT_S: f32 = 1.0;
+pub const MAX_IDLE_TIMEOUT_S: f32 = 3600.0;
+
+/// What the audio loop should do after an input block
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IdleTransition {
+    /// Keep doing what it is doing
+    Stay,
+    /// The input has been silent for the timeout, stop the output
+    Sleep,
+    /// Signal is back, restart the output
+    Wake,
+}
+
+/// Decides when to idle the audio output on a silent input
+///
+/// The audio loop reports the RMS of every input block together with its duration.
+/// Once the input has stayed below the threshold for the timeout the detector goes
+/// idle; the first block at or above the threshold wakes it again.
+#[derive(Debug, Clone)]
+pub struct IdleDetector {
+    threshold: f32,
+    timeout: Duration,
+    quiet_for: Duration,
+    idle: bool,
+}
+
+impl IdleDetector {
+    /// Create a new detector that is not idle
+    pub fn new(threshold: f32, timeout_s: f32) -> Self {
+        Self {
+            threshold,
+            timeout: Duration::from_secs_f32(timeout_s.max(0.0)),
+            quiet_for: Duration::ZERO,
+            idle: false,
+        }
+    }
+    
+    /// Check whether the output should currently be stopped
+    pub fn is_idle(&self) -> bool {
+        self.idle
+    }
+    
+    /// Feed the RMS level of an input block lasting `duration`
+    pub fn update(&mut self, rms: f32, duration: Duration) -> IdleTransition {
+        if rms >= self.threshold {
+            self.quiet_for = Duration::ZERO;
+            if self.idle {
+                self.idle = false;
+                return IdleTransition::Wake;
+            }
+            return IdleTransition::Stay;
+        }
+        
+        self.quiet_for += duration;
+        if !self.idle && self.quiet_for >= self.timeout {
+            self.idle = true;
+            return IdleTransition::Sleep;
+        }
+        IdleTransition::Stay
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_idle_after_timeout_and_wake_on_signal() {
+        let mut detector = IdleDetector::new(0.01, 2.0);
+        let block = Duration::from_millis(500);
+        let readings = [
+            (0.1, IdleTransition::Stay),
+            // Three quiet blocks are not enough, a loud one starts the count again
+            (0.001, IdleTransition::Stay),
+            (0.001, IdleTransition::Stay),
+            (0.001, IdleTransition::Stay),
+            (0.05, IdleTransition::Stay),
+            (0.001, IdleTransition::Stay),
+            (0.001, IdleTransition::Stay),
+            (0.001, IdleTransition::Stay),
+            (0.001, IdleTransition::Sleep),
+            // Stays asleep while silent, wakes once, then carries on
+            (0.0, IdleTransition::Stay),
+            (0.02, IdleTransition::Wake),
+            (0.02, IdleTransition::Stay),
+        ];
+        
+        for (i, (rms, expected)) in readings.into_iter().enumerate() {
+            assert_eq!(detector.update(rms, block), expected, "reading {}", i);
+        }
+        assert!(!detector.is_idle());
+    }
+}
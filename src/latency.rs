@@ -0,0 +1,118 @@
+use std::sync::atomic::{AtomicU32, AtomicUsize, Ordering};
+
+/// Convert a number of frames to milliseconds at a sample rate
+pub fn frames_to_ms(frames: usize, sample_rate: u32) -> f32 {
+    if sample_rate == 0 {
+        return 0.0;
+    }
+    frames as f32 * 1000.0 / sample_rate as f32
+}
+
+/// Estimated round-trip latency, broken down by where the audio waits
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct LatencyReport {
+    /// Time to fill one input buffer before it is handed to the processor
+    pub input_ms: f32,
+    
+    /// Time processed audio is queued between the input and the output
+    pub processing_ms: f32,
+    
+    /// Time queued in the output buffer before it is played
+    pub output_ms: f32,
+    
+    /// Total round-trip latency in frames, to delay a parallel dry track by
+    pub frames: usize,
+}
+
+impl LatencyReport {
+    /// Estimate latency from buffer sizes in frames
+    pub fn from_frames(input_frames: usize, processing_frames: usize, output_frames: usize, sample_rate: u32) -> Self {
+        Self {
+            input_ms: frames_to_ms(input_frames, sample_rate),
+            processing_ms: frames_to_ms(processing_frames, sample_rate),
+            output_ms: frames_to_ms(output_frames, sample_rate),
+            frames: input_frames + processing_frames + output_frames,
+        }
+    }
+    
+    /// Total round-trip latency in milliseconds
+    pub fn total_ms(&self) -> f32 {
+        self.input_ms + self.processing_ms + self.output_ms
+    }
+    
+    /// Entries for status and meter reports
+    pub fn entries(&self) -> [(&'static str, f32); 4] {
+        [
+            ("latency_ms", self.total_ms()),
+            ("latency_input_ms", self.input_ms),
+            ("latency_processing_ms", self.processing_ms),
+            ("latency_output_ms", self.output_ms),
+        ]
+    }
+}
+
+/// Buffer sizes seen by the audio thread, shared lock-free for latency reports
+#[derive(Debug, Default)]
+pub struct BufferFrames {
+    /// Frames per input callback or read
+    pub input: AtomicUsize,
+    /// Frames waiting between input and output
+    pub processing: AtomicUsize,
+    /// Frames per output callback or in the output device buffer
+    pub output: AtomicUsize,
+    /// Sample rate the frames are counted at
+    pub sample_rate: AtomicU32,
+    /// Input rate converted to `sample_rate`, 0 when the devices agree
+    pub resampled_from: AtomicU32,
+}
+
+impl BufferFrames {
+    /// Forget the sizes of a previous run
+    pub fn clear(&self) {
+        self.input.store(0, Ordering::Relaxed);
+        self.processing.store(0, Ordering::Relaxed);
+        self.output.store(0, Ordering::Relaxed);
+        self.sample_rate.store(0, Ordering::Relaxed);
+        self.resampled_from.store(0, Ordering::Relaxed);
+    }
+    
+    /// Current latency estimate, `None` until the stream has processed audio
+    pub fn latency(&self) -> Option<LatencyReport> {
+        let input = self.input.load(Ordering::Relaxed);
+        let sample_rate = self.sample_rate.load(Ordering::Relaxed);
+        if input == 0 || sample_rate == 0 {
+            return None;
+        }
+        Some(LatencyReport::from_frames(
+            input,
+            self.processing.load(Ordering::Relaxed),
+            self.output.load(Ordering::Relaxed),
+            sample_rate,
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_latency_from_buffer_sizes() {
+        // 256 frames at 48kHz is 5.33ms per buffer
+        let report = LatencyReport::from_frames(256, 128, 512, 48000);
+        assert!((report.input_ms - 5.333).abs() < 1e-3);
+        assert!((report.processing_ms - 2.667).abs() < 1e-3);
+        assert!((report.output_ms - 10.667).abs() < 1e-3);
+        assert!((report.total_ms() - 18.667).abs() < 1e-3);
+        assert_eq!(report.frames, 896);
+        
+        let frames = BufferFrames::default();
+        assert_eq!(frames.latency(), None);
+        frames.input.store(256, Ordering::Relaxed);
+        frames.output.store(256, Ordering::Relaxed);
+        frames.sample_rate.store(48000, Ordering::Relaxed);
+        let report = frames.latency().unwrap();
+        assert_eq!(report.processing_ms, 0.0);
+        assert!((report.total_ms() - 10.667).abs() < 1e-3);
+    }
+}
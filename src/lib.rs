@@ -3,11 +3,44 @@
 //! This crate provides a high-performance audio processing system for guitar effects,
 //! specifically designed for stereo delay effects with cross-feedback distortion.
 
+// Tests build their configs from the default one field at a time and keep plain modulo checks
+
 pub mod audio_processor;
+pub mod automation;
+pub mod backend;
+pub mod batch;
+pub mod chain;
 pub mod config;
 pub mod delay;
+pub mod devices;
+pub mod diagnostics;
 pub mod distortion;
+pub mod double_track;
+pub mod dynamics;
 pub mod error;
+pub mod file_input;
+pub mod filters;
+pub mod footswitch;
+pub mod health;
+pub mod idle;
+pub mod latency;
+pub mod logging;
+pub mod macro_control;
+pub mod meter;
+pub mod metronome;
+pub mod null_processor;
+pub mod observer;
+pub mod processor_core;
+pub mod realtime;
+pub mod recorder;
+pub mod resample;
+pub mod ring_buffer;
+pub mod schema;
+pub mod shimmer;
+pub mod smoothing;
+#[cfg(feature = "spectral")]
+pub mod spectral;
+pub mod test_signal;
 pub mod web_server;
 #[cfg(target_os = "linux")]
 pub mod alsa_processor;
@@ -15,82 +48,151 @@ pub mod alsa_processor;
 
 
 /// Common trait for audio processors
+///
+/// Everything that does not depend on the device is implemented once, on the
+/// backend's `ProcessorCore`; a backend provides the core and its device I/O.
 pub trait AudioProcessorTrait: Send {
+    /// The state and behaviour shared by every backend
+    fn core(&self) -> &processor_core::ProcessorCore;
+    fn core_mut(&mut self) -> &mut processor_core::ProcessorCore;
+    
     fn start_audio(&mut self) -> std::result::Result<(), AudioProcessorError>;
-    fn stop_audio(&mut self) -> std::result::Result<(), AudioProcessorError>;
-    fn test_audio(&self) -> std::result::Result<(), AudioProcessorError>;
+    fn test_audio_with(&self, params: test_signal::TestToneParams) -> std::result::Result<(), AudioProcessorError>;
     fn get_status(&self) -> std::result::Result<std::collections::HashMap<String, String>, AudioProcessorError>;
-    fn set_stereo_delay_parameter(&mut self, param: &str, value: f32) -> std::result::Result<(), AudioProcessorError>;
-    fn set_distortion_type(&self, distortion_type: &str) -> std::result::Result<(), AudioProcessorError>;
-    fn reset_delay(&self) -> std::result::Result<(), AudioProcessorError>;
-}
-
-// Implement the trait for AudioProcessor
-impl AudioProcessorTrait for audio_processor::AudioProcessor {
-    fn start_audio(&mut self) -> std::result::Result<(), AudioProcessorError> {
-        self.start_audio()
-    }
+    fn get_meters(&self) -> std::result::Result<std::collections::HashMap<String, f32>, AudioProcessorError>;
+    fn list_devices(&self) -> devices::DeviceList;
     
     fn stop_audio(&mut self) -> std::result::Result<(), AudioProcessorError> {
-        self.stop_audio()
+        self.core_mut().stop_audio()
+    }
+    
+    fn stop_with_tails(&mut self) -> std::result::Result<(), AudioProcessorError> {
+        self.core_mut().stop_with_tails()
+    }
+    
+    fn ring_out(&mut self) -> std::result::Result<(), AudioProcessorError> {
+        self.core_mut().ring_out()
     }
     
     fn test_audio(&self) -> std::result::Result<(), AudioProcessorError> {
-        self.test_audio()
+        self.test_audio_with(test_signal::TestToneParams::default())
     }
     
-    fn get_status(&self) -> std::result::Result<std::collections::HashMap<String, String>, AudioProcessorError> {
-        self.get_status()
+    fn get_health(&self) -> health::HealthStatus {
+        self.core().get_health()
     }
     
     fn set_stereo_delay_parameter(&mut self, param: &str, value: f32) -> std::result::Result<(), AudioProcessorError> {
-        self.set_stereo_delay_parameter(param, value)
+        self.core_mut().set_stereo_delay_parameter(param, value)
+    }
+    
+    fn set_parameters(&mut self, params: &std::collections::HashMap<String, f32>) -> std::result::Result<(), AudioProcessorError> {
+        self.core_mut().set_parameters(params)
+    }
+    
+    fn get_parameters(&self) -> std::result::Result<std::collections::HashMap<String, f32>, AudioProcessorError> {
+        self.core().get_parameters()
     }
     
     fn set_distortion_type(&self, distortion_type: &str) -> std::result::Result<(), AudioProcessorError> {
-        self.set_distortion_type(distortion_type)
+        self.core().set_distortion_type(distortion_type)
     }
     
     fn reset_delay(&self) -> std::result::Result<(), AudioProcessorError> {
-        self.reset_delay()
+        self.core().reset_delay()
     }
-}
-
-// Implement the trait for AlsaAudioProcessor (Linux only)
-#[cfg(target_os = "linux")]
-impl AudioProcessorTrait for alsa_processor::AlsaAudioProcessor {
-    fn start_audio(&mut self) -> std::result::Result<(), AudioProcessorError> {
-        self.start_audio()
+    
+    fn panic(&mut self, restore_defaults: bool) -> std::result::Result<(), AudioProcessorError> {
+        self.core_mut().panic(restore_defaults)
     }
     
-    fn stop_audio(&mut self) -> std::result::Result<(), AudioProcessorError> {
-        self.stop_audio()
+    fn get_effect_chain(&self) -> std::result::Result<Vec<delay::EffectStage>, AudioProcessorError> {
+        self.core().get_effect_chain()
     }
     
-    fn test_audio(&self) -> std::result::Result<(), AudioProcessorError> {
-        self.test_audio()
+    fn get_config(&self) -> &AudioConfig {
+        self.core().get_config()
     }
     
-    fn get_status(&self) -> std::result::Result<std::collections::HashMap<String, String>, AudioProcessorError> {
-        self.get_status()
+    /// Check the devices and configuration and measure an impulse through the current settings
+    fn diagnose(&self) -> diagnostics::DiagnosticReport {
+        diagnostics::diagnose(self.get_config(), &self.list_devices())
     }
     
-    fn set_stereo_delay_parameter(&mut self, param: &str, value: f32) -> std::result::Result<(), AudioProcessorError> {
-        self.set_stereo_delay_parameter(param, value)
+    /// Switch the input and/or output device, restarting audio if it is running
+    ///
+    /// `None` keeps the current device; an empty name goes back to automatic selection.
+    fn set_devices(&mut self, input_device: Option<String>, output_device: Option<String>) -> std::result::Result<(), AudioProcessorError> {
+        let was_running = *self.core().is_running.read();
+        if was_running {
+            self.stop_audio()?;
+        }
+        
+        self.core_mut().set_device_names(input_device, output_device);
+        
+        if was_running {
+            self.start_audio()?;
+        }
+        
+        Ok(())
     }
     
-    fn set_distortion_type(&self, distortion_type: &str) -> std::result::Result<(), AudioProcessorError> {
-        self.set_distortion_type(distortion_type)
+    fn export_state(&self) -> std::result::Result<String, AudioProcessorError> {
+        self.core().export_state()
     }
     
-    fn reset_delay(&self) -> std::result::Result<(), AudioProcessorError> {
-        self.reset_delay()
+    fn import_state(&mut self, state: &str) -> std::result::Result<(), AudioProcessorError> {
+        self.core_mut().import_state(state)
+    }
+    
+    fn on_parameter_change(&mut self, observer: observer::ParameterObserver) {
+        self.core_mut().on_parameter_change(observer)
+    }
+    
+    fn automation_command(&mut self, command: automation::AutomationCommand) -> std::result::Result<(), AudioProcessorError> {
+        self.core_mut().automation_command(command)
+    }
+    
+    fn get_automation(&self) -> automation::AutomationStatus {
+        self.core().get_automation()
+    }
+    
+    fn poll_automation(&mut self) -> std::result::Result<usize, AudioProcessorError> {
+        self.core_mut().poll_automation()
+    }
+    
+    fn get_session_stats(&self) -> meter::SessionStatsReport {
+        self.core().get_session_stats()
+    }
+    
+    fn reset_session_stats(&self) {
+        self.core().reset_session_stats()
+    }
+    
+    /// Get how often the ring between the callbacks ran dry or overflowed since the last reset
+    ///
+    /// Backends without a ring between callbacks never count anything.
+    fn get_buffer_xruns(&self) -> ring_buffer::XrunReport {
+        ring_buffer::XrunReport::default()
+    }
+    
+    /// Start counting underruns and overruns afresh
+    fn reset_buffer_xruns(&self) {}
+    
+    fn start_recording(&mut self, path: &str) -> std::result::Result<(), AudioProcessorError> {
+        self.core_mut().start_recording(path)
+    }
+    
+    fn stop_recording(&mut self) -> std::result::Result<recorder::RecordingSummary, AudioProcessorError> {
+        self.core_mut().stop_recording()
     }
 }
 
 // Re-export commonly used types
 pub use audio_processor::AudioProcessor;
+pub use null_processor::NullProcessor;
 pub use delay::StereoDelay;
 pub use distortion::{DistortionType, CrossFeedbackDistortion};
 pub use config::AudioConfig;
 pub use error::AudioProcessorError;
+pub use test_signal::{SignalKind, SignalLevel, TestSignal};
@@ -0,0 +1,114 @@
+use std::time::{Duration, Instant};
+
+/// Default window during which repeated identical messages are suppressed
+pub const DEFAULT_LOG_INTERVAL: Duration = Duration::from_secs(5);
+
+/// Rate limiter for log messages emitted from audio callbacks and loops
+///
+/// A persistent device error can fire thousands of times per second. This helper
+/// only lets an identical message through once per interval and reports how many
+/// repeats were suppressed in between.
+pub struct RateLimitedLog {
+    interval: Duration,
+    last_message: Option<String>,
+    last_emitted: Option<Instant>,
+    suppressed: u64,
+}
+
+impl RateLimitedLog {
+    /// Create a new rate limiter with the given suppression interval
+    pub fn new(interval: Duration) -> Self {
+        Self {
+            interval,
+            last_message: None,
+            last_emitted: None,
+            suppressed: 0,
+        }
+    }
+    
+    /// Decide whether a message should be logged now
+    ///
+    /// Returns the text to log (annotated with the number of suppressed repeats)
+    /// or `None` if the message is a repeat inside the current interval.
+    pub fn check(&mut self, message: &str) -> Option<String> {
+        self.check_at(message, Instant::now())
+    }
+    
+    /// Same as `check` but with an explicit timestamp
+    pub fn check_at(&mut self, message: &str, now: Instant) -> Option<String> {
+        let is_repeat = self.last_message.as_deref() == Some(message);
+        let within_interval = self
+            .last_emitted
+            .map(|last| now.duration_since(last) < self.interval)
+            .unwrap_or(false);
+        
+        if is_repeat && within_interval {
+            self.suppressed += 1;
+            return None;
+        }
+        
+        let text = if is_repeat && self.suppressed > 0 {
+            format!("{} (repeated {} more times)", message, self.suppressed)
+        } else {
+            message.to_string()
+        };
+        
+        self.last_message = Some(message.to_string());
+        self.last_emitted = Some(now);
+        self.suppressed = 0;
+        Some(text)
+    }
+    
+    /// Log a warning through the rate limiter
+    pub fn warn(&mut self, message: &str) {
+        if let Some(text) = self.check(message) {
+            log::warn!("{}", text);
+        }
+    }
+    
+    /// Log an error through the rate limiter
+    pub fn error(&mut self, message: &str) {
+        if let Some(text) = self.check(message) {
+            log::error!("{}", text);
+        }
+    }
+}
+
+impl Default for RateLimitedLog {
+    fn default() -> Self {
+        Self::new(DEFAULT_LOG_INTERVAL)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_repeated_messages_are_suppressed() {
+        let mut limiter = RateLimitedLog::new(Duration::from_secs(1));
+        let start = Instant::now();
+        
+        assert!(limiter.check_at("Input read error", start).is_some());
+        for i in 1..100 {
+            let now = start + Duration::from_millis(i);
+            assert!(limiter.check_at("Input read error", now).is_none());
+        }
+        
+        // A different message is let through immediately
+        assert!(limiter.check_at("Output write error", start + Duration::from_millis(200)).is_some());
+    }
+    
+    #[test]
+    fn test_repeat_is_reported_after_interval() {
+        let mut limiter = RateLimitedLog::new(Duration::from_secs(1));
+        let start = Instant::now();
+        
+        limiter.check_at("xrun", start);
+        limiter.check_at("xrun", start + Duration::from_millis(10));
+        limiter.check_at("xrun", start + Duration::from_millis(20));
+        
+        let text = limiter.check_at("xrun", start + Duration::from_secs(2));
+        assert_eq!(text.as_deref(), Some("xrun (repeated 2 more times)"));
+    }
+}
@@ -0,0 +1,92 @@
+use serde::{Deserialize, Serialize};
+
+/// Response curve of a macro mapping
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MacroCurve {
+    /// Parameter follows the macro directly
+    #[default]
+    Linear,
+    /// Slow start, most of the change happens at the top of the travel (t²)
+    Exponential,
+    /// Fast start, most of the change happens at the bottom of the travel (√t)
+    Logarithmic,
+}
+
+impl MacroCurve {
+    /// Shape a macro position (0.0 to 1.0)
+    pub fn apply(&self, position: f32) -> f32 {
+        let position = position.clamp(0.0, 1.0);
+        match self {
+            MacroCurve::Linear => position,
+            MacroCurve::Exponential => position * position,
+            MacroCurve::Logarithmic => position.sqrt(),
+        }
+    }
+}
+
+/// One parameter driven by the macro control
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct MacroMapping {
+    /// Parameter name as accepted by `set_stereo_delay_parameter`
+    pub parameter: String,
+    
+    /// Parameter value with the macro at 0.0
+    pub min: f32,
+    
+    /// Parameter value with the macro at 1.0
+    pub max: f32,
+    
+    /// How the parameter moves between `min` and `max`
+    #[serde(default)]
+    pub curve: MacroCurve,
+}
+
+impl MacroMapping {
+    /// Create a new macro mapping
+    pub fn new(parameter: &str, min: f32, max: f32, curve: MacroCurve) -> Self {
+        Self {
+            parameter: parameter.to_string(),
+            min,
+            max,
+            curve,
+        }
+    }
+    
+    /// Calculate the parameter value for a macro position (0.0 to 1.0)
+    pub fn value_at(&self, position: f32) -> f32 {
+        self.min + (self.max - self.min) * self.curve.apply(position)
+    }
+    
+    /// Default mapping that opens up into an ambient wash as the macro is turned up
+    pub fn ambient_wash() -> Vec<MacroMapping> {
+        vec![
+            MacroMapping::new("feedback", 0.2, 0.8, MacroCurve::Exponential),
+            MacroMapping::new("wet_mix", 0.3, 0.7, MacroCurve::Linear),
+            MacroMapping::new("stereo_width", 0.2, 1.0, MacroCurve::Logarithmic),
+        ]
+    }
+}
+
+/// Calculate every mapped (parameter, value) pair for a macro position
+pub fn macro_values(mappings: &[MacroMapping], position: f32) -> Vec<(String, f32)> {
+    mappings
+        .iter()
+        .map(|mapping| (mapping.parameter.clone(), mapping.value_at(position)))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_curves_share_endpoints() {
+        for curve in [MacroCurve::Linear, MacroCurve::Exponential, MacroCurve::Logarithmic] {
+            assert_eq!(curve.apply(0.0), 0.0);
+            assert_eq!(curve.apply(1.0), 1.0);
+        }
+        assert!(MacroCurve::Exponential.apply(0.5) < 0.5);
+        assert!(MacroCurve::Logarithmic.apply(0.5) > 0.5);
+    }
+}
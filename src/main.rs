@@ -1,14 +1,15 @@
-use rust_audio_processor::{config::AudioConfig, audio_processor::AudioProcessor, AudioProcessorTrait, web_server::WebServer};
-#[cfg(target_os = "linux")]
-use rust_audio_processor::alsa_processor::AlsaAudioProcessor;
+use rust_audio_processor::{config::AudioConfig, automation::{self, AutomationCommand}, backend, batch, diagnostics::DiagnosticReport, footswitch, test_signal::{TestToneParams, ToneWaveform}, AudioProcessorTrait, web_server::{self, WebServer}};
 use std::io::{self, Write};
 use std::env;
 use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::{Arc, Mutex};
-use chrono;
 
 #[tokio::main]
 async fn main() -> Result<(), Box<dyn std::error::Error>> {
+    // Initialise logging (override the level with RUST_LOG, e.g. RUST_LOG=debug)
+    env_logger::Builder::from_env(env_logger::Env::default().default_filter_or("info")).init();
+    
     println!("🎸 Rust Audio Processor for Guitar Stereo Delay Effects");
     println!("=====================================================\n");
     
@@ -16,11 +17,19 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let args: Vec<String> = env::args().collect();
     let is_daemon_mode = args.contains(&"--daemon".to_string());
     let enable_web = args.contains(&"--web".to_string());
+    let use_null_backend = args.contains(&"--null".to_string());
+    let input_file = args.iter().position(|arg| arg == "--input-file").and_then(|i| args.get(i + 1));
     let web_port = args.iter().position(|arg| arg == "--web-port")
         .and_then(|i| args.get(i + 1))
         .and_then(|s| s.parse::<u16>().ok())
         .unwrap_or(1051);
+    let web_port_retries = args.iter().position(|arg| arg == "--web-port-retries")
+        .and_then(|i| args.get(i + 1))
+        .and_then(|s| s.parse::<u16>().ok())
+        .unwrap_or(web_server::DEFAULT_PORT_RETRIES);
     let _device_arg = args.iter().position(|arg| arg == "--device").map(|i| args.get(i + 1));
+    let batch_dir = args.iter().position(|arg| arg == "--batch").and_then(|i| args.get(i + 1));
+    let batch_out_dir = args.iter().position(|arg| arg == "--batch-out").and_then(|i| args.get(i + 1));
     
     // Show help if requested
     if args.contains(&"--help".to_string()) || args.contains(&"-h".to_string()) {
@@ -29,27 +38,65 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     }
     
     // Load configuration from file or use default
-    let config = AudioConfig::load_or_default("pi_config.json");
+    let mut config = AudioConfig::load_or_default("pi_config.json");
+    if let Some(input_file) = input_file {
+        config.input_file = Some(input_file.clone());
+    }
     println!("📋 Loaded configuration:");
     println!("   Sample rate: {} Hz", config.sample_rate);
     println!("   Buffer size: {}", config.buffer_size);
     println!("   Input device: {:?}", config.input_device);
     println!("   Output device: {:?}", config.output_device);
+    if let Some(input_file) = &config.input_file {
+        println!("   Input file: {}", input_file);
+    }
     
-    // Create audio processor with loaded configuration
-    #[cfg(target_os = "linux")]
-    let processor = AlsaAudioProcessor::with_config(config)?;
-    #[cfg(not(target_os = "linux"))]
-    let processor = AudioProcessor::with_config(config)?;
+    // Offline batch processing doesn't need an audio device
+    if let Some(batch_dir) = batch_dir {
+        let input_dir = std::path::Path::new(batch_dir);
+        let output_dir = batch_out_dir
+            .map(std::path::PathBuf::from)
+            .unwrap_or_else(|| input_dir.join("processed"));
+        println!("📁 Batch processing {} -> {}", input_dir.display(), output_dir.display());
+        
+        let summary = batch::process_directory(input_dir, &output_dir, &config)?;
+        for path in &summary.succeeded {
+            println!("  ✅ {}", path.display());
+        }
+        for (path, reason) in &summary.failed {
+            println!("  ❌ {}: {}", path.display(), reason);
+        }
+        println!("📊 {}", summary.get_info());
+        return Ok(());
+    }
+    
+    // Create audio processor on the first backend that can open the devices,
+    // or without any device when asked for the null backend
+    let processor = if use_null_backend {
+        backend::open_null_processor(config)?
+    } else {
+        backend::open_processor(config)?
+    };
     
     // Wrap processor in Arc<Mutex> for sharing between threads
-    let processor_arc = Arc::new(Mutex::new(Box::new(processor) as Box<dyn AudioProcessorTrait + Send>));
+    let processor_arc = Arc::new(Mutex::new(processor));
+    
+    // Play back recorded automation whichever mode is running
+    spawn_automation_player(processor_arc.clone());
+    
+    // Footswitches on the Pi GPIO header, when configured
+    let footswitch_config = processor_arc.lock().unwrap().get_config().footswitch.clone();
+    if footswitch_config.enabled {
+        if let Err(e) = footswitch::spawn_footswitch(processor_arc.clone(), &footswitch_config) {
+            println!("⚠️  Footswitch disabled: {}", e);
+        }
+    }
     
-    // Test the audio processing
+    // Test the audio processing, silently so starting up does not beep
     println!("Testing audio processing...");
     {
         let processor_guard = processor_arc.lock().unwrap();
-        match processor_guard.test_audio() {
+        match processor_guard.test_audio_with(TestToneParams { play: false, ..Default::default() }) {
             Ok(_) => println!("✅ Audio test completed successfully"),
             Err(e) => {
                 println!("⚠️  Audio test failed: {}", e);
@@ -64,7 +111,7 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
         daemon_mode(processor_arc)?;
     } else if enable_web {
         println!("🌐 Running with web interface...");
-        web_mode(processor_arc, web_port).await?;
+        web_mode(processor_arc, web_port, web_port_retries).await?;
     } else {
         println!("🎛️  Running in interactive mode...");
         interactive_mode(processor_arc)?;
@@ -73,6 +120,17 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Apply due automation events every tick on a background thread
+fn spawn_automation_player(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>) {
+    std::thread::spawn(move || loop {
+        std::thread::sleep(automation::AUTOMATION_TICK);
+        let mut processor_guard = processor.lock().unwrap();
+        if let Err(e) = processor_guard.poll_automation() {
+            println!("⚠️  Automation playback failed: {}", e);
+        }
+    });
+}
+
 fn show_cli_help() {
     println!("🎸 Rust Audio Processor - Command Line Options");
     println!("===============================================");
@@ -84,7 +142,12 @@ fn show_cli_help() {
     println!("  --daemon             Run in daemon mode (non-interactive)");
     println!("  --web                Run with web interface");
     println!("  --web-port <port>    Web interface port (default: 1051)");
+    println!("  --web-port-retries <n>  Following ports to try if the port is in use (default: {})", web_server::DEFAULT_PORT_RETRIES);
     println!("  --device <device>    Specify audio device (e.g., hw:2,0)");
+    println!("  --null               Run the effect without an audio device (also used when none is found)");
+    println!("  --input-file <wav>   Loop a WAV file through the live path instead of the input device");
+    println!("  --batch <dir>        Process every .wav in <dir> offline and exit");
+    println!("  --batch-out <dir>    Output directory for --batch (default: <dir>/processed)");
     println!();
     println!("Examples:");
     println!("  cargo run --release                    # Interactive mode");
@@ -92,21 +155,27 @@ fn show_cli_help() {
     println!("  cargo run --release --web              # Web interface mode");
     println!("  cargo run --release --web --web-port 9090  # Custom port");
     println!("  cargo run --release --device hw:2,0    # Use specific device");
+    println!("  cargo run --release --web --null       # Web interface without a sound card");
+    println!("  cargo run --release --batch ./stems    # Process a folder of stems");
     println!();
     println!("Interactive Commands:");
     println!("  start               - Start real-time audio processing");
     println!("  stop                - Stop real-time audio processing");
     println!("  reset               - Reset delay buffers (clear feedback)");
+    println!("  panic               - Clear every effect's state (panic defaults: also reset parameters)");
     println!("  status              - Show current system status");
-    println!("  test                - Run audio test");
+    println!("  diagnose            - Check devices and config and measure an impulse");
+    println!("  automation <cmd>    - Automation: record, play (loops), stop or clear");
+    println!("  test [key=value...] - Play a test tone (frequency, duration, amplitude, waveform)");
     println!("  quit/exit           - Exit the program");
     println!();
     println!("Parameter Settings (format: parameter=value):");
     println!("  bpm=120              - Tempo in beats per minute (20-300 BPM)");
-    println!("  feedback=0.3        - Feedback amount (0.0-0.9)");
+    println!("  feedback=0.3        - Feedback amount (-0.9 to 0.9, negative inverts repeats)");
     println!("  wet_mix=0.6         - Wet signal mix (0.0-1.0)");
+    println!("  mix_law=1           - Dry/wet crossfade law (0=linear, 1=equal power)");
     println!("  stereo_width=0.5    - Stereo width enhancement (0.0-1.0)");
-    println!("  cross_feedback=0.2  - Cross-feedback between channels (0.0-0.5)");
+    println!("  cross_feedback=0.2  - Cross-feedback between channels (0.0-0.9)");
 }
 
 fn daemon_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>) -> Result<(), Box<dyn std::error::Error>> {
@@ -145,10 +214,14 @@ fn daemon_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>) -> Re
         {
             let processor_guard = processor.lock().unwrap();
             if let Ok(status) = processor_guard.get_status() {
-                if status.get("audio_running").map(|s| s == "true").unwrap_or(false) {
+                if status.get("is_running").map(|s| s == "true").unwrap_or(false) {
                     // Audio is running, continue
                 } else {
-                    println!("⚠️  Audio processing stopped, attempting restart...");
+                    if let Some(last_error) = status.get("last_error") {
+                        println!("⚠️  Audio processing stopped ({}), attempting restart...", last_error);
+                    } else {
+                        println!("⚠️  Audio processing stopped, attempting restart...");
+                    }
                     drop(processor_guard); // Release lock before calling start_audio
                     let mut processor_guard = processor.lock().unwrap();
                     if let Err(e) = processor_guard.start_audio() {
@@ -163,7 +236,7 @@ fn daemon_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>) -> Re
     }
 }
 
-async fn web_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>, port: u16) -> Result<(), Box<dyn std::error::Error>> {
+async fn web_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>, port: u16, port_retries: u16) -> Result<(), Box<dyn std::error::Error>> {
     println!("🌐 Starting web interface mode...");
     println!("📊 Initial status:");
     show_status(&**processor.lock().unwrap())?;
@@ -186,24 +259,69 @@ async fn web_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>, po
     }
     
     println!("🌐 Starting web server...");
-    println!("📱 Web interface will be available at:");
+    let listener = match web_server::bind(port, port_retries) {
+        Ok(listener) => listener,
+        Err(e) => {
+            // Don't leave the audio thread running without a way to control it
+            println!("❌ Could not start web server: {}", e);
+            stop_audio_before_exit(&processor);
+            return Err(e.into());
+        }
+    };
+    let port = listener.local_addr()?.port();
+    println!("📱 Web interface available at:");
     println!("   http://localhost:{}", port);
     println!("   http://0.0.0.0:{} (from other devices on network)", port);
     
     // Create web server with the shared processor
-    let web_server = WebServer::new(processor);
-    web_server.start(port).await?;
+    let web_server = WebServer::new(processor.clone());
+    if let Err(e) = web_server.start(listener).await {
+        println!("❌ Web server stopped: {}", e);
+        stop_audio_before_exit(&processor);
+        return Err(e.into());
+    }
     
     Ok(())
 }
 
+fn stop_audio_before_exit(processor: &Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>) {
+    println!("🛑 Stopping audio processing...");
+    let mut processor_guard = processor.lock().unwrap();
+    if let Err(e) = processor_guard.stop_audio() {
+        println!("⚠️  Failed to stop audio processing: {}", e);
+    }
+}
+
 fn interactive_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>) -> Result<(), Box<dyn std::error::Error>> {
     println!("\n🎛️  Interactive Parameter Control");
     println!("Type 'help' for available commands, 'quit' to exit");
     println!("📱 Web interface changes will be shown here\n");
     
-    // Store last known parameter values for change detection
-    let mut last_status: HashMap<String, f32> = HashMap::new();
+    // Show changes from the web interface and automation as they are made. Changes
+    // typed here are set with `local_change` raised, so they are not echoed back.
+    let local_change = Arc::new(AtomicBool::new(false));
+    {
+        let mut processor_guard = processor.lock().unwrap();
+        let mut last_values: HashMap<String, f32> = HashMap::new();
+        if let Ok(status) = processor_guard.get_status() {
+            for (key, value_str) in &status {
+                if let Ok(value) = value_str.parse::<f32>() {
+                    last_values.insert(key.clone(), value);
+                }
+            }
+        }
+        
+        let last_values = Mutex::new(last_values);
+        let local_change = Arc::clone(&local_change);
+        processor_guard.on_parameter_change(Box::new(move |param, value| {
+            let last_value = last_values.lock().unwrap().insert(param.to_string(), value);
+            if let Some(last_value) = last_value {
+                if (value - last_value).abs() > 0.001 && !local_change.load(Ordering::Relaxed) {
+                    show_parameter_change_notification(param, last_value, value);
+                }
+            }
+        }));
+    }
     
     // Main interactive loop
     loop {
@@ -214,24 +332,6 @@ fn interactive_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>)
         io::stdin().read_line(&mut input)?;
         let input = input.trim();
         
-        // Check for parameter changes before processing input
-        {
-            let processor_guard = processor.lock().unwrap();
-            if let Ok(status) = processor_guard.get_status() {
-                for (key, value_str) in &status {
-                    if let Ok(value) = value_str.parse::<f32>() {
-                        if let Some(last_value) = last_status.get(key) {
-                            if (value - last_value).abs() > 0.001 {
-                                // Value changed! Show notification
-                                show_parameter_change_notification(key, *last_value, value);
-                            }
-                        }
-                        last_status.insert(key.clone(), value);
-                    }
-                }
-            }
-        }
-        
         match input {
             "help" => show_help(),
             "quit" | "exit" => break,
@@ -244,6 +344,19 @@ fn interactive_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>)
                 let processor_guard = processor.lock().unwrap();
                 processor_guard.test_audio()?;
             }
+            _ if input.starts_with("test ") => {
+                match parse_test_tone(input.trim_start_matches("test ")) {
+                    Ok(params) => {
+                        println!("Playing {:.0} Hz {} test tone...", params.frequency, params.waveform);
+                        let processor_guard = processor.lock().unwrap();
+                        match processor_guard.test_audio_with(params) {
+                            Ok(_) => println!("✅ Audio test completed"),
+                            Err(e) => println!("❌ Error: {}", e),
+                        }
+                    }
+                    Err(e) => println!("❓ {}", e),
+                }
+            }
             "start" => {
                 println!("Starting real-time audio processing...");
                 let mut processor_guard = processor.lock().unwrap();
@@ -255,35 +368,93 @@ fn interactive_mode(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>)
             "stop" => {
                 println!("Stopping real-time audio processing...");
                 let mut processor_guard = processor.lock().unwrap();
-                match processor_guard.stop_audio() {
+                match processor_guard.stop_with_tails() {
                     Ok(_) => println!("✅ Real-time audio processing stopped!"),
                     Err(e) => println!("❌ Error: {}", e),
                 }
             }
             "reset" => {
                 println!("Resetting delay buffers...");
-                let mut processor_guard = processor.lock().unwrap();
+                let processor_guard = processor.lock().unwrap();
                 match processor_guard.reset_delay() {
                     Ok(_) => println!("✅ Delay buffers reset!"),
                     Err(e) => println!("❌ Error: {}", e),
                 }
             }
+            "automation" => {
+                let processor_guard = processor.lock().unwrap();
+                let automation = processor_guard.get_automation();
+                println!("🎚️  Automation {}: {} event(s) over {:.1} beats", automation.state, automation.events.len(), automation.length);
+                for event in &automation.events {
+                    println!("  {:>7.2}  {}={:.3}", event.time, event.parameter, event.value);
+                }
+            }
+            _ if input.starts_with("automation ") => {
+                let name = input.trim_start_matches("automation ").trim();
+                match AutomationCommand::from_name(name) {
+                    Some(command) => {
+                        let mut processor_guard = processor.lock().unwrap();
+                        match processor_guard.automation_command(command) {
+                            Ok(_) => println!("✅ Automation {}", processor_guard.get_automation().state),
+                            Err(e) => println!("❌ Error: {}", e),
+                        }
+                    }
+                    None => println!("❓ Unknown automation command. Use record, play, stop or clear."),
+                }
+            }
+            "record stop" => {
+                let mut processor_guard = processor.lock().unwrap();
+                match processor_guard.stop_recording() {
+                    Ok(summary) => println!("✅ Recorded {:.1}s to {} ({} frames dropped)", summary.seconds, summary.path, summary.dropped_frames),
+                    Err(e) => println!("❌ Error: {}", e),
+                }
+            }
+            _ if input.starts_with("record start") => {
+                let path = input.trim_start_matches("record start").trim();
+                let path = if path.is_empty() {
+                    format!("recording_{}.wav", chrono::Local::now().format("%Y%m%d_%H%M%S"))
+                } else {
+                    path.to_string()
+                };
+                let mut processor_guard = processor.lock().unwrap();
+                match processor_guard.start_recording(&path) {
+                    Ok(_) => println!("⏺️  Recording output to {}", path),
+                    Err(e) => println!("❌ Error: {}", e),
+                }
+            }
+            "diagnose" => {
+                println!("Running diagnostics...");
+                let processor_guard = processor.lock().unwrap();
+                show_diagnostics(&processor_guard.diagnose());
+            }
+            "panic" | "panic defaults" => {
+                let restore_defaults = input == "panic defaults";
+                println!("Clearing all effect state...");
+                let mut processor_guard = processor.lock().unwrap();
+                match processor_guard.panic(restore_defaults) {
+                    Ok(_) if restore_defaults => println!("✅ Effects cleared and parameters reset to defaults!"),
+                    Ok(_) => println!("✅ Effects cleared!"),
+                    Err(e) => println!("❌ Error: {}", e),
+                }
+            }
             _ => {
                 if let Some((param, value)) = parse_parameter(input) {
                     let mut processor_guard = processor.lock().unwrap();
-                    match processor_guard.set_stereo_delay_parameter(param, value) {
-                        Ok(_) => {
-                            println!("✅ Set {} to {:.3}", param, value);
-                            // Update last known value
-                            last_status.insert(param.to_string(), value);
-                        }
+                    local_change.store(true, Ordering::Relaxed);
+                    let result = processor_guard.set_stereo_delay_parameter(param, value);
+                    local_change.store(false, Ordering::Relaxed);
+                    match result {
+                        Ok(_) => println!("✅ Set {} to {:.3}", param, value),
                         Err(e) => println!("❌ Error: {}", e),
                     }
                 } else if input.starts_with("distortion_type=") {
                     // Handle distortion type command
                     let distortion_type = input.strip_prefix("distortion_type=").unwrap_or("");
-                    let mut processor_guard = processor.lock().unwrap();
-                    match processor_guard.set_distortion_type(distortion_type) {
+                    let processor_guard = processor.lock().unwrap();
+                    local_change.store(true, Ordering::Relaxed);
+                    let result = processor_guard.set_distortion_type(distortion_type);
+                    local_change.store(false, Ordering::Relaxed);
+                    match result {
                         Ok(_) => println!("✅ Set distortion type to {}", distortion_type),
                         Err(e) => println!("❌ Error: {}", e),
                     }
@@ -346,22 +517,111 @@ fn show_help() {
     println!("\n📋 Available Commands:");
     println!("  help                    - Show this help message");
     println!("  status                  - Show current system status");
-    println!("  test                    - Run audio test");
+    println!("  test                    - Play a 1s 440Hz test tone through the delay");
+    println!("  test frequency=1000 duration=0.5 amplitude=0.2 waveform=square");
+    println!("                          - Play a custom test tone (sine, square, triangle or sawtooth)");
+    println!("  diagnose                - Check devices and config, measure tap times and feedback decay");
+    println!("  automation              - Show the recorded parameter automation");
+    println!("  automation record       - Record parameter changes, in beats at the current BPM");
+    println!("  automation play         - Loop the recorded changes in time with the BPM");
+    println!("  automation stop         - Stop recording or playing");
+    println!("  automation clear        - Forget the recorded changes");
     println!("  start                   - Start real-time audio processing");
     println!("  stop                    - Stop real-time audio processing");
+    println!("  record start [file.wav] - Record the output to a WAV file (default: recording_<time>.wav)");
+    println!("  record stop             - Stop recording and close the file");
     println!("  reset                   - Reset delay buffers (clear feedback)");
+    println!("  panic                   - Clear every effect's state, meters included");
+    println!("  panic defaults          - Panic and reset every effect parameter to its default");
     println!("  quit/exit               - Exit the program");
     println!("\n🎛️  Parameter Settings (format: parameter=value):");
     println!("  bpm=120              - Tempo in beats per minute (20-300 BPM)");
-    println!("  feedback=0.3            - Feedback amount (0.0-0.9)");
+    println!("  feedback=0.3            - Feedback amount (-0.9 to 0.9, negative inverts repeats)");
+    println!("  allow_self_oscillation=1 - Allow feedback up to ±1.1 (limiter-protected)");
     println!("  wet_mix=0.6             - Wet signal mix (0.0-1.0)");
+    println!("  mix_law=1               - Dry/wet crossfade law (0=linear, 1=equal power)");
+    println!("  interpolation=1         - Delay tap reads (0=linear, 1=none for stepped lo-fi repeats)");
+    println!("  ping_pong_mode=1        - Ping-pong style (0=swap taps, 1=bounce each echo between sides)");
+    println!("  ping_pong_start=1       - Side of the first bounced echo (0=left, 1=right)");
+    println!("  tap_pan_mode=1          - Tap panning (0=manual, 1=spread first left to last right, 2=alternate)");
+    println!("  tap_spread=1.0          - Width of the automatic tap panning (0.0-1.0)");
+    println!("  delay_glide=300         - Time in ms the delay taps glide to a new delay time");
+    println!("  bpm_glide_ms=2000       - Time in ms the delay taps slide to a new tempo (0 = delay glide)");
     println!("  stereo_width=0.5        - Stereo width enhancement (0.0-1.0)");
-    println!("  cross_feedback=0.2      - Cross-feedback between channels (0.0-0.5)");
+    println!("  mono_safe=1             - Limit the width so the delay survives a mono sum (0/1)");
+    println!("  mono_mode=1             - Run a single mono delay line to save CPU (0/1)");
+    println!("  wet_limiter_enabled=1   - Limit the repeats only, leaving the dry attack untouched (0/1)");
+    println!("  wet_limiter_threshold=-6 - Wet limiter threshold in dBFS (-40-0)");
+    println!("  double_track_enabled=1  - Thicken the dry with two short detuned copies panned apart (0/1)");
+    println!("  double_track_spread=1.0 - Pan spread of the double-tracked copies (0.0-1.0)");
+    println!("  double_track_detune=8   - Detune of the double-tracked copies in cents (0-25)");
+    println!("  auto_width_enabled=1    - Open the width as far as the correlation target allows (0/1)");
+    println!("  auto_width_target_correlation=0.3 - Correlation auto-width keeps (0.0-0.9)");
+    println!("  latency_compensation=1  - Delay the dry path to line up with the shortest tap (0/1)");
+    println!("  cross_feedback=0.2      - Cross-feedback between channels (0.0-0.9)");
+    println!("  feedback_tilt=0.5       - Tilt EQ on the repeats (-1.0 darker to 1.0 brighter)");
+    println!("  feedback_saturation=0.3 - Tape-style saturation of the repeats (0.0 to 1.0)");
+    println!("  feedback_color=2        - Feedback voicing (0=custom, 1=digital, 2=tape, 3=bbd, 4=dub)");
+    println!("  feedback_stage_order=1  - Feedback filters before (0) or after (1) the distortion");
+    println!("  feedback_bit_depth=8    - Bit depth the repeats are stored at (4 gritty to 24 clean)");
+    println!("  shimmer_amount=0.5      - Octave-up, diffused repeats, one knob for both (0.0-1.0)");
+    println!("  shimmer_pitch_mix=0.5   - Octave-up share of the repeats at full shimmer (0.0-1.0)");
+    println!("  shimmer_diffusion=0.7   - Diffusion of the repeats at full shimmer (0.0-1.0)");
+    println!("  delay_sweep_trigger=1   - Sweep the delay time on each note for a pitch dive (0/1)");
+    println!("  delay_sweep_amount=0.25 - Delay sweep as a fraction of the delay time (-0.5-0.5)");
+    println!("  delay_spread=0.1        - Shorten the left and lengthen the right delay by a fraction (0.0-0.5)");
+    println!("  feedback_hp_hz=300      - High-pass on the repeats in Hz (0 for full range)");
+    println!("  feedback_lp_hz=3000     - Low-pass on the repeats in Hz (20000 for full range)");
+    println!("  dub_cutoff=800          - Resonant dub filter on the repeats in Hz (20000 bypasses)");
+    println!("  dub_resonance=0.7       - Dub filter resonance (0.0-1.0, self-oscillates at 1.0)");
+    println!("  dub_lfo_rate=0.5        - Dub filter sweep rate in Hz (0 for a fixed cutoff)");
+    println!("  dub_lfo_depth=2         - Dub filter sweep in octaves either side (0.0-4.0)");
+    println!("  dub_lfo_sync=1          - Sync the dub filter sweep to the BPM (0/1)");
+    println!("  dub_lfo_division=1      - Synced sweep cycle as a fraction of a whole note");
+    println!("  meter_mode=0            - Output meter ballistics (0=VU, 1=PPM, 2=RMS)");
+    println!("  input_source=1          - Input gain staging for an instrument (0) or line-level (1) source");
+    println!("  di_sim_enabled=1        - Simulate amp input loading on a direct guitar signal (0/1)");
+    println!("  di_sim_resonance=0.6    - Resonant bump of the DI simulation (0.0-0.8)");
+    println!("  dc_block=1              - Remove DC offset from the input (0/1)");
+    println!("  eq_tilt=0.5             - Tone EQ tilt for the chain's eq stage (-1.0 to 1.0)");
+    println!("  eq_mix=0.5              - Wet/dry mix of the chain's eq stage (0.0-1.0)");
+    println!("  eq_dry_enabled=1        - Enable the EQ on the dry path (0/1)");
+    println!("  eq_dry_tilt=-0.3        - Dry path EQ tilt (-1.0 darker to 1.0 brighter)");
+    println!("  eq_wet_enabled=1        - Enable the EQ on the wet path (0/1)");
+    println!("  eq_wet_tilt=-0.5        - Wet path EQ tilt (-1.0 darker to 1.0 brighter)");
+    println!("  tails=1                 - Let echoes ring out on stop (0/1)");
+    println!("  fade_out_ms=20          - Output fade on stop and start in ms (0-1000)");
+    println!("  max_output_db=-6        - Hard safety ceiling on the output in dBFS (-40-0)");
+    println!("  freeze=1                - Hold the current repeats as a drone (0/1)");
+    println!("  freeze_crossfade_ms=50  - Crossfade into and out of freeze in ms (0-2000)");
+    println!("  freeze_kill_dry=1       - Mute the dry signal while frozen (0/1)");
+    println!("  auto_freeze_enabled=1   - Freeze when the input falls silent after a phrase (0/1)");
+    println!("  auto_freeze_threshold=-50 - Auto-freeze silence threshold in dBFS (-80 to -20)");
+    println!("  spectral_freeze=1       - Hold the spectrum of the moment as a pad (0/1, 'spectral' feature)");
+    println!("  monitor_dry_only=1      - Hear only the input, for setting levels (0/1)");
+    println!("  smoothing_feedback=20   - Ramp time in ms (left_delay, right_delay, feedback, wet_mix)");
+    println!("  macro=0.5               - Macro knob driving the configured parameter mappings (0.0-1.0)");
+    println!("  comp_enabled=1          - Output compressor on/off (0/1)");
+    println!("  comp_threshold=-18      - Compressor threshold in dBFS (-60-0)");
+    println!("  comp_ratio=4            - Compressor ratio (1-20)");
+    println!("  comp_attack=10          - Compressor attack in ms (0.1-200)");
+    println!("  comp_release=150        - Compressor release in ms (10-2000)");
+    println!("  comp_makeup=3           - Compressor makeup gain in dB (0-24)");
+    println!("  swell_enabled=1         - Swell each note in like a volume pedal (0/1)");
+    println!("  swell_time_ms=500       - Time for a note to swell to full level in ms (10-5000)");
+    println!("  swell_sensitivity=0.5   - Note onset sensitivity (0.0-1.0)");
+    println!("  metronome_enabled=1     - Click on every beat at the configured BPM (0/1)");
+    println!("  metronome_volume=0.5    - Click level (0.0-1.0)");
+    println!("  metronome_subdivision=2 - Clicks per beat (1-4)");
     println!("\n🎸 Distortion Commands:");
     println!("  distortion_type=soft_clip    - Set distortion type");
     println!("  distortion_enabled=1        - Enable/disable distortion (0/1)");
     println!("  distortion_drive=0.5        - Distortion drive amount (0.0-1.0)");
     println!("  distortion_mix=0.7          - Distortion wet/dry mix (0.0-1.0)");
+    println!("  distortion_placement=1      - Distortion stage (0=input, 1=feedback, 2=wet output)");
+    println!("  distortion_dry_bypass=1     - Keep the dry signal clear of the distortion (0/1)");
+    println!("  distortion_auto_gain_comp=1 - Keep the level constant across distortion settings (0/1)");
+    println!("  distortion_tone=-0.5    - Distortion tone (-1.0 darker to 1.0 brighter)");
     println!("  distortion_feedback_intensity=0.3 - How much distortion affects feedback (0.0-1.0)");
     println!("\n🎛️  Available Distortion Types:");
     println!("  soft_clip, hard_clip, tube, fuzz, bit_crush, waveshaper");
@@ -380,6 +640,24 @@ fn show_status(processor: &dyn AudioProcessorTrait) -> Result<(), Box<dyn std::e
     Ok(())
 }
 
+fn show_diagnostics(report: &DiagnosticReport) {
+    println!("\n🩺 Diagnostics:");
+    for check in &report.checks {
+        println!("  {} {}: {}", if check.passed { "✅" } else { "❌" }, check.name, check.detail);
+    }
+    for tap in &report.taps {
+        println!(
+            "  {} tap: {:.2}ms (expected {:.2}ms), decay {:.2}",
+            tap.channel, tap.measured_ms, tap.expected_ms, tap.decay
+        );
+    }
+    if report.passed {
+        println!("✅ All checks passed");
+    } else {
+        println!("❌ {} check(s) failed", report.failures().count());
+    }
+}
+
 fn parse_parameter(input: &str) -> Option<(&str, f32)> {
     if let Some(pos) = input.find('=') {
         let param = &input[..pos];
@@ -390,6 +668,24 @@ fn parse_parameter(input: &str) -> Option<(&str, f32)> {
     None
 }
 
+/// Parse the `key=value` options of the `test` command over the default tone
+fn parse_test_tone(input: &str) -> Result<TestToneParams, String> {
+    let mut params = TestToneParams::default();
+    for option in input.split_whitespace() {
+        let unknown = || format!("Unknown test option '{}'. Use frequency=, duration=, amplitude= or waveform=.", option);
+        let (key, value) = option.split_once('=').ok_or_else(unknown)?;
+        let number = |value: &str| value.parse::<f32>().map_err(|_| format!("Invalid {} '{}'", key, value));
+        match key {
+            "frequency" => params.frequency = number(value)?,
+            "duration" => params.duration = number(value)?,
+            "amplitude" => params.amplitude = number(value)?,
+            "waveform" => params.waveform = value.parse::<ToneWaveform>().map_err(|e| e.to_string())?,
+            _ => return Err(unknown()),
+        }
+    }
+    Ok(params)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -401,4 +697,15 @@ mod tests {
         assert_eq!(parse_parameter("invalid"), None);
         assert_eq!(parse_parameter("param=invalid"), None);
     }
+    
+    #[test]
+    fn test_test_tone_parsing() {
+        let params = parse_test_tone("frequency=1000 waveform=square").unwrap();
+        assert_eq!(params.frequency, 1000.0);
+        assert_eq!(params.waveform, ToneWaveform::Square);
+        assert_eq!(params.duration, TestToneParams::default().duration);
+        assert!(parse_test_tone("volume=0.5").unwrap_err().contains("volume"));
+        assert!(parse_test_tone("duration=long").is_err());
+        assert!(parse_test_tone("waveform=sqaure").unwrap_err().contains("sqaure"));
+    }
 }
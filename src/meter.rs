@@ -0,0 +1,431 @@
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
+use serde::{Deserialize, Serialize};
+use crate::dynamics::time_constant_coeff;
+
+/// Rise time of the VU meter to 99% of a steady level in milliseconds
+pub const VU_RISE_TIME_MS: f32 = 300.0;
+
+/// Attack integration time of the PPM meter in milliseconds
+pub const PPM_ATTACK_MS: f32 = 5.0;
+
+/// Release time constant of the PPM meter in milliseconds
+pub const PPM_RELEASE_MS: f32 = 1500.0;
+
+/// Averaging time constant of the RMS meter in milliseconds
+pub const RMS_WINDOW_MS: f32 = 300.0;
+
+/// Averaging time constant of the stereo correlation meter in milliseconds
+pub const CORRELATION_WINDOW_MS: f32 = 300.0;
+
+/// Output level at or above which a sample counts as clipped (0dBFS)
+pub const CLIP_LEVEL: f32 = 1.0;
+
+/// Meter ballistics
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MeterMode {
+    /// Volume unit meter: rectified average reaching 99% of a step in 300ms
+    #[default]
+    Vu,
+    /// Peak programme meter: fast attack, slow release
+    Ppm,
+    /// True RMS over a 300ms window
+    Rms,
+}
+
+impl MeterMode {
+    /// Map a numeric parameter value (0 = VU, 1 = PPM, 2 = RMS) to a meter mode
+    pub fn from_parameter(value: f32) -> Self {
+        match value.round() as i32 {
+            1 => MeterMode::Ppm,
+            2 => MeterMode::Rms,
+            _ => MeterMode::Vu,
+        }
+    }
+}
+
+impl From<&str> for MeterMode {
+    fn from(s: &str) -> Self {
+        match s.to_lowercase().as_str() {
+            "ppm" => MeterMode::Ppm,
+            "rms" => MeterMode::Rms,
+            _ => MeterMode::Vu,
+        }
+    }
+}
+
+impl std::fmt::Display for MeterMode {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            MeterMode::Vu => "vu",
+            MeterMode::Ppm => "ppm",
+            MeterMode::Rms => "rms",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Single-channel level meter with selectable ballistics
+pub struct ChannelMeter {
+    mode: MeterMode,
+    attack_coeff: f32,
+    release_coeff: f32,
+    state: f32,
+    sample_rate: u32,
+}
+
+impl ChannelMeter {
+    /// Create a new channel meter
+    pub fn new(mode: MeterMode, sample_rate: u32) -> Self {
+        let mut meter = Self {
+            mode,
+            attack_coeff: 0.0,
+            release_coeff: 0.0,
+            state: 0.0,
+            sample_rate,
+        };
+        meter.set_mode(mode);
+        meter
+    }
+    
+    /// Change the meter ballistics, clearing the current reading
+    pub fn set_mode(&mut self, mode: MeterMode) {
+        self.mode = mode;
+        let (attack_ms, release_ms) = match mode {
+            // 99% of a step is reached after ln(100) time constants
+            MeterMode::Vu => {
+                let tau = VU_RISE_TIME_MS / 100.0f32.ln();
+                (tau, tau)
+            }
+            MeterMode::Ppm => (PPM_ATTACK_MS, PPM_RELEASE_MS),
+            MeterMode::Rms => (RMS_WINDOW_MS, RMS_WINDOW_MS),
+        };
+        self.attack_coeff = time_constant_coeff(attack_ms, self.sample_rate);
+        self.release_coeff = time_constant_coeff(release_ms, self.sample_rate);
+        self.state = 0.0;
+    }
+    
+    /// Get the meter ballistics
+    pub fn get_mode(&self) -> MeterMode {
+        self.mode
+    }
+    
+    /// Feed one sample into the meter
+    pub fn process(&mut self, sample: f32) {
+        let input = match self.mode {
+            MeterMode::Rms => sample * sample,
+            MeterMode::Vu | MeterMode::Ppm => sample.abs(),
+        };
+        let coeff = if input > self.state {
+            self.attack_coeff
+        } else {
+            self.release_coeff
+        };
+        self.state = input + coeff * (self.state - input);
+    }
+    
+    /// Get the current reading (linear amplitude)
+    pub fn level(&self) -> f32 {
+        match self.mode {
+            MeterMode::Rms => self.state.sqrt(),
+            MeterMode::Vu | MeterMode::Ppm => self.state,
+        }
+    }
+    
+    /// Get the current reading in dBFS
+    pub fn level_db(&self) -> f32 {
+        linear_to_db(self.level())
+    }
+    
+    /// Clear the meter reading
+    pub fn reset(&mut self) {
+        self.state = 0.0;
+    }
+}
+
+/// Pair of channel meters for a stereo signal
+pub struct StereoMeter {
+    left: ChannelMeter,
+    right: ChannelMeter,
+}
+
+impl StereoMeter {
+    /// Create a new stereo meter
+    pub fn new(mode: MeterMode, sample_rate: u32) -> Self {
+        Self {
+            left: ChannelMeter::new(mode, sample_rate),
+            right: ChannelMeter::new(mode, sample_rate),
+        }
+    }
+    
+    /// Change the meter ballistics for both channels
+    pub fn set_mode(&mut self, mode: MeterMode) {
+        self.left.set_mode(mode);
+        self.right.set_mode(mode);
+    }
+    
+    /// Get the meter ballistics
+    pub fn get_mode(&self) -> MeterMode {
+        self.left.get_mode()
+    }
+    
+    /// Feed one stereo sample into the meters
+    pub fn process(&mut self, left: f32, right: f32) {
+        self.left.process(left);
+        self.right.process(right);
+    }
+    
+    /// Get the current (left, right) readings as linear amplitude
+    pub fn levels(&self) -> (f32, f32) {
+        (self.left.level(), self.right.level())
+    }
+    
+    /// Get the current (left, right) readings in dBFS
+    pub fn levels_db(&self) -> (f32, f32) {
+        (self.left.level_db(), self.right.level_db())
+    }
+    
+    /// Clear both meter readings
+    pub fn reset(&mut self) {
+        self.left.reset();
+        self.right.reset();
+    }
+}
+
+/// Stereo correlation meter
+///
+/// Reads +1 when both channels carry the same signal, 0 when they are unrelated
+/// and -1 when one is the inverse of the other, which cancels in a mono sum. The
+/// cross product and the power of each channel are averaged over
+/// `CORRELATION_WINDOW_MS`; their normalized ratio is the correlation.
+pub struct CorrelationMeter {
+    coeff: f32,
+    cross: f32,
+    left_power: f32,
+    right_power: f32,
+}
+
+impl CorrelationMeter {
+    /// Create a new correlation meter
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            coeff: time_constant_coeff(CORRELATION_WINDOW_MS, sample_rate),
+            cross: 0.0,
+            left_power: 0.0,
+            right_power: 0.0,
+        }
+    }
+    
+    /// Feed one stereo sample into the meter
+    pub fn process(&mut self, left: f32, right: f32) {
+        let coeff = self.coeff;
+        self.cross = left * right + coeff * (self.cross - left * right);
+        self.left_power = left * left + coeff * (self.left_power - left * left);
+        self.right_power = right * right + coeff * (self.right_power - right * right);
+    }
+    
+    /// Get the current correlation (-1.0 to 1.0), 0.0 while either channel is silent
+    pub fn correlation(&self) -> f32 {
+        if self.is_silent() {
+            return 0.0;
+        }
+        (self.cross / (self.left_power * self.right_power).sqrt()).clamp(-1.0, 1.0)
+    }
+    
+    /// Check whether either channel is too quiet for the correlation to mean anything
+    pub fn is_silent(&self) -> bool {
+        self.left_power * self.right_power < 1e-12
+    }
+    
+    /// Clear the meter reading
+    pub fn reset(&mut self) {
+        self.cross = 0.0;
+        self.left_power = 0.0;
+        self.right_power = 0.0;
+    }
+}
+
+/// Session statistics as served by `GET /api/stats`
+#[derive(Debug, Clone, Copy, PartialEq, Default, Serialize, Deserialize)]
+pub struct SessionStatsReport {
+    /// Loudest output sample of either channel since the last reset
+    pub peak_level: f32,
+    pub peak_level_db: f32,
+    
+    /// Output samples at or above `CLIP_LEVEL`, counted per channel
+    pub clip_count: u64,
+    
+    /// Loudest sample written into either delay line since the last reset
+    pub peak_feedback: f32,
+    pub peak_feedback_db: f32,
+}
+
+/// Peak and clip statistics over a whole session, shared lock-free with the audio thread
+///
+/// Unlike the meters these never decay, so a patch that overloads once in an
+/// hour still shows up. Levels are stored as the bits of a non-negative `f32`,
+/// which order the same way as the levels themselves.
+#[derive(Debug, Default)]
+pub struct SessionStats {
+    peak_level: AtomicU32,
+    clip_count: AtomicU64,
+    peak_feedback: AtomicU32,
+}
+
+impl SessionStats {
+    /// Create statistics with nothing recorded
+    pub fn new() -> Self {
+        Self::default()
+    }
+    
+    /// Record one stereo output sample
+    pub fn record_output(&self, left: f32, right: f32) {
+        let clipped = (left.abs() >= CLIP_LEVEL) as u64 + (right.abs() >= CLIP_LEVEL) as u64;
+        if clipped > 0 {
+            self.clip_count.fetch_add(clipped, Ordering::Relaxed);
+        }
+        raise_peak(&self.peak_level, left.abs().max(right.abs()));
+    }
+    
+    /// Record the level of the samples just written into the delay lines
+    pub fn record_feedback(&self, left: f32, right: f32) {
+        raise_peak(&self.peak_feedback, left.abs().max(right.abs()));
+    }
+    
+    /// Current statistics
+    pub fn report(&self) -> SessionStatsReport {
+        let peak_level = f32::from_bits(self.peak_level.load(Ordering::Relaxed));
+        let peak_feedback = f32::from_bits(self.peak_feedback.load(Ordering::Relaxed));
+        SessionStatsReport {
+            peak_level,
+            peak_level_db: linear_to_db(peak_level),
+            clip_count: self.clip_count.load(Ordering::Relaxed),
+            peak_feedback,
+            peak_feedback_db: linear_to_db(peak_feedback),
+        }
+    }
+    
+    /// Start a new session
+    pub fn reset(&self) {
+        self.peak_level.store(0, Ordering::Relaxed);
+        self.clip_count.store(0, Ordering::Relaxed);
+        self.peak_feedback.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Raise a stored peak to `level` if it is louder, ignoring NaN
+fn raise_peak(peak: &AtomicU32, level: f32) {
+    if level.is_finite() && level.to_bits() > peak.load(Ordering::Relaxed) {
+        peak.fetch_max(level.to_bits(), Ordering::Relaxed);
+    }
+}
+
+/// Convert a linear amplitude to dBFS (floored at -120dB)
+pub fn linear_to_db(level: f32) -> f32 {
+    if level <= 1e-6 {
+        -120.0
+    } else {
+        20.0 * level.log10()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn reading_after_ms(meter: &mut ChannelMeter, level: f32, ms: f32) -> f32 {
+        let samples = (ms * 0.001 * 48000.0) as usize;
+        for _ in 0..samples {
+            meter.process(level);
+        }
+        meter.level()
+    }
+    
+    #[test]
+    fn test_vu_step_response_follows_rise_time() {
+        let mut meter = ChannelMeter::new(MeterMode::Vu, 48000);
+        let tau = VU_RISE_TIME_MS / 100.0f32.ln();
+        
+        // One time constant in, the reading is ~63% of the step
+        let after_tau = reading_after_ms(&mut meter, 1.0, tau);
+        assert!((after_tau - 0.632).abs() < 0.03, "after one time constant: {}", after_tau);
+        
+        // At the rise time it has reached ~99%
+        let after_rise = reading_after_ms(&mut meter, 1.0, VU_RISE_TIME_MS - tau);
+        assert!((after_rise - 0.99).abs() < 0.01, "after rise time: {}", after_rise);
+    }
+    
+    #[test]
+    fn test_ppm_attacks_fast_and_releases_slowly() {
+        let mut meter = ChannelMeter::new(MeterMode::Ppm, 48000);
+        let attacked = reading_after_ms(&mut meter, 1.0, 20.0);
+        assert!(attacked > 0.95);
+        
+        let released = reading_after_ms(&mut meter, 0.0, 100.0);
+        assert!(released > 0.9);
+    }
+    
+    #[test]
+    fn test_rms_reads_sine_level() {
+        let mut meter = ChannelMeter::new(MeterMode::Rms, 48000);
+        for i in 0..48000 {
+            let t = i as f32 / 48000.0;
+            meter.process((2.0 * std::f32::consts::PI * 440.0 * t).sin());
+        }
+        assert!((meter.level() - std::f32::consts::FRAC_1_SQRT_2).abs() < 0.02);
+    }
+    
+    #[test]
+    fn test_correlation_of_identical_inverted_and_unrelated_channels() {
+        let mut state = 0x2545f491u32;
+        let mut noise = move || {
+            state ^= state << 13;
+            state ^= state >> 17;
+            state ^= state << 5;
+            state as f32 / u32::MAX as f32 * 2.0 - 1.0
+        };
+        
+        let mut correlation_of = |right_of: &dyn Fn(f32, f32) -> f32| {
+            let mut meter = CorrelationMeter::new(48000);
+            for _ in 0..48000 {
+                let (left, other) = (noise(), noise());
+                meter.process(left, right_of(left, other));
+            }
+            meter.correlation()
+        };
+        
+        let identical = correlation_of(&|left, _| left);
+        let inverted = correlation_of(&|left, _| -0.5 * left);
+        let unrelated = correlation_of(&|_, other| other);
+        assert!(identical > 0.99, "identical: {}", identical);
+        assert!(inverted < -0.99, "inverted: {}", inverted);
+        assert!(unrelated.abs() < 0.1, "unrelated: {}", unrelated);
+        
+        assert_eq!(CorrelationMeter::new(48000).correlation(), 0.0);
+    }
+    
+    #[test]
+    fn test_session_stats_count_occasional_clips() {
+        let stats = SessionStats::new();
+        
+        // A quiet sine with a clipped burst on one channel every 1000 samples
+        let mut expected_clips = 0;
+        for i in 0..48000 {
+            let sample = 0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin();
+            let left = if i % 1000 == 0 { 1.3 } else { sample };
+            let right = if i % 16000 == 0 { -1.0 } else { sample };
+            expected_clips += (left.abs() >= CLIP_LEVEL) as u64 + (right.abs() >= CLIP_LEVEL) as u64;
+            stats.record_output(left, right);
+            stats.record_feedback(sample * 0.8, sample * 0.4);
+        }
+        
+        let report = stats.report();
+        assert_eq!(report.clip_count, expected_clips);
+        assert_eq!(report.clip_count, 51);
+        assert_eq!(report.peak_level, 1.3);
+        assert!((report.peak_feedback - 0.4).abs() < 1e-3, "peak feedback: {}", report.peak_feedback);
+        
+        stats.reset();
+        assert_eq!(stats.report(), SessionStatsReport { peak_level_db: -120.0, peak_feedback_db: -120.0, ..Default::default() });
+    }
+}
@@ -0,0 +1,199 @@
+use crate::chain::{unknown_parameter, AudioStage};
+use crate::dynamics::time_constant_coeff;
+
+/// Tempo the metronome clicks at when no BPM has been configured
+pub const DEFAULT_METRONOME_BPM: f32 = 120.0;
+
+/// Most clicks the metronome can play per beat
+pub const MAX_METRONOME_SUBDIVISION: u32 = 4;
+
+/// Length of one click in milliseconds
+const CLICK_MS: f32 = 15.0;
+
+/// Time constant of the click's exponential decay in milliseconds
+const CLICK_DECAY_MS: f32 = 3.0;
+
+/// Pitch of the click on the beat and on the subdivisions in between
+const ACCENT_HZ: f32 = 1500.0;
+const SUBDIVISION_HZ: f32 = 1000.0;
+
+/// Metronome click for practicing with the tempo-synced delay
+///
+/// Counts samples at the audio sample rate, the same clock the delay lines run on,
+/// so the clicks never drift against tempo-synced repeats. Each click is a short
+/// decaying sine burst, higher pitched on the beat than on the subdivisions.
+#[derive(Debug, Clone)]
+pub struct Metronome {
+    sample_rate: u32,
+    bpm: f32,
+    subdivision: u32,
+    volume: f32,
+    // Samples until the next click is due
+    countdown: f64,
+    // Subdivision the next click falls on, 0 is the beat
+    tick: u32,
+    // Samples since the current click started, `None` between clicks
+    click_age: Option<usize>,
+    click_hz: f32,
+    click_samples: usize,
+    decay_coeff: f32,
+}
+
+impl Metronome {
+    /// Create a new metronome that clicks on its first sample
+    ///
+    /// # Arguments
+    /// * `bpm` - Tempo in beats per minute
+    /// * `subdivision` - Clicks per beat (1 to `MAX_METRONOME_SUBDIVISION`)
+    /// * `volume` - Click level (0.0 to 1.0)
+    /// * `sample_rate` - Sample rate in Hz
+    pub fn new(bpm: f32, subdivision: u32, volume: f32, sample_rate: u32) -> Self {
+        let mut metronome = Self {
+            sample_rate,
+            bpm: DEFAULT_METRONOME_BPM,
+            subdivision: 1,
+            volume: 0.0,
+            countdown: 0.0,
+            tick: 0,
+            click_age: None,
+            click_hz: ACCENT_HZ,
+            click_samples: (CLICK_MS * 0.001 * sample_rate as f32) as usize,
+            decay_coeff: time_constant_coeff(CLICK_DECAY_MS, sample_rate),
+        };
+        metronome.set_bpm(bpm);
+        metronome.set_subdivision(subdivision);
+        metronome.set_volume(volume);
+        metronome
+    }
+    
+    /// Set the tempo in beats per minute, staying in phase with the current beat
+    pub fn set_bpm(&mut self, bpm: f32) {
+        let previous_interval = self.interval();
+        self.bpm = bpm.clamp(20.0, 300.0);
+        self.countdown *= self.interval() / previous_interval;
+    }
+    
+    /// Get the tempo in beats per minute
+    pub fn get_bpm(&self) -> f32 {
+        self.bpm
+    }
+    
+    /// Set the number of clicks per beat (1 to `MAX_METRONOME_SUBDIVISION`)
+    pub fn set_subdivision(&mut self, subdivision: u32) {
+        let previous_interval = self.interval();
+        self.subdivision = subdivision.clamp(1, MAX_METRONOME_SUBDIVISION);
+        self.countdown *= self.interval() / previous_interval;
+        self.tick %= self.subdivision;
+    }
+    
+    /// Get the number of clicks per beat
+    pub fn get_subdivision(&self) -> u32 {
+        self.subdivision
+    }
+    
+    /// Set the click level (0.0 to 1.0)
+    pub fn set_volume(&mut self, volume: f32) {
+        self.volume = volume.clamp(0.0, 1.0);
+    }
+    
+    /// Get the click level
+    pub fn get_volume(&self) -> f32 {
+        self.volume
+    }
+    
+    /// Samples between two clicks
+    fn interval(&self) -> f64 {
+        self.sample_rate as f64 * 60.0 / (self.bpm as f64 * self.subdivision as f64)
+    }
+    
+    /// Generate the next click sample
+    pub fn process(&mut self) -> f32 {
+        // Keep the fractional remainder so the clicks stay on the beat grid
+        if self.countdown < 0.5 {
+            self.countdown += self.interval();
+            self.click_hz = if self.tick == 0 { ACCENT_HZ } else { SUBDIVISION_HZ };
+            self.click_age = Some(0);
+            self.tick = (self.tick + 1) % self.subdivision;
+        }
+        self.countdown -= 1.0;
+        
+        let age = match self.click_age {
+            Some(age) => age,
+            None => return 0.0,
+        };
+        self.click_age = if age + 1 < self.click_samples { Some(age + 1) } else { None };
+        
+        let phase = 2.0 * std::f32::consts::PI * self.click_hz * age as f32 / self.sample_rate as f32;
+        self.volume * self.decay_coeff.powi(age as i32) * phase.cos()
+    }
+    
+    /// Restart on a beat, clicking on the next sample
+    pub fn reset(&mut self) {
+        self.countdown = 0.0;
+        self.tick = 0;
+        self.click_age = None;
+    }
+}
+
+/// Mixes the click into both channels
+impl AudioStage for Metronome {
+    fn name(&self) -> &'static str {
+        "metronome"
+    }
+    
+    fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let click = Metronome::process(self);
+        (left + click, right + click)
+    }
+    
+    fn reset(&mut self) {
+        Metronome::reset(self);
+    }
+    
+    fn set_param(&mut self, param: &str, value: f32) -> Result<(), crate::AudioProcessorError> {
+        match param {
+            "bpm" => self.set_bpm(value),
+            "subdivision" => self.set_subdivision(value.round().max(1.0) as u32),
+            "volume" => self.set_volume(value),
+            _ => return Err(unknown_parameter(param, value)),
+        }
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    /// Samples at which a click starts
+    fn click_starts(metronome: &mut Metronome, len: usize) -> Vec<usize> {
+        let mut starts = Vec::new();
+        let mut silent = true;
+        for i in 0..len {
+            let sample = metronome.process();
+            if silent && sample != 0.0 {
+                starts.push(i);
+            }
+            silent = sample == 0.0;
+        }
+        starts
+    }
+    
+    #[test]
+    fn test_clicks_land_on_the_beat_grid() {
+        // 120 BPM in eighths at 48kHz is a click every 12000 samples
+        let mut metronome = Metronome::new(120.0, 2, 0.5, 48000);
+        let starts = click_starts(&mut metronome, 48000 * 4);
+        let expected: Vec<usize> = (0..16).map(|k| k * 12000).collect();
+        assert_eq!(starts, expected);
+        
+        // A tempo that does not divide the sample rate must not drift either
+        let mut metronome = Metronome::new(97.0, 1, 0.5, 44100);
+        let interval = 44100.0 * 60.0 / 97.0;
+        let starts = click_starts(&mut metronome, 44100 * 60);
+        assert_eq!(starts.len(), 97);
+        for (k, start) in starts.iter().enumerate() {
+            assert!((*start as f64 - k as f64 * interval).abs() <= 0.5, "click {} at {}", k, start);
+        }
+    }
+}
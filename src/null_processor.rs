@@ -0,0 +1,284 @@
+use std::path::Path;
+use std::sync::{Arc, Mutex};
+use parking_lot::RwLock;
+use crate::backend::AudioBackend;
+use crate::config::AudioConfig;
+use crate::delay::StereoDelay;
+use crate::devices::DeviceList;
+use crate::error::AudioProcessorError;
+use crate::file_input::{run_input_loop, InputLoop};
+use crate::health::Heartbeat;
+use crate::processor_core::ProcessorCore;
+use crate::test_signal::{TestSignal, TestToneParams};
+use crate::AudioProcessorTrait;
+use log::info;
+
+/// Audio processor that runs the full effect chain without any sound card
+///
+/// A background thread feeds blocks of silence, or a looped test signal or input
+/// file, through the delay at the configured sample rate and buffer size, paced in
+/// real time, and discards the output. Meters, recording, automation and health all behave
+/// as with a real device, so the web UI and the parameter handling can be used on
+/// headless machines and in CI.
+pub struct NullProcessor {
+    core: ProcessorCore,
+    // Looped in place of device input from the next start, silence when unset
+    input_signal: Option<TestSignal>,
+}
+
+impl NullProcessor {
+    /// Create a new null processor with default configuration
+    pub fn new() -> Result<Self, AudioProcessorError> {
+        Self::with_config(AudioConfig::default())
+    }
+    
+    /// Create a new null processor with custom configuration
+    ///
+    /// The device names in the configuration are kept but never opened.
+    pub fn with_config(config: AudioConfig) -> Result<Self, AudioProcessorError> {
+        Ok(Self {
+            core: ProcessorCore::new(AudioBackend::Null, config)?,
+            input_signal: None,
+        })
+    }
+    
+    /// Loop a test signal as the input instead of silence, from the next start
+    pub fn set_input_signal(&mut self, signal: Option<TestSignal>) {
+        self.input_signal = signal;
+    }
+    
+    /// Process blocks of synthetic input in real time until stopped
+    fn run_null_stream(
+        config: AudioConfig,
+        input: Option<InputLoop>,
+        stereo_delay: Arc<Mutex<StereoDelay>>,
+        is_running: Arc<RwLock<bool>>,
+        heartbeat: Arc<Heartbeat>,
+    ) -> Result<(), AudioProcessorError> {
+        info!("🎵 Starting null audio processing - no device is opened");
+        
+        // The output is discarded, as there is no device to play it on
+        let blocks_processed = run_input_loop(&config, input, &stereo_delay, &is_running, &heartbeat, |_| Ok(()))?;
+        
+        info!("🎵 Null audio processing stopped - processed {} blocks", blocks_processed);
+        Ok(())
+    }
+}
+
+impl AudioProcessorTrait for NullProcessor {
+    fn core(&self) -> &ProcessorCore {
+        &self.core
+    }
+    
+    fn core_mut(&mut self) -> &mut ProcessorCore {
+        &mut self.core
+    }
+    
+    /// Start processing
+    fn start_audio(&mut self) -> Result<(), AudioProcessorError> {
+        self.core.prepare_start()?;
+        
+        // The input file is read on every start, so a missing file is reported here
+        let config = self.core.config.clone();
+        let input = match &config.input_file {
+            Some(path) => Some(InputLoop::open(Path::new(path), config.sample_rate, config.resample_quality)?),
+            None => self.input_signal
+                .as_ref()
+                .map(TestSignal::generate_stereo)
+                .filter(|(left, _)| !left.is_empty())
+                .map(|(left, right)| InputLoop::new(left, right))
+                .transpose()?,
+        };
+        let stereo_delay = Arc::clone(&self.core.stereo_delay);
+        let is_running = Arc::clone(&self.core.is_running);
+        let heartbeat = Arc::clone(&self.core.heartbeat);
+        
+        self.core.start(move || Self::run_null_stream(config, input, stereo_delay, is_running, heartbeat));
+        
+        Ok(())
+    }
+    
+    /// Run a custom test tone through a copy of the current settings
+    ///
+    /// There is no device to play it on, so the tone is only processed.
+    fn test_audio_with(&self, params: TestToneParams) -> Result<(), AudioProcessorError> {
+        info!("🧪 Testing null audio processing...");
+        
+        let (left, _right) = params.render(&self.core.config)?;
+        
+        info!("✅ Null audio test completed - processed {} samples", left.len());
+        Ok(())
+    }
+    
+    /// Get overall system status
+    fn get_status(&self) -> Result<std::collections::HashMap<String, String>, AudioProcessorError> {
+        let mut status = self.core.status(None)?;
+        status.insert("resampler".to_string(), "off".to_string());
+        status.insert("idle".to_string(), "false".to_string());
+        Ok(status)
+    }
+    
+    /// Get the smoothed output meter readings
+    fn get_meters(&self) -> Result<std::collections::HashMap<String, f32>, AudioProcessorError> {
+        self.core.meters(None)
+    }
+    
+    /// List the available devices, always empty
+    fn list_devices(&self) -> DeviceList {
+        DeviceList::default()
+    }
+    
+    /// Store the device names for the configuration; nothing is opened
+    fn set_devices(&mut self, input_device: Option<String>, output_device: Option<String>) -> Result<(), AudioProcessorError> {
+        self.core.set_device_names(input_device, output_device);
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    use std::time::Duration;
+    use crate::distortion::DistortionType;
+    use crate::test_signal::{SignalKind, SignalLevel};
+    
+    #[test]
+    fn test_null_processor_runs_without_a_device() {
+        let config = AudioConfig {
+            input_device: Some("no-such-device".to_string()),
+            output_device: Some("no-such-device".to_string()),
+            ..Default::default()
+        };
+        let mut processor = NullProcessor::with_config(config).unwrap();
+        processor.set_input_signal(Some(TestSignal::new(
+            SignalKind::Sine { frequency: 220.0 },
+            SignalLevel::Peak(0.5),
+            0.5,
+            48000,
+        )));
+        
+        processor.start_audio().unwrap();
+        processor.set_stereo_delay_parameter("feedback", 0.5).unwrap();
+        processor.set_stereo_delay_parameter("bpm", 100.0).unwrap();
+        assert!(processor.set_stereo_delay_parameter("no_such_parameter", 1.0).is_err());
+        
+        // Blocks keep coming at the device pace, with the tone reaching the meters
+        thread::sleep(Duration::from_millis(300));
+        assert!(processor.get_health().is_healthy(), "{:?}", processor.get_health());
+        let status = processor.get_status().unwrap();
+        assert_eq!(status["backend"], "null");
+        assert_eq!(status["is_running"], "true");
+        assert_eq!(status["feedback"], "0.500");
+        assert_eq!(status["bpm"], "100");
+        assert!(processor.get_session_stats().peak_level > 0.0);
+        assert!(processor.list_devices().inputs.is_empty());
+        
+        processor.stop_audio().unwrap();
+        assert_eq!(processor.get_status().unwrap()["is_running"], "false");
+        assert!(!processor.get_health().is_healthy());
+    }
+    
+    #[test]
+    fn test_input_file_runs_through_the_live_path() {
+        let path = std::env::temp_dir().join(format!("null_input_file_{}.wav", std::process::id()));
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate: 44100,
+            bits_per_sample: 16,
+            sample_format: hound::SampleFormat::Int,
+        };
+        let mut writer = hound::WavWriter::create(&path, spec).unwrap();
+        for i in 0..4410 {
+            let sample = (0.5 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 44100.0).sin() * i16::MAX as f32) as i16;
+            writer.write_sample(sample).unwrap();
+            writer.write_sample(sample).unwrap();
+        }
+        writer.finalize().unwrap();
+        
+        // A missing file fails the start instead of running on silence
+        let mut config = AudioConfig {
+            input_file: Some(path.with_extension("missing").display().to_string()),
+            ..Default::default()
+        };
+        let mut processor = NullProcessor::with_config(config.clone()).unwrap();
+        assert!(processor.start_audio().is_err());
+        
+        // The file, at another sample rate, loops through the delay in real time
+        config.input_file = Some(path.display().to_string());
+        let mut processor = NullProcessor::with_config(config).unwrap();
+        processor.start_audio().unwrap();
+        thread::sleep(Duration::from_millis(250));
+        assert!(processor.get_health().is_healthy(), "{:?}", processor.get_health());
+        let stats = processor.get_session_stats();
+        assert!(stats.peak_level > 0.1, "processed output peaked at {}", stats.peak_level);
+        processor.stop_audio().unwrap();
+        
+        std::fs::remove_file(&path).unwrap();
+    }
+    
+    #[test]
+    fn test_parameter_observer_is_told_about_each_change() {
+        let mut processor = NullProcessor::new().unwrap();
+        let changes = Arc::new(Mutex::new(Vec::new()));
+        let observed = Arc::clone(&changes);
+        processor.on_parameter_change(Box::new(move |param, value| {
+            observed.lock().unwrap().push((param.to_string(), value));
+        }));
+        
+        processor.set_stereo_delay_parameter("feedback", 0.4).unwrap();
+        processor.set_stereo_delay_parameter("wet_mix", 0.25).unwrap();
+        assert!(processor.set_stereo_delay_parameter("no_such_parameter", 1.0).is_err());
+        processor.set_distortion_type("fuzz").unwrap();
+        
+        // Rejected changes are not reported
+        assert_eq!(*changes.lock().unwrap(), vec![
+            ("feedback".to_string(), 0.4),
+            ("wet_mix".to_string(), 0.25),
+            ("distortion_type".to_string(), DistortionType::Fuzz.to_parameter()),
+        ]);
+    }
+    
+    #[test]
+    fn test_parameter_batch_is_applied_all_or_none() {
+        let mut processor = NullProcessor::new().unwrap();
+        let batch = |params: &[(&str, f32)]| params.iter().map(|(param, value)| (param.to_string(), *value)).collect();
+        let before = processor.get_parameters().unwrap();
+        
+        // One value out of range rejects the whole batch
+        let invalid = batch(&[("feedback", 0.5), ("wet_mix", 0.2), ("stereo_width", 1.5)]);
+        assert!(processor.set_parameters(&invalid).is_err());
+        assert!(processor.set_parameters(&batch(&[("wet_mix", 0.2), ("no_such_parameter", 1.0)])).is_err());
+        assert!(processor.set_parameters(&batch(&[("feedback_hp_hz", 5000.0), ("feedback_lp_hz", 3000.0), ("wet_mix", 0.2)])).is_err());
+        assert!(processor.set_parameters(&batch(&[("bpm", 100.0), ("tails", 1.0), ("wet_mix", 0.2), ("bpm_glide_ms", -1.0)])).is_err());
+        if !cfg!(feature = "spectral") {
+            // Only fails once applied, after the tempo and tails are set
+            let late = batch(&[("bpm", 100.0), ("tails", 1.0), ("wet_mix", 0.95), ("spectral_freeze", 1.0)]);
+            assert!(processor.set_parameters(&late).is_err());
+        }
+        assert_eq!(processor.get_parameters().unwrap(), before);
+        
+        // Modes are in place before the values they allow, whatever the map order
+        let valid = batch(&[("feedback", 1.05), ("allow_self_oscillation", 1.0), ("bpm", 100.0), ("left_delay", 0.2)]);
+        processor.set_parameters(&valid).unwrap();
+        let after = processor.get_parameters().unwrap();
+        assert_eq!(after["feedback"], 1.05);
+        assert_eq!(after["bpm"], 100.0);
+        assert_eq!(after["left_delay"], 0.2);
+    }
+    
+    #[test]
+    fn test_parameter_batch_keeps_an_explicit_delay_time_over_the_tempo() {
+        let mut processor = NullProcessor::new().unwrap();
+        let batch = [("bpm".to_string(), 100.0), ("left_delay".to_string(), 0.2)].into_iter().collect();
+        processor.set_parameters(&batch).unwrap();
+        
+        // The stored config holds the times the delay runs with
+        let running = processor.get_parameters().unwrap();
+        let config = &processor.get_config().stereo_delay;
+        assert_eq!(config.left_delay, 0.2);
+        assert_eq!(config.right_delay, running["right_delay"]);
+        assert_ne!(config.right_delay, 0.2);
+        assert_eq!(config.bpm, Some(100.0));
+    }
+}
@@ -0,0 +1,37 @@
+/// Callback told the name and new value of every parameter that is set
+pub type ParameterObserver = Box<dyn Fn(&str, f32) + Send>;
+
+/// Observers registered on a processor with `on_parameter_change`
+///
+/// Observers are called on the thread that set the parameter, right after the
+/// change is applied, and never on the audio thread. That thread holds the
+/// processor, so an observer must not lock it again; hand the change to another
+/// thread or a channel for anything slow.
+#[derive(Default)]
+pub struct ParameterObservers {
+    observers: Vec<ParameterObserver>,
+}
+
+impl ParameterObservers {
+    /// Register an observer; it stays registered for the life of the processor
+    pub fn add(&mut self, observer: ParameterObserver) {
+        self.observers.push(observer);
+    }
+    
+    /// Tell every observer about a parameter change, in registration order
+    pub fn notify(&self, param: &str, value: f32) {
+        for observer in &self.observers {
+            observer(param, value);
+        }
+    }
+    
+    /// Number of registered observers
+    pub fn len(&self) -> usize {
+        self.observers.len()
+    }
+    
+    /// Whether no observer is registered
+    pub fn is_empty(&self) -> bool {
+        self.observers.is_empty()
+    }
+}
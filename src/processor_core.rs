@@ -0,0 +1,616 @@
+use std::collections::HashMap;
+use std::sync::{Arc, Mutex, MutexGuard, PoisonError};
+use std::thread;
+use std::time::{Duration, Instant};
+use parking_lot::RwLock;
+use crate::automation::{Automation, AutomationCommand, AutomationStatus};
+use crate::backend::AudioBackend;
+use crate::config::AudioConfig;
+use crate::delay::{BaseDelay, EffectStage, StereoDelay};
+use crate::distortion::DistortionType;
+use crate::error::AudioProcessorError;
+use crate::health::{catch_audio_thread_failure, AudioState, HealthStatus, Heartbeat, HEARTBEAT_TIMEOUT};
+use crate::latency::BufferFrames;
+use crate::macro_control::macro_values;
+use crate::meter::{SessionStats, SessionStatsReport};
+use crate::metronome::DEFAULT_METRONOME_BPM;
+use crate::observer::{ParameterObserver, ParameterObservers};
+use crate::recorder::{Recording, RecordingSummary};
+use crate::smoothing::SMOOTHED_PARAMETERS;
+use log::{error, info};
+
+/// Extra time allowed for the audio thread to finish a fade out before stopping anyway
+const FADE_WAIT_MARGIN: Duration = Duration::from_millis(100);
+
+/// State and behaviour shared by every audio backend
+///
+/// Holds the delay, the audio thread and everything about it that does not
+/// depend on the device: parameters, tempo, macro, automation, recording,
+/// observers, state export and the start, fade and stop sequence. A backend
+/// only adds its device I/O, run on the thread started with `start`.
+pub struct ProcessorCore {
+    backend: AudioBackend,
+    pub(crate) config: AudioConfig,
+    pub(crate) stereo_delay: Arc<Mutex<StereoDelay>>,
+    pub(crate) is_running: Arc<RwLock<bool>>,
+    audio_thread: Option<thread::JoinHandle<()>>,
+    pub(crate) heartbeat: Arc<Heartbeat>,
+    last_error: Arc<RwLock<Option<String>>>,
+    // Recorded parameter changes, played back by `poll_automation`
+    automation: Automation,
+    // Peak and clip statistics kept by the delay, readable while the audio thread holds its lock
+    session_stats: Arc<SessionStats>,
+    // Output WAV recording in progress, fed by a tap in the delay
+    recording: Option<Recording>,
+    // Callbacks told about every parameter set, see `on_parameter_change`
+    parameter_observers: ParameterObservers,
+}
+
+impl ProcessorCore {
+    /// Create the shared state for a backend from a validated copy of `config`
+    pub(crate) fn new(backend: AudioBackend, config: AudioConfig) -> Result<Self, AudioProcessorError> {
+        config.validate()?;
+        
+        let stereo_delay = StereoDelay::from_config(&config);
+        let automation = Automation::new(config.stereo_delay.bpm.unwrap_or(DEFAULT_METRONOME_BPM));
+        let session_stats = stereo_delay.session_stats();
+        
+        Ok(Self {
+            backend,
+            config,
+            stereo_delay: Arc::new(Mutex::new(stereo_delay)),
+            is_running: Arc::new(RwLock::new(false)),
+            audio_thread: None,
+            heartbeat: Arc::new(Heartbeat::new()),
+            last_error: Arc::new(RwLock::new(None)),
+            automation,
+            session_stats,
+            recording: None,
+            parameter_observers: ParameterObservers::default(),
+        })
+    }
+    
+    /// Lock the delay, failing if a panicking audio thread left it poisoned
+    pub(crate) fn delay(&self) -> Result<MutexGuard<'_, StereoDelay>, AudioProcessorError> {
+        self.stereo_delay.lock().map_err(|_| {
+            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
+        })
+    }
+    
+    /// Check that audio is stopped and get the delay ready for a new start
+    ///
+    /// Accepts input again if the previous run ended with a tails stop, and fades in.
+    pub(crate) fn prepare_start(&self) -> Result<(), AudioProcessorError> {
+        if *self.is_running.read() {
+            return Err(AudioProcessorError::Processing("Audio already running".to_string()));
+        }
+        
+        let mut delay = self.delay()?;
+        delay.end_tails();
+        delay.begin_fade_in();
+        self.heartbeat.clear();
+        
+        Ok(())
+    }
+    
+    /// Mark audio as running and run `body` on a new audio thread
+    ///
+    /// The flag is set before spawning so a thread that fails straight away can clear it.
+    pub(crate) fn start<F>(&mut self, body: F)
+    where
+        F: FnOnce() -> Result<(), AudioProcessorError> + Send + 'static,
+    {
+        *self.is_running.write() = true;
+        self.audio_thread = Some(self.spawn_audio_thread(body));
+    }
+    
+    /// Spawn the audio thread, catching errors and panics in its body
+    ///
+    /// A failed or panicked thread clears the running flag and records the reason
+    /// under `last_error` in the status, so the daemon watchdog can restart audio.
+    /// A panic while the delay was locked poisons the lock, so the delay is reset
+    /// and the poison cleared before the next start.
+    pub(crate) fn spawn_audio_thread<F>(&self, body: F) -> thread::JoinHandle<()>
+    where
+        F: FnOnce() -> Result<(), AudioProcessorError> + Send + 'static,
+    {
+        let backend = self.backend;
+        let stereo_delay = Arc::clone(&self.stereo_delay);
+        let is_running = Arc::clone(&self.is_running);
+        let last_error = Arc::clone(&self.last_error);
+        
+        thread::spawn(move || {
+            if let Err(e) = catch_audio_thread_failure(body) {
+                error!("❌ {} audio stream stopped: {}", backend, e);
+                if stereo_delay.is_poisoned() {
+                    stereo_delay.lock().unwrap_or_else(PoisonError::into_inner).reset();
+                    stereo_delay.clear_poison();
+                }
+                *last_error.write() = Some(e);
+                *is_running.write() = false;
+            }
+        })
+    }
+    
+    /// Fade the output out and wait until the audio thread has reached silence
+    ///
+    /// Gives up after the fade time plus a margin, e.g. if the device stopped delivering callbacks.
+    fn fade_out(&self) {
+        let fade_ms = self.config.fade_out_ms;
+        if fade_ms <= 0.0 {
+            return;
+        }
+        match self.stereo_delay.lock() {
+            Ok(mut delay) => delay.begin_fade_out(),
+            Err(_) => return,
+        }
+        
+        let deadline = Instant::now() + Duration::from_secs_f32(fade_ms / 1000.0) + FADE_WAIT_MARGIN;
+        while Instant::now() < deadline {
+            let faded_out = self.stereo_delay.lock().map(|delay| delay.is_faded_out()).unwrap_or(true);
+            if faded_out {
+                break;
+            }
+            thread::sleep(Duration::from_millis(1));
+        }
+    }
+    
+    /// Stop audio processing
+    pub fn stop_audio(&mut self) -> Result<(), AudioProcessorError> {
+        if !*self.is_running.read() {
+            return Err(AudioProcessorError::Processing("Audio not running".to_string()));
+        }
+        
+        // Fade out first so the stream doesn't stop with a click
+        self.fade_out();
+        *self.is_running.write() = false;
+        
+        if let Some(thread) = self.audio_thread.take() {
+            thread.join().map_err(|_| {
+                AudioProcessorError::Threading("Failed to join audio thread".to_string())
+            })?;
+        }
+        
+        // Reset delay buffers to clear any lingering feedback
+        self.reset_delay()?;
+        
+        Ok(())
+    }
+    
+    /// Stop audio processing, letting the repeats ring out first when tails are enabled
+    ///
+    /// With `tails` off this is the same as `stop_audio`. With `tails` on it returns
+    /// straight away: new input stops feeding the delay and the audio thread shuts
+    /// itself down once the echoes have decayed to silence.
+    pub fn stop_with_tails(&mut self) -> Result<(), AudioProcessorError> {
+        if !self.config.stereo_delay.tails {
+            return self.stop_audio();
+        }
+        self.ring_out()
+    }
+    
+    /// Stop feeding new input and stop audio once the repeats have rung out
+    ///
+    /// Unlike `stop_with_tails` this lets the echoes ring out whatever the `tails`
+    /// setting. The status reports `audio_state` "stopping" until they have decayed.
+    pub fn ring_out(&mut self) -> Result<(), AudioProcessorError> {
+        if !*self.is_running.read() {
+            return Err(AudioProcessorError::Processing("Audio not running".to_string()));
+        }
+        
+        self.delay()?.begin_tails();
+        
+        Ok(())
+    }
+    
+    /// Check that the audio thread is alive and has processed audio recently
+    pub fn get_health(&self) -> HealthStatus {
+        if !*self.is_running.read() {
+            let reason = match self.last_error.read().as_ref() {
+                Some(last_error) => format!("audio is not running: {}", last_error),
+                None => "audio is not running".to_string(),
+            };
+            return HealthStatus::Unhealthy { reason };
+        }
+        
+        let thread_alive = self.audio_thread.as_ref().map(|thread| !thread.is_finished()).unwrap_or(false);
+        if !thread_alive {
+            return HealthStatus::Unhealthy { reason: "audio thread has exited".to_string() };
+        }
+        
+        self.heartbeat.status(HEARTBEAT_TIMEOUT)
+    }
+    
+    /// Get the status entries every backend reports
+    ///
+    /// The backend adds its device, buffer and resampler entries. `latency` is
+    /// the buffer sizes its audio thread has seen, if it measures them.
+    pub(crate) fn status(&self, latency: Option<&BufferFrames>) -> Result<HashMap<String, String>, AudioProcessorError> {
+        let delay = self.delay()?;
+        
+        // Delay, distortion and meter state straight from the running effect
+        let mut status = delay.get_status();
+        status.insert("tails".to_string(), self.config.stereo_delay.tails.to_string());
+        status.insert("macro".to_string(), format!("{:.3}", self.config.macro_control.position));
+        
+        // System parameters
+        status.insert("backend".to_string(), self.backend.name().to_string());
+        status.insert("sample_rate".to_string(), self.config.sample_rate.to_string());
+        status.insert("buffer_size".to_string(), self.config.buffer_size.to_string());
+        status.insert("resample_quality".to_string(), self.config.resample_quality.to_string());
+        if let Some(latency) = latency.and_then(BufferFrames::latency) {
+            for (key, ms) in latency.entries() {
+                status.insert(key.to_string(), format!("{:.1}", ms));
+            }
+            status.insert("latency_samples".to_string(), latency.frames.to_string());
+        }
+        status.insert("is_running".to_string(), self.is_running.read().to_string());
+        status.insert("recording".to_string(), self.recording.is_some().to_string());
+        if let Some(recording) = &self.recording {
+            status.insert("recording_path".to_string(), recording.path().display().to_string());
+        }
+        status.insert("audio_state".to_string(), AudioState::new(*self.is_running.read(), delay.is_tailing()).to_string());
+        if let Some(last_error) = self.last_error.read().as_ref() {
+            status.insert("last_error".to_string(), last_error.clone());
+        }
+        
+        // Parameter smoothing times
+        for param in SMOOTHED_PARAMETERS {
+            if let Some(smoothing_ms) = delay.get_smoothing_time(param) {
+                status.insert(format!("smoothing_{}", param), format!("{:.0}", smoothing_ms));
+            }
+        }
+        
+        // Add BPM information if available
+        if let Some(bpm) = self.config.stereo_delay.bpm {
+            status.insert("bpm".to_string(), format!("{:.0}", bpm));
+        }
+        
+        Ok(status)
+    }
+    
+    /// Get the smoothed output meter readings, with the latency from `latency` if given
+    pub(crate) fn meters(&self, latency: Option<&BufferFrames>) -> Result<HashMap<String, f32>, AudioProcessorError> {
+        let mut meters = self.delay()?.get_meters();
+        if let Some(latency) = latency.and_then(BufferFrames::latency) {
+            meters.extend(latency.entries().map(|(key, ms)| (key.to_string(), ms)));
+            meters.insert("latency_samples".to_string(), latency.frames as f32);
+        }
+        Ok(meters)
+    }
+    
+    /// Move the macro control, setting every mapped parameter along its curve
+    pub fn set_macro(&mut self, value: f32) -> Result<(), AudioProcessorError> {
+        if !(0.0..=1.0).contains(&value) {
+            return Err(AudioProcessorError::InvalidParameter {
+                param: "macro".to_string(),
+                value,
+                min: 0.0,
+                max: 1.0,
+            });
+        }
+        
+        for (param, mapped_value) in macro_values(&self.config.macro_control.mappings, value) {
+            self.set_stereo_delay_parameter(&param, mapped_value)?;
+        }
+        self.config.macro_control.position = value;
+        self.parameter_observers.notify("macro", value);
+        
+        Ok(())
+    }
+    
+    /// Set stereo delay effect parameter
+    pub fn set_stereo_delay_parameter(&mut self, param: &str, value: f32) -> Result<(), AudioProcessorError> {
+        if param == "macro" {
+            return self.set_macro(value);
+        }
+        
+        let stereo_delay = Arc::clone(&self.stereo_delay);
+        let mut delay = stereo_delay.lock().map_err(|_| {
+            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
+        })?;
+        
+        match param {
+            "bpm" => self.set_tempo(&mut delay, value),
+            "fade_out_ms" => {
+                delay.set_parameter(param, value)?;
+                self.config.fade_out_ms = value;
+            },
+            "tails" => self.config.stereo_delay.tails = value > 0.5,
+            _ => delay.set_parameter(param, value)?,
+        }
+        
+        // A macro move is recorded as the parameters it sets
+        self.automation.record(param, value, Instant::now());
+        
+        // Observers run without the delay lock, so they never hold up the audio thread
+        drop(delay);
+        self.parameter_observers.notify(param, value);
+        
+        Ok(())
+    }
+    
+    /// Set the tempo, moving the delay taps and the metronome to it
+    fn set_tempo(&mut self, delay: &mut StereoDelay, bpm: f32) {
+        let mut config = self.config.clone();
+        config.stereo_delay.set_bpm(bpm);
+        delay.set_tempo_delays(config.stereo_delay.left_delay, config.stereo_delay.right_delay);
+        delay.set_metronome_bpm(bpm);
+        self.automation.set_bpm(bpm, Instant::now());
+        // Update the stored config
+        self.config.stereo_delay.bpm = config.stereo_delay.bpm;
+        self.config.stereo_delay.left_delay = config.stereo_delay.left_delay;
+        self.config.stereo_delay.right_delay = config.stereo_delay.right_delay;
+    }
+    
+    /// Set several parameters at once, all or none
+    ///
+    /// The whole batch is checked first and nothing changes if any parameter is
+    /// invalid. It is applied under a single lock of the delay, so the audio thread
+    /// never runs with part of it applied. A `macro` position adds the parameters
+    /// it maps to the batch, unless the batch sets them itself.
+    pub fn set_parameters(&mut self, params: &HashMap<String, f32>) -> Result<(), AudioProcessorError> {
+        let mut params = params.clone();
+        let macro_position = params.remove("macro");
+        if let Some(position) = macro_position {
+            crate::schema::check("macro", position)?;
+            for (param, value) in macro_values(&self.config.macro_control.mappings, position) {
+                params.entry(param).or_insert(value);
+            }
+        }
+        
+        // The processor's own parameters are checked here and the rest by the delay,
+        // all before anything is applied
+        let mut delay_params = params.clone();
+        let bpm = delay_params.remove("bpm");
+        let tails = delay_params.remove("tails");
+        for (param, value) in [("bpm", bpm), ("tails", tails)] {
+            if let Some(value) = value {
+                crate::schema::check(param, value)?;
+            }
+        }
+        
+        let stereo_delay = Arc::clone(&self.stereo_delay);
+        let mut delay = stereo_delay.lock().map_err(|_| {
+            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
+        })?;
+        delay.check_parameters(&delay_params)?;
+        
+        // The processor's own parameters, then the delay's; an explicit delay time
+        // wins over the one the tempo sets
+        if let Some(bpm) = bpm {
+            self.set_tempo(&mut delay, bpm);
+        }
+        if let Some(tails) = tails {
+            self.config.stereo_delay.tails = tails > 0.5;
+        }
+        delay.set_parameters(&delay_params)?;
+        if bpm.is_some() {
+            // Keep the delay times the batch ended on, not the ones the tempo set
+            let (left_delay, right_delay) = delay.get_delay_times();
+            self.config.stereo_delay.left_delay = left_delay;
+            self.config.stereo_delay.right_delay = right_delay;
+        }
+        if let Some(&fade_out_ms) = params.get("fade_out_ms") {
+            self.config.fade_out_ms = fade_out_ms;
+        }
+        if let Some(position) = macro_position {
+            self.config.macro_control.position = position;
+        }
+        
+        let now = Instant::now();
+        for (param, &value) in &params {
+            self.automation.record(param, value, now);
+        }
+        
+        // Observers run without the delay lock, so they never hold up the audio thread
+        drop(delay);
+        for (param, &value) in &params {
+            self.parameter_observers.notify(param, value);
+        }
+        if let Some(position) = macro_position {
+            self.parameter_observers.notify("macro", position);
+        }
+        
+        Ok(())
+    }
+    
+    /// Get every parameter by name, as taken by `set_parameters`
+    pub fn get_parameters(&self) -> Result<HashMap<String, f32>, AudioProcessorError> {
+        let delay = self.delay()?;
+        
+        let mut params = delay.get_parameters();
+        params.insert("bpm".to_string(), self.config.stereo_delay.bpm.unwrap_or(DEFAULT_METRONOME_BPM));
+        params.insert("tails".to_string(), if self.config.stereo_delay.tails { 1.0 } else { 0.0 });
+        params.insert("macro".to_string(), self.config.macro_control.position);
+        Ok(params)
+    }
+    
+    /// Register a callback told the name and value of every parameter set from now on
+    ///
+    /// It runs on the thread setting the parameter, after the change is applied,
+    /// and must not lock the processor. See `ParameterObservers`.
+    pub fn on_parameter_change(&mut self, observer: ParameterObserver) {
+        self.parameter_observers.add(observer);
+    }
+    
+    /// Record, play, stop or clear parameter automation
+    pub fn automation_command(&mut self, command: AutomationCommand) -> Result<(), AudioProcessorError> {
+        self.automation.command(command, Instant::now())
+    }
+    
+    /// Get the recorded automation and whether it is recording or playing
+    pub fn get_automation(&self) -> AutomationStatus {
+        self.automation.status()
+    }
+    
+    /// Apply the automation events that have come due, returning how many were applied
+    ///
+    /// Call this regularly (every `automation::AUTOMATION_TICK`) while playing.
+    pub fn poll_automation(&mut self) -> Result<usize, AudioProcessorError> {
+        let due = self.automation.due_events(Instant::now());
+        for event in &due {
+            self.set_stereo_delay_parameter(&event.parameter, event.value)?;
+        }
+        Ok(due.len())
+    }
+    
+    /// Get the peak level, clip count and peak feedback level since the last reset
+    pub fn get_session_stats(&self) -> SessionStatsReport {
+        self.session_stats.report()
+    }
+    
+    /// Start collecting session statistics afresh
+    pub fn reset_session_stats(&self) {
+        self.session_stats.reset();
+    }
+    
+    /// Start writing the processed output to a 32-bit float WAV file
+    ///
+    /// Disk writes happen on a background thread, see `recorder::Recording`.
+    pub fn start_recording(&mut self, path: &str) -> Result<(), AudioProcessorError> {
+        if let Some(recording) = &self.recording {
+            return Err(AudioProcessorError::Processing(format!(
+                "Already recording to {}", recording.path().display()
+            )));
+        }
+        
+        let recording = Recording::start(path, self.config.sample_rate)?;
+        self.delay()?.set_recording_tap(Some(recording.tap()));
+        info!("⏺️  Recording output to {}", path);
+        self.recording = Some(recording);
+        Ok(())
+    }
+    
+    /// Stop the recording and close its file
+    ///
+    /// The audio thread holds the delay lock for a whole block, so detaching the tap
+    /// under that lock ends the recording on a block boundary.
+    pub fn stop_recording(&mut self) -> Result<RecordingSummary, AudioProcessorError> {
+        let recording = self.recording.take().ok_or_else(|| {
+            AudioProcessorError::Processing("Not recording".to_string())
+        })?;
+        self.stereo_delay
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .set_recording_tap(None);
+        
+        let summary = recording.finish()?;
+        info!("⏹️  Recorded {:.1}s to {}", summary.seconds, summary.path);
+        Ok(summary)
+    }
+    
+    /// Set distortion type (string parameter)
+    pub fn set_distortion_type(&self, distortion_type: &str) -> Result<(), AudioProcessorError> {
+        let mut delay = self.delay()?;
+        
+        let dist_type = DistortionType::from_name(distortion_type).ok_or_else(|| {
+            AudioProcessorError::Configuration(format!("Unknown distortion type: {}", distortion_type))
+        })?;
+        delay.set_cross_feedback_distortion(None, Some(dist_type), None, None, None);
+        drop(delay);
+        self.parameter_observers.notify("distortion_type", dist_type.to_parameter());
+        
+        Ok(())
+    }
+    
+    /// Get the current configuration
+    pub fn get_config(&self) -> &AudioConfig {
+        &self.config
+    }
+    
+    /// Store new device names; `None` keeps a device and an empty name goes back to automatic selection
+    pub(crate) fn set_device_names(&mut self, input_device: Option<String>, output_device: Option<String>) {
+        if let Some(name) = input_device {
+            info!("🎤 Input device set to '{}'", name);
+            self.config.input_device = Some(name).filter(|name| !name.is_empty());
+        }
+        if let Some(name) = output_device {
+            info!("🔊 Output device set to '{}'", name);
+            self.config.output_device = Some(name).filter(|name| !name.is_empty());
+        }
+    }
+    
+    /// Export the complete live configuration as JSON
+    ///
+    /// Unlike the configuration file this includes every parameter changed at runtime.
+    pub fn export_state(&self) -> Result<String, AudioProcessorError> {
+        let delay = self.delay()?;
+        
+        let mut state = self.config.clone();
+        delay.write_config(&mut state);
+        
+        serde_json::to_string_pretty(&state).map_err(|e| {
+            AudioProcessorError::Configuration(format!("Failed to export state: {}", e))
+        })
+    }
+    
+    /// Restore a configuration exported with `export_state`
+    ///
+    /// The state is validated first and then applied to the delay under a single lock,
+    /// so either every parameter changes or none does. The sample rate, buffer size and
+    /// devices of the running processor are kept. Changed delay times crossfade like a
+    /// preset change, see `StereoDelay::load_preset`.
+    pub fn import_state(&mut self, state: &str) -> Result<(), AudioProcessorError> {
+        let mut config: AudioConfig = serde_json::from_str(state).map_err(|e| {
+            AudioProcessorError::Configuration(format!("Invalid state: {}", e))
+        })?;
+        config.validate()?;
+        
+        config.sample_rate = self.config.sample_rate;
+        config.buffer_size = self.config.buffer_size;
+        config.input_device = self.config.input_device.clone();
+        config.output_device = self.config.output_device.clone();
+        
+        let stereo_delay = Arc::clone(&self.stereo_delay);
+        let mut delay = stereo_delay.lock().map_err(|_| {
+            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
+        })?;
+        delay.load_preset(&config);
+        self.config = config;
+        
+        Ok(())
+    }
+    
+    /// Reset the delay buffers to clear any lingering feedback
+    pub fn reset_delay(&self) -> Result<(), AudioProcessorError> {
+        self.delay()?.reset();
+        
+        Ok(())
+    }
+    
+    /// Clear the internal state of every effect, for when a patch runs away
+    ///
+    /// Unlike `reset_delay`, which only clears the delay buffers, this also clears
+    /// the distortion and the meters. With `restore_defaults` every effect parameter
+    /// goes back to its default as well; device settings are kept.
+    pub fn panic(&mut self, restore_defaults: bool) -> Result<(), AudioProcessorError> {
+        // Playback would bring back the settings being cleared
+        self.automation.stop(Instant::now());
+        
+        let stereo_delay = Arc::clone(&self.stereo_delay);
+        let mut delay = stereo_delay.lock().map_err(|_| {
+            AudioProcessorError::Threading("Failed to acquire stereo delay lock".to_string())
+        })?;
+        
+        if restore_defaults {
+            self.config = self.config.with_default_effects();
+            delay.apply_config(&self.config);
+        }
+        delay.panic();
+        
+        Ok(())
+    }
+    
+    /// Get the optional effect stages in signal order with their enabled state
+    pub fn get_effect_chain(&self) -> Result<Vec<EffectStage>, AudioProcessorError> {
+        Ok(self.delay()?.effect_chain())
+    }
+}
+
+impl Drop for ProcessorCore {
+    fn drop(&mut self) {
+        // Ensure audio is stopped when the processor is dropped
+        let _ = self.stop_audio();
+    }
+}
@@ -0,0 +1,121 @@
+use log::{info, warn};
+
+use crate::config::AudioConfig;
+use crate::error::AudioProcessorError;
+
+/// SCHED_FIFO priority requested for the audio thread, above most system threads
+pub const AUDIO_THREAD_RT_PRIORITY: i32 = 70;
+
+/// Request real-time (SCHED_FIFO) scheduling for the calling thread
+///
+/// The priority is clamped to the range the OS allows. Without the
+/// `CAP_SYS_NICE` capability or an rtprio limit the request is denied.
+#[cfg(target_os = "linux")]
+pub fn set_realtime_priority(priority: i32) -> Result<(), AudioProcessorError> {
+    // SAFETY: plain libc calls on the current thread with a valid sched_param
+    let result = unsafe {
+        let min = libc::sched_get_priority_min(libc::SCHED_FIFO);
+        let max = libc::sched_get_priority_max(libc::SCHED_FIFO);
+        let param = libc::sched_param { sched_priority: priority.clamp(min, max) };
+        libc::pthread_setschedparam(libc::pthread_self(), libc::SCHED_FIFO, &param)
+    };
+    if result != 0 {
+        return Err(AudioProcessorError::Processing(format!(
+            "real-time priority {} denied: {}",
+            priority,
+            std::io::Error::from_raw_os_error(result)
+        )));
+    }
+    Ok(())
+}
+
+/// Request real-time scheduling for the calling thread (Linux only)
+#[cfg(not(target_os = "linux"))]
+pub fn set_realtime_priority(priority: i32) -> Result<(), AudioProcessorError> {
+    Err(AudioProcessorError::Processing(format!(
+        "real-time priority {} is only supported on Linux",
+        priority
+    )))
+}
+
+/// Pin the calling thread to one CPU core
+#[cfg(target_os = "linux")]
+pub fn pin_to_core(core: usize) -> Result<(), AudioProcessorError> {
+    if core >= libc::CPU_SETSIZE as usize {
+        return Err(AudioProcessorError::Processing(format!("CPU core {} does not exist", core)));
+    }
+    
+    // SAFETY: the set is zeroed before use and `core` is within CPU_SETSIZE
+    let result = unsafe {
+        let mut set: libc::cpu_set_t = std::mem::zeroed();
+        libc::CPU_SET(core, &mut set);
+        libc::sched_setaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &set)
+    };
+    if result != 0 {
+        return Err(AudioProcessorError::Processing(format!(
+            "pinning to CPU core {} denied: {}",
+            core,
+            std::io::Error::last_os_error()
+        )));
+    }
+    Ok(())
+}
+
+/// Pin the calling thread to one CPU core (Linux only)
+#[cfg(not(target_os = "linux"))]
+pub fn pin_to_core(core: usize) -> Result<(), AudioProcessorError> {
+    Err(AudioProcessorError::Processing(format!(
+        "pinning to CPU core {} is only supported on Linux",
+        core
+    )))
+}
+
+/// Apply the configured scheduling hints to the calling audio thread
+///
+/// Hints the OS refuses are logged as warnings; audio runs either way, just
+/// with more risk of xruns when other threads compete for the CPU.
+pub fn apply_audio_thread_hints(config: &AudioConfig) {
+    if let Some(core) = config.audio_thread_core {
+        match pin_to_core(core) {
+            Ok(()) => info!("📌 Audio thread pinned to CPU core {}", core),
+            Err(e) => warn!("⚠️  Could not pin the audio thread: {}", e),
+        }
+    }
+    
+    if config.audio_thread_rt {
+        match set_realtime_priority(AUDIO_THREAD_RT_PRIORITY) {
+            Ok(()) => info!("⏱️  Audio thread running with real-time priority {}", AUDIO_THREAD_RT_PRIORITY),
+            Err(e) => warn!("⚠️  Could not raise the audio thread priority: {}", e),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::thread;
+    
+    #[test]
+    fn test_scheduling_hints_succeed_or_fail_cleanly() {
+        // Each request runs on its own thread so the test thread is left alone
+        let priority = thread::spawn(|| set_realtime_priority(AUDIO_THREAD_RT_PRIORITY)).join().unwrap();
+        if let Err(e) = priority {
+            assert!(e.to_string().contains("real-time priority"), "{}", e);
+        }
+        
+        let missing = thread::spawn(|| pin_to_core(usize::MAX)).join().unwrap();
+        assert!(missing.is_err());
+        
+        // Pinning to a core the process already runs on is allowed without privileges
+        #[cfg(target_os = "linux")]
+        {
+            let allowed = unsafe {
+                let mut set: libc::cpu_set_t = std::mem::zeroed();
+                libc::sched_getaffinity(0, std::mem::size_of::<libc::cpu_set_t>(), &mut set);
+                (0..libc::CPU_SETSIZE as usize).find(|&core| libc::CPU_ISSET(core, &set))
+            };
+            let core = allowed.expect("the process runs on at least one core");
+            thread::spawn(move || pin_to_core(core)).join().unwrap().unwrap();
+        }
+    }
+}
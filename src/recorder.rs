@@ -0,0 +1,196 @@
+use crossbeam::queue::ArrayQueue;
+use serde::Serialize;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+use std::thread::{self, JoinHandle};
+use std::time::Duration;
+
+use crate::error::AudioProcessorError;
+
+/// Seconds of output the queue holds while the writer thread catches up with the disk
+pub const RECORDING_QUEUE_SECONDS: u32 = 2;
+
+/// How long the writer thread sleeps when the queue is empty
+const WRITER_POLL: Duration = Duration::from_millis(20);
+
+/// Audio thread end of a recording
+///
+/// Pushes whole stereo frames into a lock-free queue and never blocks. When the
+/// writer falls behind, frames that do not fit are dropped and counted.
+#[derive(Debug, Clone)]
+pub struct RecordingTap {
+    queue: Arc<ArrayQueue<(f32, f32)>>,
+    dropped: Arc<AtomicU64>,
+}
+
+impl RecordingTap {
+    /// Queue one output frame for the writer
+    pub fn push(&self, left: f32, right: f32) {
+        if self.queue.push((left, right)).is_err() {
+            self.dropped.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+}
+
+/// What ended up in a finished recording
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct RecordingSummary {
+    pub path: String,
+    pub frames: u64,
+    pub seconds: f64,
+    /// Frames lost because the writer could not keep up
+    pub dropped_frames: u64,
+}
+
+/// A WAV file being written from the live output
+///
+/// The file is created up front so a bad path fails the start; a background thread
+/// moves frames from the tap's queue into it. Because the tap only ever holds
+/// whole frames, stopping at any point leaves a valid file with no half frame.
+pub struct Recording {
+    path: PathBuf,
+    sample_rate: u32,
+    tap: RecordingTap,
+    stop: Arc<AtomicBool>,
+    writer: Option<JoinHandle<Result<u64, AudioProcessorError>>>,
+}
+
+impl Recording {
+    /// Create a 32-bit float stereo WAV file and start the writer thread
+    ///
+    /// Only `.wav` paths are accepted, so a mistyped path cannot overwrite other files.
+    pub fn start<P: AsRef<Path>>(path: P, sample_rate: u32) -> Result<Self, AudioProcessorError> {
+        let path = path.as_ref().to_path_buf();
+        if !path.extension().is_some_and(|extension| extension.eq_ignore_ascii_case("wav")) {
+            return Err(AudioProcessorError::Configuration(format!(
+                "Recording path {} must end in .wav", path.display()
+            )));
+        }
+        let spec = hound::WavSpec {
+            channels: 2,
+            sample_rate,
+            bits_per_sample: 32,
+            sample_format: hound::SampleFormat::Float,
+        };
+        let wav_error = |e: hound::Error| AudioProcessorError::Processing(format!("{}: {}", path.display(), e));
+        let mut writer = hound::WavWriter::create(&path, spec).map_err(wav_error)?;
+        
+        let tap = RecordingTap {
+            queue: Arc::new(ArrayQueue::new((sample_rate * RECORDING_QUEUE_SECONDS).max(1) as usize)),
+            dropped: Arc::new(AtomicU64::new(0)),
+        };
+        let stop = Arc::new(AtomicBool::new(false));
+        
+        let queue = Arc::clone(&tap.queue);
+        let stopping = Arc::clone(&stop);
+        let error_path = path.clone();
+        let handle = thread::spawn(move || {
+            let wav_error = |e: hound::Error| AudioProcessorError::Processing(format!("{}: {}", error_path.display(), e));
+            let mut frames = 0u64;
+            loop {
+                // Read the flag first so the frames queued before the stop are all drained
+                let stop_requested = stopping.load(Ordering::Acquire);
+                while let Some((left, right)) = queue.pop() {
+                    writer.write_sample(left).map_err(wav_error)?;
+                    writer.write_sample(right).map_err(wav_error)?;
+                    frames += 1;
+                }
+                if stop_requested {
+                    break;
+                }
+                thread::sleep(WRITER_POLL);
+            }
+            writer.finalize().map_err(wav_error)?;
+            Ok(frames)
+        });
+        
+        Ok(Self {
+            path,
+            sample_rate,
+            tap,
+            stop,
+            writer: Some(handle),
+        })
+    }
+    
+    /// Get the tap to hand to the audio thread
+    pub fn tap(&self) -> RecordingTap {
+        self.tap.clone()
+    }
+    
+    /// Get the path of the file being written
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+    
+    /// Write out the queued frames and close the file
+    ///
+    /// Remove the tap from the audio thread first, otherwise frames it pushes
+    /// after this call are lost.
+    pub fn finish(mut self) -> Result<RecordingSummary, AudioProcessorError> {
+        self.stop.store(true, Ordering::Release);
+        let frames = match self.writer.take().map(JoinHandle::join) {
+            Some(Ok(result)) => result?,
+            _ => return Err(AudioProcessorError::Threading("Recording writer thread panicked".to_string())),
+        };
+        
+        Ok(RecordingSummary {
+            path: self.path.display().to_string(),
+            frames,
+            seconds: frames as f64 / self.sample_rate.max(1) as f64,
+            dropped_frames: self.tap.dropped.load(Ordering::Relaxed),
+        })
+    }
+}
+
+impl Drop for Recording {
+    fn drop(&mut self) {
+        // Still close the file properly if the recording is dropped without finishing
+        self.stop.store(true, Ordering::Release);
+        if let Some(handle) = self.writer.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_recording_contains_the_output_frames() {
+        let path = std::env::temp_dir().join(format!("recorder_test_{}.wav", std::process::id()));
+        let recording = Recording::start(&path, 48000).unwrap();
+        let tap = recording.tap();
+        
+        // Blocks pushed from another thread as an audio callback would, the last one short
+        let expected: Vec<(f32, f32)> = (0..1000).map(|i| (i as f32 / 1000.0, -(i as f32) / 1000.0)).collect();
+        let audio = {
+            let expected = expected.clone();
+            thread::spawn(move || {
+                for block in expected.chunks(256) {
+                    for &(left, right) in block {
+                        tap.push(left, right);
+                    }
+                    thread::sleep(Duration::from_millis(5));
+                }
+            })
+        };
+        audio.join().unwrap();
+        
+        let summary = recording.finish().unwrap();
+        assert_eq!(summary.frames, 1000);
+        assert_eq!(summary.dropped_frames, 0);
+        
+        let mut reader = hound::WavReader::open(&path).unwrap();
+        assert_eq!(reader.spec().channels, 2);
+        assert_eq!(reader.spec().sample_rate, 48000);
+        let samples: Vec<f32> = reader.samples::<f32>().map(Result::unwrap).collect();
+        let written: Vec<(f32, f32)> = samples.chunks_exact(2).map(|frame| (frame[0], frame[1])).collect();
+        assert_eq!(written, expected);
+        std::fs::remove_file(&path).unwrap();
+        
+        assert!(Recording::start(std::env::temp_dir().join("recorder_test.json"), 48000).is_err());
+    }
+}
@@ -0,0 +1,337 @@
+use serde::{Deserialize, Serialize};
+use std::f64::consts::PI;
+use std::fmt;
+
+/// Frames either side of the read position weighted by the sinc kernel
+pub const SINC_HALF_TAPS: usize = 16;
+
+// Kernel values per input frame of distance, linearly interpolated in between
+const SINC_TABLE_STEPS: usize = 512;
+
+// Cutoff below the lower Nyquist frequency, leaving room for the transition band
+const SINC_CUTOFF: f64 = 0.95;
+
+/// Interpolation used by the sample-rate converter
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ResampleQuality {
+    /// Linear interpolation between neighbouring frames, cheap but aliases
+    #[default]
+    Fast,
+    /// Blackman-windowed sinc over 32 frames, band-limited at the lower Nyquist frequency
+    High,
+}
+
+impl ResampleQuality {
+    /// Name of the interpolation, as reported in the status
+    pub fn resampler_name(self) -> &'static str {
+        match self {
+            ResampleQuality::Fast => "linear",
+            ResampleQuality::High => "sinc",
+        }
+    }
+}
+
+impl fmt::Display for ResampleQuality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ResampleQuality::Fast => "fast",
+            ResampleQuality::High => "high",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Streaming sample-rate converter for interleaved audio
+///
+/// Used when the input and output devices run at different rates. Blocks can be
+/// any length; the fractional read position and the frames the interpolation still
+/// needs are carried over, so consecutive blocks join without discontinuities.
+/// The `High` quality waits for `SINC_HALF_TAPS` frames of lookahead, which adds
+/// that much latency.
+pub struct SampleRateConversion {
+    input_rate: u32,
+    output_rate: u32,
+    channels: usize,
+    quality: ResampleQuality,
+    // Input frames advanced per output frame
+    step: f64,
+    // Read position relative to the start of the next block (-1.0 is the carried frame)
+    position: f64,
+    previous_frame: Vec<f32>,
+    // Sinc only: interleaved input frames still in reach of the kernel, the read
+    // position counting from the first of them, and half the kernel from 0 to its edge
+    history: Vec<f32>,
+    history_position: f64,
+    kernel: Vec<f32>,
+}
+
+impl SampleRateConversion {
+    /// Create a linear converter between two sample rates for the given channel count
+    pub fn new(input_rate: u32, output_rate: u32, channels: usize) -> Self {
+        Self::with_quality(input_rate, output_rate, channels, ResampleQuality::Fast)
+    }
+    
+    /// Create a converter with the given interpolation quality
+    pub fn with_quality(input_rate: u32, output_rate: u32, channels: usize, quality: ResampleQuality) -> Self {
+        let channels = channels.max(1);
+        let step = input_rate as f64 / output_rate.max(1) as f64;
+        let kernel = match quality {
+            ResampleQuality::Fast => Vec::new(),
+            ResampleQuality::High => sinc_kernel(SINC_CUTOFF * step.recip().min(1.0)),
+        };
+        let mut converter = Self {
+            input_rate,
+            output_rate,
+            channels,
+            quality,
+            step,
+            position: 0.0,
+            previous_frame: vec![0.0; channels],
+            history: Vec::new(),
+            history_position: 0.0,
+            kernel,
+        };
+        converter.reset();
+        converter
+    }
+    
+    /// Get the input sample rate in Hz
+    pub fn input_rate(&self) -> u32 {
+        self.input_rate
+    }
+    
+    /// Get the output sample rate in Hz
+    pub fn output_rate(&self) -> u32 {
+        self.output_rate
+    }
+    
+    /// Get the interpolation quality
+    pub fn quality(&self) -> ResampleQuality {
+        self.quality
+    }
+    
+    /// Check whether the converter actually changes the rate
+    pub fn is_passthrough(&self) -> bool {
+        self.input_rate == self.output_rate
+    }
+    
+    /// Convert a block of interleaved input frames, appending the result to `output`
+    pub fn process(&mut self, input: &[f32], output: &mut Vec<f32>) {
+        let frames = input.len() / self.channels;
+        if frames == 0 {
+            return;
+        }
+        
+        if self.is_passthrough() {
+            output.extend_from_slice(&input[..frames * self.channels]);
+            return;
+        }
+        
+        match self.quality {
+            ResampleQuality::Fast => self.process_linear(input, frames, output),
+            ResampleQuality::High => self.process_sinc(input, frames, output),
+        }
+    }
+    
+    fn process_linear(&mut self, input: &[f32], frames: usize, output: &mut Vec<f32>) {
+        // Interpolate between frame floor(position) and the one after it, where
+        // frame -1 is the last frame of the previous block
+        let last_index = (frames - 1) as f64;
+        while self.position < last_index {
+            let index = self.position.floor();
+            let frac = (self.position - index) as f32;
+            let index = index as isize;
+            for channel in 0..self.channels {
+                let current = if index < 0 {
+                    self.previous_frame[channel]
+                } else {
+                    input[index as usize * self.channels + channel]
+                };
+                let next = input[(index + 1) as usize * self.channels + channel];
+                output.push(current + frac * (next - current));
+            }
+            self.position += self.step;
+        }
+        
+        self.position -= frames as f64;
+        let last_frame = (frames - 1) * self.channels;
+        self.previous_frame.copy_from_slice(&input[last_frame..last_frame + self.channels]);
+    }
+    
+    fn process_sinc(&mut self, input: &[f32], frames: usize, output: &mut Vec<f32>) {
+        let channels = self.channels;
+        self.history.extend_from_slice(&input[..frames * channels]);
+        let available = self.history.len() / channels;
+        
+        // Each output frame weighs the frames within SINC_HALF_TAPS of the read position
+        while (self.history_position.floor() as usize) + SINC_HALF_TAPS < available {
+            let index = self.history_position.floor() as usize;
+            let frac = self.history_position - index as f64;
+            let first = index + 1 - SINC_HALF_TAPS;
+            let out_start = output.len();
+            output.resize(out_start + channels, 0.0);
+            
+            for tap in first..=index + SINC_HALF_TAPS {
+                let weight = self.kernel_at((tap as f64 - index as f64 - frac).abs());
+                let frame = &self.history[tap * channels..(tap + 1) * channels];
+                for (out, &sample) in output[out_start..].iter_mut().zip(frame) {
+                    *out += weight * sample;
+                }
+            }
+            self.history_position += self.step;
+        }
+        
+        // Drop the frames the kernel can no longer reach
+        let consumed = (self.history_position.floor() as usize + 1).saturating_sub(SINC_HALF_TAPS).min(available);
+        self.history.drain(..consumed * channels);
+        self.history_position -= consumed as f64;
+    }
+    
+    /// Kernel value at a distance in input frames, zero outside the window
+    fn kernel_at(&self, distance: f64) -> f32 {
+        let scaled = distance * SINC_TABLE_STEPS as f64;
+        let index = scaled.floor() as usize;
+        if index + 1 >= self.kernel.len() {
+            return 0.0;
+        }
+        let frac = (scaled - index as f64) as f32;
+        self.kernel[index] + frac * (self.kernel[index + 1] - self.kernel[index])
+    }
+    
+    /// Describe the conversion for status reports, e.g. "sinc 44100 -> 48000 Hz"
+    pub fn describe(&self) -> String {
+        if self.is_passthrough() {
+            return "off".to_string();
+        }
+        format!("{} {} -> {} Hz", self.quality.resampler_name(), self.input_rate, self.output_rate)
+    }
+    
+    /// Clear the carried-over state
+    pub fn reset(&mut self) {
+        self.position = 0.0;
+        self.previous_frame.fill(0.0);
+        
+        // Start with silence before the first frame so output lines up with the input
+        self.history.clear();
+        self.history.resize(SINC_HALF_TAPS * self.channels, 0.0);
+        self.history_position = SINC_HALF_TAPS as f64;
+    }
+}
+
+/// Half of a Blackman-windowed sinc low-pass, sampled from its centre to its edge
+///
+/// `cutoff` is relative to the input Nyquist frequency. The gain is normalised to
+/// unity at DC.
+fn sinc_kernel(cutoff: f64) -> Vec<f32> {
+    let half_width = SINC_HALF_TAPS as f64;
+    let len = SINC_HALF_TAPS * SINC_TABLE_STEPS + 1;
+    let kernel: Vec<f64> = (0..len)
+        .map(|i| {
+            let t = i as f64 / SINC_TABLE_STEPS as f64;
+            let x = PI * cutoff * t;
+            let sinc = if x == 0.0 { 1.0 } else { x.sin() / x };
+            let phase = PI * t / half_width;
+            let window = 0.42 + 0.5 * phase.cos() + 0.08 * (2.0 * phase).cos();
+            cutoff * sinc * window
+        })
+        .collect();
+    
+    // Taps at whole-frame distances sum to the DC gain
+    let dc_gain = kernel[0] + 2.0 * (1..SINC_HALF_TAPS).map(|tap| kernel[tap * SINC_TABLE_STEPS]).sum::<f64>();
+    kernel.iter().map(|&value| (value / dc_gain) as f32).collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_sine_converts_from_44100_to_48000() {
+        let frequency = 1000.0;
+        let input: Vec<f32> = (0..44100)
+            .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / 44100.0).sin())
+            .collect();
+        
+        // Feed in callback-sized blocks so state is carried across boundaries
+        let mut converter = SampleRateConversion::new(44100, 48000, 1);
+        let mut output = Vec::new();
+        for block in input.chunks(441) {
+            converter.process(block, &mut output);
+        }
+        
+        let ratio = output.len() as f32 / input.len() as f32;
+        assert!((ratio - 48000.0 / 44100.0).abs() < 0.001, "length ratio {}", ratio);
+        
+        // The waveform is the same sine sampled at the new rate
+        for (i, &sample) in output.iter().enumerate().take(47000) {
+            let expected = (2.0 * std::f32::consts::PI * frequency * i as f32 / 48000.0).sin();
+            assert!((sample - expected).abs() < 0.01, "sample {} off: {} vs {}", i, sample, expected);
+        }
+    }
+    
+    /// Energy above `above_hz` in Hann-windowed blocks of the signal
+    fn energy_above(signal: &[f32], sample_rate: f32, above_hz: f32) -> f64 {
+        const BLOCK: usize = 2048;
+        let first_bin = (above_hz / sample_rate * BLOCK as f32).ceil() as usize;
+        let mut energy = 0.0;
+        for block in signal.chunks_exact(BLOCK) {
+            let windowed: Vec<f64> = block
+                .iter()
+                .enumerate()
+                .map(|(i, &x)| x as f64 * (0.5 - 0.5 * (2.0 * PI * i as f64 / BLOCK as f64).cos()))
+                .collect();
+            for bin in first_bin..=BLOCK / 2 {
+                let (mut re, mut im) = (0.0, 0.0);
+                for (i, &x) in windowed.iter().enumerate() {
+                    let phase = 2.0 * PI * (bin * i % BLOCK) as f64 / BLOCK as f64;
+                    re += x * phase.cos();
+                    im -= x * phase.sin();
+                }
+                energy += re * re + im * im;
+            }
+        }
+        energy
+    }
+    
+    #[test]
+    fn test_high_quality_aliases_less_on_a_sweep() {
+        // Exponential sweep from 100 Hz to 20 kHz over one second
+        let (start_hz, end_hz) = (100.0f64, 20000.0f64);
+        let sweep: Vec<f32> = (0..44100)
+            .map(|i| {
+                let t = i as f64 / 44100.0;
+                let rate = (end_hz / start_hz).ln();
+                (2.0 * PI * start_hz * ((rate * t).exp() - 1.0) / rate).sin() as f32 * 0.5
+            })
+            .collect();
+        
+        let convert = |quality| {
+            let mut converter = SampleRateConversion::with_quality(44100, 48000, 1, quality);
+            let mut output = Vec::new();
+            for block in sweep.chunks(441) {
+                converter.process(block, &mut output);
+            }
+            output
+        };
+        let fast = convert(ResampleQuality::Fast);
+        let high = convert(ResampleQuality::High);
+        
+        // Both keep the length ratio; the sinc converter holds back its lookahead
+        assert!((fast.len() as i64 - 48000).abs() <= 1, "{}", fast.len());
+        assert!((high.len() as i64 + SINC_HALF_TAPS as i64 - 48000).abs() <= 2, "{}", high.len());
+        
+        // Anything above the original Nyquist frequency is an image of the sweep
+        let fast_aliasing = energy_above(&fast, 48000.0, 22050.0);
+        let high_aliasing = energy_above(&high, 48000.0, 22050.0);
+        assert!(high_aliasing * 10.0 < fast_aliasing, "sinc {} vs linear {}", high_aliasing, fast_aliasing);
+        
+        // The sweep itself comes through at the same level
+        let fast_total = energy_above(&fast, 48000.0, 0.0);
+        let high_total = energy_above(&high, 48000.0, 0.0);
+        assert!((high_total / fast_total - 1.0).abs() < 0.2, "sinc {} vs linear {}", high_total, fast_total);
+        
+        let converter = SampleRateConversion::with_quality(44100, 48000, 2, ResampleQuality::High);
+        assert_eq!(converter.describe(), "sinc 44100 -> 48000 Hz");
+    }
+}
@@ -0,0 +1,151 @@
+use crossbeam::queue::ArrayQueue;
+use serde::Serialize;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
+use std::sync::Arc;
+
+/// Stereo frames queued between the input and output callbacks
+pub const AUDIO_RING_FRAMES: usize = 2048;
+
+/// How often the ring between the callbacks ran dry or overflowed
+///
+/// Counted once per callback rather than per sample, so the numbers say how
+/// many buffers were affected. Kept by the processor across runs until reset.
+#[derive(Debug, Default)]
+pub struct XrunCounters {
+    underruns: AtomicU64,
+    overruns: AtomicU64,
+}
+
+/// Snapshot of the xrun counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Serialize)]
+pub struct XrunReport {
+    /// Output callbacks that found the ring empty and played silence
+    pub underruns: u64,
+    /// Input callbacks that found the ring full and dropped samples
+    pub overruns: u64,
+}
+
+impl XrunCounters {
+    /// Current counts
+    pub fn report(&self) -> XrunReport {
+        XrunReport {
+            underruns: self.underruns.load(Ordering::Relaxed),
+            overruns: self.overruns.load(Ordering::Relaxed),
+        }
+    }
+    
+    /// Start counting afresh
+    pub fn reset(&self) {
+        self.underruns.store(0, Ordering::Relaxed);
+        self.overruns.store(0, Ordering::Relaxed);
+    }
+}
+
+/// Lock-free queue of processed stereo frames from the input to the output callback
+///
+/// Neither side ever blocks: the input drops the frames that do not fit and
+/// the output plays silence for the frames that have not arrived. Underruns
+/// only count once audio has started arriving, so the output starting ahead
+/// of the input is not reported.
+#[derive(Debug)]
+pub struct AudioRing {
+    queue: ArrayQueue<(f32, f32)>,
+    primed: AtomicBool,
+    xruns: Arc<XrunCounters>,
+}
+
+impl AudioRing {
+    /// Create a ring holding `frames` stereo frames that reports into `xruns`
+    pub fn new(frames: usize, xruns: Arc<XrunCounters>) -> Self {
+        Self {
+            queue: ArrayQueue::new(frames.max(1)),
+            primed: AtomicBool::new(false),
+            xruns,
+        }
+    }
+    
+    /// Frames waiting for the output
+    pub fn len(&self) -> usize {
+        self.queue.len()
+    }
+    
+    /// Whether no frames are waiting
+    pub fn is_empty(&self) -> bool {
+        self.queue.is_empty()
+    }
+    
+    /// Queue interleaved stereo samples, counting an overrun if any are dropped
+    pub fn push_interleaved(&self, samples: &[f32]) {
+        let mut dropped = false;
+        for frame in samples.chunks_exact(2) {
+            if self.queue.push((frame[0], frame[1])).is_err() {
+                dropped = true;
+                break;
+            }
+        }
+        if !samples.is_empty() {
+            self.primed.store(true, Ordering::Relaxed);
+        }
+        if dropped {
+            self.xruns.overruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// Hand `frames` frames to `write`, padding with silence and counting an underrun if the ring runs dry
+    pub fn read_frames(&self, frames: usize, mut write: impl FnMut(f32, f32)) {
+        let mut starved = false;
+        for _ in 0..frames {
+            let (left, right) = self.queue.pop().unwrap_or_else(|| {
+                starved = true;
+                (0.0, 0.0)
+            });
+            write(left, right);
+        }
+        if starved && self.primed.load(Ordering::Relaxed) {
+            self.xruns.underruns.fetch_add(1, Ordering::Relaxed);
+        }
+    }
+    
+    /// Drop the queued frames, e.g. before restarting a paused output
+    pub fn clear(&self) {
+        while self.queue.pop().is_some() {}
+        self.primed.store(false, Ordering::Relaxed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_undersized_ring_counts_underruns_and_overruns() {
+        let xruns = Arc::new(XrunCounters::default());
+        let ring = AudioRing::new(4, Arc::clone(&xruns));
+        
+        // The output starting before any input is not an underrun
+        ring.read_frames(4, |_, _| {});
+        assert_eq!(xruns.report(), XrunReport::default());
+        
+        // A 6 frame input block into a 4 frame ring drops 2 frames
+        let block: Vec<f32> = (0..12).map(|i| i as f32).collect();
+        ring.push_interleaved(&block);
+        assert_eq!(xruns.report().overruns, 1);
+        assert_eq!(ring.len(), 4);
+        
+        // Frames come out in order, then the 8 frame output callback runs dry
+        let mut output = Vec::new();
+        ring.read_frames(8, |left, right| output.push((left, right)));
+        assert_eq!(&output[..2], &[(0.0, 1.0), (2.0, 3.0)]);
+        assert_eq!(&output[4..], &[(0.0, 0.0); 4]);
+        assert_eq!(xruns.report(), XrunReport { underruns: 1, overruns: 1 });
+        
+        // A ring sized for the blocks does neither
+        let roomy = AudioRing::new(8, Arc::clone(&xruns));
+        roomy.push_interleaved(&block);
+        roomy.read_frames(6, |_, _| {});
+        assert_eq!(xruns.report(), XrunReport { underruns: 1, overruns: 1 });
+        
+        xruns.reset();
+        assert_eq!(xruns.report(), XrunReport::default());
+    }
+}
@@ -0,0 +1,359 @@
+use serde::Serialize;
+
+use crate::config::AudioConfig;
+use crate::delay::{
+    StereoDelay, MAX_AUTO_WIDTH_TARGET, MAX_CROSS_FEEDBACK, MAX_DUB_LFO_DEPTH, MAX_DUB_LFO_RATE, MAX_FADE_MS, MAX_FEEDBACK,
+    MAX_DELAY_SPREAD, MAX_DELAY_SWEEP_AMOUNT, MAX_DI_SIM_RESONANCE, MAX_FEEDBACK_BIT_DEPTH, MAX_FREEZE_CROSSFADE_MS,
+    MAX_OUTPUT_CEILING_DB, MAX_WET_LIMITER_THRESHOLD_DB, MIN_FEEDBACK_BIT_DEPTH, MIN_OUTPUT_CEILING_DB,
+    MIN_WET_LIMITER_THRESHOLD_DB,
+};
+use crate::distortion::DistortionType;
+use crate::double_track::MAX_DOUBLE_TRACK_DETUNE_CENTS;
+use crate::dynamics::{
+    MAX_AUTO_FREEZE_THRESHOLD_DB, MAX_COMP_ATTACK_MS, MAX_COMP_MAKEUP_DB, MAX_COMP_RATIO, MAX_COMP_RELEASE_MS, MAX_SWELL_TIME_MS,
+    MIN_AUTO_FREEZE_THRESHOLD_DB, MIN_COMP_ATTACK_MS, MIN_COMP_RELEASE_MS, MIN_COMP_THRESHOLD_DB, MIN_SWELL_TIME_MS,
+};
+use crate::error::AudioProcessorError;
+use crate::filters::FULL_RANGE_HZ;
+use crate::metronome::{DEFAULT_METRONOME_BPM, MAX_METRONOME_SUBDIVISION};
+use crate::smoothing::{MAX_SMOOTHING_MS, SMOOTHED_PARAMETERS};
+
+/// How a parameter's value is interpreted
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ParameterKind {
+    /// Any value in the range
+    Continuous,
+    /// Whole numbers in the range
+    Integer,
+    /// 0.0 for off, 1.0 for on
+    Toggle,
+    /// The index of one of the named options
+    Choice(&'static [&'static str]),
+}
+
+/// Range and unit of a parameter accepted by `set_stereo_delay_parameter`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ParameterSpec {
+    pub name: &'static str,
+    pub min: f32,
+    pub max: f32,
+    /// Unit of the value, empty for plain amounts
+    pub unit: &'static str,
+    pub kind: ParameterKind,
+}
+
+impl ParameterSpec {
+    const fn continuous(name: &'static str, min: f32, max: f32, unit: &'static str) -> Self {
+        Self { name, min, max, unit, kind: ParameterKind::Continuous }
+    }
+    
+    const fn integer(name: &'static str, min: f32, max: f32) -> Self {
+        Self { name, min, max, unit: "", kind: ParameterKind::Integer }
+    }
+    
+    const fn toggle(name: &'static str) -> Self {
+        Self { name, min: 0.0, max: 1.0, unit: "", kind: ParameterKind::Toggle }
+    }
+    
+    const fn choice(name: &'static str, options: &'static [&'static str]) -> Self {
+        Self { name, min: 0.0, max: (options.len() - 1) as f32, unit: "", kind: ParameterKind::Choice(options) }
+    }
+    
+    /// Check that a value is inside the range
+    pub fn check(&self, value: f32) -> Result<(), AudioProcessorError> {
+        if (self.min..=self.max).contains(&value) {
+            Ok(())
+        } else {
+            Err(AudioProcessorError::InvalidParameter {
+                param: self.name.to_string(),
+                value,
+                min: self.min,
+                max: self.max,
+            })
+        }
+    }
+}
+
+/// Distortion type names in parameter order
+const DISTORTION_TYPES: [&str; 7] = ["soft_clip", "hard_clip", "tube", "fuzz", "bit_crush", "waveshaper", "none"];
+
+/// Every parameter that can be set by name, except the per-parameter `smoothing_*` times
+///
+/// The configuration is validated against these ranges, so the schema served to
+/// the web UI cannot drift from what is accepted.
+pub const PARAMETERS: &[ParameterSpec] = &[
+    ParameterSpec::continuous("bpm", 20.0, 300.0, "bpm"),
+    ParameterSpec::continuous("left_delay", 0.001, 4.0, "s"),
+    ParameterSpec::continuous("right_delay", 0.001, 4.0, "s"),
+    ParameterSpec::toggle("allow_self_oscillation"),
+    ParameterSpec::continuous("feedback", -MAX_FEEDBACK, MAX_FEEDBACK, ""),
+    ParameterSpec::continuous("wet_mix", 0.0, 1.0, ""),
+    ParameterSpec::choice("mix_law", &["linear", "equal_power"]),
+    ParameterSpec::choice("interpolation", &["linear", "none"]),
+    ParameterSpec::toggle("ping_pong"),
+    ParameterSpec::choice("ping_pong_mode", &["swap", "bounce"]),
+    ParameterSpec::choice("ping_pong_start", &["left", "right"]),
+    ParameterSpec::choice("tap_pan_mode", &["manual", "spread", "alternate"]),
+    ParameterSpec::continuous("tap_spread", 0.0, 1.0, ""),
+    ParameterSpec::continuous("delay_glide", 0.0, MAX_SMOOTHING_MS, "ms"),
+    ParameterSpec::continuous("stereo_width", 0.0, 1.0, ""),
+    ParameterSpec::toggle("mono_safe"),
+    ParameterSpec::toggle("mono_mode"),
+    ParameterSpec::toggle("wet_limiter_enabled"),
+    ParameterSpec::continuous("wet_limiter_threshold", MIN_WET_LIMITER_THRESHOLD_DB, MAX_WET_LIMITER_THRESHOLD_DB, "dB"),
+    ParameterSpec::toggle("double_track_enabled"),
+    ParameterSpec::continuous("double_track_spread", 0.0, 1.0, ""),
+    ParameterSpec::continuous("double_track_detune", 0.0, MAX_DOUBLE_TRACK_DETUNE_CENTS, "cents"),
+    ParameterSpec::toggle("auto_width_enabled"),
+    ParameterSpec::continuous("auto_width_target_correlation", 0.0, MAX_AUTO_WIDTH_TARGET, ""),
+    ParameterSpec::toggle("latency_compensation"),
+    ParameterSpec::continuous("cross_feedback", 0.0, MAX_CROSS_FEEDBACK, ""),
+    ParameterSpec::continuous("feedback_tilt", -1.0, 1.0, ""),
+    ParameterSpec::continuous("feedback_saturation", 0.0, 1.0, ""),
+    ParameterSpec::choice("feedback_color", &["custom", "digital", "tape", "bbd", "dub"]),
+    ParameterSpec::choice("feedback_stage_order", &["filter_first", "distortion_first"]),
+    ParameterSpec::continuous("feedback_bit_depth", MIN_FEEDBACK_BIT_DEPTH, MAX_FEEDBACK_BIT_DEPTH, "bits"),
+    ParameterSpec::continuous("shimmer_amount", 0.0, 1.0, ""),
+    ParameterSpec::continuous("shimmer_pitch_mix", 0.0, 1.0, ""),
+    ParameterSpec::continuous("shimmer_diffusion", 0.0, 1.0, ""),
+    ParameterSpec::continuous("bpm_glide_ms", 0.0, MAX_SMOOTHING_MS, "ms"),
+    ParameterSpec::toggle("delay_sweep_trigger"),
+    ParameterSpec::continuous("delay_sweep_amount", -MAX_DELAY_SWEEP_AMOUNT, MAX_DELAY_SWEEP_AMOUNT, ""),
+    ParameterSpec::continuous("delay_spread", 0.0, MAX_DELAY_SPREAD, ""),
+    ParameterSpec::continuous("feedback_hp_hz", 0.0, FULL_RANGE_HZ, "Hz"),
+    ParameterSpec::continuous("feedback_lp_hz", 20.0, FULL_RANGE_HZ, "Hz"),
+    ParameterSpec::continuous("dub_cutoff", 20.0, FULL_RANGE_HZ, "Hz"),
+    ParameterSpec::continuous("dub_resonance", 0.0, 1.0, ""),
+    ParameterSpec::continuous("dub_lfo_rate", 0.0, MAX_DUB_LFO_RATE, "Hz"),
+    ParameterSpec::continuous("dub_lfo_depth", 0.0, MAX_DUB_LFO_DEPTH, "oct"),
+    ParameterSpec::toggle("dub_lfo_sync"),
+    ParameterSpec::continuous("dub_lfo_division", 1.0 / 64.0, 4.0, ""),
+    ParameterSpec::choice("input_source", &["instrument", "line"]),
+    ParameterSpec::toggle("di_sim_enabled"),
+    ParameterSpec::continuous("di_sim_resonance", 0.0, MAX_DI_SIM_RESONANCE, ""),
+    ParameterSpec::toggle("dc_block"),
+    ParameterSpec::continuous("eq_tilt", -1.0, 1.0, ""),
+    ParameterSpec::continuous("eq_mix", 0.0, 1.0, ""),
+    ParameterSpec::toggle("eq_dry_enabled"),
+    ParameterSpec::continuous("eq_dry_tilt", -1.0, 1.0, ""),
+    ParameterSpec::toggle("eq_wet_enabled"),
+    ParameterSpec::continuous("eq_wet_tilt", -1.0, 1.0, ""),
+    ParameterSpec::toggle("tails"),
+    ParameterSpec::continuous("fade_out_ms", 0.0, MAX_FADE_MS, "ms"),
+    ParameterSpec::continuous("max_output_db", MIN_OUTPUT_CEILING_DB, MAX_OUTPUT_CEILING_DB, "dB"),
+    ParameterSpec::toggle("freeze"),
+    ParameterSpec::continuous("freeze_crossfade_ms", 0.0, MAX_FREEZE_CROSSFADE_MS, "ms"),
+    ParameterSpec::toggle("freeze_kill_dry"),
+    ParameterSpec::toggle("auto_freeze_enabled"),
+    ParameterSpec::continuous("auto_freeze_threshold", MIN_AUTO_FREEZE_THRESHOLD_DB, MAX_AUTO_FREEZE_THRESHOLD_DB, "dB"),
+    ParameterSpec::toggle("spectral_freeze"),
+    ParameterSpec::toggle("monitor_dry_only"),
+    ParameterSpec::continuous("macro", 0.0, 1.0, ""),
+    ParameterSpec::choice("meter_mode", &["vu", "ppm", "rms"]),
+    ParameterSpec::toggle("comp_enabled"),
+    ParameterSpec::continuous("comp_threshold", MIN_COMP_THRESHOLD_DB, 0.0, "dB"),
+    ParameterSpec::continuous("comp_ratio", 1.0, MAX_COMP_RATIO, ":1"),
+    ParameterSpec::continuous("comp_attack", MIN_COMP_ATTACK_MS, MAX_COMP_ATTACK_MS, "ms"),
+    ParameterSpec::continuous("comp_release", MIN_COMP_RELEASE_MS, MAX_COMP_RELEASE_MS, "ms"),
+    ParameterSpec::continuous("comp_makeup", 0.0, MAX_COMP_MAKEUP_DB, "dB"),
+    ParameterSpec::toggle("swell_enabled"),
+    ParameterSpec::continuous("swell_time_ms", MIN_SWELL_TIME_MS, MAX_SWELL_TIME_MS, "ms"),
+    ParameterSpec::continuous("swell_sensitivity", 0.0, 1.0, ""),
+    ParameterSpec::toggle("metronome_enabled"),
+    ParameterSpec::continuous("metronome_volume", 0.0, 1.0, ""),
+    ParameterSpec::integer("metronome_subdivision", 1.0, MAX_METRONOME_SUBDIVISION as f32),
+    ParameterSpec::toggle("distortion_enabled"),
+    ParameterSpec::choice("distortion_type", &DISTORTION_TYPES),
+    ParameterSpec::continuous("distortion_drive", 0.0, 1.0, ""),
+    ParameterSpec::continuous("distortion_mix", 0.0, 1.0, ""),
+    ParameterSpec::continuous("distortion_feedback_intensity", 0.0, 1.0, ""),
+    ParameterSpec::choice("distortion_placement", &["input", "feedback", "wet_output"]),
+    ParameterSpec::toggle("distortion_auto_gain_comp"),
+    ParameterSpec::toggle("distortion_dry_bypass"),
+    ParameterSpec::continuous("distortion_tone", -1.0, 1.0, ""),
+];
+
+/// Parameters in `PARAMETERS` the processors keep themselves instead of passing to the delay
+pub const PROCESSOR_PARAMETERS: [&str; 3] = ["bpm", "tails", "macro"];
+
+/// Look up the spec of a parameter by name
+pub fn parameter(name: &str) -> Option<&'static ParameterSpec> {
+    PARAMETERS.iter().find(|spec| spec.name == name)
+}
+
+/// Check a value against the range of a parameter in `PARAMETERS`
+pub fn check(name: &str, value: f32) -> Result<(), AudioProcessorError> {
+    match parameter(name) {
+        Some(spec) => spec.check(value),
+        None => Err(AudioProcessorError::Configuration(format!("Unknown parameter '{}'", name))),
+    }
+}
+
+/// One parameter as served by `GET /api/schema`
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct ParameterInfo {
+    pub name: String,
+    pub min: f32,
+    pub max: f32,
+    pub default: f32,
+    pub unit: &'static str,
+    pub kind: &'static str,
+    
+    /// Option names for choice parameters, indexed by value
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub options: Vec<&'static str>,
+}
+
+/// Everything a UI needs to build its controls
+#[derive(Debug, Clone, PartialEq, Serialize)]
+pub struct Schema {
+    pub parameters: Vec<ParameterInfo>,
+    pub distortion_types: Vec<String>,
+}
+
+impl Schema {
+    /// Look up a parameter by name
+    pub fn parameter(&self, name: &str) -> Option<&ParameterInfo> {
+        self.parameters.iter().find(|info| info.name == name)
+    }
+}
+
+/// Build the schema, with defaults taken from the default configuration
+pub fn schema() -> Schema {
+    let config = AudioConfig::default();
+    let delay = StereoDelay::from_config(&config);
+    let defaults = delay.get_parameters();
+    let default_of = |name: &str| match name {
+        "bpm" => config.stereo_delay.bpm.unwrap_or(DEFAULT_METRONOME_BPM),
+        "tails" => if config.stereo_delay.tails { 1.0 } else { 0.0 },
+        "macro" => config.macro_control.position,
+        _ => defaults.get(name).copied().unwrap_or(0.0),
+    };
+    
+    let mut parameters: Vec<ParameterInfo> = PARAMETERS
+        .iter()
+        .map(|spec| {
+            let (kind, options) = match spec.kind {
+                ParameterKind::Continuous => ("continuous", Vec::new()),
+                ParameterKind::Integer => ("integer", Vec::new()),
+                ParameterKind::Toggle => ("toggle", Vec::new()),
+                ParameterKind::Choice(options) => ("choice", options.to_vec()),
+            };
+            ParameterInfo {
+                name: spec.name.to_string(),
+                min: spec.min,
+                max: spec.max,
+                default: default_of(spec.name),
+                unit: spec.unit,
+                kind,
+                options,
+            }
+        })
+        .collect();
+    
+    // Each smoothed parameter has its own ramp time
+    parameters.extend(SMOOTHED_PARAMETERS.iter().map(|param| ParameterInfo {
+        name: format!("smoothing_{}", param),
+        min: 0.0,
+        max: MAX_SMOOTHING_MS,
+        default: delay.get_smoothing_time(param).unwrap_or(0.0),
+        unit: "ms",
+        kind: "continuous",
+        options: Vec::new(),
+    }));
+    
+    Schema {
+        parameters,
+        distortion_types: DistortionType::ALL.iter().map(|distortion_type| distortion_type.to_string()).collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::delay::{FeedbackColor, FeedbackStageOrder, InputSource, InterpolationMode, MixLaw, PingPongMode, PingPongStart, TapPanMode};
+    use crate::distortion::DistortionPlacement;
+    use crate::meter::MeterMode;
+    
+    #[test]
+    fn test_schema_lists_feedback_and_distortion_types() {
+        let schema = schema();
+        
+        let feedback = schema.parameter("feedback").unwrap();
+        assert_eq!((feedback.min, feedback.max), (-0.9, 0.9));
+        assert_eq!(feedback.default, AudioConfig::default().stereo_delay.feedback);
+        assert_eq!(feedback.kind, "continuous");
+        
+        for name in ["soft_clip", "hard_clip", "tube", "fuzz", "bit_crush", "waveshaper"] {
+            assert!(schema.distortion_types.iter().any(|distortion_type| distortion_type == name), "{} missing", name);
+        }
+        
+        let smoothing = schema.parameter("smoothing_feedback").unwrap();
+        assert_eq!((smoothing.min, smoothing.max, smoothing.unit), (0.0, MAX_SMOOTHING_MS, "ms"));
+    }
+    
+    #[test]
+    fn test_every_parameter_is_described() {
+        let delay = StereoDelay::from_config(&AudioConfig::default());
+        for name in delay.get_parameters().keys() {
+            assert!(parameter(name).is_some(), "{} has no schema entry", name);
+        }
+        
+        // Defaults lie inside their own ranges
+        for info in schema().parameters {
+            assert!((info.min..=info.max).contains(&info.default), "{:?}", info);
+        }
+    }
+    
+    #[test]
+    fn test_choice_options_match_the_enums() {
+        let options = |name: &str| match parameter(name).unwrap().kind {
+            ParameterKind::Choice(options) => options,
+            kind => panic!("{} is {:?}", name, kind),
+        };
+        
+        for (index, option) in options("mix_law").iter().enumerate() {
+            assert_eq!(MixLaw::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("interpolation").iter().enumerate() {
+            assert_eq!(InterpolationMode::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("ping_pong_mode").iter().enumerate() {
+            assert_eq!(PingPongMode::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("ping_pong_start").iter().enumerate() {
+            assert_eq!(PingPongStart::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("tap_pan_mode").iter().enumerate() {
+            assert_eq!(TapPanMode::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("feedback_color").iter().enumerate() {
+            assert_eq!(FeedbackColor::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("feedback_stage_order").iter().enumerate() {
+            assert_eq!(FeedbackStageOrder::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("input_source").iter().enumerate() {
+            assert_eq!(InputSource::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("meter_mode").iter().enumerate() {
+            assert_eq!(MeterMode::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("distortion_placement").iter().enumerate() {
+            assert_eq!(DistortionPlacement::from_parameter(index as f32).to_string(), *option);
+        }
+        for (index, option) in options("distortion_type").iter().enumerate() {
+            assert_eq!(DistortionType::from_parameter(index as f32).to_string(), *option);
+        }
+    }
+    
+    #[test]
+    fn test_check_uses_the_parameter_range() {
+        assert!(check("wet_mix", 0.5).is_ok());
+        assert!(matches!(
+            check("wet_mix", 1.5),
+            Err(AudioProcessorError::InvalidParameter { ref param, min, max, .. }) if param == "wet_mix" && min == 0.0 && max == 1.0
+        ));
+        assert!(check("not_a_parameter", 0.0).is_err());
+    }
+}
@@ -0,0 +1,205 @@
+use crate::delay::read_interpolated;
+
+/// Default octave-up share of the repeats at full shimmer
+pub const DEFAULT_SHIMMER_PITCH_MIX: f32 = 0.5;
+
+/// Default diffusion of the repeats at full shimmer
+pub const DEFAULT_SHIMMER_DIFFUSION: f32 = 0.7;
+
+/// Length of the pitch shifter's grains in seconds
+const PITCH_WINDOW_S: f32 = 0.05;
+
+/// Delay times of the diffusion allpasses in milliseconds, spread so their echoes do not line up
+const DIFFUSER_DELAYS_MS: [f32; 4] = [5.1, 7.7, 10.0, 12.6];
+
+/// Extra delay of the right diffuser, so the two sides smear differently
+const DIFFUSER_STEREO_OFFSET_MS: f32 = 0.5;
+
+/// Allpass coefficient of the diffuser stages
+const DIFFUSER_GAIN: f32 = 0.6;
+
+/// Octave-up pitch shifter built from two crossfaded read heads
+///
+/// Each head reads the line at a delay that shrinks by one sample per sample,
+/// so it plays back at twice the speed, and jumps back by a window when it
+/// reaches the write position. The heads are half a window apart and faded with
+/// sin² windows, which add up to one, so the jumps are never heard.
+struct OctaveShifter {
+    buffer: Vec<f32>,
+    write_index: usize,
+    window: f32,
+    phase: f32,
+}
+
+impl OctaveShifter {
+    fn new(sample_rate: u32) -> Self {
+        let window = (PITCH_WINDOW_S * sample_rate as f32).round().max(4.0);
+        Self {
+            buffer: vec![0.0; window as usize + 2],
+            write_index: 0,
+            window,
+            phase: 0.0,
+        }
+    }
+    
+    fn process(&mut self, input: f32) -> f32 {
+        self.buffer[self.write_index] = input;
+        self.write_index = (self.write_index + 1) % self.buffer.len();
+        
+        let mut output = 0.0;
+        for offset in [0.0, 0.5] {
+            let phase = (self.phase + offset).fract();
+            let delay = 1.0 + (1.0 - phase) * self.window;
+            let gain = (std::f32::consts::PI * phase).sin().powi(2);
+            output += gain * read_interpolated(&self.buffer, self.write_index, delay);
+        }
+        self.phase = (self.phase + 1.0 / self.window).fract();
+        output
+    }
+    
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.write_index = 0;
+        self.phase = 0.0;
+    }
+}
+
+/// Schroeder allpass: passes every frequency at unity gain but smears it in time
+struct Allpass {
+    buffer: Vec<f32>,
+    index: usize,
+}
+
+impl Allpass {
+    fn new(delay_ms: f32, sample_rate: u32) -> Self {
+        let len = (delay_ms / 1000.0 * sample_rate as f32).round().max(1.0) as usize;
+        Self { buffer: vec![0.0; len], index: 0 }
+    }
+    
+    fn process(&mut self, input: f32) -> f32 {
+        let delayed = self.buffer[self.index];
+        let output = delayed - DIFFUSER_GAIN * input;
+        self.buffer[self.index] = input + DIFFUSER_GAIN * output;
+        self.index = (self.index + 1) % self.buffer.len();
+        output
+    }
+    
+    fn reset(&mut self) {
+        self.buffer.fill(0.0);
+        self.index = 0;
+    }
+}
+
+/// One side of the shimmer: the octave shifter followed by the diffuser
+struct ShimmerChannel {
+    shifter: OctaveShifter,
+    diffuser: Vec<Allpass>,
+}
+
+impl ShimmerChannel {
+    fn new(sample_rate: u32, offset_ms: f32) -> Self {
+        Self {
+            shifter: OctaveShifter::new(sample_rate),
+            diffuser: DIFFUSER_DELAYS_MS.iter().map(|delay_ms| Allpass::new(delay_ms + offset_ms, sample_rate)).collect(),
+        }
+    }
+    
+    fn process(&mut self, input: f32, pitch_mix: f32, diffusion: f32) -> f32 {
+        let shifted = self.shifter.process(input);
+        let pitched = input + pitch_mix * (shifted - input);
+        let diffused = self.diffuser.iter_mut().fold(pitched, |sample, allpass| allpass.process(sample));
+        pitched + diffusion * (diffused - pitched)
+    }
+    
+    fn reset(&mut self) {
+        self.shifter.reset();
+        self.diffuser.iter_mut().for_each(Allpass::reset);
+    }
+}
+
+/// Shimmer: octave-up pitch shifting and diffusion of the repeats
+///
+/// Runs in the feedback path, so each pass around the loop climbs another
+/// octave and blurs further into a reverb-like pad. A single amount (0.0 off to
+/// 1.0) scales both: the octave-up share of the repeats up to `pitch_mix` and
+/// the diffusion up to `diffusion`. Both are crossfades against the unprocessed
+/// repeats with stages that keep their level, so the loop gain never grows.
+pub struct Shimmer {
+    channels: [ShimmerChannel; 2],
+    amount: f32,
+    pitch_mix: f32,
+    diffusion: f32,
+}
+
+impl Shimmer {
+    /// Create a shimmer, switched off, with the default pitch mix and diffusion
+    pub fn new(sample_rate: u32) -> Self {
+        Self {
+            channels: [
+                ShimmerChannel::new(sample_rate, 0.0),
+                ShimmerChannel::new(sample_rate, DIFFUSER_STEREO_OFFSET_MS),
+            ],
+            amount: 0.0,
+            pitch_mix: DEFAULT_SHIMMER_PITCH_MIX,
+            diffusion: DEFAULT_SHIMMER_DIFFUSION,
+        }
+    }
+    
+    /// Set the shimmer intensity (0.0 off to 1.0)
+    pub fn set_amount(&mut self, amount: f32) {
+        let amount = amount.clamp(0.0, 1.0);
+        if amount > 0.0 && self.amount == 0.0 {
+            self.reset();
+        }
+        self.amount = amount;
+    }
+    
+    /// Get the shimmer intensity
+    pub fn get_amount(&self) -> f32 {
+        self.amount
+    }
+    
+    /// Set the octave-up share of the repeats at full intensity (0.0 to 1.0)
+    pub fn set_pitch_mix(&mut self, pitch_mix: f32) {
+        self.pitch_mix = pitch_mix.clamp(0.0, 1.0);
+    }
+    
+    /// Get the octave-up share of the repeats at full intensity
+    pub fn get_pitch_mix(&self) -> f32 {
+        self.pitch_mix
+    }
+    
+    /// Set the diffusion of the repeats at full intensity (0.0 to 1.0)
+    pub fn set_diffusion(&mut self, diffusion: f32) {
+        self.diffusion = diffusion.clamp(0.0, 1.0);
+    }
+    
+    /// Get the diffusion of the repeats at full intensity
+    pub fn get_diffusion(&self) -> f32 {
+        self.diffusion
+    }
+    
+    /// Check whether the shimmer changes the repeats at all
+    pub fn is_active(&self) -> bool {
+        self.amount > 0.0
+    }
+    
+    /// Shimmer a stereo pair of repeats
+    pub fn process(&mut self, left: f32, right: f32) -> (f32, f32) {
+        let (pitch_mix, diffusion) = (self.amount * self.pitch_mix, self.amount * self.diffusion);
+        (
+            self.channels[0].process(left, pitch_mix, diffusion),
+            self.channels[1].process(right, pitch_mix, diffusion),
+        )
+    }
+    
+    /// Shimmer a single repeat on the left channel's stages
+    pub fn process_mono(&mut self, input: f32) -> f32 {
+        self.channels[0].process(input, self.amount * self.pitch_mix, self.amount * self.diffusion)
+    }
+    
+    /// Clear the pitch shifter and diffuser lines
+    pub fn reset(&mut self) {
+        self.channels.iter_mut().for_each(ShimmerChannel::reset);
+    }
+}
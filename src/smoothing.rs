@@ -0,0 +1,145 @@
+/// Longest ramp time accepted for any smoothed parameter in milliseconds
+pub const MAX_SMOOTHING_MS: f32 = 5000.0;
+
+/// Parameters that are ramped instead of jumping to a new value
+pub const SMOOTHED_PARAMETERS: &[&str] = &[
+    "left_delay",
+    "right_delay",
+    "feedback",
+    "wet_mix",
+];
+
+/// Default ramp time for a smoothed parameter in milliseconds
+///
+/// Delay times ramp slowly because moving the read tap quickly bends the pitch of
+/// the repeats. Gains only need to be long enough to avoid zipper noise.
+pub fn default_smoothing_ms(param: &str) -> Option<f32> {
+    match param {
+        "left_delay" | "right_delay" => Some(200.0),
+        "feedback" => Some(20.0),
+        "wet_mix" => Some(10.0),
+        _ => None,
+    }
+}
+
+/// Value that ramps linearly towards its target over a configurable time
+pub struct SmoothedValue {
+    current: f32,
+    target: f32,
+    step: f32,
+    remaining: usize,
+    ramp_samples: usize,
+}
+
+impl SmoothedValue {
+    /// Create a new smoothed value resting at `value`
+    pub fn new(value: f32, ramp_ms: f32, sample_rate: u32) -> Self {
+        let mut smoothed = Self {
+            current: value,
+            target: value,
+            step: 0.0,
+            remaining: 0,
+            ramp_samples: 0,
+        };
+        smoothed.set_ramp_time(ramp_ms, sample_rate);
+        smoothed
+    }
+    
+    /// Set the time taken to reach a new target in milliseconds
+    ///
+    /// A ramp already in progress keeps its original speed.
+    pub fn set_ramp_time(&mut self, ramp_ms: f32, sample_rate: u32) {
+        let ramp_ms = ramp_ms.clamp(0.0, MAX_SMOOTHING_MS);
+        self.ramp_samples = (ramp_ms * 0.001 * sample_rate as f32) as usize;
+    }
+    
+    /// Get the ramp time in samples
+    pub fn ramp_samples(&self) -> usize {
+        self.ramp_samples
+    }
+    
+    /// Start ramping towards a new target from the current value
+    pub fn set_target(&mut self, target: f32) {
+        self.target = target;
+        if self.ramp_samples == 0 {
+            self.snap();
+            return;
+        }
+        self.remaining = self.ramp_samples;
+        self.step = (self.target - self.current) / self.ramp_samples as f32;
+    }
+    
+    /// Start ramping towards a new target over a one-off ramp time in milliseconds
+    ///
+    /// The ramp time set with `set_ramp_time` is kept for later targets.
+    pub fn set_target_over(&mut self, target: f32, ramp_ms: f32, sample_rate: u32) {
+        let ramp_samples = self.ramp_samples;
+        self.set_ramp_time(ramp_ms, sample_rate);
+        self.set_target(target);
+        self.ramp_samples = ramp_samples;
+    }
+    
+    /// Jump straight to the target, abandoning any ramp in progress
+    pub fn snap(&mut self) {
+        self.current = self.target;
+        self.step = 0.0;
+        self.remaining = 0;
+    }
+    
+    /// Advance the ramp by one sample and return the new value
+    pub fn next_value(&mut self) -> f32 {
+        if self.remaining > 0 {
+            self.remaining -= 1;
+            if self.remaining == 0 {
+                self.current = self.target;
+            } else {
+                self.current += self.step;
+            }
+        }
+        self.current
+    }
+    
+    /// Get the current value without advancing the ramp
+    pub fn current(&self) -> f32 {
+        self.current
+    }
+    
+    /// Get the value being ramped towards
+    pub fn target(&self) -> f32 {
+        self.target
+    }
+    
+    /// Check whether a ramp is in progress
+    pub fn is_smoothing(&self) -> bool {
+        self.remaining > 0
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_ramp_reaches_target_after_ramp_time() {
+        let mut value = SmoothedValue::new(0.0, 10.0, 48000);
+        value.set_target(1.0);
+        
+        for _ in 0..239 {
+            value.next_value();
+        }
+        assert!((value.current() - 0.5).abs() < 0.01);
+        
+        for _ in 0..241 {
+            value.next_value();
+        }
+        assert_eq!(value.current(), 1.0);
+        assert!(!value.is_smoothing());
+    }
+    
+    #[test]
+    fn test_zero_ramp_time_jumps() {
+        let mut value = SmoothedValue::new(0.2, 0.0, 48000);
+        value.set_target(0.8);
+        assert_eq!(value.current(), 0.8);
+    }
+}
@@ -0,0 +1,371 @@
+use std::f32::consts::PI;
+
+use crate::filters::sanitize_sample;
+use crate::smoothing::SmoothedValue;
+
+/// Length of the spectral freeze analysis and synthesis frames in samples
+pub const SPECTRAL_FRAME_SIZE: usize = 2048;
+
+/// Step between synthesis frames, a quarter frame for 75% overlap
+pub const SPECTRAL_HOP: usize = SPECTRAL_FRAME_SIZE / 4;
+
+/// Sum of the squared Hann windows of overlapping frames at `SPECTRAL_HOP`
+const OVERLAP_GAIN: f32 = 1.5;
+
+/// Complex number for the FFT
+#[derive(Debug, Clone, Copy, Default, PartialEq)]
+pub struct Complex {
+    pub re: f32,
+    pub im: f32,
+}
+
+impl Complex {
+    /// Create a complex number from its magnitude and phase
+    pub fn from_polar(magnitude: f32, phase: f32) -> Self {
+        Self { re: magnitude * phase.cos(), im: magnitude * phase.sin() }
+    }
+    
+    /// Get the magnitude
+    pub fn norm(&self) -> f32 {
+        self.re.hypot(self.im)
+    }
+    
+    /// Get the phase in radians
+    pub fn arg(&self) -> f32 {
+        self.im.atan2(self.re)
+    }
+    
+    fn mul(self, other: Complex) -> Complex {
+        Complex {
+            re: self.re * other.re - self.im * other.im,
+            im: self.re * other.im + self.im * other.re,
+        }
+    }
+}
+
+/// In-place radix-2 FFT of a fixed power-of-two length
+///
+/// Twiddle factors and the bit-reversal order are computed up front, so the
+/// transforms do not allocate and can run on the audio thread.
+pub struct Fft {
+    size: usize,
+    bit_reverse: Vec<usize>,
+    twiddles: Vec<Complex>,
+}
+
+impl Fft {
+    /// Create an FFT of `size` points, which must be a power of two
+    pub fn new(size: usize) -> Self {
+        assert!(size.is_power_of_two(), "FFT size {} is not a power of two", size);
+        let bits = size.trailing_zeros();
+        let bit_reverse = (0..size)
+            .map(|i| if bits == 0 { 0 } else { i.reverse_bits() >> (usize::BITS - bits) })
+            .collect();
+        let twiddles = (0..size / 2)
+            .map(|i| Complex::from_polar(1.0, -2.0 * PI * i as f32 / size as f32))
+            .collect();
+        Self { size, bit_reverse, twiddles }
+    }
+    
+    /// Get the number of points
+    pub fn size(&self) -> usize {
+        self.size
+    }
+    
+    /// Transform a frame of samples into its spectrum
+    pub fn forward(&self, buffer: &mut [Complex]) {
+        self.transform(buffer, false);
+    }
+    
+    /// Transform a spectrum back into samples, scaled so `inverse(forward(x)) == x`
+    pub fn inverse(&self, buffer: &mut [Complex]) {
+        self.transform(buffer, true);
+        let scale = 1.0 / self.size as f32;
+        for value in buffer.iter_mut() {
+            value.re *= scale;
+            value.im *= scale;
+        }
+    }
+    
+    fn transform(&self, buffer: &mut [Complex], inverse: bool) {
+        assert_eq!(buffer.len(), self.size);
+        for i in 0..self.size {
+            let j = self.bit_reverse[i];
+            if i < j {
+                buffer.swap(i, j);
+            }
+        }
+        
+        let mut length = 2;
+        while length <= self.size {
+            let half = length / 2;
+            let stride = self.size / length;
+            for start in (0..self.size).step_by(length) {
+                for k in 0..half {
+                    let mut twiddle = self.twiddles[k * stride];
+                    if inverse {
+                        twiddle.im = -twiddle.im;
+                    }
+                    let even = buffer[start + k];
+                    let odd = buffer[start + k + half].mul(twiddle);
+                    buffer[start + k] = Complex { re: even.re + odd.re, im: even.im + odd.im };
+                    buffer[start + k + half] = Complex { re: even.re - odd.re, im: even.im - odd.im };
+                }
+            }
+            length *= 2;
+        }
+    }
+}
+
+/// Wrap a phase into -π..π
+fn wrap_phase(phase: f32) -> f32 {
+    phase - 2.0 * PI * (phase / (2.0 * PI)).round()
+}
+
+/// One channel of the spectral freeze: capture history, frozen spectrum and resynthesis
+struct SpectralChannel {
+    // The most recent frame plus one hop, to measure how fast each bin's phase turns
+    history: Vec<f32>,
+    history_index: usize,
+    
+    // Frozen spectrum, one entry per bin up to Nyquist
+    magnitudes: Vec<f32>,
+    phases: Vec<f32>,
+    phase_advances: Vec<f32>,
+    
+    // Overlap-add output and the read position inside its first hop
+    output: Vec<f32>,
+    output_index: usize,
+    
+    frame: Vec<Complex>,
+}
+
+impl SpectralChannel {
+    fn new() -> Self {
+        let bins = SPECTRAL_FRAME_SIZE / 2 + 1;
+        Self {
+            history: vec![0.0; SPECTRAL_FRAME_SIZE + SPECTRAL_HOP],
+            history_index: 0,
+            magnitudes: vec![0.0; bins],
+            phases: vec![0.0; bins],
+            phase_advances: vec![0.0; bins],
+            output: vec![0.0; SPECTRAL_FRAME_SIZE],
+            output_index: SPECTRAL_HOP,
+            frame: vec![Complex::default(); SPECTRAL_FRAME_SIZE],
+        }
+    }
+    
+    fn push(&mut self, sample: f32) {
+        self.history[self.history_index] = sample;
+        self.history_index = (self.history_index + 1) % self.history.len();
+    }
+    
+    /// Load a windowed frame ending `hops_back` hops before the newest sample and transform it
+    fn analyze(&mut self, fft: &Fft, window: &[f32], hops_back: usize) {
+        let length = self.history.len();
+        let start = self.history_index + length - SPECTRAL_FRAME_SIZE - hops_back * SPECTRAL_HOP;
+        for (i, value) in self.frame.iter_mut().enumerate() {
+            *value = Complex { re: self.history[(start + i) % length] * window[i], im: 0.0 };
+        }
+        fft.forward(&mut self.frame);
+    }
+    
+    /// Freeze the spectrum of the latest frame and start resynthesizing it
+    ///
+    /// Each bin keeps its magnitude and turns its phase by the amount measured
+    /// between the last two frames, which tracks the true frequency of a partial
+    /// rather than the bin centre, so steady tones come back steady.
+    fn capture(&mut self, fft: &Fft, window: &[f32]) {
+        let bins = self.magnitudes.len();
+        self.analyze(fft, window, 1);
+        for bin in 0..bins {
+            self.phases[bin] = self.frame[bin].arg();
+        }
+        self.analyze(fft, window, 0);
+        for bin in 0..bins {
+            let expected = 2.0 * PI * bin as f32 * SPECTRAL_HOP as f32 / SPECTRAL_FRAME_SIZE as f32;
+            let phase = self.frame[bin].arg();
+            self.phase_advances[bin] = expected + wrap_phase(phase - self.phases[bin] - expected);
+            self.phases[bin] = phase;
+            self.magnitudes[bin] = self.frame[bin].norm();
+        }
+        
+        // Fill the overlap so the first hop already has every frame that covers it
+        self.output.fill(0.0);
+        for _ in 0..3 {
+            self.synthesize_hop(fft, window);
+        }
+        self.output_index = SPECTRAL_HOP;
+    }
+    
+    /// Drop the oldest hop of output and overlap-add the next frame
+    fn synthesize_hop(&mut self, fft: &Fft, window: &[f32]) {
+        self.output.copy_within(SPECTRAL_HOP.., 0);
+        let tail = SPECTRAL_FRAME_SIZE - SPECTRAL_HOP;
+        self.output[tail..].fill(0.0);
+        
+        let bins = self.magnitudes.len();
+        for bin in 0..bins {
+            self.phases[bin] = wrap_phase(self.phases[bin] + self.phase_advances[bin]);
+            self.frame[bin] = Complex::from_polar(self.magnitudes[bin], self.phases[bin]);
+        }
+        // Mirror the bins so the frame comes back real
+        for bin in 1..bins - 1 {
+            let value = self.frame[bin];
+            self.frame[SPECTRAL_FRAME_SIZE - bin] = Complex { re: value.re, im: -value.im };
+        }
+        fft.inverse(&mut self.frame);
+        
+        for (i, sample) in self.output.iter_mut().enumerate() {
+            *sample += self.frame[i].re * window[i] / OVERLAP_GAIN;
+        }
+    }
+    
+    fn next_sample(&mut self, fft: &Fft, window: &[f32]) -> f32 {
+        if self.output_index >= SPECTRAL_HOP {
+            self.synthesize_hop(fft, window);
+            self.output_index = 0;
+        }
+        let sample = self.output[self.output_index];
+        self.output_index += 1;
+        sanitize_sample(sample)
+    }
+    
+    fn reset(&mut self) {
+        self.history.fill(0.0);
+        self.history_index = 0;
+        self.magnitudes.fill(0.0);
+        self.output.fill(0.0);
+        self.output_index = SPECTRAL_HOP;
+    }
+    
+    fn memory_usage_bytes(&self) -> usize {
+        let samples = self.history.capacity()
+            + self.magnitudes.capacity()
+            + self.phases.capacity()
+            + self.phase_advances.capacity()
+            + self.output.capacity();
+        samples * std::mem::size_of::<f32>() + self.frame.capacity() * std::mem::size_of::<Complex>()
+    }
+}
+
+/// Spectral freeze: holds the spectrum of one moment as an endless pad
+///
+/// While live, the last frame of the captured signal is kept. Freezing takes its
+/// magnitude spectrum and resynthesizes it phase-vocoder style with overlapping
+/// inverse FFTs, so the pad has no loop point and no repeating rhythm, unlike
+/// the time-domain freeze of the delay lines. Entering and leaving crossfade
+/// between the live signal and the pad.
+pub struct SpectralFreeze {
+    fft: Fft,
+    window: Vec<f32>,
+    left: SpectralChannel,
+    right: SpectralChannel,
+    frozen: bool,
+    mix: SmoothedValue,
+}
+
+impl SpectralFreeze {
+    /// Create a spectral freeze with the given crossfade time
+    pub fn new(crossfade_ms: f32, sample_rate: u32) -> Self {
+        // Periodic Hann window, whose squares overlap-add to a constant at a quarter-frame hop
+        let window = (0..SPECTRAL_FRAME_SIZE)
+            .map(|i| 0.5 - 0.5 * (2.0 * PI * i as f32 / SPECTRAL_FRAME_SIZE as f32).cos())
+            .collect();
+        Self {
+            fft: Fft::new(SPECTRAL_FRAME_SIZE),
+            window,
+            left: SpectralChannel::new(),
+            right: SpectralChannel::new(),
+            frozen: false,
+            mix: SmoothedValue::new(0.0, crossfade_ms, sample_rate),
+        }
+    }
+    
+    /// Freeze the spectrum of the latest captured frame, or fade back to the live signal
+    pub fn set_frozen(&mut self, frozen: bool) {
+        if frozen && !self.frozen {
+            self.left.capture(&self.fft, &self.window);
+            self.right.capture(&self.fft, &self.window);
+        }
+        self.frozen = frozen;
+        self.mix.set_target(if frozen { 1.0 } else { 0.0 });
+    }
+    
+    /// Check whether the spectrum is frozen
+    pub fn is_frozen(&self) -> bool {
+        self.frozen
+    }
+    
+    /// Set the crossfade time between the live signal and the pad in milliseconds
+    pub fn set_crossfade(&mut self, crossfade_ms: f32, sample_rate: u32) {
+        self.mix.set_ramp_time(crossfade_ms, sample_rate);
+    }
+    
+    /// Get how far the output has crossfaded to the pad (0.0 live to 1.0 frozen)
+    pub fn mix(&self) -> f32 {
+        self.mix.current()
+    }
+    
+    /// Capture one stereo sample and crossfade the live pair with the pad
+    ///
+    /// `capture` is what a freeze takes its spectrum from; `live` is what the pad
+    /// replaces. The inverse FFTs only run while the pad is audible.
+    pub fn process(&mut self, capture: (f32, f32), live: (f32, f32)) -> (f32, f32) {
+        self.left.push(capture.0);
+        self.right.push(capture.1);
+        
+        let mix = self.mix.next_value();
+        if mix <= 0.0 {
+            return live;
+        }
+        let left_pad = self.left.next_sample(&self.fft, &self.window);
+        let right_pad = self.right.next_sample(&self.fft, &self.window);
+        (
+            live.0 * (1.0 - mix) + left_pad * mix,
+            live.1 * (1.0 - mix) + right_pad * mix,
+        )
+    }
+    
+    /// Release the freeze at once and clear the captured audio
+    pub fn reset(&mut self) {
+        self.frozen = false;
+        self.mix.set_target(0.0);
+        self.mix.snap();
+        self.left.reset();
+        self.right.reset();
+    }
+    
+    /// Get the memory held by the frames and spectra in bytes
+    pub fn memory_usage_bytes(&self) -> usize {
+        self.left.memory_usage_bytes()
+            + self.right.memory_usage_bytes()
+            + (self.window.capacity() + self.fft.twiddles.capacity() * 2) * std::mem::size_of::<f32>()
+            + self.fft.bit_reverse.capacity() * std::mem::size_of::<usize>()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    #[test]
+    fn test_fft_round_trip_finds_the_tone() {
+        let fft = Fft::new(64);
+        let original: Vec<Complex> = (0..64)
+            .map(|i| Complex { re: (2.0 * PI * 5.0 * i as f32 / 64.0).cos(), im: 0.0 })
+            .collect();
+        let mut buffer = original.clone();
+        fft.forward(&mut buffer);
+        
+        // A cosine on bin 5 puts half its energy in bin 5 and half in its mirror
+        assert!((buffer[5].norm() - 32.0).abs() < 1e-3);
+        assert!((buffer[59].norm() - 32.0).abs() < 1e-3);
+        assert!(buffer[6].norm() < 1e-3);
+        
+        fft.inverse(&mut buffer);
+        for (value, expected) in buffer.iter().zip(&original) {
+            assert!((value.re - expected.re).abs() < 1e-5 && value.im.abs() < 1e-5);
+        }
+    }
+}
@@ -0,0 +1,441 @@
+use std::fmt;
+
+use crate::config::AudioConfig;
+use crate::delay::StereoDelay;
+use crate::error::AudioProcessorError;
+
+/// Seed used for the noise generators unless another one is set
+pub const DEFAULT_SEED: u64 = 0x5eed_6017_a2c4_91d3;
+
+/// Longest test tone in seconds, so a typo cannot tie up the output for minutes
+pub const MAX_TEST_TONE_SECONDS: f32 = 10.0;
+
+/// Waveform of a test signal
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignalKind {
+    /// Sine tone at a fixed frequency in Hz
+    Sine { frequency: f32 },
+    /// Square wave at a fixed frequency in Hz
+    Square { frequency: f32 },
+    /// Triangle wave at a fixed frequency in Hz
+    Triangle { frequency: f32 },
+    /// Rising sawtooth at a fixed frequency in Hz
+    Sawtooth { frequency: f32 },
+    /// White noise, equal energy per Hz
+    WhiteNoise,
+    /// Pink noise, equal energy per octave (-3dB per octave)
+    PinkNoise,
+    /// Logarithmic sine sweep between two frequencies in Hz
+    Sweep { start_hz: f32, end_hz: f32 },
+    /// A single non-zero sample at the start, silence after it
+    Impulse,
+}
+
+/// Level a test signal is normalized to, as a linear amplitude
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SignalLevel {
+    /// Root mean square level over the whole signal
+    Rms(f32),
+    /// Largest absolute sample value
+    Peak(f32),
+}
+
+/// Reproducible test signal generator
+///
+/// The same settings always produce the same samples, noise included, so processed
+/// output can be compared between runs.
+#[derive(Debug, Clone, PartialEq)]
+pub struct TestSignal {
+    pub kind: SignalKind,
+    pub level: SignalLevel,
+    
+    /// Duration in seconds
+    pub duration: f32,
+    
+    /// Sample rate in Hz
+    pub sample_rate: u32,
+    
+    /// Seed for the noise generators
+    pub seed: u64,
+}
+
+impl TestSignal {
+    /// Create a new test signal with the default noise seed
+    pub fn new(kind: SignalKind, level: SignalLevel, duration: f32, sample_rate: u32) -> Self {
+        Self {
+            kind,
+            level,
+            duration,
+            sample_rate,
+            seed: DEFAULT_SEED,
+        }
+    }
+    
+    /// Get the number of samples the signal lasts
+    pub fn len(&self) -> usize {
+        (self.duration.max(0.0) * self.sample_rate as f32) as usize
+    }
+    
+    /// Check whether the signal has no samples
+    pub fn is_empty(&self) -> bool {
+        self.len() == 0
+    }
+    
+    /// Generate the mono signal
+    pub fn generate(&self) -> Vec<f32> {
+        self.generate_with_seed(self.seed)
+    }
+    
+    /// Generate a (left, right) pair
+    ///
+    /// Noise is generated independently for each channel, so it is uncorrelated
+    /// between them; all other kinds are identical on both channels.
+    pub fn generate_stereo(&self) -> (Vec<f32>, Vec<f32>) {
+        let left = self.generate();
+        let right = match self.kind {
+            SignalKind::WhiteNoise | SignalKind::PinkNoise => self.generate_with_seed(self.seed.rotate_left(32) ^ 1),
+            _ => left.clone(),
+        };
+        (left, right)
+    }
+    
+    fn generate_with_seed(&self, seed: u64) -> Vec<f32> {
+        let len = self.len();
+        let sample_rate = self.sample_rate as f32;
+        let mut noise = NoiseSource::new(seed);
+        
+        let mut samples: Vec<f32> = match self.kind {
+            SignalKind::Sine { frequency } => (0..len)
+                .map(|i| (2.0 * std::f32::consts::PI * frequency * i as f32 / sample_rate).sin())
+                .collect(),
+            SignalKind::Square { frequency } => (0..len)
+                .map(|i| if cycle_phase(frequency, i, sample_rate) < 0.5 { 1.0 } else { -1.0 })
+                .collect(),
+            SignalKind::Triangle { frequency } => (0..len)
+                .map(|i| 1.0 - 4.0 * (cycle_phase(frequency, i, sample_rate) - 0.5).abs())
+                .collect(),
+            SignalKind::Sawtooth { frequency } => (0..len)
+                .map(|i| 2.0 * cycle_phase(frequency, i, sample_rate) - 1.0)
+                .collect(),
+            SignalKind::WhiteNoise => (0..len).map(|_| noise.next_white()).collect(),
+            SignalKind::PinkNoise => {
+                let mut pink = PinkFilter::default();
+                (0..len).map(|_| pink.process(noise.next_white())).collect()
+            }
+            SignalKind::Sweep { start_hz, end_hz } => {
+                // Phase of an exponential sweep, integrated in closed form
+                let duration = len as f64 / sample_rate as f64;
+                let start_hz = start_hz.max(1.0) as f64;
+                let ratio = (end_hz.max(1.0) as f64 / start_hz).ln();
+                (0..len)
+                    .map(|i| {
+                        let t = i as f64 / sample_rate as f64;
+                        let phase = if ratio.abs() < 1e-9 {
+                            start_hz * t
+                        } else {
+                            start_hz * duration / ratio * ((t / duration * ratio).exp() - 1.0)
+                        };
+                        (2.0 * std::f64::consts::PI * phase).sin() as f32
+                    })
+                    .collect()
+            }
+            SignalKind::Impulse => {
+                let mut samples = vec![0.0; len];
+                if let Some(first) = samples.first_mut() {
+                    *first = 1.0;
+                }
+                samples
+            }
+        };
+        
+        let current = match self.level {
+            SignalLevel::Rms(_) => rms(&samples),
+            SignalLevel::Peak(_) => peak(&samples),
+        };
+        let target = match self.level {
+            SignalLevel::Rms(level) | SignalLevel::Peak(level) => level.max(0.0),
+        };
+        if current > 0.0 {
+            let gain = target / current;
+            samples.iter_mut().for_each(|sample| *sample *= gain);
+        }
+        samples
+    }
+}
+
+/// Waveform of the tone played by `test_audio`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ToneWaveform {
+    #[default]
+    Sine,
+    Square,
+    Triangle,
+    Sawtooth,
+}
+
+impl ToneWaveform {
+    /// Signal kind producing this waveform at `frequency` Hz
+    pub fn signal_kind(&self, frequency: f32) -> SignalKind {
+        match self {
+            ToneWaveform::Sine => SignalKind::Sine { frequency },
+            ToneWaveform::Square => SignalKind::Square { frequency },
+            ToneWaveform::Triangle => SignalKind::Triangle { frequency },
+            ToneWaveform::Sawtooth => SignalKind::Sawtooth { frequency },
+        }
+    }
+}
+
+impl std::str::FromStr for ToneWaveform {
+    type Err = AudioProcessorError;
+    
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.to_lowercase().as_str() {
+            "sine" => Ok(ToneWaveform::Sine),
+            "square" => Ok(ToneWaveform::Square),
+            "triangle" => Ok(ToneWaveform::Triangle),
+            "sawtooth" | "saw" => Ok(ToneWaveform::Sawtooth),
+            _ => Err(AudioProcessorError::Configuration(format!(
+                "Unknown test tone waveform '{}' (expected sine, square, triangle or sawtooth)", s
+            ))),
+        }
+    }
+}
+
+impl fmt::Display for ToneWaveform {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let name = match self {
+            ToneWaveform::Sine => "sine",
+            ToneWaveform::Square => "square",
+            ToneWaveform::Triangle => "triangle",
+            ToneWaveform::Sawtooth => "sawtooth",
+        };
+        write!(f, "{}", name)
+    }
+}
+
+/// Settings for the tone played by `test_audio_with`
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct TestToneParams {
+    /// Tone frequency in Hz
+    pub frequency: f32,
+    
+    /// Duration in seconds
+    pub duration: f32,
+    
+    /// Peak amplitude, 0.0 to 1.0
+    pub amplitude: f32,
+    
+    /// Shape of the tone
+    pub waveform: ToneWaveform,
+    
+    /// Play the processed tone through the output device when one is available
+    pub play: bool,
+}
+
+impl Default for TestToneParams {
+    /// A one second 440Hz sine at 0.3, the tone `test_audio` plays
+    fn default() -> Self {
+        Self {
+            frequency: 440.0,
+            duration: 1.0,
+            amplitude: 0.3,
+            waveform: ToneWaveform::Sine,
+            play: true,
+        }
+    }
+}
+
+impl TestToneParams {
+    /// Check the settings against what can be played at `sample_rate`
+    pub fn validate(&self, sample_rate: u32) -> Result<(), AudioProcessorError> {
+        let nyquist = sample_rate as f32 / 2.0;
+        let checks = [
+            ("frequency", self.frequency, 1.0, nyquist),
+            ("duration", self.duration, 0.0, MAX_TEST_TONE_SECONDS),
+            ("amplitude", self.amplitude, 0.0, 1.0),
+        ];
+        for (param, value, min, max) in checks {
+            if !(min..=max).contains(&value) {
+                return Err(AudioProcessorError::InvalidParameter {
+                    param: param.to_string(),
+                    value,
+                    min,
+                    max,
+                });
+            }
+        }
+        Ok(())
+    }
+    
+    /// The unprocessed mono tone at `sample_rate`
+    pub fn signal(&self, sample_rate: u32) -> TestSignal {
+        TestSignal::new(
+            self.waveform.signal_kind(self.frequency),
+            SignalLevel::Peak(self.amplitude),
+            self.duration,
+            sample_rate,
+        )
+    }
+    
+    /// Generate the tone and run it through a fresh delay built from `config`
+    ///
+    /// The running effect is left alone, so a test never leaves the tone in its
+    /// delay lines. Returns the (left, right) output, as long as the tone.
+    pub fn render(&self, config: &AudioConfig) -> Result<(Vec<f32>, Vec<f32>), AudioProcessorError> {
+        self.validate(config.sample_rate)?;
+        let tone = self.signal(config.sample_rate).generate();
+        let mut delay = StereoDelay::from_config(config);
+        Ok(delay.process_mono_to_stereo(&tone))
+    }
+}
+
+/// Position within the current cycle of a periodic waveform, 0.0 to 1.0
+fn cycle_phase(frequency: f32, index: usize, sample_rate: f32) -> f32 {
+    (frequency as f64 * index as f64 / sample_rate as f64).fract() as f32
+}
+
+/// Root mean square level of a block of samples
+pub fn rms(samples: &[f32]) -> f32 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|&s| s as f64 * s as f64).sum();
+    (sum / samples.len() as f64).sqrt() as f32
+}
+
+/// Largest absolute value in a block of samples
+pub fn peak(samples: &[f32]) -> f32 {
+    samples.iter().fold(0.0f32, |peak, s| peak.max(s.abs()))
+}
+
+/// Xorshift generator for uniform white noise in -1.0..1.0
+struct NoiseSource {
+    state: u64,
+}
+
+impl NoiseSource {
+    fn new(seed: u64) -> Self {
+        // Xorshift gets stuck at zero
+        Self { state: seed.max(1) }
+    }
+    
+    fn next_white(&mut self) -> f32 {
+        self.state ^= self.state << 13;
+        self.state ^= self.state >> 7;
+        self.state ^= self.state << 17;
+        (self.state >> 40) as f32 / (1u64 << 23) as f32 - 1.0
+    }
+}
+
+/// Paul Kellett's economy filter turning white noise into pink noise
+#[derive(Default)]
+struct PinkFilter {
+    b0: f32,
+    b1: f32,
+    b2: f32,
+}
+
+impl PinkFilter {
+    fn process(&mut self, white: f32) -> f32 {
+        self.b0 = 0.99765 * self.b0 + white * 0.0990460;
+        self.b1 = 0.96300 * self.b1 + white * 0.2965164;
+        self.b2 = 0.57000 * self.b2 + white * 1.0526913;
+        self.b0 + self.b1 + self.b2 + white * 0.1848
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    const SAMPLE_RATE: u32 = 44100;
+    
+    #[test]
+    fn test_every_kind_reaches_requested_level() {
+        let kinds = [
+            SignalKind::Sine { frequency: 440.0 },
+            SignalKind::Square { frequency: 440.0 },
+            SignalKind::Triangle { frequency: 440.0 },
+            SignalKind::Sawtooth { frequency: 440.0 },
+            SignalKind::WhiteNoise,
+            SignalKind::PinkNoise,
+            SignalKind::Sweep { start_hz: 20.0, end_hz: 20000.0 },
+            SignalKind::Impulse,
+        ];
+        for kind in kinds {
+            let signal = TestSignal::new(kind, SignalLevel::Rms(0.1), 1.0, SAMPLE_RATE).generate();
+            assert_eq!(signal.len(), SAMPLE_RATE as usize);
+            assert!((rms(&signal) - 0.1).abs() < 1e-3, "{:?} rms {}", kind, rms(&signal));
+            
+            let signal = TestSignal::new(kind, SignalLevel::Peak(0.5), 1.0, SAMPLE_RATE).generate();
+            assert!((peak(&signal) - 0.5).abs() < 1e-6, "{:?} peak {}", kind, peak(&signal));
+        }
+    }
+    
+    #[test]
+    fn test_custom_test_tone_lasts_the_requested_duration() {
+        let config = AudioConfig::default();
+        let params = TestToneParams {
+            frequency: 1000.0,
+            duration: 0.25,
+            amplitude: 0.5,
+            waveform: ToneWaveform::Square,
+            play: false,
+        };
+        let (left, right) = params.render(&config).unwrap();
+        let expected = (0.25 * config.sample_rate as f32) as usize;
+        assert_eq!(left.len(), expected);
+        assert_eq!(right.len(), expected);
+        
+        // The dry square passes through at the requested level before the first repeat
+        let dry = &params.signal(config.sample_rate).generate()[..64];
+        assert!((peak(dry) - 0.5).abs() < 1e-6);
+        assert!(left[..64].iter().any(|s| s.abs() > 0.0));
+        
+        assert!(TestToneParams { frequency: 30000.0, ..params }.render(&config).is_err());
+        assert!(TestToneParams { amplitude: 1.5, ..params }.render(&config).is_err());
+        assert_eq!("saw".parse::<ToneWaveform>().unwrap(), ToneWaveform::Sawtooth);
+        assert_eq!("Triangle".parse::<ToneWaveform>().unwrap().to_string(), "triangle");
+        assert!("sqaure".parse::<ToneWaveform>().is_err());
+    }
+    
+    #[test]
+    fn test_noise_is_reproducible_and_stereo_uncorrelated() {
+        let signal = TestSignal::new(SignalKind::PinkNoise, SignalLevel::Rms(0.2), 0.5, SAMPLE_RATE);
+        assert_eq!(signal.generate(), signal.generate());
+        
+        let signal = TestSignal { kind: SignalKind::WhiteNoise, ..signal };
+        let (left, right) = signal.generate_stereo();
+        let correlation: f32 = left.iter().zip(&right).map(|(l, r)| l * r).sum::<f32>()
+            / (rms(&left) * rms(&right) * left.len() as f32);
+        assert!(correlation.abs() < 0.05, "channels correlated: {}", correlation);
+    }
+    
+    #[test]
+    fn test_sweep_covers_frequency_range() {
+        let signal = TestSignal::new(
+            SignalKind::Sweep { start_hz: 100.0, end_hz: 10000.0 },
+            SignalLevel::Peak(1.0),
+            2.0,
+            SAMPLE_RATE,
+        )
+        .generate();
+        
+        // Estimate the frequency from zero crossings in a 20ms window
+        let window = SAMPLE_RATE as usize / 50;
+        let frequency_at = |start: usize| {
+            let crossings = signal[start..start + window]
+                .windows(2)
+                .filter(|pair| (pair[0] < 0.0) != (pair[1] < 0.0))
+                .count();
+            crossings as f32 / 2.0 * 50.0
+        };
+        
+        let start = frequency_at(0);
+        let middle = frequency_at(signal.len() / 2 - window / 2);
+        let end = frequency_at(signal.len() - window);
+        assert!((start - 100.0).abs() < 50.0, "sweep starts at {} Hz", start);
+        assert!((middle - 1000.0).abs() < 100.0, "sweep passes {} Hz halfway", middle);
+        assert!((end - 10000.0).abs() < 500.0, "sweep ends at {} Hz", end);
+    }
+}
@@ -1,8 +1,16 @@
 use actix_web::{web, App, HttpServer, Result, HttpResponse};
 use actix_files::Files;
 use serde::{Deserialize, Serialize};
+use std::io;
+use std::net::TcpListener;
 use std::sync::{Arc, Mutex};
 use crate::AudioProcessorTrait;
+use crate::automation::AutomationCommand;
+use crate::devices::devices_json;
+use crate::distortion::DistortionType;
+use crate::health::HealthStatus;
+use crate::schema;
+use log::{debug, error, info, warn};
 
 #[derive(Debug, Serialize, Deserialize)]
 pub struct ParameterRequest {
@@ -10,6 +18,38 @@ pub struct ParameterRequest {
     pub value: f32,
 }
 
+#[derive(Debug, Serialize, Deserialize)]
+pub struct DistortionTypeRequest {
+    #[serde(rename = "type")]
+    pub distortion_type: String,
+}
+
+#[derive(Debug, Default, Serialize, Deserialize)]
+pub struct StopRequest {
+    /// Let the repeats ring out before audio stops
+    #[serde(default)]
+    pub tails: bool,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct AutomationRequest {
+    /// "record", "play", "stop" or "clear"
+    pub command: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct RecordingRequest {
+    /// WAV file to write the output to
+    pub path: String,
+}
+
+#[derive(Debug, Serialize, Deserialize)]
+pub struct PanicRequest {
+    /// Also reset every effect parameter to its default
+    #[serde(default)]
+    pub restore_defaults: bool,
+}
+
 #[derive(Debug, Serialize, Deserialize)]
 pub struct StatusResponse {
     pub stereo_delay: StereoDelayStatus,
@@ -23,7 +63,9 @@ pub struct StereoDelayStatus {
     pub right_delay: f32,
     pub feedback: f32,
     pub wet_mix: f32,
+    pub mix_law: String,
     pub ping_pong: bool,
+    pub ping_pong_mode: String,
     pub stereo_width: f32,
     pub cross_feedback: f32,
     pub bpm: Option<f32>,
@@ -36,6 +78,10 @@ pub struct DistortionStatus {
     pub drive: f32,
     pub mix: f32,
     pub feedback_intensity: f32,
+    pub placement: String,
+    pub auto_gain_comp: bool,
+    pub tone: f32,
+    pub dry_bypass: bool,
 }
 
 #[derive(Debug, Serialize, Deserialize)]
@@ -45,41 +91,104 @@ pub struct SystemStatus {
     pub input_device: Option<String>,
     pub output_device: Option<String>,
     pub is_running: bool,
+    
+    /// "running", "stopping" while the repeats ring out, or "stopped"
+    pub state: String,
+    pub backend: Option<String>,
 }
 
+/// Processor shared between the web handlers
+///
+/// Handlers must extract exactly this type, it is what `start` registers as app data.
+type SharedProcessor = Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>;
+
+/// Ports after the requested one to try when it is already in use
+pub const DEFAULT_PORT_RETRIES: u16 = 3;
+
 pub struct WebServer {
-    processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>,
+    processor: SharedProcessor,
+}
+
+/// Bind the web interface on `port`, or on one of the next `retries` ports if it is in use
+///
+/// Only "address in use" moves on to the next port; any other error is returned
+/// straight away. Binding first lets the caller report the port actually used,
+/// or shut down cleanly, before the server starts.
+pub fn bind(port: u16, retries: u16) -> io::Result<TcpListener> {
+    let last = port.saturating_add(retries);
+    for candidate in port..=last {
+        match TcpListener::bind(("0.0.0.0", candidate)) {
+            Ok(listener) => {
+                if candidate != port {
+                    warn!("⚠️  Port {} is in use, using port {} instead", port, candidate);
+                }
+                return Ok(listener);
+            }
+            Err(e) if e.kind() == io::ErrorKind::AddrInUse => {
+                debug!("Port {} is in use", candidate);
+            }
+            Err(e) => return Err(e),
+        }
+    }
+    
+    let ports = if last == port { format!("port {} is", port) } else { format!("ports {}-{} are", port, last) };
+    Err(io::Error::new(
+        io::ErrorKind::AddrInUse,
+        format!("{} already in use, choose another with --web-port", ports),
+    ))
 }
 
 impl WebServer {
-    pub fn new(processor: Arc<Mutex<Box<dyn AudioProcessorTrait + Send>>>) -> Self {
+    pub fn new(processor: SharedProcessor) -> Self {
         Self {
             processor,
         }
     }
-
-    pub async fn start(self, port: u16) -> std::io::Result<()> {
+    
+    /// Serve the web interface on a listener from `bind` until the server stops
+    pub async fn start(self, listener: TcpListener) -> io::Result<()> {
         let processor = self.processor.clone();
+        let port = listener.local_addr()?.port();
         
-        println!("🌐 Starting web interface on http://0.0.0.0:{}", port);
-        println!("📱 Access from any device on your network");
-        println!("🎛️  Control your guitar effects via web browser");
+        info!("🌐 Starting web interface on http://0.0.0.0:{}", port);
+        info!("📱 Access from any device on your network");
+        info!("🎛️  Control your guitar effects via web browser");
         
         HttpServer::new(move || {
             App::new()
                 .app_data(web::Data::new(processor.clone()))
                 .service(Files::new("/static", "./web/static").show_files_listing())
                 .route("/", web::get().to(index))
+                .route("/health", web::get().to(health))
                 .route("/api/test", web::get().to(test_endpoint))
                 .route("/api/status", web::get().to(get_status))
+                .route("/api/meters", web::get().to(get_meters))
+                .route("/api/stats", web::get().to(get_stats))
+                .route("/api/stats/reset", web::post().to(reset_stats))
+                .route("/api/xruns", web::get().to(get_xruns))
+                .route("/api/xruns/reset", web::post().to(reset_xruns))
+                .route("/api/recording/start", web::post().to(start_recording))
+                .route("/api/recording/stop", web::post().to(stop_recording))
                 .route("/api/parameter", web::post().to(set_parameter))
+                .route("/api/parameters", web::get().to(get_parameters))
+                .route("/api/parameters", web::post().to(set_parameters))
+                .route("/api/distortion_type", web::post().to(set_distortion_type))
                 .route("/api/start", web::post().to(start_audio))
                 .route("/api/stop", web::post().to(stop_audio))
                 .route("/api/reset", web::post().to(reset_delay))
+                .route("/api/panic", web::post().to(panic))
+                .route("/api/effects", web::get().to(get_effects))
                 .route("/api/config", web::get().to(get_config))
                 .route("/api/config", web::post().to(save_config))
+                .route("/api/devices", web::get().to(get_devices))
+                .route("/api/diagnose", web::get().to(diagnose))
+                .route("/api/schema", web::get().to(get_schema))
+                .route("/api/automation", web::get().to(get_automation))
+                .route("/api/automation", web::post().to(automation_command))
+                .route("/api/state", web::get().to(export_state))
+                .route("/api/state", web::post().to(import_state))
         })
-        .bind(format!("0.0.0.0:{}", port))?
+        .listen(listener)?
         .run()
         .await
     }
@@ -90,22 +199,51 @@ async fn index() -> Result<actix_files::NamedFile> {
 }
 
 async fn test_endpoint() -> Result<HttpResponse> {
-    println!("🔍 Web API: test endpoint called");
+    debug!("🔍 Web API: test endpoint called");
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "status": "ok",
         "message": "API is working"
     })))
 }
 
+async fn health(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let status = match processor.lock() {
+        Ok(processor) => processor.get_health(),
+        Err(_) => HealthStatus::Unhealthy { reason: "processor lock poisoned".to_string() },
+    };
+    
+    Ok(health_response(&status))
+}
+
+/// Map a health check result to 200 OK or 503 Service Unavailable
+fn health_response(status: &HealthStatus) -> HttpResponse {
+    match status {
+        HealthStatus::Healthy { heartbeat_age } => {
+            HttpResponse::Ok().json(serde_json::json!({
+                "status": "ok",
+                "heartbeat_age_ms": heartbeat_age.as_millis() as u64
+            }))
+        }
+        HealthStatus::Unhealthy { reason } => {
+            HttpResponse::ServiceUnavailable().json(serde_json::json!({
+                "status": "unavailable",
+                "reason": reason
+            }))
+        }
+    }
+}
+
 async fn get_status(
-    processor: web::Data<Arc<Mutex<Box<dyn AudioProcessorTrait>>>>,
+    processor: web::Data<SharedProcessor>,
 ) -> Result<HttpResponse> {
-    println!("🔍 Web API: get_status called");
+    debug!("🔍 Web API: get_status called");
     
     let processor = match processor.lock() {
         Ok(p) => p,
         Err(e) => {
-            println!("❌ Web API: Failed to acquire processor lock: {}", e);
+            error!("❌ Web API: Failed to acquire processor lock: {}", e);
             return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": "Failed to acquire processor lock"
             })));
@@ -114,7 +252,7 @@ async fn get_status(
     
     match processor.get_status() {
         Ok(status_map) => {
-            println!("✅ Web API: Status retrieved successfully, {} fields", status_map.len());
+            debug!("✅ Web API: Status retrieved successfully, {} fields", status_map.len());
             
             // Parse status into structured response
             let stereo_delay = StereoDelayStatus {
@@ -122,7 +260,9 @@ async fn get_status(
                 right_delay: status_map.get("right_delay").unwrap_or(&"0.6".to_string()).parse().unwrap_or(0.6),
                 feedback: status_map.get("feedback").unwrap_or(&"0.3".to_string()).parse().unwrap_or(0.3),
                 wet_mix: status_map.get("wet_mix").unwrap_or(&"0.6".to_string()).parse().unwrap_or(0.6),
+                mix_law: status_map.get("mix_law").unwrap_or(&"linear".to_string()).clone(),
                 ping_pong: status_map.get("ping_pong").unwrap_or(&"true".to_string()) == "true",
+                ping_pong_mode: status_map.get("ping_pong_mode").unwrap_or(&"swap".to_string()).clone(),
                 stereo_width: status_map.get("stereo_width").unwrap_or(&"0.5".to_string()).parse().unwrap_or(0.5),
                 cross_feedback: status_map.get("cross_feedback").unwrap_or(&"0.2".to_string()).parse().unwrap_or(0.2),
                 bpm: status_map.get("bpm").and_then(|s| s.parse().ok()),
@@ -134,6 +274,10 @@ async fn get_status(
                 drive: status_map.get("distortion_drive").unwrap_or(&"0.3".to_string()).parse().unwrap_or(0.3),
                 mix: status_map.get("distortion_mix").unwrap_or(&"0.7".to_string()).parse().unwrap_or(0.7),
                 feedback_intensity: status_map.get("distortion_feedback_intensity").unwrap_or(&"0.5".to_string()).parse().unwrap_or(0.5),
+                placement: status_map.get("distortion_placement").unwrap_or(&"feedback".to_string()).clone(),
+                auto_gain_comp: status_map.get("distortion_auto_gain_comp").map(|s| s == "true").unwrap_or(false),
+                tone: status_map.get("distortion_tone").and_then(|s| s.parse().ok()).unwrap_or(0.0),
+                dry_bypass: status_map.get("distortion_dry_bypass").map(|s| s == "true").unwrap_or(false),
             };
             
             let system = SystemStatus {
@@ -142,6 +286,8 @@ async fn get_status(
                 input_device: status_map.get("input_device").cloned(),
                 output_device: status_map.get("output_device").cloned(),
                 is_running: status_map.get("is_running").unwrap_or(&"false".to_string()) == "true",
+                state: status_map.get("audio_state").cloned().unwrap_or_else(|| "stopped".to_string()),
+                backend: status_map.get("backend").cloned(),
             };
             
             let response = StatusResponse {
@@ -150,11 +296,11 @@ async fn get_status(
                 system,
             };
             
-            println!("✅ Web API: Response structured successfully");
+            debug!("✅ Web API: Response structured successfully");
             Ok(HttpResponse::Ok().json(response))
         }
         Err(e) => {
-            println!("❌ Web API: Failed to get status: {}", e);
+            error!("❌ Web API: Failed to get status: {}", e);
             Ok(HttpResponse::InternalServerError().json(serde_json::json!({
                 "error": format!("Failed to get status: {}", e)
             })))
@@ -162,8 +308,23 @@ async fn get_status(
     }
 }
 
+async fn get_meters(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    
+    match processor.get_meters() {
+        Ok(meters) => Ok(HttpResponse::Ok().json(meters)),
+        Err(e) => {
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to get meters: {}", e)
+            })))
+        }
+    }
+}
+
 async fn set_parameter(
-    processor: web::Data<Arc<Mutex<Box<dyn AudioProcessorTrait>>>>,
+    processor: web::Data<SharedProcessor>,
     param_req: web::Json<ParameterRequest>,
 ) -> Result<HttpResponse> {
     let mut processor = processor.lock().unwrap();
@@ -171,11 +332,14 @@ async fn set_parameter(
     let result = if param_req.parameter.starts_with("distortion_") {
         // Handle distortion parameters
         match param_req.parameter.as_str() {
-            "distortion_type" => processor.set_distortion_type(&param_req.value.to_string()),
+            "distortion_type" => processor.set_distortion_type(&DistortionType::from_parameter(param_req.value).to_string()),
             "distortion_enabled" => processor.set_stereo_delay_parameter("distortion_enabled", param_req.value),
             "distortion_drive" => processor.set_stereo_delay_parameter("distortion_drive", param_req.value),
             "distortion_mix" => processor.set_stereo_delay_parameter("distortion_mix", param_req.value),
             "distortion_feedback_intensity" => processor.set_stereo_delay_parameter("distortion_feedback_intensity", param_req.value),
+            "distortion_placement" => processor.set_stereo_delay_parameter("distortion_placement", param_req.value),
+            "distortion_auto_gain_comp" => processor.set_stereo_delay_parameter("distortion_auto_gain_comp", param_req.value),
+            "distortion_tone" => processor.set_stereo_delay_parameter("distortion_tone", param_req.value),
             _ => Err(crate::error::AudioProcessorError::InvalidParameter {
                 param: param_req.parameter.clone(),
                 value: param_req.value,
@@ -204,8 +368,163 @@ async fn set_parameter(
     }
 }
 
+async fn get_parameters(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    
+    match processor.get_parameters() {
+        Ok(params) => Ok(HttpResponse::Ok().json(params)),
+        Err(e) => {
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to get parameters: {}", e)
+            })))
+        }
+    }
+}
+
+// The body is a JSON object of parameter names to values, applied all or none
+async fn set_parameters(
+    processor: web::Data<SharedProcessor>,
+    params: web::Json<std::collections::HashMap<String, f32>>,
+) -> Result<HttpResponse> {
+    let mut processor = processor.lock().unwrap();
+    
+    match processor.set_parameters(&params) {
+        Ok(_) => {
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "parameters": params.len()
+            })))
+        }
+        Err(e) => {
+            Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Failed to set parameters: {}", e)
+            })))
+        }
+    }
+}
+
+async fn get_stats(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    Ok(HttpResponse::Ok().json(processor.get_session_stats()))
+}
+
+async fn reset_stats(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    processor.reset_session_stats();
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true
+    })))
+}
+
+async fn get_xruns(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    Ok(HttpResponse::Ok().json(processor.get_buffer_xruns()))
+}
+
+async fn reset_xruns(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    processor.reset_buffer_xruns();
+    Ok(HttpResponse::Ok().json(serde_json::json!({
+        "success": true
+    })))
+}
+
+async fn start_recording(
+    processor: web::Data<SharedProcessor>,
+    recording_req: web::Json<RecordingRequest>,
+) -> Result<HttpResponse> {
+    let mut processor = processor.lock().unwrap();
+    match processor.start_recording(&recording_req.path) {
+        Ok(_) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "path": recording_req.path
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Failed to start recording: {}", e)
+        }))),
+    }
+}
+
+async fn stop_recording(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let mut processor = processor.lock().unwrap();
+    match processor.stop_recording() {
+        Ok(summary) => Ok(HttpResponse::Ok().json(summary)),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Failed to stop recording: {}", e)
+        }))),
+    }
+}
+
+async fn set_distortion_type(
+    processor: web::Data<SharedProcessor>,
+    type_req: web::Json<DistortionTypeRequest>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    
+    match processor.set_distortion_type(&type_req.distortion_type) {
+        Ok(_) => {
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "type": type_req.distortion_type.to_lowercase()
+            })))
+        }
+        Err(e) => {
+            let known: Vec<String> = DistortionType::ALL.iter().map(|t| t.to_string()).collect();
+            Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Failed to set distortion type: {}", e),
+                "known_types": known
+            })))
+        }
+    }
+}
+
+async fn get_automation(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    Ok(HttpResponse::Ok().json(processor.get_automation()))
+}
+
+async fn automation_command(
+    processor: web::Data<SharedProcessor>,
+    automation_req: web::Json<AutomationRequest>,
+) -> Result<HttpResponse> {
+    let command = match AutomationCommand::from_name(&automation_req.command) {
+        Some(command) => command,
+        None => {
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "error": format!("Unknown automation command: {}", automation_req.command),
+                "known_commands": ["record", "play", "stop", "clear"]
+            })));
+        }
+    };
+    
+    let mut processor = processor.lock().unwrap();
+    match processor.automation_command(command) {
+        Ok(_) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "state": processor.get_automation().state
+        }))),
+        Err(e) => Ok(HttpResponse::BadRequest().json(serde_json::json!({
+            "error": format!("Failed to run automation command: {}", e)
+        }))),
+    }
+}
+
 async fn start_audio(
-    processor: web::Data<Arc<Mutex<Box<dyn AudioProcessorTrait>>>>,
+    processor: web::Data<SharedProcessor>,
 ) -> Result<HttpResponse> {
     let mut processor = processor.lock().unwrap();
     
@@ -224,16 +543,21 @@ async fn start_audio(
     }
 }
 
+/// Stop audio, straight away or with `{"tails": true}` (or `?tails=true`) once the repeats have rung out
 async fn stop_audio(
-    processor: web::Data<Arc<Mutex<Box<dyn AudioProcessorTrait>>>>,
+    processor: web::Data<SharedProcessor>,
+    query: web::Query<StopRequest>,
+    stop_req: Option<web::Json<StopRequest>>,
 ) -> Result<HttpResponse> {
+    let tails = query.tails || stop_req.is_some_and(|stop_req| stop_req.tails);
     let mut processor = processor.lock().unwrap();
     
-    match processor.stop_audio() {
+    let result = if tails { processor.ring_out() } else { processor.stop_audio() };
+    match result {
         Ok(_) => {
             Ok(HttpResponse::Ok().json(serde_json::json!({
                 "success": true,
-                "message": "Audio processing stopped"
+                "message": if tails { "Audio stopping, the repeats are ringing out" } else { "Audio processing stopped" }
             })))
         }
         Err(e) => {
@@ -245,9 +569,9 @@ async fn stop_audio(
 }
 
 async fn reset_delay(
-    processor: web::Data<Arc<Mutex<Box<dyn AudioProcessorTrait>>>>,
+    processor: web::Data<SharedProcessor>,
 ) -> Result<HttpResponse> {
-    let mut processor = processor.lock().unwrap();
+    let processor = processor.lock().unwrap();
     
     match processor.reset_delay() {
         Ok(_) => {
@@ -264,8 +588,48 @@ async fn reset_delay(
     }
 }
 
+async fn panic(
+    processor: web::Data<SharedProcessor>,
+    panic_req: Option<web::Json<PanicRequest>>,
+) -> Result<HttpResponse> {
+    // The body is optional, a bare POST clears the state and keeps the parameters
+    let restore_defaults = panic_req.map(|req| req.restore_defaults).unwrap_or(false);
+    let mut processor = processor.lock().unwrap();
+    
+    match processor.panic(restore_defaults) {
+        Ok(_) => {
+            info!("🚨 Panic: effect state cleared (defaults restored: {})", restore_defaults);
+            Ok(HttpResponse::Ok().json(serde_json::json!({
+                "success": true,
+                "message": "All effect state cleared",
+                "restore_defaults": restore_defaults
+            })))
+        }
+        Err(e) => {
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to panic: {}", e)
+            })))
+        }
+    }
+}
+
+async fn get_effects(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    let processor = processor.lock().unwrap();
+    
+    match processor.get_effect_chain() {
+        Ok(effects) => Ok(HttpResponse::Ok().json(serde_json::json!({ "effects": effects }))),
+        Err(e) => {
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to get effect chain: {}", e)
+            })))
+        }
+    }
+}
+
 async fn get_config(
-    _processor: web::Data<Arc<Mutex<Box<dyn AudioProcessorTrait>>>>,
+    _processor: web::Data<SharedProcessor>,
 ) -> Result<HttpResponse> {
     // For now, return a default config structure
     // In the future, this could read from the actual config file
@@ -277,7 +641,9 @@ async fn get_config(
             "right_delay": 0.6,
             "feedback": 0.3,
             "wet_mix": 0.6,
+            "mix_law": "linear",
             "ping_pong": true,
+            "ping_pong_mode": "swap",
             "stereo_width": 0.5,
             "cross_feedback": 0.2
         },
@@ -286,7 +652,10 @@ async fn get_config(
             "distortion_type": "soft_clip",
             "drive": 0.3,
             "mix": 0.7,
-            "feedback_intensity": 0.5
+            "feedback_intensity": 0.5,
+            "placement": "feedback",
+            "auto_gain_comp": false,
+            "dry_bypass": false
         }
     });
     
@@ -294,13 +663,242 @@ async fn get_config(
 }
 
 async fn save_config(
-    _processor: web::Data<Arc<Mutex<Box<dyn AudioProcessorTrait>>>>,
-    _config: web::Json<serde_json::Value>,
+    processor: web::Data<SharedProcessor>,
+    config: web::Json<serde_json::Value>,
 ) -> Result<HttpResponse> {
-    // For now, just return success
-    // In the future, this could save to the config file
+    // Device names from GET /api/devices; switching restarts audio if it is running
+    let device = |key: &str| config.get(key).and_then(|value| value.as_str()).map(|name| name.to_string());
+    let input_device = device("input_device");
+    let output_device = device("output_device");
+    
+    if input_device.is_some() || output_device.is_some() {
+        let mut processor = match processor.lock() {
+            Ok(processor) => processor,
+            Err(_) => {
+                return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                    "success": false,
+                    "message": "Failed to acquire processor lock"
+                })));
+            }
+        };
+        
+        if let Err(e) = processor.set_devices(input_device, output_device) {
+            error!("❌ Web API: Failed to switch audio devices: {}", e);
+            return Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("Failed to switch audio devices: {}", e)
+            })));
+        }
+    }
+    
+    // Other settings are not persisted yet
     Ok(HttpResponse::Ok().json(serde_json::json!({
         "success": true,
         "message": "Configuration saved"
     })))
 }
+
+async fn get_devices(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    debug!("🔍 Web API: get_devices called");
+    
+    let processor = match processor.lock() {
+        Ok(processor) => processor,
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to acquire processor lock"
+            })));
+        }
+    };
+    
+    let devices = processor.list_devices();
+    let config = processor.get_config();
+    Ok(HttpResponse::Ok().json(devices_json(
+        &devices,
+        config.input_device.as_deref(),
+        config.output_device.as_deref(),
+    )))
+}
+
+async fn get_schema() -> Result<HttpResponse> {
+    debug!("🔍 Web API: get_schema called");
+    Ok(HttpResponse::Ok().json(schema::schema()))
+}
+
+async fn diagnose(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    debug!("🔍 Web API: diagnose called");
+    
+    let processor = match processor.lock() {
+        Ok(processor) => processor,
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to acquire processor lock"
+            })));
+        }
+    };
+    
+    let report = processor.diagnose();
+    if !report.passed {
+        warn!("🩺 Diagnostics found problems: {:?}", report.failures().collect::<Vec<_>>());
+    }
+    Ok(HttpResponse::Ok().json(report))
+}
+
+async fn export_state(
+    processor: web::Data<SharedProcessor>,
+) -> Result<HttpResponse> {
+    debug!("🔍 Web API: export_state called");
+    
+    let processor = match processor.lock() {
+        Ok(processor) => processor,
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": "Failed to acquire processor lock"
+            })));
+        }
+    };
+    
+    match processor.export_state() {
+        Ok(state) => Ok(HttpResponse::Ok().content_type("application/json").body(state)),
+        Err(e) => {
+            error!("❌ Web API: Failed to export state: {}", e);
+            Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "error": format!("Failed to export state: {}", e)
+            })))
+        }
+    }
+}
+
+async fn import_state(
+    processor: web::Data<SharedProcessor>,
+    state: String,
+) -> Result<HttpResponse> {
+    debug!("🔍 Web API: import_state called");
+    
+    let mut processor = match processor.lock() {
+        Ok(processor) => processor,
+        Err(_) => {
+            return Ok(HttpResponse::InternalServerError().json(serde_json::json!({
+                "success": false,
+                "message": "Failed to acquire processor lock"
+            })));
+        }
+    };
+    
+    match processor.import_state(&state) {
+        Ok(()) => Ok(HttpResponse::Ok().json(serde_json::json!({
+            "success": true,
+            "message": "State imported"
+        }))),
+        Err(e) => {
+            error!("❌ Web API: Failed to import state: {}", e);
+            Ok(HttpResponse::BadRequest().json(serde_json::json!({
+                "success": false,
+                "message": format!("Failed to import state: {}", e)
+            })))
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::audio_processor::AudioProcessor;
+    use crate::health::{Heartbeat, HEARTBEAT_TIMEOUT};
+    use actix_web::http::StatusCode;
+    use actix_web::test as actix_test;
+    use std::time::{Duration, Instant};
+    
+    #[test]
+    fn test_stalled_heartbeat_returns_503() {
+        let heartbeat = Heartbeat::new();
+        let now = Instant::now();
+        heartbeat.beat_at(now);
+        
+        let alive = heartbeat.status_at(now + Duration::from_millis(10), HEARTBEAT_TIMEOUT);
+        assert_eq!(health_response(&alive).status(), StatusCode::OK);
+        
+        let stalled = heartbeat.status_at(now + HEARTBEAT_TIMEOUT * 3, HEARTBEAT_TIMEOUT);
+        assert_eq!(health_response(&stalled).status(), StatusCode::SERVICE_UNAVAILABLE);
+    }
+    
+    #[actix_web::test]
+    async fn test_set_distortion_type_by_name() {
+        let processor: SharedProcessor = Arc::new(Mutex::new(Box::new(AudioProcessor::new().unwrap())));
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(processor.clone()))
+                .route("/api/distortion_type", web::post().to(set_distortion_type)),
+        )
+        .await;
+        let distortion_type = || processor.lock().unwrap().get_status().unwrap()["distortion_type"].clone();
+        
+        let request = actix_test::TestRequest::post()
+            .uri("/api/distortion_type")
+            .set_json(serde_json::json!({ "type": "tube" }))
+            .to_request();
+        assert_eq!(actix_test::call_service(&app, request).await.status(), StatusCode::OK);
+        assert_eq!(distortion_type(), "tube");
+        
+        let request = actix_test::TestRequest::post()
+            .uri("/api/distortion_type")
+            .set_json(serde_json::json!({ "type": "chorus" }))
+            .to_request();
+        assert_eq!(actix_test::call_service(&app, request).await.status(), StatusCode::BAD_REQUEST);
+        assert_eq!(distortion_type(), "tube");
+    }
+    
+    #[actix_web::test]
+    async fn test_stop_with_tails_goes_through_stopping() {
+        let processor = AudioProcessor::new().unwrap();
+        processor.mark_running();
+        let processor: SharedProcessor = Arc::new(Mutex::new(Box::new(processor)));
+        let app = actix_test::init_service(
+            App::new()
+                .app_data(web::Data::new(processor.clone()))
+                .route("/api/status", web::get().to(get_status))
+                .route("/api/stop", web::post().to(stop_audio)),
+        )
+        .await;
+        
+        // Status goes running, stopping while the repeats ring out, then stopped
+        let app = &app;
+        let state = || async move {
+            let response: StatusResponse = actix_test::call_and_read_body_json(app, actix_test::TestRequest::get().uri("/api/status").to_request()).await;
+            (response.system.state, response.system.is_running)
+        };
+        assert_eq!(state().await, ("running".to_string(), true));
+        
+        let request = actix_test::TestRequest::post()
+            .uri("/api/stop")
+            .set_json(serde_json::json!({ "tails": true }))
+            .to_request();
+        assert_eq!(actix_test::call_service(app, request).await.status(), StatusCode::OK);
+        assert_eq!(state().await, ("stopping".to_string(), true));
+        
+        // Stopping again without tails cuts the repeats off
+        let request = actix_test::TestRequest::post().uri("/api/stop").to_request();
+        assert_eq!(actix_test::call_service(app, request).await.status(), StatusCode::OK);
+        assert_eq!(state().await, ("stopped".to_string(), false));
+        
+        let request = actix_test::TestRequest::post().uri("/api/stop?tails=true").to_request();
+        assert_eq!(actix_test::call_service(app, request).await.status(), StatusCode::INTERNAL_SERVER_ERROR);
+    }
+    
+    #[test]
+    fn test_bind_falls_back_when_port_is_in_use() {
+        let taken = TcpListener::bind(("0.0.0.0", 0)).unwrap();
+        let port = taken.local_addr().unwrap().port();
+        
+        let error = bind(port, 0).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::AddrInUse);
+        assert!(error.to_string().contains(&format!("port {} is already in use", port)), "{}", error);
+        
+        let listener = bind(port, DEFAULT_PORT_RETRIES).unwrap();
+        let fallback = listener.local_addr().unwrap().port();
+        assert!(fallback > port && fallback <= port + DEFAULT_PORT_RETRIES, "bound port {}", fallback);
+    }
+}
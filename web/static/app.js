@@ -26,6 +26,9 @@ class GuitarEffectsController {
     document
       .getElementById("reset-btn")
       .addEventListener("click", () => this.resetDelay());
+    document
+      .getElementById("copy-settings-btn")
+      .addEventListener("click", () => this.copySettings());
 
     // Stereo delay controls
     this.setupKnobControl("feedback", "feedback", (value) => value.toFixed(2));
@@ -98,7 +101,11 @@ class GuitarEffectsController {
       const value = e.target.value;
 
       try {
-        await this.setParameter(parameterName, value);
+        if (parameterName === "distortion_type") {
+          await this.setDistortionType(value);
+        } else {
+          await this.setParameter(parameterName, value);
+        }
       } catch (error) {
         console.error(`Failed to set ${parameterName}:`, error);
         this.showError(`Failed to set ${parameterName}`);
@@ -261,6 +268,23 @@ class GuitarEffectsController {
     return await response.json();
   }
 
+  async setDistortionType(type) {
+    const response = await fetch(`${this.apiBase}/distortion_type`, {
+      method: "POST",
+      headers: {
+        "Content-Type": "application/json",
+      },
+      body: JSON.stringify({ type: type }),
+    });
+
+    if (!response.ok) {
+      const error = await response.json();
+      throw new Error(error.error || "Failed to set distortion type");
+    }
+
+    return await response.json();
+  }
+
   async startAudio() {
     try {
       const response = await fetch(`${this.apiBase}/start`, {
@@ -315,6 +339,24 @@ class GuitarEffectsController {
     }
   }
 
+  async copySettings() {
+    try {
+      const response = await fetch(`${this.apiBase}/state`);
+
+      if (!response.ok) {
+        const error = await response.json();
+        throw new Error(error.error || "Failed to export settings");
+      }
+
+      const state = await response.text();
+      await navigator.clipboard.writeText(state);
+      this.showSuccess("Current settings copied to clipboard");
+    } catch (error) {
+      console.error("Failed to copy settings:", error);
+      this.showError("Failed to copy settings to clipboard");
+    }
+  }
+
   showSuccess(message) {
     this.showNotification(message, "success");
   }